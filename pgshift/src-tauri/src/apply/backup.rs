@@ -0,0 +1,170 @@
+//! `pg_dump` backups taken before a migration is applied.
+//!
+//! With [`BackupOptions`] set, a migration whose `meta.json` marks it
+//! `has_dangerous` (or every migration, with `always`) is applied only once
+//! `pg_dump` has written the target database to the backup directory, in
+//! the custom format `pg_restore` reads. The password is passed to
+//! `pg_dump` in `PGPASSWORD`, not on its command line.
+
+use std::path::{Path, PathBuf};
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use tokio::process::Command;
+use crate::db::introspect::strip_conninfo_password;
+use crate::db::{rds_iam, tunnel};
+use crate::environments;
+use crate::render::sql::MigrationMeta;
+
+/// Backup taken before applying a migration.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct BackupOptions {
+    /// Directory the dumps are written to; `~/PGShift/backups` by default.
+    #[serde(default)]
+    pub directory: Option<String>,
+    /// Dump the schema without the data.
+    #[serde(default)]
+    pub schema_only: bool,
+    /// Back up before every migration, not only dangerous ones.
+    #[serde(default)]
+    pub always: bool,
+    /// The `pg_dump` to run; the one on the `PATH` by default.
+    #[serde(default)]
+    pub pg_dump_path: Option<String>,
+}
+
+impl BackupOptions {
+    /// Whether the migration folder at `migration_path` must be backed up
+    /// before it is applied.
+    pub fn applies_to(&self, migration_path: &Path) -> bool {
+        self.always || std::fs::read_to_string(migration_path.join("meta.json"))
+            .ok()
+            .and_then(|json| serde_json::from_str::<MigrationMeta>(&json).ok())
+            .is_some_and(|meta| meta.has_dangerous)
+    }
+}
+
+/// Directory dumps are written to when [`BackupOptions::directory`] is unset.
+pub fn default_backup_dir() -> Result<PathBuf, String> {
+    let home_dir = dirs::home_dir().ok_or_else(|| "Could not find home directory".to_string())?;
+    Ok(home_dir.join("PGShift").join("backups"))
+}
+
+/// Dump the database of `connection_string`, or the environment it names,
+/// before `migration_name` is applied, and return the path of the dump.
+pub async fn backup_database(
+    connection_string: &str,
+    migration_name: &str,
+    options: &BackupOptions,
+) -> Result<PathBuf, String> {
+    let connection_string = &environments::resolve_connection_string(connection_string)?;
+    let connection_string = &rds_iam::with_auth_token(connection_string).await?;
+    let connection_string = &tunnel::through_tunnel(connection_string).await?;
+    let directory = match &options.directory {
+        Some(directory) => PathBuf::from(directory),
+        None => default_backup_dir()?,
+    };
+    std::fs::create_dir_all(&directory)
+        .map_err(|e| format!("Failed to create backup directory {}: {}", directory.display(), e))?;
+    let path = directory.join(format!(
+        "{}__{}{}.dump",
+        Utc::now().format("%Y%m%d%H%M%S"),
+        migration_name,
+        if options.schema_only { "__schema" } else { "" }
+    ));
+
+    let mut command = Command::new(options.pg_dump_path.as_deref().unwrap_or("pg_dump"));
+    command
+        .arg("--format=custom")
+        .arg("--no-password")
+        .arg("--file")
+        .arg(&path)
+        .arg("--dbname")
+        .arg(strip_conninfo_password(connection_string));
+    if options.schema_only {
+        command.arg("--schema-only");
+    }
+    if let Some(password) = conninfo_password(connection_string) {
+        command.env("PGPASSWORD", password);
+    }
+    let output = command.output()
+        .await
+        .map_err(|e| format!("Failed to run pg_dump: {}", e))?;
+    if !output.status.success() {
+        // Leave no partial dump that could be mistaken for a backup
+        let _ = std::fs::remove_file(&path);
+        return Err(format!("pg_dump failed: {}", String::from_utf8_lossy(&output.stderr).trim()));
+    }
+    Ok(path)
+}
+
+/// The password of a libpq connection string, in either the URI or the
+/// `key=value` form.
+fn conninfo_password(conninfo: &str) -> Option<String> {
+    let conninfo = conninfo.trim();
+    if let Some((_, rest)) = conninfo.split_once("://") {
+        let authority = &rest[..rest.find(['/', '?']).unwrap_or(rest.len())];
+        if let Some((_, password)) = authority.rsplit_once('@').and_then(|(userinfo, _)| userinfo.split_once(':')) {
+            return Some(percent_decode(password));
+        }
+        let query = rest.split_once('?')?.1;
+        return query.split('&')
+            .find_map(|p| p.strip_prefix("password="))
+            .map(percent_decode);
+    }
+
+    let start = conninfo.find("password")?;
+    let value = conninfo[start + "password".len()..].trim_start().strip_prefix('=')?.trim_start();
+    match value.strip_prefix('\'') {
+        Some(quoted) => {
+            let mut password = String::new();
+            let mut chars = quoted.chars();
+            while let Some(c) = chars.next() {
+                match c {
+                    '\\' => password.extend(chars.next()),
+                    '\'' => break,
+                    c => password.push(c),
+                }
+            }
+            Some(password)
+        }
+        None => Some(value.split_whitespace().next().unwrap_or_default().to_string()),
+    }
+}
+
+pub(crate) fn percent_decode(value: &str) -> String {
+    let bytes = value.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = (bytes[i] == b'%')
+            .then(|| value.get(i + 1..i + 3))
+            .flatten()
+            .and_then(|h| u8::from_str_radix(h, 16).ok());
+        match hex {
+            Some(byte) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            None => {
+                decoded.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_conninfo_password() {
+        assert_eq!(conninfo_password("postgres://app:s%40cret@db:5432/app").as_deref(), Some("s@cret"));
+        assert_eq!(conninfo_password("postgres://app@db/app?sslmode=require&password=pw").as_deref(), Some("pw"));
+        assert_eq!(conninfo_password("postgres://app@db/app"), None);
+        assert_eq!(conninfo_password("host=db password = 'it\\'s here' user=app").as_deref(), Some("it's here"));
+        assert_eq!(conninfo_password("host=db password=pw user=app").as_deref(), Some("pw"));
+        assert_eq!(conninfo_password("host=db user=app"), None);
+    }
+}
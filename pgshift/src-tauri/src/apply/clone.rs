@@ -0,0 +1,175 @@
+//! Schema-only clone of a database onto an empty one.
+//!
+//! The source's schema is compared against an empty schema and the resulting
+//! script, the same one `up.sql` of that migration would hold, is applied to
+//! the target in a single transaction. The target must not have any of the
+//! objects the comparison covers, so nothing is silently merged into it.
+
+use serde::{Deserialize, Serialize};
+use sqlx::postgres::PgPool;
+use crate::cancel::CancelToken;
+use crate::db::introspect::introspect_schema;
+use crate::db::progress::OperationProgress;
+use crate::db::session::fetch_session_settings;
+use crate::diff::diff::compare_schemas_with_options;
+use crate::diff::{DiffKind, DiffOptions, DiffReport};
+use crate::model::schema::SchemaModel;
+use crate::render::sql::generate_up_sql;
+use super::exec::{audit_comment, execute_migration_script, ApplyOptions, MigrationRun};
+
+/// How many existing objects are named when the target is not empty.
+const EXISTING_OBJECTS_SHOWN: usize = 5;
+
+/// Options for cloning a schema.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CloneOptions {
+    /// Object kinds to clone, as for a comparison (privileges, foreign data
+    /// and subscriptions are opt-in).
+    #[serde(default)]
+    pub diff: DiffOptions,
+    /// Render the script without applying it.
+    #[serde(default)]
+    pub dry_run: bool,
+}
+
+/// Step of a clone.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ClonePhase {
+    Introspect,
+    CheckTarget,
+    Render,
+    Apply,
+    Done,
+}
+
+/// Progress of a clone.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct CloneProgress {
+    pub phase: ClonePhase,
+    pub message: String,
+    /// Index build or other long operation running while applying.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub operation: Option<OperationProgress>,
+}
+
+/// Outcome of a clone.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CloneResult {
+    /// Objects created, counted as diff items.
+    pub items_count: usize,
+    /// Script that was applied, or would be on a dry run.
+    pub up_sql: String,
+    pub applied: bool,
+    pub logs: Vec<String>,
+}
+
+/// Create every object of the `source` database on the empty `target`.
+///
+/// Each step is passed to `on_progress`, as are the statements and index
+/// builds of the apply step. Cancelling `cancel` stops the clone; a running
+/// apply is rolled back.
+pub async fn clone_schema<F>(
+    source: &PgPool,
+    target: &PgPool,
+    options: &CloneOptions,
+    cancel: &CancelToken,
+    on_progress: F,
+) -> Result<CloneResult, String>
+where
+    F: Fn(&CloneProgress) + Sync,
+{
+    let phase = |phase: ClonePhase, message: String| {
+        on_progress(&CloneProgress { phase, message, operation: None });
+    };
+
+    phase(ClonePhase::Introspect, "Reading the source schema".to_string());
+    let source_schema = cancel.run(async {
+        introspect_schema(source).await.map_err(|e| format!("Source introspection failed: {}", e))
+    }).await?;
+
+    phase(ClonePhase::CheckTarget, "Checking that the target is empty".to_string());
+    let target_schema = cancel.run(async {
+        introspect_schema(target).await.map_err(|e| format!("Target introspection failed: {}", e))
+    }).await?;
+    let existing = existing_objects(&target_schema, &options.diff);
+    if !existing.is_empty() {
+        let mut shown = existing.iter().take(EXISTING_OBJECTS_SHOWN).cloned().collect::<Vec<_>>().join(", ");
+        if existing.len() > EXISTING_OBJECTS_SHOWN {
+            shown.push_str(", ...");
+        }
+        return Err(format!("Target is not empty; it has {} objects: {}", existing.len(), shown));
+    }
+
+    let report = bootstrap_report(&source_schema, &options.diff);
+    phase(ClonePhase::Render, format!("Rendering {} objects", report.items.len()));
+    let up_sql = generate_up_sql(&report, None, false);
+    if options.dry_run {
+        phase(ClonePhase::Done, "Dry run; nothing was applied".to_string());
+        return Ok(CloneResult { items_count: report.items.len(), up_sql, applied: false, logs: Vec::new() });
+    }
+
+    phase(ClonePhase::Apply, format!("Creating {} objects", report.items.len()));
+    let mut conn = target.acquire()
+        .await
+        .map_err(|e| format!("Failed to acquire connection: {}", e))?;
+    let session = fetch_session_settings(&mut conn)
+        .await
+        .map_err(|e| format!("Failed to read session settings: {}", e))?;
+    let mut run = MigrationRun::default();
+    let audit = audit_comment("clone", &format!("items={}", report.items.len()));
+    execute_migration_script(target, &mut conn, &session, &up_sql, &audit, &ApplyOptions::default(), None, &mut run, cancel, |operation| {
+        on_progress(&CloneProgress {
+            phase: ClonePhase::Apply,
+            message: operation.summary(),
+            operation: Some(operation.clone()),
+        });
+    }, |statement| {
+        if statement.status.is_none() {
+            phase(ClonePhase::Apply, format!("Statement {} of {}", statement.statement, statement.statements_total));
+        }
+    }).await?;
+
+    phase(ClonePhase::Done, format!("Created {} objects", report.items.len()));
+    Ok(CloneResult { items_count: report.items.len(), up_sql, applied: true, logs: run.logs })
+}
+
+/// Diff that creates every object of `source` on an empty database.
+pub fn bootstrap_report(source: &SchemaModel, options: &DiffOptions) -> DiffReport {
+    compare_schemas_with_options(source, &SchemaModel::new(), options)
+}
+
+/// Objects of `target` a clone could collide with, as `type name`.
+pub fn existing_objects(target: &SchemaModel, options: &DiffOptions) -> Vec<String> {
+    compare_schemas_with_options(&SchemaModel::new(), target, options)
+        .items
+        .iter()
+        .filter(|i| i.kind == DiffKind::Removed)
+        .map(|i| format!("{} {}", i.object_type, i.object_name))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::schema::EnumType;
+
+    #[test]
+    fn test_existing_objects_and_bootstrap() {
+        let schema = SchemaModel {
+            enums: vec![EnumType {
+                name: "status".to_string(),
+                values: vec!["active".to_string(), "archived".to_string()],
+            }],
+            ..Default::default()
+        };
+        let options = DiffOptions::default();
+
+        assert!(existing_objects(&SchemaModel::new(), &options).is_empty());
+        assert_eq!(existing_objects(&schema, &options), vec!["enum status"]);
+
+        let report = bootstrap_report(&schema, &options);
+        assert_eq!(report.items.len(), 1);
+        assert!(generate_up_sql(&report, None, false).contains("CREATE TYPE \"status\" AS ENUM ('active', 'archived');"));
+    }
+}
@@ -1,54 +1,981 @@
 //! Migration execution logic.
 
+use std::collections::HashMap;
 use std::fs;
-use std::path::Path;
-use sqlx::postgres::PgPool;
-use chrono::Utc;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use sqlx::postgres::{PgConnection, PgPool};
+use sqlx::Row;
+use tracing::Level;
+use crate::apply::backup::BackupOptions;
+use crate::apply::hooks::{hook_script, HookPoint};
+use crate::cancel::{CancelToken, CANCELLED};
+use crate::db::connect::application_name;
+use crate::db::progress::{fetch_operation_progress, OperationProgress};
+use crate::db::session::SessionSettings;
+use crate::ddl::parse::{is_transaction_control, runs_in_transaction, split_statements};
+use crate::render::sql::MigrationMeta;
+use crate::log_line;
 
-/// Apply a migration SQL file to the database.
-pub async fn apply_migration_sql(
+/// Savepoint each statement runs in when continuing past failures.
+const STATEMENT_SAVEPOINT: &str = "pgshift_statement";
+
+/// How often the progress of running operations is polled while applying.
+const PROGRESS_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Table, in the first schema of the `search_path`, recording the migrations
+/// applied to that schema.
+pub const MIGRATIONS_TABLE: &str = "pgshift_migrations";
+
+/// A migration recorded in [`MIGRATIONS_TABLE`].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AppliedMigration {
+    /// Migration directory name, e.g. `20240101120000__add_users`.
+    pub name: String,
+    /// SHA-256 of its `up.sql` when it was applied.
+    pub checksum: String,
+    pub applied_at: String,
+    pub duration_ms: i64,
+}
+
+/// A migration folder of a base path and whether it was applied.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MigrationStatus {
+    pub name: String,
+    /// SHA-256 of its `up.sql`.
+    pub checksum: String,
+    /// Its record; for a squashed folder, the record of the last folder it
+    /// replaces. None while it is pending.
+    pub applied: Option<AppliedMigration>,
+    /// `up.sql` changed after it was applied.
+    pub modified: bool,
+}
+
+/// Which migrations of a base path a database has applied.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MigrationStatusReport {
+    /// Every migration folder, in the order they apply.
+    pub migrations: Vec<MigrationStatus>,
+    /// Names of the migrations not applied yet, in order.
+    pub pending: Vec<String>,
+    /// Applied migrations without a folder in the base path.
+    pub missing: Vec<AppliedMigration>,
+}
+
+/// How a migration is applied.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ApplyOptions {
+    /// Run each statement in a savepoint and continue past the ones that
+    /// fail, as described in [`execute_migration_script`].
+    #[serde(default)]
+    pub continue_on_error: bool,
+    /// Dump the target with `pg_dump` before a dangerous migration.
+    #[serde(default)]
+    pub backup: Option<BackupOptions>,
+}
+
+/// Apply a migration SQL file to the database on `conn`, whose settings
+/// are `session`, with `options`, appending its log and statement results
+/// to `run`.
+///
+/// The folder's [hooks](crate::apply::hooks) run around it, their
+/// statements in `run` before and after those of `up.sql`.
+///
+/// While it runs, the progress views are polled on a separate connection of
+/// `pool` and every operation of the migration's session (index builds,
+/// rewrites) is passed to `on_progress`. Polling failures never affect the
+/// migration. Each statement is passed to `on_statement` when it starts and
+/// when it finishes. Cancelling `cancel` stops the script as described in
+/// [`execute_migration_script`].
+#[allow(clippy::too_many_arguments)]
+pub async fn apply_migration_sql<F, G>(
+    pool: &PgPool,
+    conn: &mut PgConnection,
+    session: &SessionSettings,
+    migration_path: &str,
+    options: &ApplyOptions,
+    run: &mut MigrationRun,
+    cancel: &CancelToken,
+    on_progress: F,
+    on_statement: G,
+) -> Result<(), String>
+where
+    F: Fn(&OperationProgress) + Sync,
+    G: Fn(&ApplyProgress) + Sync,
+{
+    let (name, sql) = read_migration_file(migration_path, "up.sql", "migration", &mut run.logs)?;
+    let before = hook_script(Path::new(migration_path), HookPoint::Before)?;
+    let after = hook_script(Path::new(migration_path), HookPoint::After)?;
+
+    ensure_migrations_table(conn)
+        .await
+        .map_err(|e| format!("Failed to create {}: {}", MIGRATIONS_TABLE, e))?;
+    if let Some(script) = &before {
+        log_line!(run.logs, Level::INFO, "Running the before hook");
+        let audit = audit_comment("apply", &format!("migration={} hook=before", name));
+        execute_migration_script(pool, conn, session, script, &audit, options, None, run, cancel, &on_progress, &on_statement)
+            .await
+            .map_err(|e| format!("The before hook failed, so the migration was not run: {}", e))?;
+    }
+    let audit = audit_comment("apply", &format!("migration={}", name));
+    let checksum = migration_checksum(&sql);
+    let record = MigrationRecord::Applied { name: &name, checksum: &checksum };
+    // A script in one transaction is recorded in it, so neither outlives the other
+    let in_script = runs_in_one_transaction(&sql);
+    let first_statement = run.statements.len();
+    let started = Instant::now();
+    execute_migration_script(
+        pool, conn, session, &sql, &audit, options, in_script.then_some(&record), run, cancel, &on_progress, &on_statement,
+    ).await?;
+    let duration_ms = started.elapsed().as_millis() as i64;
+
+    // A migration that only partly ran stays pending
+    if any_failed(&run.statements[first_statement..]) {
+        log_line!(
+            run.logs,
+            Level::WARN,
+            "⚠️  Migration ran in {} ms with failed statements, so it was not recorded in {} and its after hook did not run",
+            duration_ms,
+            MIGRATIONS_TABLE
+        );
+        return Ok(());
+    }
+    if !in_script {
+        record.write(conn, duration_ms)
+            .await
+            .map_err(|e| format!("Migration was applied but could not be recorded in {}: {}", MIGRATIONS_TABLE, e))?;
+    }
+    log_line!(run.logs, Level::INFO, "Migration completed successfully in {} ms", duration_ms);
+
+    if let Some(script) = &after {
+        log_line!(run.logs, Level::INFO, "Running the after hook");
+        let audit = audit_comment("apply", &format!("migration={} hook=after", name));
+        execute_migration_script(pool, conn, session, script, &audit, options, None, run, cancel, on_progress, on_statement)
+            .await
+            .map_err(|e| format!("Migration was applied and recorded, but its after hook failed: {}", e))?;
+    }
+
+    Ok(())
+}
+
+/// Roll back a migration by running its `down.sql` on `conn`, whose
+/// settings are `session`, and remove it from [`MIGRATIONS_TABLE`].
+///
+/// For a squashed folder, the records of the folders it replaces are
+/// removed too. Progress and cancellation are handled as in
+/// [`apply_migration_sql`].
+#[allow(clippy::too_many_arguments)]
+pub async fn rollback_migration_sql<F, G>(
     pool: &PgPool,
+    conn: &mut PgConnection,
+    session: &SessionSettings,
     migration_path: &str,
-) -> Result<Vec<String>, String> {
-    let mut logs = Vec::new();
-    
+    run: &mut MigrationRun,
+    cancel: &CancelToken,
+    on_progress: F,
+    on_statement: G,
+) -> Result<(), String>
+where
+    F: Fn(&OperationProgress) + Sync,
+    G: Fn(&ApplyProgress) + Sync,
+{
+    let (name, sql) = read_migration_file(migration_path, "down.sql", "rollback", &mut run.logs)?;
+
+    let audit = audit_comment("rollback", &format!("migration={}", name));
+    let mut names = vec![name];
+    names.extend(squashed_folders(Path::new(migration_path)));
+    let record = MigrationRecord::RolledBack { names: &names };
+    let in_script = runs_in_one_transaction(&sql);
+    let first_statement = run.statements.len();
+    let started = Instant::now();
+    execute_migration_script(
+        pool, conn, session, &sql, &audit, &ApplyOptions::default(), in_script.then_some(&record), run, cancel, on_progress, on_statement,
+    ).await?;
+    let duration_ms = started.elapsed().as_millis() as i64;
+
+    if any_failed(&run.statements[first_statement..]) {
+        log_line!(
+            run.logs,
+            Level::WARN,
+            "⚠️  Rollback ran in {} ms with failed statements, so the migration is still recorded in {}",
+            duration_ms,
+            MIGRATIONS_TABLE
+        );
+        return Ok(());
+    }
+    if !in_script {
+        record.write(conn, duration_ms)
+            .await
+            .map_err(|e| format!("Migration was rolled back but is still recorded in {}: {}", MIGRATIONS_TABLE, e))?;
+    }
+    log_line!(run.logs, Level::INFO, "Rollback completed successfully in {} ms", duration_ms);
+
+    Ok(())
+}
+
+/// Read `file` of the migration folder at `migration_path`, logging the
+/// start of the `operation`, and return the folder name and the script.
+fn read_migration_file(
+    migration_path: &str,
+    file: &str,
+    operation: &str,
+    logs: &mut Vec<String>,
+) -> Result<(String, String), String> {
     let path = Path::new(migration_path);
-    let up_sql_path = path.join("up.sql");
-    
-    if !up_sql_path.exists() {
-        return Err(format!("Migration file not found: {:?}", up_sql_path));
-    }
-    
-    logs.push(format!("[{}] Starting migration from: {}", timestamp(), migration_path));
-    
-    let sql = fs::read_to_string(&up_sql_path)
+    let sql_path = path.join(file);
+
+    if !sql_path.exists() {
+        return Err(format!("Migration file not found: {:?}", sql_path));
+    }
+
+    log_line!(logs, Level::INFO, "Starting {} from: {}", operation, migration_path);
+
+    let sql = fs::read_to_string(&sql_path)
         .map_err(|e| format!("Failed to read migration file: {}", e))?;
-    
-    logs.push(format!("[{}] Read migration file ({} bytes)", timestamp(), sql.len()));
-    
-    // Execute the SQL
-    logs.push(format!("[{}] Executing migration...", timestamp()));
-    
-    match sqlx::raw_sql(&sql).execute(pool).await {
-        Ok(result) => {
-            logs.push(format!(
-                "[{}] Migration executed successfully. Rows affected: {}",
-                timestamp(),
-                result.rows_affected()
-            ));
-        }
-        Err(e) => {
-            logs.push(format!("[{}] Migration FAILED: {}", timestamp(), e));
+
+    log_line!(logs, Level::INFO, "Read {} ({} bytes)", file, sql.len());
+
+    let name = path.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+    Ok((name, sql))
+}
+
+/// Comment identifying the origin of an applied script, e.g.
+/// `/* pgshift/0.1.0/apply migration=20240101120000__add_users */`.
+pub fn audit_comment(operation: &str, subject: &str) -> String {
+    // The subject must not end the comment early
+    format!("/* {} {} */", application_name(operation), subject.replace("*/", "* /"))
+}
+
+/// Outcome of one statement of an executed script.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StatementStatus {
+    Succeeded,
+    Failed,
+    /// Not run because an earlier statement failed.
+    Skipped,
+}
+
+/// A statement of an executed script.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StatementResult {
+    /// Position of the statement in the script, from 1.
+    pub index: usize,
+    /// Line of the script the statement starts on, from 1.
+    pub line: usize,
+    /// The statement, without the comment lines before it.
+    pub sql: String,
+    pub status: StatementStatus,
+    pub duration_ms: u64,
+    pub rows_affected: u64,
+    #[serde(default)]
+    pub error: Option<String>,
+}
+
+/// A statement of a running script, sent when it starts and when it
+/// finishes.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ApplyProgress {
+    /// Position of the statement in the script, from 1.
+    pub statement: usize,
+    pub statements_total: usize,
+    /// Line of the script the statement starts on, from 1.
+    pub line: usize,
+    pub sql: String,
+    /// None while the statement runs.
+    pub status: Option<StatementStatus>,
+    pub duration_ms: u64,
+}
+
+/// Log and statement results of an executed script.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct MigrationRun {
+    pub logs: Vec<String>,
+    pub statements: Vec<StatementResult>,
+}
+
+/// Execute a migration script on `conn`, whose settings are `session`,
+/// appending to the logs and statement results of `run`.
+///
+/// The statements run one at a time, each starting with `audit`, so the
+/// statement text in `pg_stat_activity` and the server log shows where it
+/// came from. Statements that cannot run in a transaction block, such as
+/// `CREATE INDEX CONCURRENTLY` or `ALTER TYPE ... ADD VALUE`, run on their
+/// own and every run of statements between them in one transaction; a
+/// script with its own `BEGIN` / `COMMIT` runs as written instead. The first
+/// failing statement stops the script and its transaction is rolled back.
+///
+/// With `continue_on_error` in `options`, each statement in a transaction
+/// runs in a savepoint instead, and a failing one is rolled back to it and
+/// recorded as failed while the script goes on; statements outside a
+/// transaction leave nothing to roll back. A failing `BEGIN`, `COMMIT` or
+/// other transaction control still stops the script, and the script
+/// succeeds with its failures in `run`.
+///
+/// A `record` is written to [`MIGRATIONS_TABLE`] just before the last
+/// transaction commits, unless a statement failed, and a failure to write
+/// it rolls that transaction back; pass one only for a script that runs in
+/// one transaction.
+///
+/// Cancelling `cancel` cancels the running statement with
+/// `pg_cancel_backend` and stops the script the same way. Progress is passed
+/// to `on_progress` and `on_statement` as in [`apply_migration_sql`].
+#[allow(clippy::too_many_arguments)]
+pub async fn execute_migration_script<F, G>(
+    pool: &PgPool,
+    conn: &mut PgConnection,
+    session: &SessionSettings,
+    sql: &str,
+    audit: &str,
+    options: &ApplyOptions,
+    record: Option<&MigrationRecord<'_>>,
+    run: &mut MigrationRun,
+    cancel: &CancelToken,
+    on_progress: F,
+    on_statement: G,
+) -> Result<(), String>
+where
+    F: Fn(&OperationProgress) + Sync,
+    G: Fn(&ApplyProgress) + Sync,
+{
+    log_line!(run.logs, Level::INFO, "Session: {}", session.summary());
+
+    if session.statement_timeout != "0" && sql.to_uppercase().contains("CREATE INDEX") {
+        log_line!(
+            run.logs,
+            Level::WARN,
+            "⚠️  statement_timeout is {}; a long index build will be cancelled when it is reached",
+            session.statement_timeout
+        );
+    }
+
+    let statements = script_statements(sql);
+    let as_written = statements.iter().any(|s| is_transaction_control(&s.body));
+    let outside = statements.iter().filter(|s| !runs_in_transaction(&s.body)).count();
+    log_line!(
+        run.logs,
+        Level::INFO,
+        "Executing {} statements{}...",
+        statements.len(),
+        if as_written {
+            " as written".to_string()
+        } else if outside == 0 {
+            " in one transaction".to_string()
+        } else {
+            format!(", {} outside a transaction", outside)
+        }
+    );
+
+    let monitor = async {
+        loop {
+            tokio::time::sleep(PROGRESS_POLL_INTERVAL).await;
+            if let Ok(operations) = fetch_operation_progress(pool, Some(session.backend_pid)).await {
+                operations.iter().for_each(&on_progress);
+            }
+        }
+    };
+    let canceller = async {
+        cancel.cancelled().await;
+        // The loop below stops before the next statement; this stops the running one
+        let _ = sqlx::query("SELECT pg_cancel_backend($1)")
+            .bind(session.backend_pid)
+            .execute(pool)
+            .await;
+        std::future::pending::<()>().await
+    };
+    // Statements committed so far
+    let mut committed = 0;
+    let first_statement = run.statements.len();
+    let started = Instant::now();
+    let execution = async {
+        let mut in_transaction = false;
+        for (i, statement) in statements.iter().enumerate() {
+            if cancel.is_cancelled() {
+                let _ = sqlx::raw_sql("ROLLBACK").execute(&mut *conn).await;
+                return Err(ScriptFailure::Cancelled(i));
+            }
+            if !as_written {
+                let transactional = runs_in_transaction(&statement.body);
+                if transactional && !in_transaction {
+                    sqlx::raw_sql("BEGIN").execute(&mut *conn).await.map_err(ScriptFailure::Transaction)?;
+                    in_transaction = true;
+                } else if !transactional && in_transaction {
+                    sqlx::raw_sql("COMMIT").execute(&mut *conn).await.map_err(ScriptFailure::Transaction)?;
+                    in_transaction = false;
+                    committed = i;
+                }
+            }
+            let mut progress = ApplyProgress {
+                statement: i + 1,
+                statements_total: statements.len(),
+                line: statement.line,
+                sql: statement.body.clone(),
+                status: None,
+                duration_ms: 0,
+            };
+            on_statement(&progress);
+            // Transaction control cannot run in a savepoint it would end
+            let savepoint = options.continue_on_error && in_transaction && !is_transaction_control(&statement.body);
+            if savepoint {
+                sqlx::raw_sql(&format!("SAVEPOINT {}", STATEMENT_SAVEPOINT))
+                    .execute(&mut *conn)
+                    .await
+                    .map_err(ScriptFailure::Transaction)?;
+            }
+            let started = Instant::now();
+            let result = sqlx::raw_sql(&format!("{}\n{}", audit, statement.sql)).execute(&mut *conn).await;
+            let (status, rows_affected, error) = match &result {
+                Ok(done) => (StatementStatus::Succeeded, done.rows_affected(), None),
+                Err(e) => (StatementStatus::Failed, 0, Some(e.to_string())),
+            };
+            progress.status = Some(status);
+            progress.duration_ms = started.elapsed().as_millis() as u64;
+            on_statement(&progress);
+            run.statements.push(StatementResult {
+                index: progress.statement,
+                line: progress.line,
+                sql: progress.sql,
+                status,
+                duration_ms: progress.duration_ms,
+                rows_affected,
+                error,
+            });
+            match &result {
+                Err(_) if options.continue_on_error
+                    && !cancel.is_cancelled()
+                    && (savepoint || !in_transaction && !is_transaction_control(&statement.body)) =>
+                {
+                    if savepoint {
+                        sqlx::raw_sql(&format!("ROLLBACK TO SAVEPOINT {}", STATEMENT_SAVEPOINT))
+                            .execute(&mut *conn)
+                            .await
+                            .map_err(ScriptFailure::Transaction)?;
+                    }
+                    continue_after_failure(&mut run.logs, run.statements.last().expect("just pushed"));
+                }
+                Err(_) => {
+                    // Leave no transaction open, whether ours or the script's
+                    let _ = sqlx::raw_sql("ROLLBACK").execute(&mut *conn).await;
+                    return Err(ScriptFailure::Statement(i));
+                }
+                Ok(_) => {
+                    if savepoint {
+                        sqlx::raw_sql(&format!("RELEASE SAVEPOINT {}", STATEMENT_SAVEPOINT))
+                            .execute(&mut *conn)
+                            .await
+                            .map_err(ScriptFailure::Transaction)?;
+                    }
+                    if as_written {
+                        in_transaction = script_transaction_open(&statement.body, in_transaction);
+                    }
+                }
+            }
+            if !in_transaction {
+                committed = i + 1;
+            }
+        }
+        if in_transaction && !as_written {
+            if let Some(record) = record.filter(|_| !any_failed(&run.statements[first_statement..])) {
+                if let Err(e) = record.write(&mut *conn, started.elapsed().as_millis() as i64).await {
+                    let _ = sqlx::raw_sql("ROLLBACK").execute(&mut *conn).await;
+                    return Err(ScriptFailure::Record(e));
+                }
+            }
+            sqlx::raw_sql("COMMIT").execute(&mut *conn).await.map_err(ScriptFailure::Transaction)?;
+            committed = statements.len();
+        }
+        Ok(())
+    };
+    let execution = tokio::select! {
+        result = execution => result,
+        _ = monitor => unreachable!("progress polling never finishes"),
+        _ = canceller => unreachable!("cancelling never finishes"),
+    };
+
+    // The first statement not run, and why the script stopped
+    let (first_skipped, message) = match execution {
+        Ok(()) => {
+            let rows: u64 = run.statements.iter().map(|s| s.rows_affected).sum();
+            let failed = run.statements.iter().filter(|s| s.status == StatementStatus::Failed).count();
+            if failed > 0 {
+                log_line!(
+                    run.logs,
+                    Level::WARN,
+                    "Migration executed with {} of {} statements failed. Rows affected: {}",
+                    failed,
+                    statements.len(),
+                    rows
+                );
+            } else {
+                log_line!(
+                    run.logs,
+                    Level::INFO,
+                    "Migration executed successfully. Rows affected: {}",
+                    rows
+                );
+            }
+            return Ok(());
+        }
+        Err(ScriptFailure::Transaction(e)) => {
+            log_line!(run.logs, Level::ERROR, "Migration FAILED: {}", e);
+            push_committed_warning(&mut run.logs, committed);
             return Err(format!("Migration execution failed: {}", e));
         }
+        Err(ScriptFailure::Record(e)) => {
+            log_line!(run.logs, Level::ERROR, "Migration FAILED: could not update {}: {}", MIGRATIONS_TABLE, e);
+            push_committed_warning(&mut run.logs, committed);
+            return Err(format!("Migration was rolled back because it could not be recorded in {}: {}", MIGRATIONS_TABLE, e));
+        }
+        Err(ScriptFailure::Cancelled(next)) => (
+            next,
+            format!("{} before statement {} of {}", CANCELLED, next + 1, statements.len()),
+        ),
+        Err(ScriptFailure::Statement(failed)) => {
+            let result = &run.statements[failed];
+            (failed + 1, format!(
+                "Statement {} of {} (line {}) {}: {}\n{}",
+                result.index,
+                statements.len(),
+                result.line,
+                if cancel.is_cancelled() { "was cancelled" } else { "failed" },
+                result.error.as_deref().unwrap_or_default(),
+                result.sql
+            ))
+        }
+    };
+
+    for (i, statement) in statements.iter().enumerate().skip(first_skipped) {
+        run.statements.push(StatementResult {
+            index: i + 1,
+            line: statement.line,
+            sql: statement.body.clone(),
+            status: StatementStatus::Skipped,
+            duration_ms: 0,
+            rows_affected: 0,
+            error: None,
+        });
+    }
+    log_line!(run.logs, Level::ERROR, "Migration FAILED: {}", message);
+    push_committed_warning(&mut run.logs, committed);
+    if committed > 0 {
+        return Err(format!(
+            "Migration execution failed: {} ({} statements committed before the failure were not rolled back)",
+            message, committed
+        ));
+    }
+    Err(format!("Migration execution failed: {}", message))
+}
+
+/// Whether any of `statements` failed.
+fn any_failed(statements: &[StatementResult]) -> bool {
+    statements.iter().any(|s| s.status == StatementStatus::Failed)
+}
+
+/// Whether a script's own transaction is open after `statement` ran, given
+/// whether it was before.
+fn script_transaction_open(statement: &str, open: bool) -> bool {
+    let upper = statement.to_uppercase();
+    let mut words = upper.split_whitespace();
+    match (words.next(), words.next()) {
+        (Some("BEGIN" | "START"), _) => true,
+        (Some("ROLLBACK"), Some("TO")) => open,
+        (Some("COMMIT" | "END" | "ROLLBACK" | "ABORT"), _) => false,
+        _ => open,
+    }
+}
+
+/// Log that the script goes on after the failed statement `result`.
+fn continue_after_failure(logs: &mut Vec<String>, result: &StatementResult) {
+    log_line!(
+        logs,
+        Level::WARN,
+        "⚠️  Statement {} (line {}) failed, continuing: {}",
+        result.index,
+        result.line,
+        result.error.as_deref().unwrap_or_default()
+    );
+}
+
+/// Warn that the first `committed` statements of a failed script stay applied.
+fn push_committed_warning(logs: &mut Vec<String>, committed: usize) {
+    if committed > 0 {
+        log_line!(
+            logs,
+            Level::WARN,
+            "⚠️  The {} statements committed before the failure were not rolled back",
+            committed
+        );
+    }
+}
+
+/// Why an executed script stopped.
+enum ScriptFailure {
+    /// The statement at this position failed.
+    Statement(usize),
+    /// Cancelled before the statement at this position started.
+    Cancelled(usize),
+    /// Opening or committing the transaction around the script failed.
+    Transaction(sqlx::Error),
+    /// Writing the record of the migration failed.
+    Record(sqlx::Error),
+}
+
+/// A statement of a script and where it starts.
+struct ScriptStatement {
+    /// The statement as written, with the comment lines before it.
+    sql: String,
+    /// The statement without those comment lines.
+    body: String,
+    line: usize,
+}
+
+/// The statements of `sql`, with the line each starts on.
+fn script_statements(sql: &str) -> Vec<ScriptStatement> {
+    let mut statements = Vec::new();
+    let mut offset = 0;
+    for statement in split_statements(sql) {
+        let start = sql[offset..].find(&statement).map_or(offset, |i| offset + i);
+        let mut body = statement.as_str();
+        while body.starts_with("--") {
+            body = body.split_once('\n').map_or("", |(_, next)| next.trim_start());
+        }
+        let body_start = start + statement.len() - body.len();
+        statements.push(ScriptStatement {
+            line: sql[..body_start].matches('\n').count() + 1,
+            body: body.to_string(),
+            sql: statement.clone(),
+        });
+        offset = start + statement.len();
+    }
+    statements
+}
+
+/// Checksum recorded for a migration: the SHA-256 hex digest of its `up.sql`.
+pub fn migration_checksum(sql: &str) -> String {
+    Sha256::digest(sql.as_bytes()).iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+async fn ensure_migrations_table(conn: &mut PgConnection) -> Result<(), sqlx::Error> {
+    sqlx::query(&format!(
+        "CREATE TABLE IF NOT EXISTS {} (
+            name text PRIMARY KEY,
+            checksum text NOT NULL,
+            applied_at timestamptz NOT NULL DEFAULT now(),
+            duration_ms bigint NOT NULL
+        )",
+        MIGRATIONS_TABLE
+    ))
+    .execute(conn)
+    .await?;
+    Ok(())
+}
+
+/// Change to [`MIGRATIONS_TABLE`] once a migration script ran.
+pub enum MigrationRecord<'a> {
+    /// Record `name`, whose `up.sql` has `checksum`, as applied.
+    Applied { name: &'a str, checksum: &'a str },
+    /// Remove the records of `names`.
+    RolledBack { names: &'a [String] },
+}
+
+impl MigrationRecord<'_> {
+    /// Write the change on `conn`, the script having taken `duration_ms`.
+    async fn write(&self, conn: &mut PgConnection, duration_ms: i64) -> Result<(), sqlx::Error> {
+        match self {
+            MigrationRecord::Applied { name, checksum } => record_migration(conn, name, checksum, duration_ms).await,
+            MigrationRecord::RolledBack { names } => forget_migrations(conn, names).await,
+        }
     }
-    
-    logs.push(format!("[{}] Migration completed successfully", timestamp()));
-    
-    Ok(logs)
 }
 
-/// Get current timestamp for logging.
-fn timestamp() -> String {
-    Utc::now().format("%H:%M:%S%.3f").to_string()
+/// Whether every statement of `sql` runs in the one transaction
+/// [`execute_migration_script`] wraps it in: there is at least one, none
+/// must run outside a transaction and the script does not manage its own.
+fn runs_in_one_transaction(sql: &str) -> bool {
+    let statements = script_statements(sql);
+    !statements.is_empty()
+        && statements.iter().all(|s| runs_in_transaction(&s.body) && !is_transaction_control(&s.body))
+}
+
+/// Record `name` as applied; applying it again replaces its record.
+async fn record_migration(
+    conn: &mut PgConnection,
+    name: &str,
+    checksum: &str,
+    duration_ms: i64,
+) -> Result<(), sqlx::Error> {
+    sqlx::query(&format!(
+        "INSERT INTO {} (name, checksum, duration_ms) VALUES ($1, $2, $3)
+         ON CONFLICT (name) DO UPDATE
+         SET checksum = EXCLUDED.checksum, applied_at = now(), duration_ms = EXCLUDED.duration_ms",
+        MIGRATIONS_TABLE
+    ))
+    .bind(name)
+    .bind(checksum)
+    .bind(duration_ms)
+    .execute(conn)
+    .await?;
+    Ok(())
+}
+
+/// Whether [`MIGRATIONS_TABLE`] exists in the current schema; one further
+/// down the `search_path` belongs to another schema.
+async fn migrations_table_exists(conn: &mut PgConnection) -> Result<bool, sqlx::Error> {
+    sqlx::query_scalar("SELECT to_regclass(format('%I.%I', current_schema(), $1::text)) IS NOT NULL")
+        .bind(MIGRATIONS_TABLE)
+        .fetch_one(conn)
+        .await
+}
+
+/// The migrations recorded on the database of `conn`, oldest first; none
+/// when [`MIGRATIONS_TABLE`] does not exist.
+pub async fn fetch_applied_migrations(conn: &mut PgConnection) -> Result<Vec<AppliedMigration>, sqlx::Error> {
+    if !migrations_table_exists(conn).await? {
+        return Ok(Vec::new());
+    }
+
+    let rows = sqlx::query(&format!(
+        r#"SELECT name, checksum,
+                  to_char(applied_at AT TIME ZONE 'UTC', 'YYYY-MM-DD"T"HH24:MI:SS.MS"Z"') AS applied_at,
+                  duration_ms
+           FROM {}
+           ORDER BY applied_at, name"#,
+        MIGRATIONS_TABLE
+    ))
+    .fetch_all(conn)
+    .await?;
+    Ok(rows
+        .iter()
+        .map(|row| AppliedMigration {
+            name: row.get("name"),
+            checksum: row.get("checksum"),
+            applied_at: row.get("applied_at"),
+            duration_ms: row.get("duration_ms"),
+        })
+        .collect())
+}
+
+/// Remove `names` from [`MIGRATIONS_TABLE`], if it exists.
+async fn forget_migrations(conn: &mut PgConnection, names: &[String]) -> Result<(), sqlx::Error> {
+    if migrations_table_exists(conn).await? {
+        sqlx::query(&format!("DELETE FROM {} WHERE name = ANY($1)", MIGRATIONS_TABLE))
+            .bind(names)
+            .execute(conn)
+            .await?;
+    }
+    Ok(())
+}
+
+/// Folders the migration folder `folder` replaced when they were squashed
+/// into it, from its `meta.json`.
+fn squashed_folders(folder: &Path) -> Vec<String> {
+    fs::read_to_string(folder.join("meta.json"))
+        .ok()
+        .and_then(|json| serde_json::from_str::<MigrationMeta>(&json).ok())
+        .map(|meta| meta.squashes)
+        .unwrap_or_default()
+}
+
+/// Match the migration `folders`, in apply order, against the `applied`
+/// records of a database.
+///
+/// A squashed folder counts as applied where every folder it replaces was.
+pub fn migration_status(folders: &[PathBuf], applied: Vec<AppliedMigration>) -> Result<MigrationStatusReport, String> {
+    let mut records: HashMap<String, AppliedMigration> = applied.iter()
+        .map(|record| (record.name.clone(), record.clone()))
+        .collect();
+    let mut migrations = Vec::new();
+    for folder in folders {
+        let name = folder.file_name().unwrap_or_default().to_string_lossy().to_string();
+        let sql = fs::read_to_string(folder.join("up.sql"))
+            .map_err(|e| format!("Failed to read {}/up.sql: {}", name, e))?;
+        let checksum = migration_checksum(&sql);
+
+        let (applied, modified) = match records.remove(&name) {
+            Some(record) => {
+                let modified = record.checksum != checksum;
+                (Some(record), modified)
+            }
+            None => {
+                let squashes = squashed_folders(folder);
+                let applied = if !squashes.is_empty() && squashes.iter().all(|s| records.contains_key(s)) {
+                    squashes.iter().filter_map(|s| records.remove(s)).last()
+                } else {
+                    None
+                };
+                (applied, false)
+            }
+        };
+        migrations.push(MigrationStatus { name, checksum, applied, modified });
+    }
+
+    let pending = migrations.iter()
+        .filter(|m| m.applied.is_none())
+        .map(|m| m.name.clone())
+        .collect();
+    // Records not matched by a folder, in the order they were applied
+    let missing = applied.into_iter().filter(|r| records.contains_key(&r.name)).collect();
+    Ok(MigrationStatusReport { migrations, pending, missing })
+}
+
+/// The folder of the migration `status` shows applied last, which a
+/// rollback of the last migration of `base_path` runs.
+///
+/// Fails when none is applied, or when the last applied one has no folder.
+pub fn last_applied_migration<'a>(status: &'a MigrationStatusReport, base_path: &str) -> Result<&'a str, String> {
+    // Of equal times, the later folder was applied last
+    let last = status.migrations.iter()
+        .filter_map(|m| m.applied.as_ref().map(|applied| (m, applied)))
+        .max_by(|(_, a), (_, b)| a.applied_at.cmp(&b.applied_at))
+        .ok_or_else(|| "No applied migrations to roll back".to_string())?;
+    if let Some(missing) = status.missing.iter().find(|m| m.applied_at > last.1.applied_at) {
+        return Err(format!("The last applied migration, {}, has no folder in {}", missing.name, base_path));
+    }
+    Ok(&last.0.name)
+}
+
+/// Combine the status of the same migration folders in several schemas: a
+/// migration counts as applied, with the record of the first schema, once
+/// every schema has applied it, and as modified when it changed in any.
+pub fn merge_migration_status(reports: Vec<MigrationStatusReport>) -> MigrationStatusReport {
+    let mut reports = reports.into_iter();
+    let Some(mut merged) = reports.next() else {
+        return MigrationStatusReport { migrations: Vec::new(), pending: Vec::new(), missing: Vec::new() };
+    };
+    for report in reports {
+        for (migration, other) in merged.migrations.iter_mut().zip(report.migrations) {
+            if other.applied.is_none() {
+                migration.applied = None;
+            }
+            migration.modified |= other.modified;
+        }
+        for record in report.missing {
+            if !merged.missing.iter().any(|m| m.name == record.name) {
+                merged.missing.push(record);
+            }
+        }
+    }
+    merged.pending = merged.migrations.iter()
+        .filter(|m| m.applied.is_none())
+        .map(|m| m.name.clone())
+        .collect();
+    merged
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_audit_comment() {
+        assert_eq!(
+            audit_comment("apply", "migration=20240101120000__add_users"),
+            format!("/* pgshift/{}/apply migration=20240101120000__add_users */", env!("CARGO_PKG_VERSION"))
+        );
+        assert!(!audit_comment("apply", "migration=a*/b").contains("a*/"));
+    }
+
+    #[test]
+    fn test_script_statements() {
+        let sql = "-- Migration UP Script\n\nBEGIN;\n\n-- Create table 'users'\nCREATE TABLE users (\n    id integer\n);\n\
+                   CREATE FUNCTION f() RETURNS void AS $$ BEGIN; COMMIT; $$ LANGUAGE sql;\nCOMMIT;\n";
+        let statements = script_statements(sql);
+        let lines: Vec<(usize, &str)> = statements.iter().map(|s| (s.line, s.body.as_str())).collect();
+        assert_eq!(lines, [
+            (3, "BEGIN"),
+            (6, "CREATE TABLE users (\n    id integer\n)"),
+            (9, "CREATE FUNCTION f() RETURNS void AS $$ BEGIN; COMMIT; $$ LANGUAGE sql"),
+            (10, "COMMIT"),
+        ]);
+        assert!(statements[1].sql.starts_with("-- Create table 'users'\n"));
+    }
+
+    #[test]
+    fn test_migration_status() {
+        let dir = std::env::temp_dir().join(format!("pgshift_status_{}", uuid::Uuid::new_v4()));
+        for (folder, sql) in [
+            ("20260101000000__init", "CREATE TABLE users (id integer);"),
+            ("20260103000000__squash", "ALTER TABLE users ADD COLUMN email text;"),
+            ("20260104000000__add_name", "ALTER TABLE users ADD COLUMN name text;"),
+            ("20260105000000__edited", "ALTER TABLE users ADD COLUMN age integer;"),
+        ] {
+            fs::create_dir_all(dir.join(folder)).unwrap();
+            fs::write(dir.join(folder).join("up.sql"), sql).unwrap();
+        }
+        fs::write(
+            dir.join("20260103000000__squash/meta.json"),
+            r#"{"name": "squash", "timestamp": "20260103000000", "generated_at": "", "items_count": 1,
+                "has_dangerous": false, "items": [],
+                "squashes": ["20260102000000__a", "20260103000000__b"]}"#,
+        ).unwrap();
+        let record = |name: &str, sql: &str| AppliedMigration {
+            name: name.to_string(),
+            checksum: migration_checksum(sql),
+            applied_at: "2026-01-06T00:00:00.000Z".to_string(),
+            duration_ms: 5,
+        };
+        let applied = vec![
+            record("20260101000000__init", "CREATE TABLE users (id integer);"),
+            record("20260102000000__a", ""),
+            record("20260103000000__b", ""),
+            record("20260105000000__edited", "ALTER TABLE users ADD COLUMN age int;"),
+            record("20251231000000__removed", ""),
+        ];
+
+        let folders = crate::ddl::replay::migration_folders(dir.to_str().unwrap()).unwrap();
+        let status = migration_status(&folders, applied).unwrap();
+        let behind = migration_status(&folders, vec![record("20260101000000__init", "CREATE TABLE users (id integer);")]).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(status.pending, ["20260104000000__add_name"]);
+        // The squashed folder takes the record of the last folder it replaces
+        assert_eq!(status.migrations[1].applied.as_ref().unwrap().name, "20260103000000__b");
+        assert!(!status.migrations[0].modified && status.migrations[3].modified);
+        let missing: Vec<&str> = status.missing.iter().map(|m| m.name.as_str()).collect();
+        assert_eq!(missing, ["20251231000000__removed"]);
+
+        // Another schema behind on migrations keeps them pending
+        let merged = merge_migration_status(vec![status, behind]);
+        assert_eq!(merged.pending, ["20260103000000__squash", "20260104000000__add_name", "20260105000000__edited"]);
+        assert!(merged.migrations[0].applied.is_some() && merged.migrations[3].modified);
+        assert_eq!(merged.missing.len(), 1);
+    }
+
+    #[test]
+    fn test_last_applied_migration() {
+        let record = |name: &str, applied_at: &str| AppliedMigration {
+            name: name.to_string(),
+            checksum: String::new(),
+            applied_at: applied_at.to_string(),
+            duration_ms: 1,
+        };
+        let status = |applied: &[Option<&str>], missing: Vec<AppliedMigration>| MigrationStatusReport {
+            migrations: ["20260101000000__a", "20260102000000__b", "20260103000000__c"].iter().zip(applied)
+                .map(|(name, at)| MigrationStatus {
+                    name: name.to_string(),
+                    checksum: String::new(),
+                    applied: at.map(|at| record(name, at)),
+                    modified: false,
+                })
+                .collect(),
+            pending: Vec::new(),
+            missing,
+        };
+
+        // Applied out of order, c before b
+        let report = status(&[Some("2026-01-01T00:00:00.000Z"), Some("2026-01-03T00:00:00.000Z"), Some("2026-01-02T00:00:00.000Z")], Vec::new());
+        assert_eq!(last_applied_migration(&report, "migrations").unwrap(), "20260102000000__b");
+        // Of equal times, the later folder
+        let report = status(&[Some("2026-01-01T00:00:00.000Z"), Some("2026-01-01T00:00:00.000Z"), None], Vec::new());
+        assert_eq!(last_applied_migration(&report, "migrations").unwrap(), "20260102000000__b");
+
+        assert_eq!(last_applied_migration(&status(&[None, None, None], Vec::new()), "migrations").unwrap_err(), "No applied migrations to roll back");
+        let report = status(&[Some("2026-01-01T00:00:00.000Z"), None, None], vec![record("20260104000000__gone", "2026-01-04T00:00:00.000Z")]);
+        assert_eq!(
+            last_applied_migration(&report, "migrations").unwrap_err(),
+            "The last applied migration, 20260104000000__gone, has no folder in migrations"
+        );
+    }
 }
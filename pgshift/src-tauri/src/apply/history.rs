@@ -0,0 +1,56 @@
+//! Record of migrations applied from a base path.
+
+use serde::{Deserialize, Serialize};
+use crate::apply::exec::StatementResult;
+use crate::db::identity::DatabaseIdentity;
+use crate::db::session::SessionSettings;
+use crate::storage::{open_shared_store, HistoryStoreExt};
+use crate::store::MetadataStore;
+
+const HISTORY_FILE: &str = "apply_history.json";
+
+/// One attempt to apply or roll back a migration.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ApplyRecord {
+    pub id: String,
+    /// Migration directory name, e.g. `20240101120000__add_users`.
+    pub migration_name: String,
+    /// Database the migration was applied to; None when it could not be identified.
+    pub target: Option<DatabaseIdentity>,
+    pub applied_at: String,
+    pub success: bool,
+    #[serde(default)]
+    pub error: Option<String>,
+    /// Settings of the session the migration ran in; None when the apply
+    /// failed before they could be read.
+    #[serde(default)]
+    pub session: Option<SessionSettings>,
+    /// The migration's `down.sql` was run to roll it back.
+    #[serde(default)]
+    pub rollback: bool,
+    /// Each statement of the script, up to the one that failed.
+    #[serde(default)]
+    pub statements: Vec<StatementResult>,
+    /// `pg_dump` backup of the target taken before the migration.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub backup_path: Option<String>,
+}
+
+/// Load the apply history stored for `base_path`, oldest first.
+pub fn load_history(base_path: &str) -> Result<Vec<ApplyRecord>, String> {
+    match open_shared_store(base_path)? {
+        Some(store) => store.read_json(HISTORY_FILE),
+        None => MetadataStore::open(base_path)?.list_apply_history(),
+    }
+}
+
+/// Append a record to the apply history for `base_path`.
+pub fn record_apply(base_path: &str, record: ApplyRecord) -> Result<(), String> {
+    match open_shared_store(base_path)? {
+        Some(store) => store.update_json(HISTORY_FILE, |history: &mut Vec<ApplyRecord>| {
+            history.push(record.clone());
+            Ok(())
+        }),
+        None => MetadataStore::open(base_path)?.record_apply(&record),
+    }
+}
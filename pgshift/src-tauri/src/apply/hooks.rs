@@ -0,0 +1,130 @@
+//! Hook scripts run around the `up.sql` of a migration.
+//!
+//! A migration folder may hold a `before.sql` and an `after.sql`, and its
+//! `meta.json` may list statements under `hooks.before` and `hooks.after`,
+//! e.g. to disable triggers before a backfill, refresh materialized views
+//! after it, or `NOTIFY` an application. The `before` hook runs first and
+//! its failure keeps the migration from running; the `after` hook runs once
+//! the migration is applied and recorded.
+
+use std::fs;
+use std::path::Path;
+use serde::{Deserialize, Serialize};
+
+/// Hook statements listed in a `meta.json`.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct MigrationHooks {
+    /// Statements run before `up.sql`, after `before.sql`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub before: Vec<String>,
+    /// Statements run after `up.sql`, after `after.sql`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub after: Vec<String>,
+}
+
+impl MigrationHooks {
+    pub fn is_empty(&self) -> bool {
+        self.before.is_empty() && self.after.is_empty()
+    }
+}
+
+/// When a hook runs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HookPoint {
+    Before,
+    After,
+}
+
+impl HookPoint {
+    pub fn name(self) -> &'static str {
+        match self {
+            HookPoint::Before => "before",
+            HookPoint::After => "after",
+        }
+    }
+
+    /// Script file of the migration folder holding this hook.
+    pub fn file(self) -> &'static str {
+        match self {
+            HookPoint::Before => "before.sql",
+            HookPoint::After => "after.sql",
+        }
+    }
+}
+
+/// Only the hooks of a `meta.json`, which may be written by hand.
+#[derive(Deserialize)]
+struct HooksMeta {
+    #[serde(default)]
+    hooks: MigrationHooks,
+}
+
+/// The script the migration folder `folder` runs at `point`: its hook file
+/// followed by the hook statements of its `meta.json`, or None when it has
+/// neither.
+pub fn hook_script(folder: &Path, point: HookPoint) -> Result<Option<String>, String> {
+    let mut parts = Vec::new();
+    let file = folder.join(point.file());
+    if file.exists() {
+        let sql = fs::read_to_string(&file).map_err(|e| format!("Failed to read {}: {}", point.file(), e))?;
+        if !sql.trim().is_empty() {
+            parts.push(sql.trim_end().to_string());
+        }
+    }
+
+    let meta_path = folder.join("meta.json");
+    if meta_path.exists() {
+        let json = fs::read_to_string(&meta_path).map_err(|e| format!("Failed to read meta.json: {}", e))?;
+        let meta: HooksMeta = serde_json::from_str(&json).map_err(|e| format!("Invalid meta.json: {}", e))?;
+        let statements = match point {
+            HookPoint::Before => meta.hooks.before,
+            HookPoint::After => meta.hooks.after,
+        };
+        for statement in statements {
+            let statement = statement.trim().trim_end_matches(';');
+            if !statement.is_empty() {
+                parts.push(format!("{};", statement));
+            }
+        }
+    }
+
+    Ok((!parts.is_empty()).then(|| parts.join("\n")))
+}
+
+/// Whether the migration folder `folder` has a hook file or hook statements.
+pub fn has_hooks(folder: &Path) -> bool {
+    [HookPoint::Before, HookPoint::After]
+        .into_iter()
+        .any(|point| hook_script(folder, point).map_or(true, |script| script.is_some()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hook_script() {
+        let dir = std::env::temp_dir().join(format!("pgshift_hooks_{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(&dir).unwrap();
+        assert_eq!(hook_script(&dir, HookPoint::Before).unwrap(), None);
+
+        fs::write(dir.join("before.sql"), "ALTER TABLE orders DISABLE TRIGGER USER;\n").unwrap();
+        fs::write(
+            dir.join("meta.json"),
+            r#"{"name": "backfill", "hooks": {"after": ["REFRESH MATERIALIZED VIEW order_totals", "NOTIFY app;"]}}"#,
+        ).unwrap();
+        assert_eq!(
+            hook_script(&dir, HookPoint::Before).unwrap().as_deref(),
+            Some("ALTER TABLE orders DISABLE TRIGGER USER;")
+        );
+        assert_eq!(
+            hook_script(&dir, HookPoint::After).unwrap().as_deref(),
+            Some("REFRESH MATERIALIZED VIEW order_totals;\nNOTIFY app;")
+        );
+        assert!(has_hooks(&dir));
+
+        fs::write(dir.join("meta.json"), "{").unwrap();
+        assert!(hook_script(&dir, HookPoint::After).is_err());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,559 @@
+//! Lock impact of a migration.
+//!
+//! Each statement of `up.sql` is mapped to the table locks PostgreSQL takes
+//! for it, following the lock levels documented for each command. The
+//! target's `pg_locks` and `pg_stat_activity` then show the sessions holding
+//! or waiting for a conflicting lock on those tables: the migration would
+//! wait behind them, and while it waits for an `ACCESS EXCLUSIVE` lock every
+//! new query on the table queues behind it.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+use serde::{Deserialize, Serialize};
+use sqlparser::dialect::PostgreSqlDialect;
+use sqlparser::tokenizer::{Token, Tokenizer};
+use sqlx::postgres::PgPool;
+use sqlx::Row;
+use crate::ddl::parse::split_statements;
+
+/// A table lock mode, weakest first.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LockLevel {
+    AccessShare,
+    RowShare,
+    RowExclusive,
+    ShareUpdateExclusive,
+    Share,
+    ShareRowExclusive,
+    Exclusive,
+    AccessExclusive,
+}
+
+impl LockLevel {
+    /// e.g. `ACCESS EXCLUSIVE`.
+    pub fn name(self) -> &'static str {
+        match self {
+            LockLevel::AccessShare => "ACCESS SHARE",
+            LockLevel::RowShare => "ROW SHARE",
+            LockLevel::RowExclusive => "ROW EXCLUSIVE",
+            LockLevel::ShareUpdateExclusive => "SHARE UPDATE EXCLUSIVE",
+            LockLevel::Share => "SHARE",
+            LockLevel::ShareRowExclusive => "SHARE ROW EXCLUSIVE",
+            LockLevel::Exclusive => "EXCLUSIVE",
+            LockLevel::AccessExclusive => "ACCESS EXCLUSIVE",
+        }
+    }
+
+    /// The level of a `pg_locks.mode`, e.g. `AccessShareLock`.
+    pub fn from_mode(mode: &str) -> Option<LockLevel> {
+        let level = match mode {
+            "AccessShareLock" => LockLevel::AccessShare,
+            "RowShareLock" => LockLevel::RowShare,
+            "RowExclusiveLock" => LockLevel::RowExclusive,
+            "ShareUpdateExclusiveLock" => LockLevel::ShareUpdateExclusive,
+            "ShareLock" => LockLevel::Share,
+            "ShareRowExclusiveLock" => LockLevel::ShareRowExclusive,
+            "ExclusiveLock" => LockLevel::Exclusive,
+            "AccessExclusiveLock" => LockLevel::AccessExclusive,
+            _ => return None,
+        };
+        Some(level)
+    }
+
+    /// Whether two sessions cannot hold `self` and `other` on the same table
+    /// at once.
+    pub fn conflicts_with(self, other: LockLevel) -> bool {
+        use LockLevel::*;
+        let (weaker, stronger) = if self <= other { (self, other) } else { (other, self) };
+        match weaker {
+            AccessShare => stronger == AccessExclusive,
+            RowShare => stronger >= Exclusive,
+            RowExclusive => stronger >= Share,
+            ShareUpdateExclusive => stronger >= ShareUpdateExclusive,
+            Share => stronger != Share,
+            ShareRowExclusive | Exclusive | AccessExclusive => true,
+        }
+    }
+
+    /// Plain `SELECT`s wait while it is held.
+    pub fn blocks_reads(self) -> bool {
+        self.conflicts_with(LockLevel::AccessShare)
+    }
+
+    /// `INSERT`, `UPDATE` and `DELETE` wait while it is held.
+    pub fn blocks_writes(self) -> bool {
+        self.conflicts_with(LockLevel::RowExclusive)
+    }
+}
+
+/// How much a migration can disrupt the sessions on its tables.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LockRisk {
+    /// No lock blocks reads or writes.
+    Low,
+    /// Reads or writes of an existing table wait while the statement runs.
+    Medium,
+    /// A session already holds or waits for a conflicting lock.
+    High,
+}
+
+/// A session holding or waiting for a lock that conflicts with one the
+/// migration takes.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BlockingSession {
+    pub pid: i32,
+    /// `pg_locks.mode`, e.g. `AccessShareLock`.
+    pub mode: String,
+    /// False while the session itself waits for the lock.
+    pub granted: bool,
+    pub state: Option<String>,
+    pub application_name: String,
+    pub username: Option<String>,
+    pub query: Option<String>,
+    /// Since its transaction (or, outside one, its query) started.
+    pub running_seconds: Option<f64>,
+}
+
+/// A lock a statement takes on a table.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TableLock {
+    /// The table, or the index or view for statements on those.
+    pub table: String,
+    pub level: LockLevel,
+    pub blocks_reads: bool,
+    pub blocks_writes: bool,
+    /// False for tables the migration creates before locking them.
+    pub exists: bool,
+    pub conflicting_sessions: Vec<BlockingSession>,
+}
+
+/// A statement of the migration and its locks.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StatementLocks {
+    /// Position of the statement in `up.sql`, from 1.
+    pub position: usize,
+    pub statement: String,
+    pub locks: Vec<TableLock>,
+    pub risk: LockRisk,
+    /// Why the locks could not be determined, e.g. for a `DO` block.
+    pub note: Option<String>,
+}
+
+/// Locks a migration takes and the sessions it would wait on.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LockImpact {
+    /// The statements that take table locks or could not be analyzed.
+    pub statements: Vec<StatementLocks>,
+    /// Every statement of `up.sql`.
+    pub total_statements: usize,
+    /// The highest risk of its statements.
+    pub risk: LockRisk,
+}
+
+/// Analyze the locks of the migration at `migration_path` against the
+/// current sessions on the database of `pool`.
+pub async fn analyze_lock_impact(pool: &PgPool, migration_path: &str) -> Result<LockImpact, String> {
+    let up_sql_path = Path::new(migration_path).join("up.sql");
+    let sql = fs::read_to_string(&up_sql_path)
+        .map_err(|e| format!("Failed to read {}: {}", up_sql_path.display(), e))?;
+
+    let statements = split_statements(&sql);
+    let mut relations: BTreeMap<String, Option<Relation>> = BTreeMap::new();
+    let mut impact = LockImpact { statements: Vec::new(), total_statements: statements.len(), risk: LockRisk::Low };
+    for (i, statement) in statements.iter().enumerate() {
+        let (locks, note) = match statement_locks(statement) {
+            Some(locks) => (locks, None),
+            None => (Vec::new(), Some("Locks of procedural code are not analyzed; review it by hand".to_string())),
+        };
+        if locks.is_empty() && note.is_none() {
+            continue;
+        }
+
+        let mut table_locks = Vec::new();
+        for (name, level) in locks {
+            if !relations.contains_key(&name) {
+                let relation = find_relation(pool, &name)
+                    .await
+                    .map_err(|e| format!("Failed to look up {}: {}", name, e))?;
+                relations.insert(name.clone(), relation);
+            }
+            let relation = relations[&name].as_ref();
+            let conflicting_sessions = match relation {
+                Some(relation) => conflicting_sessions(pool, relation.oid, level)
+                    .await
+                    .map_err(|e| format!("Failed to read the locks on {}: {}", name, e))?,
+                None => Vec::new(),
+            };
+            table_locks.push(TableLock {
+                table: relation.map_or(name, |r| r.name.clone()),
+                level,
+                blocks_reads: level.blocks_reads(),
+                blocks_writes: level.blocks_writes(),
+                exists: relation.is_some(),
+                conflicting_sessions,
+            });
+        }
+
+        let risk = table_locks.iter()
+            .map(|lock| if !lock.conflicting_sessions.is_empty() {
+                LockRisk::High
+            } else if lock.exists && (lock.blocks_reads || lock.blocks_writes) {
+                LockRisk::Medium
+            } else {
+                LockRisk::Low
+            })
+            .max()
+            .unwrap_or(LockRisk::Low);
+        impact.risk = impact.risk.max(risk);
+        impact.statements.push(StatementLocks {
+            position: i + 1,
+            statement: statement.trim().to_string(),
+            locks: table_locks,
+            risk,
+            note,
+        });
+    }
+
+    Ok(impact)
+}
+
+/// A relation of the target; indexes resolve to their table, which is what
+/// sessions lock.
+struct Relation {
+    oid: u32,
+    name: String,
+}
+
+async fn find_relation(pool: &PgPool, name: &str) -> Result<Option<Relation>, sqlx::Error> {
+    let row = sqlx::query(
+        r#"
+        SELECT
+            COALESCE(x.indrelid, c.oid)::oid AS oid,
+            COALESCE(x.indrelid, c.oid)::regclass::text AS name
+        FROM pg_class c
+        LEFT JOIN pg_index x ON x.indexrelid = c.oid
+        WHERE c.oid = to_regclass($1)
+        "#
+    )
+    .bind(name)
+    .fetch_optional(pool)
+    .await?;
+    Ok(row.map(|row| Relation { oid: row.get::<sqlx::postgres::types::Oid, _>("oid").0, name: row.get("name") }))
+}
+
+/// Sessions other than this one holding or waiting for a lock on the
+/// relation `oid` that conflicts with `level`.
+async fn conflicting_sessions(pool: &PgPool, oid: u32, level: LockLevel) -> Result<Vec<BlockingSession>, sqlx::Error> {
+    let rows = sqlx::query(
+        r#"
+        SELECT
+            a.pid,
+            l.mode,
+            l.granted,
+            a.state,
+            a.application_name,
+            a.usename::text AS username,
+            a.query,
+            EXTRACT(EPOCH FROM now() - COALESCE(a.xact_start, a.query_start))::float8 AS running_seconds
+        FROM pg_locks l
+        JOIN pg_stat_activity a ON a.pid = l.pid
+        WHERE l.locktype = 'relation'
+            AND l.relation = $1
+            AND l.database = (SELECT oid FROM pg_database WHERE datname = current_database())
+            AND a.pid <> pg_backend_pid()
+        ORDER BY running_seconds DESC NULLS LAST
+        "#
+    )
+    .bind(sqlx::postgres::types::Oid(oid))
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows.into_iter()
+        .filter(|row| LockLevel::from_mode(row.get("mode")).is_some_and(|held| held.conflicts_with(level)))
+        .map(|row| BlockingSession {
+            pid: row.get("pid"),
+            mode: row.get("mode"),
+            granted: row.get("granted"),
+            state: row.get("state"),
+            application_name: row.get("application_name"),
+            username: row.get("username"),
+            query: row.get("query"),
+            running_seconds: row.get("running_seconds"),
+        })
+        .collect())
+}
+
+/// The relations `statement` locks and how, strongest lock per relation;
+/// None for procedural code whose statements are not known.
+///
+/// Relations are named as written, for `to_regclass`. Statements that lock
+/// no table, such as `CREATE TYPE` or `GRANT`, give an empty list.
+pub fn statement_locks(statement: &str) -> Option<Vec<(String, LockLevel)>> {
+    let tokens: Vec<Token> = Tokenizer::new(&PostgreSqlDialect {}, statement)
+        .tokenize()
+        .ok()?
+        .into_iter()
+        .filter(|t| !matches!(t, Token::Whitespace(_) | Token::SemiColon))
+        .collect();
+    let parser = Words { tokens: &tokens };
+    let mut locks: Vec<(String, LockLevel)> = Vec::new();
+    let mut lock = |name: Option<String>, level: LockLevel| {
+        let Some(name) = name else { return };
+        match locks.iter_mut().find(|(n, _)| *n == name) {
+            Some((_, existing)) => *existing = (*existing).max(level),
+            None => locks.push((name, level)),
+        }
+    };
+
+    let concurrently = parser.position("CONCURRENTLY").is_some_and(|i| i < 6);
+    match (parser.keyword(0).as_str(), parser.keyword(1).as_str()) {
+        ("DO" | "CALL", _) => return None,
+        ("ALTER", "TABLE") => {
+            let (name, next) = parser.name_after(2, &["IF", "EXISTS", "ONLY"]);
+            for action in parser.actions(next) {
+                let level = alter_table_level(&parser, action.clone());
+                lock(name.clone(), level);
+                // New foreign keys also lock the referenced table
+                if let Some(i) = action.clone().find(|&i| parser.keyword(i) == "REFERENCES") {
+                    lock(parser.name_after(i + 1, &["ONLY"]).0, LockLevel::ShareRowExclusive);
+                }
+                if matches!(parser.keyword(action.start).as_str(), "ATTACH" | "DETACH") {
+                    lock(parser.name_after(action.start + 2, &[]).0, LockLevel::AccessExclusive);
+                }
+            }
+        }
+        ("CREATE", "TABLE") | ("CREATE", "UNLOGGED") => {
+            for i in (0..tokens.len()).filter(|&i| parser.keyword(i) == "REFERENCES") {
+                lock(parser.name_after(i + 1, &["ONLY"]).0, LockLevel::ShareRowExclusive);
+            }
+            // PARTITION OF locks the parent
+            if let Some(i) = parser.position("PARTITION").filter(|&i| parser.keyword(i + 1) == "OF") {
+                lock(parser.name_after(i + 2, &[]).0, LockLevel::AccessExclusive);
+            }
+        }
+        ("CREATE", _) if parser.position("INDEX").is_some_and(|i| i < 3) => {
+            if let Some(i) = parser.position("ON") {
+                let level = if concurrently { LockLevel::ShareUpdateExclusive } else { LockLevel::Share };
+                lock(parser.name_after(i + 1, &["ONLY"]).0, level);
+            }
+        }
+        ("DROP", "INDEX") => {
+            let level = if concurrently { LockLevel::ShareUpdateExclusive } else { LockLevel::AccessExclusive };
+            for name in parser.name_list(2, &["CONCURRENTLY", "IF", "EXISTS"]) {
+                lock(Some(name), level);
+            }
+        }
+        ("DROP", "TABLE" | "VIEW") | ("DROP", "MATERIALIZED") | ("DROP", "FOREIGN") | ("TRUNCATE", _) => {
+            let start = if parser.keyword(1) == "TABLE" && parser.keyword(0) == "TRUNCATE" { 2 } else { 1 };
+            let skip = ["TABLE", "VIEW", "MATERIALIZED", "FOREIGN", "IF", "EXISTS", "ONLY"];
+            for name in parser.name_list(start, &skip) {
+                lock(Some(name), LockLevel::AccessExclusive);
+            }
+        }
+        ("INSERT", _) | ("MERGE", _) => lock(parser.name_after(2, &["ONLY"]).0, LockLevel::RowExclusive),
+        ("UPDATE", _) => lock(parser.name_after(1, &["ONLY"]).0, LockLevel::RowExclusive),
+        ("DELETE", _) => lock(parser.name_after(2, &["ONLY"]).0, LockLevel::RowExclusive),
+        ("CREATE", _) if parser.keyword(1) == "TRIGGER" || parser.keyword(3) == "TRIGGER" => {
+            if let Some(i) = parser.position("ON") {
+                lock(parser.name_after(i + 1, &[]).0, LockLevel::ShareRowExclusive);
+            }
+        }
+        ("DROP", "TRIGGER") | ("CREATE" | "ALTER" | "DROP", "POLICY") | ("ALTER", "TRIGGER") => {
+            if let Some(i) = parser.position("ON") {
+                lock(parser.name_after(i + 1, &[]).0, LockLevel::AccessExclusive);
+            }
+        }
+        ("COMMENT", "ON") => match parser.keyword(2).as_str() {
+            "TABLE" => lock(parser.name_after(3, &[]).0, LockLevel::ShareUpdateExclusive),
+            "COLUMN" => lock(parser.name_after(3, &[]).0.and_then(|n| {
+                n.rsplit_once('.').map(|(table, _)| table.to_string())
+            }), LockLevel::ShareUpdateExclusive),
+            _ => {}
+        },
+        ("VACUUM", _) => {
+            let level = if parser.position("FULL").is_some_and(|i| i < 4) { LockLevel::AccessExclusive } else { LockLevel::ShareUpdateExclusive };
+            for name in parser.name_list(1, &["FULL", "FREEZE", "VERBOSE", "ANALYZE"]) {
+                lock(Some(name), level);
+            }
+        }
+        ("ANALYZE", _) => {
+            for name in parser.name_list(1, &["VERBOSE"]) {
+                lock(Some(name), LockLevel::ShareUpdateExclusive);
+            }
+        }
+        ("CLUSTER", _) => lock(parser.name_after(1, &["VERBOSE"]).0, LockLevel::AccessExclusive),
+        ("REINDEX", _) => {
+            let level = if concurrently { LockLevel::ShareUpdateExclusive } else { LockLevel::AccessExclusive };
+            if matches!(parser.keyword(1).as_str(), "INDEX" | "TABLE") {
+                lock(parser.name_after(2, &["CONCURRENTLY"]).0, level);
+            }
+        }
+        ("REFRESH", "MATERIALIZED") => {
+            let level = if concurrently { LockLevel::Exclusive } else { LockLevel::AccessExclusive };
+            lock(parser.name_after(3, &["CONCURRENTLY"]).0, level);
+        }
+        ("ALTER", "INDEX") => {
+            let (name, next) = parser.name_after(2, &["IF", "EXISTS"]);
+            let level = match parser.keyword(next).as_str() {
+                "RENAME" | "SET" if parser.keyword(next + 1) != "TABLESPACE" => LockLevel::ShareUpdateExclusive,
+                _ => LockLevel::AccessExclusive,
+            };
+            lock(name, level);
+        }
+        ("ALTER", "VIEW" | "MATERIALIZED") | ("ALTER", "FOREIGN") => {
+            let start = if parser.keyword(1) == "VIEW" { 2 } else { 3 };
+            lock(parser.name_after(start, &["IF", "EXISTS"]).0, LockLevel::AccessExclusive);
+        }
+        _ => {}
+    }
+    Some(locks)
+}
+
+/// Level `ALTER TABLE` takes for the action at `range` of the tokens.
+fn alter_table_level(parser: &Words, range: std::ops::Range<usize>) -> LockLevel {
+    let word = |i: usize| parser.keyword(range.start + i);
+    match (word(0).as_str(), word(1).as_str()) {
+        ("VALIDATE", _) => LockLevel::ShareUpdateExclusive,
+        ("ADD", _) if range.clone().any(|i| parser.keyword(i) == "REFERENCES") => LockLevel::ShareRowExclusive,
+        ("ENABLE" | "DISABLE", "TRIGGER") => LockLevel::ShareRowExclusive,
+        ("CLUSTER", "ON") => LockLevel::ShareUpdateExclusive,
+        ("SET", "WITHOUT") if word(2) == "CLUSTER" => LockLevel::ShareUpdateExclusive,
+        ("SET", "STATISTICS") => LockLevel::ShareUpdateExclusive,
+        ("SET", _) if parser.tokens.get(range.start + 1) == Some(&Token::LParen) => LockLevel::ShareUpdateExclusive,
+        ("ALTER", _) if (2..6).any(|i| word(i) == "SET" && word(i + 1) == "STATISTICS") => LockLevel::ShareUpdateExclusive,
+        ("ATTACH", "PARTITION") => LockLevel::ShareUpdateExclusive,
+        ("DETACH", "PARTITION") if (3..6).any(|i| word(i) == "CONCURRENTLY") => LockLevel::ShareUpdateExclusive,
+        _ => LockLevel::AccessExclusive,
+    }
+}
+
+/// The tokens of a statement, without whitespace and comments.
+struct Words<'a> {
+    tokens: &'a [Token],
+}
+
+impl Words<'_> {
+    /// The unquoted word at `i`, upper-cased, or an empty string.
+    fn keyword(&self, i: usize) -> String {
+        match self.tokens.get(i) {
+            Some(Token::Word(w)) if w.quote_style.is_none() => w.value.to_uppercase(),
+            _ => String::new(),
+        }
+    }
+
+    fn position(&self, keyword: &str) -> Option<usize> {
+        (0..self.tokens.len()).find(|&i| self.keyword(i) == keyword)
+    }
+
+    /// The possibly qualified name at `i`, after any of the `skip` keywords,
+    /// as SQL, and the position after it.
+    fn name_after(&self, mut i: usize, skip: &[&str]) -> (Option<String>, usize) {
+        while skip.contains(&self.keyword(i).as_str()) {
+            i += 1;
+        }
+        let mut parts = Vec::new();
+        while let Some(Token::Word(w)) = self.tokens.get(i) {
+            parts.push(match w.quote_style {
+                Some(_) => format!("\"{}\"", w.value.replace('"', "\"\"")),
+                None => w.value.to_lowercase(),
+            });
+            i += 1;
+            if self.tokens.get(i) != Some(&Token::Period) {
+                break;
+            }
+            i += 1;
+        }
+        ((!parts.is_empty()).then(|| parts.join(".")), i)
+    }
+
+    /// Comma-separated names from `i`, after any of the `skip` keywords.
+    fn name_list(&self, mut i: usize, skip: &[&str]) -> Vec<String> {
+        let mut names = Vec::new();
+        loop {
+            let (name, next) = self.name_after(i, skip);
+            let Some(name) = name else { break };
+            if matches!(name.to_uppercase().as_str(), "CASCADE" | "RESTRICT") {
+                break;
+            }
+            names.push(name);
+            if self.tokens.get(next) != Some(&Token::Comma) {
+                break;
+            }
+            i = next + 1;
+        }
+        names
+    }
+
+    /// Ranges of the comma-separated actions from `i`, at parenthesis depth 0.
+    fn actions(&self, i: usize) -> Vec<std::ops::Range<usize>> {
+        let mut actions = Vec::new();
+        let mut start = i;
+        let mut depth = 0;
+        for (j, token) in self.tokens.iter().enumerate().skip(i) {
+            match token {
+                Token::LParen => depth += 1,
+                Token::RParen => depth -= 1,
+                Token::Comma if depth == 0 => {
+                    actions.push(start..j);
+                    start = j + 1;
+                }
+                _ => {}
+            }
+        }
+        if start < self.tokens.len() {
+            actions.push(start..self.tokens.len());
+        }
+        actions
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn locks(statement: &str) -> Vec<(String, LockLevel)> {
+        statement_locks(statement).unwrap()
+    }
+
+    #[test]
+    fn test_statement_locks() {
+        use LockLevel::*;
+        assert_eq!(locks("ALTER TABLE \"orders\" ADD COLUMN \"note\" text"), vec![("\"orders\"".to_string(), AccessExclusive)]);
+        assert_eq!(locks("-- Validate\nALTER TABLE ONLY public.orders VALIDATE CONSTRAINT c"), vec![("public.orders".to_string(), ShareUpdateExclusive)]);
+        assert_eq!(
+            locks("ALTER TABLE orders ADD CONSTRAINT fk FOREIGN KEY (user_id) REFERENCES users (id) NOT VALID"),
+            vec![("orders".to_string(), ShareRowExclusive), ("users".to_string(), ShareRowExclusive)]
+        );
+        assert_eq!(
+            locks("ALTER TABLE orders ALTER COLUMN note SET STATISTICS 500, ALTER COLUMN total TYPE numeric"),
+            vec![("orders".to_string(), AccessExclusive)]
+        );
+        assert_eq!(locks("CREATE INDEX CONCURRENTLY IF NOT EXISTS i ON ONLY orders (c)"), vec![("orders".to_string(), ShareUpdateExclusive)]);
+        assert_eq!(locks("CREATE UNIQUE INDEX i ON orders USING btree (c)"), vec![("orders".to_string(), Share)]);
+        assert_eq!(
+            locks("DROP TABLE IF EXISTS a, \"B\" CASCADE"),
+            vec![("a".to_string(), AccessExclusive), ("\"B\"".to_string(), AccessExclusive)]
+        );
+        assert_eq!(locks("UPDATE orders SET note = '' WHERE id < 10"), vec![("orders".to_string(), RowExclusive)]);
+        assert_eq!(locks("CREATE TRIGGER t BEFORE UPDATE ON orders FOR EACH ROW EXECUTE FUNCTION f()"), vec![("orders".to_string(), ShareRowExclusive)]);
+        assert_eq!(locks("COMMENT ON COLUMN orders.note IS 'x'"), vec![("orders".to_string(), ShareUpdateExclusive)]);
+        assert_eq!(locks("CREATE TABLE items (id int, order_id int REFERENCES orders (id))"), vec![("orders".to_string(), ShareRowExclusive)]);
+        assert!(locks("CREATE TYPE mood AS ENUM ('ok')").is_empty());
+        assert_eq!(statement_locks("DO $$ BEGIN PERFORM 1; END $$"), None);
+    }
+
+    #[test]
+    fn test_lock_conflicts() {
+        use LockLevel::*;
+        assert!(AccessExclusive.blocks_reads() && AccessExclusive.blocks_writes());
+        assert!(!Share.blocks_reads() && Share.blocks_writes());
+        assert!(!ShareUpdateExclusive.blocks_writes());
+        assert!(ShareUpdateExclusive.conflicts_with(ShareUpdateExclusive));
+        assert!(!Share.conflicts_with(Share) && RowExclusive.conflicts_with(Share));
+        assert!(!RowExclusive.conflicts_with(RowExclusive));
+        assert_eq!(LockLevel::from_mode("ShareRowExclusiveLock"), Some(ShareRowExclusive));
+    }
+}
@@ -1,3 +1,10 @@
 //! Migration execution module.
 
+pub mod backup;
+pub mod clone;
 pub mod exec;
+pub mod history;
+pub mod hooks;
+pub mod locks;
+pub mod multi;
+pub mod plan;
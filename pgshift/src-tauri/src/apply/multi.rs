@@ -0,0 +1,113 @@
+//! Applying one migration to several databases.
+//!
+//! Tenants or environments sharing one schema are migrated one after the
+//! other, or a few at a time. The first failure stops the rollout: targets
+//! not started yet are skipped, so a broken migration reaches as few
+//! databases as possible, while those already running finish.
+
+use std::future::Future;
+use std::sync::atomic::{AtomicBool, Ordering};
+use futures_util::stream::{self, StreamExt};
+use serde::{Deserialize, Serialize};
+use crate::cancel::CancelToken;
+use super::exec::MigrationRun;
+
+/// A named database to apply to.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConnectionRef {
+    pub name: String,
+    pub connection_string: String,
+}
+
+/// What happened on a target.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TargetStatus {
+    Applied,
+    Failed,
+    /// Not started, after another target failed or the rollout was cancelled.
+    Skipped,
+}
+
+/// Outcome of the migration on one target.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TargetResult {
+    pub name: String,
+    pub status: TargetStatus,
+    /// Logs and statement results of an applied migration.
+    pub run: Option<MigrationRun>,
+    pub error: Option<String>,
+}
+
+/// Apply to each of `targets` with `apply`, at most `parallelism` at once,
+/// in the order given. Results are in the order of `targets`.
+pub async fn apply_to_targets<'a, A, Fut>(
+    targets: &'a [ConnectionRef],
+    parallelism: usize,
+    cancel: &CancelToken,
+    apply: A,
+) -> Vec<TargetResult>
+where
+    A: Fn(&'a ConnectionRef) -> Fut,
+    Fut: Future<Output = Result<MigrationRun, String>>,
+{
+    let failed = AtomicBool::new(false);
+    let failed = &failed;
+    let apply = &apply;
+    let mut results: Vec<(usize, TargetResult)> = stream::iter(targets.iter().enumerate())
+        .map(|(i, target)| async move {
+            let mut result = TargetResult { name: target.name.clone(), status: TargetStatus::Skipped, run: None, error: None };
+            if failed.load(Ordering::SeqCst) || cancel.is_cancelled() {
+                return (i, result);
+            }
+            match apply(target).await {
+                Ok(run) => {
+                    result.status = TargetStatus::Applied;
+                    result.run = Some(run);
+                }
+                Err(e) => {
+                    failed.store(true, Ordering::SeqCst);
+                    result.status = TargetStatus::Failed;
+                    result.error = Some(e);
+                }
+            }
+            (i, result)
+        })
+        .buffer_unordered(parallelism.max(1))
+        .collect()
+        .await;
+    results.sort_by_key(|(i, _)| *i);
+    results.into_iter().map(|(_, result)| result).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn target(name: &str) -> ConnectionRef {
+        ConnectionRef { name: name.to_string(), connection_string: String::new() }
+    }
+
+    #[tokio::test]
+    async fn test_apply_to_targets_stops_at_first_failure() {
+        let targets: Vec<ConnectionRef> = ["a", "b", "c", "d"].into_iter().map(target).collect();
+        let apply = |target: &ConnectionRef| {
+            let name = target.name.clone();
+            async move {
+                if name == "b" { Err("boom".to_string()) } else { Ok(MigrationRun::default()) }
+            }
+        };
+
+        let results = apply_to_targets(&targets, 1, &CancelToken::new(), apply).await;
+        let statuses: Vec<TargetStatus> = results.iter().map(|r| r.status).collect();
+        assert_eq!(statuses, [TargetStatus::Applied, TargetStatus::Failed, TargetStatus::Skipped, TargetStatus::Skipped]);
+        assert_eq!(results[1].error.as_deref(), Some("boom"));
+
+        // Two at a time, "a" and "b" start together; "c" and "d" never do
+        let results = apply_to_targets(&targets, 2, &CancelToken::new(), apply).await;
+        let names: Vec<&str> = results.iter().map(|r| r.name.as_str()).collect();
+        assert_eq!(names, ["a", "b", "c", "d"]);
+        assert_eq!(results[0].status, TargetStatus::Applied);
+        assert_eq!(results[3].status, TargetStatus::Skipped);
+    }
+}
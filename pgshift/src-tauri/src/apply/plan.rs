@@ -0,0 +1,212 @@
+//! Dry-run planning of the data changes in a migration.
+//!
+//! Backfills and seed upserts in `up.sql` can touch far more rows than meant;
+//! an `UPDATE` that lost its `WHERE` rewrites the whole table. Each
+//! data-modifying statement is planned with `EXPLAIN` in a read-only
+//! transaction on the target, so nothing runs, and the planner's row estimate
+//! is reported for review before applying.
+
+use std::fs;
+use std::path::Path;
+use serde::{Deserialize, Serialize};
+use sqlparser::ast::{Delete, Statement};
+use sqlparser::dialect::PostgreSqlDialect;
+use sqlparser::keywords::Keyword;
+use sqlparser::parser::Parser;
+use sqlparser::tokenizer::{Token, Tokenizer};
+use sqlx::postgres::PgPool;
+use sqlx::Row;
+use crate::ddl::parse::split_statements;
+
+/// A data-modifying statement of a migration and its estimate.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PlannedStatement {
+    /// Position of the statement in `up.sql`, from 1.
+    pub position: usize,
+    /// `UPDATE`, `DELETE`, `INSERT`, `MERGE`, `WITH` or `DO`.
+    pub operation: String,
+    pub statement: String,
+    /// Rows the planner expects the statement to write.
+    pub estimated_rows: Option<f64>,
+    /// An `UPDATE` or `DELETE` without a `WHERE` clause.
+    pub unbounded: bool,
+    /// Why the statement could not be planned, e.g. because it uses a column
+    /// an earlier statement of the migration adds.
+    pub error: Option<String>,
+}
+
+/// The data-modifying statements of a migration.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct MigrationPlan {
+    pub statements: Vec<PlannedStatement>,
+    /// Every statement of `up.sql`, data-modifying or not.
+    pub total_statements: usize,
+}
+
+/// Plan the data-modifying statements of the migration at `migration_path`
+/// on the database of `pool`.
+pub async fn plan_migration_sql(pool: &PgPool, migration_path: &str) -> Result<MigrationPlan, String> {
+    let up_sql_path = Path::new(migration_path).join("up.sql");
+    let sql = fs::read_to_string(&up_sql_path)
+        .map_err(|e| format!("Failed to read {}: {}", up_sql_path.display(), e))?;
+
+    let statements = split_statements(&sql);
+    let mut plan = MigrationPlan { statements: Vec::new(), total_statements: statements.len() };
+    for (i, statement) in statements.into_iter().enumerate() {
+        let Some(operation) = data_operation(&statement) else { continue };
+        let (estimated_rows, error) = if operation == "DO" {
+            (None, Some("Procedural blocks cannot be explained; review the block by hand".to_string()))
+        } else {
+            match explain_rows(pool, &statement).await {
+                Ok(rows) => (Some(rows), None),
+                Err(e) => (None, Some(e)),
+            }
+        };
+        plan.statements.push(PlannedStatement {
+            position: i + 1,
+            unbounded: is_unbounded(&statement),
+            operation: operation.to_string(),
+            statement,
+            estimated_rows,
+            error,
+        });
+    }
+
+    Ok(plan)
+}
+
+/// Rows the planner expects `statement` to write, from a read-only
+/// transaction that is rolled back.
+async fn explain_rows(pool: &PgPool, statement: &str) -> Result<f64, String> {
+    let mut tx = pool.begin()
+        .await
+        .map_err(|e| format!("Failed to start transaction: {}", e))?;
+    sqlx::query("SET TRANSACTION READ ONLY")
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| format!("Failed to start read-only transaction: {}", e))?;
+    let row = sqlx::query(&format!("EXPLAIN (FORMAT JSON) {}", statement))
+        .fetch_one(&mut *tx)
+        .await
+        .map_err(|e| e.to_string())?;
+    let explained: serde_json::Value = row.try_get(0).map_err(|e| e.to_string())?;
+
+    Ok(modified_rows(&explained[0]["Plan"]))
+}
+
+/// Rows fed into the `ModifyTable` nodes of a JSON plan.
+///
+/// A `ModifyTable` node itself estimates no rows; its first child produces
+/// the rows it writes. Data-modifying CTEs add a node per CTE.
+fn modified_rows(node: &serde_json::Value) -> f64 {
+    let children = node["Plans"].as_array().map(Vec::as_slice).unwrap_or_default();
+    let own = if node["Node Type"] == "ModifyTable" {
+        children.first().and_then(|c| c["Plan Rows"].as_f64()).unwrap_or(0.0)
+    } else {
+        0.0
+    };
+    own + children.iter().map(modified_rows).sum::<f64>()
+}
+
+/// The operation of a data-modifying statement; `None` for anything else.
+fn data_operation(statement: &str) -> Option<&'static str> {
+    let code = strip_leading_comments(statement);
+    let keyword: String = code.chars().take_while(|c| c.is_ascii_alphabetic()).collect();
+    let keyword = keyword.to_uppercase();
+    match keyword.as_str() {
+        "UPDATE" => Some("UPDATE"),
+        "DELETE" => Some("DELETE"),
+        "INSERT" => Some("INSERT"),
+        "MERGE" => Some("MERGE"),
+        "WITH" if writes(code, false) => Some("WITH"),
+        "DO" if writes(code, true) => Some("DO"),
+        _ => None,
+    }
+}
+
+/// Whether `code` has an `INSERT`, `UPDATE`, `DELETE` or `MERGE` keyword;
+/// identifiers, strings and comments that mention one do not count. With
+/// `bodies`, the quoted body of a `DO` block is searched as well.
+fn writes(code: &str, bodies: bool) -> bool {
+    let Ok(tokens) = Tokenizer::new(&PostgreSqlDialect {}, code).tokenize() else {
+        return false;
+    };
+    let mut previous = Keyword::NoKeyword;
+    for token in tokens {
+        match token {
+            Token::Word(word) if word.quote_style.is_none() => {
+                match word.keyword {
+                    Keyword::INSERT | Keyword::DELETE | Keyword::MERGE => return true,
+                    // FOR [NO KEY] UPDATE locks rows without writing them
+                    Keyword::UPDATE if !matches!(previous, Keyword::FOR | Keyword::KEY) => return true,
+                    _ => {}
+                }
+                previous = word.keyword;
+            }
+            Token::DollarQuotedString(body) if bodies && writes(&body.value, false) => return true,
+            Token::SingleQuotedString(body) if bodies && writes(&body, false) => return true,
+            Token::Whitespace(_) => {}
+            _ => previous = Keyword::NoKeyword,
+        }
+    }
+    false
+}
+
+/// Whether `statement` is an `UPDATE` or `DELETE` of every row.
+fn is_unbounded(statement: &str) -> bool {
+    let Ok(parsed) = Parser::parse_sql(&PostgreSqlDialect {}, statement) else {
+        return false;
+    };
+    parsed.iter().any(|s| match s {
+        Statement::Update { selection, from, .. } => selection.is_none() && from.is_none(),
+        Statement::Delete(Delete { selection, using, .. }) => selection.is_none() && using.is_none(),
+        _ => false,
+    })
+}
+
+/// `statement` without the comments before its first keyword.
+fn strip_leading_comments(statement: &str) -> &str {
+    let mut rest = statement.trim_start();
+    loop {
+        if let Some(comment) = rest.strip_prefix("--") {
+            rest = comment.split_once('\n').map_or("", |(_, after)| after).trim_start();
+        } else if let Some(comment) = rest.strip_prefix("/*") {
+            rest = comment.split_once("*/").map_or("", |(_, after)| after).trim_start();
+        } else {
+            return rest;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_data_statements() {
+        assert_eq!(data_operation("-- Backfill\nUPDATE users SET active = true"), Some("UPDATE"));
+        assert_eq!(data_operation("/* seed */ insert into plans VALUES (1)"), Some("INSERT"));
+        assert_eq!(data_operation("WITH gone AS (DELETE FROM s RETURNING id) SELECT count(*) FROM gone"), Some("WITH"));
+        assert_eq!(data_operation("ALTER TABLE users ADD COLUMN active boolean"), None);
+        assert_eq!(data_operation("DO $$ BEGIN PERFORM 1; END $$"), None);
+        assert_eq!(data_operation("DO $body$ BEGIN UPDATE plans SET active = true; END $body$"), Some("DO"));
+        assert_eq!(data_operation("WITH x AS (SELECT updated_at FROM t) SELECT * FROM x"), None);
+        assert_eq!(data_operation("WITH x AS (SELECT id, \"deleted\" FROM t FOR UPDATE) SELECT * FROM x -- insert"), None);
+        assert_eq!(data_operation("DO $$ BEGIN PERFORM merged_into FROM t; END $$"), None);
+
+        assert!(is_unbounded("UPDATE users SET active = true"));
+        assert!(is_unbounded("DELETE FROM sessions"));
+        assert!(!is_unbounded("UPDATE users SET active = true WHERE active IS NULL"));
+        assert!(!is_unbounded("UPDATE users SET plan = p.id FROM plans p WHERE p.name = users.plan_name"));
+    }
+
+    #[test]
+    fn test_modified_rows() {
+        let plan = serde_json::json!({
+            "Node Type": "ModifyTable",
+            "Plan Rows": 0,
+            "Plans": [{ "Node Type": "Seq Scan", "Plan Rows": 200000 }],
+        });
+        assert_eq!(modified_rows(&plan), 200000.0);
+    }
+}
@@ -0,0 +1,163 @@
+//! Audit log of consequential actions.
+//!
+//! Introspections, comparisons, renders, applies and rollbacks, console
+//! queries, and saved or deleted schema versions are each recorded with when
+//! they ran, who ran them, the connection they used and how they ended, for
+//! compliance reviews. Entries go to the metadata database of the default
+//! base path, whatever base path the action used, so one log covers every
+//! window, the headless server and the CLI. The database refuses to update
+//! or delete them.
+
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use crate::commands::default_migrations_dir;
+use crate::db::connect::connection_label;
+use crate::store::MetadataStore;
+
+/// Longest query text kept in an entry.
+const MAX_DETAIL_CHARS: usize = 2000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AuditAction {
+    Introspect,
+    Diff,
+    Render,
+    Apply,
+    Rollback,
+    ExecuteQuery,
+    SaveVersion,
+    DeleteVersion,
+}
+
+impl AuditAction {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AuditAction::Introspect => "introspect",
+            AuditAction::Diff => "diff",
+            AuditAction::Render => "render",
+            AuditAction::Apply => "apply",
+            AuditAction::Rollback => "rollback",
+            AuditAction::ExecuteQuery => "execute_query",
+            AuditAction::SaveVersion => "save_version",
+            AuditAction::DeleteVersion => "delete_version",
+        }
+    }
+}
+
+/// One recorded action.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AuditEntry {
+    /// Position in the log, assigned when the entry is written.
+    #[serde(default)]
+    pub id: i64,
+    pub occurred_at: String,
+    pub action: AuditAction,
+    /// Label of the database acted on; None when the action used no
+    /// connection, e.g. rendering a report of two schema files.
+    pub connection: Option<String>,
+    /// What was acted on: the migration, version or query, or the source of
+    /// a comparison.
+    pub detail: String,
+    /// Operating system user running pgshift.
+    pub actor: String,
+    pub success: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl AuditEntry {
+    /// Entry of `action` on `connection` that ended with `result`.
+    pub fn new<T>(action: AuditAction, connection: Option<&str>, detail: &str, result: &Result<T, String>) -> Self {
+        Self {
+            id: 0,
+            occurred_at: Utc::now().to_rfc3339(),
+            action,
+            connection: connection.filter(|c| !c.is_empty()).map(connection_label),
+            detail: detail.chars().take(MAX_DETAIL_CHARS).collect(),
+            actor: std::env::var("USER")
+                .or_else(|_| std::env::var("USERNAME"))
+                .unwrap_or_default(),
+            success: result.is_ok(),
+            error: result.as_ref().err().cloned(),
+        }
+    }
+}
+
+/// Filters for reading the audit log; empty fields match everything.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct AuditQuery {
+    #[serde(default)]
+    pub action: Option<AuditAction>,
+    /// Substring of the connection label.
+    #[serde(default)]
+    pub connection: Option<String>,
+    #[serde(default)]
+    pub success: Option<bool>,
+    /// RFC 3339 time of the first entry returned.
+    #[serde(default)]
+    pub since: Option<String>,
+    /// RFC 3339 time the entries returned are before.
+    #[serde(default)]
+    pub until: Option<String>,
+    /// Newest entries returned at most.
+    #[serde(default)]
+    pub limit: Option<usize>,
+}
+
+/// Record `action` on `connection` that ended with `result`.
+///
+/// The action already ran, so a failure to record it is logged rather than
+/// hiding its outcome.
+pub fn record<T>(action: AuditAction, connection: Option<&str>, detail: &str, result: &Result<T, String>) {
+    let entry = AuditEntry::new(action, connection, detail, result);
+    let written = default_migrations_dir()
+        .and_then(|base_path| MetadataStore::open(&base_path)?.append_audit_entry(&entry));
+    if let Err(e) = written {
+        tracing::error!("Failed to record {} in the audit log: {}", action.as_str(), e);
+    }
+}
+
+/// Entries of the audit log under `base_path` matching `query`, newest first.
+pub fn load_audit_log(base_path: &str, query: &AuditQuery) -> Result<Vec<AuditEntry>, String> {
+    MetadataStore::open(base_path)?.list_audit_log(query)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::store::{temp_base_path, DATABASE_FILE};
+
+    #[test]
+    fn test_audit_log() {
+        let base = temp_base_path();
+        let mut store = MetadataStore::open(&base).unwrap();
+        let applied: Result<(), String> = Ok(());
+        let failed: Result<(), String> = Err("Migration failed: relation exists".to_string());
+        let mut first = AuditEntry::new(AuditAction::Apply, Some("postgres://app:s3cr3t@db/app"), "20240101__a", &applied);
+        first.occurred_at = "2024-01-01T00:00:00+00:00".to_string();
+        store.append_audit_entry(&first).unwrap();
+        let second = AuditEntry::new(AuditAction::Apply, Some("staging"), "20240102__b", &failed);
+        store.append_audit_entry(&second).unwrap();
+        store.append_audit_entry(&AuditEntry::new(AuditAction::Render, None, "add users", &applied)).unwrap();
+
+        let all = load_audit_log(&base, &AuditQuery::default()).unwrap();
+        assert_eq!(all.len(), 3);
+        assert_eq!(all[2].id, 1);
+        assert_eq!(all[2].connection.as_deref(), Some("postgres://app@db/app"));
+
+        let query = AuditQuery { action: Some(AuditAction::Apply), success: Some(false), ..Default::default() };
+        let failures = load_audit_log(&base, &query).unwrap();
+        assert_eq!(failures.len(), 1);
+        assert_eq!(failures[0].error.as_deref(), Some("Migration failed: relation exists"));
+
+        let query = AuditQuery { connection: Some("app@db".to_string()), ..Default::default() };
+        assert_eq!(load_audit_log(&base, &query).unwrap()[0].detail, "20240101__a");
+        let query = AuditQuery { since: Some("2024-06-01T00:00:00+00:00".to_string()), limit: Some(1), ..Default::default() };
+        assert_eq!(load_audit_log(&base, &query).unwrap().len(), 1);
+
+        let conn = rusqlite::Connection::open(std::path::Path::new(&base).join(DATABASE_FILE)).unwrap();
+        assert!(conn.execute("DELETE FROM audit_log", []).is_err());
+        assert!(conn.execute("UPDATE audit_log SET success = 1", []).is_err());
+    }
+}
@@ -0,0 +1,7 @@
+//! `pgshift-cli`: the diff engine without the desktop app, for CI and
+//! scripts; see [`pgshift_lib::cli`].
+
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    std::process::exit(pgshift_lib::cli::run(&args));
+}
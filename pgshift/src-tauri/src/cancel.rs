@@ -0,0 +1,150 @@
+//! Cancellation of long-running operations.
+//!
+//! Introspection, diffs and applies take an optional `operation_id` chosen
+//! by the caller. While they run, the id is registered in the
+//! [`OperationRegistry`], and `cancel_operation` with the same id cancels
+//! its [`CancelToken`]: introspection and diffs stop and return an error,
+//! and a running migration script has its current statement cancelled with
+//! `pg_cancel_backend` and runs no further statements, so its transaction is
+//! rolled back as on any other failure.
+
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use tokio::sync::Notify;
+
+/// Error returned by an operation that was cancelled.
+pub const CANCELLED: &str = "Operation cancelled";
+
+/// Cancellation flag shared by an operation and whoever cancels it.
+#[derive(Debug, Clone, Default)]
+pub struct CancelToken {
+    state: Arc<CancelState>,
+}
+
+#[derive(Debug, Default)]
+struct CancelState {
+    cancelled: AtomicBool,
+    notify: Notify,
+}
+
+impl CancelToken {
+    /// A token that is not cancelled until [`CancelToken::cancel`] is called.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn cancel(&self) {
+        self.state.cancelled.store(true, Ordering::SeqCst);
+        self.state.notify.notify_waiters();
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.state.cancelled.load(Ordering::SeqCst)
+    }
+
+    /// Wait until the token is cancelled.
+    pub async fn cancelled(&self) {
+        loop {
+            // Created before the check, so a cancel in between still wakes it
+            let notified = self.state.notify.notified();
+            if self.is_cancelled() {
+                return;
+            }
+            notified.await;
+        }
+    }
+
+    /// Run `operation`, dropping it with [`CANCELLED`] if the token is
+    /// cancelled first.
+    pub async fn run<T>(&self, operation: impl Future<Output = Result<T, String>>) -> Result<T, String> {
+        tokio::select! {
+            result = operation => result,
+            _ = self.cancelled() => Err(CANCELLED.to_string()),
+        }
+    }
+}
+
+/// Running operations by id, managed as Tauri state.
+#[derive(Default)]
+pub struct OperationRegistry {
+    operations: Mutex<HashMap<String, CancelToken>>,
+}
+
+impl OperationRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register an operation under `operation_id` until the returned guard
+    /// is dropped. Without an id the operation cannot be cancelled.
+    pub fn register(&self, operation_id: Option<String>) -> OperationGuard<'_> {
+        let token = CancelToken::new();
+        if let Some(id) = &operation_id {
+            self.operations.lock().unwrap().insert(id.clone(), token.clone());
+        }
+        OperationGuard { registry: self, operation_id, token }
+    }
+
+    /// Cancel the operation registered under `operation_id`. Returns false
+    /// if none is running.
+    pub fn cancel(&self, operation_id: &str) -> bool {
+        match self.operations.lock().unwrap().get(operation_id) {
+            Some(token) => {
+                token.cancel();
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+/// Registration of a running operation; dropping it unregisters the id.
+pub struct OperationGuard<'a> {
+    registry: &'a OperationRegistry,
+    operation_id: Option<String>,
+    token: CancelToken,
+}
+
+impl OperationGuard<'_> {
+    pub fn token(&self) -> &CancelToken {
+        &self.token
+    }
+}
+
+impl Drop for OperationGuard<'_> {
+    fn drop(&mut self) {
+        if let Some(id) = &self.operation_id {
+            let mut operations = self.registry.operations.lock().unwrap();
+            // A later operation may have reused the id
+            if operations.get(id).is_some_and(|t| Arc::ptr_eq(&t.state, &self.token.state)) {
+                operations.remove(id);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_cancel_registered_operation() {
+        let registry = OperationRegistry::new();
+        assert!(!registry.cancel("compare-1"));
+
+        let guard = registry.register(Some("compare-1".to_string()));
+        let token = guard.token().clone();
+        let operation = token.run(async {
+            tokio::time::sleep(std::time::Duration::from_secs(60)).await;
+            Ok(())
+        });
+        assert!(registry.cancel("compare-1"));
+        assert_eq!(operation.await, Err(CANCELLED.to_string()));
+
+        drop(guard);
+        assert!(!registry.cancel("compare-1"));
+        assert_eq!(registry.register(None).token().run(async { Ok(1) }).await, Ok(1));
+    }
+}
@@ -0,0 +1,275 @@
+//! Headless command-line interface.
+//!
+//! `pgshift-cli` runs the same introspection, diff engine, renderer and
+//! apply as the desktop app, for CI jobs and scripts. The result of a
+//! command is printed to stdout as JSON, an error to stderr as
+//! `{"error": "..."}`, and the outcome is told by the exit code:
+//! [`EXIT_OK`], [`EXIT_ERROR`] or [`EXIT_CHANGES`].
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::io::Read;
+use serde::de::DeserializeOwned;
+use serde_json::{json, Value};
+use crate::apply::exec::{ApplyOptions, StatementStatus};
+use crate::cancel::CancelToken;
+use crate::commands::{self, default_migrations_dir};
+use crate::db::retry::{load_retry_policy, set_retry_policy};
+use crate::db::tunnel;
+use crate::diff::{DiffOptions, DiffReport};
+use crate::logging;
+use crate::render::sql::RenderOptions;
+use crate::source::SchemaSource;
+
+/// The command succeeded; `diff` found no differences.
+pub const EXIT_OK: i32 = 0;
+/// The command failed, or its arguments are wrong.
+pub const EXIT_ERROR: i32 = 1;
+/// `diff` found differences, or `apply` recorded failed statements (with
+/// `continue_on_error`).
+pub const EXIT_CHANGES: i32 = 2;
+
+const SEE_HELP: &str = "see pgshift-cli --help";
+
+const USAGE: &str = "\
+Usage: pgshift-cli <command> [arguments]
+
+Commands:
+  introspect <source>
+  diff <source> <target> [--options <file>] [--allow-same-database]
+  render <report> --name <name> [--options <file>]
+  apply <migration> <connection> [--options <file>]
+  snapshot <connection> --name <name> [--description <text>] [--tag <tag>]...
+
+A source is a connection string or environment name, or one of
+version:<id>, migrations:<dir>, sql:<path>, schema_file:<path>,
+prisma:<path> and diesel:<path>. <report> and option files are JSON; - reads
+stdin. --dir <dir> sets the migrations directory (default: ./migrations).
+
+Exit codes: 0 success, 1 error, 2 differences found (diff) or failed
+statements (apply).";
+
+/// Run the command in `args` (without the program name) and return the
+/// exit code.
+pub fn run(args: &[String]) -> i32 {
+    if matches!(args.first().map(String::as_str), Some("-h" | "--help" | "help")) {
+        println!("{}", USAGE);
+        return EXIT_OK;
+    }
+    // Logs go to the app's log files; stderr is kept for the JSON error
+    let _ = logging::init();
+    let runtime = match tokio::runtime::Runtime::new() {
+        Ok(runtime) => runtime,
+        Err(e) => return fail(&format!("Failed to start async runtime: {}", e)),
+    };
+    let outcome = runtime.block_on(run_command(args));
+    tunnel::close_tunnels();
+    match outcome {
+        Ok((value, code)) => match serde_json::to_string_pretty(&value) {
+            Ok(output) => {
+                println!("{}", output);
+                code
+            }
+            Err(e) => fail(&format!("Failed to serialize result: {}", e)),
+        },
+        Err(e) => fail(&e),
+    }
+}
+
+fn fail(message: &str) -> i32 {
+    eprintln!("{}", json!({ "error": message }));
+    EXIT_ERROR
+}
+
+async fn run_command(args: &[String]) -> Result<(Value, i32), String> {
+    let Some((command, rest)) = args.split_first() else {
+        return Err(format!("Missing command; {}", SEE_HELP));
+    };
+    let (values, switches): (&[&str], &[&str]) = match command.as_str() {
+        "introspect" => (&["--dir"], &[]),
+        "diff" => (&["--dir", "--options"], &["--allow-same-database"]),
+        "render" => (&["--dir", "--name", "--options"], &[]),
+        "apply" => (&["--dir", "--options"], &[]),
+        "snapshot" => (&["--dir", "--name", "--description", "--tag"], &[]),
+        other => return Err(format!("Unknown command '{}'; {}", other, SEE_HELP)),
+    };
+    let args = Args::parse(rest, values, switches)?;
+    let base_path = match args.value("--dir") {
+        Some(dir) => dir.to_string(),
+        None => default_migrations_dir()?,
+    };
+    if let Ok(policy) = load_retry_policy(&base_path) {
+        set_retry_policy(policy);
+    }
+
+    match command.as_str() {
+        "introspect" => {
+            let [source] = args.positional::<1>()?;
+            let schema = match parse_source(source, &base_path) {
+                SchemaSource::Live { connection_string } => {
+                    commands::run_introspect(&connection_string, &Default::default(), |_| {}).await?
+                }
+                source => source.load().await?.schema,
+            };
+            Ok((to_value(&schema)?, EXIT_OK))
+        }
+        "diff" => {
+            let [source, target] = args.positional::<2>()?;
+            let options: Option<DiffOptions> = args.value("--options").map(read_json).transpose()?;
+            let report = commands::run_compare(
+                parse_source(source, &base_path),
+                parse_source(target, &base_path),
+                options,
+                Some(args.switch("--allow-same-database")),
+            ).await?;
+            let code = if report.items.is_empty() { EXIT_OK } else { EXIT_CHANGES };
+            Ok((to_value(&report)?, code))
+        }
+        "render" => {
+            let [report] = args.positional::<1>()?;
+            let report: DiffReport = read_json(report)?;
+            let name = args.value("--name").ok_or("render needs --name")?;
+            let options: RenderOptions = args.value("--options").map(read_json).transpose()?.unwrap_or_default();
+            let path = commands::run_render(&report, name, &base_path, &options)?;
+            Ok((json!({ "path": path }), EXIT_OK))
+        }
+        "apply" => {
+            let [migration, connection] = args.positional::<2>()?;
+            let options: ApplyOptions = args.value("--options").map(read_json).transpose()?.unwrap_or_default();
+            let run = commands::run_apply_migration(connection, migration, &options, &CancelToken::new(), |_| {}, |_| {})
+                .await?;
+            let failed = run.statements.iter().any(|s| s.status == StatementStatus::Failed);
+            Ok((to_value(&run)?, if failed { EXIT_CHANGES } else { EXIT_OK }))
+        }
+        "snapshot" => {
+            let [connection] = args.positional::<1>()?;
+            let name = args.value("--name").ok_or("snapshot needs --name")?;
+            let version = commands::save_schema_version(
+                connection.to_string(),
+                name.to_string(),
+                args.value("--description").unwrap_or_default().to_string(),
+                args.values("--tag"),
+                base_path,
+            ).await?;
+            Ok((to_value(&version)?, EXIT_OK))
+        }
+        _ => unreachable!("commands are checked above"),
+    }
+}
+
+/// The side of a comparison `arg` names; see [`SchemaSource::label`], whose
+/// prefixes it takes. Anything else is a connection string or environment.
+pub fn parse_source(arg: &str, base_path: &str) -> SchemaSource {
+    let Some((kind, rest)) = arg.split_once(':') else {
+        return SchemaSource::Live { connection_string: arg.to_string() };
+    };
+    let path = rest.to_string();
+    match kind {
+        "version" => SchemaSource::Version { base_path: base_path.to_string(), version_id: path },
+        "migrations" => SchemaSource::Migrations { path },
+        "sql" => SchemaSource::SqlFile { path },
+        "schema_file" => SchemaSource::SchemaFile { path },
+        "prisma" => SchemaSource::Prisma { path },
+        "diesel" => SchemaSource::Diesel { path },
+        _ => SchemaSource::Live { connection_string: arg.to_string() },
+    }
+}
+
+/// Read JSON from the file at `path`, or stdin for `-`.
+fn read_json<T: DeserializeOwned>(path: &str) -> Result<T, String> {
+    let text = if path == "-" {
+        let mut text = String::new();
+        std::io::stdin()
+            .read_to_string(&mut text)
+            .map_err(|e| format!("Failed to read stdin: {}", e))?;
+        text
+    } else {
+        std::fs::read_to_string(path).map_err(|e| format!("Failed to read {}: {}", path, e))?
+    };
+    serde_json::from_str(&text).map_err(|e| format!("Invalid JSON in {}: {}", path, e))
+}
+
+fn to_value<T: serde::Serialize>(value: &T) -> Result<Value, String> {
+    serde_json::to_value(value).map_err(|e| format!("Failed to serialize result: {}", e))
+}
+
+/// Arguments of a command: positional ones, flags taking a value (which
+/// may repeat) and switches.
+#[derive(Debug, Default)]
+struct Args {
+    positional: Vec<String>,
+    values: BTreeMap<String, Vec<String>>,
+    switches: BTreeSet<String>,
+}
+
+impl Args {
+    fn parse(args: &[String], values: &[&str], switches: &[&str]) -> Result<Self, String> {
+        let mut parsed = Args::default();
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            if values.contains(&arg.as_str()) {
+                let value = args.next().ok_or_else(|| format!("{} needs a value", arg))?;
+                parsed.values.entry(arg.clone()).or_default().push(value.clone());
+            } else if switches.contains(&arg.as_str()) {
+                parsed.switches.insert(arg.clone());
+            } else if arg.starts_with("--") {
+                return Err(format!("Unknown option '{}'; {}", arg, SEE_HELP));
+            } else {
+                parsed.positional.push(arg.clone());
+            }
+        }
+        Ok(parsed)
+    }
+
+    /// Exactly `N` positional arguments.
+    fn positional<const N: usize>(&self) -> Result<[&str; N], String> {
+        let args: Vec<&str> = self.positional.iter().map(String::as_str).collect();
+        args.try_into().map_err(|args: Vec<&str>| {
+            format!("Expected {} argument(s), got {}; {}", N, args.len(), SEE_HELP)
+        })
+    }
+
+    /// The last value of `flag`.
+    fn value(&self, flag: &str) -> Option<&str> {
+        self.values.get(flag).and_then(|v| v.last()).map(String::as_str)
+    }
+
+    fn values(&self, flag: &str) -> Vec<String> {
+        self.values.get(flag).cloned().unwrap_or_default()
+    }
+
+    fn switch(&self, flag: &str) -> bool {
+        self.switches.contains(flag)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_arguments() {
+        let args: Vec<String> = ["db", "--tag", "a", "--name", "nightly", "--tag", "b", "--allow-same-database"]
+            .iter()
+            .map(|a| a.to_string())
+            .collect();
+        let parsed = Args::parse(&args, &["--name", "--tag"], &["--allow-same-database"]).unwrap();
+        assert_eq!(parsed.positional::<1>().unwrap(), ["db"]);
+        assert!(parsed.positional::<2>().is_err());
+        assert_eq!(parsed.value("--name"), Some("nightly"));
+        assert_eq!(parsed.values("--tag"), ["a", "b"]);
+        assert!(parsed.switch("--allow-same-database"));
+        assert!(Args::parse(&args, &["--name"], &[]).unwrap_err().starts_with("Unknown option '--tag'"));
+        assert!(Args::parse(&args[..2], &["--tag"], &[]).unwrap_err().contains("needs a value"));
+
+        assert!(matches!(
+            parse_source("postgres://app@localhost/app", "migrations"),
+            SchemaSource::Live { connection_string } if connection_string == "postgres://app@localhost/app"
+        ));
+        assert!(matches!(parse_source("staging", "migrations"), SchemaSource::Live { .. }));
+        assert!(matches!(
+            parse_source("version:v1", "migrations"),
+            SchemaSource::Version { base_path, version_id } if base_path == "migrations" && version_id == "v1"
+        ));
+        assert!(matches!(parse_source("sql:schema/", "migrations"), SchemaSource::SqlFile { path } if path == "schema/"));
+    }
+}
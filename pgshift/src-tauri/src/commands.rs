@@ -1,16 +1,70 @@
 //! Tauri commands for the PGShift application.
 
-use crate::db::{connect, introspect as db_introspect};
+use crate::audit::{self, AuditAction, AuditEntry, AuditQuery};
+use crate::log_line;
+use tracing::Level;
+use crate::logging::{self, LogRecord};
+use crate::db::{connect, introspect as db_introspect, row_counts};
+use crate::db::capabilities::{fetch_capabilities, ServerCapabilities};
+use crate::db::connect::TlsOptions;
+use crate::db::databases::{self, DatabaseEntry};
+use crate::db::pool::{self as db_pool, release_pool, PoolManager, PoolSettings};
+use crate::db::retry::{self, ConnectError, RetryPolicy};
+use crate::db::introspect::{IntrospectOptions, IntrospectProgress};
+use crate::db::identity::{fetch_identity, same_database_warning, DatabaseIdentity};
+use crate::db::locale::{fetch_locale, locale_warnings, DatabaseLocale};
+use crate::db::progress::{fetch_operation_progress, OperationProgress};
+use crate::db::saved_connections::{self, SavedConnection};
+use crate::db::schemas::{self, SchemaFilter};
+use crate::db::session::{fetch_session_settings, SessionSettings};
 use crate::model::schema::SchemaModel;
+use crate::model::{diesel, prisma, schema_file};
 use crate::diff::diff as diff_engine;
-use crate::diff::DiffReport;
-use crate::render::sql::render_migration_files;
-use crate::apply::exec::apply_migration_sql;
+use crate::diff::delta::{compare_reports, ReportDelta};
+use crate::diff::disk::estimate_disk_space;
+use crate::diff::{DangerAcknowledgment, DiffItem, DiffReport, DiffOptions, EnumReorderStrategy, IgnoreConfig, RenameMapping};
+use crate::diff::roles::{self as role_diff, RoleDiffReport};
+use crate::diff::store::{ReportStore, DiffReportSummary, DiffItemFilter, DiffItemsPage};
+use crate::render::sql::{render_migration_files, RenderOptions};
+use crate::render::dictionary::write_data_dictionary;
+use crate::render::liquibase::{self, write_liquibase_changelog, ChangelogFormat};
+use crate::render::report::{write_report, ReportFormat};
+use crate::render::squash::{self, SquashResult};
+use crate::render::erd::{self, render_dbml, render_mermaid, SchemaGraph};
+use crate::render::roles::render_role_migration_files;
+use crate::render::dual_write::{expand_contract, plan_dual_write, render_dual_write_migrations, DualWriteOptions};
+use crate::render::version::compare_folder_names;
+use crate::render::schema_sql::{write_schema_sql, SchemaSqlDump};
+use crate::apply::backup::backup_database;
+use crate::apply::clone::{self as schema_clone, CloneOptions, CloneProgress, CloneResult};
+use crate::apply::exec::{
+    apply_migration_sql, fetch_applied_migrations, last_applied_migration, merge_migration_status, migration_status, rollback_migration_sql, ApplyOptions, ApplyProgress,
+    MigrationRun, MigrationStatusReport,
+};
+use crate::apply::history::{self as apply_history, ApplyRecord};
+use crate::apply::hooks::{hook_script, HookPoint};
+use crate::apply::multi::{apply_to_targets, ConnectionRef, TargetResult};
+use crate::apply::locks::{analyze_lock_impact as analyze_lock_impact_sql, LockImpact};
+use crate::apply::plan::{plan_migration_sql, MigrationPlan};
+use crate::schedule::jobs::{self as schedule_jobs, ScheduledComparison};
+use crate::schedule::webhooks::{self, notify, Notification, Webhook};
+use crate::source::SchemaSource;
+use crate::ddl::parse::ParsedSchema;
+use crate::ddl::{files, replay};
+use crate::cancel::{CancelToken, OperationRegistry, CANCELLED};
+use crate::safe_mode;
+use crate::environments::{self, Environment, ProjectConfig};
+use crate::storage::{self, open_shared_store, HistoryStoreExt, StorageConfig};
+use crate::store::{MetadataStore, VersionQuery};
+use crate::store::backup::{self as store_backup, AppDataSummary};
+use crate::store::compact::{self as store_compact, CompactResult, RetentionSettings, StorageReport};
+use std::collections::BTreeMap;
 use std::fs;
 use std::path::Path;
 use serde::{Deserialize, Serialize};
 use sqlx::{Row, Column};
 use chrono::Utc;
+use tauri::Emitter;
 
 /// Table row data for browsing
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -34,11 +88,20 @@ pub struct SchemaVersion {
     pub id: String,
     pub name: String,
     pub description: String,
-    pub connection_string: String,
+    /// Database the snapshot was taken from, as an environment name or a
+    /// connection string without its password.
+    #[serde(alias = "connection_string", deserialize_with = "connect::deserialize_connection_label")]
+    pub connection: String,
     pub database_name: String,
     pub schema: SchemaModel,
     pub created_at: String,
     pub tags: Vec<String>,
+    /// Database the snapshot was taken from, used to flag self-comparisons.
+    #[serde(default)]
+    pub identity: Option<DatabaseIdentity>,
+    /// Encoding and collation of that database, compared against other sides.
+    #[serde(default)]
+    pub locale: Option<DatabaseLocale>,
 }
 
 /// Schema version diff between two versions
@@ -50,30 +113,395 @@ pub struct VersionDiff {
 }
 
 /// Test a PostgreSQL connection string.
+///
+/// Fails with a [`ConnectError`] telling what kind of failure it was and
+/// what to check.
 #[tauri::command]
-pub async fn test_connection(connection_string: String) -> Result<bool, String> {
-    match connect::test_connection(&connection_string).await {
-        Ok(_) => Ok(true),
-        Err(e) => Err(format!("Connection failed: {}", e)),
-    }
+pub async fn test_connection(connection_string: String, tls: Option<TlsOptions>) -> Result<bool, ConnectError> {
+    let connection_string = connect::with_tls(&connection_string, tls.as_ref())
+        .map_err(ConnectError::configuration)?;
+    connect::test_connection(&connection_string).await?;
+    Ok(true)
 }
 
+/// Event emitted with an [`IntrospectProgress`] after each chunk of tables
+/// is introspected.
+pub const INTROSPECT_PROGRESS_EVENT: &str = "introspect-progress";
+
 /// Introspect a PostgreSQL database and return its schema model.
+///
+/// Tables are read in chunks of `options.tables_per_chunk`; the progress
+/// after each chunk is emitted as an [`INTROSPECT_PROGRESS_EVENT`] event.
+/// Passing an `operation_id` lets [`cancel_operation`] stop it.
+#[tauri::command]
+pub async fn introspect(
+    app: tauri::AppHandle,
+    connection_string: String,
+    options: Option<IntrospectOptions>,
+    tls: Option<TlsOptions>,
+    operation_id: Option<String>,
+    operations: tauri::State<'_, OperationRegistry>,
+) -> Result<SchemaModel, String> {
+    let connection_string = connect::with_tls(&connection_string, tls.as_ref())?;
+    let operation = operations.register(operation_id);
+    operation.token().run(run_introspect(&connection_string, &options.unwrap_or_default(), |progress| {
+        if let Err(e) = app.emit(INTROSPECT_PROGRESS_EVENT, progress) {
+            tracing::warn!("Failed to emit progress: {}", e);
+        }
+    })).await
+}
+
+/// Cancel the introspection, diff, compare, apply, rollback or clone started
+/// with `operation_id`. Returns false if none is running.
+///
+/// A running migration script has its current statement cancelled and its
+/// transaction rolled back; the command that ran it fails with the reason.
+#[tauri::command]
+pub async fn cancel_operation(
+    operation_id: String,
+    operations: tauri::State<'_, OperationRegistry>,
+) -> Result<bool, String> {
+    Ok(operations.cancel(&operation_id))
+}
+
+/// Introspect a database, passing the progress after each chunk of tables
+/// to `on_progress`.
+pub(crate) async fn run_introspect<F>(
+    connection_string: &str,
+    options: &IntrospectOptions,
+    on_progress: F,
+) -> Result<SchemaModel, String>
+where
+    F: Fn(&IntrospectProgress),
+{
+    let result = async {
+        let pool = schemas::connect_to_schema(
+            &default_migrations_dir()?,
+            connection_string,
+            "introspect",
+            options.schema_filter.as_ref(),
+        ).await?;
+        
+        db_introspect::introspect_schema_with_options(&pool, options, on_progress)
+            .await
+            .map_err(|e| format!("Introspection failed: {}", e))
+    }.await;
+    audit::record(AuditAction::Introspect, Some(connection_string), "", &result);
+    result
+}
+
+/// Check whether two connection strings point at the same database.
 #[tauri::command]
-pub async fn introspect(connection_string: String) -> Result<SchemaModel, String> {
-    let pool = connect::create_pool(&connection_string)
+pub async fn check_same_database(
+    source_connection: String,
+    target_connection: String,
+) -> Result<bool, String> {
+    let source_pool = connect::create_pool(&source_connection, "check_same_database")
         .await
-        .map_err(|e| format!("Failed to connect: {}", e))?;
+        .map_err(|e| format!("Failed to connect to source: {}", e))?;
+    let source_identity = fetch_identity(&source_pool)
+        .await
+        .map_err(|e| format!("Failed to identify source: {}", e))?;
+    release_pool(source_pool).await;
     
-    db_introspect::introspect_schema(&pool)
+    let target_pool = connect::create_pool(&target_connection, "check_same_database")
         .await
-        .map_err(|e| format!("Introspection failed: {}", e))
+        .map_err(|e| format!("Failed to connect to target: {}", e))?;
+    let target_identity = fetch_identity(&target_pool)
+        .await
+        .map_err(|e| format!("Failed to identify target: {}", e))?;
+    release_pool(target_pool).await;
+    
+    Ok(source_identity.is_same_database(&target_identity))
+}
+
+/// Compare the encodings, locales and collation versions of two databases.
+///
+/// Returns a warning per text-affecting difference; run it before generating
+/// or applying a migration between them.
+#[tauri::command]
+pub async fn preflight_locales(
+    source_connection: String,
+    target_connection: String,
+) -> Result<Vec<String>, String> {
+    let source_pool = connect::create_pool(&source_connection, "preflight_locales")
+        .await
+        .map_err(|e| format!("Failed to connect to source: {}", e))?;
+    let source_locale = fetch_locale(&source_pool)
+        .await
+        .map_err(|e| format!("Failed to read source locale: {}", e))?;
+    release_pool(source_pool).await;
+    
+    let target_pool = connect::create_pool(&target_connection, "preflight_locales")
+        .await
+        .map_err(|e| format!("Failed to connect to target: {}", e))?;
+    let target_locale = fetch_locale(&target_pool)
+        .await
+        .map_err(|e| format!("Failed to read target locale: {}", e))?;
+    release_pool(target_pool).await;
+    
+    Ok(locale_warnings(&source_locale, &target_locale))
 }
 
 /// Compare two schema models and return a diff report.
+///
+/// `renames` lists tables and columns renamed from the target to the source;
+/// they are migrated with `RENAME` instead of a drop and an add.
+/// `include_types` limits the report to those object types (e.g. `index` or
+/// `indexes`) and `exclude_types` leaves those out; an unknown type is an
+/// error. Both filter the report, so the schemas are still compared in full.
+/// Passing an `operation_id` lets [`cancel_operation`] stop waiting for the
+/// comparison.
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub async fn diff(
+    source: SchemaModel,
+    target: SchemaModel,
+    options: Option<DiffOptions>,
+    renames: Option<Vec<RenameMapping>>,
+    include_types: Option<Vec<String>>,
+    exclude_types: Option<Vec<String>>,
+    operation_id: Option<String>,
+    operations: tauri::State<'_, OperationRegistry>,
+) -> Result<DiffReport, String> {
+    let operation = operations.register(operation_id);
+    // Comparing never awaits, so it runs on its own thread to be cancellable
+    let comparison = tokio::task::spawn_blocking(move || {
+        run_diff(source, target, options, renames, include_types, exclude_types)
+    });
+    operation.token().run(async {
+        comparison.await.map_err(|e| format!("Diff failed: {}", e))?
+    }).await
+}
+
+/// Compare two schema models as [`diff`] does.
+pub(crate) fn run_diff(
+    source: SchemaModel,
+    target: SchemaModel,
+    options: Option<DiffOptions>,
+    renames: Option<Vec<RenameMapping>>,
+    include_types: Option<Vec<String>>,
+    exclude_types: Option<Vec<String>>,
+) -> Result<DiffReport, String> {
+    let mut options = options.unwrap_or_default();
+    options.renames.extend(renames.unwrap_or_default());
+    options.include_types.extend(include_types.unwrap_or_default());
+    options.include_types = options.include_types.iter()
+        .map(|t| diff_engine::object_type_name(t))
+        .collect::<Result<_, _>>()?;
+    for name in exclude_types.unwrap_or_default() {
+        options.ignore.object_types.push(diff_engine::object_type_name(&name)?);
+    }
+    Ok(diff_engine::compare_schemas_with_options(&source, &target, &options))
+}
+
+/// Compare two diff reports of the same databases, e.g. yesterday's and
+/// today's, into new, resolved, changed and unchanged items.
+#[tauri::command]
+pub async fn compare_diff_reports(previous: DiffReport, current: DiffReport) -> Result<ReportDelta, String> {
+    Ok(compare_reports(&previous, &current))
+}
+
+/// Compare any two schema sources (live database, stored version, migrations, SQL files).
+/// Source is the desired state, target is the current state.
+///
+/// Two live connections to the same database are refused unless
+/// `allow_same_database` is set; other same-database comparisons get a warning.
+/// Passing an `operation_id` lets [`cancel_operation`] stop it.
 #[tauri::command]
-pub async fn diff(source: SchemaModel, target: SchemaModel) -> Result<DiffReport, String> {
-    Ok(diff_engine::compare_schemas(&source, &target))
+pub async fn compare(
+    source: SchemaSource,
+    target: SchemaSource,
+    options: Option<DiffOptions>,
+    allow_same_database: Option<bool>,
+    operation_id: Option<String>,
+    operations: tauri::State<'_, OperationRegistry>,
+) -> Result<DiffReport, String> {
+    let operation = operations.register(operation_id);
+    operation.token().run(run_compare(source, target, options, allow_same_database)).await
+}
+
+/// Compare two schema sources as [`compare`] does.
+pub(crate) async fn run_compare(
+    source: SchemaSource,
+    target: SchemaSource,
+    options: Option<DiffOptions>,
+    allow_same_database: Option<bool>,
+) -> Result<DiffReport, String> {
+    let result = compare_sources(&source, &target, options, allow_same_database).await;
+    let connection = match &target {
+        SchemaSource::Live { connection_string } => Some(connection_string.as_str()),
+        _ => None,
+    };
+    audit::record(AuditAction::Diff, connection, &format!("{} -> {}", source.label(), target.label()), &result);
+    result
+}
+
+async fn compare_sources(
+    source: &SchemaSource,
+    target: &SchemaSource,
+    options: Option<DiffOptions>,
+    allow_same_database: Option<bool>,
+) -> Result<DiffReport, String> {
+    let source_schema = source.load()
+        .await
+        .map_err(|e| format!("Failed to load source: {}", e))?;
+    let target_schema = target.load()
+        .await
+        .map_err(|e| format!("Failed to load target: {}", e))?;
+    
+    let warning = same_database_warning(source_schema.identity.as_ref(), target_schema.identity.as_ref());
+    if warning.is_some() && source.is_live() && target.is_live() && !allow_same_database.unwrap_or(false) {
+        return Err("Source and target are the same database; refusing to compare".to_string());
+    }
+    
+    let mut options = options.unwrap_or_default();
+    if options.target_major_version.is_none() {
+        options.target_major_version = target_schema.capabilities.as_ref().map(|c| c.major_version);
+    }
+    let mut report = diff_engine::compare_schemas_with_options(
+        &source_schema.schema,
+        &target_schema.schema,
+        &options,
+    );
+    report.source_connection = source.label();
+    report.target_connection = target.label();
+    report.warnings.extend(warning);
+    if let (Some(s), Some(t)) = (&source_schema.locale, &target_schema.locale) {
+        report.warnings.extend(locale_warnings(s, t));
+    }
+    if let Some(sizes) = &target_schema.sizes {
+        estimate_disk_space(&mut report, &source_schema.schema, sizes);
+    }
+    Ok(report)
+}
+
+/// Compare two schema models and keep the report on the backend.
+///
+/// Returns only a summary; items are fetched with `get_diff_items`.
+#[tauri::command]
+pub async fn diff_stored(
+    source: SchemaModel,
+    target: SchemaModel,
+    options: Option<DiffOptions>,
+    store: tauri::State<'_, ReportStore>,
+) -> Result<DiffReportSummary, String> {
+    let report = diff_engine::compare_schemas_with_options(&source, &target, &options.unwrap_or_default());
+    Ok(store.insert(report))
+}
+
+/// Get a page of items from a stored diff report.
+#[tauri::command]
+pub async fn get_diff_items(
+    report_id: String,
+    offset: usize,
+    limit: usize,
+    filters: Option<DiffItemFilter>,
+    store: tauri::State<'_, ReportStore>,
+) -> Result<DiffItemsPage, String> {
+    store
+        .page(&report_id, offset, limit, &filters.unwrap_or_default())
+        .ok_or_else(|| format!("Report not found: {}", report_id))
+}
+
+/// Release a stored diff report.
+#[tauri::command]
+pub async fn release_diff_report(
+    report_id: String,
+    store: tauri::State<'_, ReportStore>,
+) -> Result<(), String> {
+    if store.remove(&report_id) {
+        Ok(())
+    } else {
+        Err(format!("Report not found: {}", report_id))
+    }
+}
+
+/// Acknowledge a dangerous item of a stored diff report.
+///
+/// The acknowledgment is written into the migration rendered from the report.
+#[tauri::command]
+pub async fn acknowledge_diff_item(
+    report_id: String,
+    item_id: String,
+    acknowledged_by: String,
+    reason: String,
+    store: tauri::State<'_, ReportStore>,
+) -> Result<DiffItem, String> {
+    let acknowledgment = DangerAcknowledgment::new(&acknowledged_by, &reason)?;
+    store.acknowledge(&report_id, &item_id, acknowledgment)
+}
+
+/// Choose how a reordered enum of a stored diff report is migrated.
+#[tauri::command]
+pub async fn resolve_enum_reorder(
+    report_id: String,
+    item_id: String,
+    strategy: EnumReorderStrategy,
+    store: tauri::State<'_, ReportStore>,
+) -> Result<DiffItem, String> {
+    store.resolve_enum_reorder(&report_id, &item_id, strategy)
+}
+
+/// Render migration files for a stored diff report.
+#[tauri::command]
+pub async fn render_stored_migration(
+    report_id: String,
+    name: String,
+    base_path: String,
+    options: Option<RenderOptions>,
+    store: tauri::State<'_, ReportStore>,
+) -> Result<String, String> {
+    let report = store
+        .get(&report_id)
+        .ok_or_else(|| format!("Report not found: {}", report_id))?;
+    
+    run_render(&report, &name, &base_path, &options.unwrap_or_default())
+}
+
+/// Compare the roles of two servers.
+///
+/// Roles are cluster-level: the result applies to the whole target server,
+/// not just the database in the connection string.
+#[tauri::command]
+pub async fn compare_roles(
+    source_connection: String,
+    target_connection: String,
+) -> Result<RoleDiffReport, String> {
+    let source_pool = connect::create_pool(&source_connection, "compare_roles")
+        .await
+        .map_err(|e| format!("Failed to connect to source: {}", e))?;
+    let source_roles = db_introspect::introspect_roles(&source_pool)
+        .await
+        .map_err(|e| format!("Role introspection failed: {}", e))?;
+    
+    let target_pool = connect::create_pool(&target_connection, "compare_roles")
+        .await
+        .map_err(|e| format!("Failed to connect to target: {}", e))?;
+    let target_roles = db_introspect::introspect_roles(&target_pool)
+        .await
+        .map_err(|e| format!("Role introspection failed: {}", e))?;
+    
+    let mut report = role_diff::compare_roles(&source_roles, &target_roles);
+    report.source_connection = connect::connection_label(&source_connection);
+    report.target_connection = connect::connection_label(&target_connection);
+    Ok(report)
+}
+
+/// Render the scripts of a role comparison to a migration folder.
+///
+/// The scripts change roles on the whole target server.
+#[tauri::command]
+pub async fn render_role_migration(
+    report: RoleDiffReport,
+    name: String,
+    base_path: String,
+) -> Result<String, String> {
+    let result = render_role_migration_files(&report, &name, &base_path)
+        .map_err(|e| format!("Failed to render role migration: {}", e));
+    let detail = format!("{} (roles, {} -> {})", name, report.source_connection, report.target_connection);
+    audit::record(AuditAction::Render, None, &detail, &result);
+    result
 }
 
 /// Render migration files to disk.
@@ -82,29 +510,735 @@ pub async fn render_migration(
     report: DiffReport,
     name: String,
     base_path: String,
+    options: Option<RenderOptions>,
 ) -> Result<String, String> {
-    render_migration_files(&report, &name, &base_path)
-        .map_err(|e| format!("Failed to render migration: {}", e))
+    run_render(&report, &name, &base_path, &options.unwrap_or_default())
+}
+
+/// Render migration files as [`render_migration`] does.
+pub(crate) fn run_render(report: &DiffReport, name: &str, base_path: &str, options: &RenderOptions) -> Result<String, String> {
+    let result = render_migration_files(report, name, base_path, options)
+        .map_err(|e| format!("Failed to render migration: {}", e));
+    audit_render(report, name, &result);
+    result
+}
+
+fn audit_render<T>(report: &DiffReport, name: &str, result: &Result<T, String>) {
+    let detail = format!("{} ({} -> {})", name, report.source_connection, report.target_connection);
+    audit::record(AuditAction::Render, None, &detail, result);
+}
+
+/// Render a column type change as a dual-write migration set: add a column
+/// of the new type, keep it in sync with a trigger, backfill in batches,
+/// verify, swap and drop the old column. With `expand_contract`, the phases
+/// are written as two migrations.
+///
+/// `source` has the column with its new type and `target` with its current
+/// one. Returns the phase folders in the order they are applied.
+#[tauri::command]
+pub async fn render_dual_write_migration(
+    source: SchemaModel,
+    target: SchemaModel,
+    table: String,
+    column: String,
+    name: String,
+    base_path: String,
+    options: Option<DualWriteOptions>,
+) -> Result<Vec<String>, String> {
+    let options = options.unwrap_or_default();
+    let mut phases = plan_dual_write(&source, &target, &table, &column, &options)?;
+    if options.expand_contract {
+        phases = expand_contract(phases);
+    }
+    let result = render_dual_write_migrations(&phases, &table, &column, &name, &base_path)
+        .map_err(|e| format!("Failed to render migration: {}", e));
+    audit::record(AuditAction::Render, None, &format!("{} (dual write of {}.{})", name, table, column), &result);
+    result
+}
+
+/// Event emitted with an [`OperationProgress`] while a migration builds
+/// indexes or rewrites tables.
+pub const OPERATION_PROGRESS_EVENT: &str = "operation-progress";
+
+/// Event emitted with an [`ApplyProgress`] when each statement of a
+/// migration starts and finishes.
+pub const APPLY_PROGRESS_EVENT: &str = "apply-progress";
+
+/// Callback emitting its argument as `event`.
+fn emitter<'a, T: Serialize + Clone>(app: &'a tauri::AppHandle, event: &'static str) -> impl Fn(&T) + Sync + 'a {
+    move |payload| {
+        if let Err(e) = app.emit(event, payload) {
+            tracing::warn!("Failed to emit progress: {}", e);
+        }
+    }
 }
 
 /// Apply a migration to the target database.
+///
+/// The attempt is recorded in the apply history of the directory containing
+/// the migration. With `continue_on_error` in `options`, failing statements
+/// are recorded in the returned run and the rest still applied. Progress of
+/// index builds and rewrites is emitted as [`OPERATION_PROGRESS_EVENT`]
+/// events, and each statement as it starts and finishes as
+/// [`APPLY_PROGRESS_EVENT`] events. Passing an `operation_id` lets
+/// [`cancel_operation`] stop it.
 #[tauri::command]
 pub async fn apply_migration(
+    app: tauri::AppHandle,
     connection_string: String,
     migration_path: String,
-) -> Result<Vec<String>, String> {
-    let pool = connect::create_pool(&connection_string)
+    options: Option<ApplyOptions>,
+    operation_id: Option<String>,
+    operations: tauri::State<'_, OperationRegistry>,
+) -> Result<MigrationRun, String> {
+    let operation = operations.register(operation_id);
+    run_apply_migration(&connection_string, &migration_path, &options.unwrap_or_default(), operation.token(),
+        emitter(&app, OPERATION_PROGRESS_EVENT),
+        emitter(&app, APPLY_PROGRESS_EVENT),
+    ).await
+}
+
+/// Apply a migration, passing the progress of long operations to
+/// `on_progress` and of its statements to `on_statement`, until `cancel` is
+/// cancelled.
+pub(crate) async fn run_apply_migration<F, G>(
+    connection_string: &str,
+    migration_path: &str,
+    options: &ApplyOptions,
+    cancel: &CancelToken,
+    on_progress: F,
+    on_statement: G,
+) -> Result<MigrationRun, String>
+where
+    F: Fn(&OperationProgress) + Sync,
+    G: Fn(&ApplyProgress) + Sync,
+{
+    run_migration(connection_string, migration_path, false, options, cancel, on_progress, on_statement).await
+}
+
+/// Apply a migration to several target databases, such as the databases of
+/// every tenant, and return the outcome on each.
+///
+/// Targets are applied in order, `parallelism` at a time (one by default).
+/// Once one fails, no further target starts and the rest are returned as
+/// skipped; each target is otherwise applied and recorded as by
+/// [`apply_migration`]. Progress events of concurrent targets are
+/// interleaved.
+#[tauri::command]
+pub async fn apply_migration_multi(
+    app: tauri::AppHandle,
+    targets: Vec<ConnectionRef>,
+    migration_path: String,
+    options: Option<ApplyOptions>,
+    parallelism: Option<usize>,
+    operation_id: Option<String>,
+    operations: tauri::State<'_, OperationRegistry>,
+) -> Result<Vec<TargetResult>, String> {
+    let operation = operations.register(operation_id);
+    run_apply_migration_multi(&targets, &migration_path, &options.unwrap_or_default(), parallelism.unwrap_or(1),
+        operation.token(),
+        emitter(&app, OPERATION_PROGRESS_EVENT),
+        emitter(&app, APPLY_PROGRESS_EVENT),
+    ).await
+}
+
+/// Apply a migration to several targets, passing the progress of long
+/// operations to `on_progress` and of its statements to `on_statement`.
+pub(crate) async fn run_apply_migration_multi<F, G>(
+    targets: &[ConnectionRef],
+    migration_path: &str,
+    options: &ApplyOptions,
+    parallelism: usize,
+    cancel: &CancelToken,
+    on_progress: F,
+    on_statement: G,
+) -> Result<Vec<TargetResult>, String>
+where
+    F: Fn(&OperationProgress) + Sync,
+    G: Fn(&ApplyProgress) + Sync,
+{
+    if targets.is_empty() {
+        return Err("No target databases to apply to".to_string());
+    }
+    safe_mode::ensure_writes_allowed(&default_migrations_dir()?, "Applying migrations")?;
+
+    Ok(apply_to_targets(targets, parallelism, cancel, |target| {
+        run_apply_migration(&target.connection_string, migration_path, options, cancel, &on_progress, &on_statement)
+    }).await)
+}
+
+/// Roll back a migration by running its `down.sql` on the target database.
+///
+/// The migration is removed from the target's `pgshift_migrations` table,
+/// and the attempt is recorded in the apply history like an apply. Progress
+/// and cancellation work as for [`apply_migration`].
+#[tauri::command]
+pub async fn rollback_migration(
+    app: tauri::AppHandle,
+    connection_string: String,
+    migration_path: String,
+    operation_id: Option<String>,
+    operations: tauri::State<'_, OperationRegistry>,
+) -> Result<MigrationRun, String> {
+    let operation = operations.register(operation_id);
+    run_rollback_migration(&connection_string, &migration_path, operation.token(),
+        emitter(&app, OPERATION_PROGRESS_EVENT),
+        emitter(&app, APPLY_PROGRESS_EVENT),
+    ).await
+}
+
+/// Roll back a migration, passing the progress of long operations to
+/// `on_progress` and of its statements to `on_statement`.
+pub(crate) async fn run_rollback_migration<F, G>(
+    connection_string: &str,
+    migration_path: &str,
+    cancel: &CancelToken,
+    on_progress: F,
+    on_statement: G,
+) -> Result<MigrationRun, String>
+where
+    F: Fn(&OperationProgress) + Sync,
+    G: Fn(&ApplyProgress) + Sync,
+{
+    run_migration(connection_string, migration_path, true, &ApplyOptions::default(), cancel, on_progress, on_statement).await
+}
+
+/// Apply a migration with `options`, or roll it back with `rollback`, in
+/// each schema the connection's saved filter selects, and record the attempt
+/// in the apply history.
+async fn run_migration<F, G>(
+    connection_string: &str,
+    migration_path: &str,
+    rollback: bool,
+    options: &ApplyOptions,
+    cancel: &CancelToken,
+    on_progress: F,
+    on_statement: G,
+) -> Result<MigrationRun, String>
+where
+    F: Fn(&OperationProgress) + Sync,
+    G: Fn(&ApplyProgress) + Sync,
+{
+    let operation = if rollback { "Rolling back migrations" } else { "Applying migrations" };
+    let action = if rollback { AuditAction::Rollback } else { AuditAction::Apply };
+    let migration_name = Path::new(migration_path).file_name().unwrap_or_default().to_string_lossy();
+    let checked = async {
+        safe_mode::ensure_writes_allowed(&default_migrations_dir()?, operation)?;
+        environments::ensure_writes_allowed(connection_string, operation)?;
+        schemas::selected_schemas(&default_migrations_dir()?, connection_string, if rollback { "rollback" } else { "apply" }, None).await
+    }.await;
+    // Refused and unreachable attempts are audited too
+    if checked.is_err() {
+        audit::record(action, Some(connection_string), &migration_name, &checked);
+    }
+    let schemas = checked?;
+
+    let mut run = MigrationRun::default();
+    for (i, schema) in schemas.iter().enumerate() {
+        if i > 0 && cancel.is_cancelled() {
+            return Err(format!("Ran in {} of {} schemas; {}", i, schemas.len(), CANCELLED));
+        }
+        if schemas.len() > 1 {
+            log_line!(run.logs, Level::INFO, "Schema {}", schema);
+        }
+        // One backup covers every schema of the database
+        let schema_run = run_migration_in_schema(
+            connection_string, migration_path, rollback, options, schema, i == 0, cancel, &on_progress, &on_statement,
+        )
         .await
-        .map_err(|e| format!("Failed to connect: {}", e))?;
+        .map_err(|e| if schemas.len() > 1 {
+            format!("Ran in {} of {} schemas; {} failed: {}", i, schemas.len(), schema, e)
+        } else {
+            e
+        })?;
+        run.logs.extend(schema_run.logs);
+        run.statements.extend(schema_run.statements);
+    }
+    Ok(run)
+}
+
+/// Apply a migration with `options`, or roll it back with `rollback`, in
+/// `schema`, backing the target up first when configured and `back_up`, and
+/// record the attempt in the apply history.
+///
+/// Safe mode and the environment's settings are checked by the caller.
+#[allow(clippy::too_many_arguments)]
+async fn run_migration_in_schema<F, G>(
+    connection_string: &str,
+    migration_path: &str,
+    rollback: bool,
+    options: &ApplyOptions,
+    schema: &str,
+    back_up: bool,
+    cancel: &CancelToken,
+    on_progress: F,
+    on_statement: G,
+) -> Result<MigrationRun, String>
+where
+    F: Fn(&OperationProgress) + Sync,
+    G: Fn(&ApplyProgress) + Sync,
+{
+    let action = if rollback { AuditAction::Rollback } else { AuditAction::Apply };
+    let path = Path::new(migration_path);
+    let migration_name = path.file_name().unwrap_or_default().to_string_lossy();
+    let audit_detail = if schema == schemas::DEFAULT_SCHEMA {
+        migration_name.to_string()
+    } else {
+        format!("{} (schema {})", migration_name, schema)
+    };
+    let connected = async {
+        let environment_backup = environments::find_environment(connection_string)?.and_then(|e| e.backup);
+        let pool = connect::create_pool_in_schema(connection_string, if rollback { "rollback" } else { "apply" }, schema)
+            .await
+            .map_err(|e| format!("Failed to connect: {}", e))?;
+        Ok((pool, environment_backup))
+    }.await;
+    if connected.is_err() {
+        audit::record(action, Some(connection_string), &audit_detail, &connected);
+    }
+    let (pool, environment_backup) = connected?;
+    
+    let target = fetch_identity(&pool).await.ok();
+    let mut session: Option<SessionSettings> = None;
+    let mut run = MigrationRun::default();
+    let mut backup_path = None;
+    let result = async {
+        let backup = options.backup.as_ref().or(environment_backup.as_ref()).filter(|_| back_up);
+        if let Some(backup) = backup.filter(|b| !rollback && b.applies_to(Path::new(migration_path))) {
+            let name = Path::new(migration_path).file_name().unwrap_or_default().to_string_lossy().to_string();
+            let path = backup_database(connection_string, &name, backup)
+                .await
+                .map_err(|e| format!("Backup before applying failed, so the migration was not run: {}", e))?;
+            log_line!(run.logs, Level::INFO, "Backed up the target to {}", path.display());
+            backup_path = Some(path.to_string_lossy().to_string());
+        }
+        let mut conn = pool.acquire()
+            .await
+            .map_err(|e| format!("Failed to acquire connection: {}", e))?;
+        let settings = fetch_session_settings(&mut conn)
+            .await
+            .map_err(|e| format!("Failed to read session settings: {}", e))?;
+        let settings = session.insert(settings);
+        if rollback {
+            rollback_migration_sql(&pool, &mut conn, settings, migration_path, &mut run, cancel, on_progress, on_statement).await
+        } else {
+            apply_migration_sql(&pool, &mut conn, settings, migration_path, options, &mut run, cancel, on_progress, on_statement).await
+        }
+    }
+    .await
+    .map_err(|e| if rollback { format!("Rollback failed: {}", e) } else { format!("Migration failed: {}", e) });
+    release_pool(pool).await;
+    
+    if let Some(base_path) = path.parent().and_then(|p| p.to_str()) {
+        let record = ApplyRecord {
+            id: uuid::Uuid::new_v4().to_string(),
+            migration_name: path.file_name()
+                .map(|n| n.to_string_lossy().to_string())
+                .unwrap_or_default(),
+            target,
+            applied_at: Utc::now().to_rfc3339(),
+            success: result.is_ok(),
+            error: result.as_ref().err().cloned(),
+            session,
+            rollback,
+            statements: run.statements.clone(),
+            backup_path,
+        };
+        // The migration already ran; a history failure must not hide its outcome
+        if let Err(e) = apply_history::record_apply(base_path, record) {
+            tracing::error!("Failed to record apply history: {}", e);
+        }
+    }
+    audit::record(action, Some(connection_string), &audit_detail, &result);
+    let notification = Notification::migration(
+        &connect::connection_label(connection_string),
+        &migration_name,
+        rollback,
+        result.as_ref().err().map(String::as_str),
+    );
+    if let Ok(base_path) = default_migrations_dir() {
+        notify(&base_path, &notification).await;
+    }
+    
+    result.map(|()| run)
+}
+
+/// Count the rows each table and column dropped by `report` holds on the
+/// target database, and return the report with them attached.
+///
+/// Counts come from the planner statistics unless `exact` is set, which
+/// scans every such table. The connection's saved schema filter applies.
+#[tauri::command]
+pub async fn estimate_data_loss(
+    connection_string: String,
+    report: DiffReport,
+    exact: Option<bool>,
+) -> Result<DiffReport, String> {
+    let mut report = report;
+    let pool = schemas::connect_to_schema(&default_migrations_dir()?, &connection_string, "estimate", None).await?;
+    let counted = row_counts::attach_data_loss(&pool, &mut report, exact.unwrap_or(false)).await;
+    release_pool(pool).await;
+    counted.map_err(|e| format!("Failed to count rows: {}", e))?;
+    Ok(report)
+}
+
+/// Estimate the rows each data-modifying statement of a migration writes,
+/// with `EXPLAIN` on the target database and without running anything.
+///
+/// The plan is made on the first schema the connection's saved filter
+/// selects.
+#[tauri::command]
+pub async fn plan_migration(
+    connection_string: String,
+    migration_path: String,
+) -> Result<MigrationPlan, String> {
+    let pool = schemas::connect_to_schema(&default_migrations_dir()?, &connection_string, "plan", None).await?;
+    let plan = plan_migration_sql(&pool, &migration_path).await;
+    release_pool(pool).await;
+    plan
+}
+
+/// Map each statement of a migration to the table locks it takes, with the
+/// sessions on the target that hold or wait for a conflicting lock.
+///
+/// Tables are looked up in the first schema the connection's saved filter
+/// selects.
+#[tauri::command]
+pub async fn analyze_lock_impact(
+    connection_string: String,
+    migration_path: String,
+) -> Result<LockImpact, String> {
+    let pool = schemas::connect_to_schema(&default_migrations_dir()?, &connection_string, "locks", None).await?;
+    let impact = analyze_lock_impact_sql(&pool, &migration_path).await;
+    release_pool(pool).await;
+    impact
+}
+
+/// Event emitted with a [`CloneProgress`] while a schema is cloned.
+pub const CLONE_PROGRESS_EVENT: &str = "clone-progress";
+
+/// Create every object of the source database on an empty target database.
+///
+/// The script is the one a migration from an empty schema would hold and is
+/// applied in one transaction. Each step is emitted as a
+/// [`CLONE_PROGRESS_EVENT`] event. Passing an `operation_id` lets
+/// [`cancel_operation`] stop it.
+#[tauri::command]
+pub async fn clone_schema(
+    app: tauri::AppHandle,
+    source_connection: String,
+    target_connection: String,
+    options: Option<CloneOptions>,
+    operation_id: Option<String>,
+    operations: tauri::State<'_, OperationRegistry>,
+) -> Result<CloneResult, String> {
+    let operation = operations.register(operation_id);
+    run_clone_schema(&source_connection, &target_connection, &options.unwrap_or_default(), operation.token(), |progress| {
+        if let Err(e) = app.emit(CLONE_PROGRESS_EVENT, progress) {
+            tracing::warn!("Failed to emit progress: {}", e);
+        }
+    }).await
+}
+
+/// Clone a schema, passing each step to `on_progress`, until `cancel` is
+/// cancelled.
+pub(crate) async fn run_clone_schema<F>(
+    source_connection: &str,
+    target_connection: &str,
+    options: &CloneOptions,
+    cancel: &CancelToken,
+    on_progress: F,
+) -> Result<CloneResult, String>
+where
+    F: Fn(&CloneProgress) + Sync,
+{
+    // A dry run only renders the script
+    if !options.dry_run {
+        safe_mode::ensure_writes_allowed(&default_migrations_dir()?, "Cloning a schema")?;
+        environments::ensure_writes_allowed(target_connection, "Cloning a schema")?;
+    }
+    
+    let base_path = default_migrations_dir()?;
+    let source_pool = schemas::connect_to_schema(&base_path, source_connection, "clone", None)
+        .await
+        .map_err(|e| format!("Source: {}", e))?;
+    let target_pool = schemas::connect_to_schema(&base_path, target_connection, "clone", None)
+        .await
+        .map_err(|e| format!("Target: {}", e))?;
+    
+    let result = async {
+        if let (Ok(s), Ok(t)) = (fetch_identity(&source_pool).await, fetch_identity(&target_pool).await) {
+            if s.is_same_database(&t) {
+                return Err("Source and target are the same database; refusing to clone".to_string());
+            }
+        }
+        schema_clone::clone_schema(&source_pool, &target_pool, options, cancel, on_progress).await
+    }
+    .await;
+    release_pool(source_pool).await;
+    release_pool(target_pool).await;
     
-    apply_migration_sql(&pool, &migration_path)
+    result.map_err(|e| format!("Clone failed: {}", e))
+}
+
+/// Running index builds, rewrites, vacuums, analyzes, COPYs and base backups
+/// of the database, from the `pg_stat_progress_*` views.
+#[tauri::command]
+pub async fn get_operation_progress(connection_string: String) -> Result<Vec<OperationProgress>, String> {
+    let pool = connect::create_pool(&connection_string, "progress")
         .await
-        .map_err(|e| format!("Migration failed: {}", e))
+        .map_err(|e| format!("Failed to connect: {}", e))?;
+    let progress = fetch_operation_progress(&pool, None)
+        .await
+        .map_err(|e| format!("Failed to read operation progress: {}", e));
+    release_pool(pool).await;
+    progress
 }
 
-/// Get the default migrations directory.
+/// Which migration folders of a base path are applied to the target
+/// database, from its `pgshift_migrations` table.
+///
+/// An empty base path takes the migrations directory of the environment
+/// `connection_string` names. When the connection's saved filter selects
+/// several schemas, a migration is applied once every schema applied it.
 #[tauri::command]
-pub async fn get_migrations_dir() -> Result<String, String> {
+pub async fn get_migration_status(
+    connection_string: String,
+    base_path: String,
+) -> Result<MigrationStatusReport, String> {
+    let base_path = environments::migrations_dir(&base_path, &connection_string)?;
+    let schemas = schemas::selected_schemas(&default_migrations_dir()?, &connection_string, "status", None).await?;
+    let mut reports = Vec::new();
+    for schema in &schemas {
+        reports.push(schema_migration_status(&connection_string, &base_path, schema).await?);
+    }
+    Ok(merge_migration_status(reports))
+}
+
+/// Which migration folders of `base_path` are applied in `schema`.
+async fn schema_migration_status(
+    connection_string: &str,
+    base_path: &str,
+    schema: &str,
+) -> Result<MigrationStatusReport, String> {
+    let folders = replay::migration_folders(base_path)?;
+    let pool = connect::create_pool_in_schema(connection_string, "status", schema)
+        .await
+        .map_err(|e| format!("Failed to connect: {}", e))?;
+    let applied = async {
+        let mut conn = pool.acquire().await?;
+        fetch_applied_migrations(&mut conn).await
+    }
+    .await
+    .map_err(|e| format!("Failed to read applied migrations: {}", e));
+    release_pool(pool).await;
+    migration_status(&folders, applied?)
+}
+
+/// Apply the pending migrations of a base path to the target database in
+/// order, stopping at the first that fails.
+///
+/// Each is applied and recorded as by [`apply_migration`]; once cancelled,
+/// no further migration starts. With several schemas selected by the
+/// connection's saved filter, each schema's pending migrations are applied
+/// in turn.
+#[tauri::command]
+pub async fn apply_pending(
+    app: tauri::AppHandle,
+    connection_string: String,
+    base_path: String,
+    operation_id: Option<String>,
+    operations: tauri::State<'_, OperationRegistry>,
+) -> Result<Vec<String>, String> {
+    let operation = operations.register(operation_id);
+    run_apply_pending(&connection_string, &base_path, operation.token(),
+        emitter(&app, OPERATION_PROGRESS_EVENT),
+        emitter(&app, APPLY_PROGRESS_EVENT),
+    ).await
+}
+
+/// Apply the pending migrations, passing the progress of long operations to
+/// `on_progress` and of their statements to `on_statement`.
+pub(crate) async fn run_apply_pending<F, G>(
+    connection_string: &str,
+    base_path: &str,
+    cancel: &CancelToken,
+    on_progress: F,
+    on_statement: G,
+) -> Result<Vec<String>, String>
+where
+    F: Fn(&OperationProgress) + Sync,
+    G: Fn(&ApplyProgress) + Sync,
+{
+    safe_mode::ensure_writes_allowed(&default_migrations_dir()?, "Applying migrations")?;
+    environments::ensure_writes_allowed(connection_string, "Applying migrations")?;
+    let base_path = &environments::migrations_dir(base_path, connection_string)?;
+    let schemas = schemas::selected_schemas(&default_migrations_dir()?, connection_string, "apply", None).await?;
+    
+    let mut logs = Vec::new();
+    for (i, schema) in schemas.iter().enumerate() {
+        if schemas.len() > 1 {
+            log_line!(logs, Level::INFO, "Schema {}", schema);
+        }
+        apply_pending_in_schema(connection_string, base_path, schema, i == 0, &mut logs, cancel, &on_progress, &on_statement)
+            .await
+            .map_err(|e| if schemas.len() > 1 { format!("Schema {}: {}", schema, e) } else { e })?;
+    }
+    Ok(logs)
+}
+
+/// Apply the pending migrations of `base_path` in `schema`, appending their
+/// logs to `logs`; backups are taken when `back_up`.
+#[allow(clippy::too_many_arguments)]
+async fn apply_pending_in_schema<F, G>(
+    connection_string: &str,
+    base_path: &str,
+    schema: &str,
+    back_up: bool,
+    logs: &mut Vec<String>,
+    cancel: &CancelToken,
+    on_progress: F,
+    on_statement: G,
+) -> Result<(), String>
+where
+    F: Fn(&OperationProgress) + Sync,
+    G: Fn(&ApplyProgress) + Sync,
+{
+    let status = schema_migration_status(connection_string, base_path, schema).await?;
+    for migration in status.migrations.iter().filter(|m| m.modified) {
+        log_line!(logs, Level::WARN, "⚠️  {} changed after it was applied", migration.name);
+    }
+    let last_applied = status.migrations.iter().rposition(|m| m.applied.is_some());
+    if status.pending.is_empty() {
+        log_line!(logs, Level::INFO, "No pending migrations");
+        return Ok(());
+    }
+    
+    for (i, name) in status.pending.iter().enumerate() {
+        if cancel.is_cancelled() {
+            return Err(format!("Applied {} of {} pending migrations; {}", i, status.pending.len(), CANCELLED));
+        }
+        if last_applied.is_some_and(|last| status.migrations[..last].iter().any(|m| &m.name == name)) {
+            log_line!(logs, Level::WARN, "⚠️  {} is older than migrations already applied", name);
+        }
+        let path = Path::new(base_path).join(name);
+        match run_migration_in_schema(
+            connection_string, &path.to_string_lossy(), false, &ApplyOptions::default(), schema, back_up,
+            cancel, &on_progress, &on_statement,
+        ).await {
+            Ok(run) => logs.extend(run.logs),
+            Err(e) => return Err(format!(
+                "Applied {} of {} pending migrations; {} failed: {}",
+                i, status.pending.len(), name, e
+            )),
+        }
+    }
+    Ok(())
+}
+
+/// Roll back the migration of a base path applied last to the target
+/// database, according to its `pgshift_migrations` table.
+#[tauri::command]
+pub async fn rollback_last(
+    app: tauri::AppHandle,
+    connection_string: String,
+    base_path: String,
+    operation_id: Option<String>,
+    operations: tauri::State<'_, OperationRegistry>,
+) -> Result<MigrationRun, String> {
+    let operation = operations.register(operation_id);
+    run_rollback_last(&connection_string, &base_path, operation.token(),
+        emitter(&app, OPERATION_PROGRESS_EVENT),
+        emitter(&app, APPLY_PROGRESS_EVENT),
+    ).await
+}
+
+/// Roll back the last applied migration, passing the progress of long
+/// operations to `on_progress` and of its statements to `on_statement`.
+pub(crate) async fn run_rollback_last<F, G>(
+    connection_string: &str,
+    base_path: &str,
+    cancel: &CancelToken,
+    on_progress: F,
+    on_statement: G,
+) -> Result<MigrationRun, String>
+where
+    F: Fn(&OperationProgress) + Sync,
+    G: Fn(&ApplyProgress) + Sync,
+{
+    safe_mode::ensure_writes_allowed(&default_migrations_dir()?, "Rolling back migrations")?;
+    environments::ensure_writes_allowed(connection_string, "Rolling back migrations")?;
+    let base_path = &environments::migrations_dir(base_path, connection_string)?;
+    let schemas = schemas::selected_schemas(&default_migrations_dir()?, connection_string, "rollback", None).await?;
+
+    let mut run = MigrationRun::default();
+    for (i, schema) in schemas.iter().enumerate() {
+        if i > 0 && cancel.is_cancelled() {
+            return Err(format!("Rolled back in {} of {} schemas; {}", i, schemas.len(), CANCELLED));
+        }
+        if schemas.len() > 1 {
+            log_line!(run.logs, Level::INFO, "Schema {}", schema);
+        }
+        let schema_run = rollback_last_in_schema(connection_string, base_path, schema, cancel, &on_progress, &on_statement)
+            .await
+            .map_err(|e| if schemas.len() > 1 { format!("Schema {}: {}", schema, e) } else { e })?;
+        run.logs.extend(schema_run.logs);
+        run.statements.extend(schema_run.statements);
+    }
+    Ok(run)
+}
+
+/// Roll back the migration of `base_path` applied last in `schema`.
+async fn rollback_last_in_schema<F, G>(
+    connection_string: &str,
+    base_path: &str,
+    schema: &str,
+    cancel: &CancelToken,
+    on_progress: F,
+    on_statement: G,
+) -> Result<MigrationRun, String>
+where
+    F: Fn(&OperationProgress) + Sync,
+    G: Fn(&ApplyProgress) + Sync,
+{
+    let status = schema_migration_status(connection_string, base_path, schema).await?;
+    let last = last_applied_migration(&status, base_path)?;
+    
+    let path = Path::new(base_path).join(last);
+    run_migration_in_schema(
+        connection_string, &path.to_string_lossy(), true, &ApplyOptions::default(), schema, false,
+        cancel, on_progress, on_statement,
+    ).await
+}
+
+/// List the migrations applied from a base path, oldest first.
+#[tauri::command]
+pub async fn list_apply_history(base_path: String) -> Result<Vec<ApplyRecord>, String> {
+    apply_history::load_history(&base_path)
+}
+
+/// Entries of the audit log matching `query`, newest first.
+#[tauri::command]
+pub async fn get_audit_log(query: Option<AuditQuery>) -> Result<Vec<AuditEntry>, String> {
+    audit::load_audit_log(&default_migrations_dir()?, &query.unwrap_or_default())
+}
+
+/// The latest `limit` logged events at `level` (`error`, `warn`, `info`,
+/// `debug` or `trace`) or more severe, oldest first. Defaults to the last
+/// 500 at `info`.
+#[tauri::command]
+pub async fn get_recent_logs(level: Option<String>, limit: Option<usize>) -> Result<Vec<LogRecord>, String> {
+    let level = match level {
+        Some(level) => level.parse::<Level>().map_err(|_| format!("Unknown log level: {}", level))?,
+        None => Level::INFO,
+    };
+    Ok(logging::recent_logs(level, limit.unwrap_or(500)))
+}
+
+/// Resolve (and create) the default migrations directory.
+///
+/// This is also the base path the frontend uses for versions and schedules.
+pub fn default_migrations_dir() -> Result<String, String> {
     // Use user's home directory to avoid triggering hot-reload
     let home_dir = dirs::home_dir()
         .ok_or_else(|| "Could not find home directory".to_string())?;
@@ -120,12 +1254,16 @@ pub async fn get_migrations_dir() -> Result<String, String> {
     Ok(migrations_dir.to_string_lossy().to_string())
 }
 
+/// Get the default migrations directory.
+#[tauri::command]
+pub async fn get_migrations_dir() -> Result<String, String> {
+    default_migrations_dir()
+}
+
 /// Get database info (name, version, size, etc.)
 #[tauri::command]
 pub async fn get_database_info(connection_string: String) -> Result<serde_json::Value, String> {
-    let pool = connect::create_pool(&connection_string)
-        .await
-        .map_err(|e| format!("Failed to connect: {}", e))?;
+    let pool = schemas::connect_to_schema(&default_migrations_dir()?, &connection_string, "database_info", None).await?;
     
     let row = sqlx::query(
         r#"
@@ -134,7 +1272,7 @@ pub async fn get_database_info(connection_string: String) -> Result<serde_json::
             current_user as current_user,
             version() as pg_version,
             pg_size_pretty(pg_database_size(current_database())) as database_size,
-            (SELECT count(*) FROM information_schema.tables WHERE table_schema = 'public' AND table_type = 'BASE TABLE') as table_count
+            (SELECT count(*) FROM information_schema.tables WHERE table_schema = current_schema() AND table_type = 'BASE TABLE') as table_count
         "#
     )
     .fetch_one(&pool)
@@ -150,6 +1288,33 @@ pub async fn get_database_info(connection_string: String) -> Result<serde_json::
     }))
 }
 
+/// List the databases of the server behind a connection, to pick the one to
+/// connect to; any database the role may connect to, such as `postgres`,
+/// will do.
+#[tauri::command]
+pub async fn list_databases(connection_string: String) -> Result<Vec<DatabaseEntry>, String> {
+    let pool = connect::create_pool(&connection_string, "list_databases")
+        .await
+        .map_err(|e| format!("Failed to connect: {}", e))?;
+    let databases = databases::list_databases(&pool)
+        .await
+        .map_err(|e| format!("Failed to list databases: {}", e));
+    release_pool(pool).await;
+    databases
+}
+
+/// Get the version of the server behind a connection and the features that
+/// depend on it, which generated SQL adapts to.
+#[tauri::command]
+pub async fn get_server_capabilities(connection_string: String) -> Result<ServerCapabilities, String> {
+    let pool = schemas::connect_to_schema(&default_migrations_dir()?, &connection_string, "server_capabilities", None).await?;
+    let capabilities = fetch_capabilities(&pool)
+        .await
+        .map_err(|e| format!("Failed to read server version: {}", e));
+    release_pool(pool).await;
+    capabilities
+}
+
 /// Get table data with pagination for database browser
 #[tauri::command]
 pub async fn get_table_data(
@@ -160,16 +1325,14 @@ pub async fn get_table_data(
     order_by: Option<String>,
     order_dir: Option<String>,
 ) -> Result<TableDataResult, String> {
-    let pool = connect::create_pool(&connection_string)
-        .await
-        .map_err(|e| format!("Failed to connect: {}", e))?;
+    let pool = schemas::connect_to_schema(&default_migrations_dir()?, &connection_string, "table_data", None).await?;
     
     // Get column names
     let col_rows = sqlx::query(
         r#"
         SELECT column_name 
         FROM information_schema.columns 
-        WHERE table_schema = 'public' AND table_name = $1
+        WHERE table_schema = current_schema() AND table_name = $1
         ORDER BY ordinal_position
         "#
     )
@@ -244,20 +1407,35 @@ pub async fn get_table_data(
 }
 
 /// Execute a raw SQL query (for database browser)
+///
+/// In safe mode, or on a read-only environment, only reads run. Reads run
+/// inside a read-only transaction, so a data modifying `WITH` is rejected by
+/// the server.
 #[tauri::command]
 pub async fn execute_query(
     connection_string: String,
     query: String,
 ) -> Result<serde_json::Value, String> {
-    let pool = connect::create_pool(&connection_string)
-        .await
-        .map_err(|e| format!("Failed to connect: {}", e))?;
+    let result = run_query(&connection_string, &query).await;
+    audit::record(AuditAction::ExecuteQuery, Some(&connection_string), &query, &result);
+    result
+}
+
+async fn run_query(connection_string: &str, query: &str) -> Result<serde_json::Value, String> {
+    let safe_mode = safe_mode::load_safe_mode(&default_migrations_dir()?)?;
+    if safe_mode && !safe_mode::is_read_query(query) {
+        return Err("Only SELECT queries can run in safe mode".to_string());
+    }
+    let read_only_environment = environments::find_environment(connection_string)?.is_some_and(|e| e.read_only);
+    if read_only_environment && !safe_mode::is_read_query(query) {
+        return Err("Only SELECT queries can run on a read-only environment".to_string());
+    }
+    
+    let pool = schemas::connect_to_schema(&default_migrations_dir()?, connection_string, "query", None).await?;
     
     // Detect if it's a SELECT query
-    let trimmed = query.trim().to_uppercase();
-    if trimmed.starts_with("SELECT") || trimmed.starts_with("WITH") {
-        let rows = sqlx::query(&query)
-            .fetch_all(&pool)
+    if safe_mode::is_read_query(query) {
+        let rows = safe_mode::fetch_read_query(&pool, query, safe_mode || read_only_environment)
             .await
             .map_err(|e| format!("Query failed: {}", e))?;
         
@@ -303,7 +1481,7 @@ pub async fn execute_query(
         }))
     } else {
         // Execute non-select query
-        let result = sqlx::query(&query)
+        let result = sqlx::query(query)
             .execute(&pool)
             .await
             .map_err(|e| format!("Query failed: {}", e))?;
@@ -321,14 +1499,96 @@ pub async fn export_migration(
     report: DiffReport,
     name: String,
     export_path: String,
+    options: Option<RenderOptions>,
 ) -> Result<String, String> {
     let path = Path::new(&export_path);
     if !path.exists() {
         fs::create_dir_all(path).map_err(|e| format!("Failed to create directory: {}", e))?;
     }
     
-    render_migration_files(&report, &name, &export_path)
-        .map_err(|e| format!("Failed to export migration: {}", e))
+    let result = render_migration_files(&report, &name, &export_path, &options.unwrap_or_default())
+        .map_err(|e| format!("Failed to export migration: {}", e));
+    audit_render(&report, &name, &result);
+    result
+}
+
+/// Export a schema as a static HTML data dictionary.
+/// Returns the path of the generated index.html.
+#[tauri::command]
+pub async fn export_data_dictionary(
+    schema: SchemaModel,
+    title: String,
+    export_path: String,
+) -> Result<String, String> {
+    write_data_dictionary(&schema, &title, &export_path)
+        .map_err(|e| format!("Failed to export data dictionary: {}", e))
+}
+
+/// Write a diff report as Markdown or standalone HTML to `path`, for
+/// attaching to pull requests and change tickets. Returns `path`.
+#[tauri::command]
+pub async fn export_diff_report(
+    report: DiffReport,
+    format: ReportFormat,
+    path: String,
+) -> Result<String, String> {
+    write_report(&report, format, &path)
+        .map_err(|e| format!("Failed to export diff report: {}", e))?;
+    Ok(path)
+}
+
+/// Write a diff report as a Liquibase changelog to `path`, one changeset
+/// per change with its rollback. Returns `path`.
+#[tauri::command]
+pub async fn export_liquibase_changelog(
+    report: DiffReport,
+    format: ChangelogFormat,
+    path: String,
+    author: Option<String>,
+) -> Result<String, String> {
+    let author = author.unwrap_or_else(|| liquibase::DEFAULT_AUTHOR.to_string());
+    write_liquibase_changelog(&report, format, &author, &path)
+        .map_err(|e| format!("Failed to export Liquibase changelog: {}", e))?;
+    Ok(path)
+}
+
+/// Render a schema as DBML, for dbdiagram.io or dbdocs.
+#[tauri::command]
+pub async fn export_dbml(schema: SchemaModel) -> Result<String, String> {
+    Ok(render_dbml(&schema))
+}
+
+/// Render a schema as a Mermaid `erDiagram`, for markdown pages and wikis.
+#[tauri::command]
+pub async fn export_mermaid(schema: SchemaModel, title: Option<String>) -> Result<String, String> {
+    Ok(render_mermaid(&schema, title.as_deref()))
+}
+
+/// Render a saved schema version as a Mermaid `erDiagram` titled with the
+/// version name.
+#[tauri::command]
+pub async fn export_version_mermaid(base_path: String, version_id: String) -> Result<String, String> {
+    let version = find_version(&base_path, &version_id)?;
+    Ok(render_mermaid(&version.schema, Some(&version.name)))
+}
+
+/// Tables and foreign keys of a schema as nodes and edges, for drawing an
+/// entity-relationship diagram.
+#[tauri::command]
+pub async fn schema_graph(schema: SchemaModel) -> Result<SchemaGraph, String> {
+    Ok(erd::schema_graph(&schema))
+}
+
+/// Squash the migration folders from `from` to `to` into one folder called
+/// `name`, moving the originals under `.squashed/`.
+#[tauri::command]
+pub async fn squash_migrations(
+    base_path: String,
+    from: String,
+    to: String,
+    name: String,
+) -> Result<SquashResult, String> {
+    squash::squash_migrations(&base_path, &from, &to, &name)
 }
 
 /// Get list of all migration files from a directory
@@ -354,7 +1614,7 @@ pub async fn list_migrations(base_path: String) -> Result<Vec<serde_json::Value>
                 .unwrap_or("")
                 .to_string();
             
-            // Look for up.sql, down.sql, meta.json
+            // Look for up.sql, down.sql, meta.json and the hooks
             let up_sql = entry_path.join("up.sql");
             let down_sql = entry_path.join("down.sql");
             let meta_json = entry_path.join("meta.json");
@@ -363,6 +1623,8 @@ pub async fn list_migrations(base_path: String) -> Result<Vec<serde_json::Value>
                 let up_content = fs::read_to_string(&up_sql).ok();
                 let down_content = fs::read_to_string(&down_sql).ok();
                 let meta_content = fs::read_to_string(&meta_json).ok();
+                let before_content = hook_script(&entry_path, HookPoint::Before).ok().flatten();
+                let after_content = hook_script(&entry_path, HookPoint::After).ok().flatten();
                 
                 let meta: Option<serde_json::Value> = meta_content
                     .and_then(|c| serde_json::from_str(&c).ok());
@@ -372,57 +1634,101 @@ pub async fn list_migrations(base_path: String) -> Result<Vec<serde_json::Value>
                     "path": entry_path.to_string_lossy(),
                     "up_sql": up_content,
                     "down_sql": down_content,
+                    "before_sql": before_content,
+                    "after_sql": after_content,
                     "meta": meta,
                 }));
             }
         }
     }
     
-    // Sort by name (timestamp)
+    // Sort by version, then name
     migrations.sort_by(|a, b| {
         let name_a = a.get("name").and_then(|v| v.as_str()).unwrap_or("");
         let name_b = b.get("name").and_then(|v| v.as_str()).unwrap_or("");
-        name_b.cmp(name_a) // Newest first
+        compare_folder_names(name_b, name_a) // Newest first
     });
     
     Ok(migrations)
 }
 
+/// Reconstruct the schema a migrations directory produces, without a database.
+#[tauri::command]
+pub async fn replay_migrations(base_path: String) -> Result<ParsedSchema, String> {
+    replay::replay_migrations(&base_path)
+}
+
+/// Build a schema model from a `.sql` file or a directory of them, e.g. a
+/// repository's `schema.sql`, to compare as the desired state.
+#[tauri::command]
+pub async fn introspect_sql_file(path: String) -> Result<ParsedSchema, String> {
+    files::parse_schema_files(&path)
+}
+
+/// Build a schema model from a `schema.prisma`, to compare as the desired state.
+#[tauri::command]
+pub async fn import_prisma_schema(path: String) -> Result<ParsedSchema, String> {
+    prisma::load_prisma_schema(&path)
+}
+
+/// Build a schema model from a Diesel `schema.rs`, to compare as the desired state.
+#[tauri::command]
+pub async fn import_diesel_schema(path: String) -> Result<ParsedSchema, String> {
+    diesel::load_diesel_schema(&path)
+}
+
+/// Read a declarative `schema.toml` or `schema.yaml`, to compare as the
+/// desired state.
+#[tauri::command]
+pub async fn load_schema_file(path: String) -> Result<SchemaModel, String> {
+    schema_file::load_schema_file(&path)
+}
+
+/// Write a schema, e.g. an introspected database, to a declarative schema
+/// file at `path`, in YAML when it ends in `.yaml` or `.yml`.
+#[tauri::command]
+pub async fn export_schema_file(schema: SchemaModel, path: String) -> Result<(), String> {
+    schema_file::save_schema_file(&schema, &path)
+}
+
+/// Write the canonical `schema.sql` of a database to `path`, for committing
+/// to version control; the file is left untouched when the schema did not
+/// change.
+#[tauri::command]
+pub async fn dump_schema_sql(connection_string: String, path: String) -> Result<SchemaSqlDump, String> {
+    let schema = run_introspect(&connection_string, &IntrospectOptions::default(), |_| {}).await?;
+    write_schema_sql(&schema, &DiffOptions::default(), &path)
+        .map_err(|e| format!("Failed to write {}: {}", path, e))
+}
+
 // ===================== SCHEMA VERSIONING =====================
 
 const VERSIONS_FILE: &str = "schema_versions.json";
 
-fn get_versions_path(base_path: &str) -> std::path::PathBuf {
-    Path::new(base_path).join(VERSIONS_FILE)
+pub(crate) fn load_versions(base_path: &str) -> Result<Vec<SchemaVersion>, String> {
+    find_versions(base_path, &VersionQuery::default())
 }
 
-fn load_versions(base_path: &str) -> Result<Vec<SchemaVersion>, String> {
-    let path = get_versions_path(base_path);
-    if !path.exists() {
-        return Ok(Vec::new());
+/// Versions matching `query`, oldest first.
+fn find_versions(base_path: &str, query: &VersionQuery) -> Result<Vec<SchemaVersion>, String> {
+    match open_shared_store(base_path)? {
+        Some(store) => {
+            let versions: Vec<SchemaVersion> = store.read_json(VERSIONS_FILE)?;
+            Ok(versions.into_iter().filter(|v| query.matches(v)).collect())
+        }
+        None => MetadataStore::open(base_path)?.list_versions(query),
     }
-    
-    let content = fs::read_to_string(&path)
-        .map_err(|e| format!("Failed to read versions file: {}", e))?;
-    
-    serde_json::from_str(&content)
-        .map_err(|e| format!("Failed to parse versions file: {}", e))
 }
 
-fn save_versions(base_path: &str, versions: &[SchemaVersion]) -> Result<(), String> {
-    let path = get_versions_path(base_path);
-    let parent = path.parent().ok_or("Invalid path")?;
-    
-    if !parent.exists() {
-        fs::create_dir_all(parent)
-            .map_err(|e| format!("Failed to create directory: {}", e))?;
-    }
-    
-    let content = serde_json::to_string_pretty(versions)
-        .map_err(|e| format!("Failed to serialize versions: {}", e))?;
-    
-    fs::write(&path, content)
-        .map_err(|e| format!("Failed to write versions file: {}", e))
+pub(crate) fn find_version(base_path: &str, version_id: &str) -> Result<SchemaVersion, String> {
+    let version = match open_shared_store(base_path)? {
+        Some(store) => {
+            let versions: Vec<SchemaVersion> = store.read_json(VERSIONS_FILE)?;
+            versions.into_iter().find(|v| v.id == version_id)
+        }
+        None => MetadataStore::open(base_path)?.get_version(version_id)?,
+    };
+    version.ok_or_else(|| format!("Version not found: {}", version_id))
 }
 
 /// Save a schema version snapshot
@@ -433,11 +1739,22 @@ pub async fn save_schema_version(
     description: String,
     tags: Vec<String>,
     base_path: String,
+) -> Result<SchemaVersion, String> {
+    let detail = name.clone();
+    let result = snapshot_schema(&connection_string, name, description, tags, &base_path).await;
+    audit::record(AuditAction::SaveVersion, Some(&connection_string), &detail, &result);
+    result
+}
+
+async fn snapshot_schema(
+    connection_string: &str,
+    name: String,
+    description: String,
+    tags: Vec<String>,
+    base_path: &str,
 ) -> Result<SchemaVersion, String> {
     // Introspect current schema
-    let pool = connect::create_pool(&connection_string)
-        .await
-        .map_err(|e| format!("Failed to connect: {}", e))?;
+    let pool = schemas::connect_to_schema(&default_migrations_dir()?, connection_string, "save_schema_version", None).await?;
     
     let schema = db_introspect::introspect_schema(&pool)
         .await
@@ -450,21 +1767,34 @@ pub async fn save_schema_version(
         .map_err(|e| format!("Failed to get database name: {}", e))?;
     let database_name: String = db_row.get("db_name");
     
+    let identity = fetch_identity(&pool)
+        .await
+        .map_err(|e| format!("Failed to identify database: {}", e))?;
+    
+    let locale = fetch_locale(&pool)
+        .await
+        .map_err(|e| format!("Failed to read database locale: {}", e))?;
+    
     let version = SchemaVersion {
         id: uuid::Uuid::new_v4().to_string(),
         name,
         description,
-        connection_string: connection_string.clone(),
+        connection: connect::connection_label(connection_string),
         database_name,
         schema,
         created_at: Utc::now().to_rfc3339(),
         tags,
+        identity: Some(identity),
+        locale: Some(locale),
     };
     
-    // Load existing versions and add new one
-    let mut versions = load_versions(&base_path)?;
-    versions.push(version.clone());
-    save_versions(&base_path, &versions)?;
+    match open_shared_store(base_path)? {
+        Some(store) => store.update_json(VERSIONS_FILE, |versions: &mut Vec<SchemaVersion>| {
+            versions.push(version.clone());
+            Ok(())
+        })?,
+        None => MetadataStore::open(base_path)?.insert_version(&version)?,
+    }
     
     Ok(version)
 }
@@ -475,21 +1805,35 @@ pub async fn list_schema_versions(base_path: String) -> Result<Vec<SchemaVersion
     load_versions(&base_path)
 }
 
+/// List the schema versions matching a search, oldest first
+#[tauri::command]
+pub async fn search_schema_versions(base_path: String, query: VersionQuery) -> Result<Vec<SchemaVersion>, String> {
+    find_versions(&base_path, &query)
+}
+
 /// Get a specific schema version by ID
 #[tauri::command]
 pub async fn get_schema_version(base_path: String, version_id: String) -> Result<SchemaVersion, String> {
-    let versions = load_versions(&base_path)?;
-    versions.into_iter()
-        .find(|v| v.id == version_id)
-        .ok_or_else(|| format!("Version not found: {}", version_id))
+    find_version(&base_path, &version_id)
 }
 
 /// Delete a schema version
 #[tauri::command]
 pub async fn delete_schema_version(base_path: String, version_id: String) -> Result<(), String> {
-    let mut versions = load_versions(&base_path)?;
-    versions.retain(|v| v.id != version_id);
-    save_versions(&base_path, &versions)
+    let version = find_version(&base_path, &version_id).ok();
+    let result = match open_shared_store(&base_path)? {
+        Some(store) => store.update_json(VERSIONS_FILE, |versions: &mut Vec<SchemaVersion>| {
+            versions.retain(|v| v.id != version_id);
+            Ok(())
+        }),
+        None => MetadataStore::open(&base_path)?.delete_version(&version_id),
+    };
+    let detail = match &version {
+        Some(version) => format!("{} ({})", version.name, version_id),
+        None => version_id,
+    };
+    audit::record(AuditAction::DeleteVersion, version.as_ref().map(|v| v.connection.as_str()), &detail, &result);
+    result
 }
 
 /// Compare two schema versions
@@ -499,15 +1843,11 @@ pub async fn compare_schema_versions(
     from_version_id: String,
     to_version_id: String,
 ) -> Result<VersionDiff, String> {
-    let versions = load_versions(&base_path)?;
-    
-    let from_version = versions.iter()
-        .find(|v| v.id == from_version_id)
-        .ok_or_else(|| format!("From version not found: {}", from_version_id))?;
+    let from_version = find_version(&base_path, &from_version_id)
+        .map_err(|_| format!("From version not found: {}", from_version_id))?;
     
-    let to_version = versions.iter()
-        .find(|v| v.id == to_version_id)
-        .ok_or_else(|| format!("To version not found: {}", to_version_id))?;
+    let to_version = find_version(&base_path, &to_version_id)
+        .map_err(|_| format!("To version not found: {}", to_version_id))?;
     
     let diff_report = diff_engine::compare_schemas(&to_version.schema, &from_version.schema);
     
@@ -525,20 +1865,327 @@ pub async fn compare_version_with_live(
     version_id: String,
     connection_string: String,
 ) -> Result<DiffReport, String> {
-    let versions = load_versions(&base_path)?;
+    let result = async {
+        let version = find_version(&base_path, &version_id)?;
+        
+        // Get live schema
+        let pool = schemas::connect_to_schema(&default_migrations_dir()?, &connection_string, "compare_version_with_live", None).await?;
+        
+        let live_schema = db_introspect::introspect_schema(&pool)
+            .await
+            .map_err(|e| format!("Introspection failed: {}", e))?;
+        
+        let live_identity = fetch_identity(&pool)
+            .await
+            .map_err(|e| format!("Failed to identify database: {}", e))?;
+        
+        let mut report = diff_engine::compare_schemas(&version.schema, &live_schema);
+        report.warnings.extend(same_database_warning(version.identity.as_ref(), Some(&live_identity)));
+        Ok(report)
+    }.await;
+    audit::record(AuditAction::Diff, Some(&connection_string), &format!("version:{} -> live", version_id), &result);
+    result
+}
+
+// ===================== SAFE MODE =====================
+
+/// Whether safe mode is on
+#[tauri::command]
+pub async fn get_safe_mode() -> Result<bool, String> {
+    safe_mode::load_safe_mode(&default_migrations_dir()?)
+}
+
+/// Turn safe mode on or off.
+///
+/// While it is on, applying, cloning and non-SELECT queries are refused.
+#[tauri::command]
+pub async fn set_safe_mode(enabled: bool) -> Result<(), String> {
+    safe_mode::save_safe_mode(&default_migrations_dir()?, enabled)
+}
+
+// ===================== SCHEMA FILTERS =====================
+
+/// The schema filter saved for a connection; empty reads `public`.
+#[tauri::command]
+pub async fn get_schema_filter(connection_string: String) -> Result<SchemaFilter, String> {
+    schemas::load_schema_filter(&default_migrations_dir()?, &connection_string)
+}
+
+/// Save the schema a connection is introspected, browsed and compared on.
+#[tauri::command]
+pub async fn save_schema_filter(connection_string: String, filter: SchemaFilter) -> Result<(), String> {
+    schemas::save_schema_filter(&default_migrations_dir()?, &connection_string, &filter)
+}
+
+// ===================== SAVED CONNECTIONS =====================
+
+/// List the saved connections, without their passwords
+#[tauri::command]
+pub async fn list_connections() -> Result<Vec<SavedConnection>, String> {
+    saved_connections::list_connections(&default_migrations_dir()?)
+}
+
+/// Save a connection, keeping its password in the OS keychain.
+///
+/// A new connection (empty `id`) is given an id. An empty password removes
+/// the stored one and no password keeps it.
+#[tauri::command]
+pub async fn save_connection(connection: SavedConnection, password: Option<String>) -> Result<SavedConnection, String> {
+    saved_connections::save_connection(&default_migrations_dir()?, connection, password.as_deref())
+}
+
+/// Delete a saved connection and its password
+#[tauri::command]
+pub async fn delete_connection(id: String) -> Result<(), String> {
+    saved_connections::delete_connection(&default_migrations_dir()?, &id)
+}
+
+/// The connection string of a saved connection, with its password from the
+/// OS keychain
+#[tauri::command]
+pub async fn get_saved_connection_string(id: String) -> Result<String, String> {
+    saved_connections::saved_connection_string(&default_migrations_dir()?, &id)
+}
+
+// ===================== CONNECTION POOLS =====================
+
+/// Close the pools kept open for `connection_string`; returns how many
+/// were open.
+#[tauri::command]
+pub async fn close_connection(
+    connection_string: String,
+    pools: tauri::State<'_, PoolManager>,
+) -> Result<usize, String> {
+    Ok(pools.close_connection(&connection_string).await)
+}
+
+/// Get the size and idle timeout of the shared connection pools
+#[tauri::command]
+pub async fn get_pool_settings(base_path: String) -> Result<PoolSettings, String> {
+    db_pool::load_pool_settings(&base_path)
+}
+
+/// Save the pool settings; pools opened from now on use them.
+#[tauri::command]
+pub async fn save_pool_settings(
+    base_path: String,
+    settings: PoolSettings,
+    pools: tauri::State<'_, PoolManager>,
+) -> Result<(), String> {
+    db_pool::save_pool_settings(&base_path, &settings)?;
+    pools.configure(settings);
+    Ok(())
+}
+
+/// Get how often opening a connection is retried
+#[tauri::command]
+pub async fn get_retry_policy(base_path: String) -> Result<RetryPolicy, String> {
+    retry::load_retry_policy(&base_path)
+}
+
+/// Save the retry policy; connections opened from now on use it.
+#[tauri::command]
+pub async fn save_retry_policy(base_path: String, policy: RetryPolicy) -> Result<(), String> {
+    retry::save_retry_policy(&base_path, &policy)?;
+    retry::set_retry_policy(policy);
+    Ok(())
+}
+
+/// Get the webhooks notified of applies and drift
+#[tauri::command]
+pub async fn get_webhooks(base_path: String) -> Result<Vec<Webhook>, String> {
+    webhooks::load_webhooks(&base_path)
+}
+
+#[tauri::command]
+pub async fn save_webhooks(base_path: String, webhooks: Vec<Webhook>) -> Result<(), String> {
+    webhooks::save_webhooks(&base_path, &webhooks)
+}
+
+/// Send a sample notification to `webhook`, whatever its events.
+#[tauri::command]
+pub async fn test_webhook(webhook: Webhook) -> Result<(), String> {
+    let notification = Notification::migration("pgshift-test", "20240101120000__webhook_test", false, None);
+    webhooks::send_webhook(&webhook, &notification).await
+}
+
+// ===================== BACKUP =====================
+
+/// Write snapshots, apply history, schedules, settings and migration folders
+/// of the default migrations directory to one archive at `path`.
+///
+/// Passwords and access keys are left out.
+#[tauri::command]
+pub async fn export_app_data(path: String) -> Result<AppDataSummary, String> {
+    store_backup::export_app_data(&default_migrations_dir()?, &path)
+}
+
+/// Merge an archive written by [`export_app_data`] into the default
+/// migrations directory; entries already there are kept.
+#[tauri::command]
+pub async fn import_app_data(path: String) -> Result<AppDataSummary, String> {
+    store_backup::import_app_data(&default_migrations_dir()?, &path)
+}
+
+// ===================== IGNORE CONFIG =====================
+
+/// Get the ignore rules kept in the base path, to pass as `options.ignore`,
+/// with those of `environment` added when it is given
+#[tauri::command]
+pub async fn get_ignore_config(base_path: String, environment: Option<String>) -> Result<IgnoreConfig, String> {
+    let mut ignore = IgnoreConfig::load(&base_path)?;
+    if let Some(name) = environment {
+        let config = ProjectConfig::load(&default_migrations_dir()?)?;
+        let environment = config.environments.get(&name)
+            .ok_or_else(|| format!("Environment not found: {}", name))?;
+        ignore.merge(environment.ignore.clone());
+    }
+    Ok(ignore)
+}
+
+// ===================== ENVIRONMENTS =====================
+
+/// List the environments of the project, by name
+#[tauri::command]
+pub async fn list_environments() -> Result<BTreeMap<String, Environment>, String> {
+    Ok(ProjectConfig::load(&default_migrations_dir()?)?.environments)
+}
+
+/// Add or replace an environment; its name can then be passed wherever a
+/// connection string is expected
+#[tauri::command]
+pub async fn save_environment(name: String, environment: Environment) -> Result<(), String> {
+    environments::save_environment(&default_migrations_dir()?, &name, environment)
+}
+
+/// Delete an environment
+#[tauri::command]
+pub async fn delete_environment(name: String) -> Result<(), String> {
+    environments::delete_environment(&default_migrations_dir()?, &name)
+}
+
+// ===================== STORAGE BACKEND =====================
+
+/// Get where versions and apply history for a base path are stored
+#[tauri::command]
+pub async fn get_storage_config(base_path: String) -> Result<StorageConfig, String> {
+    storage::load_storage_config(&base_path)
+}
+
+/// Change where versions and apply history for a base path are stored.
+///
+/// Existing documents are not copied to the new backend.
+#[tauri::command]
+pub async fn save_storage_config(base_path: String, config: StorageConfig) -> Result<(), String> {
+    // Fail early on missing credentials instead of on the next save
+    if let StorageConfig::S3(settings) = &config {
+        storage::s3::S3Store::new(settings.clone())?;
+    }
+    storage::save_storage_config(&base_path, &config)
+}
+
+/// Sizes of the snapshots, apply history, schedules, settings and migration
+/// folders of a base path.
+///
+/// Only the local metadata database is measured; documents kept in shared
+/// storage are not.
+#[tauri::command]
+pub async fn storage_report(base_path: String) -> Result<StorageReport, String> {
+    store_compact::storage_report(&base_path)
+}
+
+/// Get how many snapshots and apply records compaction keeps
+#[tauri::command]
+pub async fn get_retention_settings(base_path: String) -> Result<RetentionSettings, String> {
+    store_compact::load_retention_settings(&base_path)
+}
+
+#[tauri::command]
+pub async fn save_retention_settings(base_path: String, retention: RetentionSettings) -> Result<(), String> {
+    store_compact::save_retention_settings(&base_path, &retention)
+}
+
+/// Prune snapshots and apply history to the saved retention settings and
+/// vacuum the metadata database.
+#[tauri::command]
+pub async fn compact_storage(base_path: String) -> Result<CompactResult, String> {
+    let retention = store_compact::load_retention_settings(&base_path)?;
+    store_compact::compact_storage(&base_path, &retention)
+}
+
+// ===================== SCHEDULED COMPARISONS =====================
+
+/// Create or update a scheduled comparison
+#[tauri::command]
+pub async fn save_scheduled_comparison(
+    base_path: String,
+    mut schedule: ScheduledComparison,
+) -> Result<ScheduledComparison, String> {
+    if schedule.interval_minutes == 0 {
+        return Err("Interval must be at least one minute".to_string());
+    }
     
-    let version = versions.iter()
-        .find(|v| v.id == version_id)
-        .ok_or_else(|| format!("Version not found: {}", version_id))?;
+    if schedule.id.is_empty() {
+        schedule.id = uuid::Uuid::new_v4().to_string();
+        schedule.created_at = Utc::now().to_rfc3339();
+        schedule.store_secrets(None)?;
+        schedule_jobs::update_schedules(&base_path, |schedules| {
+            schedules.push(schedule.clone());
+            Ok(())
+        })?;
+    } else {
+        schedule_jobs::update_schedules(&base_path, |schedules| {
+            let existing = schedules.iter_mut()
+                .find(|s| s.id == schedule.id)
+                .ok_or_else(|| format!("Schedule not found: {}", schedule.id))?;
+            schedule.store_secrets(Some(existing))?;
+            *existing = schedule.clone();
+            Ok(())
+        })?;
+    }
     
-    // Get live schema
-    let pool = connect::create_pool(&connection_string)
-        .await
-        .map_err(|e| format!("Failed to connect: {}", e))?;
+    Ok(schedule)
+}
+
+/// List all scheduled comparisons
+#[tauri::command]
+pub async fn list_scheduled_comparisons(base_path: String) -> Result<Vec<ScheduledComparison>, String> {
+    schedule_jobs::load_schedules(&base_path)
+}
+
+/// Delete a scheduled comparison
+#[tauri::command]
+pub async fn delete_scheduled_comparison(base_path: String, schedule_id: String) -> Result<(), String> {
+    schedule_jobs::update_schedules(&base_path, |schedules| {
+        if let Some(schedule) = schedules.iter().find(|s| s.id == schedule_id) {
+            schedule.delete_secrets()?;
+        }
+        schedules.retain(|s| s.id != schedule_id);
+        Ok(())
+    })
+}
+
+/// Run a scheduled comparison immediately, outside its schedule
+#[tauri::command]
+pub async fn run_scheduled_comparison_now(base_path: String, schedule_id: String) -> Result<String, String> {
+    let schedule = schedule_jobs::load_schedules(&base_path)?
+        .into_iter()
+        .find(|s| s.id == schedule_id)
+        .ok_or_else(|| format!("Schedule not found: {}", schedule_id))?;
     
-    let live_schema = db_introspect::introspect_schema(&pool)
-        .await
-        .map_err(|e| format!("Introspection failed: {}", e))?;
+    let ignore = IgnoreConfig::load(&base_path)?;
+    let result = schedule_jobs::run_comparison(&schedule, &ignore).await;
+    
+    schedule_jobs::update_schedules(&base_path, |schedules| {
+        if let Some(stored) = schedules.iter_mut().find(|s| s.id == schedule_id) {
+            stored.last_run_at = Some(Utc::now().to_rfc3339());
+            stored.last_status = Some(match &result {
+                Ok(status) => status.clone(),
+                Err(e) => format!("FAILED: {}", e),
+            });
+        }
+        Ok(())
+    })?;
     
-    Ok(diff_engine::compare_schemas(&version.schema, &live_schema))
+    result
 }
@@ -0,0 +1,84 @@
+//! Features of the server behind a connection.
+//!
+//! Generated SQL has to run on the target, and some statements are only
+//! understood, or only allowed in a transaction block, from a given major
+//! version on. The version is read when the target is introspected and
+//! passed to the diff as [`crate::diff::DiffOptions::target_major_version`].
+
+use serde::{Deserialize, Serialize};
+use sqlx::postgres::PgPool;
+use sqlx::Row;
+
+/// First major version with `CREATE INDEX ... INCLUDE`.
+pub const INDEX_INCLUDE_SINCE: u32 = 11;
+
+/// First major version with `GENERATED ALWAYS AS (...) STORED` columns.
+pub const GENERATED_COLUMNS_SINCE: u32 = 12;
+
+/// First major version running `ALTER TYPE ... ADD VALUE` in a transaction
+/// block.
+pub const TRANSACTIONAL_ADD_VALUE_SINCE: u32 = 12;
+
+/// Version of a server and what it supports.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ServerCapabilities {
+    /// `server_version`, e.g. `16.2 (Debian 16.2-1.pgdg120+2)`.
+    pub server_version: String,
+    /// `server_version_num`, e.g. 160002.
+    pub server_version_num: u32,
+    /// e.g. 16, or 9 for 9.6.
+    pub major_version: u32,
+    pub index_include: bool,
+    pub generated_columns: bool,
+    pub transactional_add_value: bool,
+}
+
+impl ServerCapabilities {
+    pub fn new(server_version: &str, server_version_num: u32) -> Self {
+        let major_version = server_version_num / 10000;
+        Self {
+            server_version: server_version.to_string(),
+            server_version_num,
+            major_version,
+            index_include: major_version >= INDEX_INCLUDE_SINCE,
+            generated_columns: major_version >= GENERATED_COLUMNS_SINCE,
+            transactional_add_value: major_version >= TRANSACTIONAL_ADD_VALUE_SINCE,
+        }
+    }
+}
+
+/// Read the version of the server behind `pool`.
+pub async fn fetch_capabilities(pool: &PgPool) -> Result<ServerCapabilities, sqlx::Error> {
+    let row = sqlx::query(
+        r#"
+        SELECT
+            current_setting('server_version') AS server_version,
+            current_setting('server_version_num')::int AS server_version_num
+        "#
+    )
+    .fetch_one(pool)
+    .await?;
+
+    let server_version: String = row.get("server_version");
+    let server_version_num: i32 = row.get("server_version_num");
+    Ok(ServerCapabilities::new(&server_version, server_version_num.max(0) as u32))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_capabilities() {
+        let old = ServerCapabilities::new("9.6.24", 90624);
+        assert_eq!(old.major_version, 9);
+        assert!(!old.index_include && !old.generated_columns && !old.transactional_add_value);
+
+        let eleven = ServerCapabilities::new("11.22", 110022);
+        assert!(eleven.index_include && !eleven.generated_columns);
+
+        let current = ServerCapabilities::new("16.2 (Debian 16.2-1.pgdg120+2)", 160002);
+        assert_eq!(current.major_version, 16);
+        assert!(current.generated_columns && current.transactional_add_value);
+    }
+}
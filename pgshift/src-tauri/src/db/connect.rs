@@ -1,28 +1,278 @@
 //! PostgreSQL connection management.
 
-use sqlx::postgres::{PgPool, PgPoolOptions};
-use sqlx::Error;
+use std::path::Path;
+use std::str::FromStr;
+use serde::{Deserialize, Serialize};
+use sqlx::postgres::{PgConnectOptions, PgPool, PgPoolOptions};
+use sqlx::{ConnectOptions, Connection, Error};
+use crate::environments;
+use super::saved_connections::percent_encode;
+use super::pool::{keep_token_fresh, PoolManager};
+use super::retry::{with_retry, ConnectError};
+use super::{rds_iam, tunnel};
+
+/// Identifies PGShift sessions in `pg_stat_activity` and server logs.
+pub const APPLICATION_NAME_PREFIX: &str = "pgshift";
+
+/// `application_name` of connections opened for `operation`, e.g.
+/// `pgshift/0.1.0/apply`.
+pub fn application_name(operation: &str) -> String {
+    format!("{}/{}/{}", APPLICATION_NAME_PREFIX, env!("CARGO_PKG_VERSION"), operation)
+}
+
+/// How the server's certificate is checked, as libpq's `sslmode`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum SslMode {
+    Disable,
+    Allow,
+    Prefer,
+    Require,
+    /// Require a certificate signed by the root certificate.
+    VerifyCa,
+    /// As `VerifyCa`, and the certificate must name the host connected to.
+    VerifyFull,
+}
+
+impl SslMode {
+    pub fn name(self) -> &'static str {
+        match self {
+            SslMode::Disable => "disable",
+            SslMode::Allow => "allow",
+            SslMode::Prefer => "prefer",
+            SslMode::Require => "require",
+            SslMode::VerifyCa => "verify-ca",
+            SslMode::VerifyFull => "verify-full",
+        }
+    }
+}
+
+/// TLS settings added to a connection string, for servers that require
+/// `verify-full` with the CA bundle of their provider, or client
+/// certificates.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct TlsOptions {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ssl_mode: Option<SslMode>,
+    /// PEM file of the certificate authorities trusted for the server.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub root_cert: Option<String>,
+    /// PEM files of the client certificate and its key; set both or neither.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub client_cert: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub client_key: Option<String>,
+}
+
+impl TlsOptions {
+    /// The libpq parameters of the options, checking that the files exist.
+    fn params(&self) -> Result<Vec<(&'static str, String)>, String> {
+        if self.client_cert.is_some() != self.client_key.is_some() {
+            return Err("A client certificate needs its key, and a key its certificate".to_string());
+        }
+        let mut params = Vec::new();
+        if let Some(mode) = self.ssl_mode {
+            params.push(("sslmode", mode.name().to_string()));
+        }
+        for (name, path) in [("sslrootcert", &self.root_cert), ("sslcert", &self.client_cert), ("sslkey", &self.client_key)] {
+            if let Some(path) = path {
+                if !Path::new(path).is_file() {
+                    return Err(format!("Certificate file not found: {}", path));
+                }
+                params.push((name, path.clone()));
+            }
+        }
+        Ok(params)
+    }
+
+    /// `connection_string` with these options, replacing any TLS parameters
+    /// it already has. Both `postgres://` URLs and `key=value` strings are
+    /// accepted.
+    pub fn apply(&self, connection_string: &str) -> Result<String, String> {
+        let params = self.params()?;
+        if params.is_empty() {
+            return Ok(connection_string.to_string());
+        }
+        let names: Vec<&str> = params.iter().map(|(name, _)| *name).collect();
+        let connection_string = connection_string.trim();
+
+        if connection_string.contains("://") {
+            let (base, query) = connection_string.split_once('?').unwrap_or((connection_string, ""));
+            let mut pairs: Vec<String> = query.split('&')
+                .filter(|p| !p.is_empty() && !names.iter().any(|name| tls_param_is(p.split('=').next().unwrap_or_default(), name)))
+                .map(str::to_string)
+                .collect();
+            pairs.extend(params.iter().map(|(name, value)| format!("{}={}", name, percent_encode(value))));
+            return Ok(format!("{}?{}", base, pairs.join("&")));
+        }
+
+        let mut conninfo = connection_string.to_string();
+        for (name, value) in &params {
+            conninfo.push_str(&format!(" {}='{}'", name, value.replace('\\', "\\\\").replace('\'', "\\'")));
+        }
+        Ok(conninfo)
+    }
+}
+
+/// Whether the URL parameter `key` is the TLS parameter `name`, under any of
+/// the spellings sqlx accepts.
+fn tls_param_is(key: &str, name: &str) -> bool {
+    match name {
+        "sslmode" => matches!(key, "sslmode" | "ssl-mode"),
+        "sslrootcert" => matches!(key, "sslrootcert" | "ssl-root-cert" | "ssl-ca"),
+        "sslcert" => matches!(key, "sslcert" | "ssl-cert"),
+        "sslkey" => matches!(key, "sslkey" | "ssl-key"),
+        _ => false,
+    }
+}
+
+/// `connection` with `tls` applied, when given.
+pub fn with_tls(connection: &str, tls: Option<&TlsOptions>) -> Result<String, String> {
+    match tls {
+        Some(tls) => tls.apply(&environments::resolve_connection_string(connection)?),
+        None => Ok(connection.to_string()),
+    }
+}
+
+/// How `connection` is recorded wherever it is kept or shown: the name of
+/// an environment as is, a connection string without its password.
+pub fn connection_label(connection: &str) -> String {
+    if environments::is_environment_name(connection) {
+        return connection.trim().to_string();
+    }
+    crate::db::introspect::strip_conninfo_password(connection)
+}
+
+/// Deserialize a connection label, dropping the password of one written
+/// before connections were stored as labels.
+pub fn deserialize_connection_label<'de, D: serde::Deserializer<'de>>(deserializer: D) -> Result<String, D::Error> {
+    let connection = <String as serde::Deserialize>::deserialize(deserializer)?;
+    Ok(connection_label(&connection))
+}
+
+/// Connection options for `connection_string`, or the environment it names,
+/// with the application name of `operation` replacing any set in the string.
+/// RDS IAM authentication gets a new token, and a database behind an SSH
+/// tunnel is connected through its local end.
+async fn connect_options(connection_string: &str, operation: &str) -> Result<PgConnectOptions, Error> {
+    let connection_string = environments::resolve_connection_string(connection_string)
+        .map_err(|e| Error::Configuration(e.into()))?;
+    let connection_string = rds_iam::with_auth_token(&connection_string)
+        .await
+        .map_err(|e| Error::Configuration(e.into()))?;
+    let connection_string = tunnel::through_tunnel(&connection_string)
+        .await
+        .map_err(|e| Error::Configuration(e.into()))?;
+    Ok(PgConnectOptions::from_str(&connection_string)?.application_name(&application_name(operation)))
+}
 
 /// Test a PostgreSQL connection by attempting to connect and executing a simple query.
-pub async fn test_connection(connection_string: &str) -> Result<(), Error> {
+pub async fn test_connection(connection_string: &str) -> Result<(), ConnectError> {
+    with_retry(|| async {
+        let mut conn = connect_options(connection_string, "test_connection").await?
+            .connect()
+            .await?;
+        
+        // Execute a simple query to verify connection
+        let checked = sqlx::query("SELECT 1")
+            .execute(&mut conn)
+            .await;
+        
+        conn.close().await?;
+        checked.map(|_| ())
+    }).await
+}
+
+/// Create a connection pool to the PostgreSQL database for `operation`,
+/// which is reported in the connections' application name.
+///
+/// Under an installed [`PoolManager`] the pool is shared with later calls;
+/// give it back with [`release_pool`](super::pool::release_pool).
+pub async fn create_pool(connection_string: &str, operation: &str) -> Result<PgPool, ConnectError> {
+    with_retry(|| open_pool(connection_string, operation, None)).await
+}
+
+/// Create a connection pool for `operation` whose connections resolve
+/// unqualified names in `schema`, then `public`.
+pub async fn create_pool_in_schema(connection_string: &str, operation: &str, schema: &str) -> Result<PgPool, ConnectError> {
+    with_retry(|| open_pool(connection_string, operation, Some(schema))).await
+}
+
+async fn open_pool(connection_string: &str, operation: &str, schema: Option<&str>) -> Result<PgPool, Error> {
+    let options = async {
+        let options = pool_connect_options(connection_string, operation, schema).await?;
+        // A pool retries refused connections until it times out; a single
+        // connection first fails with the actual error
+        options.connect().await?.close().await?;
+        Ok(options)
+    };
+    // Connections a pool on RDS IAM opens later need a token of their own
+    let refresh = {
+        let (connection_string, operation, schema) = (connection_string.to_string(), operation.to_string(), schema.map(str::to_string));
+        move || {
+            let (connection_string, operation, schema) = (connection_string.clone(), operation.clone(), schema.clone());
+            async move { pool_connect_options(&connection_string, &operation, schema.as_deref()).await }
+        }
+    };
+    if let Some(manager) = PoolManager::installed() {
+        return manager.get(connection_string, schema, operation, options, refresh).await;
+    }
     let pool = PgPoolOptions::new()
-        .max_connections(1)
-        .connect(connection_string)
-        .await?;
-    
-    // Execute a simple query to verify connection
-    sqlx::query("SELECT 1")
-        .execute(&pool)
+        .max_connections(5)
+        .connect_with(options.await?)
         .await?;
-    
-    pool.close().await;
-    Ok(())
+    keep_token_fresh(&pool, connection_string, refresh);
+    Ok(pool)
 }
 
-/// Create a connection pool to the PostgreSQL database.
-pub async fn create_pool(connection_string: &str) -> Result<PgPool, Error> {
-    PgPoolOptions::new()
-        .max_connections(5)
-        .connect(connection_string)
-        .await
+/// Connection options of a pool for `operation`, resolving names in
+/// `schema` when given.
+async fn pool_connect_options(connection_string: &str, operation: &str, schema: Option<&str>) -> Result<PgConnectOptions, Error> {
+    let options = connect_options(connection_string, operation).await?;
+    Ok(match schema {
+        Some(schema) => options.options([("search_path", search_path(schema))]),
+        None => options,
+    })
+}
+
+/// The `search_path` startup option resolving names in `schema`, then
+/// `public`.
+fn search_path(schema: &str) -> String {
+    let search_path = if schema == "public" {
+        "public".to_string()
+    } else {
+        format!("\"{}\",public", schema.replace('"', "\"\""))
+    };
+    // Startup options are split on spaces unless escaped
+    search_path.replace('\\', "\\\\").replace(' ', "\\ ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tls_options() {
+        let cert = std::env::temp_dir().join(format!("pgshift-ca-{}.pem", uuid::Uuid::new_v4()));
+        std::fs::write(&cert, "").unwrap();
+        let cert_path = cert.to_string_lossy().to_string();
+        let tls = TlsOptions { ssl_mode: Some(SslMode::VerifyFull), root_cert: Some(cert_path.clone()), ..Default::default() };
+
+        assert_eq!(
+            tls.apply("postgres://app@db/app?ssl-mode=prefer&connect_timeout=5").unwrap(),
+            format!("postgres://app@db/app?connect_timeout=5&sslmode=verify-full&sslrootcert={}", percent_encode(&cert_path))
+        );
+        assert_eq!(
+            tls.apply("host=db dbname=app").unwrap(),
+            format!("host=db dbname=app sslmode='verify-full' sslrootcert='{}'", cert_path)
+        );
+        assert_eq!(TlsOptions::default().apply("postgres://db/app").unwrap(), "postgres://db/app");
+
+        let missing = TlsOptions { root_cert: Some("/nonexistent/ca.pem".to_string()), ..Default::default() };
+        assert_eq!(missing.apply("postgres://db/app").unwrap_err(), "Certificate file not found: /nonexistent/ca.pem");
+        let no_key = TlsOptions { client_cert: Some(cert_path), ..Default::default() };
+        assert!(no_key.apply("postgres://db/app").is_err());
+
+        std::fs::remove_file(&cert).unwrap();
+    }
 }
@@ -0,0 +1,59 @@
+//! Databases of a server.
+//!
+//! A connection names one database, but users often know only the server.
+//! Connected to any database they may use, usually `postgres`, the others
+//! can be listed to pick from.
+
+use serde::{Deserialize, Serialize};
+use sqlx::postgres::PgPool;
+use sqlx::Row;
+
+/// A database of the server, as listed by `pg_database`.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct DatabaseEntry {
+    pub name: String,
+    pub owner: String,
+    /// From `pg_database_size`; None when the role may not connect to the
+    /// database, as the size is then not readable.
+    pub size_bytes: Option<i64>,
+    /// e.g. `UTF8`.
+    pub encoding: String,
+    /// Whether the current role may connect to it.
+    pub can_connect: bool,
+}
+
+/// List the databases of the server behind `pool`, by name.
+///
+/// Templates and databases not accepting connections are left out. Databases
+/// the role lacks CONNECT on are listed, with `can_connect` false and no size.
+pub async fn list_databases(pool: &PgPool) -> Result<Vec<DatabaseEntry>, sqlx::Error> {
+    let rows = sqlx::query(
+        r#"
+        SELECT
+            d.datname AS name,
+            pg_get_userbyid(d.datdba) AS owner,
+            has_database_privilege(d.oid, 'CONNECT') AS can_connect,
+            CASE
+                WHEN has_database_privilege(d.oid, 'CONNECT') THEN pg_database_size(d.oid)
+            END AS size_bytes,
+            pg_encoding_to_char(d.encoding) AS encoding
+        FROM pg_database d
+        WHERE NOT d.datistemplate
+            AND d.datallowconn
+        ORDER BY d.datname
+        "#
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows
+        .iter()
+        .map(|r| DatabaseEntry {
+            name: r.get("name"),
+            owner: r.get("owner"),
+            size_bytes: r.get("size_bytes"),
+            encoding: r.get("encoding"),
+            can_connect: r.get("can_connect"),
+        })
+        .collect())
+}
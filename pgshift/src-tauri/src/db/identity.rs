@@ -0,0 +1,119 @@
+//! Identification of the database behind a connection.
+//!
+//! Two connection strings can point at the same database (different host
+//! aliases, a pooler in front, an IP instead of a name). Comparing a database
+//! with itself yields a meaningless, and when applied destructive, migration,
+//! so comparisons check identities before diffing.
+
+use serde::{Deserialize, Serialize};
+use sqlx::postgres::PgPool;
+use sqlx::Row;
+
+/// Identity of the database a pool is connected to.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct DatabaseIdentity {
+    /// Cluster system identifier from `pg_control_system()`; None when the
+    /// role may not call it.
+    pub system_identifier: Option<String>,
+    pub database_oid: u32,
+    pub database_name: String,
+    /// Server address as seen by the session; None over a Unix socket.
+    pub server_addr: Option<String>,
+    pub server_port: Option<i32>,
+}
+
+impl DatabaseIdentity {
+    /// Check whether both identities describe the same database.
+    ///
+    /// The system identifier is unique per cluster, so when both sides have it
+    /// the host is irrelevant. Otherwise fall back to address, port and OID.
+    pub fn is_same_database(&self, other: &DatabaseIdentity) -> bool {
+        if self.database_oid != other.database_oid || self.database_name != other.database_name {
+            return false;
+        }
+        match (&self.system_identifier, &other.system_identifier) {
+            (Some(a), Some(b)) => a == b,
+            _ => self.server_addr == other.server_addr && self.server_port == other.server_port,
+        }
+    }
+}
+
+/// Warning to attach to a report when both sides come from the same database.
+pub fn same_database_warning(
+    source: Option<&DatabaseIdentity>,
+    target: Option<&DatabaseIdentity>,
+) -> Option<String> {
+    match (source, target) {
+        (Some(s), Some(t)) if s.is_same_database(t) => Some(format!(
+            "Source and target are the same database ('{}'); applying this migration would \
+             change the database to match an older state of itself",
+            t.database_name
+        )),
+        _ => None,
+    }
+}
+
+/// Fetch the identity of the database behind `pool`.
+pub async fn fetch_identity(pool: &PgPool) -> Result<DatabaseIdentity, sqlx::Error> {
+    let row = sqlx::query(
+        r#"
+        SELECT
+            d.oid::bigint AS database_oid,
+            d.datname AS database_name,
+            host(inet_server_addr()) AS server_addr,
+            inet_server_port() AS server_port
+        FROM pg_database d
+        WHERE d.datname = current_database()
+        "#
+    )
+    .fetch_one(pool)
+    .await?;
+
+    // Requires superuser or an explicit grant; the other fields still identify the database
+    let system_identifier: Option<String> = sqlx::query("SELECT system_identifier::text AS id FROM pg_control_system()")
+        .fetch_optional(pool)
+        .await
+        .ok()
+        .flatten()
+        .map(|r| r.get("id"));
+
+    Ok(DatabaseIdentity {
+        system_identifier,
+        database_oid: row.get::<i64, _>("database_oid") as u32,
+        database_name: row.get("database_name"),
+        server_addr: row.get("server_addr"),
+        server_port: row.get("server_port"),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn create_test_identity(system_identifier: Option<&str>, addr: &str) -> DatabaseIdentity {
+        DatabaseIdentity {
+            system_identifier: system_identifier.map(|s| s.to_string()),
+            database_oid: 16384,
+            database_name: "app".to_string(),
+            server_addr: Some(addr.to_string()),
+            server_port: Some(5432),
+        }
+    }
+
+    #[test]
+    fn test_same_database() {
+        // Same cluster reached through different addresses
+        let a = create_test_identity(Some("7301"), "10.0.0.1");
+        let b = create_test_identity(Some("7301"), "10.0.0.2");
+        assert!(a.is_same_database(&b));
+
+        // Restored copy on another cluster with the same OID
+        let c = create_test_identity(Some("7302"), "10.0.0.1");
+        assert!(!a.is_same_database(&c));
+
+        // Without system identifiers the address decides
+        let d = create_test_identity(None, "10.0.0.1");
+        assert!(a.is_same_database(&d));
+        assert!(!d.is_same_database(&create_test_identity(None, "10.0.0.2")));
+    }
+}
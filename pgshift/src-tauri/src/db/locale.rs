@@ -0,0 +1,195 @@
+//! Encoding, locale and collation settings of a database.
+//!
+//! Two databases with identical schemas can still disagree on how text is
+//! stored and ordered: a different encoding rejects characters, a different
+//! collation sorts and compares strings differently, and a different
+//! collation library version can leave text indexes silently corrupt. Such
+//! differences never show up in a schema diff, so comparisons check them
+//! separately and warn.
+
+use serde::{Deserialize, Serialize};
+use sqlx::postgres::PgPool;
+use sqlx::Row;
+
+/// Text-affecting settings of the database a pool is connected to.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct DatabaseLocale {
+    /// Server encoding, e.g. `UTF8`.
+    pub encoding: String,
+    /// `LC_COLLATE` of the database.
+    pub collate: String,
+    /// `LC_CTYPE` of the database.
+    pub ctype: String,
+    /// Collation provider: `c` (libc), `i` (ICU) or `b` (builtin); None before PostgreSQL 15.
+    #[serde(default)]
+    pub provider: Option<String>,
+    /// ICU or builtin locale when the provider is not libc.
+    #[serde(default)]
+    pub icu_locale: Option<String>,
+    /// Version of the collation library the database currently uses; None
+    /// before PostgreSQL 15 or when the provider does not report one.
+    #[serde(default)]
+    pub collation_version: Option<String>,
+}
+
+fn provider_name(provider: Option<&str>) -> &str {
+    match provider {
+        Some("i") => "ICU",
+        Some("b") => "builtin",
+        _ => "libc",
+    }
+}
+
+/// Warnings about differences between two databases that change how text
+/// behaves; empty when they agree.
+pub fn locale_warnings(source: &DatabaseLocale, target: &DatabaseLocale) -> Vec<String> {
+    let mut warnings = Vec::new();
+
+    if source.encoding != target.encoding {
+        warnings.push(format!(
+            "Source uses encoding {} but target uses {}; text valid in the source may not be \
+             storable in the target",
+            source.encoding, target.encoding
+        ));
+    }
+
+    let source_provider = provider_name(source.provider.as_deref());
+    let target_provider = provider_name(target.provider.as_deref());
+    if source_provider != target_provider {
+        warnings.push(format!(
+            "Source collates text with {} but target uses {}; ORDER BY, text indexes and \
+             unique constraints can behave differently",
+            source_provider, target_provider
+        ));
+    } else if source.icu_locale != target.icu_locale {
+        warnings.push(format!(
+            "Source uses {} locale '{}' but target uses '{}'; ORDER BY, text indexes and \
+             unique constraints can behave differently",
+            source_provider,
+            source.icu_locale.as_deref().unwrap_or_default(),
+            target.icu_locale.as_deref().unwrap_or_default()
+        ));
+    }
+
+    if source.collate != target.collate {
+        warnings.push(format!(
+            "Source has LC_COLLATE '{}' but target has '{}'; ORDER BY, text indexes and \
+             unique constraints can behave differently",
+            source.collate, target.collate
+        ));
+    }
+    if source.ctype != target.ctype {
+        warnings.push(format!(
+            "Source has LC_CTYPE '{}' but target has '{}'; upper(), lower() and pattern \
+             matching can behave differently",
+            source.ctype, target.ctype
+        ));
+    }
+
+    // Versions are only comparable for the same provider
+    if source_provider == target_provider {
+        if let (Some(s), Some(t)) = (&source.collation_version, &target.collation_version) {
+            if s != t {
+                warnings.push(format!(
+                    "Source uses collation version {} but target uses {}; text indexes built \
+                     on one may be out of order on the other and should be rebuilt with REINDEX",
+                    s, t
+                ));
+            }
+        }
+    }
+
+    warnings
+}
+
+/// Fetch the encoding, locale and collation settings of the database behind `pool`.
+pub async fn fetch_locale(pool: &PgPool) -> Result<DatabaseLocale, sqlx::Error> {
+    // Provider and locale columns were added and renamed across versions; read them by name
+    let row = sqlx::query(
+        r#"
+        SELECT
+            pg_encoding_to_char(d.encoding) AS encoding,
+            d.datcollate::text AS collate,
+            d.datctype::text AS ctype,
+            to_jsonb(d) ->> 'datlocprovider' AS provider,
+            COALESCE(to_jsonb(d) ->> 'datlocale', to_jsonb(d) ->> 'daticulocale') AS icu_locale,
+            to_jsonb(d) ->> 'datcollversion' AS recorded_version
+        FROM pg_database d
+        WHERE d.datname = current_database()
+        "#
+    )
+    .fetch_one(pool)
+    .await?;
+
+    // The library version in use can differ from the recorded one after an OS upgrade
+    let actual_version: Option<String> = sqlx::query(
+        "SELECT pg_database_collation_actual_version(oid) AS version FROM pg_database WHERE datname = current_database()"
+    )
+        .fetch_optional(pool)
+        .await
+        .ok()
+        .flatten()
+        .and_then(|r| r.get("version"));
+
+    Ok(DatabaseLocale {
+        encoding: row.get("encoding"),
+        collate: row.get("collate"),
+        ctype: row.get("ctype"),
+        provider: row.get("provider"),
+        icu_locale: row.get("icu_locale"),
+        collation_version: actual_version.or_else(|| row.get("recorded_version")),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn create_test_locale() -> DatabaseLocale {
+        DatabaseLocale {
+            encoding: "UTF8".to_string(),
+            collate: "en_US.UTF-8".to_string(),
+            ctype: "en_US.UTF-8".to_string(),
+            provider: Some("c".to_string()),
+            icu_locale: None,
+            collation_version: Some("2.31".to_string()),
+        }
+    }
+
+    #[test]
+    fn test_identical_locales() {
+        assert!(locale_warnings(&create_test_locale(), &create_test_locale()).is_empty());
+
+        // Servers before 15 report no provider; that is libc too
+        let mut old = create_test_locale();
+        old.provider = None;
+        old.collation_version = None;
+        assert!(locale_warnings(&old, &create_test_locale()).is_empty());
+    }
+
+    #[test]
+    fn test_locale_differences() {
+        let source = create_test_locale();
+
+        let mut target = create_test_locale();
+        target.collate = "C".to_string();
+        let warnings = locale_warnings(&source, &target);
+        assert_eq!(warnings.len(), 1);
+        assert!(warnings[0].contains("LC_COLLATE"));
+
+        let mut target = create_test_locale();
+        target.collation_version = Some("2.36".to_string());
+        let warnings = locale_warnings(&source, &target);
+        assert_eq!(warnings.len(), 1);
+        assert!(warnings[0].contains("REINDEX"));
+
+        // Versions of different providers are not comparable
+        let mut target = create_test_locale();
+        target.provider = Some("i".to_string());
+        target.icu_locale = Some("en-US".to_string());
+        target.collation_version = Some("153.120".to_string());
+        let warnings = locale_warnings(&source, &target);
+        assert_eq!(warnings.len(), 1);
+        assert!(warnings[0].contains("ICU"));
+    }
+}
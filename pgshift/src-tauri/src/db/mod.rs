@@ -1,4 +1,18 @@
 //! Database connection and introspection modules.
 
+pub mod capabilities;
 pub mod connect;
+pub mod databases;
 pub mod introspect;
+pub mod identity;
+pub mod locale;
+pub mod pool;
+pub mod progress;
+pub mod retry;
+pub mod rds_iam;
+pub mod row_counts;
+pub mod saved_connections;
+pub mod schemas;
+pub mod session;
+pub mod sizes;
+pub mod tunnel;
@@ -0,0 +1,341 @@
+//! Connection pools shared between commands.
+//!
+//! Opening a pool costs a TCP and TLS handshake, authentication and, behind
+//! a bastion or with RDS IAM, a tunnel or token on top. Once a
+//! [`PoolManager`] is installed, which the app does at startup, the pools of
+//! [`create_pool`](super::connect::create_pool) are kept and handed out again
+//! to later commands on the same connection, schema and operation. A pool
+//! unused for [`PoolSettings::idle_timeout_secs`], with none of its
+//! connections in use, is closed in the background the next time a pool is
+//! asked for. A pool authenticating with RDS IAM gets a new token
+//! before its current one expires, so every connection it opens presents a
+//! valid one; connections already open stay authenticated.
+//!
+//! Connections are reset when they go back to their pool, so settings a
+//! migration script changes do not leak into the next command.
+
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::{Duration, Instant};
+use serde::{Deserialize, Serialize};
+use sqlx::postgres::{PgConnectOptions, PgPool, PgPoolOptions};
+use sqlx::{Error, Executor};
+use crate::store::MetadataStore;
+use super::rds_iam;
+
+/// Settings key holding the [`PoolSettings`].
+pub const POOL_SETTINGS_SETTING: &str = "pool_settings";
+
+/// IAM tokens are valid for 15 minutes; pools using one get a new one after 10.
+const IAM_TOKEN_REFRESH: Duration = Duration::from_secs(10 * 60);
+
+/// Undo whatever a command changed in its session.
+const RESET_SESSION: &str = "SET SESSION AUTHORIZATION DEFAULT; RESET ALL; UNLISTEN *; \
+                             SELECT pg_advisory_unlock_all(); DISCARD TEMP";
+
+static INSTALLED: OnceLock<PoolManager> = OnceLock::new();
+
+/// Size and lifetime of the shared pools.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PoolSettings {
+    /// Connections each pool may open.
+    pub max_connections: u32,
+    /// Seconds an unused pool, or an idle connection in a pool, is kept.
+    pub idle_timeout_secs: u64,
+}
+
+impl Default for PoolSettings {
+    fn default() -> Self {
+        Self { max_connections: 5, idle_timeout_secs: 300 }
+    }
+}
+
+pub fn load_pool_settings(base_path: &str) -> Result<PoolSettings, String> {
+    Ok(MetadataStore::open(base_path)?
+        .get_setting(POOL_SETTINGS_SETTING)?
+        .unwrap_or_default())
+}
+
+pub fn save_pool_settings(base_path: &str, settings: &PoolSettings) -> Result<(), String> {
+    if settings.max_connections == 0 {
+        return Err("A pool needs at least one connection".to_string());
+    }
+    MetadataStore::open(base_path)?.set_setting(POOL_SETTINGS_SETTING, settings)
+}
+
+/// What a pool is shared by. The operation is part of it so
+/// `pg_stat_activity` still tells which command a connection serves.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct PoolKey {
+    connection: String,
+    schema: Option<String>,
+    operation: String,
+}
+
+struct PooledEntry {
+    pool: PgPool,
+    last_used: Instant,
+}
+
+#[derive(Default)]
+struct PoolManagerState {
+    settings: PoolSettings,
+    pools: HashMap<PoolKey, PooledEntry>,
+}
+
+/// The open pools, by connection, schema and operation.
+#[derive(Clone, Default)]
+pub struct PoolManager {
+    state: Arc<Mutex<PoolManagerState>>,
+}
+
+impl PoolManager {
+    pub fn new(settings: PoolSettings) -> Self {
+        Self { state: Arc::new(Mutex::new(PoolManagerState { settings, pools: HashMap::new() })) }
+    }
+
+    /// Make this the manager [`create_pool`](super::connect::create_pool)
+    /// uses. Only the first call has an effect.
+    pub fn install(&self) {
+        let _ = INSTALLED.set(self.clone());
+    }
+
+    /// The installed manager, if any.
+    pub fn installed() -> Option<&'static PoolManager> {
+        INSTALLED.get()
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, PoolManagerState> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    pub fn settings(&self) -> PoolSettings {
+        self.lock().settings.clone()
+    }
+
+    /// Use `settings` for pools opened from now on.
+    pub fn configure(&self, settings: PoolSettings) {
+        self.lock().settings = settings;
+    }
+
+    /// The pool for `connection`, opened with `options` unless a live one
+    /// is kept. A pool opened on RDS IAM takes the options of `refresh`,
+    /// with a new token, every [`IAM_TOKEN_REFRESH`] while it is open.
+    pub(crate) async fn get<F, R>(
+        &self,
+        connection: &str,
+        schema: Option<&str>,
+        operation: &str,
+        options: impl Future<Output = Result<PgConnectOptions, Error>>,
+        refresh: F,
+    ) -> Result<PgPool, Error>
+    where
+        F: Fn() -> R + Send + 'static,
+        R: Future<Output = Result<PgConnectOptions, Error>> + Send + 'static,
+    {
+        let key = PoolKey {
+            connection: connection.to_string(),
+            schema: schema.map(str::to_string),
+            operation: operation.to_string(),
+        };
+        let (kept, stale, settings) = {
+            let mut state = self.lock();
+            let stale = state.evict_stale();
+            let kept = state.pools.get_mut(&key).map(|entry| {
+                entry.last_used = Instant::now();
+                entry.pool.clone()
+            });
+            (kept, stale, state.settings.clone())
+        };
+        // Closing waits for their connections; the caller need not
+        if !stale.is_empty() {
+            tokio::spawn(close_all(stale));
+        }
+        if let Some(pool) = kept {
+            return Ok(pool);
+        }
+
+        let pool = pool_options(&settings).connect_with(options.await?).await?;
+        // Another command may have opened the same pool meanwhile
+        let kept = {
+            let mut state = self.lock();
+            match state.pools.get(&key) {
+                Some(entry) => Some(entry.pool.clone()),
+                None => {
+                    state.pools.insert(key, PooledEntry { pool: pool.clone(), last_used: Instant::now() });
+                    None
+                }
+            }
+        };
+        match kept {
+            Some(kept) => {
+                pool.close().await;
+                Ok(kept)
+            }
+            None => {
+                keep_token_fresh(&pool, connection, refresh);
+                Ok(pool)
+            }
+        }
+    }
+
+    /// Close every pool of `connection`; returns how many were open.
+    pub async fn close_connection(&self, connection: &str) -> usize {
+        let closed: Vec<PgPool> = {
+            let mut state = self.lock();
+            let keys: Vec<PoolKey> = state.pools.keys().filter(|k| k.connection == connection).cloned().collect();
+            keys.iter().filter_map(|k| state.pools.remove(k)).map(|e| e.pool).collect()
+        };
+        let count = closed.len();
+        close_all(closed).await;
+        count
+    }
+
+    /// Close every pool.
+    pub async fn close_all(&self) {
+        let closed: Vec<PgPool> = self.lock().pools.drain().map(|(_, e)| e.pool).collect();
+        close_all(closed).await;
+    }
+}
+
+impl PoolManagerState {
+    /// Take out pools closed, or unused for too long and with no connection
+    /// checked out; a long command may hold one past the idle timeout.
+    fn evict_stale(&mut self) -> Vec<PgPool> {
+        let now = Instant::now();
+        let idle_timeout = Duration::from_secs(self.settings.idle_timeout_secs);
+        let stale: Vec<PoolKey> = self.pools.iter()
+            .filter(|(_, e)| {
+                e.pool.is_closed()
+                    || (now.duration_since(e.last_used) > idle_timeout && !in_use(&e.pool))
+            })
+            .map(|(k, _)| k.clone())
+            .collect();
+        stale.iter().filter_map(|k| self.pools.remove(k)).map(|e| e.pool).collect()
+    }
+}
+
+/// Whether a connection of `pool` is checked out.
+fn in_use(pool: &PgPool) -> bool {
+    pool.size() > pool.num_idle() as u32
+}
+
+/// When `connection` authenticates with RDS IAM, give `pool` the options of
+/// `refresh`, with a new token, every [`IAM_TOKEN_REFRESH`] until it is
+/// closed.
+pub(crate) fn keep_token_fresh<F, R>(pool: &PgPool, connection: &str, refresh: F)
+where
+    F: Fn() -> R + Send + 'static,
+    R: Future<Output = Result<PgConnectOptions, Error>> + Send + 'static,
+{
+    if rds_iam::uses_iam(connection) {
+        tokio::spawn(refresh_token(pool.clone(), refresh, IAM_TOKEN_REFRESH));
+    }
+}
+
+/// Give `pool` the options of `refresh` every `interval` until it is closed,
+/// so the connections it opens carry a current IAM token. A failed refresh
+/// keeps the previous options and is tried again at the next interval.
+async fn refresh_token<F, R>(pool: PgPool, refresh: F, interval: Duration)
+where
+    F: Fn() -> R,
+    R: Future<Output = Result<PgConnectOptions, Error>>,
+{
+    loop {
+        tokio::time::sleep(interval).await;
+        if pool.is_closed() {
+            return;
+        }
+        match refresh().await {
+            Ok(options) => pool.set_connect_options(options),
+            Err(e) => tracing::warn!("Failed to refresh the RDS IAM token: {}", e),
+        }
+    }
+}
+
+async fn close_all(pools: Vec<PgPool>) {
+    for pool in pools {
+        pool.close().await;
+    }
+}
+
+/// Options of a shared pool: idle connections are closed after the idle
+/// timeout, and connections are reset on release.
+fn pool_options(settings: &PoolSettings) -> PgPoolOptions {
+    PgPoolOptions::new()
+        .max_connections(settings.max_connections.max(1))
+        .idle_timeout(Duration::from_secs(settings.idle_timeout_secs))
+        .after_release(|conn, _| Box::pin(async move {
+            // A connection that cannot be reset is dropped rather than reused
+            Ok(conn.execute(RESET_SESSION).await.is_ok())
+        }))
+}
+
+/// Give back a pool from [`create_pool`](super::connect::create_pool): kept
+/// open for reuse under a manager, closed otherwise.
+pub async fn release_pool(pool: PgPool) {
+    if PoolManager::installed().is_none() {
+        pool.close().await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+    use super::*;
+
+    fn key(operation: &str) -> PoolKey {
+        PoolKey { connection: "postgres://db/app".to_string(), schema: None, operation: operation.to_string() }
+    }
+
+    #[tokio::test]
+    async fn test_evict_stale() {
+        let lazy = || PgPoolOptions::new().connect_lazy("postgres://db/app").unwrap();
+        let now = Instant::now();
+        let mut state = PoolManagerState { settings: PoolSettings { max_connections: 1, idle_timeout_secs: 60 }, pools: HashMap::new() };
+        let closed = lazy();
+        closed.close().await;
+        state.pools.insert(key("fresh"), PooledEntry { pool: lazy(), last_used: now });
+        state.pools.insert(key("idle"), PooledEntry { pool: lazy(), last_used: now - Duration::from_secs(61) });
+        state.pools.insert(key("closed"), PooledEntry { pool: closed, last_used: now });
+
+        assert_eq!(state.evict_stale().len(), 2);
+        assert_eq!(state.pools.keys().cloned().collect::<Vec<_>>(), vec![key("fresh")]);
+    }
+
+    #[cfg(feature = "test-support")]
+    #[tokio::test]
+    async fn test_pool_in_use_is_kept() {
+        let postgres = crate::test_support::TestPostgres::start().unwrap();
+        let db = postgres.create_database().await.unwrap();
+        let pool = PgPoolOptions::new().connect(&db.connection_string).await.unwrap();
+        let mut state = PoolManagerState { settings: PoolSettings { max_connections: 1, idle_timeout_secs: 60 }, pools: HashMap::new() };
+        let idle_since = Instant::now() - Duration::from_secs(61);
+        state.pools.insert(key("busy"), PooledEntry { pool: pool.clone(), last_used: idle_since });
+
+        let conn = pool.acquire().await.unwrap();
+        assert!(state.evict_stale().is_empty());
+        drop(conn);
+        // The connection goes back to the pool in the background
+        while in_use(&pool) {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        assert_eq!(state.evict_stale().len(), 1);
+        pool.close().await;
+    }
+
+    #[tokio::test]
+    async fn test_refresh_token() {
+        let pool = PgPoolOptions::new().connect_lazy("postgres://app:old-token@db/app").unwrap();
+        let refreshing = tokio::spawn(refresh_token(pool.clone(), || async {
+            PgConnectOptions::from_str("postgres://app:new-token@db/app")
+        }, Duration::from_millis(10)));
+
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert!(format!("{:?}", pool.connect_options()).contains("new-token"));
+        // Closing the pool ends the refreshing
+        pool.close().await;
+        tokio::time::timeout(Duration::from_secs(1), refreshing).await.unwrap().unwrap();
+    }
+}
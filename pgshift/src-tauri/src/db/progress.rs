@@ -0,0 +1,190 @@
+//! Progress of long-running operations from the `pg_stat_progress_*` views.
+//!
+//! Index builds, table rewrites (CLUSTER / VACUUM FULL), vacuums, analyzes,
+//! COPY and base backups report how far along they are. The views differ per
+//! operation and per server version, so each is read into one common shape
+//! and views the server does not have yet are skipped.
+
+use serde::{Deserialize, Serialize};
+use sqlx::postgres::PgPool;
+use sqlx::Row;
+
+/// How long a progress query may run; it must never hold up the caller.
+const PROGRESS_QUERY_TIMEOUT: &str = "2s";
+
+/// One running operation.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct OperationProgress {
+    pub pid: i32,
+    /// `create_index`, `cluster`, `vacuum`, `analyze`, `copy` or `basebackup`.
+    pub operation: String,
+    /// Command as reported by the view, e.g. `CREATE INDEX CONCURRENTLY`.
+    pub command: Option<String>,
+    /// Table the operation works on.
+    pub relation: Option<String>,
+    /// Index being built, for `create_index`.
+    pub index_name: Option<String>,
+    pub phase: String,
+    /// Work done and total in the unit of the current phase (blocks, tuples,
+    /// lockers or bytes); None when the phase reports no total.
+    pub done: Option<i64>,
+    pub total: Option<i64>,
+    pub percent: Option<f64>,
+}
+
+/// Per view: the operation name and a query returning pid, command,
+/// relation, index_name, phase, done and total. `$1` optionally filters on a
+/// backend pid.
+const PROGRESS_QUERIES: &[(&str, &str)] = &[
+    ("create_index", r#"
+        SELECT pid, command, relid::regclass::text AS relation,
+            NULLIF(index_relid, 0)::regclass::text AS index_name, phase,
+            CASE WHEN blocks_total > 0 THEN blocks_done
+                 WHEN tuples_total > 0 THEN tuples_done
+                 WHEN lockers_total > 0 THEN lockers_done
+                 WHEN partitions_total > 0 THEN partitions_done END AS done,
+            CASE WHEN blocks_total > 0 THEN blocks_total
+                 WHEN tuples_total > 0 THEN tuples_total
+                 WHEN lockers_total > 0 THEN lockers_total
+                 WHEN partitions_total > 0 THEN partitions_total END AS total
+        FROM pg_stat_progress_create_index
+        WHERE datname = current_database() AND ($1::int4 IS NULL OR pid = $1)
+    "#),
+    ("cluster", r#"
+        SELECT pid, command, relid::regclass::text AS relation, NULL::text AS index_name, phase,
+            heap_blks_scanned AS done, NULLIF(heap_blks_total, 0) AS total
+        FROM pg_stat_progress_cluster
+        WHERE datname = current_database() AND ($1::int4 IS NULL OR pid = $1)
+    "#),
+    ("vacuum", r#"
+        SELECT pid, NULL::text AS command, relid::regclass::text AS relation, NULL::text AS index_name,
+            phase, heap_blks_scanned AS done, NULLIF(heap_blks_total, 0) AS total
+        FROM pg_stat_progress_vacuum
+        WHERE datname = current_database() AND ($1::int4 IS NULL OR pid = $1)
+    "#),
+    ("analyze", r#"
+        SELECT pid, NULL::text AS command, relid::regclass::text AS relation, NULL::text AS index_name,
+            phase, sample_blks_scanned AS done, NULLIF(sample_blks_total, 0) AS total
+        FROM pg_stat_progress_analyze
+        WHERE datname = current_database() AND ($1::int4 IS NULL OR pid = $1)
+    "#),
+    ("copy", r#"
+        SELECT pid, command, NULLIF(relid, 0)::regclass::text AS relation, NULL::text AS index_name,
+            lower(type) AS phase, bytes_processed AS done, NULLIF(bytes_total, 0) AS total
+        FROM pg_stat_progress_copy
+        WHERE datname = current_database() AND ($1::int4 IS NULL OR pid = $1)
+    "#),
+    // Base backups are cluster-wide
+    ("basebackup", r#"
+        SELECT pid, NULL::text AS command, NULL::text AS relation, NULL::text AS index_name,
+            phase, backup_streamed AS done, backup_total AS total
+        FROM pg_stat_progress_basebackup
+        WHERE $1::int4 IS NULL OR pid = $1
+    "#),
+];
+
+/// Running operations in the current database, optionally only those of
+/// backend `pid`.
+pub async fn fetch_operation_progress(
+    pool: &PgPool,
+    pid: Option<i32>,
+) -> Result<Vec<OperationProgress>, sqlx::Error> {
+    let mut conn = pool.acquire().await?;
+    sqlx::query(&format!("SET statement_timeout = '{}'", PROGRESS_QUERY_TIMEOUT))
+        .execute(&mut *conn)
+        .await?;
+
+    let mut operations = Vec::new();
+    let mut result = Ok(());
+    for (operation, sql) in PROGRESS_QUERIES {
+        let rows = match sqlx::query(sql).bind(pid).fetch_all(&mut *conn).await {
+            Ok(rows) => rows,
+            // The view was added in a later server version
+            Err(e) if is_undefined_table(&e) => continue,
+            Err(e) => {
+                result = Err(e);
+                break;
+            }
+        };
+        for row in rows {
+            let done: Option<i64> = row.get("done");
+            let total: Option<i64> = row.get("total");
+            operations.push(OperationProgress {
+                pid: row.get("pid"),
+                operation: operation.to_string(),
+                command: row.get("command"),
+                relation: row.get("relation"),
+                index_name: row.get("index_name"),
+                phase: row.get("phase"),
+                done,
+                total,
+                percent: percent(done, total),
+            });
+        }
+    }
+
+    // The connection goes back to the pool; do not leave the timeout on it
+    let reset = sqlx::query("RESET statement_timeout").execute(&mut *conn).await;
+    result?;
+    reset?;
+    Ok(operations)
+}
+
+fn is_undefined_table(error: &sqlx::Error) -> bool {
+    error.as_database_error()
+        .and_then(|e| e.code())
+        .is_some_and(|code| code == "42P01")
+}
+
+/// Share of `total` that is `done`, in percent.
+pub fn percent(done: Option<i64>, total: Option<i64>) -> Option<f64> {
+    match (done, total) {
+        (Some(done), Some(total)) if total > 0 => Some((done as f64 * 100.0 / total as f64).min(100.0)),
+        _ => None,
+    }
+}
+
+impl OperationProgress {
+    /// One-line description for logs, e.g.
+    /// `CREATE INDEX CONCURRENTLY orders_customer_idx: building index: scanning table (42.0%)`.
+    pub fn summary(&self) -> String {
+        let command = self.command.clone().unwrap_or_else(|| self.operation.to_uppercase());
+        let object = self.index_name.as_ref().or(self.relation.as_ref());
+        let mut summary = match object {
+            Some(object) => format!("{} {}: {}", command, object, self.phase),
+            None => format!("{}: {}", command, self.phase),
+        };
+        if let Some(percent) = self.percent {
+            summary.push_str(&format!(" ({:.1}%)", percent));
+        }
+        summary
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_percent_and_summary() {
+        assert_eq!(percent(Some(50), Some(200)), Some(25.0));
+        assert_eq!(percent(Some(5), Some(0)), None);
+        assert_eq!(percent(Some(5), None), None);
+
+        let progress = OperationProgress {
+            pid: 42,
+            operation: "create_index".to_string(),
+            command: Some("CREATE INDEX CONCURRENTLY".to_string()),
+            relation: Some("orders".to_string()),
+            index_name: Some("orders_customer_idx".to_string()),
+            phase: "building index: scanning table".to_string(),
+            done: Some(420),
+            total: Some(1000),
+            percent: percent(Some(420), Some(1000)),
+        };
+        assert_eq!(
+            progress.summary(),
+            "CREATE INDEX CONCURRENTLY orders_customer_idx: building index: scanning table (42.0%)"
+        );
+    }
+}
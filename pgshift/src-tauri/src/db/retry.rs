@@ -0,0 +1,233 @@
+//! Retrying connections, and telling why one failed.
+//!
+//! A database that is restarting, failing over or briefly out of
+//! connections refuses or times out for a few seconds; opening a connection
+//! is retried with exponential backoff per the [`RetryPolicy`]. Failures that
+//! a retry cannot fix (a wrong password, an unknown host, a certificate that
+//! does not verify) fail at once, with a [`ConnectErrorKind`] and a hint the
+//! UI can show instead of the driver's message alone.
+
+use std::future::Future;
+use std::sync::RwLock;
+use std::time::Duration;
+use serde::{Deserialize, Serialize};
+use sqlx::Error;
+use crate::store::MetadataStore;
+
+/// Settings key holding the [`RetryPolicy`].
+pub const RETRY_SETTING: &str = "connection_retry";
+
+static POLICY: RwLock<RetryPolicy> = RwLock::new(RetryPolicy::DEFAULT);
+
+/// How often, and how patiently, opening a connection is retried.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RetryPolicy {
+    /// Attempts in all, the first included; 1 disables retries.
+    pub attempts: u32,
+    /// Wait before the second attempt, doubled before each next one.
+    pub initial_delay_ms: u64,
+    pub max_delay_ms: u64,
+}
+
+impl RetryPolicy {
+    pub const DEFAULT: RetryPolicy = RetryPolicy { attempts: 3, initial_delay_ms: 250, max_delay_ms: 4000 };
+
+    /// Wait before attempt `attempt` (counting from 2).
+    fn delay(&self, attempt: u32) -> Duration {
+        let factor = 2u64.saturating_pow(attempt.saturating_sub(2));
+        Duration::from_millis(self.initial_delay_ms.saturating_mul(factor).min(self.max_delay_ms))
+    }
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+/// The policy connections are opened with.
+pub fn retry_policy() -> RetryPolicy {
+    POLICY.read().map(|p| p.clone()).unwrap_or_default()
+}
+
+/// Open connections with `policy` from now on.
+pub fn set_retry_policy(policy: RetryPolicy) {
+    if let Ok(mut current) = POLICY.write() {
+        *current = policy;
+    }
+}
+
+pub fn load_retry_policy(base_path: &str) -> Result<RetryPolicy, String> {
+    Ok(MetadataStore::open(base_path)?
+        .get_setting(RETRY_SETTING)?
+        .unwrap_or_default())
+}
+
+pub fn save_retry_policy(base_path: &str, policy: &RetryPolicy) -> Result<(), String> {
+    if policy.attempts == 0 {
+        return Err("At least one connection attempt is needed".to_string());
+    }
+    MetadataStore::open(base_path)?.set_setting(RETRY_SETTING, policy)
+}
+
+/// Why a connection could not be opened.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConnectErrorKind {
+    /// The host name does not resolve.
+    Dns,
+    /// Wrong user or password, or no `pg_hba.conf` entry allows the login.
+    Auth,
+    /// The TLS handshake or certificate verification failed.
+    Tls,
+    Timeout,
+    /// Nothing listens on the host and port.
+    Refused,
+    /// The database does not exist.
+    UnknownDatabase,
+    /// The server is starting, shutting down or out of connection slots.
+    Unavailable,
+    /// The connection string, environment, tunnel or token is invalid.
+    Configuration,
+    Other,
+}
+
+impl ConnectErrorKind {
+    /// Whether trying again later may succeed.
+    pub fn is_transient(self) -> bool {
+        matches!(self, ConnectErrorKind::Timeout | ConnectErrorKind::Refused | ConnectErrorKind::Unavailable)
+    }
+
+    fn hint(self) -> &'static str {
+        match self {
+            ConnectErrorKind::Dns => "Check the host name; it could not be resolved",
+            ConnectErrorKind::Auth => "Check the user and password, and that pg_hba.conf allows this login",
+            ConnectErrorKind::Tls => "Check the SSL mode and certificates; the server may require or not support TLS",
+            ConnectErrorKind::Timeout => "The server did not answer in time; check the host, port and firewall",
+            ConnectErrorKind::Refused => "Nothing accepts connections on this host and port; check that the server is running",
+            ConnectErrorKind::UnknownDatabase => "The database does not exist; check its name",
+            ConnectErrorKind::Unavailable => "The server is starting up, shutting down or out of connections; try again shortly",
+            ConnectErrorKind::Configuration => "Check the connection string",
+            ConnectErrorKind::Other => "",
+        }
+    }
+}
+
+/// A failed connection, classified.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ConnectError {
+    pub kind: ConnectErrorKind,
+    /// The driver's message.
+    pub message: String,
+    /// What to check, for the user.
+    pub hint: String,
+    pub attempts: u32,
+}
+
+impl ConnectError {
+    pub fn new(error: &Error, attempts: u32) -> Self {
+        let kind = classify(error);
+        Self { kind, message: error.to_string(), hint: kind.hint().to_string(), attempts }
+    }
+
+    /// An invalid connection string or option.
+    pub fn configuration(message: impl Into<String>) -> Self {
+        let kind = ConnectErrorKind::Configuration;
+        Self { kind, message: message.into(), hint: kind.hint().to_string(), attempts: 0 }
+    }
+}
+
+impl std::fmt::Display for ConnectError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.message)?;
+        if !self.hint.is_empty() {
+            write!(f, ". {}", self.hint)?;
+        }
+        if self.attempts > 1 {
+            write!(f, " (after {} attempts)", self.attempts)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for ConnectError {}
+
+/// The kind of a connection failure.
+pub fn classify(error: &Error) -> ConnectErrorKind {
+    match error {
+        Error::Configuration(_) => ConnectErrorKind::Configuration,
+        Error::Tls(_) => ConnectErrorKind::Tls,
+        Error::PoolTimedOut => ConnectErrorKind::Timeout,
+        Error::Io(e) => match e.kind() {
+            std::io::ErrorKind::ConnectionRefused => ConnectErrorKind::Refused,
+            std::io::ErrorKind::TimedOut => ConnectErrorKind::Timeout,
+            _ => {
+                let message = e.to_string().to_lowercase();
+                let dns = ["failed to lookup address", "name or service not known", "nodename nor servname", "no such host", "temporary failure in name resolution"];
+                if dns.iter().any(|m| message.contains(m)) {
+                    ConnectErrorKind::Dns
+                } else if message.contains("certificate") || message.contains("tls") {
+                    ConnectErrorKind::Tls
+                } else {
+                    ConnectErrorKind::Other
+                }
+            }
+        },
+        Error::Database(e) => match e.code().as_deref() {
+            // invalid_password, invalid_authorization_specification
+            Some("28P01" | "28000") => ConnectErrorKind::Auth,
+            // invalid_catalog_name
+            Some("3D000") => ConnectErrorKind::UnknownDatabase,
+            // too_many_connections, cannot_connect_now, admin_shutdown
+            Some("53300" | "57P03" | "57P01") => ConnectErrorKind::Unavailable,
+            _ => ConnectErrorKind::Other,
+        },
+        _ => ConnectErrorKind::Other,
+    }
+}
+
+/// Run `connect` until it succeeds, fails for good or runs out of the
+/// attempts of the current [`RetryPolicy`].
+pub async fn with_retry<T, F, Fut>(mut connect: F) -> Result<T, ConnectError>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T, Error>>,
+{
+    let policy = retry_policy();
+    let mut attempt = 1;
+    loop {
+        match connect().await {
+            Ok(value) => return Ok(value),
+            Err(e) if attempt < policy.attempts && classify(&e).is_transient() => {
+                attempt += 1;
+                tokio::time::sleep(policy.delay(attempt)).await;
+            }
+            Err(e) => return Err(ConnectError::new(&e, attempt)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_classify() {
+        let refused = Error::Io(std::io::Error::new(std::io::ErrorKind::ConnectionRefused, "refused"));
+        assert_eq!(classify(&refused), ConnectErrorKind::Refused);
+        let dns = Error::Io(std::io::Error::other("failed to lookup address information: Name or service not known"));
+        assert_eq!(classify(&dns), ConnectErrorKind::Dns);
+        assert_eq!(classify(&Error::PoolTimedOut), ConnectErrorKind::Timeout);
+        assert_eq!(classify(&Error::Tls("server does not support TLS".into())), ConnectErrorKind::Tls);
+
+        let error = ConnectError::new(&refused, 3);
+        assert_eq!(error.to_string(), format!("{}. {} (after 3 attempts)", refused, ConnectErrorKind::Refused.hint()));
+    }
+
+    #[test]
+    fn test_backoff() {
+        let policy = RetryPolicy { attempts: 5, initial_delay_ms: 100, max_delay_ms: 300 };
+        let delays: Vec<u128> = (2..=5).map(|attempt| policy.delay(attempt).as_millis()).collect();
+        assert_eq!(delays, [100, 200, 300, 300]);
+    }
+}
@@ -0,0 +1,67 @@
+//! Rows held by tables and columns a migration drops.
+//!
+//! See [`crate::diff::data_loss`]. Estimates come from `pg_class.reltuples`
+//! and `pg_stats.null_frac` and cost nothing; exact counts scan the table.
+
+use sqlx::postgres::PgPool;
+use sqlx::Row;
+use crate::diff::data_loss::{dropped_data, DataLoss};
+use crate::diff::DiffReport;
+
+/// Count the rows every dropped table and column of `report` holds on the
+/// database of `pool`, and attach them to its items.
+///
+/// Without `exact`, the planner statistics are used, and tables never
+/// analyzed are counted. Tables missing from the database are skipped.
+pub async fn attach_data_loss(pool: &PgPool, report: &mut DiffReport, exact: bool) -> Result<(), sqlx::Error> {
+    for item in &mut report.items {
+        let Some((table, column)) = dropped_data(item) else {
+            continue;
+        };
+        item.data_loss = count_rows(pool, table, column, exact).await?;
+    }
+    Ok(())
+}
+
+/// Rows of `table` in the current schema and, with `column`, rows with a
+/// value in it; None when the table does not exist.
+pub async fn count_rows(pool: &PgPool, table: &str, column: Option<&str>, exact: bool) -> Result<Option<DataLoss>, sqlx::Error> {
+    // reltuples is -1 (before 14: 0) until the table is first analyzed or vacuumed
+    let statistics = sqlx::query(
+        r#"
+        SELECT
+            CASE WHEN c.reltuples > 0 OR c.relpages > 0 THEN GREATEST(c.reltuples, 0)::float8 END AS row_estimate,
+            s.null_frac::float8 AS null_frac
+        FROM pg_class c
+        JOIN pg_namespace n ON n.oid = c.relnamespace
+        LEFT JOIN pg_stats s ON s.schemaname = n.nspname AND s.tablename = c.relname AND s.attname = $2
+        WHERE n.nspname = current_schema() AND c.relname = $1 AND c.relkind IN ('r', 'p')
+        "#
+    )
+    .bind(table)
+    .bind(column)
+    .fetch_optional(pool)
+    .await?;
+    let Some(statistics) = statistics else {
+        return Ok(None);
+    };
+
+    let row_estimate: Option<f64> = statistics.get("row_estimate");
+    let null_frac: Option<f64> = statistics.get("null_frac");
+    if let (false, Some(rows)) = (exact, row_estimate) {
+        // Without column statistics, every row is assumed to hold a value
+        let non_null_rows = column.map(|_| (rows * (1.0 - null_frac.unwrap_or(0.0))).round() as i64);
+        return Ok(Some(DataLoss { rows: rows.round() as i64, non_null_rows, estimated: true }));
+    }
+
+    let sql = match column {
+        Some(column) => format!("SELECT count(*) AS rows, count({}) AS non_null_rows FROM {}", quote(column), quote(table)),
+        None => format!("SELECT count(*) AS rows, NULL::int8 AS non_null_rows FROM {}", quote(table)),
+    };
+    let row = sqlx::query(&sql).fetch_one(pool).await?;
+    Ok(Some(DataLoss { rows: row.get("rows"), non_null_rows: row.get("non_null_rows"), estimated: false }))
+}
+
+fn quote(name: &str) -> String {
+    format!("\"{}\"", name.replace('"', "\"\""))
+}
@@ -0,0 +1,152 @@
+//! Settings of the session a migration runs in.
+//!
+//! The same migration can behave differently depending on the server version,
+//! the role it runs as, the search_path that resolves unqualified names and
+//! the timeouts that cancel long statements. A snapshot of the session is kept
+//! with each apply so a failure can be reproduced in the same context.
+
+use std::collections::BTreeMap;
+use serde::{Deserialize, Serialize};
+use sqlx::postgres::PgConnection;
+use sqlx::Row;
+
+/// Session settings at the start of an apply.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct SessionSettings {
+    pub backend_pid: i32,
+    /// `server_version`, e.g. `16.2 (Debian 16.2-1.pgdg120+2)`.
+    pub server_version: String,
+    pub session_user: String,
+    /// Role privileges are checked against; differs from `session_user`
+    /// after `SET ROLE` or a role-level `SET role` default.
+    pub current_user: String,
+    pub search_path: String,
+    pub statement_timeout: String,
+    pub lock_timeout: String,
+    pub idle_in_transaction_session_timeout: String,
+    /// Every setting a session may change whose value comes from the
+    /// configuration file, database, role or client rather than the built-in
+    /// default, by name.
+    #[serde(default)]
+    pub settings: BTreeMap<String, String>,
+}
+
+/// Read the settings of the session behind `conn`.
+pub async fn fetch_session_settings(conn: &mut PgConnection) -> Result<SessionSettings, sqlx::Error> {
+    let row = sqlx::query(
+        r#"
+        SELECT
+            pg_backend_pid() AS backend_pid,
+            current_setting('server_version') AS server_version,
+            session_user::text AS session_user,
+            current_user::text AS current_user,
+            current_setting('search_path') AS search_path,
+            current_setting('statement_timeout') AS statement_timeout,
+            current_setting('lock_timeout') AS lock_timeout,
+            current_setting('idle_in_transaction_session_timeout') AS idle_in_transaction_session_timeout
+        "#
+    )
+    .fetch_one(&mut *conn)
+    .await?;
+
+    let settings = sqlx::query(
+        r#"
+        SELECT name, current_setting(name) AS value
+        FROM pg_settings
+        WHERE source NOT IN ('default', 'override')
+            AND context IN ('user', 'superuser')
+        ORDER BY name
+        "#
+    )
+    .fetch_all(&mut *conn)
+    .await?
+    .into_iter()
+    .map(|r| (r.get("name"), r.get("value")))
+    .collect();
+
+    Ok(SessionSettings {
+        backend_pid: row.get("backend_pid"),
+        server_version: row.get("server_version"),
+        session_user: row.get("session_user"),
+        current_user: row.get("current_user"),
+        search_path: row.get("search_path"),
+        statement_timeout: row.get("statement_timeout"),
+        lock_timeout: row.get("lock_timeout"),
+        idle_in_transaction_session_timeout: row.get("idle_in_transaction_session_timeout"),
+        settings,
+    })
+}
+
+impl SessionSettings {
+    /// One-line description for logs.
+    pub fn summary(&self) -> String {
+        format!(
+            "PostgreSQL {} as {}, search_path {}, statement_timeout {}, lock_timeout {}",
+            self.server_version, self.current_user, self.search_path, self.statement_timeout, self.lock_timeout
+        )
+    }
+
+    /// Statements that put another session into this context, to run before
+    /// replaying a migration by hand.
+    ///
+    /// Values go through `set_config` like pg_dump does, so lists such as
+    /// search_path keep their meaning.
+    pub fn reproduction_sql(&self) -> String {
+        let mut statements = Vec::new();
+        if self.current_user != self.session_user {
+            statements.push(format!("SET ROLE \"{}\";", self.current_user.replace('"', "\"\"")));
+        }
+
+        let named = [
+            ("search_path", &self.search_path),
+            ("statement_timeout", &self.statement_timeout),
+            ("lock_timeout", &self.lock_timeout),
+            ("idle_in_transaction_session_timeout", &self.idle_in_transaction_session_timeout),
+        ];
+        let others = self.settings.iter()
+            .map(|(name, value)| (name.as_str(), value))
+            .filter(|(name, _)| *name != "role" && !named.iter().any(|(n, _)| n == name));
+        for (name, value) in named.into_iter().chain(others) {
+            statements.push(format!(
+                "SELECT pg_catalog.set_config('{}', '{}', false);",
+                name,
+                value.replace('\'', "''")
+            ));
+        }
+        statements.join("\n")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reproduction_sql() {
+        let session = SessionSettings {
+            backend_pid: 4242,
+            server_version: "16.2".to_string(),
+            session_user: "deploy".to_string(),
+            current_user: "app_owner".to_string(),
+            search_path: "\"$user\", public".to_string(),
+            statement_timeout: "30s".to_string(),
+            lock_timeout: "0".to_string(),
+            idle_in_transaction_session_timeout: "0".to_string(),
+            settings: BTreeMap::from([
+                ("application_name".to_string(), "pgshift's apply".to_string()),
+                ("role".to_string(), "app_owner".to_string()),
+                ("statement_timeout".to_string(), "30s".to_string()),
+            ]),
+        };
+
+        assert_eq!(
+            session.reproduction_sql(),
+            "SET ROLE \"app_owner\";\n\
+             SELECT pg_catalog.set_config('search_path', '\"$user\", public', false);\n\
+             SELECT pg_catalog.set_config('statement_timeout', '30s', false);\n\
+             SELECT pg_catalog.set_config('lock_timeout', '0', false);\n\
+             SELECT pg_catalog.set_config('idle_in_transaction_session_timeout', '0', false);\n\
+             SELECT pg_catalog.set_config('application_name', 'pgshift''s apply', false);"
+        );
+    }
+}
@@ -0,0 +1,120 @@
+//! On-disk sizes of tables and indexes.
+//!
+//! Rewriting a table or building an index needs free space on the target's
+//! volume, and a migration that runs out of it fails halfway through after
+//! holding locks for a long time. The current sizes let the plan estimate
+//! that space up front (see [`crate::diff::disk`]).
+
+use std::collections::HashMap;
+use serde::{Deserialize, Serialize};
+use sqlx::postgres::PgPool;
+use sqlx::Row;
+
+/// Sizes and statistics of one table.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct TableSize {
+    /// Heap, TOAST and free space map, from `pg_table_size`.
+    pub table_bytes: i64,
+    /// All indexes together, from `pg_indexes_size`.
+    pub indexes_bytes: i64,
+    /// Size of each index by name.
+    #[serde(default)]
+    pub index_bytes: HashMap<String, i64>,
+    /// Planner row estimate; None when the table was never analyzed.
+    #[serde(default)]
+    pub row_estimate: Option<f64>,
+    /// Average stored width of each column in bytes, from `pg_stats` or the
+    /// type's fixed length.
+    #[serde(default)]
+    pub column_widths: HashMap<String, i64>,
+}
+
+/// Sizes of the tables of a database, by table name.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct RelationSizes {
+    pub tables: HashMap<String, TableSize>,
+}
+
+/// Fetch the sizes of the tables and indexes in the current schema.
+pub async fn fetch_relation_sizes(pool: &PgPool) -> Result<RelationSizes, sqlx::Error> {
+    let mut sizes = RelationSizes::default();
+
+    // reltuples is -1 (before 14: 0) until the table is first analyzed or vacuumed
+    let rows = sqlx::query(
+        r#"
+        SELECT
+            c.relname AS table_name,
+            pg_table_size(c.oid) AS table_bytes,
+            pg_indexes_size(c.oid) AS indexes_bytes,
+            CASE
+                WHEN c.reltuples > 0 OR c.relpages > 0 THEN GREATEST(c.reltuples, 0)::float8
+            END AS row_estimate
+        FROM pg_class c
+        JOIN pg_namespace n ON n.oid = c.relnamespace
+        WHERE n.nspname = current_schema()
+            AND c.relkind IN ('r', 'p', 'm')
+        "#
+    )
+    .fetch_all(pool)
+    .await?;
+
+    for row in rows {
+        sizes.tables.insert(row.get("table_name"), TableSize {
+            table_bytes: row.get("table_bytes"),
+            indexes_bytes: row.get("indexes_bytes"),
+            row_estimate: row.get("row_estimate"),
+            ..Default::default()
+        });
+    }
+
+    let rows = sqlx::query(
+        r#"
+        SELECT t.relname AS table_name, i.relname AS index_name, pg_relation_size(i.oid) AS index_bytes
+        FROM pg_index x
+        JOIN pg_class i ON i.oid = x.indexrelid
+        JOIN pg_class t ON t.oid = x.indrelid
+        JOIN pg_namespace n ON n.oid = t.relnamespace
+        WHERE n.nspname = current_schema()
+        "#
+    )
+    .fetch_all(pool)
+    .await?;
+
+    for row in rows {
+        let table_name: String = row.get("table_name");
+        if let Some(table) = sizes.tables.get_mut(&table_name) {
+            table.index_bytes.insert(row.get("index_name"), row.get("index_bytes"));
+        }
+    }
+
+    // Variable-length columns without statistics fall back to a guess
+    let rows = sqlx::query(
+        r#"
+        SELECT
+            c.relname AS table_name,
+            a.attname AS column_name,
+            COALESCE(s.avg_width, CASE WHEN t.typlen > 0 THEN t.typlen END, 32)::int8 AS width
+        FROM pg_attribute a
+        JOIN pg_class c ON c.oid = a.attrelid
+        JOIN pg_namespace n ON n.oid = c.relnamespace
+        JOIN pg_type t ON t.oid = a.atttypid
+        LEFT JOIN pg_stats s
+            ON s.schemaname = n.nspname AND s.tablename = c.relname AND s.attname = a.attname
+        WHERE n.nspname = current_schema()
+            AND c.relkind IN ('r', 'p', 'm')
+            AND a.attnum > 0
+            AND NOT a.attisdropped
+        "#
+    )
+    .fetch_all(pool)
+    .await?;
+
+    for row in rows {
+        let table_name: String = row.get("table_name");
+        if let Some(table) = sizes.tables.get_mut(&table_name) {
+            table.column_widths.insert(row.get("column_name"), row.get("width"));
+        }
+    }
+
+    Ok(sizes)
+}
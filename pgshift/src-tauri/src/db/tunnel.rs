@@ -0,0 +1,279 @@
+//! SSH tunnels to databases behind a bastion host.
+//!
+//! A connection string with `sshhost` (and optionally `sshport`, `sshuser`
+//! and `sshkey`) parameters is connected through a local port forwarded by
+//! the system `ssh` client, which brings its configuration, known hosts and
+//! agent along. Tunnels stay open for the life of the process and are shared
+//! by every connection to the same database through the same bastion.
+
+use std::collections::BTreeMap;
+use std::io::Read;
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::process::{Child, Command, Stdio};
+use std::sync::Mutex;
+use std::time::Duration;
+use serde::{Deserialize, Serialize};
+use crate::apply::backup::percent_decode;
+use super::saved_connections::percent_encode;
+
+/// How long `ssh` gets to authenticate and start forwarding.
+const TUNNEL_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Open tunnels by [`TunnelKey`].
+static TUNNELS: Mutex<BTreeMap<String, SshTunnel>> = Mutex::new(BTreeMap::new());
+
+/// The bastion a database is reached through. Without `identity_file`,
+/// keys come from the SSH agent and `~/.ssh/config`.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SshTunnelOptions {
+    pub host: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub port: Option<u16>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub user: Option<String>,
+    /// Private key file, as `ssh -i`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub identity_file: Option<String>,
+}
+
+impl SshTunnelOptions {
+    /// `connection_string` with the tunnel parameters of these options.
+    pub fn apply(&self, connection_string: &str) -> Result<String, String> {
+        let connection_string = connection_string.trim();
+        if !connection_string.contains("://") {
+            return Err("SSH tunnels need a postgres:// connection string".to_string());
+        }
+        let mut params = vec![format!("sshhost={}", percent_encode(&self.host))];
+        if let Some(port) = self.port {
+            params.push(format!("sshport={}", port));
+        }
+        if let Some(user) = &self.user {
+            params.push(format!("sshuser={}", percent_encode(user)));
+        }
+        if let Some(identity_file) = &self.identity_file {
+            params.push(format!("sshkey={}", percent_encode(identity_file)));
+        }
+        let separator = if connection_string.contains('?') { '&' } else { '?' };
+        Ok(format!("{}{}{}", connection_string, separator, params.join("&")))
+    }
+}
+
+/// A running `ssh -L`, killed when dropped.
+struct SshTunnel {
+    child: Child,
+    local_port: u16,
+}
+
+impl Drop for SshTunnel {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+/// A connection string split into its tunnel and what remains of it.
+#[derive(Debug, PartialEq)]
+struct TunnelTarget {
+    ssh: SshTunnelOptions,
+    /// Database host and port, as seen from the bastion.
+    host: String,
+    port: u16,
+    /// The connection string without tunnel parameters, and the host and
+    /// port to replace with the local end of the tunnel.
+    before_host: String,
+    after_port: String,
+}
+
+impl TunnelTarget {
+    fn key(&self) -> String {
+        format!(
+            "{}@{}:{} {} -> {}:{}",
+            self.ssh.user.as_deref().unwrap_or_default(),
+            self.ssh.host,
+            self.ssh.port.unwrap_or(22),
+            self.ssh.identity_file.as_deref().unwrap_or_default(),
+            self.host,
+            self.port
+        )
+    }
+
+    fn through(&self, local_port: u16) -> String {
+        format!("{}127.0.0.1:{}{}", self.before_host, local_port, self.after_port)
+    }
+}
+
+/// The tunnel `connection_string` asks for; None when it has no `sshhost`.
+fn parse_tunnel(connection_string: &str) -> Result<Option<TunnelTarget>, String> {
+    let connection_string = connection_string.trim();
+    let Some((scheme, rest)) = connection_string.split_once("://") else {
+        if connection_string.split_whitespace().any(|p| p.starts_with("sshhost")) {
+            return Err("SSH tunnels need a postgres:// connection string".to_string());
+        }
+        return Ok(None);
+    };
+    let (rest, query) = rest.split_once('?').unwrap_or((rest, ""));
+
+    let mut ssh = SshTunnelOptions::default();
+    let mut params = Vec::new();
+    for param in query.split('&').filter(|p| !p.is_empty()) {
+        let (key, value) = param.split_once('=').unwrap_or((param, ""));
+        let value = percent_decode(value);
+        match key {
+            "sshhost" => ssh.host = value,
+            "sshport" => ssh.port = Some(value.parse().map_err(|_| format!("Invalid sshport: {}", value))?),
+            "sshuser" => ssh.user = Some(value),
+            "sshkey" => ssh.identity_file = Some(value),
+            _ => params.push(param),
+        }
+    }
+    // ssh would read them as options, e.g. -oProxyCommand=...
+    for (name, value) in [("sshhost", Some(&ssh.host)), ("sshuser", ssh.user.as_ref())] {
+        if value.is_some_and(|v| v.starts_with('-')) {
+            return Err(format!("Invalid {}: it must not start with '-'", name));
+        }
+    }
+    if ssh.host.is_empty() {
+        if ssh != SshTunnelOptions::default() {
+            return Err("SSH tunnel parameters need an sshhost".to_string());
+        }
+        return Ok(None);
+    }
+
+    let (authority, path) = match rest.find('/') {
+        Some(i) => rest.split_at(i),
+        None => (rest, ""),
+    };
+    let (userinfo, host_port) = match authority.rsplit_once('@') {
+        Some((userinfo, host_port)) => (format!("{}@", userinfo), host_port),
+        None => (String::new(), authority),
+    };
+    if host_port.contains(',') {
+        return Err("SSH tunnels need a connection string with a single host".to_string());
+    }
+    let (host, port) = match host_port.rsplit_once(':').filter(|(_, port)| !port.contains(']')) {
+        Some((host, port)) => (host, port.parse().map_err(|_| format!("Invalid port: {}", port))?),
+        None => (host_port, 5432),
+    };
+    let host = host.trim_start_matches('[').trim_end_matches(']');
+
+    Ok(Some(TunnelTarget {
+        ssh,
+        host: if host.is_empty() { "localhost".to_string() } else { percent_decode(host) },
+        port,
+        before_host: format!("{}://{}", scheme, userinfo),
+        after_port: if params.is_empty() { path.to_string() } else { format!("{}?{}", path, params.join("&")) },
+    }))
+}
+
+/// `connection_string`, pointed at the local end of its SSH tunnel, which is
+/// opened if it is not yet. Strings without a tunnel are returned as is.
+pub async fn through_tunnel(connection_string: &str) -> Result<String, String> {
+    let Some(target) = parse_tunnel(connection_string)? else {
+        return Ok(connection_string.to_string());
+    };
+    let key = target.key();
+    {
+        let mut tunnels = TUNNELS.lock().map_err(|_| "SSH tunnels are unavailable".to_string())?;
+        if let Some(tunnel) = tunnels.get_mut(&key) {
+            // ssh exits when the bastion drops it; open a new one then
+            if matches!(tunnel.child.try_wait(), Ok(None)) {
+                return Ok(target.through(tunnel.local_port));
+            }
+            tunnels.remove(&key);
+        }
+    }
+
+    let tunnel = open_tunnel(&target).await?;
+    let mut tunnels = TUNNELS.lock().map_err(|_| "SSH tunnels are unavailable".to_string())?;
+    // Another connection may have opened the same tunnel meanwhile
+    let local_port = tunnels.entry(key).or_insert(tunnel).local_port;
+    Ok(target.through(local_port))
+}
+
+/// Start `ssh -N -L` for `target` and wait until its local port accepts
+/// connections.
+async fn open_tunnel(target: &TunnelTarget) -> Result<SshTunnel, String> {
+    let local_port = TcpListener::bind("127.0.0.1:0")
+        .and_then(|listener| listener.local_addr())
+        .map_err(|e| format!("Failed to find a free local port: {}", e))?
+        .port();
+    let forward_host = if target.host.contains(':') { format!("[{}]", target.host) } else { target.host.clone() };
+
+    let mut command = Command::new("ssh");
+    command
+        .arg("-N")
+        .arg("-L")
+        .arg(format!("127.0.0.1:{}:{}:{}", local_port, forward_host, target.port))
+        .args(["-o", "ExitOnForwardFailure=yes", "-o", "BatchMode=yes", "-o", "ServerAliveInterval=30"])
+        .arg("-p")
+        .arg(target.ssh.port.unwrap_or(22).to_string());
+    if let Some(user) = &target.ssh.user {
+        command.arg("-l").arg(user);
+    }
+    if let Some(identity_file) = &target.ssh.identity_file {
+        command.arg("-i").arg(identity_file);
+    }
+    command
+        .arg("--")
+        .arg(&target.ssh.host)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped());
+    let child = command.spawn()
+        .map_err(|e| format!("Failed to run ssh: {}", e))?;
+    let mut tunnel = SshTunnel { child, local_port };
+
+    let address = SocketAddr::from(([127, 0, 0, 1], local_port));
+    let started = std::time::Instant::now();
+    loop {
+        if let Ok(Some(status)) = tunnel.child.try_wait() {
+            let mut stderr = String::new();
+            if let Some(mut pipe) = tunnel.child.stderr.take() {
+                let _ = pipe.read_to_string(&mut stderr);
+            }
+            return Err(format!("SSH tunnel through {} failed ({}): {}", target.ssh.host, status, stderr.trim()));
+        }
+        if TcpStream::connect_timeout(&address, Duration::from_millis(200)).is_ok() {
+            return Ok(tunnel);
+        }
+        if started.elapsed() > TUNNEL_TIMEOUT {
+            return Err(format!("SSH tunnel through {} did not open within {} seconds", target.ssh.host, TUNNEL_TIMEOUT.as_secs()));
+        }
+        tokio::time::sleep(Duration::from_millis(200)).await;
+    }
+}
+
+/// Close every tunnel, e.g. when the app exits.
+pub fn close_tunnels() {
+    if let Ok(mut tunnels) = TUNNELS.lock() {
+        tunnels.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_tunnel() {
+        assert_eq!(parse_tunnel("postgres://app@db/app?sslmode=require").unwrap(), None);
+        assert!(parse_tunnel("postgres://app@db/app?sshuser=ops").is_err());
+
+        let ssh = SshTunnelOptions { host: "bastion".to_string(), user: Some("ops".to_string()), ..Default::default() };
+        let connection_string = ssh.apply("postgres://app:pw@db.internal:6432/app?sslmode=require").unwrap();
+        assert_eq!(connection_string, "postgres://app:pw@db.internal:6432/app?sslmode=require&sshhost=bastion&sshuser=ops");
+
+        let target = parse_tunnel(&connection_string).unwrap().unwrap();
+        assert_eq!(target.ssh, ssh);
+        assert_eq!((target.host.as_str(), target.port), ("db.internal", 6432));
+        assert_eq!(target.through(40000), "postgres://app:pw@127.0.0.1:40000/app?sslmode=require");
+
+        let target = parse_tunnel("postgres://[fd00::5]/app?sshhost=bastion").unwrap().unwrap();
+        assert_eq!((target.host.as_str(), target.port), ("fd00::5", 5432));
+        assert_eq!(target.through(40000), "postgres://127.0.0.1:40000/app");
+
+        // A host or user ssh would take for an option is refused
+        assert!(parse_tunnel("postgres://db/app?sshhost=-oProxyCommand%3Dtouch%20%2Ftmp%2Fx").is_err());
+        assert!(parse_tunnel("postgres://db/app?sshhost=bastion&sshuser=-oProxyCommand=x").is_err());
+    }
+}
@@ -0,0 +1,88 @@
+//! Build the desired schema from SQL files kept in a repository.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use super::parse::{apply_sql, ParsedSchema};
+
+/// Build a schema model from a `.sql` file, or from every `.sql` file under a
+/// directory.
+///
+/// Files of a directory are applied in path order, subdirectories included,
+/// so `01_types.sql` can come before `02_tables.sql`. Warnings name the file
+/// of the statement they are about.
+pub fn parse_schema_files(path: &str) -> Result<ParsedSchema, String> {
+    let root = Path::new(path);
+    let files = if root.is_dir() {
+        let mut files = Vec::new();
+        collect_sql_files(root, &mut files)?;
+        files.sort();
+        files
+    } else if root.is_file() {
+        vec![root.to_path_buf()]
+    } else {
+        return Err(format!("SQL file or directory not found: {}", path));
+    };
+
+    let mut parsed = ParsedSchema::default();
+    for file in files {
+        let sql = fs::read_to_string(&file)
+            .map_err(|e| format!("Failed to read {}: {}", file.display(), e))?;
+        let name = file.strip_prefix(root).ok()
+            .filter(|relative| !relative.as_os_str().is_empty())
+            .unwrap_or(&file)
+            .display()
+            .to_string();
+
+        for warning in apply_sql(&mut parsed.schema, &sql) {
+            parsed.warnings.push(format!("{}: {}", name, warning));
+        }
+    }
+
+    Ok(parsed)
+}
+
+/// Add the `.sql` files under `dir` to `files`.
+fn collect_sql_files(dir: &Path, files: &mut Vec<PathBuf>) -> Result<(), String> {
+    let entries = fs::read_dir(dir)
+        .map_err(|e| format!("Failed to read directory {}: {}", dir.display(), e))?;
+    for entry in entries {
+        let path = entry.map_err(|e| format!("Failed to read entry: {}", e))?.path();
+        if path.is_dir() {
+            collect_sql_files(&path, files)?;
+        } else if path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("sql")) {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_schema_directory() {
+        let dir = std::env::temp_dir().join(format!("pgshift_schema_{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(dir.join("tables")).unwrap();
+        fs::write(dir.join("01_types.sql"), "CREATE TYPE status AS ENUM ('active', 'archived');").unwrap();
+        fs::write(
+            dir.join("tables/users.sql"),
+            "CREATE TABLE users (id integer PRIMARY KEY, status status NOT NULL);\n\
+             CREATE INDEX users_status_idx ON users (status);\n\
+             CREATE VIEW active_users AS SELECT * FROM users;",
+        ).unwrap();
+        fs::write(dir.join("README.md"), "CREATE TABLE ignored (id integer);").unwrap();
+
+        let parsed = parse_schema_files(dir.to_str().unwrap()).unwrap();
+        let single = parse_schema_files(dir.join("01_types.sql").to_str().unwrap()).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(parsed.schema.enums.len(), 1);
+        assert_eq!(parsed.schema.tables.len(), 1);
+        assert_eq!(parsed.schema.find_table("users").unwrap().indexes.len(), 1);
+        assert_eq!(parsed.warnings.len(), 1);
+        assert!(parsed.warnings[0].starts_with(&format!("{}: ", Path::new("tables/users.sql").display())));
+        assert_eq!(single.schema.enums.len(), 1);
+        assert!(single.warnings.is_empty());
+    }
+}
@@ -0,0 +1,5 @@
+//! Building schema models from SQL text.
+
+pub mod files;
+pub mod parse;
+pub mod replay;
@@ -0,0 +1,1423 @@
+//! Apply DDL statements to a schema model without a database.
+//!
+//! Only the objects the model represents are tracked: tables, columns,
+//! primary/unique/foreign keys, indexes, enums, comments, the row-level
+//! security flag, unlogged tables and table storage parameters. Types and
+//! default names are rendered the way introspection reports them so a parsed
+//! model can be compared with a live one.
+
+use serde::{Deserialize, Serialize};
+use sqlparser::ast::{
+    AlterColumnOperation, AlterTableOperation, ArrayElemTypeDef, CharacterLength, ColumnDef,
+    ColumnOption, CommentObject, ConstraintCharacteristics, CreateIndex, CreateTable, DataType,
+    DeferrableInitial, ExactNumberInfo, Expr, Ident, ObjectName, ObjectType, ReferentialAction,
+    Statement, TableConstraint, TimezoneInfo, UserDefinedTypeRepresentation,
+};
+use sqlparser::dialect::PostgreSqlDialect;
+use sqlparser::parser::Parser;
+use sqlparser::tokenizer::{Token, Tokenizer};
+use crate::model::schema::{Column, Constraint, EnumType, ForeignKey, Index, IndexColumnOrder, SchemaModel, Table};
+
+/// PostgreSQL truncates identifiers to NAMEDATALEN - 1 bytes.
+const MAX_IDENTIFIER_LENGTH: usize = 63;
+
+/// A schema built from SQL text, with the statements that could not be applied.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ParsedSchema {
+    pub schema: SchemaModel,
+    /// Statements that were skipped or could not be parsed.
+    pub warnings: Vec<String>,
+}
+
+/// Build a schema model from SQL text.
+pub fn parse_schema_sql(sql: &str) -> ParsedSchema {
+    let mut parsed = ParsedSchema::default();
+    parsed.warnings = apply_sql(&mut parsed.schema, sql);
+    parsed
+}
+
+/// Apply every statement in `sql` to `schema`, returning warnings for
+/// statements that were skipped.
+pub fn apply_sql(schema: &mut SchemaModel, sql: &str) -> Vec<String> {
+    let mut warnings = Vec::new();
+
+    for statement in split_statements(sql) {
+        if let Some(result) = apply_alter_type(schema, &statement)
+            .or_else(|| apply_alter_table_set(schema, &statement))
+        {
+            if let Err(e) = result {
+                warnings.push(e);
+            }
+            continue;
+        }
+
+        let (statement, unlogged) = strip_unlogged(&statement);
+        let (statement, match_types) = strip_match_types(&statement);
+        let (statement, not_valid) = strip_not_valid(&statement);
+        match Parser::parse_sql(&PostgreSqlDialect {}, &statement) {
+            Ok(parsed) => {
+                for stmt in parsed {
+                    let created = match &stmt {
+                        Statement::CreateTable(create) if unlogged => object_name(&create.name)
+                            .filter(|name| schema.find_table(name).is_none()),
+                        _ => None,
+                    };
+                    let existing_foreign_keys = if match_types.is_empty() && !not_valid {
+                        Vec::new()
+                    } else {
+                        foreign_key_names(schema)
+                    };
+                    match apply_statement(schema, stmt) {
+                        Ok(()) => {
+                            if let Some(table) = created.and_then(|name| schema.tables.iter_mut().find(|t| t.name == name)) {
+                                table.unlogged = true;
+                            }
+                            apply_match_types(schema, &existing_foreign_keys, &match_types);
+                            if not_valid {
+                                mark_not_valid(schema, &existing_foreign_keys);
+                            }
+                        }
+                        Err(e) => warnings.push(e),
+                    }
+                }
+            }
+            Err(e) => warnings.push(format!("Could not parse '{}': {}", summarize(&statement), e)),
+        }
+    }
+
+    schema.indexes = schema.tables.iter().flat_map(|t| t.indexes.clone()).collect();
+    resolve_referenced_columns(schema);
+    warnings
+}
+
+fn apply_statement(schema: &mut SchemaModel, statement: Statement) -> Result<(), String> {
+    match statement {
+        Statement::CreateTable(create) => apply_create_table(schema, create),
+        Statement::CreateIndex(create) => apply_create_index(schema, create),
+        Statement::CreateType { name, representation: UserDefinedTypeRepresentation::Enum { labels } } => {
+            let Some(name) = object_name(&name) else {
+                return Ok(());
+            };
+            if schema.find_enum(&name).is_some() {
+                return Err(format!("Type '{}' already exists", name));
+            }
+            schema.enums.push(EnumType {
+                name,
+                values: labels.into_iter().map(|l| l.value).collect(),
+            });
+            Ok(())
+        }
+        Statement::AlterTable { name, if_exists, operations, .. } => {
+            let Some(table_name) = object_name(&name) else {
+                return Ok(());
+            };
+            if schema.find_table(&table_name).is_none() {
+                return if if_exists { Ok(()) } else { Err(format!("Table '{}' does not exist", table_name)) };
+            }
+            for operation in operations {
+                apply_alter_table(schema, &table_name, operation)?;
+            }
+            Ok(())
+        }
+        Statement::Drop { object_type, if_exists, names, cascade, .. } => {
+            for name in names.iter().filter_map(object_name) {
+                let found = match object_type {
+                    ObjectType::Table => drop_table(schema, &name, cascade),
+                    ObjectType::Index => drop_index(schema, &name),
+                    ObjectType::Type => {
+                        let before = schema.enums.len();
+                        schema.enums.retain(|e| e.name != name);
+                        schema.enums.len() != before
+                    }
+                    _ => return Err(format!("Skipped DROP {} '{}'", object_type, name)),
+                };
+                if !found && !if_exists {
+                    return Err(format!("Cannot drop '{}': it does not exist", name));
+                }
+            }
+            Ok(())
+        }
+        Statement::Comment { object_type, object_name: name, comment, .. } => {
+            apply_comment(schema, object_type, &name, comment);
+            Ok(())
+        }
+        // Transaction control and session settings do not change the schema
+        Statement::StartTransaction { .. }
+        | Statement::Commit { .. }
+        | Statement::Rollback { .. }
+        | Statement::SetVariable { .. } => Ok(()),
+        other => Err(format!("Skipped unsupported statement '{}'", summarize(&other.to_string()))),
+    }
+}
+
+fn apply_create_table(schema: &mut SchemaModel, create: CreateTable) -> Result<(), String> {
+    let Some(name) = object_name(&create.name) else {
+        return Ok(());
+    };
+    if schema.find_table(&name).is_some() {
+        return if create.if_not_exists { Ok(()) } else { Err(format!("Table '{}' already exists", name)) };
+    }
+
+    let mut table = Table {
+        name,
+        columns: Vec::new(),
+        primary_key: None,
+        unique_constraints: Vec::new(),
+        indexes: Vec::new(),
+        rls_enabled: false,
+        rls_forced: false,
+        policies: Vec::new(),
+        triggers: Vec::new(),
+        foreign_keys: Vec::new(),
+        comment: None,
+        inherits: Vec::new(),
+        unlogged: false,
+        storage_parameters: create.with_options.iter().map(|o| o.to_string().replace(" = ", "=")).collect(),
+    };
+
+    for column in create.columns {
+        add_column(&mut table, column);
+    }
+    for constraint in create.constraints {
+        add_constraint(&mut table, constraint);
+    }
+
+    schema.tables.push(table);
+    Ok(())
+}
+
+fn apply_create_index(schema: &mut SchemaModel, create: CreateIndex) -> Result<(), String> {
+    let Some(table_name) = object_name(&create.table_name) else {
+        return Ok(());
+    };
+    let columns: Vec<String> = create.columns.iter().map(|c| expr_name(&c.expr)).collect();
+    let column_orders = create.columns.iter()
+        .map(|c| {
+            let descending = c.asc == Some(false);
+            // Nulls sort as larger than any value unless told otherwise
+            IndexColumnOrder { descending, nulls_first: c.nulls_first.unwrap_or(descending) }
+        })
+        .collect();
+    let name = match create.name.as_ref() {
+        Some(name) => object_name(name).unwrap_or_default(),
+        None => default_name(&table_name, &columns, "idx"),
+    };
+
+    let table = find_table_mut(schema, &table_name)?;
+    if table.find_index(&name).is_some() {
+        return if create.if_not_exists { Ok(()) } else { Err(format!("Index '{}' already exists", name)) };
+    }
+
+    table.indexes.push(Index {
+        name,
+        columns,
+        is_unique: create.unique,
+        index_type: create.using.map(|u| u.value.to_lowercase()).unwrap_or_else(|| "btree".to_string()),
+        predicate: create.predicate.map(|p| p.to_string()),
+        column_orders,
+        include_columns: create.include.iter().map(ident).collect(),
+        column_opclasses: Vec::new(),
+        // Stored as PostgreSQL reports them in reloptions: name=value
+        storage_parameters: create.with.iter().map(|w| w.to_string().replace(" = ", "=")).collect(),
+    });
+    Ok(())
+}
+
+fn apply_alter_table(schema: &mut SchemaModel, table_name: &str, operation: AlterTableOperation) -> Result<(), String> {
+    if let AlterTableOperation::RenameTable { table_name: new_name } = &operation {
+        let new_name = object_name(new_name).unwrap_or_default();
+        for table in &mut schema.tables {
+            if table.name == table_name {
+                table.name = new_name.clone();
+            }
+            for fk in &mut table.foreign_keys {
+                if fk.referenced_table == table_name {
+                    fk.referenced_table = new_name.clone();
+                }
+            }
+        }
+        return Ok(());
+    }
+
+    let table = find_table_mut(schema, table_name)?;
+    match operation {
+        AlterTableOperation::AddColumn { if_not_exists, column_def, .. } => {
+            let name = ident(&column_def.name);
+            if table.find_column(&name).is_some() {
+                return if if_not_exists { Ok(()) } else { Err(format!("Column '{}.{}' already exists", table_name, name)) };
+            }
+            add_column(table, column_def);
+        }
+        AlterTableOperation::DropColumn { column_name, if_exists, .. } => {
+            let name = ident(&column_name);
+            if table.find_column(&name).is_none() {
+                return if if_exists { Ok(()) } else { Err(format!("Column '{}.{}' does not exist", table_name, name)) };
+            }
+            // Indexes and constraints that include the column go with it
+            table.columns.retain(|c| c.name != name);
+            table.indexes.retain(|i| !i.columns.contains(&name));
+            table.unique_constraints.retain(|c| !c.columns.contains(&name));
+            table.foreign_keys.retain(|fk| !fk.columns.contains(&name));
+            if table.primary_key.as_ref().is_some_and(|pk| pk.columns.contains(&name)) {
+                table.primary_key = None;
+            }
+        }
+        AlterTableOperation::AlterColumn { column_name, op } => {
+            let name = ident(&column_name);
+            let column = table.columns.iter_mut()
+                .find(|c| c.name == name)
+                .ok_or_else(|| format!("Column '{}.{}' does not exist", table_name, name))?;
+            match op {
+                AlterColumnOperation::SetNotNull => column.is_nullable = false,
+                AlterColumnOperation::DropNotNull => column.is_nullable = true,
+                AlterColumnOperation::SetDefault { value } => column.default_value = Some(value.to_string()),
+                AlterColumnOperation::DropDefault => column.default_value = None,
+                AlterColumnOperation::SetDataType { data_type, .. } => column.data_type = render_data_type(&data_type),
+                other => return Err(format!("Skipped ALTER COLUMN {} on '{}.{}'", other, table_name, name)),
+            }
+        }
+        AlterTableOperation::AddConstraint(constraint) => add_constraint(table, constraint),
+        AlterTableOperation::DropConstraint { name, if_exists, .. } => {
+            let name = ident(&name);
+            let before = table.unique_constraints.len() + table.foreign_keys.len();
+            table.unique_constraints.retain(|c| c.name != name);
+            table.foreign_keys.retain(|fk| fk.name != name);
+            let mut found = before != table.unique_constraints.len() + table.foreign_keys.len();
+            if table.primary_key.as_ref().is_some_and(|pk| pk.name == name) {
+                table.primary_key = None;
+                found = true;
+            }
+            if !found && !if_exists {
+                return Err(format!("Constraint '{}' does not exist on '{}'", name, table_name));
+            }
+        }
+        AlterTableOperation::RenameColumn { old_column_name, new_column_name } => {
+            let old_name = ident(&old_column_name);
+            let new_name = ident(&new_column_name);
+            let rename = |columns: &mut Vec<String>| {
+                for c in columns.iter_mut().filter(|c| **c == old_name) {
+                    *c = new_name.clone();
+                }
+            };
+            for column in table.columns.iter_mut().filter(|c| c.name == old_name) {
+                column.name = new_name.clone();
+            }
+            table.indexes.iter_mut().for_each(|i| rename(&mut i.columns));
+            table.unique_constraints.iter_mut().for_each(|c| rename(&mut c.columns));
+            table.foreign_keys.iter_mut().for_each(|fk| rename(&mut fk.columns));
+            if let Some(pk) = &mut table.primary_key {
+                rename(&mut pk.columns);
+            }
+        }
+        AlterTableOperation::RenameConstraint { old_name, new_name } => {
+            let old_name = ident(&old_name);
+            let new_name = ident(&new_name);
+            for constraint in table.primary_key.iter_mut().chain(table.unique_constraints.iter_mut()) {
+                if constraint.name == old_name {
+                    constraint.name = new_name.clone();
+                }
+            }
+            for fk in table.foreign_keys.iter_mut().filter(|fk| fk.name == old_name) {
+                fk.name = new_name.clone();
+            }
+        }
+        AlterTableOperation::EnableRowLevelSecurity => table.rls_enabled = true,
+        AlterTableOperation::DisableRowLevelSecurity => table.rls_enabled = false,
+        other => return Err(format!("Skipped ALTER TABLE '{}' {}", table_name, other)),
+    }
+    Ok(())
+}
+
+/// `ALTER TYPE` is not supported by the parser, so enum changes are matched on tokens.
+/// Returns None when the statement is not an `ALTER TYPE`.
+fn apply_alter_type(schema: &mut SchemaModel, statement: &str) -> Option<Result<(), String>> {
+    let tokens: Vec<Token> = Tokenizer::new(&PostgreSqlDialect {}, statement)
+        .tokenize()
+        .ok()?
+        .into_iter()
+        .filter(|t| !matches!(t, Token::Whitespace(_) | Token::SemiColon | Token::Period))
+        .collect();
+
+    let keyword = |i: usize| match tokens.get(i) {
+        Some(Token::Word(w)) if w.quote_style.is_none() => w.value.to_uppercase(),
+        _ => String::new(),
+    };
+    let string = |i: usize| match tokens.get(i) {
+        Some(Token::SingleQuotedString(s)) => Some(s.clone()),
+        _ => None,
+    };
+
+    if keyword(0) != "ALTER" || keyword(1) != "TYPE" {
+        return None;
+    }
+
+    // Type name, optionally qualified with the public schema
+    let mut i = 2;
+    if keyword(i) == "PUBLIC" && matches!(tokens.get(i + 1), Some(Token::Word(_))) && keyword(i + 1) != "ADD" && keyword(i + 1) != "RENAME" {
+        i += 1;
+    }
+    let name = match tokens.get(i) {
+        Some(Token::Word(w)) if w.quote_style.is_some() => w.value.clone(),
+        Some(Token::Word(w)) => w.value.to_lowercase(),
+        _ => return Some(Err(format!("Could not parse '{}'", summarize(statement)))),
+    };
+    i += 1;
+
+    let Some(enum_type) = schema.enums.iter_mut().find(|e| e.name == name) else {
+        return Some(Err(format!("Type '{}' does not exist", name)));
+    };
+
+    match (keyword(i).as_str(), keyword(i + 1).as_str()) {
+        ("ADD", "VALUE") => {
+            i += 2;
+            let if_not_exists = keyword(i) == "IF";
+            if if_not_exists {
+                i += 3;
+            }
+            let Some(value) = string(i) else {
+                return Some(Err(format!("Could not parse '{}'", summarize(statement))));
+            };
+            if enum_type.values.contains(&value) {
+                return Some(if if_not_exists { Ok(()) } else { Err(format!("Enum '{}' already has value '{}'", name, value)) });
+            }
+            let position = match (keyword(i + 1).as_str(), string(i + 2)) {
+                ("BEFORE", Some(other)) => enum_type.values.iter().position(|v| *v == other),
+                ("AFTER", Some(other)) => enum_type.values.iter().position(|v| *v == other).map(|p| p + 1),
+                _ => None,
+            };
+            match position {
+                Some(position) => enum_type.values.insert(position, value),
+                None => enum_type.values.push(value),
+            }
+            Some(Ok(()))
+        }
+        ("RENAME", "VALUE") => {
+            let (Some(old_value), Some(new_value)) = (string(i + 2), string(i + 4)) else {
+                return Some(Err(format!("Could not parse '{}'", summarize(statement))));
+            };
+            for value in enum_type.values.iter_mut().filter(|v| **v == old_value) {
+                *value = new_value.clone();
+            }
+            Some(Ok(()))
+        }
+        _ => Some(Err(format!("Skipped unsupported statement '{}'", summarize(statement)))),
+    }
+}
+
+/// The parser does not know `CREATE UNLOGGED TABLE`; returns
+/// the statement without `UNLOGGED` and whether it was there.
+fn strip_unlogged(statement: &str) -> (String, bool) {
+    let Ok(tokens) = Tokenizer::new(&PostgreSqlDialect {}, statement).tokenize() else {
+        return (statement.to_string(), false);
+    };
+
+    let words: Vec<(usize, String)> = tokens.iter()
+        .enumerate()
+        .filter(|(_, t)| !matches!(t, Token::Whitespace(_)))
+        .take(3)
+        .map(|(i, t)| match t {
+            Token::Word(w) if w.quote_style.is_none() => (i, w.value.to_uppercase()),
+            _ => (i, String::new()),
+        })
+        .collect();
+    let [(_, create), (unlogged, keyword), (_, table)] = words.as_slice() else {
+        return (statement.to_string(), false);
+    };
+    if create != "CREATE" || keyword != "UNLOGGED" || table != "TABLE" {
+        return (statement.to_string(), false);
+    }
+
+    let stripped = tokens.iter()
+        .enumerate()
+        .filter(|(i, _)| i != unlogged)
+        .map(|(_, t)| t.to_string())
+        .collect();
+    (stripped, true)
+}
+
+/// The parser does not know `MATCH FULL | PARTIAL | SIMPLE` on foreign keys;
+/// returns the statement without it and, in order of appearance, the
+/// referenced table and match type of each reference that had one.
+fn strip_match_types(statement: &str) -> (String, Vec<(String, String)>) {
+    let Ok(tokens) = Tokenizer::new(&PostgreSqlDialect {}, statement).tokenize() else {
+        return (statement.to_string(), Vec::new());
+    };
+
+    let keyword = |t: &Token| match t {
+        Token::Word(w) if w.quote_style.is_none() => w.value.to_uppercase(),
+        _ => String::new(),
+    };
+    let significant: Vec<usize> = (0..tokens.len())
+        .filter(|&i| !matches!(tokens[i], Token::Whitespace(_)))
+        .collect();
+
+    let mut match_types = Vec::new();
+    let mut stripped = Vec::new();
+    let mut referenced_table = String::new();
+    for (n, &i) in significant.iter().enumerate() {
+        if keyword(&tokens[i]) == "REFERENCES" {
+            // The last name part, skipping a schema qualifier
+            let mut k = n + 1;
+            while significant.get(k + 1).is_some_and(|&j| tokens[j] == Token::Period) {
+                k += 2;
+            }
+            referenced_table = match significant.get(k).map(|&j| &tokens[j]) {
+                Some(Token::Word(w)) if w.quote_style.is_some() => w.value.clone(),
+                Some(Token::Word(w)) => w.value.to_lowercase(),
+                _ => String::new(),
+            };
+        }
+        if keyword(&tokens[i]) != "MATCH" {
+            continue;
+        }
+        let Some(&j) = significant.get(n + 1) else {
+            continue;
+        };
+        let match_type = keyword(&tokens[j]);
+        if matches!(match_type.as_str(), "FULL" | "PARTIAL" | "SIMPLE") {
+            stripped.extend([i, j]);
+            match_types.push((referenced_table.clone(), match_type));
+        }
+    }
+
+    if stripped.is_empty() {
+        return (statement.to_string(), match_types);
+    }
+    let statement = tokens.iter()
+        .enumerate()
+        .filter(|(i, _)| !stripped.contains(i))
+        .map(|(_, t)| t.to_string())
+        .collect();
+    (statement, match_types)
+}
+
+/// The parser does not know `ALTER TABLE ... ADD CONSTRAINT ... NOT VALID`;
+/// returns the statement without the trailing `NOT VALID` and whether it was
+/// there.
+fn strip_not_valid(statement: &str) -> (String, bool) {
+    let Ok(tokens) = Tokenizer::new(&PostgreSqlDialect {}, statement).tokenize() else {
+        return (statement.to_string(), false);
+    };
+
+    let keyword = |i: usize| match &tokens[i] {
+        Token::Word(w) if w.quote_style.is_none() => w.value.to_uppercase(),
+        _ => String::new(),
+    };
+    let significant: Vec<usize> = (0..tokens.len())
+        .filter(|&i| !matches!(tokens[i], Token::Whitespace(_) | Token::SemiColon))
+        .collect();
+    let [.., not, valid] = significant.as_slice() else {
+        return (statement.to_string(), false);
+    };
+    if keyword(significant[0]) != "ALTER" || keyword(*not) != "NOT" || keyword(*valid) != "VALID" {
+        return (statement.to_string(), false);
+    }
+
+    let stripped = tokens.iter()
+        .enumerate()
+        .filter(|(i, _)| i != not && i != valid)
+        .map(|(_, t)| t.to_string())
+        .collect();
+    (stripped, true)
+}
+
+/// Mark the foreign keys a statement added `NOT VALID` as not validated.
+fn mark_not_valid(schema: &mut SchemaModel, existing: &[(String, String)]) {
+    for table in &mut schema.tables {
+        for fk in &mut table.foreign_keys {
+            if !existing.iter().any(|(t, name)| *t == table.name && *name == fk.name) {
+                fk.validated = false;
+            }
+        }
+    }
+}
+
+/// Table and name of every foreign key in the schema.
+fn foreign_key_names(schema: &SchemaModel) -> Vec<(String, String)> {
+    schema.tables.iter()
+        .flat_map(|t| t.foreign_keys.iter().map(|fk| (t.name.clone(), fk.name.clone())))
+        .collect()
+}
+
+/// Set the match types stripped from a statement on the foreign keys it
+/// created, pairing them up in order by referenced table.
+fn apply_match_types(schema: &mut SchemaModel, existing: &[(String, String)], match_types: &[(String, String)]) {
+    if match_types.is_empty() {
+        return;
+    }
+    let mut match_types: Vec<&(String, String)> = match_types.iter().collect();
+    for table in &mut schema.tables {
+        for fk in &mut table.foreign_keys {
+            if existing.iter().any(|(t, name)| *t == table.name && *name == fk.name) {
+                continue;
+            }
+            if let Some(n) = match_types.iter().position(|(referenced, _)| *referenced == fk.referenced_table) {
+                fk.match_type = match_types.remove(n).1.clone();
+            }
+        }
+    }
+}
+
+/// Fill in the referenced columns of foreign keys declared without a column
+/// list with the referenced table's primary key, as the server does.
+fn resolve_referenced_columns(schema: &mut SchemaModel) {
+    let primary_keys: Vec<(String, Vec<String>)> = schema.tables.iter()
+        .filter_map(|t| t.primary_key.as_ref().map(|pk| (t.name.clone(), pk.columns.clone())))
+        .collect();
+    for fk in schema.tables.iter_mut().flat_map(|t| t.foreign_keys.iter_mut()) {
+        if fk.referenced_columns.is_empty() {
+            if let Some((_, columns)) = primary_keys.iter().find(|(t, _)| *t == fk.referenced_table) {
+                fk.referenced_columns = columns.clone();
+            }
+        }
+    }
+}
+
+/// Apply `ALTER TABLE ... SET LOGGED | UNLOGGED`, `SET (...)` / `RESET (...)`
+/// of storage parameters and `VALIDATE CONSTRAINT`, matched on tokens.
+/// Returns None for any other statement.
+fn apply_alter_table_set(schema: &mut SchemaModel, statement: &str) -> Option<Result<(), String>> {
+    let tokens: Vec<Token> = Tokenizer::new(&PostgreSqlDialect {}, statement)
+        .tokenize()
+        .ok()?
+        .into_iter()
+        .filter(|t| !matches!(t, Token::Whitespace(_) | Token::SemiColon))
+        .collect();
+
+    let keyword = |i: usize| match tokens.get(i) {
+        Some(Token::Word(w)) if w.quote_style.is_none() => w.value.to_uppercase(),
+        _ => String::new(),
+    };
+
+    if keyword(0) != "ALTER" || keyword(1) != "TABLE" {
+        return None;
+    }
+
+    let mut i = 2;
+    let if_exists = keyword(i) == "IF" && keyword(i + 1) == "EXISTS";
+    if if_exists {
+        i += 2;
+    }
+    if keyword(i) == "ONLY" {
+        i += 1;
+    }
+    // Table name, optionally qualified with the public schema
+    if keyword(i) == "PUBLIC" && tokens.get(i + 1) == Some(&Token::Period) {
+        i += 2;
+    }
+    let name = match tokens.get(i) {
+        Some(Token::Word(w)) if w.quote_style.is_some() => w.value.clone(),
+        Some(Token::Word(w)) => w.value.to_lowercase(),
+        _ => return None,
+    };
+    i += 1;
+
+    let action = keyword(i);
+    let rest = &tokens[(i + 1).min(tokens.len())..];
+    let change: Box<dyn FnOnce(&mut Table)> = match (action.as_str(), rest) {
+        ("SET", [Token::Word(w)]) if w.value.eq_ignore_ascii_case("logged") => Box::new(|t| t.unlogged = false),
+        ("SET", [Token::Word(w)]) if w.value.eq_ignore_ascii_case("unlogged") => Box::new(|t| t.unlogged = true),
+        ("SET", [Token::LParen, options @ .., Token::RParen]) => {
+            let options = storage_options(options);
+            Box::new(move |t| {
+                for option in options {
+                    let key = option.split('=').next().unwrap_or_default().to_string();
+                    t.storage_parameters.retain(|p| p.split('=').next() != Some(&key));
+                    t.storage_parameters.push(option);
+                }
+            })
+        }
+        ("RESET", [Token::LParen, options @ .., Token::RParen]) => {
+            let names = storage_options(options);
+            Box::new(move |t| t.storage_parameters.retain(|p| !names.iter().any(|n| p.split('=').next() == Some(n))))
+        }
+        ("VALIDATE", [Token::Word(c), Token::Word(w)]) if c.value.eq_ignore_ascii_case("constraint") => {
+            let constraint = match w.quote_style {
+                Some(_) => w.value.clone(),
+                None => w.value.to_lowercase(),
+            };
+            Box::new(move |t| {
+                if let Some(fk) = t.foreign_keys.iter_mut().find(|fk| fk.name == constraint) {
+                    fk.validated = true;
+                }
+            })
+        }
+        ("ALTER", rest) => {
+            let rest = match rest {
+                [Token::Word(w), rest @ ..] if w.quote_style.is_none() && w.value.eq_ignore_ascii_case("column") => rest,
+                _ => rest,
+            };
+            let column = match rest.first() {
+                Some(Token::Word(w)) if w.quote_style.is_some() => w.value.clone(),
+                Some(Token::Word(w)) => w.value.to_lowercase(),
+                _ => return None,
+            };
+            let setting = |i: usize| match rest.get(i) {
+                Some(Token::Word(w)) => w.value.to_uppercase(),
+                _ => String::new(),
+            };
+            if setting(1) != "SET" {
+                return None;
+            }
+            let change: Box<dyn FnOnce(&mut Column)> = match (setting(2).as_str(), &rest[3.min(rest.len())..]) {
+                ("STORAGE", [Token::Word(w)]) => {
+                    let storage = w.value.to_uppercase();
+                    Box::new(move |c| {
+                        c.storage = (storage != "DEFAULT" && storage != c.default_storage()).then_some(storage);
+                    })
+                }
+                ("STATISTICS", [Token::Number(n, _)]) => {
+                    let target = n.parse().ok()?;
+                    Box::new(move |c| c.statistics_target = Some(target))
+                }
+                // -1 and DEFAULT (PG17) restore default_statistics_target
+                ("STATISTICS", [Token::Minus, Token::Number(_, _)] | [Token::Word(_)]) => {
+                    Box::new(|c| c.statistics_target = None)
+                }
+                _ => return None,
+            };
+            Box::new(move |t| {
+                if let Some(c) = t.columns.iter_mut().find(|c| c.name == column) {
+                    change(c);
+                }
+            })
+        }
+        _ => return None,
+    };
+
+    match schema.tables.iter_mut().find(|t| t.name == name) {
+        Some(table) => {
+            change(table);
+            Some(Ok(()))
+        }
+        None if if_exists => Some(Ok(())),
+        None => Some(Err(format!("Table '{}' does not exist", name))),
+    }
+}
+
+/// Render comma-separated storage options the way `reloptions` stores them:
+/// `name=value` without spaces or quotes, names in lower case.
+fn storage_options(tokens: &[Token]) -> Vec<String> {
+    tokens
+        .split(|t| *t == Token::Comma)
+        .map(|option| option.iter().map(|t| match t {
+            Token::Word(w) if w.quote_style.is_none() => w.value.to_lowercase(),
+            Token::Word(w) => w.value.clone(),
+            Token::SingleQuotedString(s) => s.clone(),
+            other => other.to_string(),
+        }).collect::<String>())
+        .filter(|option| !option.is_empty())
+        .collect()
+}
+
+fn apply_comment(schema: &mut SchemaModel, object_type: CommentObject, name: &ObjectName, comment: Option<String>) {
+    let parts: Vec<String> = name.0.iter().map(ident).collect();
+    let parts: Vec<&str> = parts.iter().map(|s| s.as_str()).skip_while(|p| *p == "public").collect();
+    match (object_type, parts.as_slice()) {
+        (CommentObject::Table, [table]) => {
+            if let Some(t) = schema.tables.iter_mut().find(|t| t.name == *table) {
+                t.comment = comment;
+            }
+        }
+        (CommentObject::Column, [table, column]) => {
+            if let Some(c) = schema.tables.iter_mut()
+                .find(|t| t.name == *table)
+                .and_then(|t| t.columns.iter_mut().find(|c| c.name == *column))
+            {
+                c.comment = comment;
+            }
+        }
+        _ => {}
+    }
+}
+
+fn add_column(table: &mut Table, column_def: ColumnDef) {
+    let name = ident(&column_def.name);
+    let mut data_type = render_data_type(&column_def.data_type);
+    let mut column = Column {
+        name: name.clone(),
+        data_type: data_type.clone(),
+        is_nullable: true,
+        default_value: None,
+        ordinal_position: table.columns.iter().map(|c| c.ordinal_position).max().unwrap_or(0) + 1,
+        generation_expression: None,
+        comment: None,
+        inherited: false,
+        storage: None,
+        statistics_target: None,
+    };
+
+    // serial types are integers with a sequence default
+    let serial = match data_type.as_str() {
+        "smallserial" | "serial2" => Some("smallint"),
+        "serial" | "serial4" => Some("integer"),
+        "bigserial" | "serial8" => Some("bigint"),
+        _ => None,
+    };
+    if let Some(integer_type) = serial {
+        data_type = integer_type.to_string();
+        column.data_type = data_type;
+        column.is_nullable = false;
+        column.default_value = Some(format!(
+            "nextval('{}'::regclass)",
+            truncate_identifier(&format!("{}_{}_seq", table.name, name))
+        ));
+    }
+
+    for option in column_def.options {
+        let constraint_name = option.name.as_ref().map(ident);
+        match option.option {
+            ColumnOption::NotNull => column.is_nullable = false,
+            ColumnOption::Null => column.is_nullable = true,
+            ColumnOption::Default(expr) => column.default_value = Some(expr.to_string()),
+            ColumnOption::Generated { generation_expr: Some(expr), .. } => {
+                column.generation_expression = Some(expr.to_string());
+            }
+            ColumnOption::Unique { is_primary: true, .. } => {
+                column.is_nullable = false;
+                table.primary_key = Some(Constraint {
+                    name: constraint_name.unwrap_or_else(|| truncate_identifier(&format!("{}_pkey", table.name))),
+                    constraint_type: "PRIMARY KEY".to_string(),
+                    columns: vec![name.clone()],
+                });
+            }
+            ColumnOption::Unique { is_primary: false, .. } => {
+                let columns = vec![name.clone()];
+                table.unique_constraints.push(Constraint {
+                    name: constraint_name.unwrap_or_else(|| default_name(&table.name, &columns, "key")),
+                    constraint_type: "UNIQUE".to_string(),
+                    columns,
+                });
+            }
+            ColumnOption::ForeignKey { foreign_table, referred_columns, on_delete, on_update, characteristics } => {
+                let columns = vec![name.clone()];
+                let name = constraint_name.unwrap_or_else(|| default_name(&table.name, &columns, "fkey"));
+                table.foreign_keys.push(foreign_key(
+                    name, columns, &foreign_table, &referred_columns, on_delete, on_update, characteristics,
+                ));
+            }
+            _ => {}
+        }
+    }
+
+    table.columns.push(column);
+}
+
+fn add_constraint(table: &mut Table, constraint: TableConstraint) {
+    match constraint {
+        TableConstraint::PrimaryKey { name, columns, .. } => {
+            let columns: Vec<String> = columns.iter().map(ident).collect();
+            for column in table.columns.iter_mut().filter(|c| columns.contains(&c.name)) {
+                column.is_nullable = false;
+            }
+            table.primary_key = Some(Constraint {
+                name: name.as_ref().map(ident).unwrap_or_else(|| truncate_identifier(&format!("{}_pkey", table.name))),
+                constraint_type: "PRIMARY KEY".to_string(),
+                columns,
+            });
+        }
+        TableConstraint::Unique { name, columns, .. } => {
+            let columns: Vec<String> = columns.iter().map(ident).collect();
+            table.unique_constraints.push(Constraint {
+                name: name.as_ref().map(ident).unwrap_or_else(|| default_name(&table.name, &columns, "key")),
+                constraint_type: "UNIQUE".to_string(),
+                columns,
+            });
+        }
+        TableConstraint::ForeignKey { name, columns, foreign_table, referred_columns, on_delete, on_update, characteristics } => {
+            let columns: Vec<String> = columns.iter().map(ident).collect();
+            let name = name.as_ref().map(ident).unwrap_or_else(|| default_name(&table.name, &columns[..1.min(columns.len())], "fkey"));
+            table.foreign_keys.push(foreign_key(
+                name, columns, &foreign_table, &referred_columns, on_delete, on_update, characteristics,
+            ));
+        }
+        // CHECK constraints are not part of the model
+        _ => {}
+    }
+}
+
+fn foreign_key(
+    name: String,
+    columns: Vec<String>,
+    foreign_table: &ObjectName,
+    referred_columns: &[Ident],
+    on_delete: Option<ReferentialAction>,
+    on_update: Option<ReferentialAction>,
+    characteristics: Option<ConstraintCharacteristics>,
+) -> ForeignKey {
+    let action = |action: Option<ReferentialAction>| {
+        action.map(|a| a.to_string()).unwrap_or_else(|| "NO ACTION".to_string())
+    };
+    let initially_deferred = characteristics
+        .is_some_and(|c| c.initially == Some(DeferrableInitial::Deferred));
+    ForeignKey {
+        name,
+        columns,
+        referenced_table: object_name(foreign_table).unwrap_or_default(),
+        // Without a column list the referenced table's primary key is used;
+        // filled in by resolve_referenced_columns
+        referenced_columns: referred_columns.iter().map(ident).collect(),
+        // MATCH is not understood by the parser; see strip_match_types
+        match_type: "SIMPLE".to_string(),
+        on_delete: action(on_delete),
+        on_update: action(on_update),
+        // INITIALLY DEFERRED implies DEFERRABLE
+        deferrable: characteristics.and_then(|c| c.deferrable).unwrap_or(initially_deferred),
+        initially_deferred,
+        // NOT VALID is not understood by the parser; see strip_not_valid
+        validated: true,
+    }
+}
+
+fn drop_table(schema: &mut SchemaModel, name: &str, cascade: bool) -> bool {
+    let before = schema.tables.len();
+    schema.tables.retain(|t| t.name != name);
+    if cascade {
+        for table in &mut schema.tables {
+            table.foreign_keys.retain(|fk| fk.referenced_table != name);
+        }
+    }
+    schema.tables.len() != before
+}
+
+fn drop_index(schema: &mut SchemaModel, name: &str) -> bool {
+    let mut found = false;
+    for table in &mut schema.tables {
+        let before = table.indexes.len();
+        table.indexes.retain(|i| i.name != name);
+        found |= table.indexes.len() != before;
+    }
+    found
+}
+
+fn find_table_mut<'a>(schema: &'a mut SchemaModel, name: &str) -> Result<&'a mut Table, String> {
+    schema.tables.iter_mut()
+        .find(|t| t.name == name)
+        .ok_or_else(|| format!("Table '{}' does not exist", name))
+}
+
+/// Render a parsed type the way introspection reports it.
+fn render_data_type(data_type: &DataType) -> String {
+    match data_type {
+        DataType::SmallInt(_) | DataType::Int2(_) => "smallint".to_string(),
+        DataType::Int(_) | DataType::Integer(_) | DataType::Int4(_) => "integer".to_string(),
+        DataType::BigInt(_) | DataType::Int8(_) => "bigint".to_string(),
+        DataType::Real | DataType::Float4 => "real".to_string(),
+        DataType::Double | DataType::DoublePrecision | DataType::Float8 => "double precision".to_string(),
+        DataType::Float(Some(p)) if *p <= 24 => "real".to_string(),
+        DataType::Float(_) => "double precision".to_string(),
+        DataType::Bool | DataType::Boolean => "boolean".to_string(),
+        DataType::Text => "text".to_string(),
+        DataType::Uuid => "uuid".to_string(),
+        DataType::JSON => "json".to_string(),
+        DataType::JSONB => "jsonb".to_string(),
+        DataType::Bytea => "bytea".to_string(),
+        DataType::Date => "date".to_string(),
+        DataType::Interval => "interval".to_string(),
+        DataType::Varchar(len) | DataType::CharacterVarying(len) | DataType::CharVarying(len) => {
+            match character_length(len) {
+                Some(n) => format!("varchar({})", n),
+                None => "varchar".to_string(),
+            }
+        }
+        DataType::Char(len) | DataType::Character(len) => {
+            // CHAR without a length is char(1)
+            format!("char({})", character_length(len).unwrap_or(1))
+        }
+        DataType::Numeric(info) | DataType::Decimal(info) | DataType::Dec(info) => match info {
+            ExactNumberInfo::PrecisionAndScale(p, s) if *s > 0 => format!("numeric({},{})", p, s),
+            ExactNumberInfo::PrecisionAndScale(p, _) | ExactNumberInfo::Precision(p) => format!("numeric({})", p),
+            ExactNumberInfo::None => "numeric".to_string(),
+        },
+        DataType::Timestamp(_, tz) => match tz {
+            TimezoneInfo::WithTimeZone | TimezoneInfo::Tz => "timestamp with time zone".to_string(),
+            _ => "timestamp without time zone".to_string(),
+        },
+        DataType::Time(_, tz) => match tz {
+            TimezoneInfo::WithTimeZone | TimezoneInfo::Tz => "time with time zone".to_string(),
+            _ => "time without time zone".to_string(),
+        },
+        DataType::Array(
+            ArrayElemTypeDef::SquareBracket(inner, _)
+            | ArrayElemTypeDef::AngleBracket(inner)
+            | ArrayElemTypeDef::Parenthesis(inner),
+        ) => format!("{}[]", udt_name(inner)),
+        DataType::Custom(name, _) => match name.0.iter().map(ident).next_back().unwrap_or_default().as_str() {
+            "timestamptz" => "timestamp with time zone".to_string(),
+            "timetz" => "time with time zone".to_string(),
+            "int" | "int4" => "integer".to_string(),
+            "int8" => "bigint".to_string(),
+            "int2" => "smallint".to_string(),
+            "bool" => "boolean".to_string(),
+            "float4" => "real".to_string(),
+            "float8" => "double precision".to_string(),
+            other => other.to_string(),
+        },
+        other => other.to_string().to_lowercase(),
+    }
+}
+
+/// Internal type name used for array element types, e.g. `int4` for `integer[]`.
+fn udt_name(data_type: &DataType) -> String {
+    match render_data_type(data_type).as_str() {
+        "smallint" => "int2".to_string(),
+        "integer" => "int4".to_string(),
+        "bigint" => "int8".to_string(),
+        "real" => "float4".to_string(),
+        "double precision" => "float8".to_string(),
+        "boolean" => "bool".to_string(),
+        "timestamp with time zone" => "timestamptz".to_string(),
+        "timestamp without time zone" => "timestamp".to_string(),
+        "time with time zone" => "timetz".to_string(),
+        "time without time zone" => "time".to_string(),
+        other => other.split('(').next().unwrap_or(other).to_string(),
+    }
+}
+
+fn character_length(len: &Option<CharacterLength>) -> Option<u64> {
+    match len {
+        Some(CharacterLength::IntegerLength { length, .. }) => Some(*length),
+        _ => None,
+    }
+}
+
+/// Identifier as PostgreSQL stores it: unquoted names are folded to lower case.
+fn ident(ident: &Ident) -> String {
+    if ident.quote_style.is_some() {
+        ident.value.clone()
+    } else {
+        ident.value.to_lowercase()
+    }
+}
+
+/// Unqualified name of an object in the public schema; None for other schemas.
+fn object_name(name: &ObjectName) -> Option<String> {
+    match name.0.as_slice() {
+        [object] => Some(ident(object)),
+        [schema, object] if ident(schema) == "public" => Some(ident(object)),
+        _ => None,
+    }
+}
+
+fn expr_name(expr: &Expr) -> String {
+    match expr {
+        Expr::Identifier(i) => ident(i),
+        other => other.to_string(),
+    }
+}
+
+/// Name PostgreSQL generates for an unnamed constraint or index, e.g. `users_email_key`.
+fn default_name(table: &str, columns: &[String], suffix: &str) -> String {
+    let mut parts = vec![table.to_string()];
+    parts.extend(columns.iter().cloned());
+    parts.push(suffix.to_string());
+    truncate_identifier(&parts.join("_"))
+}
+
+pub(crate) fn truncate_identifier(name: &str) -> String {
+    let mut end = name.len().min(MAX_IDENTIFIER_LENGTH);
+    while !name.is_char_boundary(end) {
+        end -= 1;
+    }
+    name[..end].to_string()
+}
+
+/// First line of a statement, shortened for warnings.
+fn summarize(statement: &str) -> String {
+    let line = statement.trim().lines().next().unwrap_or("");
+    if line.chars().count() > 80 {
+        format!("{}...", line.chars().take(80).collect::<String>())
+    } else {
+        line.to_string()
+    }
+}
+
+/// Split SQL text into statements on top-level semicolons.
+///
+/// Quoted strings, quoted identifiers, dollar-quoted bodies and comments are
+/// kept intact so function bodies are not cut apart.
+pub fn split_statements(sql: &str) -> Vec<String> {
+    let chars: Vec<char> = sql.chars().collect();
+    let mut statements = Vec::new();
+    let mut current = String::new();
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+
+        if c == '-' && chars.get(i + 1) == Some(&'-') {
+            while i < chars.len() && chars[i] != '\n' {
+                current.push(chars[i]);
+                i += 1;
+            }
+            continue;
+        }
+
+        if c == '/' && chars.get(i + 1) == Some(&'*') {
+            let mut depth = 0;
+            while i < chars.len() {
+                if chars[i] == '/' && chars.get(i + 1) == Some(&'*') {
+                    depth += 1;
+                    current.push_str("/*");
+                    i += 2;
+                } else if chars[i] == '*' && chars.get(i + 1) == Some(&'/') {
+                    depth -= 1;
+                    current.push_str("*/");
+                    i += 2;
+                    if depth == 0 {
+                        break;
+                    }
+                } else {
+                    current.push(chars[i]);
+                    i += 1;
+                }
+            }
+            continue;
+        }
+
+        if c == '\'' || c == '"' {
+            current.push(c);
+            i += 1;
+            while i < chars.len() {
+                current.push(chars[i]);
+                if chars[i] == c {
+                    if chars.get(i + 1) == Some(&c) {
+                        current.push(c);
+                        i += 2;
+                        continue;
+                    }
+                    break;
+                }
+                i += 1;
+            }
+            i += 1;
+            continue;
+        }
+
+        if c == '$' {
+            // Dollar-quote tag: $$ or $name$ (a digit after $ is a parameter)
+            let mut end = i + 1;
+            while end < chars.len() && (chars[end].is_alphanumeric() || chars[end] == '_') {
+                end += 1;
+            }
+            let is_tag = end < chars.len()
+                && chars[end] == '$'
+                && !chars.get(i + 1).is_some_and(|c| c.is_ascii_digit());
+            if is_tag {
+                let tag: String = chars[i..=end].iter().collect();
+                current.push_str(&tag);
+                i = end + 1;
+                let tag_chars: Vec<char> = tag.chars().collect();
+                while i < chars.len() {
+                    if chars[i..].starts_with(&tag_chars) {
+                        current.push_str(&tag);
+                        i += tag_chars.len();
+                        break;
+                    }
+                    current.push(chars[i]);
+                    i += 1;
+                }
+                continue;
+            }
+        }
+
+        if c == ';' {
+            if !is_blank(&current) {
+                statements.push(current.trim().to_string());
+            }
+            current.clear();
+            i += 1;
+            continue;
+        }
+
+        current.push(c);
+        i += 1;
+    }
+
+    if !is_blank(&current) {
+        statements.push(current.trim().to_string());
+    }
+
+    statements
+}
+
+/// Whether a statement holds nothing but whitespace and comments.
+fn is_blank(statement: &str) -> bool {
+    statement
+        .lines()
+        .map(|l| l.split("--").next().unwrap_or("").trim())
+        .all(|l| l.is_empty())
+        && !statement.contains("/*")
+}
+
+/// The first words of a statement of [`split_statements`], upper-cased,
+/// after the comment lines before it.
+fn leading_words(statement: &str, count: usize) -> Vec<String> {
+    statement
+        .lines()
+        .skip_while(|line| line.trim().is_empty() || line.trim_start().starts_with("--"))
+        .flat_map(str::split_whitespace)
+        .take(count)
+        .map(str::to_uppercase)
+        .collect()
+}
+
+/// Whether `statement` starts, ends or rolls back (part of) a transaction
+/// block.
+pub fn is_transaction_control(statement: &str) -> bool {
+    let words = leading_words(statement, 1);
+    matches!(
+        words.first().map(String::as_str),
+        Some("BEGIN" | "START" | "COMMIT" | "END" | "ROLLBACK" | "ABORT" | "SAVEPOINT" | "RELEASE")
+    )
+}
+
+/// Whether `statement` can run inside a transaction block opened for it.
+///
+/// Transaction control cannot, nor can statements PostgreSQL refuses in a
+/// transaction block (`CREATE INDEX CONCURRENTLY`, `VACUUM`, ...) or that
+/// commit on their own. `ALTER TYPE ... ADD VALUE` is refused before
+/// PostgreSQL 12 and since then leaves the new value unusable until the
+/// transaction commits, so it runs on its own too.
+pub fn runs_in_transaction(statement: &str) -> bool {
+    if is_transaction_control(statement) {
+        return false;
+    }
+    let words = leading_words(statement, 8);
+    let word = |i: usize| words.get(i).map(String::as_str).unwrap_or_default();
+    if matches!(word(0), "VACUUM" | "CALL") {
+        return false;
+    }
+    if matches!(word(0), "CREATE" | "DROP" | "REINDEX") && words.iter().take(5).any(|w| w == "CONCURRENTLY") {
+        return false;
+    }
+    if matches!((word(0), word(1)), ("CREATE" | "DROP", "DATABASE" | "TABLESPACE" | "SUBSCRIPTION") | ("ALTER", "SYSTEM")) {
+        return false;
+    }
+    if adds_enum_value(statement) {
+        return false;
+    }
+    // A DO block that commits, such as a batched backfill
+    let upper = statement.to_uppercase();
+    !(word(0) == "DO" && (upper.contains("COMMIT;") || upper.contains("ROLLBACK;")))
+}
+
+/// Whether `statement` adds a value to an enum type.
+pub fn adds_enum_value(statement: &str) -> bool {
+    let words = leading_words(statement, 8);
+    words.len() > 1
+        && (words[0].as_str(), words[1].as_str()) == ("ALTER", "TYPE")
+        && words.windows(2).any(|w| w[0] == "ADD" && w[1] == "VALUE")
+}
+
+/// Whether `statement` validates a constraint added `NOT VALID`.
+pub fn validates_constraint(statement: &str) -> bool {
+    let words = leading_words(statement, 8);
+    words.len() > 1
+        && (words[0].as_str(), words[1].as_str()) == ("ALTER", "TABLE")
+        && words.windows(2).any(|w| w[0] == "VALIDATE" && w[1] == "CONSTRAINT")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_runs_in_transaction() {
+        assert!(is_transaction_control("BEGIN") && is_transaction_control("-- done\ncommit"));
+        assert!(!runs_in_transaction("BEGIN") && !runs_in_transaction("ROLLBACK TO SAVEPOINT s"));
+        assert!(!is_transaction_control("CREATE FUNCTION f() RETURNS void AS $$ BEGIN; COMMIT; $$ LANGUAGE sql"));
+        assert!(runs_in_transaction("CREATE FUNCTION f() RETURNS void AS $$ BEGIN; COMMIT; $$ LANGUAGE sql"));
+        assert!(!runs_in_transaction("CREATE UNIQUE INDEX CONCURRENTLY i ON t (c)"));
+        assert!(!runs_in_transaction("-- Add index\nCREATE INDEX CONCURRENTLY IF NOT EXISTS i ON t (c)"));
+        assert!(!runs_in_transaction("ALTER TYPE \"mood\" ADD VALUE IF NOT EXISTS 'ok'"));
+        assert!(runs_in_transaction("ALTER TYPE \"mood\" RENAME VALUE 'ok' TO 'fine'"));
+        assert!(!runs_in_transaction("DO $$ BEGIN LOOP COMMIT; END LOOP; END $$"));
+        assert!(runs_in_transaction("DO $$ BEGIN PERFORM 1; END $$"));
+        assert!(runs_in_transaction("CREATE INDEX i ON t (c)"));
+        assert!(validates_constraint("-- Validate\nALTER TABLE ONLY \"orders\" VALIDATE CONSTRAINT \"orders_user_id_fkey\""));
+        assert!(!validates_constraint("ALTER TABLE \"orders\" DROP CONSTRAINT \"orders_user_id_fkey\""));
+    }
+
+    #[test]
+    fn test_split_statements_keeps_bodies() {
+        let sql = "CREATE TABLE a (x text DEFAULT ';');\n-- done;\n\
+                   CREATE FUNCTION f() RETURNS int AS $fn$ SELECT 1; $fn$ LANGUAGE sql;\n";
+
+        let statements = split_statements(sql);
+
+        assert_eq!(statements.len(), 2);
+        assert!(statements[1].ends_with("LANGUAGE sql"));
+    }
+
+    #[test]
+    fn test_parse_create_and_alter() {
+        let parsed = parse_schema_sql(
+            r#"
+            CREATE TYPE status AS ENUM ('active', 'disabled');
+            CREATE TABLE Users (
+                id SERIAL PRIMARY KEY,
+                email VARCHAR(255) NOT NULL UNIQUE,
+                state status DEFAULT 'active',
+                tags TEXT[],
+                created_at TIMESTAMPTZ
+            );
+            CREATE TABLE orders (
+                id bigint,
+                user_id integer REFERENCES users (id),
+                total numeric(10, 2),
+                CONSTRAINT orders_pk PRIMARY KEY (id)
+            );
+            CREATE INDEX idx_orders_user ON orders (user_id DESC) INCLUDE (total) WITH (fillfactor = 70) WHERE total > 0;
+            ALTER TYPE status ADD VALUE 'pending' BEFORE 'disabled';
+            ALTER TABLE users ADD COLUMN name text, ALTER COLUMN created_at SET NOT NULL;
+            ALTER TABLE users DROP COLUMN tags;
+            COMMENT ON TABLE users IS 'Registered accounts';
+            "#,
+        );
+
+        assert!(parsed.warnings.is_empty(), "{:?}", parsed.warnings);
+        let schema = parsed.schema;
+
+        assert_eq!(schema.enums[0].values, vec!["active", "pending", "disabled"]);
+
+        let users = schema.find_table("users").unwrap();
+        let names: Vec<_> = users.columns.iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(names, vec!["id", "email", "state", "created_at", "name"]);
+        let id = users.find_column("id").unwrap();
+        assert_eq!(id.data_type, "integer");
+        assert_eq!(id.default_value.as_deref(), Some("nextval('users_id_seq'::regclass)"));
+        assert_eq!(users.primary_key.as_ref().unwrap().name, "users_pkey");
+        assert_eq!(users.unique_constraints[0].name, "users_email_key");
+        assert_eq!(users.find_column("email").unwrap().data_type, "varchar(255)");
+        assert_eq!(users.find_column("created_at").unwrap().data_type, "timestamp with time zone");
+        assert!(!users.find_column("created_at").unwrap().is_nullable);
+        assert_eq!(users.comment.as_deref(), Some("Registered accounts"));
+
+        let orders = schema.find_table("orders").unwrap();
+        assert_eq!(orders.primary_key.as_ref().unwrap().name, "orders_pk");
+        assert_eq!(orders.foreign_keys[0].name, "orders_user_id_fkey");
+        assert_eq!(orders.find_column("total").unwrap().data_type, "numeric(10,2)");
+        assert_eq!(orders.indexes[0].predicate.as_deref(), Some("total > 0"));
+        assert_eq!(orders.indexes[0].column_order(0), IndexColumnOrder { descending: true, nulls_first: true });
+        assert_eq!(orders.indexes[0].include_columns, vec!["total"]);
+        assert_eq!(orders.indexes[0].storage_parameters, vec!["fillfactor=70"]);
+        assert_eq!(schema.indexes.len(), 1);
+    }
+
+    #[test]
+    fn test_parse_unlogged_tables() {
+        let parsed = parse_schema_sql(
+            r#"
+            -- Scratch data, rebuilt on demand
+            CREATE UNLOGGED TABLE cache (key text PRIMARY KEY, value jsonb);
+            CREATE TABLE events (id bigint);
+            ALTER TABLE public.events SET UNLOGGED;
+            ALTER TABLE IF EXISTS cache SET LOGGED;
+            ALTER TABLE IF EXISTS missing SET LOGGED;
+            "#,
+        );
+
+        assert!(parsed.warnings.is_empty(), "{:?}", parsed.warnings);
+        let cache = parsed.schema.find_table("cache").unwrap();
+        assert!(!cache.unlogged);
+        assert_eq!(cache.primary_key.as_ref().unwrap().name, "cache_pkey");
+        assert!(parsed.schema.find_table("events").unwrap().unlogged);
+
+        let parsed = parse_schema_sql("CREATE UNLOGGED TABLE cache (key text);");
+        assert!(parsed.schema.find_table("cache").unwrap().unlogged);
+    }
+
+    #[test]
+    fn test_parse_table_storage_parameters() {
+        let parsed = parse_schema_sql(
+            r#"
+            CREATE TABLE events (id bigint) WITH (fillfactor = 80, autovacuum_enabled = false);
+            ALTER TABLE ONLY public.events SET (autovacuum_vacuum_scale_factor = 0.01, FILLFACTOR = 70);
+            ALTER TABLE events RESET (autovacuum_enabled);
+            "#,
+        );
+
+        assert!(parsed.warnings.is_empty(), "{:?}", parsed.warnings);
+        assert_eq!(
+            parsed.schema.find_table("events").unwrap().storage_parameters,
+            vec!["autovacuum_vacuum_scale_factor=0.01", "fillfactor=70"]
+        );
+    }
+
+    #[test]
+    fn test_parse_foreign_key_options() {
+        let parsed = parse_schema_sql(
+            r#"
+            CREATE TABLE users (id integer PRIMARY KEY);
+            CREATE TABLE orders (
+                id bigint,
+                user_id integer REFERENCES public.users MATCH FULL ON DELETE CASCADE,
+                reviewer_id integer,
+                CONSTRAINT orders_reviewer_fk FOREIGN KEY (reviewer_id) REFERENCES users (id)
+                    ON UPDATE SET NULL DEFERRABLE INITIALLY DEFERRED
+            );
+            ALTER TABLE orders ADD CONSTRAINT orders_id_fk FOREIGN KEY (id) REFERENCES users (id) MATCH SIMPLE DEFERRABLE;
+            ALTER TABLE orders ADD CONSTRAINT orders_reviewer_user_fk FOREIGN KEY (reviewer_id) REFERENCES users (id) NOT VALID;
+            ALTER TABLE orders ADD CONSTRAINT orders_user_fk FOREIGN KEY (user_id) REFERENCES users (id) not valid;
+            ALTER TABLE public.orders VALIDATE CONSTRAINT orders_user_fk;
+            "#,
+        );
+
+        assert!(parsed.warnings.is_empty(), "{:?}", parsed.warnings);
+        let orders = parsed.schema.find_table("orders").unwrap();
+        let fk = |name: &str| orders.foreign_keys.iter().find(|fk| fk.name == name).unwrap();
+
+        let user = fk("orders_user_id_fkey");
+        assert_eq!(user.referenced_columns, vec!["id"]);
+        assert_eq!(user.match_type, "FULL");
+        assert_eq!(user.on_delete, "CASCADE");
+        assert_eq!(user.on_update, "NO ACTION");
+        assert!(!user.deferrable);
+
+        let reviewer = fk("orders_reviewer_fk");
+        assert_eq!(reviewer.match_type, "SIMPLE");
+        assert_eq!(reviewer.on_update, "SET NULL");
+        assert!(reviewer.deferrable && reviewer.initially_deferred);
+
+        let id = fk("orders_id_fk");
+        assert!(id.deferrable && !id.initially_deferred);
+        assert!(id.validated);
+
+        assert!(!fk("orders_reviewer_user_fk").validated);
+        assert!(fk("orders_user_fk").validated);
+    }
+
+    #[test]
+    fn test_parse_column_storage_and_statistics() {
+        let parsed = parse_schema_sql(
+            r#"
+            CREATE TABLE events (id integer, payload jsonb, body text);
+            ALTER TABLE ONLY public.events ALTER COLUMN payload SET STORAGE EXTERNAL;
+            ALTER TABLE events ALTER body SET STORAGE extended;
+            ALTER TABLE events ALTER COLUMN id SET STATISTICS 1000;
+            ALTER TABLE events ALTER COLUMN payload SET STATISTICS 500;
+            ALTER TABLE events ALTER COLUMN payload SET STATISTICS -1;
+            "#,
+        );
+
+        assert!(parsed.warnings.is_empty(), "{:?}", parsed.warnings);
+        let events = parsed.schema.find_table("events").unwrap();
+        let payload = events.find_column("payload").unwrap();
+        assert_eq!(payload.storage.as_deref(), Some("EXTERNAL"));
+        assert_eq!(payload.statistics_target, None);
+        // The type's own storage is not recorded
+        assert_eq!(events.find_column("body").unwrap().storage, None);
+        assert_eq!(events.find_column("id").unwrap().statistics_target, Some(1000));
+    }
+
+    #[test]
+    fn test_unsupported_statements_are_reported() {
+        let parsed = parse_schema_sql(
+            "CREATE TABLE a (id int);\nCREATE VIEW v AS SELECT 1;\nALTER TABLE missing ADD COLUMN x int;",
+        );
+
+        assert_eq!(parsed.schema.tables.len(), 1);
+        assert_eq!(parsed.warnings.len(), 2);
+    }
+}
@@ -0,0 +1,70 @@
+//! Reconstruct the schema a migrations directory produces.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use crate::render::version::compare_folder_names;
+use super::parse::{apply_sql, ParsedSchema};
+
+/// Replay every `up.sql` under `dir` in migration order into a schema model.
+///
+/// Migrations are the `<version>__<name>` folders written by
+/// `render_migration_files`, ordered by `compare_folder_names`.
+pub fn replay_migrations(dir: &str) -> Result<ParsedSchema, String> {
+    let mut parsed = ParsedSchema::default();
+    for folder in migration_folders(dir)? {
+        let name = folder.file_name()
+            .and_then(|n| n.to_str())
+            .unwrap_or("")
+            .to_string();
+        let sql = fs::read_to_string(folder.join("up.sql"))
+            .map_err(|e| format!("Failed to read {}/up.sql: {}", name, e))?;
+
+        for warning in apply_sql(&mut parsed.schema, &sql) {
+            parsed.warnings.push(format!("{}: {}", name, warning));
+        }
+    }
+
+    Ok(parsed)
+}
+
+/// The migration folders under `dir`, those with an `up.sql`, in order.
+pub(crate) fn migration_folders(dir: &str) -> Result<Vec<PathBuf>, String> {
+    let path = Path::new(dir);
+    if !path.is_dir() {
+        return Err(format!("Migrations directory not found: {}", dir));
+    }
+
+    let mut folders: Vec<_> = fs::read_dir(path)
+        .map_err(|e| format!("Failed to read directory: {}", e))?
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|p| p.join("up.sql").is_file())
+        .collect();
+    folders.sort_by(|a, b| compare_folder_names(
+        &a.file_name().unwrap_or_default().to_string_lossy(),
+        &b.file_name().unwrap_or_default().to_string_lossy(),
+    ));
+    Ok(folders)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_replay_in_folder_order() {
+        let dir = std::env::temp_dir().join(format!("pgshift_replay_{}", uuid::Uuid::new_v4()));
+        for (folder, sql) in [
+            ("20260102000000__add_email", "ALTER TABLE users ADD COLUMN email text;"),
+            ("20260101000000__init", "CREATE TABLE users (id integer PRIMARY KEY);"),
+        ] {
+            fs::create_dir_all(dir.join(folder)).unwrap();
+            fs::write(dir.join(folder).join("up.sql"), sql).unwrap();
+        }
+
+        let parsed = replay_migrations(dir.to_str().unwrap()).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert!(parsed.warnings.is_empty(), "{:?}", parsed.warnings);
+        assert_eq!(parsed.schema.find_table("users").unwrap().columns.len(), 2);
+    }
+}
@@ -0,0 +1,73 @@
+//! Rows lost by dropping tables and columns.
+//!
+//! A dropped table or column is marked dangerous whatever it holds. Counting
+//! the rows it holds on the target turns that flag into "Drop table orders
+//! (4.2M rows)", so an empty leftover can be told apart from live data.
+
+use serde::{Deserialize, Serialize};
+use super::diff::{DiffItem, DiffKind};
+
+/// Rows a dropped table or column holds on the target.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub struct DataLoss {
+    /// Rows of the table.
+    pub rows: i64,
+    /// Rows with a value in the dropped column; None for a dropped table.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub non_null_rows: Option<i64>,
+    /// Taken from the planner statistics rather than counted.
+    #[serde(default)]
+    pub estimated: bool,
+}
+
+impl DataLoss {
+    /// e.g. `4.2M rows` or `~4.2M rows, 3.1M with a value`.
+    pub fn summary(&self) -> String {
+        let approx = if self.estimated { "~" } else { "" };
+        let rows = format!("{}{} {}", approx, format_count(self.rows), if self.rows == 1 { "row" } else { "rows" });
+        match self.non_null_rows {
+            Some(values) => format!("{}, {}{} with a value", rows, approx, format_count(values)),
+            None => rows,
+        }
+    }
+}
+
+/// The table, and the column for a dropped column, whose data `item` drops.
+pub fn dropped_data(item: &DiffItem) -> Option<(&str, Option<&str>)> {
+    if item.kind != DiffKind::Removed {
+        return None;
+    }
+    match item.object_type.as_str() {
+        "table" => Some((&item.object_name, None)),
+        "column" => item.object_name.split_once('.').map(|(table, column)| (table, Some(column))),
+        _ => None,
+    }
+}
+
+/// A count for plan output, e.g. `4.2M`.
+pub fn format_count(count: i64) -> String {
+    const UNITS: &[(i64, &str)] = &[(1_000_000_000, "B"), (1_000_000, "M"), (1_000, "K")];
+
+    UNITS.iter()
+        .find(|(size, _)| count.abs() >= *size)
+        .map_or_else(|| count.to_string(), |(size, unit)| format!("{:.1}{}", count as f64 / *size as f64, unit))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_data_loss_summary() {
+        let table = DataLoss { rows: 4_200_000, non_null_rows: None, estimated: false };
+        assert_eq!(table.summary(), "4.2M rows");
+        let column = DataLoss { rows: 12_500, non_null_rows: Some(1), estimated: true };
+        assert_eq!(column.summary(), "~12.5K rows, ~1 with a value");
+        assert_eq!(DataLoss { rows: 1, non_null_rows: None, estimated: false }.summary(), "1 row");
+
+        let item = DiffItem::new(DiffKind::Removed, "column", "orders.note", "", "", "", true);
+        assert_eq!(dropped_data(&item), Some(("orders", Some("note"))));
+        let item = DiffItem::new(DiffKind::Added, "table", "orders", "", "", "", false);
+        assert_eq!(dropped_data(&item), None);
+    }
+}
@@ -0,0 +1,97 @@
+//! Changes between two diff reports.
+//!
+//! Drift monitoring compares the same databases again and again; most items
+//! are the same every time. [`compare_reports`] sorts the items of a later
+//! report against an earlier one, so only what is new, resolved or changed
+//! needs a look. Items match by their content-derived id, and an object whose
+//! change differs between the reports is matched by its type and name.
+
+use serde::{Deserialize, Serialize};
+use super::diff::{DiffItem, DiffReport};
+
+/// An object reported in both reports with a different change.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChangedItem {
+    pub previous: DiffItem,
+    pub current: DiffItem,
+}
+
+/// Items of a current report, sorted against a previous one.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ReportDelta {
+    /// Only in the current report.
+    pub new: Vec<DiffItem>,
+    /// Only in the previous report.
+    pub resolved: Vec<DiffItem>,
+    /// Same object in both, with a different change.
+    pub changed: Vec<ChangedItem>,
+    /// Identical in both, as in the current report.
+    pub unchanged: Vec<DiffItem>,
+}
+
+/// Sort the items of `current` against those of `previous`.
+pub fn compare_reports(previous: &DiffReport, current: &DiffReport) -> ReportDelta {
+    let mut delta = ReportDelta::default();
+    let same_object = |a: &DiffItem, b: &DiffItem| a.object_type == b.object_type && a.object_name == b.object_name;
+
+    let mut remaining: Vec<&DiffItem> = previous.items.iter().collect();
+    let mut unmatched = Vec::new();
+    for item in &current.items {
+        match remaining.iter().position(|p| p.id == item.id) {
+            Some(i) => {
+                remaining.remove(i);
+                delta.unchanged.push(item.clone());
+            }
+            None => unmatched.push(item),
+        }
+    }
+
+    for item in unmatched {
+        match remaining.iter().position(|p| same_object(p, item)) {
+            Some(i) => delta.changed.push(ChangedItem {
+                previous: remaining.remove(i).clone(),
+                current: item.clone(),
+            }),
+            None => delta.new.push(item.clone()),
+        }
+    }
+    delta.resolved = remaining.into_iter().cloned().collect();
+
+    delta
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::diff::DiffKind;
+
+    fn report(items: Vec<DiffItem>) -> DiffReport {
+        DiffReport { items, ..DiffReport::new() }
+    }
+
+    fn item(name: &str, up_sql: &str) -> DiffItem {
+        DiffItem::new(DiffKind::Modified, "column", name, "", up_sql, "", false)
+    }
+
+    #[test]
+    fn test_compare_reports() {
+        let previous = report(vec![
+            item("users.email", "ALTER TABLE users ALTER COLUMN email SET NOT NULL;"),
+            item("users.age", "ALTER TABLE users ALTER COLUMN age TYPE integer;"),
+            item("orders.total", "ALTER TABLE orders ALTER COLUMN total TYPE numeric;"),
+        ]);
+        let current = report(vec![
+            item("users.email", "ALTER TABLE users ALTER COLUMN email SET NOT NULL;"),
+            item("users.age", "ALTER TABLE users ALTER COLUMN age TYPE bigint;"),
+            item("users.name", "ALTER TABLE users ALTER COLUMN name SET NOT NULL;"),
+        ]);
+
+        let delta = compare_reports(&previous, &current);
+        let names = |items: &[DiffItem]| items.iter().map(|i| i.object_name.clone()).collect::<Vec<_>>();
+        assert_eq!(names(&delta.unchanged), vec!["users.email"]);
+        assert_eq!(names(&delta.new), vec!["users.name"]);
+        assert_eq!(names(&delta.resolved), vec!["orders.total"]);
+        assert_eq!(delta.changed.len(), 1);
+        assert!(delta.changed[0].current.generated_up_sql.contains("bigint"));
+    }
+}
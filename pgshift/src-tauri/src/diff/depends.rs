@@ -0,0 +1,212 @@
+//! Dependencies between diff items.
+//!
+//! Each item names the objects it creates or drops in `provides` and the
+//! objects those refer to in `requires`, as keys like `table:users` or
+//! `key:users(email)`. [`dependency_order`] sorts items so that everything
+//! an object refers to is created before it, and dropped after it.
+
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap};
+use crate::model::schema::{Column, Constraint, ForeignKey, Index, Table};
+use super::diff::{default_function_names, is_builtin_type, normalize_function_name, DiffItem, DiffKind};
+
+pub(crate) fn table_key(name: &str) -> String {
+    format!("table:{}", name)
+}
+
+pub(crate) fn column_key(table_name: &str, column_name: &str) -> String {
+    format!("column:{}.{}", table_name, column_name)
+}
+
+/// Key of a set of columns a foreign key can reference; the order of the
+/// columns does not matter.
+pub(crate) fn unique_key(table_name: &str, columns: &[String]) -> String {
+    let mut columns = columns.to_vec();
+    columns.sort();
+    format!("key:{}({})", table_name, columns.join(","))
+}
+
+pub(crate) fn type_key(name: &str) -> String {
+    format!("type:{}", name)
+}
+
+pub(crate) fn function_key(name: &str) -> String {
+    format!("function:{}", normalize_function_name(name))
+}
+
+pub(crate) fn role_key(name: &str) -> String {
+    format!("role:{}", name)
+}
+
+/// Key of a column's type, unless it is built in.
+fn column_type_key(column: &Column) -> Option<String> {
+    let base = column.data_type.trim_end_matches("[]");
+    (!is_builtin_type(base)).then(|| type_key(base.trim_matches('"')))
+}
+
+/// Types and functions a column uses.
+pub(crate) fn column_requires(column: &Column) -> Vec<String> {
+    let functions = column.default_value.iter().flat_map(|d| default_function_names(d));
+    column_type_key(column)
+        .into_iter()
+        .chain(functions.map(|name| function_key(&name)))
+        .collect()
+}
+
+/// Keys a constraint provides.
+pub(crate) fn constraint_provides(table_name: &str, constraint: &Constraint) -> Vec<String> {
+    vec![unique_key(table_name, &constraint.columns)]
+}
+
+/// Keys a unique index on plain columns provides; other indexes provide none.
+pub(crate) fn index_provides(table_name: &str, index: &Index) -> Vec<String> {
+    if index.is_unique && index.predicate.is_none() {
+        vec![unique_key(table_name, &index.columns)]
+    } else {
+        Vec::new()
+    }
+}
+
+/// The table and unique key a foreign key references.
+pub(crate) fn foreign_key_requires(fk: &ForeignKey) -> Vec<String> {
+    vec![table_key(&fk.referenced_table), unique_key(&fk.referenced_table, &fk.referenced_columns)]
+}
+
+/// A table, its columns and the keys it is created with.
+pub(crate) fn table_provides(table: &Table) -> Vec<String> {
+    let mut keys = vec![table_key(&table.name)];
+    keys.extend(table.columns.iter().map(|c| column_key(&table.name, &c.name)));
+    keys.extend(table.primary_key.iter().flat_map(|pk| constraint_provides(&table.name, pk)));
+    keys.extend(table.unique_constraints.iter().flat_map(|uc| constraint_provides(&table.name, uc)));
+    keys.extend(table.indexes.iter().flat_map(|idx| index_provides(&table.name, idx)));
+    keys
+}
+
+/// Parents, column types and default functions of a table.
+pub(crate) fn table_requires(table: &Table) -> Vec<String> {
+    let mut keys: Vec<String> = table.inherits.iter().map(|p| table_key(p)).collect();
+    keys.extend(table.columns.iter().filter(|c| !c.inherited).flat_map(column_requires));
+    keys
+}
+
+/// Order `items` so that dependencies come first.
+///
+/// An added or modified item comes after the added or modified items
+/// providing what it requires; a removed item comes before the removed items
+/// providing what it required. Among items free to go, the one with the
+/// lowest `rank` and then the earliest in `items` goes first, so without
+/// dependencies the order is by rank alone. Items without a rank are left
+/// out. A cycle is broken at its lowest ranked item.
+pub(crate) fn dependency_order<R, F>(items: &[DiffItem], rank: F) -> Vec<&DiffItem>
+where
+    R: Ord + Copy,
+    F: Fn(&DiffItem) -> Option<R>,
+{
+    let ranked: Vec<(usize, R)> = items.iter()
+        .enumerate()
+        .filter_map(|(i, item)| rank(item).map(|r| (i, r)))
+        .collect();
+    let removed = |i: usize| items[i].kind == DiffKind::Removed;
+
+    let mut providers: HashMap<&str, Vec<usize>> = HashMap::new();
+    for &(i, _) in &ranked {
+        for key in &items[i].provides {
+            providers.entry(key.as_str()).or_default().push(i);
+        }
+    }
+
+    // Edges run from the item that must go first
+    let mut next: HashMap<usize, Vec<usize>> = HashMap::new();
+    let mut waiting: HashMap<usize, usize> = HashMap::new();
+    for &(i, _) in &ranked {
+        for key in &items[i].requires {
+            for &p in providers.get(key.as_str()).into_iter().flatten() {
+                let edge = match (removed(i), removed(p)) {
+                    _ if p == i => None,
+                    (false, false) => Some((p, i)),
+                    (true, true) => Some((i, p)),
+                    _ => None,
+                };
+                if let Some((from, to)) = edge {
+                    if !next.get(&from).is_some_and(|n| n.contains(&to)) {
+                        next.entry(from).or_default().push(to);
+                        *waiting.entry(to).or_default() += 1;
+                    }
+                }
+            }
+        }
+    }
+
+    let ranks: HashMap<usize, R> = ranked.iter().copied().collect();
+    let mut ready: BinaryHeap<Reverse<(R, usize)>> = ranked.iter()
+        .filter(|(i, _)| !waiting.contains_key(i))
+        .map(|&(i, r)| Reverse((r, i)))
+        .collect();
+    let mut done = vec![false; items.len()];
+    let mut ordered = Vec::with_capacity(ranked.len());
+
+    while ordered.len() < ranked.len() {
+        let i = match ready.pop() {
+            Some(Reverse((_, i))) if done[i] => continue,
+            Some(Reverse((_, i))) => i,
+            // Only a cycle is left; release its lowest ranked item
+            None => match ranked.iter().filter(|(i, _)| !done[*i]).min_by_key(|&&(i, r)| (r, i)) {
+                Some(&(i, _)) => i,
+                None => break,
+            },
+        };
+        done[i] = true;
+        ordered.push(&items[i]);
+        for &to in next.get(&i).into_iter().flatten() {
+            let count = waiting.entry(to).or_default();
+            *count = count.saturating_sub(1);
+            if *count == 0 && !done[to] {
+                ready.push(Reverse((ranks[&to], to)));
+            }
+        }
+    }
+
+    ordered
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn item(kind: DiffKind, name: &str, provides: &[&str], requires: &[&str]) -> DiffItem {
+        let mut item = DiffItem::new(kind, "constraint", name, "", "", "", false);
+        item.provides = provides.iter().map(|k| k.to_string()).collect();
+        item.requires = requires.iter().map(|k| k.to_string()).collect();
+        item
+    }
+
+    fn names(items: &[&DiffItem]) -> Vec<String> {
+        items.iter().map(|i| i.object_name.clone()).collect()
+    }
+
+    #[test]
+    fn test_dependency_order() {
+        let items = vec![
+            item(DiffKind::Added, "orders.fk", &[], &["key:users(email)"]),
+            item(DiffKind::Added, "users.email_key", &["key:users(email)"], &[]),
+            item(DiffKind::Removed, "accounts.pkey", &["key:accounts(id)"], &[]),
+            item(DiffKind::Removed, "payments.fk", &[], &["key:accounts(id)"]),
+            item(DiffKind::Added, "unranked", &[], &[]),
+        ];
+        let rank = |i: &DiffItem| (i.object_name != "unranked").then_some(0);
+
+        let ordered = dependency_order(&items, rank);
+        assert_eq!(names(&ordered), vec!["users.email_key", "orders.fk", "payments.fk", "accounts.pkey"]);
+
+        // Dependencies win over ranks
+        let by_name = |i: &DiffItem| Some(i.object_name.len());
+        assert_eq!(names(&dependency_order(&items[..2], by_name))[0], "users.email_key");
+
+        // A cycle does not lose items
+        let cycle = vec![
+            item(DiffKind::Added, "a", &["a"], &["b"]),
+            item(DiffKind::Added, "b", &["b"], &["a"]),
+        ];
+        assert_eq!(names(&dependency_order(&cycle, |_| Some(0))), vec!["a", "b"]);
+    }
+}
@@ -1,8 +1,20 @@
 //! Schema diff engine for comparing PostgreSQL schemas.
 
 use serde::{Deserialize, Serialize};
-use uuid::Uuid;
-use crate::model::schema::{SchemaModel, Table, Column, Index, EnumType};
+use sha2::{Digest, Sha256};
+use crate::model::schema::{SchemaModel, Table, Column, Index, EnumType, Policy, Privilege, Function, Trigger, ForeignKey, ForeignServer, UserMapping, ForeignTable, Publication, Subscription};
+use super::depends::{
+    column_key, column_requires, constraint_provides, foreign_key_requires, function_key, index_provides,
+    table_provides, table_requires, type_key,
+};
+use super::data_loss::DataLoss;
+use super::disk::DiskEstimate;
+use super::ignore::IgnoreConfig;
+use super::normalize::bodies_equal;
+use super::not_null::not_null_column_steps;
+use super::renames::{apply_renames, RenameMapping};
+use crate::extension::compare_custom_objects;
+use crate::db::capabilities::{GENERATED_COLUMNS_SINCE, INDEX_INCLUDE_SINCE};
 
 /// The kind of difference detected.
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
@@ -11,11 +23,17 @@ pub enum DiffKind {
     Added,
     Removed,
     Modified,
+    /// Same values in a different order; see [`EnumReorder`].
+    Reordered,
+    /// Same object under another name.
+    Renamed,
 }
 
 /// A single diff item representing a schema difference.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct DiffItem {
+    /// Hash of the item's kind, object and SQL; the same change gets the same
+    /// id every time it is compared.
     pub id: String,
     pub kind: DiffKind,
     pub object_type: String,
@@ -24,10 +42,136 @@ pub struct DiffItem {
     pub generated_up_sql: String,
     pub generated_down_sql: String,
     pub dangerous: bool,
+    /// The statement rewrites the whole table under an ACCESS EXCLUSIVE lock.
+    #[serde(default)]
+    pub rewrites_table: bool,
+    /// Set when someone confirmed that a dangerous change is intended.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub acknowledgment: Option<DangerAcknowledgment>,
+    /// Disk space the statement needs, when the target's sizes are known.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub disk_estimate: Option<DiskEstimate>,
+    /// Rows the dropped table or column holds, once counted on the target.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub data_loss: Option<DataLoss>,
+    /// The orderings and strategies of a [`DiffKind::Reordered`] enum.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub enum_reorder: Option<EnumReorder>,
+    /// Objects the item creates, or drops when removed; see
+    /// [`super::depends`].
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub provides: Vec<String>,
+    /// Objects the created or dropped object refers to.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub requires: Vec<String>,
+    /// Properties that differ, for changes to an existing object.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub changes: Vec<PropertyChange>,
+}
+
+/// A property of an object whose value differs between the databases.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct PropertyChange {
+    /// Field name in the schema model, e.g. `data_type`.
+    pub property: String,
+    /// Value in the target (current); null when unset.
+    pub old: serde_json::Value,
+    /// Value in the source (desired); null when unset.
+    pub new: serde_json::Value,
+}
+
+impl PropertyChange {
+    pub(crate) fn new(property: &str, old: impl Serialize, new: impl Serialize) -> Self {
+        Self {
+            property: property.to_string(),
+            old: serde_json::to_value(old).unwrap_or_default(),
+            new: serde_json::to_value(new).unwrap_or_default(),
+        }
+    }
+}
+
+/// The `properties` of two versions of an object that differ, compared as
+/// serialized.
+pub(crate) fn property_changes<T: Serialize>(source: &T, target: &T, properties: &[&str]) -> Vec<PropertyChange> {
+    let source = serde_json::to_value(source).unwrap_or_default();
+    let target = serde_json::to_value(target).unwrap_or_default();
+    properties
+        .iter()
+        .filter_map(|&property| {
+            let old = target.get(property).cloned().unwrap_or_default();
+            let new = source.get(property).cloned().unwrap_or_default();
+            (old != new).then(|| PropertyChange { property: property.to_string(), old, new })
+        })
+        .collect()
+}
+
+/// An enum whose values match but are declared in a different order.
+///
+/// Order matters for comparisons and sorting on enum columns, but the only
+/// way to change it is to recreate the type and convert every column using
+/// it. Neither is picked silently: the item offers both strategies and its
+/// SQL follows the chosen one, ignoring the order until someone decides.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct EnumReorder {
+    /// Value order in the source (desired).
+    pub source_order: Vec<String>,
+    /// Value order in the target (current).
+    pub target_order: Vec<String>,
+    /// Strategy the item's SQL currently follows.
+    pub strategy: EnumReorderStrategy,
+    /// What each strategy would run.
+    pub resolutions: Vec<EnumReorderResolution>,
+}
+
+/// How to deal with a differing enum value order.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum EnumReorderStrategy {
+    /// Keep the target's order.
+    IgnoreOrder,
+    /// Recreate the type in the source's order and convert its columns.
+    RecreateType,
+}
+
+/// The SQL one strategy for an [`EnumReorder`] generates.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct EnumReorderResolution {
+    pub strategy: EnumReorderStrategy,
+    pub description: String,
+    pub up_sql: String,
+    pub down_sql: String,
+    #[serde(default)]
+    pub rewrites_table: bool,
+}
+
+/// A record that a dangerous change was reviewed and is deliberate.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct DangerAcknowledgment {
+    pub acknowledged_by: String,
+    /// RFC 3339 timestamp.
+    pub acknowledged_at: String,
+    pub reason: String,
+}
+
+impl DangerAcknowledgment {
+    /// Acknowledge now; both who and why are required.
+    pub fn new(acknowledged_by: &str, reason: &str) -> Result<Self, String> {
+        if acknowledged_by.trim().is_empty() {
+            return Err("An acknowledgment needs the name of who made it".to_string());
+        }
+        if reason.trim().is_empty() {
+            return Err("An acknowledgment needs a reason".to_string());
+        }
+        Ok(Self {
+            acknowledged_by: acknowledged_by.trim().to_string(),
+            acknowledged_at: chrono::Utc::now().to_rfc3339(),
+            reason: reason.trim().to_string(),
+        })
+    }
 }
 
 impl DiffItem {
-    fn new(
+    pub(crate) fn new(
         kind: DiffKind,
         object_type: &str,
         object_name: &str,
@@ -37,7 +181,7 @@ impl DiffItem {
         dangerous: bool,
     ) -> Self {
         Self {
-            id: Uuid::new_v4().to_string(),
+            id: Self::content_id(&kind, object_type, object_name, up_sql, down_sql),
             kind,
             object_type: object_type.to_string(),
             object_name: object_name.to_string(),
@@ -45,7 +189,56 @@ impl DiffItem {
             generated_up_sql: up_sql.to_string(),
             generated_down_sql: down_sql.to_string(),
             dangerous,
+            rewrites_table: false,
+            acknowledgment: None,
+            disk_estimate: None,
+            data_loss: None,
+            enum_reorder: None,
+            provides: Vec::new(),
+            requires: Vec::new(),
+            changes: Vec::new(),
+        }
+    }
+    
+    /// Stable id of a change: the first 128 bits of a SHA-256 over its kind,
+    /// object type and name, and SQL, in hex. Re-running a diff gives the
+    /// same ids, so they can be kept in approvals, ignore lists and baselines.
+    fn content_id(kind: &DiffKind, object_type: &str, object_name: &str, up_sql: &str, down_sql: &str) -> String {
+        let kind = format!("{:?}", kind).to_lowercase();
+        let mut hasher = Sha256::new();
+        for part in [kind.as_str(), object_type, object_name, up_sql, down_sql] {
+            hasher.update(part.as_bytes());
+            hasher.update([0]);
         }
+        hex::encode(&hasher.finalize()[..16])
+    }
+    
+    /// Set the objects the item provides and requires.
+    pub(crate) fn with_dependencies(mut self, provides: Vec<String>, requires: Vec<String>) -> Self {
+        self.provides = provides;
+        self.requires = requires;
+        self
+    }
+    
+    /// Set the properties that differ.
+    pub(crate) fn with_changes(mut self, changes: Vec<PropertyChange>) -> Self {
+        self.changes = changes;
+        self
+    }
+
+    /// Switch a reordered enum item to `strategy`, replacing its SQL.
+    pub fn resolve_enum_reorder(&mut self, strategy: EnumReorderStrategy) -> Result<(), String> {
+        let reorder = self.enum_reorder.as_mut()
+            .ok_or_else(|| format!("{} {} is not a reordered enum", self.object_type, self.object_name))?;
+        let resolution = reorder.resolutions.iter()
+            .find(|r| r.strategy == strategy)
+            .ok_or_else(|| format!("Strategy {:?} is not available for {}", strategy, self.object_name))?;
+
+        self.generated_up_sql = resolution.up_sql.clone();
+        self.generated_down_sql = resolution.down_sql.clone();
+        self.rewrites_table = resolution.rewrites_table;
+        reorder.strategy = strategy;
+        Ok(())
     }
 }
 
@@ -53,9 +246,20 @@ impl DiffItem {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct DiffReport {
     pub items: Vec<DiffItem>,
+    /// Labels of the compared sides; connection strings never carry their
+    /// password.
+    #[serde(deserialize_with = "crate::db::connect::deserialize_connection_label")]
     pub source_connection: String,
+    #[serde(deserialize_with = "crate::db::connect::deserialize_connection_label")]
     pub target_connection: String,
     pub generated_at: String,
+    /// Problems with the comparison itself, e.g. both sides being the same database.
+    #[serde(default)]
+    pub warnings: Vec<String>,
+    /// Major version of the target server the SQL was generated for, when
+    /// known; see [`DiffOptions::target_major_version`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub target_major_version: Option<u32>,
 }
 
 impl DiffReport {
@@ -65,12 +269,27 @@ impl DiffReport {
             source_connection: String::new(),
             target_connection: String::new(),
             generated_at: chrono::Utc::now().to_rfc3339(),
+            warnings: Vec::new(),
+            target_major_version: None,
         }
     }
     
     pub fn has_dangerous(&self) -> bool {
         self.items.iter().any(|i| i.dangerous)
     }
+
+    /// Dangerous items nobody has acknowledged yet.
+    pub fn unacknowledged_dangerous(&self) -> usize {
+        self.items.iter().filter(|i| i.dangerous && i.acknowledgment.is_none()).count()
+    }
+
+    /// Disk space needed by the whole migration; None when no item has an estimate.
+    ///
+    /// The migration runs in one transaction, so the temporary space of every
+    /// statement adds up until it commits.
+    pub fn disk_estimate(&self) -> Option<DiskEstimate> {
+        self.items.iter().filter_map(|i| i.disk_estimate).reduce(|a, b| a + b)
+    }
 }
 
 impl Default for DiffReport {
@@ -97,7 +316,7 @@ const BUILTIN_TYPES: &[&str] = &[
 ];
 
 /// Check if a data type is a PostgreSQL built-in type.
-fn is_builtin_type(data_type: &str) -> bool {
+pub(crate) fn is_builtin_type(data_type: &str) -> bool {
     let lower = data_type.to_lowercase();
     // Remove any array suffix or size specifier
     let base_type = lower
@@ -120,963 +339,4480 @@ fn format_data_type(data_type: &str) -> String {
     }
 }
 
+/// Functions that are safe as column defaults when adding a column on PG11+.
+///
+/// These are stable, so PostgreSQL evaluates them once and stores the value
+/// in the catalog instead of rewriting every row.
+pub const DEFAULT_SAFE_DEFAULT_FUNCTIONS: &[&str] = &[
+    "now", "transaction_timestamp", "statement_timestamp",
+    "current_timestamp", "current_date", "current_time",
+    "localtimestamp", "localtime", "cast",
+];
+
+/// Options controlling which object categories are compared.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct DiffOptions {
+    /// Compare GRANT/REVOKE privileges (opt-in).
+    #[serde(default)]
+    pub include_privileges: bool,
+    /// Functions considered safe as column defaults. `None` uses
+    /// [`DEFAULT_SAFE_DEFAULT_FUNCTIONS`].
+    #[serde(default)]
+    pub safe_default_functions: Option<Vec<String>>,
+    /// Major version of the target server, e.g. 16; filled in from a live
+    /// target when not set. Before PG11 any default on an added column
+    /// rewrites the table, indexes lose their `INCLUDE` columns and, before
+    /// PG12, generated columns are reported as unsupported. See
+    /// [`crate::db::capabilities`].
+    #[serde(default)]
+    pub target_major_version: Option<u32>,
+    /// Compare function and trigger bodies as exact text instead of by
+    /// normalized hash (whitespace and comments ignored).
+    #[serde(default)]
+    pub strict_body_comparison: bool,
+    /// Compare foreign servers, user mappings and foreign tables (opt-in).
+    #[serde(default)]
+    pub include_foreign_data: bool,
+    /// Compare logical replication subscriptions (opt-in, as they carry
+    /// connection strings to the publisher).
+    #[serde(default)]
+    pub include_subscriptions: bool,
+    /// Tables and columns renamed between target and source, migrated with
+    /// `RENAME` instead of a drop and an add.
+    #[serde(default)]
+    pub renames: Vec<RenameMapping>,
+    /// Rename an index defined the same as one of the target's under another
+    /// name, instead of leaving the names to differ.
+    #[serde(default)]
+    pub rename_matching_indexes: bool,
+    /// Add foreign keys to existing tables `NOT VALID` and validate them in
+    /// a separate statement, so the table is not locked against writes while
+    /// its rows are checked.
+    #[serde(default)]
+    pub validate_constraints_separately: bool,
+    /// Add NOT NULL columns without a default to existing tables in steps
+    /// (nullable, backfilled in batches, then `SET NOT NULL`) instead of one
+    /// statement that fails on a table with rows.
+    #[serde(default)]
+    pub safe_migrations: bool,
+    /// Tables, columns and object types left out of the comparison.
+    #[serde(default)]
+    pub ignore: IgnoreConfig,
+    /// Object types to report, e.g. `index`; empty reports every type not
+    /// ignored. The schemas are still compared in full and the report is
+    /// filtered afterwards.
+    #[serde(default)]
+    pub include_types: Vec<String>,
+}
+
+/// Object types of the items the comparison reports, besides those of
+/// registered extensions.
+pub const OBJECT_TYPES: &[&str] = &[
+    "table", "column", "index", "enum", "constraint", "validation", "function", "trigger",
+    "policy", "rls", "privilege", "role", "role_membership", "inheritance", "persistence",
+    "storage_parameters", "rename", "foreign_server", "user_mapping", "foreign_table",
+    "publication", "subscription",
+];
+
+/// Object type named `name`, which may also be written in the plural
+/// (`tables`, `indexes`, `policies`, ...).
+pub fn object_type_name(name: &str) -> Result<String, String> {
+    let mut known = OBJECT_TYPES.iter().map(|t| t.to_string()).collect::<Vec<_>>();
+    known.extend(crate::extension::registered_object_types());
+
+    let singular = match name {
+        "indexes" | "indices" => Some("index".to_string()),
+        "policies" => Some("policy".to_string()),
+        _ => name.strip_suffix('s').map(str::to_string),
+    };
+    [Some(name.to_string()), singular]
+        .into_iter()
+        .flatten()
+        .find(|n| known.contains(n))
+        .ok_or_else(|| format!("Unknown object type '{}', expected one of: {}", name, known.join(", ")))
+}
+
+impl DiffOptions {
+    /// Check whether changes of `object_type` are reported.
+    pub fn reports_type(&self, object_type: &str) -> bool {
+        (self.include_types.is_empty() || self.include_types.iter().any(|t| t == object_type))
+            && !self.ignore.ignores_object_type(object_type)
+    }
+    
+    /// Check whether adding a column with this default rewrites the table.
+    pub fn default_rewrites_table(&self, default: &str) -> bool {
+        if self.target_major_version.is_some_and(|v| v < 11) {
+            return true;
+        }
+        
+        default_function_names(default).iter().any(|name| match &self.safe_default_functions {
+            Some(safe) => !safe.iter().any(|s| normalize_function_name(s) == *name),
+            None => !DEFAULT_SAFE_DEFAULT_FUNCTIONS.contains(&name.as_str()),
+        })
+    }
+}
+
+/// Lowercase a function name and strip any schema prefix and argument list,
+/// so `pg_catalog.now()` and `NOW` both become `now`.
+pub(crate) fn normalize_function_name(name: &str) -> String {
+    let name = name.trim();
+    let name = name.split('(').next().unwrap_or(name);
+    name.rsplit('.').next().unwrap_or(name).trim().trim_matches('"').to_lowercase()
+}
+
+/// Extract the names of functions called in a default expression.
+///
+/// String literals are skipped and `::type(...)` casts are not treated as calls.
+pub(crate) fn default_function_names(default: &str) -> Vec<String> {
+    let mut names = Vec::new();
+    let chars: Vec<char> = default.chars().collect();
+    let mut i = 0;
+    
+    while i < chars.len() {
+        let c = chars[i];
+        if c == '\'' {
+            // Skip string literal ('' is an escaped quote)
+            i += 1;
+            while i < chars.len() {
+                if chars[i] == '\'' {
+                    if i + 1 < chars.len() && chars[i + 1] == '\'' {
+                        i += 2;
+                        continue;
+                    }
+                    break;
+                }
+                i += 1;
+            }
+            i += 1;
+        } else if c.is_alphabetic() || c == '_' || c == '"' {
+            let start = i;
+            while i < chars.len() && (chars[i].is_alphanumeric() || matches!(chars[i], '_' | '.' | '"')) {
+                i += 1;
+            }
+            let is_cast = start >= 2 && chars[start - 1] == ':' && chars[start - 2] == ':';
+            let mut j = i;
+            while j < chars.len() && chars[j].is_whitespace() {
+                j += 1;
+            }
+            if !is_cast && j < chars.len() && chars[j] == '(' {
+                let ident: String = chars[start..i].iter().collect();
+                names.push(normalize_function_name(&ident));
+            }
+        } else {
+            i += 1;
+        }
+    }
+    
+    names
+}
+
 /// Compare two schema models and return a diff report.
 /// Source is what we want to achieve (the desired state).
 /// Target is the current state of the database.
 pub fn compare_schemas(source: &SchemaModel, target: &SchemaModel) -> DiffReport {
+    compare_schemas_with_options(source, target, &DiffOptions::default())
+}
+
+/// Compare two schema models using the given options.
+pub fn compare_schemas_with_options(
+    source: &SchemaModel,
+    target: &SchemaModel,
+    options: &DiffOptions,
+) -> DiffReport {
     let mut report = DiffReport::new();
     
+    let ignored;
+    let (source, target) = if options.ignore.is_empty() {
+        (source, target)
+    } else {
+        ignored = (options.ignore.apply(source), options.ignore.apply(target));
+        (&ignored.0, &ignored.1)
+    };
+    
+    // Compare against the target as it is after the renames
+    let renamed;
+    let target = if options.renames.is_empty() {
+        target
+    } else {
+        renamed = apply_renames(&mut report, source, target, &options.renames);
+        &renamed
+    };
+    
     // IMPORTANT: Compare ENUM types first (they must be created before tables that use them)
     compare_enums(&mut report, source, target);
     
-    // Find tables that need to be added (in source but not in target)
-    for source_table in &source.tables {
-        if target.find_table(&source_table.name).is_none() {
-            let up_sql = generate_create_table_sql(source_table);
-            let down_sql = format!("DROP TABLE IF EXISTS \"{}\" CASCADE;", source_table.name);
-            
-            report.items.push(DiffItem::new(
-                DiffKind::Added,
-                "table",
-                &source_table.name,
-                &format!("Create table '{}'", source_table.name),
-                &up_sql,
-                &down_sql,
-                false,
-            ));
-        }
+    compare_functions(&mut report, source, target, options);
+    
+    // Find tables that need to be added (in source but not in target);
+    // parents are created before the tables inheriting from them
+    let added_tables = source.tables.iter().filter(|t| target.find_table(&t.name).is_none());
+    for source_table in parents_first(added_tables.collect()) {
+        let up_sql = generate_create_table_sql(source_table);
+        let down_sql = format!("DROP TABLE IF EXISTS \"{}\" CASCADE;", source_table.name);
+        
+        report.items.push(DiffItem::new(
+            DiffKind::Added,
+            "table",
+            &source_table.name,
+            &format!("Create table '{}'", source_table.name),
+            &up_sql,
+            &down_sql,
+            false,
+        ).with_dependencies(table_provides(source_table), table_requires(source_table)));
     }
     
-    // Find tables that need to be removed (in target but not in source)
-    for target_table in &target.tables {
-        if source.find_table(&target_table.name).is_none() {
-            let up_sql = format!("DROP TABLE IF EXISTS \"{}\" CASCADE;", target_table.name);
-            let down_sql = generate_create_table_sql(target_table);
-            
-            report.items.push(DiffItem::new(
-                DiffKind::Removed,
-                "table",
-                &target_table.name,
-                &format!("Drop table '{}'", target_table.name),
-                &up_sql,
-                &down_sql,
-                true, // Dropping a table is dangerous
-            ));
+    // Foreign keys of new tables are added once every new table exists
+    for source_table in source.tables.iter().filter(|t| target.find_table(&t.name).is_none()) {
+        for fk in &source_table.foreign_keys {
+            report.items.push(add_foreign_key_item(&source_table.name, fk));
         }
     }
     
+    // Find tables that need to be removed (in target but not in source);
+    // children are dropped before their parents
+    let removed_tables = target.tables.iter().filter(|t| source.find_table(&t.name).is_none());
+    for target_table in parents_first(removed_tables.collect()).into_iter().rev() {
+        let up_sql = format!("DROP TABLE IF EXISTS \"{}\" CASCADE;", target_table.name);
+        let down_sql = generate_create_table_sql(target_table);
+        
+        report.items.push(DiffItem::new(
+            DiffKind::Removed,
+            "table",
+            &target_table.name,
+            &format!("Drop table '{}'", target_table.name),
+            &up_sql,
+            &down_sql,
+            true, // Dropping a table is dangerous
+        ).with_dependencies(table_provides(target_table), table_requires(target_table)));
+    }
+    
     // Compare tables that exist in both
     for source_table in &source.tables {
         if let Some(target_table) = target.find_table(&source_table.name) {
-            compare_tables(&mut report, source_table, target_table);
+            compare_tables(&mut report, source_table, target_table, options);
+        }
+    }
+    
+    if options.include_foreign_data {
+        compare_foreign_data(&mut report, source, target);
+    }
+    
+    compare_publications(&mut report, source, target);
+    
+    if options.include_subscriptions {
+        let unreadable: Vec<&str> = [("source", source), ("target", target)]
+            .into_iter()
+            .filter(|(_, schema)| schema.unreadable_object_types.iter().any(|t| t == "subscription"))
+            .map(|(side, _)| side)
+            .collect();
+        if unreadable.is_empty() {
+            compare_subscriptions(&mut report, source, target);
+        } else {
+            // An unreadable side would look like one without subscriptions
+            report.warnings.push(format!(
+                "Subscriptions were not compared: reading pg_subscription on the {} needs superuser",
+                unreadable.join(" and ")
+            ));
         }
     }
     
+    if options.include_privileges {
+        compare_privileges(&mut report, source, target);
+    }
+    
+    compare_custom_objects(&mut report, source, target);
+    
+    report.items.retain(|i| options.reports_type(&i.object_type));
+    
+    if let Some(major) = options.target_major_version {
+        adapt_to_server_version(&mut report, major);
+    }
+    
     report
 }
 
-/// Compare ENUM types between source and target.
-fn compare_enums(report: &mut DiffReport, source: &SchemaModel, target: &SchemaModel) {
-    // Find ENUMs that need to be added
-    for source_enum in &source.enums {
-        if let Some(target_enum) = target.find_enum(&source_enum.name) {
-            // ENUM exists, check if values differ
-            if source_enum.values != target_enum.values {
-                // Find new values to add
-                let new_values: Vec<&String> = source_enum.values.iter()
-                    .filter(|v| !target_enum.values.contains(v))
-                    .collect();
-                
-                // Find values to remove (dangerous!)
-                let removed_values: Vec<&String> = target_enum.values.iter()
-                    .filter(|v| !source_enum.values.contains(v))
-                    .collect();
-                
-                if !new_values.is_empty() {
-                    let up_sql = new_values.iter()
-                        .map(|v| format!("ALTER TYPE \"{}\" ADD VALUE IF NOT EXISTS '{}';", source_enum.name, v))
-                        .collect::<Vec<_>>()
-                        .join("\n");
-                    
-                    // Note: PostgreSQL doesn't support removing ENUM values easily
-                    let down_sql = format!("-- Cannot easily remove ENUM values in PostgreSQL\n-- Removed values: {:?}", new_values);
-                    
-                    report.items.push(DiffItem::new(
-                        DiffKind::Modified,
-                        "enum",
-                        &source_enum.name,
-                        &format!("Add values to enum '{}': {:?}", source_enum.name, new_values),
-                        &up_sql,
-                        &down_sql,
-                        false,
-                    ));
-                }
-                
-                if !removed_values.is_empty() {
-                    // Removing ENUM values requires recreating the type
-                    let up_sql = format!(
-                        "-- WARNING: Removing ENUM values requires recreating the type\n\
-                         -- Values to remove: {:?}\n\
-                         -- This is a destructive operation that requires manual handling",
-                        removed_values
-                    );
-                    let down_sql = removed_values.iter()
-                        .map(|v| format!("ALTER TYPE \"{}\" ADD VALUE IF NOT EXISTS '{}';", source_enum.name, v))
-                        .collect::<Vec<_>>()
-                        .join("\n");
-                    
-                    report.items.push(DiffItem::new(
-                        DiffKind::Modified,
-                        "enum",
-                        &source_enum.name,
-                        &format!("Remove values from enum '{}': {:?} (DANGEROUS)", source_enum.name, removed_values),
-                        &up_sql,
-                        &down_sql,
-                        true,
-                    ));
-                }
+/// Rewrite the SQL of `report` for a target running PostgreSQL `major`,
+/// warning about features the target does not have.
+fn adapt_to_server_version(report: &mut DiffReport, major: u32) {
+    report.target_major_version = Some(major);
+    for item in &mut report.items {
+        if major < INDEX_INCLUDE_SINCE {
+            let (up_sql, dropped) = strip_index_include(&item.generated_up_sql);
+            if !dropped.is_empty() {
+                report.warnings.push(format!(
+                    "PostgreSQL {} does not support INCLUDE columns; {} '{}' is created without {}",
+                    major,
+                    item.object_type,
+                    item.object_name,
+                    dropped.join(", ")
+                ));
+                item.generated_up_sql = up_sql;
             }
-        } else {
-            // ENUM doesn't exist, create it
-            let up_sql = generate_create_enum_sql(source_enum);
-            let down_sql = format!("DROP TYPE IF EXISTS \"{}\" CASCADE;", source_enum.name);
-            
-            report.items.push(DiffItem::new(
-                DiffKind::Added,
-                "enum",
-                &source_enum.name,
-                &format!("Create enum type '{}' with values: {:?}", source_enum.name, source_enum.values),
-                &up_sql,
-                &down_sql,
-                false,
+            item.generated_down_sql = strip_index_include(&item.generated_down_sql).0;
+        }
+        if major < GENERATED_COLUMNS_SINCE && item.generated_up_sql.contains(" GENERATED ALWAYS AS (") {
+            report.warnings.push(format!(
+                "PostgreSQL {} does not support generated columns; {} '{}' will fail on the target",
+                major, item.object_type, item.object_name
             ));
         }
+        item.id = DiffItem::content_id(
+            &item.kind,
+            &item.object_type,
+            &item.object_name,
+            &item.generated_up_sql,
+            &item.generated_down_sql,
+        );
+    }
+}
+
+/// `sql` without the `INCLUDE (...)` clauses of its `CREATE INDEX`
+/// statements, and the dropped columns.
+fn strip_index_include(sql: &str) -> (String, Vec<String>) {
+    let mut stripped = String::new();
+    let mut dropped = Vec::new();
+    let mut rest = sql;
+    while let Some(start) = rest.find(" INCLUDE (") {
+        let statement_start = rest[..start].rfind(';').map_or(0, |i| i + 1);
+        let list_start = start + " INCLUDE (".len();
+        let mut quoted = false;
+        let end = rest[list_start..].char_indices().find_map(|(i, c)| match c {
+            '"' => {
+                quoted = !quoted;
+                None
+            }
+            ')' if !quoted => Some(list_start + i),
+            _ => None,
+        });
+        let Some(end) = end.filter(|_| rest[statement_start..start].contains(" INDEX ")) else {
+            stripped.push_str(&rest[..list_start]);
+            rest = &rest[list_start..];
+            continue;
+        };
+        stripped.push_str(&rest[..start]);
+        dropped.extend(rest[list_start..end].split(", ").map(str::to_string));
+        rest = &rest[end + 1..];
+    }
+    stripped.push_str(rest);
+    (stripped, dropped)
+}
+
+/// Compare granted privileges between source and target.
+fn compare_privileges(report: &mut DiffReport, source: &SchemaModel, target: &SchemaModel) {
+    // Find privileges to grant
+    for source_priv in &source.privileges {
+        match target.privileges.iter().find(|t| t.same_grant(source_priv)) {
+            None => {
+                report.items.push(DiffItem::new(
+                    DiffKind::Added,
+                    "privilege",
+                    &privilege_label(source_priv),
+                    &format!(
+                        "Grant {} on {} {} to {}",
+                        source_priv.privilege_type,
+                        source_priv.object_type,
+                        privilege_object_label(source_priv),
+                        source_priv.grantee
+                    ),
+                    &generate_grant_sql(source_priv),
+                    &generate_revoke_sql(source_priv),
+                    false,
+                ));
+            }
+            Some(target_priv) if target_priv.is_grantable != source_priv.is_grantable => {
+                let (up_sql, down_sql) = if source_priv.is_grantable {
+                    (generate_grant_sql(source_priv), generate_revoke_grant_option_sql(source_priv))
+                } else {
+                    (generate_revoke_grant_option_sql(source_priv), generate_grant_sql(target_priv))
+                };
+                
+                report.items.push(DiffItem::new(
+                    DiffKind::Modified,
+                    "privilege",
+                    &privilege_label(source_priv),
+                    &format!(
+                        "{} grant option for {} on {} {} to {}",
+                        if source_priv.is_grantable { "Add" } else { "Remove" },
+                        source_priv.privilege_type,
+                        source_priv.object_type,
+                        privilege_object_label(source_priv),
+                        source_priv.grantee
+                    ),
+                    &up_sql,
+                    &down_sql,
+                    false,
+                ).with_changes(property_changes(source_priv, target_priv, &["is_grantable"])));
+            }
+            Some(_) => {}
+        }
     }
     
-    // Find ENUMs that need to be removed
-    for target_enum in &target.enums {
-        if source.find_enum(&target_enum.name).is_none() {
-            let up_sql = format!("DROP TYPE IF EXISTS \"{}\" CASCADE;", target_enum.name);
-            let down_sql = generate_create_enum_sql(target_enum);
-            
+    // Find privileges to revoke
+    for target_priv in &target.privileges {
+        if !source.privileges.iter().any(|s| s.same_grant(target_priv)) {
             report.items.push(DiffItem::new(
                 DiffKind::Removed,
-                "enum",
-                &target_enum.name,
-                &format!("Drop enum type '{}'", target_enum.name),
-                &up_sql,
-                &down_sql,
-                true,
+                "privilege",
+                &privilege_label(target_priv),
+                &format!(
+                    "Revoke {} on {} {} from {}",
+                    target_priv.privilege_type,
+                    target_priv.object_type,
+                    privilege_object_label(target_priv),
+                    target_priv.grantee
+                ),
+                &generate_revoke_sql(target_priv),
+                &generate_grant_sql(target_priv),
+                false,
             ));
         }
     }
 }
 
-/// Generate CREATE TYPE ... AS ENUM SQL for an enum type.
-fn generate_create_enum_sql(enum_type: &EnumType) -> String {
-    let values = enum_type.values.iter()
-        .map(|v| format!("'{}'", v))
-        .collect::<Vec<_>>()
-        .join(", ");
-    
-    format!("CREATE TYPE \"{}\" AS ENUM ({});", enum_type.name, values)
+/// Human-readable name of the object a privilege applies to.
+fn privilege_object_label(privilege: &Privilege) -> String {
+    match (&privilege.column_name, &privilege.arguments) {
+        (Some(column), _) => format!("{}.{}", privilege.object_name, column),
+        (None, Some(args)) => format!("{}({})", privilege.object_name, args),
+        (None, None) => privilege.object_name.clone(),
+    }
 }
 
-/// Compare two tables and add differences to the report.
-fn compare_tables(report: &mut DiffReport, source: &Table, target: &Table) {
-    // Compare columns
-    compare_columns(report, source, target);
-    
-    // Compare primary key
-    compare_primary_keys(report, source, target);
-    
-    // Compare unique constraints
-    compare_unique_constraints(report, source, target);
-    
-    // Compare indexes
-    compare_indexes(report, source, target);
+/// Object name used for a privilege diff item.
+fn privilege_label(privilege: &Privilege) -> String {
+    format!(
+        "{}:{}:{}",
+        privilege_object_label(privilege),
+        privilege.privilege_type,
+        privilege.grantee
+    )
 }
 
-/// Compare columns between two tables.
-fn compare_columns(report: &mut DiffReport, source: &Table, target: &Table) {
-    // Find columns to add
-    for source_col in &source.columns {
-        if target.find_column(&source_col.name).is_none() {
-            let up_sql = generate_add_column_sql(&source.name, source_col);
-            let down_sql = format!(
-                "ALTER TABLE \"{}\" DROP COLUMN IF EXISTS \"{}\";",
-                source.name, source_col.name
-            );
-            
-            report.items.push(DiffItem::new(
-                DiffKind::Added,
-                "column",
-                &format!("{}.{}", source.name, source_col.name),
-                &format!("Add column '{}' to table '{}'", source_col.name, source.name),
-                &up_sql,
-                &down_sql,
-                false,
-            ));
-        }
+/// Render the privilege and ON clause of a GRANT/REVOKE statement.
+fn privilege_clause(privilege: &Privilege) -> String {
+    match privilege.object_type.as_str() {
+        "column" => format!(
+            "{} (\"{}\") ON TABLE \"{}\"",
+            privilege.privilege_type,
+            privilege.column_name.as_deref().unwrap_or_default(),
+            privilege.object_name
+        ),
+        "sequence" => format!("{} ON SEQUENCE \"{}\"", privilege.privilege_type, privilege.object_name),
+        "function" => format!(
+            "{} ON FUNCTION \"{}\"({})",
+            privilege.privilege_type,
+            privilege.object_name,
+            privilege.arguments.as_deref().unwrap_or_default()
+        ),
+        _ => format!("{} ON TABLE \"{}\"", privilege.privilege_type, privilege.object_name),
     }
-    
-    // Find columns to remove
-    for target_col in &target.columns {
-        if source.find_column(&target_col.name).is_none() {
-            let up_sql = format!(
-                "ALTER TABLE \"{}\" DROP COLUMN IF EXISTS \"{}\";",
-                source.name, target_col.name
-            );
-            let down_sql = generate_add_column_sql(&target.name, target_col);
-            
-            report.items.push(DiffItem::new(
-                DiffKind::Removed,
-                "column",
-                &format!("{}.{}", target.name, target_col.name),
-                &format!("Drop column '{}' from table '{}'", target_col.name, target.name),
-                &up_sql,
-                &down_sql,
-                true, // Dropping a column is dangerous
-            ));
+}
+
+/// Generate GRANT SQL statement.
+fn generate_grant_sql(privilege: &Privilege) -> String {
+    format!(
+        "GRANT {} TO {}{};",
+        privilege_clause(privilege),
+        format_role(&privilege.grantee),
+        if privilege.is_grantable { " WITH GRANT OPTION" } else { "" }
+    )
+}
+
+/// Generate REVOKE SQL statement.
+fn generate_revoke_sql(privilege: &Privilege) -> String {
+    format!("REVOKE {} FROM {};", privilege_clause(privilege), format_role(&privilege.grantee))
+}
+
+/// Generate REVOKE GRANT OPTION FOR SQL statement.
+fn generate_revoke_grant_option_sql(privilege: &Privilege) -> String {
+    format!(
+        "REVOKE GRANT OPTION FOR {} FROM {};",
+        privilege_clause(privilege),
+        format_role(&privilege.grantee)
+    )
+}
+
+/// Compare foreign servers, user mappings and foreign tables.
+///
+/// Items are ordered so that servers exist before the mappings and tables
+/// that use them, and are dropped after them; the down script replays the
+/// items in reverse.
+fn compare_foreign_data(report: &mut DiffReport, source: &SchemaModel, target: &SchemaModel) {
+    for source_server in &source.foreign_servers {
+        match target.find_foreign_server(&source_server.name) {
+            None => report.items.push(DiffItem::new(
+                DiffKind::Added,
+                "foreign_server",
+                &source_server.name,
+                &format!(
+                    "Create foreign server '{}' using {}",
+                    source_server.name, source_server.wrapper
+                ),
+                &generate_create_server_sql(source_server),
+                &generate_drop_server_sql(&source_server.name),
+                false,
+            )),
+            Some(target_server) if target_server != source_server => {
+                report.items.push(diff_foreign_server(source_server, target_server));
+            }
+            Some(_) => {}
         }
     }
     
-    // Find modified columns
-    for source_col in &source.columns {
-        if let Some(target_col) = target.find_column(&source_col.name) {
-            if !source_col.same_definition(target_col) {
-                let changes = describe_column_changes(source_col, target_col);
-                let (up_sql, down_sql) = generate_alter_column_sql(&source.name, source_col, target_col);
-                
-                let dangerous = source_col.data_type != target_col.data_type;
-                
+    for source_mapping in &source.user_mappings {
+        match target.find_user_mapping(&source_mapping.server, &source_mapping.user) {
+            None => report.items.push(DiffItem::new(
+                DiffKind::Added,
+                "user_mapping",
+                &user_mapping_label(source_mapping),
+                &format!(
+                    "Create user mapping for {} on server '{}'",
+                    source_mapping.user, source_mapping.server
+                ),
+                &generate_create_user_mapping_sql(source_mapping),
+                &generate_drop_user_mapping_sql(source_mapping),
+                false,
+            )),
+            Some(target_mapping) if target_mapping.options != source_mapping.options => {
                 report.items.push(DiffItem::new(
                     DiffKind::Modified,
-                    "column",
-                    &format!("{}.{}", source.name, source_col.name),
-                    &changes,
-                    &up_sql,
-                    &down_sql,
-                    dangerous,
-                ));
+                    "user_mapping",
+                    &user_mapping_label(source_mapping),
+                    &format!(
+                        "Change options of user mapping for {} on server '{}'",
+                        source_mapping.user, source_mapping.server
+                    ),
+                    &generate_alter_user_mapping_sql(source_mapping, target_mapping),
+                    &generate_alter_user_mapping_sql(target_mapping, source_mapping),
+                    false,
+                ).with_changes(property_changes(source_mapping, target_mapping, &["options"])));
             }
+            Some(_) => {}
         }
     }
-}
-
-/// Compare primary keys between two tables.
-fn compare_primary_keys(report: &mut DiffReport, source: &Table, target: &Table) {
-    match (&source.primary_key, &target.primary_key) {
-        (Some(source_pk), None) => {
-            // Add primary key
-            let up_sql = format!(
-                "ALTER TABLE \"{}\" ADD CONSTRAINT \"{}\" PRIMARY KEY ({});",
-                source.name,
-                source_pk.name,
-                source_pk.columns.iter().map(|c| format!("\"{}\"", c)).collect::<Vec<_>>().join(", ")
-            );
-            let down_sql = format!(
-                "ALTER TABLE \"{}\" DROP CONSTRAINT IF EXISTS \"{}\";",
-                source.name, source_pk.name
-            );
-            
-            report.items.push(DiffItem::new(
+    
+    for source_table in &source.foreign_tables {
+        match target.find_foreign_table(&source_table.name) {
+            None => report.items.push(DiffItem::new(
                 DiffKind::Added,
-                "constraint",
-                &format!("{}.{}", source.name, source_pk.name),
-                &format!("Add primary key '{}' to table '{}'", source_pk.name, source.name),
-                &up_sql,
-                &down_sql,
+                "foreign_table",
+                &source_table.name,
+                &format!(
+                    "Create foreign table '{}' on server '{}'",
+                    source_table.name, source_table.server
+                ),
+                &generate_create_foreign_table_sql(source_table),
+                &generate_drop_foreign_table_sql(&source_table.name),
                 false,
-            ));
-        }
-        (None, Some(target_pk)) => {
-            // Remove primary key
-            let up_sql = format!(
-                "ALTER TABLE \"{}\" DROP CONSTRAINT IF EXISTS \"{}\";",
-                target.name, target_pk.name
-            );
-            let down_sql = format!(
-                "ALTER TABLE \"{}\" ADD CONSTRAINT \"{}\" PRIMARY KEY ({});",
-                target.name,
-                target_pk.name,
-                target_pk.columns.iter().map(|c| format!("\"{}\"", c)).collect::<Vec<_>>().join(", ")
-            );
-            
-            report.items.push(DiffItem::new(
-                DiffKind::Removed,
-                "constraint",
-                &format!("{}.{}", target.name, target_pk.name),
-                &format!("Drop primary key '{}' from table '{}'", target_pk.name, target.name),
-                &up_sql,
-                &down_sql,
-                true,
-            ));
-        }
-        (Some(source_pk), Some(target_pk)) => {
-            // Check if primary key columns changed
-            if source_pk.columns != target_pk.columns {
-                let up_sql = format!(
-                    "ALTER TABLE \"{}\" DROP CONSTRAINT IF EXISTS \"{}\";\nALTER TABLE \"{}\" ADD CONSTRAINT \"{}\" PRIMARY KEY ({});",
-                    source.name, target_pk.name,
-                    source.name, source_pk.name,
-                    source_pk.columns.iter().map(|c| format!("\"{}\"", c)).collect::<Vec<_>>().join(", ")
-                );
-                let down_sql = format!(
-                    "ALTER TABLE \"{}\" DROP CONSTRAINT IF EXISTS \"{}\";\nALTER TABLE \"{}\" ADD CONSTRAINT \"{}\" PRIMARY KEY ({});",
-                    source.name, source_pk.name,
-                    source.name, target_pk.name,
-                    target_pk.columns.iter().map(|c| format!("\"{}\"", c)).collect::<Vec<_>>().join(", ")
-                );
-                
+            )),
+            Some(target_table) if target_table.columns == source_table.columns
+                && target_table.server == source_table.server =>
+            {
+                if target_table.options != source_table.options {
+                    report.items.push(DiffItem::new(
+                        DiffKind::Modified,
+                        "foreign_table",
+                        &source_table.name,
+                        &format!("Change options of foreign table '{}'", source_table.name),
+                        &format!(
+                            "ALTER FOREIGN TABLE \"{}\" {};",
+                            source_table.name,
+                            alter_options_clause(&source_table.options, &target_table.options)
+                        ),
+                        &format!(
+                            "ALTER FOREIGN TABLE \"{}\" {};",
+                            source_table.name,
+                            alter_options_clause(&target_table.options, &source_table.options)
+                        ),
+                        false,
+                    ).with_changes(property_changes(source_table, target_table, &["options"])));
+                }
+            }
+            Some(target_table) => {
+                // The data lives on the remote server, so recreating loses nothing
                 report.items.push(DiffItem::new(
                     DiffKind::Modified,
-                    "constraint",
-                    &format!("{}.{}", source.name, source_pk.name),
-                    &format!("Modify primary key '{}' on table '{}'", source_pk.name, source.name),
-                    &up_sql,
-                    &down_sql,
-                    true,
-                ));
+                    "foreign_table",
+                    &source_table.name,
+                    &format!("Recreate foreign table '{}'", source_table.name),
+                    &format!(
+                        "{}\n{}",
+                        generate_drop_foreign_table_sql(&source_table.name),
+                        generate_create_foreign_table_sql(source_table)
+                    ),
+                    &format!(
+                        "{}\n{}",
+                        generate_drop_foreign_table_sql(&target_table.name),
+                        generate_create_foreign_table_sql(target_table)
+                    ),
+                    false,
+                )
+                .with_changes(property_changes(source_table, target_table, &["server", "columns", "options"])));
             }
         }
-        (None, None) => {}
-    }
-}
-
-/// Compare unique constraints between two tables.
-fn compare_unique_constraints(report: &mut DiffReport, source: &Table, target: &Table) {
-    // Find constraints to add
-    for source_uc in &source.unique_constraints {
-        let exists = target.unique_constraints.iter().any(|t| {
-            t.name == source_uc.name || t.columns == source_uc.columns
-        });
-        
-        if !exists {
-            let up_sql = format!(
-                "ALTER TABLE \"{}\" ADD CONSTRAINT \"{}\" UNIQUE ({});",
-                source.name,
-                source_uc.name,
-                source_uc.columns.iter().map(|c| format!("\"{}\"", c)).collect::<Vec<_>>().join(", ")
-            );
-            let down_sql = format!(
-                "ALTER TABLE \"{}\" DROP CONSTRAINT IF EXISTS \"{}\";",
-                source.name, source_uc.name
-            );
-            
-            report.items.push(DiffItem::new(
-                DiffKind::Added,
-                "constraint",
-                &format!("{}.{}", source.name, source_uc.name),
-                &format!("Add unique constraint '{}' to table '{}'", source_uc.name, source.name),
-                &up_sql,
-                &down_sql,
-                false,
-            ));
-        }
     }
     
-    // Find constraints to remove
-    for target_uc in &target.unique_constraints {
-        let exists = source.unique_constraints.iter().any(|s| {
-            s.name == target_uc.name || s.columns == target_uc.columns
-        });
-        
-        if !exists {
-            let up_sql = format!(
-                "ALTER TABLE \"{}\" DROP CONSTRAINT IF EXISTS \"{}\";",
-                target.name, target_uc.name
-            );
-            let down_sql = format!(
-                "ALTER TABLE \"{}\" ADD CONSTRAINT \"{}\" UNIQUE ({});",
-                target.name,
-                target_uc.name,
-                target_uc.columns.iter().map(|c| format!("\"{}\"", c)).collect::<Vec<_>>().join(", ")
-            );
-            
+    for target_table in &target.foreign_tables {
+        if source.find_foreign_table(&target_table.name).is_none() {
             report.items.push(DiffItem::new(
                 DiffKind::Removed,
-                "constraint",
-                &format!("{}.{}", target.name, target_uc.name),
-                &format!("Drop unique constraint '{}' from table '{}'", target_uc.name, target.name),
-                &up_sql,
-                &down_sql,
+                "foreign_table",
+                &target_table.name,
+                &format!("Drop foreign table '{}'", target_table.name),
+                &generate_drop_foreign_table_sql(&target_table.name),
+                &generate_create_foreign_table_sql(target_table),
                 false,
             ));
         }
     }
-}
-
-/// Compare indexes between two tables.
-fn compare_indexes(report: &mut DiffReport, source: &Table, target: &Table) {
-    // Find indexes to add
-    for source_idx in &source.indexes {
-        let exists = target.indexes.iter().any(|t| {
-            t.name == source_idx.name || (t.columns == source_idx.columns && t.is_unique == source_idx.is_unique)
-        });
-        
-        if !exists {
-            let up_sql = generate_create_index_sql(&source.name, source_idx);
-            let down_sql = format!("DROP INDEX IF EXISTS \"{}\";", source_idx.name);
-            
+    
+    for target_mapping in &target.user_mappings {
+        if source.find_user_mapping(&target_mapping.server, &target_mapping.user).is_none() {
             report.items.push(DiffItem::new(
-                DiffKind::Added,
-                "index",
-                &format!("{}.{}", source.name, source_idx.name),
-                &format!("Create index '{}' on table '{}'", source_idx.name, source.name),
-                &up_sql,
-                &down_sql,
+                DiffKind::Removed,
+                "user_mapping",
+                &user_mapping_label(target_mapping),
+                &format!(
+                    "Drop user mapping for {} on server '{}'",
+                    target_mapping.user, target_mapping.server
+                ),
+                &generate_drop_user_mapping_sql(target_mapping),
+                &generate_create_user_mapping_sql(target_mapping),
                 false,
             ));
         }
     }
     
-    // Find indexes to remove
-    for target_idx in &target.indexes {
-        let exists = source.indexes.iter().any(|s| {
-            s.name == target_idx.name || (s.columns == target_idx.columns && s.is_unique == target_idx.is_unique)
-        });
-        
-        if !exists {
-            let up_sql = format!("DROP INDEX IF EXISTS \"{}\";", target_idx.name);
-            let down_sql = generate_create_index_sql(&target.name, target_idx);
-            
+    for target_server in &target.foreign_servers {
+        if source.find_foreign_server(&target_server.name).is_none() {
             report.items.push(DiffItem::new(
                 DiffKind::Removed,
-                "index",
-                &format!("{}.{}", target.name, target_idx.name),
-                &format!("Drop index '{}' from table '{}'", target_idx.name, target.name),
-                &up_sql,
-                &down_sql,
+                "foreign_server",
+                &target_server.name,
+                &format!("Drop foreign server '{}'", target_server.name),
+                &generate_drop_server_sql(&target_server.name),
+                &generate_create_server_sql(target_server),
                 false,
             ));
         }
     }
 }
 
-/// Generate CREATE TABLE SQL statement.
-fn generate_create_table_sql(table: &Table) -> String {
-    let mut sql = String::new();
-    
-    // First, create sequences for columns with nextval defaults
-    for col in &table.columns {
-        if let Some(default) = &col.default_value {
-            if let Some(seq_name) = extract_sequence_name(default) {
-                sql.push_str(&format!(
-                    "CREATE SEQUENCE IF NOT EXISTS \"{}\";\n",
-                    seq_name
-                ));
-            }
-        }
+/// Diff item for a foreign server that exists on both sides but differs.
+fn diff_foreign_server(source: &ForeignServer, target: &ForeignServer) -> DiffItem {
+    // The wrapper and type cannot be altered; anything using the server must be dropped first
+    if source.wrapper != target.wrapper || source.server_type != target.server_type {
+        return DiffItem::new(
+            DiffKind::Modified,
+            "foreign_server",
+            &source.name,
+            &format!(
+                "Recreate foreign server '{}' (wrapper {} -> {})",
+                source.name, target.wrapper, source.wrapper
+            ),
+            &format!("{}\n{}", generate_drop_server_sql(&source.name), generate_create_server_sql(source)),
+            &format!("{}\n{}", generate_drop_server_sql(&target.name), generate_create_server_sql(target)),
+            true,
+        ).with_changes(property_changes(source, target, &["wrapper", "server_type", "version", "options"]));
     }
     
-    sql.push_str(&format!("CREATE TABLE \"{}\" (\n", table.name));
-    
-    let mut parts: Vec<String> = Vec::new();
+    let alter = |to: &ForeignServer, from: &ForeignServer| {
+        let mut sql = format!("ALTER SERVER \"{}\"", to.name);
+        if to.version != from.version {
+            let version = to.version.as_deref().map(quote_literal);
+            sql.push_str(&format!(" VERSION {}", version.as_deref().unwrap_or("NULL")));
+        }
+        if to.options != from.options {
+            sql.push(' ');
+            sql.push_str(&alter_options_clause(&to.options, &from.options));
+        }
+        sql.push(';');
+        sql
+    };
     
-    // Columns
-    for col in &table.columns {
-        let col_def = generate_column_definition(col);
-        parts.push(format!("    {}", col_def));
+    DiffItem::new(
+        DiffKind::Modified,
+        "foreign_server",
+        &source.name,
+        &format!("Change foreign server '{}'", source.name),
+        &alter(source, target),
+        &alter(target, source),
+        false,
+    ).with_changes(property_changes(source, target, &["wrapper", "server_type", "version", "options"]))
+}
+
+/// Object name used for a user mapping diff item.
+fn user_mapping_label(mapping: &UserMapping) -> String {
+    format!("{}:{}", mapping.server, mapping.user)
+}
+
+/// Quote a string literal for SQL.
+fn quote_literal(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "''"))
+}
+
+/// Split a `name=value` option into its parts.
+fn split_option(option: &str) -> (&str, &str) {
+    option.split_once('=').unwrap_or((option, ""))
+}
+
+/// Render an `OPTIONS (...)` clause for CREATE; empty when there are no options.
+fn options_clause(options: &[String]) -> String {
+    if options.is_empty() {
+        return String::new();
     }
+    let rendered: Vec<String> = options.iter()
+        .map(|o| {
+            let (name, value) = split_option(o);
+            format!("{} {}", name, quote_literal(value))
+        })
+        .collect();
+    format!(" OPTIONS ({})", rendered.join(", "))
+}
+
+/// Render an `OPTIONS (ADD/SET/DROP ...)` clause that turns `from` into `to`.
+fn alter_options_clause(to: &[String], from: &[String]) -> String {
+    let from_value = |name: &str| from.iter().map(|o| split_option(o)).find(|(n, _)| *n == name).map(|(_, v)| v);
+    let mut changes = Vec::new();
     
-    // Primary key
-    if let Some(pk) = &table.primary_key {
-        parts.push(format!(
-            "    CONSTRAINT \"{}\" PRIMARY KEY ({})",
-            pk.name,
-            pk.columns.iter().map(|c| format!("\"{}\"", c)).collect::<Vec<_>>().join(", ")
-        ));
-    }
-    
-    // Unique constraints
-    for uc in &table.unique_constraints {
-        parts.push(format!(
-            "    CONSTRAINT \"{}\" UNIQUE ({})",
-            uc.name,
-            uc.columns.iter().map(|c| format!("\"{}\"", c)).collect::<Vec<_>>().join(", ")
-        ));
+    for option in to {
+        let (name, value) = split_option(option);
+        match from_value(name) {
+            None => changes.push(format!("ADD {} {}", name, quote_literal(value))),
+            Some(old) if old != value => changes.push(format!("SET {} {}", name, quote_literal(value))),
+            Some(_) => {}
+        }
     }
-    
-    sql.push_str(&parts.join(",\n"));
-    sql.push_str("\n);\n");
-    
-    // Indexes (created separately)
-    for idx in &table.indexes {
-        sql.push_str(&generate_create_index_sql(&table.name, idx));
-        sql.push('\n');
+    for option in from {
+        let (name, _) = split_option(option);
+        if !to.iter().any(|o| split_option(o).0 == name) {
+            changes.push(format!("DROP {}", name));
+        }
     }
     
-    sql
+    format!("OPTIONS ({})", changes.join(", "))
 }
 
-/// Generate column definition for CREATE TABLE or ADD COLUMN.
-fn generate_column_definition(col: &Column) -> String {
-    // Check if this is a serial/identity column
-    if let Some(default) = &col.default_value {
-        if is_serial_default(default) {
-            // Convert to appropriate SERIAL type
-            let serial_type = match col.data_type.as_str() {
-                "bigint" => "BIGSERIAL",
-                "smallint" => "SMALLSERIAL", 
-                _ => "SERIAL",
-            };
-            
-            let mut col_def = format!("\"{}\" {}", col.name, serial_type);
-            if !col.is_nullable {
-                col_def.push_str(" NOT NULL");
-            }
-            return col_def;
-        }
-    }
-    
-    // Use format_data_type to properly quote ENUM types
-    let mut col_def = format!("\"{}\" {}", col.name, format_data_type(&col.data_type));
-    
-    if !col.is_nullable {
-        col_def.push_str(" NOT NULL");
+/// Generate CREATE SERVER SQL statement.
+fn generate_create_server_sql(server: &ForeignServer) -> String {
+    let mut sql = format!("CREATE SERVER \"{}\"", server.name);
+    if let Some(server_type) = &server.server_type {
+        sql.push_str(&format!(" TYPE {}", quote_literal(server_type)));
     }
-    
-    if let Some(default) = &col.default_value {
-        col_def.push_str(&format!(" DEFAULT {}", default));
+    if let Some(version) = &server.version {
+        sql.push_str(&format!(" VERSION {}", quote_literal(version)));
     }
-    
-    col_def
+    sql.push_str(&format!(" FOREIGN DATA WRAPPER \"{}\"", server.wrapper));
+    sql.push_str(&options_clause(&server.options));
+    sql.push(';');
+    sql
 }
 
-/// Check if a default value represents a serial/sequence column.
-fn is_serial_default(default: &str) -> bool {
-    let lower = default.to_lowercase();
-    lower.contains("nextval(") && lower.contains("_seq")
+/// Generate DROP SERVER SQL statement.
+fn generate_drop_server_sql(server_name: &str) -> String {
+    format!("DROP SERVER IF EXISTS \"{}\";", server_name)
 }
 
-/// Extract sequence name from a nextval default.
-fn extract_sequence_name(default: &str) -> Option<String> {
-    // Match patterns like: nextval('table_id_seq'::regclass)
-    let lower = default.to_lowercase();
-    if lower.contains("nextval(") {
-        // Extract the sequence name from the string
-        if let Some(start) = default.find('\'') {
-            if let Some(end) = default[start + 1..].find('\'') {
-                let seq_name = &default[start + 1..start + 1 + end];
-                // Remove schema prefix if present
-                let clean_name = seq_name.split('.').last().unwrap_or(seq_name);
-                return Some(clean_name.to_string());
-            }
-        }
-    }
-    None
+/// Generate CREATE USER MAPPING SQL statement.
+///
+/// Passwords are not part of the model, so a mapping that needs one has to be
+/// completed by hand with `ALTER USER MAPPING ... OPTIONS (ADD password ...)`.
+fn generate_create_user_mapping_sql(mapping: &UserMapping) -> String {
+    format!(
+        "CREATE USER MAPPING FOR {} SERVER \"{}\"{};",
+        format_role(&mapping.user),
+        mapping.server,
+        options_clause(&mapping.options)
+    )
 }
 
-/// Generate ADD COLUMN SQL statement.
-fn generate_add_column_sql(table_name: &str, column: &Column) -> String {
-    let mut sql = String::new();
-    
-    // First, create sequence if needed
-    if let Some(default) = &column.default_value {
-        if let Some(seq_name) = extract_sequence_name(default) {
-            sql.push_str(&format!(
-                "CREATE SEQUENCE IF NOT EXISTS \"{}\";\n",
-                seq_name
-            ));
+/// Generate DROP USER MAPPING SQL statement.
+fn generate_drop_user_mapping_sql(mapping: &UserMapping) -> String {
+    format!(
+        "DROP USER MAPPING IF EXISTS FOR {} SERVER \"{}\";",
+        format_role(&mapping.user),
+        mapping.server
+    )
+}
+
+/// Generate ALTER USER MAPPING SQL that turns `from` into `to`.
+fn generate_alter_user_mapping_sql(to: &UserMapping, from: &UserMapping) -> String {
+    format!(
+        "ALTER USER MAPPING FOR {} SERVER \"{}\" {};",
+        format_role(&to.user),
+        to.server,
+        alter_options_clause(&to.options, &from.options)
+    )
+}
+
+/// Generate CREATE FOREIGN TABLE SQL statement.
+fn generate_create_foreign_table_sql(table: &ForeignTable) -> String {
+    let columns: Vec<String> = table.columns.iter()
+        .map(|c| format!("    {}", generate_column_definition(c)))
+        .collect();
+    format!(
+        "CREATE FOREIGN TABLE \"{}\" (\n{}\n) SERVER \"{}\"{};",
+        table.name,
+        columns.join(",\n"),
+        table.server,
+        options_clause(&table.options)
+    )
+}
+
+/// Generate DROP FOREIGN TABLE SQL statement.
+fn generate_drop_foreign_table_sql(table_name: &str) -> String {
+    format!("DROP FOREIGN TABLE IF EXISTS \"{}\";", table_name)
+}
+
+/// Compare logical replication publications.
+fn compare_publications(report: &mut DiffReport, source: &SchemaModel, target: &SchemaModel) {
+    for source_pub in &source.publications {
+        match target.find_publication(&source_pub.name) {
+            None => report.items.push(DiffItem::new(
+                DiffKind::Added,
+                "publication",
+                &source_pub.name,
+                &format!("Create publication '{}'", source_pub.name),
+                &generate_create_publication_sql(source_pub),
+                &generate_drop_publication_sql(&source_pub.name),
+                false,
+            )),
+            Some(target_pub) if target_pub != source_pub => {
+                report.items.push(diff_publication(source_pub, target_pub));
+            }
+            Some(_) => {}
         }
     }
     
-    // Check if this is a serial column
-    if let Some(default) = &column.default_value {
-        if is_serial_default(default) {
-            let serial_type = match column.data_type.as_str() {
-                "bigint" => "BIGSERIAL",
-                "smallint" => "SMALLSERIAL",
-                _ => "SERIAL",
-            };
-            
-            sql.push_str(&format!(
-                "ALTER TABLE \"{}\" ADD COLUMN \"{}\" {}",
-                table_name, column.name, serial_type
+    for target_pub in &target.publications {
+        if source.find_publication(&target_pub.name).is_none() {
+            report.items.push(DiffItem::new(
+                DiffKind::Removed,
+                "publication",
+                &target_pub.name,
+                &format!("Drop publication '{}' (subscribers stop receiving changes)", target_pub.name),
+                &generate_drop_publication_sql(&target_pub.name),
+                &generate_create_publication_sql(target_pub),
+                true,
             ));
-            
-            if !column.is_nullable {
-                sql.push_str(" NOT NULL");
-            }
-            
-            sql.push(';');
-            return sql;
         }
     }
-    
-    // Use format_data_type to properly quote ENUM types
-    sql.push_str(&format!(
-        "ALTER TABLE \"{}\" ADD COLUMN \"{}\" {}",
-        table_name, column.name, format_data_type(&column.data_type)
-    ));
-    
-    if !column.is_nullable {
-        sql.push_str(" NOT NULL");
-    }
-    
-    if let Some(default) = &column.default_value {
-        sql.push_str(&format!(" DEFAULT {}", default));
-    }
-    
-    sql.push(';');
-    sql
 }
 
-/// Generate ALTER COLUMN SQL statements.
-fn generate_alter_column_sql(table_name: &str, source: &Column, target: &Column) -> (String, String) {
-    let mut up_parts = Vec::new();
-    let mut down_parts = Vec::new();
-    
-    // Type change
-    if source.data_type != target.data_type {
-        let source_type = format_data_type(&source.data_type);
-        let target_type = format_data_type(&target.data_type);
-        up_parts.push(format!(
-            "ALTER TABLE \"{}\" ALTER COLUMN \"{}\" TYPE {} USING \"{}\"::{}",
-            table_name, source.name, source_type, source.name, source_type
-        ));
-        down_parts.push(format!(
-            "ALTER TABLE \"{}\" ALTER COLUMN \"{}\" TYPE {} USING \"{}\"::{}",
-            table_name, source.name, target_type, source.name, target_type
-        ));
+/// Diff item for a publication that exists on both sides but differs.
+fn diff_publication(source: &Publication, target: &Publication) -> DiffItem {
+    // A publication cannot be switched to or from FOR ALL TABLES
+    if source.all_tables != target.all_tables {
+        return DiffItem::new(
+            DiffKind::Modified,
+            "publication",
+            &source.name,
+            &format!(
+                "Recreate publication '{}' {}",
+                source.name,
+                if source.all_tables { "for all tables" } else { "for a table list" }
+            ),
+            &format!("{}\n{}", generate_drop_publication_sql(&source.name), generate_create_publication_sql(source)),
+            &format!("{}\n{}", generate_drop_publication_sql(&target.name), generate_create_publication_sql(target)),
+            true,
+        )
+        .with_changes(property_changes(source, target, &["all_tables", "tables", "publish", "publish_via_partition_root"]));
     }
     
-    // Nullability change
-    if source.is_nullable != target.is_nullable {
-        if source.is_nullable {
-            up_parts.push(format!(
-                "ALTER TABLE \"{}\" ALTER COLUMN \"{}\" DROP NOT NULL",
-                table_name, source.name
-            ));
-            down_parts.push(format!(
-                "ALTER TABLE \"{}\" ALTER COLUMN \"{}\" SET NOT NULL",
-                table_name, source.name
-            ));
-        } else {
-            up_parts.push(format!(
-                "ALTER TABLE \"{}\" ALTER COLUMN \"{}\" SET NOT NULL",
-                table_name, source.name
-            ));
-            down_parts.push(format!(
-                "ALTER TABLE \"{}\" ALTER COLUMN \"{}\" DROP NOT NULL",
-                table_name, source.name
-            ));
+    let alter = |to: &Publication, from: &Publication| {
+        let mut statements = Vec::new();
+        let added: Vec<_> = to.tables.iter().filter(|t| !from.tables.contains(t)).collect();
+        let dropped: Vec<_> = from.tables.iter().filter(|t| !to.tables.contains(t)).collect();
+        if !added.is_empty() {
+            statements.push(format!("ALTER PUBLICATION \"{}\" ADD TABLE {};", to.name, quote_table_list(&added)));
         }
-    }
-    
-    // Default change
-    if source.default_value != target.default_value {
-        match &source.default_value {
-            Some(default) => {
-                up_parts.push(format!(
-                    "ALTER TABLE \"{}\" ALTER COLUMN \"{}\" SET DEFAULT {}",
-                    table_name, source.name, default
-                ));
-            }
-            None => {
-                up_parts.push(format!(
-                    "ALTER TABLE \"{}\" ALTER COLUMN \"{}\" DROP DEFAULT",
-                    table_name, source.name
-                ));
-            }
+        if !dropped.is_empty() {
+            statements.push(format!("ALTER PUBLICATION \"{}\" DROP TABLE {};", to.name, quote_table_list(&dropped)));
         }
-        
-        match &target.default_value {
-            Some(default) => {
-                down_parts.push(format!(
-                    "ALTER TABLE \"{}\" ALTER COLUMN \"{}\" SET DEFAULT {}",
-                    table_name, source.name, default
-                ));
-            }
-            None => {
-                down_parts.push(format!(
-                    "ALTER TABLE \"{}\" ALTER COLUMN \"{}\" DROP DEFAULT",
-                    table_name, source.name
-                ));
-            }
+        if to.publish != from.publish || to.publish_via_partition_root != from.publish_via_partition_root {
+            statements.push(format!("ALTER PUBLICATION \"{}\" SET ({});", to.name, publication_parameters(to)));
         }
-    }
+        statements.join("\n")
+    };
     
-    (
-        up_parts.iter().map(|s| format!("{};", s)).collect::<Vec<_>>().join("\n"),
-        down_parts.iter().map(|s| format!("{};", s)).collect::<Vec<_>>().join("\n"),
+    DiffItem::new(
+        DiffKind::Modified,
+        "publication",
+        &source.name,
+        &format!("Change publication '{}'", source.name),
+        &alter(source, target),
+        &alter(target, source),
+        false,
     )
+    .with_changes(property_changes(source, target, &["all_tables", "tables", "publish", "publish_via_partition_root"]))
 }
 
-/// Generate CREATE INDEX SQL statement.
-fn generate_create_index_sql(table_name: &str, index: &Index) -> String {
-    let unique = if index.is_unique { "UNIQUE " } else { "" };
-    let using = if index.index_type != "btree" {
-        format!(" USING {}", index.index_type)
-    } else {
+/// Render a comma-separated list of quoted table names.
+fn quote_table_list(tables: &[&String]) -> String {
+    tables.iter().map(|t| format!("\"{}\"", t)).collect::<Vec<_>>().join(", ")
+}
+
+/// Render the WITH parameters of a publication.
+fn publication_parameters(publication: &Publication) -> String {
+    format!(
+        "publish = '{}', publish_via_partition_root = {}",
+        publication.publish.join(", "),
+        publication.publish_via_partition_root
+    )
+}
+
+/// Generate CREATE PUBLICATION SQL statement.
+fn generate_create_publication_sql(publication: &Publication) -> String {
+    let target = if publication.all_tables {
+        " FOR ALL TABLES".to_string()
+    } else if publication.tables.is_empty() {
         String::new()
+    } else {
+        let tables: Vec<_> = publication.tables.iter().collect();
+        format!(" FOR TABLE {}", quote_table_list(&tables))
     };
-    
     format!(
-        "CREATE {}INDEX \"{}\" ON \"{}\"{} ({});",
-        unique,
-        index.name,
-        table_name,
-        using,
-        index.columns.iter().map(|c| format!("\"{}\"", c)).collect::<Vec<_>>().join(", ")
+        "CREATE PUBLICATION \"{}\"{} WITH ({});",
+        publication.name,
+        target,
+        publication_parameters(publication)
     )
 }
 
-/// Describe the changes between two columns.
-fn describe_column_changes(source: &Column, target: &Column) -> String {
-    let mut changes = Vec::new();
-    
-    if source.data_type != target.data_type {
-        changes.push(format!("type: {} -> {}", target.data_type, source.data_type));
+/// Generate DROP PUBLICATION SQL statement.
+fn generate_drop_publication_sql(publication_name: &str) -> String {
+    format!("DROP PUBLICATION IF EXISTS \"{}\";", publication_name)
+}
+
+/// Compare logical replication subscriptions.
+///
+/// Statements that create a replication slot or refresh publications cannot
+/// run inside a transaction block, so the generated SQL never touches the
+/// publisher: subscriptions are created with `connect = false` and dropped
+/// after detaching their slot. Slots are then created, refreshed or dropped
+/// on the publisher by hand.
+fn compare_subscriptions(report: &mut DiffReport, source: &SchemaModel, target: &SchemaModel) {
+    for source_sub in &source.subscriptions {
+        match target.find_subscription(&source_sub.name) {
+            None => report.items.push(DiffItem::new(
+                DiffKind::Added,
+                "subscription",
+                &source_sub.name,
+                &format!(
+                    "Create subscription '{}' without connecting; create its slot on the publisher, then enable it",
+                    source_sub.name
+                ),
+                &generate_create_subscription_sql(source_sub),
+                &generate_drop_subscription_sql(&source_sub.name),
+                false,
+            )),
+            Some(target_sub) if target_sub != source_sub => {
+                report.items.push(DiffItem::new(
+                    DiffKind::Modified,
+                    "subscription",
+                    &source_sub.name,
+                    &format!("Change subscription '{}'", source_sub.name),
+                    &generate_alter_subscription_sql(source_sub, target_sub),
+                    &generate_alter_subscription_sql(target_sub, source_sub),
+                    false,
+                )
+                .with_changes(property_changes(source_sub, target_sub, &["publications", "enabled", "slot_name"])));
+            }
+            Some(_) => {}
+        }
     }
     
-    if source.is_nullable != target.is_nullable {
-        changes.push(format!(
-            "nullable: {} -> {}",
-            target.is_nullable, source.is_nullable
-        ));
-    }
+    for target_sub in &target.subscriptions {
+        if source.find_subscription(&target_sub.name).is_none() {
+            report.items.push(DiffItem::new(
+                DiffKind::Removed,
+                "subscription",
+                &target_sub.name,
+                &format!(
+                    "Drop subscription '{}'; its slot on the publisher must be dropped by hand",
+                    target_sub.name
+                ),
+                &generate_drop_subscription_sql(&target_sub.name),
+                &generate_create_subscription_sql(target_sub),
+                true,
+            ));
+        }
+    }
+}
+
+/// Generate CREATE SUBSCRIPTION SQL that does not connect to the publisher.
+fn generate_create_subscription_sql(subscription: &Subscription) -> String {
+    let slot_name = match &subscription.slot_name {
+        Some(slot) => quote_literal(slot),
+        None => "NONE".to_string(),
+    };
+    let publications: Vec<_> = subscription.publications.iter().map(|p| format!("\"{}\"", p)).collect();
+    format!(
+        "CREATE SUBSCRIPTION \"{}\" CONNECTION {} PUBLICATION {} WITH (connect = false, slot_name = {});",
+        subscription.name,
+        quote_literal(&subscription.connection),
+        publications.join(", "),
+        slot_name
+    )
+}
+
+/// Generate SQL that drops a subscription without dropping its remote slot.
+fn generate_drop_subscription_sql(subscription_name: &str) -> String {
+    format!(
+        "ALTER SUBSCRIPTION \"{0}\" DISABLE;\nALTER SUBSCRIPTION \"{0}\" SET (slot_name = NONE);\nDROP SUBSCRIPTION IF EXISTS \"{0}\";",
+        subscription_name
+    )
+}
+
+/// Generate ALTER SUBSCRIPTION SQL that turns `from` into `to`.
+fn generate_alter_subscription_sql(to: &Subscription, from: &Subscription) -> String {
+    let mut statements = Vec::new();
+    if to.connection != from.connection {
+        statements.push(format!(
+            "ALTER SUBSCRIPTION \"{}\" CONNECTION {};",
+            to.name,
+            quote_literal(&to.connection)
+        ));
+    }
+    if to.publications != from.publications {
+        let publications: Vec<_> = to.publications.iter().map(|p| format!("\"{}\"", p)).collect();
+        statements.push(format!(
+            "ALTER SUBSCRIPTION \"{}\" SET PUBLICATION {} WITH (refresh = false);",
+            to.name,
+            publications.join(", ")
+        ));
+    }
+    if to.slot_name != from.slot_name {
+        statements.push(format!(
+            "ALTER SUBSCRIPTION \"{}\" SET (slot_name = {});",
+            to.name,
+            to.slot_name.as_deref().map(quote_literal).unwrap_or_else(|| "NONE".to_string())
+        ));
+    }
+    if to.enabled != from.enabled {
+        statements.push(format!(
+            "ALTER SUBSCRIPTION \"{}\" {};",
+            to.name,
+            if to.enabled { "ENABLE" } else { "DISABLE" }
+        ));
+    }
+    statements.join("\n")
+}
+
+/// Compare ENUM types between source and target.
+fn compare_enums(report: &mut DiffReport, source: &SchemaModel, target: &SchemaModel) {
+    // Find ENUMs that need to be added
+    for source_enum in &source.enums {
+        if let Some(target_enum) = target.find_enum(&source_enum.name) {
+            let same_values = source_enum.values.len() == target_enum.values.len()
+                && source_enum.values.iter().all(|v| target_enum.values.contains(v));
+            if source_enum.values != target_enum.values && same_values {
+                report.items.push(enum_reorder_item(source_enum, target_enum, target));
+            } else if source_enum.values != target_enum.values {
+                // ENUM exists, check if values differ
+                // Find new values to add
+                let new_values: Vec<&String> = source_enum.values.iter()
+                    .filter(|v| !target_enum.values.contains(v))
+                    .collect();
+                
+                // Find values to remove (dangerous!)
+                let removed_values: Vec<&String> = target_enum.values.iter()
+                    .filter(|v| !source_enum.values.contains(v))
+                    .collect();
+                
+                if !new_values.is_empty() {
+                    let up_sql = new_values.iter()
+                        .map(|v| format!("ALTER TYPE \"{}\" ADD VALUE IF NOT EXISTS '{}';", source_enum.name, v))
+                        .collect::<Vec<_>>()
+                        .join("\n");
+                    
+                    // Note: PostgreSQL doesn't support removing ENUM values easily
+                    let down_sql = format!("-- Cannot easily remove ENUM values in PostgreSQL\n-- Removed values: {:?}", new_values);
+                    
+                    report.items.push(DiffItem::new(
+                        DiffKind::Modified,
+                        "enum",
+                        &source_enum.name,
+                        &format!("Add values to enum '{}': {:?}", source_enum.name, new_values),
+                        &up_sql,
+                        &down_sql,
+                        false,
+                    ).with_changes(property_changes(source_enum, target_enum, &["values"])));
+                }
+                
+                if !removed_values.is_empty() {
+                    // Removing ENUM values requires recreating the type
+                    let up_sql = format!(
+                        "-- WARNING: Removing ENUM values requires recreating the type\n\
+                         -- Values to remove: {:?}\n\
+                         -- This is a destructive operation that requires manual handling",
+                        removed_values
+                    );
+                    let down_sql = removed_values.iter()
+                        .map(|v| format!("ALTER TYPE \"{}\" ADD VALUE IF NOT EXISTS '{}';", source_enum.name, v))
+                        .collect::<Vec<_>>()
+                        .join("\n");
+                    
+                    report.items.push(DiffItem::new(
+                        DiffKind::Modified,
+                        "enum",
+                        &source_enum.name,
+                        &format!("Remove values from enum '{}': {:?} (DANGEROUS)", source_enum.name, removed_values),
+                        &up_sql,
+                        &down_sql,
+                        true,
+                    ).with_changes(property_changes(source_enum, target_enum, &["values"])));
+                }
+            }
+        } else {
+            // ENUM doesn't exist, create it
+            let up_sql = generate_create_enum_sql(source_enum);
+            let down_sql = format!("DROP TYPE IF EXISTS \"{}\" CASCADE;", source_enum.name);
+            
+            report.items.push(DiffItem::new(
+                DiffKind::Added,
+                "enum",
+                &source_enum.name,
+                &format!("Create enum type '{}' with values: {:?}", source_enum.name, source_enum.values),
+                &up_sql,
+                &down_sql,
+                false,
+            ).with_dependencies(vec![type_key(&source_enum.name)], Vec::new()));
+        }
+    }
     
-    if source.default_value != target.default_value {
-        changes.push(format!(
-            "default: {:?} -> {:?}",
-            target.default_value, source.default_value
+    // Find ENUMs that need to be removed
+    for target_enum in &target.enums {
+        if source.find_enum(&target_enum.name).is_none() {
+            let up_sql = format!("DROP TYPE IF EXISTS \"{}\" CASCADE;", target_enum.name);
+            let down_sql = generate_create_enum_sql(target_enum);
+            
+            report.items.push(DiffItem::new(
+                DiffKind::Removed,
+                "enum",
+                &target_enum.name,
+                &format!("Drop enum type '{}'", target_enum.name),
+                &up_sql,
+                &down_sql,
+                true,
+            ).with_dependencies(vec![type_key(&target_enum.name)], Vec::new()));
+        }
+    }
+}
+
+/// Diff item for an enum declared with the same values in another order.
+///
+/// Columns are taken from the target, where the up script runs. The item
+/// starts out ignoring the order.
+fn enum_reorder_item(source_enum: &EnumType, target_enum: &EnumType, target: &SchemaModel) -> DiffItem {
+    let array_type = format!("{}[]", source_enum.name);
+    let columns: Vec<(&str, &Column)> = target.tables.iter()
+        .flat_map(|t| t.columns.iter().map(move |c| (t.name.as_str(), c)))
+        .filter(|(_, c)| !c.inherited && (c.data_type == source_enum.name || c.data_type == array_type))
+        .collect();
+
+    let ignore_sql = format!(
+        "-- Value order of enum \"{}\" differs; the current order is kept",
+        source_enum.name
+    );
+    let resolutions = vec![
+        EnumReorderResolution {
+            strategy: EnumReorderStrategy::IgnoreOrder,
+            description: "Keep the target's value order; nothing is changed".to_string(),
+            up_sql: ignore_sql.clone(),
+            down_sql: ignore_sql.clone(),
+            rewrites_table: false,
+        },
+        EnumReorderResolution {
+            strategy: EnumReorderStrategy::RecreateType,
+            description: format!(
+                "Recreate the type in the source's order and convert {} column(s); \
+                 rewrites their tables and fails while views or functions depend on the type",
+                columns.len()
+            ),
+            up_sql: generate_recreate_enum_sql(source_enum, &columns),
+            down_sql: generate_recreate_enum_sql(target_enum, &columns),
+            rewrites_table: !columns.is_empty(),
+        },
+    ];
+
+    let mut item = DiffItem::new(
+        DiffKind::Reordered,
+        "enum",
+        &source_enum.name,
+        &format!(
+            "Enum '{}' has the same values in a different order: {:?} -> {:?}",
+            source_enum.name, target_enum.values, source_enum.values
+        ),
+        &ignore_sql,
+        &ignore_sql,
+        false,
+    );
+    item.enum_reorder = Some(EnumReorder {
+        source_order: source_enum.values.clone(),
+        target_order: target_enum.values.clone(),
+        strategy: EnumReorderStrategy::IgnoreOrder,
+        resolutions,
+    });
+    item
+}
+
+/// Generate SQL that recreates an enum type with its values in the order of
+/// `enum_type` and converts `columns` (table, column) to it through text.
+fn generate_recreate_enum_sql(enum_type: &EnumType, columns: &[(&str, &Column)]) -> String {
+    let old_name = format!("{}_old", enum_type.name);
+    let mut statements = vec![
+        format!("ALTER TYPE \"{}\" RENAME TO \"{}\";", enum_type.name, old_name),
+        generate_create_enum_sql(enum_type),
+    ];
+    for (table, column) in columns {
+        let (new_type, text_type) = if column.data_type.ends_with("[]") {
+            (format!("\"{}\"[]", enum_type.name), "text[]")
+        } else {
+            (format!("\"{}\"", enum_type.name), "text")
+        };
+        // The default still casts to the old type and would block the conversion
+        if column.default_value.is_some() {
+            statements.push(format!("ALTER TABLE \"{}\" ALTER COLUMN \"{}\" DROP DEFAULT;", table, column.name));
+        }
+        statements.push(format!(
+            "ALTER TABLE \"{}\" ALTER COLUMN \"{}\" TYPE {} USING \"{}\"::{}::{};",
+            table, column.name, new_type, column.name, text_type, new_type
         ));
+        if let Some(default) = &column.default_value {
+            statements.push(format!("ALTER TABLE \"{}\" ALTER COLUMN \"{}\" SET DEFAULT {};", table, column.name, default));
+        }
     }
+    statements.push(format!("DROP TYPE \"{}\";", old_name));
+    statements.join("\n")
+}
+
+/// Generate CREATE TYPE ... AS ENUM SQL for an enum type.
+fn generate_create_enum_sql(enum_type: &EnumType) -> String {
+    let values = enum_type.values.iter()
+        .map(|v| format!("'{}'", v))
+        .collect::<Vec<_>>()
+        .join(", ");
     
-    format!("Modify column '{}': {}", source.name, changes.join(", "))
+    format!("CREATE TYPE \"{}\" AS ENUM ({});", enum_type.name, values)
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+/// Compare two tables and add differences to the report.
+/// Order tables so every table comes after the parents it inherits from.
+fn parents_first(tables: Vec<&Table>) -> Vec<&Table> {
+    let mut ordered: Vec<&Table> = Vec::with_capacity(tables.len());
+    let mut pending = tables;
+    
+    while !pending.is_empty() {
+        let before = pending.len();
+        let mut i = 0;
+        while i < pending.len() {
+            // Parents outside the set already exist (or are gone) and do not constrain the order
+            let ready = pending[i].inherits.iter().all(|parent| {
+                ordered.iter().any(|t| &t.name == parent) || !pending.iter().any(|t| &t.name == parent)
+            });
+            if ready {
+                ordered.push(pending.remove(i));
+            } else {
+                i += 1;
+            }
+        }
+        // Inheritance cannot be circular, but never loop forever on bad input
+        if pending.len() == before {
+            ordered.append(&mut pending);
+        }
+    }
+    
+    ordered
+}
 
-    fn create_test_column(name: &str, data_type: &str, nullable: bool) -> Column {
-        Column {
-            name: name.to_string(),
-            data_type: data_type.to_string(),
-            is_nullable: nullable,
-            default_value: None,
-            ordinal_position: 1,
+/// Whether two versions of a table still inherit from a common parent, so
+/// inherited columns are kept in sync through that parent.
+fn shares_parent(source: &Table, target: &Table) -> bool {
+    source.inherits.iter().any(|p| target.inherits.contains(p))
+}
+
+fn compare_tables(report: &mut DiffReport, source: &Table, target: &Table, options: &DiffOptions) {
+    // Compare inheritance
+    compare_inheritance(report, source, target);
+    
+    // Compare columns
+    compare_columns(report, source, target, options);
+    
+    // Compare logged/unlogged and storage parameters
+    compare_persistence(report, source, target);
+    compare_storage_parameters(report, source, target);
+    
+    // Compare primary key
+    compare_primary_keys(report, source, target);
+    
+    // Compare unique constraints
+    compare_unique_constraints(report, source, target);
+    
+    // Compare foreign keys
+    compare_foreign_keys(report, source, target, options);
+    
+    // Compare indexes
+    compare_indexes(report, source, target, options);
+    
+    // Compare row-level security settings and policies
+    compare_rls(report, source, target);
+    compare_policies(report, source, target);
+    
+    // Compare triggers
+    compare_triggers(report, source, target, options);
+}
+
+/// Compare functions and procedures between source and target.
+fn compare_functions(report: &mut DiffReport, source: &SchemaModel, target: &SchemaModel, options: &DiffOptions) {
+    // Find functions to add
+    for source_fn in &source.functions {
+        if target.find_function(&source_fn.name, &source_fn.arguments).is_none() {
+            report.items.push(DiffItem::new(
+                DiffKind::Added,
+                "function",
+                &function_signature(source_fn),
+                &format!("Create {} '{}'", function_kind(source_fn), function_signature(source_fn)),
+                &generate_create_function_sql(source_fn),
+                &generate_drop_function_sql(source_fn),
+                false,
+            ).with_dependencies(vec![function_key(&source_fn.name)], Vec::new()));
+        }
+    }
+    
+    // Find functions to remove
+    for target_fn in &target.functions {
+        if source.find_function(&target_fn.name, &target_fn.arguments).is_none() {
+            report.items.push(DiffItem::new(
+                DiffKind::Removed,
+                "function",
+                &function_signature(target_fn),
+                &format!("Drop {} '{}'", function_kind(target_fn), function_signature(target_fn)),
+                &generate_drop_function_sql(target_fn),
+                &generate_create_function_sql(target_fn),
+                false,
+            ).with_dependencies(vec![function_key(&target_fn.name)], Vec::new()));
+        }
+    }
+    
+    // Find modified functions
+    for source_fn in &source.functions {
+        if let Some(target_fn) = target.find_function(&source_fn.name, &source_fn.arguments) {
+            if bodies_equal(&source_fn.definition, &target_fn.definition, options.strict_body_comparison) {
+                continue;
+            }
+            
+            // CREATE OR REPLACE cannot change the return type or function kind
+            let replaceable = source_fn.return_type == target_fn.return_type
+                && source_fn.is_procedure == target_fn.is_procedure;
+            let (up_sql, down_sql) = if replaceable {
+                (generate_create_function_sql(source_fn), generate_create_function_sql(target_fn))
+            } else {
+                (
+                    format!("{}\n{}", generate_drop_function_sql(target_fn), generate_create_function_sql(source_fn)),
+                    format!("{}\n{}", generate_drop_function_sql(source_fn), generate_create_function_sql(target_fn)),
+                )
+            };
+            
+            report.items.push(DiffItem::new(
+                DiffKind::Modified,
+                "function",
+                &function_signature(source_fn),
+                &format!("Replace {} '{}'", function_kind(source_fn), function_signature(source_fn)),
+                &up_sql,
+                &down_sql,
+                false,
+            ).with_dependencies(vec![function_key(&source_fn.name)], Vec::new())
+            .with_changes(property_changes(source_fn, target_fn, &["return_type", "is_procedure", "definition"])));
+        }
+    }
+}
+
+fn function_kind(function: &Function) -> &'static str {
+    if function.is_procedure { "procedure" } else { "function" }
+}
+
+/// Name and identity arguments, e.g. `add(a integer, b integer)`.
+fn function_signature(function: &Function) -> String {
+    format!("{}({})", function.name, function.arguments)
+}
+
+/// Generate CREATE OR REPLACE FUNCTION SQL from the stored definition.
+fn generate_create_function_sql(function: &Function) -> String {
+    format!("{};", function.definition.trim_end().trim_end_matches(';'))
+}
+
+/// Generate DROP FUNCTION / DROP PROCEDURE SQL statement.
+fn generate_drop_function_sql(function: &Function) -> String {
+    format!(
+        "DROP {} IF EXISTS \"{}\"({});",
+        function_kind(function).to_uppercase(),
+        function.name,
+        function.arguments
+    )
+}
+
+/// Compare triggers between two tables.
+fn compare_triggers(report: &mut DiffReport, source: &Table, target: &Table, options: &DiffOptions) {
+    // Find triggers to add
+    for source_trigger in &source.triggers {
+        if target.find_trigger(&source_trigger.name).is_none() {
+            report.items.push(DiffItem::new(
+                DiffKind::Added,
+                "trigger",
+                &format!("{}.{}", source.name, source_trigger.name),
+                &format!("Create trigger '{}' on table '{}'", source_trigger.name, source.name),
+                &generate_create_trigger_sql(source_trigger),
+                &generate_drop_trigger_sql(&source.name, &source_trigger.name),
+                false,
+            ));
+        }
+    }
+    
+    // Find triggers to remove
+    for target_trigger in &target.triggers {
+        if source.find_trigger(&target_trigger.name).is_none() {
+            report.items.push(DiffItem::new(
+                DiffKind::Removed,
+                "trigger",
+                &format!("{}.{}", target.name, target_trigger.name),
+                &format!("Drop trigger '{}' from table '{}'", target_trigger.name, target.name),
+                &generate_drop_trigger_sql(&target.name, &target_trigger.name),
+                &generate_create_trigger_sql(target_trigger),
+                false,
+            ));
+        }
+    }
+    
+    // Find modified triggers (no CREATE OR REPLACE before PG14, so drop and recreate)
+    for source_trigger in &source.triggers {
+        if let Some(target_trigger) = target.find_trigger(&source_trigger.name) {
+            if bodies_equal(&source_trigger.definition, &target_trigger.definition, options.strict_body_comparison) {
+                continue;
+            }
+            
+            let drop_sql = generate_drop_trigger_sql(&source.name, &source_trigger.name);
+            report.items.push(DiffItem::new(
+                DiffKind::Modified,
+                "trigger",
+                &format!("{}.{}", source.name, source_trigger.name),
+                &format!("Recreate trigger '{}' on table '{}'", source_trigger.name, source.name),
+                &format!("{}\n{}", drop_sql, generate_create_trigger_sql(source_trigger)),
+                &format!("{}\n{}", drop_sql, generate_create_trigger_sql(target_trigger)),
+                false,
+            ).with_changes(property_changes(source_trigger, target_trigger, &["definition"])));
         }
     }
+}
+
+/// Generate CREATE TRIGGER SQL from the stored definition.
+fn generate_create_trigger_sql(trigger: &Trigger) -> String {
+    format!("{};", trigger.definition.trim_end().trim_end_matches(';'))
+}
+
+/// Generate DROP TRIGGER SQL statement.
+fn generate_drop_trigger_sql(table_name: &str, trigger_name: &str) -> String {
+    format!("DROP TRIGGER IF EXISTS \"{}\" ON \"{}\";", trigger_name, table_name)
+}
+
+/// Column properties reported in the changes of a modified column.
+const COLUMN_PROPERTIES: &[&str] = &[
+    "data_type", "is_nullable", "default_value", "generation_expression", "storage", "statistics_target",
+];
+
+/// Compare columns between two tables.
+fn compare_columns(report: &mut DiffReport, source: &Table, target: &Table, options: &DiffOptions) {
+    // Columns inherited through a common parent change with that parent
+    let via_parent = |col: &Column| col.inherited && shares_parent(source, target);
+    
+    // Find columns to add
+    for source_col in &source.columns {
+        if target.find_column(&source_col.name).is_none() && !via_parent(source_col) {
+            let needs_value = !source_col.is_nullable
+                && source_col.default_value.is_none()
+                && source_col.generation_expression.is_none();
+            let steps = if options.safe_migrations && needs_value {
+                let nullable = Column { is_nullable: true, ..source_col.clone() };
+                not_null_column_steps(source, source_col, &generate_add_column_sql(&source.name, &nullable))
+            } else {
+                None
+            };
+            let up_sql = steps.clone().unwrap_or_else(|| generate_add_column_sql(&source.name, source_col));
+            let down_sql = format!(
+                "ALTER TABLE \"{}\" DROP COLUMN IF EXISTS \"{}\";",
+                source.name, source_col.name
+            );
+            
+            let rewrites_table = source_col.generation_expression.is_some()
+                || source_col.default_value.as_deref().is_some_and(|d| options.default_rewrites_table(d));
+            let mut details = format!("Add column '{}' to table '{}'", source_col.name, source.name);
+            if steps.is_some() {
+                details.push_str(" (nullable, backfilled in batches, then SET NOT NULL)");
+            } else if options.safe_migrations && needs_value {
+                details.push_str(&format!(
+                    " (NOT NULL without a default fails on a table with rows; no backfill value for type '{}')",
+                    source_col.data_type
+                ));
+            }
+            if rewrites_table {
+                details.push_str(if source_col.generation_expression.is_some() {
+                    " (stored generated column; rewrites the table)"
+                } else {
+                    " (default is not on the safe list; rewrites the table)"
+                });
+            }
+            
+            let mut item = DiffItem::new(
+                DiffKind::Added,
+                "column",
+                &format!("{}.{}", source.name, source_col.name),
+                &details,
+                &up_sql,
+                &down_sql,
+                false,
+            ).with_dependencies(vec![column_key(&source.name, &source_col.name)], column_requires(source_col));
+            item.rewrites_table = rewrites_table;
+            report.items.push(item);
+        }
+    }
+    
+    // Find columns to remove
+    for target_col in &target.columns {
+        if source.find_column(&target_col.name).is_none() && !via_parent(target_col) {
+            let up_sql = format!(
+                "ALTER TABLE \"{}\" DROP COLUMN IF EXISTS \"{}\";",
+                source.name, target_col.name
+            );
+            let down_sql = generate_add_column_sql(&target.name, target_col);
+            
+            report.items.push(DiffItem::new(
+                DiffKind::Removed,
+                "column",
+                &format!("{}.{}", target.name, target_col.name),
+                &format!("Drop column '{}' from table '{}'", target_col.name, target.name),
+                &up_sql,
+                &down_sql,
+                true, // Dropping a column is dangerous
+            ).with_dependencies(vec![column_key(&target.name, &target_col.name)], column_requires(target_col)));
+        }
+    }
+    
+    // Find modified columns
+    for source_col in &source.columns {
+        if let Some(target_col) = target.find_column(&source_col.name) {
+            if via_parent(source_col) || via_parent(target_col) {
+                continue;
+            }
+            if !source_col.same_definition(target_col) {
+                let changes = describe_column_changes(source_col, target_col);
+                
+                if source_col.generation_expression != target_col.generation_expression {
+                    let (up_sql, down_sql, dangerous) =
+                        generate_generated_column_change_sql(&source.name, source_col, target_col);
+                    
+                    let mut item = DiffItem::new(
+                        DiffKind::Modified,
+                        "column",
+                        &format!("{}.{}", source.name, source_col.name),
+                        &changes,
+                        &up_sql,
+                        &down_sql,
+                        dangerous,
+                    ).with_dependencies(vec![column_key(&source.name, &source_col.name)], column_requires(source_col))
+                    .with_changes(property_changes(source_col, target_col, COLUMN_PROPERTIES));
+                    // Adding a stored generated column computes it for every row
+                    item.rewrites_table = source_col.generation_expression.is_some();
+                    report.items.push(item);
+                    continue;
+                }
+                
+                let (up_sql, down_sql) = generate_alter_column_sql(&source.name, source_col, target_col);
+                
+                let dangerous = source_col.data_type != target_col.data_type;
+                
+                report.items.push(DiffItem::new(
+                    DiffKind::Modified,
+                    "column",
+                    &format!("{}.{}", source.name, source_col.name),
+                    &changes,
+                    &up_sql,
+                    &down_sql,
+                    dangerous,
+                ).with_dependencies(vec![column_key(&source.name, &source_col.name)], column_requires(source_col))
+                .with_changes(property_changes(source_col, target_col, COLUMN_PROPERTIES)));
+            }
+        }
+    }
+}
+
+/// Compare primary keys between two tables.
+fn compare_primary_keys(report: &mut DiffReport, source: &Table, target: &Table) {
+    match (&source.primary_key, &target.primary_key) {
+        (Some(source_pk), None) => {
+            // Add primary key
+            let up_sql = format!(
+                "ALTER TABLE \"{}\" ADD CONSTRAINT \"{}\" PRIMARY KEY ({});",
+                source.name,
+                source_pk.name,
+                source_pk.columns.iter().map(|c| format!("\"{}\"", c)).collect::<Vec<_>>().join(", ")
+            );
+            let down_sql = format!(
+                "ALTER TABLE \"{}\" DROP CONSTRAINT IF EXISTS \"{}\";",
+                source.name, source_pk.name
+            );
+            
+            report.items.push(DiffItem::new(
+                DiffKind::Added,
+                "constraint",
+                &format!("{}.{}", source.name, source_pk.name),
+                &format!("Add primary key '{}' to table '{}'", source_pk.name, source.name),
+                &up_sql,
+                &down_sql,
+                false,
+            ).with_dependencies(constraint_provides(&source.name, source_pk), Vec::new()));
+        }
+        (None, Some(target_pk)) => {
+            // Remove primary key
+            let up_sql = format!(
+                "ALTER TABLE \"{}\" DROP CONSTRAINT IF EXISTS \"{}\";",
+                target.name, target_pk.name
+            );
+            let down_sql = format!(
+                "ALTER TABLE \"{}\" ADD CONSTRAINT \"{}\" PRIMARY KEY ({});",
+                target.name,
+                target_pk.name,
+                target_pk.columns.iter().map(|c| format!("\"{}\"", c)).collect::<Vec<_>>().join(", ")
+            );
+            
+            report.items.push(DiffItem::new(
+                DiffKind::Removed,
+                "constraint",
+                &format!("{}.{}", target.name, target_pk.name),
+                &format!("Drop primary key '{}' from table '{}'", target_pk.name, target.name),
+                &up_sql,
+                &down_sql,
+                true,
+            ).with_dependencies(constraint_provides(&target.name, target_pk), Vec::new()));
+        }
+        (Some(source_pk), Some(target_pk)) => {
+            // Check if primary key columns changed
+            if source_pk.columns != target_pk.columns {
+                let up_sql = format!(
+                    "ALTER TABLE \"{}\" DROP CONSTRAINT IF EXISTS \"{}\";\nALTER TABLE \"{}\" ADD CONSTRAINT \"{}\" PRIMARY KEY ({});",
+                    source.name, target_pk.name,
+                    source.name, source_pk.name,
+                    source_pk.columns.iter().map(|c| format!("\"{}\"", c)).collect::<Vec<_>>().join(", ")
+                );
+                let down_sql = format!(
+                    "ALTER TABLE \"{}\" DROP CONSTRAINT IF EXISTS \"{}\";\nALTER TABLE \"{}\" ADD CONSTRAINT \"{}\" PRIMARY KEY ({});",
+                    source.name, source_pk.name,
+                    source.name, target_pk.name,
+                    target_pk.columns.iter().map(|c| format!("\"{}\"", c)).collect::<Vec<_>>().join(", ")
+                );
+                
+                report.items.push(DiffItem::new(
+                    DiffKind::Modified,
+                    "constraint",
+                    &format!("{}.{}", source.name, source_pk.name),
+                    &format!("Modify primary key '{}' on table '{}'", source_pk.name, source.name),
+                    &up_sql,
+                    &down_sql,
+                    true,
+                ).with_dependencies(constraint_provides(&source.name, source_pk), Vec::new())
+                .with_changes(property_changes(source_pk, target_pk, &["name", "columns"])));
+            }
+        }
+        (None, None) => {}
+    }
+}
+
+/// Compare unique constraints between two tables.
+fn compare_unique_constraints(report: &mut DiffReport, source: &Table, target: &Table) {
+    // Find constraints to add
+    for source_uc in &source.unique_constraints {
+        let exists = target.unique_constraints.iter().any(|t| {
+            t.name == source_uc.name || t.columns == source_uc.columns
+        });
+        
+        if !exists {
+            let up_sql = format!(
+                "ALTER TABLE \"{}\" ADD CONSTRAINT \"{}\" UNIQUE ({});",
+                source.name,
+                source_uc.name,
+                source_uc.columns.iter().map(|c| format!("\"{}\"", c)).collect::<Vec<_>>().join(", ")
+            );
+            let down_sql = format!(
+                "ALTER TABLE \"{}\" DROP CONSTRAINT IF EXISTS \"{}\";",
+                source.name, source_uc.name
+            );
+            
+            report.items.push(DiffItem::new(
+                DiffKind::Added,
+                "constraint",
+                &format!("{}.{}", source.name, source_uc.name),
+                &format!("Add unique constraint '{}' to table '{}'", source_uc.name, source.name),
+                &up_sql,
+                &down_sql,
+                false,
+            ).with_dependencies(constraint_provides(&source.name, source_uc), Vec::new()));
+        }
+    }
+    
+    // Find constraints to remove
+    for target_uc in &target.unique_constraints {
+        let exists = source.unique_constraints.iter().any(|s| {
+            s.name == target_uc.name || s.columns == target_uc.columns
+        });
+        
+        if !exists {
+            let up_sql = format!(
+                "ALTER TABLE \"{}\" DROP CONSTRAINT IF EXISTS \"{}\";",
+                target.name, target_uc.name
+            );
+            let down_sql = format!(
+                "ALTER TABLE \"{}\" ADD CONSTRAINT \"{}\" UNIQUE ({});",
+                target.name,
+                target_uc.name,
+                target_uc.columns.iter().map(|c| format!("\"{}\"", c)).collect::<Vec<_>>().join(", ")
+            );
+            
+            report.items.push(DiffItem::new(
+                DiffKind::Removed,
+                "constraint",
+                &format!("{}.{}", target.name, target_uc.name),
+                &format!("Drop unique constraint '{}' from table '{}'", target_uc.name, target.name),
+                &up_sql,
+                &down_sql,
+                false,
+            ).with_dependencies(constraint_provides(&target.name, target_uc), Vec::new()));
+        }
+    }
+}
+
+/// Compare foreign keys between two tables.
+///
+/// A foreign key whose columns, reference, match type, actions or
+/// deferrability changed is dropped and added back. One that only differs in
+/// whether existing rows were validated gets a separate validation item.
+///
+/// With `validate_constraints_separately`, a foreign key that is added or
+/// added back is added `NOT VALID`, followed by a validation item.
+fn compare_foreign_keys(report: &mut DiffReport, source: &Table, target: &Table, options: &DiffOptions) {
+    for source_fk in &source.foreign_keys {
+        let target_fk = target.foreign_keys.iter().find(|t| t.name == source_fk.name);
+        let not_valid = (options.validate_constraints_separately && source_fk.validated)
+            .then(|| ForeignKey { validated: false, ..source_fk.clone() });
+        let added_fk = not_valid.as_ref().unwrap_or(source_fk);
+        match target_fk {
+            None => {
+                if !target.foreign_keys.iter().any(|t| t.same_definition(source_fk)) {
+                    report.items.push(add_foreign_key_item(&source.name, added_fk));
+                    if let Some(not_valid) = &not_valid {
+                        report.items.push(validation_item(&source.name, source_fk, not_valid));
+                    }
+                }
+            }
+            Some(target_fk) if !source_fk.same_definition(target_fk) => {
+                let up_sql = format!(
+                    "ALTER TABLE \"{}\" DROP CONSTRAINT IF EXISTS \"{}\";\n{}",
+                    source.name,
+                    target_fk.name,
+                    generate_add_foreign_key_sql(&source.name, added_fk)
+                );
+                let down_sql = format!(
+                    "ALTER TABLE \"{}\" DROP CONSTRAINT IF EXISTS \"{}\";\n{}",
+                    source.name,
+                    source_fk.name,
+                    generate_add_foreign_key_sql(&source.name, target_fk)
+                );
+                
+                report.items.push(DiffItem::new(
+                    DiffKind::Modified,
+                    "constraint",
+                    &format!("{}.{}", source.name, source_fk.name),
+                    &format!(
+                        "Modify foreign key '{}' on table '{}': {}",
+                        source_fk.name,
+                        source.name,
+                        describe_foreign_key_changes(source_fk, target_fk)
+                    ),
+                    &up_sql,
+                    &down_sql,
+                    false,
+                ).with_dependencies(Vec::new(), foreign_key_requires(source_fk))
+                .with_changes(property_changes(source_fk, target_fk, &[
+                    "columns", "referenced_table", "referenced_columns", "match_type",
+                    "on_delete", "on_update", "deferrable", "initially_deferred",
+                ])));
+                if let Some(not_valid) = &not_valid {
+                    report.items.push(validation_item(&source.name, source_fk, not_valid));
+                }
+            }
+            Some(target_fk) if source_fk.validated != target_fk.validated => {
+                report.items.push(validation_item(&source.name, source_fk, target_fk));
+            }
+            Some(_) => {}
+        }
+    }
+    
+    for target_fk in &target.foreign_keys {
+        let exists = source.foreign_keys.iter().any(|s| {
+            s.name == target_fk.name || s.same_definition(target_fk)
+        });
+        
+        if !exists {
+            let up_sql = format!(
+                "ALTER TABLE \"{}\" DROP CONSTRAINT IF EXISTS \"{}\";",
+                target.name, target_fk.name
+            );
+            let down_sql = generate_add_foreign_key_sql(&target.name, target_fk);
+            
+            report.items.push(DiffItem::new(
+                DiffKind::Removed,
+                "constraint",
+                &format!("{}.{}", target.name, target_fk.name),
+                &format!("Drop foreign key '{}' from table '{}'", target_fk.name, target.name),
+                &up_sql,
+                &down_sql,
+                false,
+            ).with_dependencies(Vec::new(), foreign_key_requires(target_fk)));
+        }
+    }
+}
+
+/// Diff item adding a foreign key to a table.
+fn add_foreign_key_item(table_name: &str, fk: &ForeignKey) -> DiffItem {
+    DiffItem::new(
+        DiffKind::Added,
+        "constraint",
+        &format!("{}.{}", table_name, fk.name),
+        &format!(
+            "Add foreign key '{}' to table '{}' referencing '{}'",
+            fk.name, table_name, fk.referenced_table
+        ),
+        &generate_add_foreign_key_sql(table_name, fk),
+        &format!("ALTER TABLE \"{}\" DROP CONSTRAINT IF EXISTS \"{}\";", table_name, fk.name),
+        false,
+    ).with_dependencies(Vec::new(), foreign_key_requires(fk))
+}
+
+/// Diff item validating a constraint added `NOT VALID`, or marking one not
+/// valid again.
+///
+/// PostgreSQL cannot take back a validation, so the way back drops the
+/// constraint and adds it `NOT VALID`.
+fn validation_item(table_name: &str, source: &ForeignKey, target: &ForeignKey) -> DiffItem {
+    let validate_sql = format!(
+        "ALTER TABLE \"{}\" VALIDATE CONSTRAINT \"{}\";",
+        table_name, source.name
+    );
+    let invalidate_sql = |fk: &ForeignKey| format!(
+        "ALTER TABLE \"{}\" DROP CONSTRAINT IF EXISTS \"{}\";\n{}",
+        table_name,
+        fk.name,
+        generate_add_foreign_key_sql(table_name, &ForeignKey { validated: false, ..fk.clone() })
+    );
+    
+    let (details, up_sql, down_sql) = if source.validated {
+        (
+            format!("Validate existing rows against foreign key '{}' on table '{}'", source.name, table_name),
+            validate_sql,
+            invalidate_sql(target),
+        )
+    } else {
+        (
+            format!("Mark foreign key '{}' on table '{}' as not valid", source.name, table_name),
+            invalidate_sql(source),
+            validate_sql,
+        )
+    };
+    
+    DiffItem::new(
+        DiffKind::Modified,
+        "validation",
+        &format!("{}.{}", table_name, source.name),
+        &details,
+        &up_sql,
+        &down_sql,
+        false,
+    ).with_changes(property_changes(source, target, &["validated"]))
+}
+
+/// Generate ADD CONSTRAINT ... FOREIGN KEY SQL; defaults are left out.
+fn generate_add_foreign_key_sql(table_name: &str, fk: &ForeignKey) -> String {
+    let quoted = |columns: &[String]| columns.iter().map(|c| format!("\"{}\"", c)).collect::<Vec<_>>().join(", ");
+    
+    let mut sql = format!(
+        "ALTER TABLE \"{}\" ADD CONSTRAINT \"{}\" FOREIGN KEY ({}) REFERENCES \"{}\"",
+        table_name,
+        fk.name,
+        quoted(&fk.columns),
+        fk.referenced_table
+    );
+    if !fk.referenced_columns.is_empty() {
+        sql.push_str(&format!(" ({})", quoted(&fk.referenced_columns)));
+    }
+    if fk.match_type != "SIMPLE" {
+        sql.push_str(&format!(" MATCH {}", fk.match_type));
+    }
+    if fk.on_delete != "NO ACTION" {
+        sql.push_str(&format!(" ON DELETE {}", fk.on_delete));
+    }
+    if fk.on_update != "NO ACTION" {
+        sql.push_str(&format!(" ON UPDATE {}", fk.on_update));
+    }
+    if fk.deferrable {
+        sql.push_str(" DEFERRABLE");
+        if fk.initially_deferred {
+            sql.push_str(" INITIALLY DEFERRED");
+        }
+    }
+    if !fk.validated {
+        sql.push_str(" NOT VALID");
+    }
+    sql.push(';');
+    sql
+}
+
+/// Describe the changes between two foreign keys.
+fn describe_foreign_key_changes(source: &ForeignKey, target: &ForeignKey) -> String {
+    let mut changes = Vec::new();
+    
+    let reference = |fk: &ForeignKey| format!(
+        "({}) references {}({})",
+        fk.columns.join(", "),
+        fk.referenced_table,
+        fk.referenced_columns.join(", ")
+    );
+    if reference(source) != reference(target) {
+        changes.push(format!("{} -> {}", reference(target), reference(source)));
+    }
+    if source.match_type != target.match_type {
+        changes.push(format!("match: {} -> {}", target.match_type, source.match_type));
+    }
+    if source.on_delete != target.on_delete {
+        changes.push(format!("on delete: {} -> {}", target.on_delete, source.on_delete));
+    }
+    if source.on_update != target.on_update {
+        changes.push(format!("on update: {} -> {}", target.on_update, source.on_update));
+    }
+    if source.deferrable != target.deferrable || source.initially_deferred != target.initially_deferred {
+        let deferrability = |fk: &ForeignKey| match (fk.deferrable, fk.initially_deferred) {
+            (true, true) => "deferrable initially deferred",
+            (true, false) => "deferrable",
+            _ => "not deferrable",
+        };
+        changes.push(format!("{} -> {}", deferrability(target), deferrability(source)));
+    }
+    
+    changes.join(", ")
+}
+
+/// Compare indexes between two tables.
+fn compare_indexes(report: &mut DiffReport, source: &Table, target: &Table, options: &DiffOptions) {
+    // Match indexes defined the same under other names, usually ones named
+    // by the server in each environment
+    if options.rename_matching_indexes {
+        let mut renamed: Vec<&str> = Vec::new();
+        for source_idx in source.indexes.iter().filter(|s| target.find_index(&s.name).is_none()) {
+            let target_idx = target.indexes.iter().find(|t| {
+                t.same_definition(source_idx)
+                    && source.find_index(&t.name).is_none()
+                    && !renamed.contains(&t.name.as_str())
+            });
+            let Some(target_idx) = target_idx else { continue };
+            renamed.push(&target_idx.name);
+            
+            report.items.push(DiffItem::new(
+                DiffKind::Renamed,
+                "index",
+                &format!("{}.{}", source.name, source_idx.name),
+                &format!(
+                    "Rename index '{}' on table '{}' to '{}'",
+                    target_idx.name, source.name, source_idx.name
+                ),
+                &format!("ALTER INDEX \"{}\" RENAME TO \"{}\";", target_idx.name, source_idx.name),
+                &format!("ALTER INDEX \"{}\" RENAME TO \"{}\";", source_idx.name, target_idx.name),
+                false,
+            ).with_dependencies(index_provides(&source.name, source_idx), Vec::new())
+            .with_changes(vec![PropertyChange::new("name", &target_idx.name, &source_idx.name)]));
+        }
+    }
+    
+    // Find indexes to add
+    for source_idx in &source.indexes {
+        let exists = target.indexes.iter().any(|t| {
+            t.name == source_idx.name || t.same_definition(source_idx)
+        });
+        
+        if !exists {
+            let up_sql = generate_create_index_sql(&source.name, source_idx);
+            let down_sql = format!("DROP INDEX IF EXISTS \"{}\";", source_idx.name);
+            
+            report.items.push(DiffItem::new(
+                DiffKind::Added,
+                "index",
+                &format!("{}.{}", source.name, source_idx.name),
+                &format!("Create index '{}' on table '{}'", source_idx.name, source.name),
+                &up_sql,
+                &down_sql,
+                false,
+            ).with_dependencies(index_provides(&source.name, source_idx), Vec::new()));
+        }
+    }
+    
+    // Find indexes to remove
+    for target_idx in &target.indexes {
+        let exists = source.indexes.iter().any(|s| {
+            s.name == target_idx.name || s.same_definition(target_idx)
+        });
+        
+        if !exists {
+            let up_sql = format!("DROP INDEX IF EXISTS \"{}\";", target_idx.name);
+            let down_sql = generate_create_index_sql(&target.name, target_idx);
+            
+            report.items.push(DiffItem::new(
+                DiffKind::Removed,
+                "index",
+                &format!("{}.{}", target.name, target_idx.name),
+                &format!("Drop index '{}' from table '{}'", target_idx.name, target.name),
+                &up_sql,
+                &down_sql,
+                false,
+            ).with_dependencies(index_provides(&target.name, target_idx), Vec::new()));
+        }
+    }
+    
+    // Find indexes whose definition changed under the same name
+    for source_idx in &source.indexes {
+        if let Some(target_idx) = target.find_index(&source_idx.name) {
+            if source_idx.same_definition(target_idx) {
+                continue;
+            }
+            
+            let drop_sql = format!("DROP INDEX IF EXISTS \"{}\";", source_idx.name);
+            report.items.push(DiffItem::new(
+                DiffKind::Modified,
+                "index",
+                &format!("{}.{}", source.name, source_idx.name),
+                &format!(
+                    "Recreate index '{}' on table '{}': {}",
+                    source_idx.name,
+                    source.name,
+                    describe_index_changes(source_idx, target_idx)
+                ),
+                &format!("{}\n{}", drop_sql, generate_create_index_sql(&source.name, source_idx)),
+                &format!("{}\n{}", drop_sql, generate_create_index_sql(&target.name, target_idx)),
+                false,
+            ).with_dependencies(index_provides(&source.name, source_idx), Vec::new())
+            .with_changes(property_changes(source_idx, target_idx, &[
+                "columns", "is_unique", "index_type", "predicate", "column_orders",
+                "include_columns", "column_opclasses", "storage_parameters",
+            ])));
+        }
+    }
+}
+
+/// Describe the changes between two indexes with the same name.
+fn describe_index_changes(source: &Index, target: &Index) -> String {
+    let mut changes = Vec::new();
+    
+    if source.columns != target.columns
+        || (0..source.columns.len()).any(|i| {
+            source.column_order(i) != target.column_order(i)
+                || source.column_opclass(i) != target.column_opclass(i)
+        })
+    {
+        changes.push(format!("columns ({}) -> ({})", index_key_sql(target), index_key_sql(source)));
+    }
+    if source.is_unique != target.is_unique {
+        changes.push(if source.is_unique { "make unique".to_string() } else { "drop uniqueness".to_string() });
+    }
+    if source.index_type != target.index_type {
+        changes.push(format!("method {} -> {}", target.index_type, source.index_type));
+    }
+    if source.storage_parameters != target.storage_parameters {
+        changes.push(format!(
+            "storage ({}) -> ({})",
+            target.storage_parameters.join(", "),
+            source.storage_parameters.join(", ")
+        ));
+    }
+    if source.predicate != target.predicate {
+        changes.push(format!(
+            "predicate {} -> {}",
+            target.predicate.as_deref().unwrap_or("none"),
+            source.predicate.as_deref().unwrap_or("none")
+        ));
+    }
+    if source.include_columns != target.include_columns {
+        changes.push(format!(
+            "include ({}) -> ({})",
+            target.include_columns.join(", "),
+            source.include_columns.join(", ")
+        ));
+    }
+    
+    changes.join(", ")
+}
+
+/// Compare the parents two tables inherit from.
+fn compare_inheritance(report: &mut DiffReport, source: &Table, target: &Table) {
+    for parent in &source.inherits {
+        if !target.inherits.contains(parent) {
+            report.items.push(DiffItem::new(
+                DiffKind::Added,
+                "inheritance",
+                &format!("{} -> {}", source.name, parent),
+                &format!("Make table '{}' inherit from '{}'", source.name, parent),
+                &format!("ALTER TABLE \"{}\" INHERIT \"{}\";", source.name, parent),
+                &format!("ALTER TABLE \"{}\" NO INHERIT \"{}\";", source.name, parent),
+                false,
+            ));
+        }
+    }
+    
+    for parent in &target.inherits {
+        if !source.inherits.contains(parent) {
+            report.items.push(DiffItem::new(
+                DiffKind::Removed,
+                "inheritance",
+                &format!("{} -> {}", target.name, parent),
+                &format!(
+                    "Stop table '{}' inheriting from '{}' (its rows no longer appear in queries on '{}')",
+                    target.name, parent, parent
+                ),
+                &format!("ALTER TABLE \"{}\" NO INHERIT \"{}\";", target.name, parent),
+                &format!("ALTER TABLE \"{}\" INHERIT \"{}\";", target.name, parent),
+                true,
+            ));
+        }
+    }
+}
+
+/// Compare row-level security flags between two tables.
+fn compare_rls(report: &mut DiffReport, source: &Table, target: &Table) {
+    if source.rls_enabled == target.rls_enabled && source.rls_forced == target.rls_forced {
+        return;
+    }
+    
+    let up_sql = generate_rls_sql(&source.name, source, target);
+    let down_sql = generate_rls_sql(&source.name, target, source);
+    
+    let mut changes = Vec::new();
+    if source.rls_enabled != target.rls_enabled {
+        changes.push(if source.rls_enabled { "enable" } else { "disable" });
+    }
+    if source.rls_forced != target.rls_forced {
+        changes.push(if source.rls_forced { "force" } else { "no force" });
+    }
+    
+    // Turning RLS off exposes every row to every role with table privileges
+    let dangerous = target.rls_enabled && !source.rls_enabled;
+    
+    report.items.push(DiffItem::new(
+        DiffKind::Modified,
+        "rls",
+        &source.name,
+        &format!("Row level security on table '{}': {}", source.name, changes.join(", ")),
+        &up_sql,
+        &down_sql,
+        dangerous,
+    ).with_changes(property_changes(source, target, &["rls_enabled", "rls_forced"])));
+}
+
+/// Compare whether a table is logged or unlogged.
+fn compare_persistence(report: &mut DiffReport, source: &Table, target: &Table) {
+    if source.unlogged == target.unlogged {
+        return;
+    }
+    
+    let persistence = |unlogged: bool| if unlogged { "UNLOGGED" } else { "LOGGED" };
+    let up_sql = format!("ALTER TABLE \"{}\" SET {};", source.name, persistence(source.unlogged));
+    let down_sql = format!("ALTER TABLE \"{}\" SET {};", source.name, persistence(target.unlogged));
+    
+    // An unlogged table loses its rows on a crash and is empty on standbys
+    let mut item = DiffItem::new(
+        DiffKind::Modified,
+        "persistence",
+        &source.name,
+        &format!(
+            "Set table '{}' {} (rewrites the table)",
+            source.name,
+            persistence(source.unlogged).to_lowercase()
+        ),
+        &up_sql,
+        &down_sql,
+        source.unlogged,
+    ).with_changes(property_changes(source, target, &["unlogged"]));
+    item.rewrites_table = true;
+    report.items.push(item);
+}
+
+/// Compare table storage parameters (fillfactor, autovacuum settings, ...).
+fn compare_storage_parameters(report: &mut DiffReport, source: &Table, target: &Table) {
+    let up_sql = generate_storage_parameters_sql(&source.name, &source.storage_parameters, &target.storage_parameters);
+    if up_sql.is_empty() {
+        return;
+    }
+    let down_sql = generate_storage_parameters_sql(&source.name, &target.storage_parameters, &source.storage_parameters);
+    
+    let describe = |parameters: &[String]| {
+        if parameters.is_empty() { "defaults".to_string() } else { parameters.join(", ") }
+    };
+    
+    report.items.push(DiffItem::new(
+        DiffKind::Modified,
+        "storage_parameters",
+        &source.name,
+        &format!(
+            "Storage parameters of table '{}': {} -> {}",
+            source.name,
+            describe(&target.storage_parameters),
+            describe(&source.storage_parameters)
+        ),
+        &up_sql,
+        &down_sql,
+        false,
+    ).with_changes(property_changes(source, target, &["storage_parameters"])));
+}
+
+/// Generate ALTER TABLE statements moving storage parameters from `from` to `to`;
+/// empty when they already match. Order does not matter.
+fn generate_storage_parameters_sql(table_name: &str, to: &[String], from: &[String]) -> String {
+    let name = |parameter: &String| parameter.split('=').next().unwrap_or_default().to_string();
+    
+    let set: Vec<&String> = to.iter().filter(|p| !from.contains(p)).collect();
+    let reset: Vec<String> = from.iter()
+        .map(name)
+        .filter(|n| !to.iter().any(|p| name(p) == *n))
+        .collect();
+    
+    let mut parts = Vec::new();
+    if !set.is_empty() {
+        parts.push(format!(
+            "ALTER TABLE \"{}\" SET ({});",
+            table_name,
+            set.iter().map(|p| p.as_str()).collect::<Vec<_>>().join(", ")
+        ));
+    }
+    if !reset.is_empty() {
+        parts.push(format!("ALTER TABLE \"{}\" RESET ({});", table_name, reset.join(", ")));
+    }
+    parts.join("\n")
+}
+
+/// Generate ALTER TABLE statements moving RLS flags from `from` to `to`.
+fn generate_rls_sql(table_name: &str, to: &Table, from: &Table) -> String {
+    let mut parts = Vec::new();
+    
+    if to.rls_enabled != from.rls_enabled {
+        parts.push(format!(
+            "ALTER TABLE \"{}\" {} ROW LEVEL SECURITY;",
+            table_name,
+            if to.rls_enabled { "ENABLE" } else { "DISABLE" }
+        ));
+    }
+    
+    if to.rls_forced != from.rls_forced {
+        parts.push(format!(
+            "ALTER TABLE \"{}\" {} ROW LEVEL SECURITY;",
+            table_name,
+            if to.rls_forced { "FORCE" } else { "NO FORCE" }
+        ));
+    }
+    
+    parts.join("\n")
+}
+
+/// Compare row-level security policies between two tables.
+fn compare_policies(report: &mut DiffReport, source: &Table, target: &Table) {
+    // Find policies to add
+    for source_policy in &source.policies {
+        if target.find_policy(&source_policy.name).is_none() {
+            let up_sql = generate_create_policy_sql(&source.name, source_policy);
+            let down_sql = generate_drop_policy_sql(&source.name, &source_policy.name);
+            
+            report.items.push(DiffItem::new(
+                DiffKind::Added,
+                "policy",
+                &format!("{}.{}", source.name, source_policy.name),
+                &format!("Create policy '{}' on table '{}'", source_policy.name, source.name),
+                &up_sql,
+                &down_sql,
+                false,
+            ));
+        }
+    }
+    
+    // Find policies to remove
+    for target_policy in &target.policies {
+        if source.find_policy(&target_policy.name).is_none() {
+            let up_sql = generate_drop_policy_sql(&target.name, &target_policy.name);
+            let down_sql = generate_create_policy_sql(&target.name, target_policy);
+            
+            report.items.push(DiffItem::new(
+                DiffKind::Removed,
+                "policy",
+                &format!("{}.{}", target.name, target_policy.name),
+                &format!("Drop policy '{}' from table '{}'", target_policy.name, target.name),
+                &up_sql,
+                &down_sql,
+                false,
+            ));
+        }
+    }
+    
+    // Find modified policies
+    for source_policy in &source.policies {
+        if let Some(target_policy) = target.find_policy(&source_policy.name) {
+            if source_policy != target_policy {
+                let up_sql = generate_alter_policy_sql(&source.name, source_policy, target_policy);
+                let down_sql = generate_alter_policy_sql(&source.name, target_policy, source_policy);
+                
+                report.items.push(DiffItem::new(
+                    DiffKind::Modified,
+                    "policy",
+                    &format!("{}.{}", source.name, source_policy.name),
+                    &describe_policy_changes(source_policy, target_policy),
+                    &up_sql,
+                    &down_sql,
+                    false,
+                )
+                .with_changes(property_changes(source_policy, target_policy, &["command", "permissive", "roles", "using_expr", "check_expr"])));
+            }
+        }
+    }
+}
+
+/// Format a role name for a TO/FROM clause (PUBLIC is a keyword, not a role).
+fn format_role(role: &str) -> String {
+    if role.eq_ignore_ascii_case("public") {
+        "PUBLIC".to_string()
+    } else {
+        format!("\"{}\"", role)
+    }
+}
+
+/// Generate CREATE POLICY SQL statement.
+fn generate_create_policy_sql(table_name: &str, policy: &Policy) -> String {
+    let mut sql = format!("CREATE POLICY \"{}\" ON \"{}\"", policy.name, table_name);
+    
+    if !policy.permissive {
+        sql.push_str(" AS RESTRICTIVE");
+    }
+    
+    sql.push_str(&format!(" FOR {}", policy.command));
+    
+    if !policy.roles.is_empty() {
+        sql.push_str(&format!(
+            " TO {}",
+            policy.roles.iter().map(|r| format_role(r)).collect::<Vec<_>>().join(", ")
+        ));
+    }
+    
+    if let Some(using) = &policy.using_expr {
+        sql.push_str(&format!(" USING ({})", using));
+    }
+    
+    if let Some(check) = &policy.check_expr {
+        sql.push_str(&format!(" WITH CHECK ({})", check));
+    }
+    
+    sql.push(';');
+    sql
+}
+
+/// Generate DROP POLICY SQL statement.
+fn generate_drop_policy_sql(table_name: &str, policy_name: &str) -> String {
+    format!("DROP POLICY IF EXISTS \"{}\" ON \"{}\";", policy_name, table_name)
+}
+
+/// Generate SQL turning policy `from` into policy `to`.
+///
+/// ALTER POLICY can change roles and expressions, but not the command or
+/// permissive mode, and it cannot remove an expression. Those cases fall back
+/// to dropping and recreating the policy.
+fn generate_alter_policy_sql(table_name: &str, to: &Policy, from: &Policy) -> String {
+    let needs_recreate = to.command != from.command
+        || to.permissive != from.permissive
+        || (to.using_expr.is_none() && from.using_expr.is_some())
+        || (to.check_expr.is_none() && from.check_expr.is_some());
+    
+    if needs_recreate {
+        return format!(
+            "{}\n{}",
+            generate_drop_policy_sql(table_name, &from.name),
+            generate_create_policy_sql(table_name, to)
+        );
+    }
+    
+    let mut sql = format!("ALTER POLICY \"{}\" ON \"{}\"", to.name, table_name);
+    
+    if to.roles != from.roles {
+        let roles = if to.roles.is_empty() {
+            "PUBLIC".to_string()
+        } else {
+            to.roles.iter().map(|r| format_role(r)).collect::<Vec<_>>().join(", ")
+        };
+        sql.push_str(&format!(" TO {}", roles));
+    }
+    
+    if to.using_expr != from.using_expr {
+        if let Some(using) = &to.using_expr {
+            sql.push_str(&format!(" USING ({})", using));
+        }
+    }
+    
+    if to.check_expr != from.check_expr {
+        if let Some(check) = &to.check_expr {
+            sql.push_str(&format!(" WITH CHECK ({})", check));
+        }
+    }
+    
+    sql.push(';');
+    sql
+}
+
+/// Describe the changes between two policies.
+fn describe_policy_changes(source: &Policy, target: &Policy) -> String {
+    let mut changes = Vec::new();
+    
+    if source.command != target.command {
+        changes.push(format!("command: {} -> {}", target.command, source.command));
+    }
+    
+    if source.permissive != target.permissive {
+        changes.push(format!("permissive: {} -> {}", target.permissive, source.permissive));
+    }
+    
+    if source.roles != target.roles {
+        changes.push(format!("roles: {:?} -> {:?}", target.roles, source.roles));
+    }
+    
+    if source.using_expr != target.using_expr {
+        changes.push(format!("using: {:?} -> {:?}", target.using_expr, source.using_expr));
+    }
+    
+    if source.check_expr != target.check_expr {
+        changes.push(format!("with check: {:?} -> {:?}", target.check_expr, source.check_expr));
+    }
+    
+    format!("Modify policy '{}': {}", source.name, changes.join(", "))
+}
+
+/// Generate CREATE TABLE SQL statement.
+fn generate_create_table_sql(table: &Table) -> String {
+    let mut sql = String::new();
+    
+    // Inherited columns are created by the parent
+    let local_columns: Vec<&Column> = table.columns.iter().filter(|c| !c.inherited).collect();
+    
+    // First, create sequences for columns with nextval defaults; SERIAL
+    // columns create their own, and a sequence already holding the name
+    // would make PostgreSQL pick another
+    for col in &local_columns {
+        if let Some(default) = col.default_value.as_deref().filter(|d| !is_serial_default(d)) {
+            if let Some(seq_name) = extract_sequence_name(default) {
+                sql.push_str(&format!(
+                    "CREATE SEQUENCE IF NOT EXISTS \"{}\";\n",
+                    seq_name
+                ));
+            }
+        }
+    }
+    
+    sql.push_str(&format!(
+        "CREATE {}TABLE \"{}\" (\n",
+        if table.unlogged { "UNLOGGED " } else { "" },
+        table.name
+    ));
+    
+    let mut parts: Vec<String> = Vec::new();
+    
+    // Columns
+    for col in &local_columns {
+        let col_def = generate_column_definition(col);
+        parts.push(format!("    {}", col_def));
+    }
+    
+    // Primary key
+    if let Some(pk) = &table.primary_key {
+        parts.push(format!(
+            "    CONSTRAINT \"{}\" PRIMARY KEY ({})",
+            pk.name,
+            pk.columns.iter().map(|c| format!("\"{}\"", c)).collect::<Vec<_>>().join(", ")
+        ));
+    }
+    
+    // Unique constraints
+    for uc in &table.unique_constraints {
+        parts.push(format!(
+            "    CONSTRAINT \"{}\" UNIQUE ({})",
+            uc.name,
+            uc.columns.iter().map(|c| format!("\"{}\"", c)).collect::<Vec<_>>().join(", ")
+        ));
+    }
+    
+    sql.push_str(&parts.join(",\n"));
+    sql.push_str("\n)");
+    if !table.inherits.is_empty() {
+        sql.push_str(&format!(
+            " INHERITS ({})",
+            table.inherits.iter().map(|p| format!("\"{}\"", p)).collect::<Vec<_>>().join(", ")
+        ));
+    }
+    if !table.storage_parameters.is_empty() {
+        sql.push_str(&format!(" WITH ({})", table.storage_parameters.join(", ")));
+    }
+    sql.push_str(";\n");
+    
+    // Column storage and statistics targets
+    for col in &local_columns {
+        for setting in generate_column_settings_sql(&table.name, col) {
+            sql.push_str(&setting);
+            sql.push('\n');
+        }
+    }
+    
+    // Indexes (created separately)
+    for idx in &table.indexes {
+        sql.push_str(&generate_create_index_sql(&table.name, idx));
+        sql.push('\n');
+    }
+    
+    // Row level security and policies
+    if table.rls_enabled {
+        sql.push_str(&format!("ALTER TABLE \"{}\" ENABLE ROW LEVEL SECURITY;\n", table.name));
+    }
+    if table.rls_forced {
+        sql.push_str(&format!("ALTER TABLE \"{}\" FORCE ROW LEVEL SECURITY;\n", table.name));
+    }
+    for policy in &table.policies {
+        sql.push_str(&generate_create_policy_sql(&table.name, policy));
+        sql.push('\n');
+    }
+    
+    // Triggers
+    for trigger in &table.triggers {
+        sql.push_str(&generate_create_trigger_sql(trigger));
+        sql.push('\n');
+    }
+    
+    sql
+}
+
+/// Generate column definition for CREATE TABLE or ADD COLUMN.
+fn generate_column_definition(col: &Column) -> String {
+    // Check if this is a serial/identity column
+    if let Some(default) = &col.default_value {
+        if is_serial_default(default) {
+            // Convert to appropriate SERIAL type
+            let serial_type = match col.data_type.as_str() {
+                "bigint" => "BIGSERIAL",
+                "smallint" => "SMALLSERIAL", 
+                _ => "SERIAL",
+            };
+            
+            let mut col_def = format!("\"{}\" {}", col.name, serial_type);
+            if !col.is_nullable {
+                col_def.push_str(" NOT NULL");
+            }
+            return col_def;
+        }
+    }
+    
+    // Use format_data_type to properly quote ENUM types
+    let mut col_def = format!("\"{}\" {}", col.name, format_data_type(&col.data_type));
+    
+    if !col.is_nullable {
+        col_def.push_str(" NOT NULL");
+    }
+    
+    if let Some(default) = &col.default_value {
+        col_def.push_str(&format!(" DEFAULT {}", default));
+    }
+    
+    if let Some(expr) = &col.generation_expression {
+        col_def.push_str(&format!(" GENERATED ALWAYS AS ({}) STORED", expr));
+    }
+    
+    col_def
+}
+
+/// Check if a default value represents a serial/sequence column.
+fn is_serial_default(default: &str) -> bool {
+    let lower = default.to_lowercase();
+    lower.contains("nextval(") && lower.contains("_seq")
+}
+
+/// Extract sequence name from a nextval default.
+fn extract_sequence_name(default: &str) -> Option<String> {
+    // Match patterns like: nextval('table_id_seq'::regclass)
+    let lower = default.to_lowercase();
+    if lower.contains("nextval(") {
+        // Extract the sequence name from the string
+        if let Some(start) = default.find('\'') {
+            if let Some(end) = default[start + 1..].find('\'') {
+                let seq_name = &default[start + 1..start + 1 + end];
+                // Remove schema prefix if present
+                let clean_name = seq_name.split('.').last().unwrap_or(seq_name);
+                return Some(clean_name.to_string());
+            }
+        }
+    }
+    None
+}
+
+/// Generate ADD COLUMN SQL statement.
+fn generate_add_column_sql(table_name: &str, column: &Column) -> String {
+    let mut sql = String::new();
+    
+    // First, create sequence if needed, unless SERIAL creates it
+    if let Some(default) = column.default_value.as_deref().filter(|d| !is_serial_default(d)) {
+        if let Some(seq_name) = extract_sequence_name(default) {
+            sql.push_str(&format!(
+                "CREATE SEQUENCE IF NOT EXISTS \"{}\";\n",
+                seq_name
+            ));
+        }
+    }
+    
+    // Check if this is a serial column
+    if let Some(default) = &column.default_value {
+        if is_serial_default(default) {
+            let serial_type = match column.data_type.as_str() {
+                "bigint" => "BIGSERIAL",
+                "smallint" => "SMALLSERIAL",
+                _ => "SERIAL",
+            };
+            
+            sql.push_str(&format!(
+                "ALTER TABLE \"{}\" ADD COLUMN \"{}\" {}",
+                table_name, column.name, serial_type
+            ));
+            
+            if !column.is_nullable {
+                sql.push_str(" NOT NULL");
+            }
+            
+            sql.push(';');
+            push_column_settings_sql(&mut sql, table_name, column);
+            return sql;
+        }
+    }
+    
+    // Use format_data_type to properly quote ENUM types
+    sql.push_str(&format!(
+        "ALTER TABLE \"{}\" ADD COLUMN \"{}\" {}",
+        table_name, column.name, format_data_type(&column.data_type)
+    ));
+    
+    if !column.is_nullable {
+        sql.push_str(" NOT NULL");
+    }
+    
+    if let Some(default) = &column.default_value {
+        sql.push_str(&format!(" DEFAULT {}", default));
+    }
+    
+    if let Some(expr) = &column.generation_expression {
+        sql.push_str(&format!(" GENERATED ALWAYS AS ({}) STORED", expr));
+    }
+    
+    sql.push(';');
+    push_column_settings_sql(&mut sql, table_name, column);
+    sql
+}
+
+/// Append the storage and statistics statements of a new column.
+fn push_column_settings_sql(sql: &mut String, table_name: &str, column: &Column) {
+    for setting in generate_column_settings_sql(table_name, column) {
+        sql.push('\n');
+        sql.push_str(&setting);
+    }
+}
+
+/// `SET STORAGE` and `SET STATISTICS` statements for a column's non-default
+/// settings.
+fn generate_column_settings_sql(table_name: &str, column: &Column) -> Vec<String> {
+    let mut statements = Vec::new();
+    if let Some(storage) = &column.storage {
+        statements.push(format!(
+            "ALTER TABLE \"{}\" ALTER COLUMN \"{}\" SET STORAGE {};",
+            table_name, column.name, storage
+        ));
+    }
+    if let Some(target) = column.statistics_target {
+        statements.push(format!(
+            "ALTER TABLE \"{}\" ALTER COLUMN \"{}\" SET STATISTICS {};",
+            table_name, column.name, target
+        ));
+    }
+    statements
+}
+
+/// Generate SQL that drops a column and adds it back with a new definition.
+fn generate_readd_column_sql(table_name: &str, column: &Column) -> String {
+    format!(
+        "ALTER TABLE \"{}\" DROP COLUMN IF EXISTS \"{}\";\n{}",
+        table_name,
+        column.name,
+        generate_add_column_sql(table_name, column)
+    )
+}
+
+/// Generate SQL for a column whose generation expression differs.
+///
+/// Returns (up, down, dangerous). A generated column that becomes a plain
+/// column keeps its values via DROP EXPRESSION (PG13+). Any other change has
+/// to drop and re-add the column, since the expression cannot be altered in
+/// place; that loses data only when a plain column becomes generated.
+fn generate_generated_column_change_sql(
+    table_name: &str,
+    source: &Column,
+    target: &Column,
+) -> (String, String, bool) {
+    match (&source.generation_expression, &target.generation_expression) {
+        (None, Some(_)) => {
+            let mut up_sql = format!(
+                "ALTER TABLE \"{}\" ALTER COLUMN \"{}\" DROP EXPRESSION;",
+                table_name, source.name
+            );
+            let plain_target = Column { generation_expression: None, ..target.clone() };
+            let (alter_up, _) = generate_alter_column_sql(table_name, source, &plain_target);
+            if !alter_up.is_empty() {
+                up_sql.push('\n');
+                up_sql.push_str(&alter_up);
+            }
+            (up_sql, generate_readd_column_sql(table_name, target), false)
+        }
+        (Some(_), None) => (
+            generate_readd_column_sql(table_name, source),
+            generate_readd_column_sql(table_name, target),
+            true,
+        ),
+        _ => (
+            generate_readd_column_sql(table_name, source),
+            generate_readd_column_sql(table_name, target),
+            false,
+        ),
+    }
+}
+
+/// Generate ALTER COLUMN SQL statements.
+fn generate_alter_column_sql(table_name: &str, source: &Column, target: &Column) -> (String, String) {
+    let mut up_parts = Vec::new();
+    let mut down_parts = Vec::new();
+    
+    // Type change
+    if source.data_type != target.data_type {
+        let source_type = format_data_type(&source.data_type);
+        let target_type = format_data_type(&target.data_type);
+        up_parts.push(format!(
+            "ALTER TABLE \"{}\" ALTER COLUMN \"{}\" TYPE {} USING \"{}\"::{}",
+            table_name, source.name, source_type, source.name, source_type
+        ));
+        down_parts.push(format!(
+            "ALTER TABLE \"{}\" ALTER COLUMN \"{}\" TYPE {} USING \"{}\"::{}",
+            table_name, source.name, target_type, source.name, target_type
+        ));
+    }
+    
+    // Nullability change
+    if source.is_nullable != target.is_nullable {
+        if source.is_nullable {
+            up_parts.push(format!(
+                "ALTER TABLE \"{}\" ALTER COLUMN \"{}\" DROP NOT NULL",
+                table_name, source.name
+            ));
+            down_parts.push(format!(
+                "ALTER TABLE \"{}\" ALTER COLUMN \"{}\" SET NOT NULL",
+                table_name, source.name
+            ));
+        } else {
+            up_parts.push(format!(
+                "ALTER TABLE \"{}\" ALTER COLUMN \"{}\" SET NOT NULL",
+                table_name, source.name
+            ));
+            down_parts.push(format!(
+                "ALTER TABLE \"{}\" ALTER COLUMN \"{}\" DROP NOT NULL",
+                table_name, source.name
+            ));
+        }
+    }
+    
+    // Storage change
+    if source.storage != target.storage {
+        up_parts.push(format!(
+            "ALTER TABLE \"{}\" ALTER COLUMN \"{}\" SET STORAGE {}",
+            table_name,
+            source.name,
+            source.storage.as_deref().unwrap_or_else(|| source.default_storage())
+        ));
+        down_parts.push(format!(
+            "ALTER TABLE \"{}\" ALTER COLUMN \"{}\" SET STORAGE {}",
+            table_name,
+            source.name,
+            target.storage.as_deref().unwrap_or_else(|| target.default_storage())
+        ));
+    }
+    
+    // Statistics target change; -1 restores default_statistics_target
+    if source.statistics_target != target.statistics_target {
+        up_parts.push(format!(
+            "ALTER TABLE \"{}\" ALTER COLUMN \"{}\" SET STATISTICS {}",
+            table_name, source.name, source.statistics_target.unwrap_or(-1)
+        ));
+        down_parts.push(format!(
+            "ALTER TABLE \"{}\" ALTER COLUMN \"{}\" SET STATISTICS {}",
+            table_name, source.name, target.statistics_target.unwrap_or(-1)
+        ));
+    }
+    
+    // Default change
+    if source.default_value != target.default_value {
+        match &source.default_value {
+            Some(default) => {
+                up_parts.push(format!(
+                    "ALTER TABLE \"{}\" ALTER COLUMN \"{}\" SET DEFAULT {}",
+                    table_name, source.name, default
+                ));
+            }
+            None => {
+                up_parts.push(format!(
+                    "ALTER TABLE \"{}\" ALTER COLUMN \"{}\" DROP DEFAULT",
+                    table_name, source.name
+                ));
+            }
+        }
+        
+        match &target.default_value {
+            Some(default) => {
+                down_parts.push(format!(
+                    "ALTER TABLE \"{}\" ALTER COLUMN \"{}\" SET DEFAULT {}",
+                    table_name, source.name, default
+                ));
+            }
+            None => {
+                down_parts.push(format!(
+                    "ALTER TABLE \"{}\" ALTER COLUMN \"{}\" DROP DEFAULT",
+                    table_name, source.name
+                ));
+            }
+        }
+    }
+    
+    (
+        up_parts.iter().map(|s| format!("{};", s)).collect::<Vec<_>>().join("\n"),
+        down_parts.iter().map(|s| format!("{};", s)).collect::<Vec<_>>().join("\n"),
+    )
+}
+
+/// Generate CREATE INDEX SQL statement.
+fn generate_create_index_sql(table_name: &str, index: &Index) -> String {
+    let unique = if index.is_unique { "UNIQUE " } else { "" };
+    let using = if index.index_type != "btree" {
+        format!(" USING {}", index.index_type)
+    } else {
+        String::new()
+    };
+    
+    let include = if index.include_columns.is_empty() {
+        String::new()
+    } else {
+        format!(
+            " INCLUDE ({})",
+            index.include_columns.iter().map(|c| format!("\"{}\"", c)).collect::<Vec<_>>().join(", ")
+        )
+    };
+    
+    let with = if index.storage_parameters.is_empty() {
+        String::new()
+    } else {
+        format!(" WITH ({})", index.storage_parameters.join(", "))
+    };
+    
+    let predicate = match &index.predicate {
+        Some(predicate) => format!(" WHERE {}", predicate),
+        None => String::new(),
+    };
+    
+    format!(
+        "CREATE {}INDEX \"{}\" ON \"{}\"{} ({}){}{}{};",
+        unique,
+        index.name,
+        table_name,
+        using,
+        index_key_sql(index),
+        include,
+        with,
+        predicate
+    )
+}
+
+/// Key column list of an index with operator class and sort order,
+/// e.g. `"name" gin_trgm_ops`, `"created_at" DESC`.
+fn index_key_sql(index: &Index) -> String {
+    index.columns.iter()
+        .enumerate()
+        .map(|(i, c)| {
+            let opclass = index.column_opclass(i).map(|o| format!(" {}", o)).unwrap_or_default();
+            format!("\"{}\"{}{}", c, opclass, index.column_order(i).keywords())
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Describe the changes between two columns.
+fn describe_column_changes(source: &Column, target: &Column) -> String {
+    let mut changes = Vec::new();
+    
+    if source.data_type != target.data_type {
+        changes.push(format!("type: {} -> {}", target.data_type, source.data_type));
+    }
+    
+    if source.is_nullable != target.is_nullable {
+        changes.push(format!(
+            "nullable: {} -> {}",
+            target.is_nullable, source.is_nullable
+        ));
+    }
+    
+    if source.default_value != target.default_value {
+        changes.push(format!(
+            "default: {:?} -> {:?}",
+            target.default_value, source.default_value
+        ));
+    }
+    
+    if source.generation_expression != target.generation_expression {
+        changes.push(format!(
+            "generated: {:?} -> {:?}",
+            target.generation_expression, source.generation_expression
+        ));
+    }
+    
+    if source.storage != target.storage {
+        changes.push(format!("storage: {:?} -> {:?}", target.storage, source.storage));
+    }
+    
+    if source.statistics_target != target.statistics_target {
+        changes.push(format!(
+            "statistics: {:?} -> {:?}",
+            target.statistics_target, source.statistics_target
+        ));
+    }
+    
+    format!("Modify column '{}': {}", source.name, changes.join(", "))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::schema::{Constraint, IndexColumnOrder};
+
+    fn create_test_column(name: &str, data_type: &str, nullable: bool) -> Column {
+        Column {
+            name: name.to_string(),
+            data_type: data_type.to_string(),
+            is_nullable: nullable,
+            default_value: None,
+            ordinal_position: 1,
+            generation_expression: None,
+            comment: None,
+            inherited: false,
+            storage: None,
+            statistics_target: None,
+        }
+    }
+
+    fn create_test_table(name: &str, columns: Vec<Column>) -> Table {
+        Table {
+            name: name.to_string(),
+            columns,
+            primary_key: None,
+            unique_constraints: Vec::new(),
+            indexes: Vec::new(),
+            rls_enabled: false,
+            rls_forced: false,
+            policies: Vec::new(),
+            triggers: Vec::new(),
+            foreign_keys: Vec::new(),
+            comment: None,
+            inherits: Vec::new(),
+            unlogged: false,
+            storage_parameters: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_added_table() {
+        let source = SchemaModel {
+            tables: vec![create_test_table(
+                "users",
+                vec![create_test_column("id", "integer", false)],
+            )],
+            ..Default::default()
+        };
+        let target = SchemaModel::new();
+
+        let report = compare_schemas(&source, &target);
+
+        assert_eq!(report.items.len(), 1);
+        assert_eq!(report.items[0].kind, DiffKind::Added);
+        assert_eq!(report.items[0].object_type, "table");
+        assert_eq!(report.items[0].object_name, "users");
+        assert!(!report.items[0].dangerous);
+    }
+
+    #[test]
+    fn test_stable_item_ids() {
+        let source = SchemaModel {
+            tables: vec![create_test_table("users", vec![create_test_column("id", "integer", false)])],
+            ..Default::default()
+        };
+        let target = SchemaModel::new();
+
+        let first = compare_schemas(&source, &target);
+        let second = compare_schemas(&source, &target);
+        assert_eq!(first.items[0].id, second.items[0].id);
+        assert_eq!(first.items[0].id.len(), 32);
+
+        // Any change to the SQL is a different item
+        let mut changed = source.clone();
+        changed.tables[0].columns[0].data_type = "bigint".to_string();
+        assert_ne!(compare_schemas(&changed, &target).items[0].id, first.items[0].id);
+        assert_ne!(compare_schemas(&target, &source).items[0].id, first.items[0].id);
+    }
+
+    #[test]
+    fn test_removed_table() {
+        let source = SchemaModel::new();
+        let target = SchemaModel {
+            tables: vec![create_test_table(
+                "users",
+                vec![create_test_column("id", "integer", false)],
+            )],
+            ..Default::default()
+        };
+
+        let report = compare_schemas(&source, &target);
+
+        assert_eq!(report.items.len(), 1);
+        assert_eq!(report.items[0].kind, DiffKind::Removed);
+        assert_eq!(report.items[0].object_type, "table");
+        assert_eq!(report.items[0].object_name, "users");
+        assert!(report.items[0].dangerous);
+    }
+
+    fn create_test_child_table(name: &str, parent: &str) -> Table {
+        let mut inherited = create_test_column("id", "integer", false);
+        inherited.inherited = true;
+        let mut table = create_test_table(name, vec![inherited, create_test_column("doors", "integer", true)]);
+        table.inherits = vec![parent.to_string()];
+        table
+    }
+
+    #[test]
+    fn test_inherited_tables_created_after_parents() {
+        let source = SchemaModel {
+            tables: vec![
+                create_test_child_table("cars", "vehicles"),
+                create_test_table("vehicles", vec![create_test_column("id", "integer", false)]),
+            ],
+            ..Default::default()
+        };
+
+        let report = compare_schemas(&source, &SchemaModel::new());
+
+        let names: Vec<&str> = report.items.iter().map(|i| i.object_name.as_str()).collect();
+        assert_eq!(names, vec!["vehicles", "cars"]);
+        // The inherited column comes from the parent and is not declared again
+        assert_eq!(
+            report.items[1].generated_up_sql,
+            "CREATE TABLE \"cars\" (\n    \"doors\" integer\n) INHERITS (\"vehicles\");\n"
+        );
+
+        // Dropping goes the other way round
+        let report = compare_schemas(&SchemaModel::new(), &source);
+        let names: Vec<&str> = report.items.iter().map(|i| i.object_name.as_str()).collect();
+        assert_eq!(names, vec!["cars", "vehicles"]);
+    }
+
+    #[test]
+    fn test_inheritance_changes() {
+        let parents = vec![
+            create_test_table("vehicles", vec![create_test_column("id", "integer", false)]),
+            create_test_table("assets", vec![create_test_column("id", "integer", false)]),
+        ];
+        let mut source_child = create_test_child_table("cars", "vehicles");
+        let mut target_child = create_test_child_table("cars", "vehicles");
+        // A column only the source parent has arrives through the parent's own diff
+        let mut wheels = create_test_column("wheels", "integer", true);
+        wheels.inherited = true;
+        source_child.columns.push(wheels);
+        source_child.inherits.push("assets".to_string());
+        target_child.inherits = vec!["vehicles".to_string()];
+
+        let mut source = SchemaModel { tables: parents.clone(), ..Default::default() };
+        source.tables.push(source_child);
+        let mut target = SchemaModel { tables: parents, ..Default::default() };
+        target.tables.push(target_child.clone());
+
+        let report = compare_schemas(&source, &target);
+
+        assert_eq!(report.items.len(), 1);
+        assert_eq!(report.items[0].object_type, "inheritance");
+        assert_eq!(report.items[0].kind, DiffKind::Added);
+        assert_eq!(report.items[0].generated_up_sql, "ALTER TABLE \"cars\" INHERIT \"assets\";");
+
+        // Detaching is dangerous and keeps the formerly inherited columns local
+        let mut detached = target_child;
+        detached.inherits.clear();
+        detached.columns[0].inherited = false;
+        let mut source = target.clone();
+        source.tables[2] = detached;
+
+        let report = compare_schemas(&source, &target);
+
+        assert_eq!(report.items.len(), 1);
+        assert_eq!(report.items[0].kind, DiffKind::Removed);
+        assert_eq!(report.items[0].generated_up_sql, "ALTER TABLE \"cars\" NO INHERIT \"vehicles\";");
+        assert!(report.items[0].dangerous);
+    }
+
+    #[test]
+    fn test_persistence_change() {
+        let mut unlogged = create_test_table("cache", vec![create_test_column("key", "text", false)]);
+        unlogged.unlogged = true;
+        let logged = create_test_table("cache", vec![create_test_column("key", "text", false)]);
+
+        let source = SchemaModel { tables: vec![unlogged.clone()], ..Default::default() };
+        let target = SchemaModel { tables: vec![logged], ..Default::default() };
+
+        let report = compare_schemas(&source, &target);
+        assert_eq!(report.items.len(), 1);
+        assert_eq!(report.items[0].object_type, "persistence");
+        assert_eq!(report.items[0].generated_up_sql, "ALTER TABLE \"cache\" SET UNLOGGED;");
+        assert_eq!(report.items[0].generated_down_sql, "ALTER TABLE \"cache\" SET LOGGED;");
+        assert!(report.items[0].dangerous);
+        assert!(report.items[0].rewrites_table);
+
+        // Back to logged is safe
+        let report = compare_schemas(&target, &source);
+        assert_eq!(report.items[0].generated_up_sql, "ALTER TABLE \"cache\" SET LOGGED;");
+        assert!(!report.items[0].dangerous);
+
+        let report = compare_schemas(&source, &SchemaModel::new());
+        assert!(report.items[0].generated_up_sql.starts_with("CREATE UNLOGGED TABLE \"cache\" ("));
+    }
+
+    #[test]
+    fn test_storage_parameter_changes() {
+        let mut tuned = create_test_table("events", vec![create_test_column("id", "integer", false)]);
+        tuned.storage_parameters = vec![
+            "autovacuum_vacuum_scale_factor=0.01".to_string(),
+            "fillfactor=80".to_string(),
+        ];
+        let mut drifted = tuned.clone();
+        drifted.storage_parameters = vec!["fillfactor=90".to_string(), "autovacuum_enabled=false".to_string()];
+
+        let source = SchemaModel { tables: vec![tuned.clone()], ..Default::default() };
+        let target = SchemaModel { tables: vec![drifted], ..Default::default() };
+
+        let report = compare_schemas(&source, &target);
+        assert_eq!(report.items.len(), 1);
+        assert_eq!(report.items[0].object_type, "storage_parameters");
+        assert_eq!(
+            report.items[0].generated_up_sql,
+            "ALTER TABLE \"events\" SET (autovacuum_vacuum_scale_factor=0.01, fillfactor=80);\n\
+             ALTER TABLE \"events\" RESET (autovacuum_enabled);"
+        );
+        assert_eq!(
+            report.items[0].generated_down_sql,
+            "ALTER TABLE \"events\" SET (fillfactor=90, autovacuum_enabled=false);\n\
+             ALTER TABLE \"events\" RESET (autovacuum_vacuum_scale_factor);"
+        );
+
+        // Order alone is no drift
+        let mut reordered = tuned.clone();
+        reordered.storage_parameters.reverse();
+        let target = SchemaModel { tables: vec![reordered], ..Default::default() };
+        assert!(compare_schemas(&source, &target).items.is_empty());
+
+        let report = compare_schemas(&source, &SchemaModel::new());
+        assert!(report.items[0].generated_up_sql.contains(") WITH (autovacuum_vacuum_scale_factor=0.01, fillfactor=80);"));
+    }
+
+    fn create_test_foreign_key(name: &str, column: &str, referenced_table: &str) -> ForeignKey {
+        ForeignKey {
+            name: name.to_string(),
+            columns: vec![column.to_string()],
+            referenced_table: referenced_table.to_string(),
+            referenced_columns: vec!["id".to_string()],
+            match_type: "SIMPLE".to_string(),
+            on_delete: "NO ACTION".to_string(),
+            on_update: "NO ACTION".to_string(),
+            deferrable: false,
+            initially_deferred: false,
+            validated: true,
+        }
+    }
+
+    #[test]
+    fn test_foreign_key_changes() {
+        let users = create_test_table("users", vec![create_test_column("id", "integer", false)]);
+        let orders = |fk: ForeignKey| {
+            let mut orders = create_test_table("orders", vec![create_test_column("user_id", "integer", false)]);
+            orders.foreign_keys = vec![fk];
+            orders
+        };
+
+        let plain = create_test_foreign_key("orders_user_id_fkey", "user_id", "users");
+        let mut cascading = plain.clone();
+        cascading.match_type = "FULL".to_string();
+        cascading.on_delete = "CASCADE".to_string();
+        cascading.deferrable = true;
+        cascading.initially_deferred = true;
+
+        let source = SchemaModel { tables: vec![users.clone(), orders(cascading)], ..Default::default() };
+        let target = SchemaModel { tables: vec![users.clone(), orders(plain.clone())], ..Default::default() };
+
+        let report = compare_schemas(&source, &target);
+        assert_eq!(report.items.len(), 1);
+        let item = &report.items[0];
+        assert_eq!(item.kind, DiffKind::Modified);
+        assert_eq!(item.object_type, "constraint");
+        assert_eq!(item.object_name, "orders.orders_user_id_fkey");
+        assert!(item.details.contains("match: SIMPLE -> FULL"));
+        assert!(item.details.contains("on delete: NO ACTION -> CASCADE"));
+        assert!(item.details.contains("not deferrable -> deferrable initially deferred"));
+        let changed: Vec<_> = item.changes.iter().map(|c| c.property.as_str()).collect();
+        assert_eq!(changed, vec!["match_type", "on_delete", "deferrable", "initially_deferred"]);
+        assert_eq!(item.changes[1].old, serde_json::json!("NO ACTION"));
+        assert_eq!(item.changes[1].new, serde_json::json!("CASCADE"));
+        assert_eq!(
+            item.generated_up_sql,
+            "ALTER TABLE \"orders\" DROP CONSTRAINT IF EXISTS \"orders_user_id_fkey\";\n\
+             ALTER TABLE \"orders\" ADD CONSTRAINT \"orders_user_id_fkey\" FOREIGN KEY (\"user_id\") \
+             REFERENCES \"users\" (\"id\") MATCH FULL ON DELETE CASCADE DEFERRABLE INITIALLY DEFERRED;"
+        );
+        assert!(item.generated_down_sql.ends_with("REFERENCES \"users\" (\"id\");"));
+
+        // New tables get their foreign keys after every table is created
+        let report = compare_schemas(&target, &SchemaModel::new());
+        let kinds: Vec<_> = report.items.iter().map(|i| (i.object_type.as_str(), i.object_name.as_str())).collect();
+        assert_eq!(kinds, vec![("table", "users"), ("table", "orders"), ("constraint", "orders.orders_user_id_fkey")]);
+
+        // Dropped foreign keys on existing tables
+        let unreferenced = SchemaModel {
+            tables: vec![users, create_test_table("orders", vec![create_test_column("user_id", "integer", false)])],
+            ..Default::default()
+        };
+        let report = compare_schemas(&unreferenced, &target);
+        assert_eq!(report.items.len(), 1);
+        assert_eq!(report.items[0].kind, DiffKind::Removed);
+    }
+
+    #[test]
+    fn test_explicit_renames() {
+        use crate::diff::renames::RenameObject;
+
+        let users = |table: &str, column: &str| create_test_table(table, vec![
+            create_test_column("id", "integer", false),
+            create_test_column(column, "text", true),
+        ]);
+        let orders = |referenced_table: &str| {
+            let mut orders = create_test_table("orders", vec![create_test_column("user_id", "integer", false)]);
+            orders.foreign_keys = vec![create_test_foreign_key("orders_user_id_fkey", "user_id", referenced_table)];
+            orders
+        };
+        let source = SchemaModel { tables: vec![users("accounts", "email_address"), orders("accounts")], ..Default::default() };
+        let target = SchemaModel { tables: vec![users("users", "email"), orders("users")], ..Default::default() };
+
+        // Without the mapping the table is dropped and created again
+        let report = compare_schemas(&source, &target);
+        assert!(report.items.iter().any(|i| i.kind == DiffKind::Removed && i.object_name == "users"));
+
+        let options = DiffOptions {
+            renames: vec![
+                RenameMapping { object_type: RenameObject::Column, table: Some("accounts".to_string()), from: "email".to_string(), to: "email_address".to_string() },
+                RenameMapping { object_type: RenameObject::Table, table: None, from: "users".to_string(), to: "accounts".to_string() },
+                RenameMapping { object_type: RenameObject::Column, table: Some("accounts".to_string()), from: "name".to_string(), to: "full_name".to_string() },
+            ],
+            ..Default::default()
+        };
+        let report = compare_schemas_with_options(&source, &target, &options);
+        let statements: Vec<_> = report.items.iter().map(|i| i.generated_up_sql.as_str()).collect();
+        assert_eq!(statements, vec![
+            "ALTER TABLE \"users\" RENAME TO \"accounts\";",
+            "ALTER TABLE \"accounts\" RENAME COLUMN \"email\" TO \"email_address\";",
+        ]);
+        assert_eq!(report.items[1].generated_down_sql, "ALTER TABLE \"accounts\" RENAME COLUMN \"email_address\" TO \"email\";");
+        assert!(report.items.iter().all(|i| i.kind == DiffKind::Renamed));
+        assert_eq!(report.items[0].changes, vec![PropertyChange::new("name", "users", "accounts")]);
+        assert_eq!(report.warnings.len(), 1);
+        assert!(report.warnings[0].contains("'accounts.name'"));
+    }
+
+    #[test]
+    fn test_foreign_key_validation() {
+        let users = create_test_table("users", vec![create_test_column("id", "integer", false)]);
+        let orders = |validated: bool| {
+            let mut orders = create_test_table("orders", vec![create_test_column("user_id", "integer", false)]);
+            let mut fk = create_test_foreign_key("orders_user_id_fkey", "user_id", "users");
+            fk.validated = validated;
+            orders.foreign_keys = vec![fk];
+            orders
+        };
+        let validated = SchemaModel { tables: vec![users.clone(), orders(true)], ..Default::default() };
+        let not_valid = SchemaModel { tables: vec![users, orders(false)], ..Default::default() };
+        let add_not_valid = "ALTER TABLE \"orders\" DROP CONSTRAINT IF EXISTS \"orders_user_id_fkey\";\n\
+             ALTER TABLE \"orders\" ADD CONSTRAINT \"orders_user_id_fkey\" FOREIGN KEY (\"user_id\") \
+             REFERENCES \"users\" (\"id\") NOT VALID;";
+
+        let report = compare_schemas(&validated, &not_valid);
+        assert_eq!(report.items.len(), 1);
+        let item = &report.items[0];
+        assert_eq!(item.kind, DiffKind::Modified);
+        assert_eq!(item.object_type, "validation");
+        assert_eq!(item.object_name, "orders.orders_user_id_fkey");
+        assert_eq!(item.generated_up_sql, "ALTER TABLE \"orders\" VALIDATE CONSTRAINT \"orders_user_id_fkey\";");
+        assert_eq!(item.generated_down_sql, add_not_valid);
+
+        let report = compare_schemas(&not_valid, &validated);
+        assert_eq!(report.items.len(), 1);
+        assert_eq!(report.items[0].generated_up_sql, add_not_valid);
+
+        // Other changes recreate the constraint in its source state
+        let mut cascading = not_valid.clone();
+        cascading.tables[1].foreign_keys[0].on_delete = "CASCADE".to_string();
+        let report = compare_schemas(&cascading, &validated);
+        assert_eq!(report.items.len(), 1);
+        assert_eq!(report.items[0].object_type, "constraint");
+        assert!(report.items[0].generated_up_sql.ends_with("ON DELETE CASCADE NOT VALID;"));
+    }
+
+    #[test]
+    fn test_validate_constraints_separately() {
+        let users = create_test_table("users", vec![create_test_column("id", "integer", false)]);
+        let mut orders = create_test_table("orders", vec![create_test_column("user_id", "integer", false)]);
+        let target = SchemaModel { tables: vec![users.clone(), orders.clone()], ..Default::default() };
+        orders.foreign_keys = vec![create_test_foreign_key("orders_user_id_fkey", "user_id", "users")];
+        let source = SchemaModel { tables: vec![users, orders], ..Default::default() };
+        let options = DiffOptions { validate_constraints_separately: true, ..Default::default() };
+
+        let report = compare_schemas_with_options(&source, &target, &options);
+        assert_eq!(report.items.len(), 2);
+        assert!(report.items[0].generated_up_sql.ends_with("REFERENCES \"users\" (\"id\") NOT VALID;"));
+        assert_eq!(report.items[1].object_type, "validation");
+        assert_eq!(
+            report.items[1].generated_up_sql,
+            "ALTER TABLE \"orders\" VALIDATE CONSTRAINT \"orders_user_id_fkey\";"
+        );
+
+        // Without the option, and for new tables, foreign keys are validated as they are added
+        assert_eq!(compare_schemas(&source, &target).items.len(), 1);
+        let empty = SchemaModel::default();
+        let report = compare_schemas_with_options(&source, &empty, &options);
+        assert!(!report.items.iter().any(|i| i.object_type == "validation"));
+    }
+
+    #[test]
+    fn test_safe_not_null_column() {
+        let mut orders = create_test_table("orders", vec![create_test_column("id", "bigint", false)]);
+        let target = SchemaModel { tables: vec![orders.clone()], ..Default::default() };
+        orders.columns.push(create_test_column("status", "text", false));
+        orders.columns.push(create_test_column("mood", "mood", false));
+        let source = SchemaModel { tables: vec![orders], ..Default::default() };
+        let options = DiffOptions { safe_migrations: true, ..Default::default() };
+
+        let report = compare_schemas_with_options(&source, &target, &options);
+        let status = &report.items[0].generated_up_sql;
+        let position = |text: &str| status.find(text).unwrap();
+        assert!(status.starts_with("ALTER TABLE \"orders\" ADD COLUMN \"status\" text;\n"));
+        assert!(position("SET DEFAULT '';") < position("DO $$"));
+        assert!(status.contains("WHERE ctid IN (SELECT ctid FROM \"orders\" WHERE \"status\" IS NULL LIMIT 10000)"));
+        assert!(position("VALIDATE CONSTRAINT \"orders_status_not_null\"") < position("SET NOT NULL"));
+        assert!(status.ends_with("ALTER TABLE \"orders\" ALTER COLUMN \"status\" DROP DEFAULT;"));
+        // Without a value to backfill, the column is added as before
+        assert_eq!(report.items[1].generated_up_sql, "ALTER TABLE \"orders\" ADD COLUMN \"mood\" \"mood\" NOT NULL;");
+        assert!(report.items[1].details.contains("no backfill value"));
+
+        let mut keyed = source.clone();
+        keyed.tables[0].primary_key = Some(Constraint {
+            name: "orders_pkey".to_string(),
+            constraint_type: "PRIMARY KEY".to_string(),
+            columns: vec!["id".to_string()],
+        });
+        let mut keyed_target = target.clone();
+        keyed_target.tables[0].primary_key = keyed.tables[0].primary_key.clone();
+        let report = compare_schemas_with_options(&keyed, &keyed_target, &options);
+        assert!(report.items[0].generated_up_sql.contains("last_key bigint;"));
+        assert!(report.items[0].generated_up_sql.contains("\"orders\".\"status\" IS NULL"));
+
+        assert!(compare_schemas(&source, &target).items[0].generated_up_sql.ends_with("\"status\" text NOT NULL;"));
+    }
+
+    #[test]
+    fn test_column_storage_and_statistics_changes() {
+        let mut payload = create_test_column("payload", "jsonb", true);
+        payload.storage = Some("EXTERNAL".to_string());
+        let mut tenant_id = create_test_column("tenant_id", "integer", false);
+        tenant_id.statistics_target = Some(1000);
+        let tuned = create_test_table("events", vec![payload, tenant_id]);
+
+        let source = SchemaModel { tables: vec![tuned.clone()], ..Default::default() };
+        let target = SchemaModel {
+            tables: vec![create_test_table("events", vec![
+                create_test_column("payload", "jsonb", true),
+                create_test_column("tenant_id", "integer", false),
+            ])],
+            ..Default::default()
+        };
+
+        let report = compare_schemas(&source, &target);
+        assert_eq!(report.items.len(), 2);
+        assert!(report.items.iter().all(|i| i.kind == DiffKind::Modified && !i.dangerous));
+        assert_eq!(
+            report.items[0].generated_up_sql,
+            "ALTER TABLE \"events\" ALTER COLUMN \"payload\" SET STORAGE EXTERNAL;"
+        );
+        assert_eq!(
+            report.items[0].generated_down_sql,
+            "ALTER TABLE \"events\" ALTER COLUMN \"payload\" SET STORAGE EXTENDED;"
+        );
+        assert_eq!(
+            report.items[1].generated_up_sql,
+            "ALTER TABLE \"events\" ALTER COLUMN \"tenant_id\" SET STATISTICS 1000;"
+        );
+        assert_eq!(
+            report.items[1].generated_down_sql,
+            "ALTER TABLE \"events\" ALTER COLUMN \"tenant_id\" SET STATISTICS -1;"
+        );
+
+        // New tables get their settings after CREATE TABLE
+        let report = compare_schemas(&source, &SchemaModel::new());
+        let up_sql = &report.items[0].generated_up_sql;
+        assert!(up_sql.contains("ALTER TABLE \"events\" ALTER COLUMN \"payload\" SET STORAGE EXTERNAL;\n"));
+        assert!(up_sql.contains("ALTER TABLE \"events\" ALTER COLUMN \"tenant_id\" SET STATISTICS 1000;\n"));
+    }
+
+    #[test]
+    fn test_enum_reordering() {
+        let status = |values: &[&str]| EnumType {
+            name: "status".to_string(),
+            values: values.iter().map(|v| v.to_string()).collect(),
+        };
+        let mut state = create_test_column("state", "status", false);
+        state.default_value = Some("'active'::status".to_string());
+        let users = create_test_table("users", vec![state, create_test_column("history", "status[]", true)]);
+
+        let source = SchemaModel { enums: vec![status(&["pending", "active", "disabled"])], ..Default::default() };
+        let target = SchemaModel {
+            enums: vec![status(&["active", "pending", "disabled"])],
+            tables: vec![users],
+            ..Default::default()
+        };
+
+        let mut report = compare_schemas(&source, &target);
+        let item = report.items.iter_mut().find(|i| i.object_type == "enum").unwrap();
+        assert_eq!(item.kind, DiffKind::Reordered);
+        assert!(!item.dangerous);
+        let reorder = item.enum_reorder.as_ref().unwrap();
+        assert_eq!(reorder.target_order, vec!["active", "pending", "disabled"]);
+        assert_eq!(reorder.strategy, EnumReorderStrategy::IgnoreOrder);
+        assert!(item.generated_up_sql.starts_with("--"));
+
+        item.resolve_enum_reorder(EnumReorderStrategy::RecreateType).unwrap();
+        assert!(item.rewrites_table);
+        assert_eq!(
+            item.generated_up_sql,
+            "ALTER TYPE \"status\" RENAME TO \"status_old\";\n\
+             CREATE TYPE \"status\" AS ENUM ('pending', 'active', 'disabled');\n\
+             ALTER TABLE \"users\" ALTER COLUMN \"state\" DROP DEFAULT;\n\
+             ALTER TABLE \"users\" ALTER COLUMN \"state\" TYPE \"status\" USING \"state\"::text::\"status\";\n\
+             ALTER TABLE \"users\" ALTER COLUMN \"state\" SET DEFAULT 'active'::status;\n\
+             ALTER TABLE \"users\" ALTER COLUMN \"history\" TYPE \"status\"[] USING \"history\"::text[]::\"status\"[];\n\
+             DROP TYPE \"status_old\";"
+        );
+        assert!(item.generated_down_sql.contains("AS ENUM ('active', 'pending', 'disabled');"));
+
+        // Different values are still added or removed, not reordered
+        let source = SchemaModel { enums: vec![status(&["pending", "active"])], ..Default::default() };
+        let report = compare_schemas(&source, &target);
+        assert!(report.items.iter().filter(|i| i.object_type == "enum").all(|i| i.kind == DiffKind::Modified));
+    }
+
+    #[test]
+    fn test_serial_columns_create_no_sequence() {
+        let mut id = create_test_column("id", "bigint", false);
+        id.default_value = Some("nextval('users_id_seq'::regclass)".to_string());
+        let mut number = create_test_column("number", "integer", false);
+        number.default_value = Some("nextval('invoice_numbers'::regclass)".to_string());
+        let table = create_test_table("users", vec![id.clone(), number.clone()]);
+
+        let sql = generate_create_table_sql(&table);
+        assert!(!sql.contains("CREATE SEQUENCE IF NOT EXISTS \"users_id_seq\""));
+        assert!(sql.contains("\"id\" BIGSERIAL"));
+        assert!(sql.starts_with("CREATE SEQUENCE IF NOT EXISTS \"invoice_numbers\";\n"));
+
+        let sql = generate_add_column_sql("users", &id);
+        assert!(sql.starts_with("ALTER TABLE \"users\" ADD COLUMN \"id\" BIGSERIAL NOT NULL;"));
+        let sql = generate_add_column_sql("users", &number);
+        assert!(sql.starts_with("CREATE SEQUENCE IF NOT EXISTS \"invoice_numbers\";\n"));
+    }
+
+    #[test]
+    fn test_added_column() {
+        let source = SchemaModel {
+            tables: vec![create_test_table(
+                "users",
+                vec![
+                    create_test_column("id", "integer", false),
+                    create_test_column("email", "varchar(255)", false),
+                ],
+            )],
+            ..Default::default()
+        };
+        let target = SchemaModel {
+            tables: vec![create_test_table(
+                "users",
+                vec![create_test_column("id", "integer", false)],
+            )],
+            ..Default::default()
+        };
+
+        let report = compare_schemas(&source, &target);
+
+        assert_eq!(report.items.len(), 1);
+        assert_eq!(report.items[0].kind, DiffKind::Added);
+        assert_eq!(report.items[0].object_type, "column");
+        assert_eq!(report.items[0].object_name, "users.email");
+    }
+
+    #[test]
+    fn test_removed_column() {
+        let source = SchemaModel {
+            tables: vec![create_test_table(
+                "users",
+                vec![create_test_column("id", "integer", false)],
+            )],
+            ..Default::default()
+        };
+        let target = SchemaModel {
+            tables: vec![create_test_table(
+                "users",
+                vec![
+                    create_test_column("id", "integer", false),
+                    create_test_column("email", "varchar(255)", false),
+                ],
+            )],
+            ..Default::default()
+        };
+
+        let report = compare_schemas(&source, &target);
+
+        assert_eq!(report.items.len(), 1);
+        assert_eq!(report.items[0].kind, DiffKind::Removed);
+        assert_eq!(report.items[0].object_type, "column");
+        assert!(report.items[0].dangerous);
+    }
+
+    #[test]
+    fn test_modified_column_type() {
+        let source = SchemaModel {
+            tables: vec![create_test_table(
+                "users",
+                vec![create_test_column("name", "text", false)],
+            )],
+            ..Default::default()
+        };
+        let target = SchemaModel {
+            tables: vec![create_test_table(
+                "users",
+                vec![create_test_column("name", "varchar(100)", false)],
+            )],
+            ..Default::default()
+        };
+
+        let report = compare_schemas(&source, &target);
+
+        assert_eq!(report.items.len(), 1);
+        assert_eq!(report.items[0].kind, DiffKind::Modified);
+        assert_eq!(report.items[0].object_type, "column");
+        assert!(report.items[0].dangerous); // Type change is dangerous
+    }
+
+    #[test]
+    fn test_modified_column_nullability() {
+        let source = SchemaModel {
+            tables: vec![create_test_table(
+                "users",
+                vec![create_test_column("name", "text", false)],
+            )],
+            ..Default::default()
+        };
+        let target = SchemaModel {
+            tables: vec![create_test_table(
+                "users",
+                vec![create_test_column("name", "text", true)],
+            )],
+            ..Default::default()
+        };
+
+        let report = compare_schemas(&source, &target);
+
+        assert_eq!(report.items.len(), 1);
+        assert_eq!(report.items[0].kind, DiffKind::Modified);
+        assert!(!report.items[0].dangerous); // Nullability change is not dangerous
+        assert_eq!(report.items[0].changes, vec![PropertyChange::new("is_nullable", true, false)]);
+    }
+
+    #[test]
+    fn test_no_diff() {
+        let schema = SchemaModel {
+            tables: vec![create_test_table(
+                "users",
+                vec![create_test_column("id", "integer", false)],
+            )],
+            ..Default::default()
+        };
+
+        let report = compare_schemas(&schema, &schema);
+
+        assert!(report.items.is_empty());
+    }
+
+    #[test]
+    fn test_primary_key_added() {
+        let mut source_table = create_test_table(
+            "users",
+            vec![create_test_column("id", "integer", false)],
+        );
+        source_table.primary_key = Some(Constraint {
+            name: "users_pkey".to_string(),
+            constraint_type: "PRIMARY KEY".to_string(),
+            columns: vec!["id".to_string()],
+        });
+
+        let source = SchemaModel {
+            tables: vec![source_table],
+            ..Default::default()
+        };
+        let target = SchemaModel {
+            tables: vec![create_test_table(
+                "users",
+                vec![create_test_column("id", "integer", false)],
+            )],
+            ..Default::default()
+        };
+
+        let report = compare_schemas(&source, &target);
+
+        assert_eq!(report.items.len(), 1);
+        assert_eq!(report.items[0].kind, DiffKind::Added);
+        assert_eq!(report.items[0].object_type, "constraint");
+    }
+
+    #[test]
+    fn test_index_added() {
+        let mut source_table = create_test_table(
+            "users",
+            vec![create_test_column("email", "varchar(255)", false)],
+        );
+        source_table.indexes.push(Index {
+            name: "idx_users_email".to_string(),
+            columns: vec!["email".to_string()],
+            is_unique: false,
+            index_type: "btree".to_string(),
+            predicate: None,
+            column_orders: Vec::new(),
+            include_columns: Vec::new(),
+            column_opclasses: Vec::new(),
+            storage_parameters: Vec::new(),
+        });
+
+        let source = SchemaModel {
+            tables: vec![source_table],
+            ..Default::default()
+        };
+        let target = SchemaModel {
+            tables: vec![create_test_table(
+                "users",
+                vec![create_test_column("email", "varchar(255)", false)],
+            )],
+            ..Default::default()
+        };
+
+        let report = compare_schemas(&source, &target);
+
+        assert_eq!(report.items.len(), 1);
+        assert_eq!(report.items[0].kind, DiffKind::Added);
+        assert_eq!(report.items[0].object_type, "index");
+    }
+
+    fn create_test_policy(name: &str, using: &str) -> Policy {
+        Policy {
+            name: name.to_string(),
+            command: "SELECT".to_string(),
+            permissive: true,
+            roles: vec!["authenticated".to_string()],
+            using_expr: Some(using.to_string()),
+            check_expr: None,
+        }
+    }
+
+    #[test]
+    fn test_rls_enabled() {
+        let mut source_table = create_test_table(
+            "users",
+            vec![create_test_column("id", "integer", false)],
+        );
+        source_table.rls_enabled = true;
+
+        let source = SchemaModel {
+            tables: vec![source_table],
+            ..Default::default()
+        };
+        let target = SchemaModel {
+            tables: vec![create_test_table(
+                "users",
+                vec![create_test_column("id", "integer", false)],
+            )],
+            ..Default::default()
+        };
+
+        let report = compare_schemas(&source, &target);
+
+        assert_eq!(report.items.len(), 1);
+        assert_eq!(report.items[0].object_type, "rls");
+        assert_eq!(report.items[0].generated_up_sql, "ALTER TABLE \"users\" ENABLE ROW LEVEL SECURITY;");
+        assert_eq!(report.items[0].generated_down_sql, "ALTER TABLE \"users\" DISABLE ROW LEVEL SECURITY;");
+        assert!(!report.items[0].dangerous);
+
+        // Disabling RLS is the reverse direction and is flagged
+        let report = compare_schemas(&target, &source);
+        assert!(report.items[0].dangerous);
+    }
+
+    #[test]
+    fn test_policy_added() {
+        let mut source_table = create_test_table(
+            "users",
+            vec![create_test_column("id", "integer", false)],
+        );
+        source_table.policies.push(create_test_policy("users_select", "(id = 1)"));
+
+        let source = SchemaModel {
+            tables: vec![source_table],
+            ..Default::default()
+        };
+        let target = SchemaModel {
+            tables: vec![create_test_table(
+                "users",
+                vec![create_test_column("id", "integer", false)],
+            )],
+            ..Default::default()
+        };
+
+        let report = compare_schemas(&source, &target);
+
+        assert_eq!(report.items.len(), 1);
+        assert_eq!(report.items[0].kind, DiffKind::Added);
+        assert_eq!(report.items[0].object_type, "policy");
+        assert_eq!(
+            report.items[0].generated_up_sql,
+            "CREATE POLICY \"users_select\" ON \"users\" FOR SELECT TO \"authenticated\" USING ((id = 1));"
+        );
+    }
+
+    #[test]
+    fn test_policy_modified() {
+        let mut source_table = create_test_table("users", vec![]);
+        source_table.policies.push(create_test_policy("users_select", "(id = 2)"));
+        let mut target_table = create_test_table("users", vec![]);
+        target_table.policies.push(create_test_policy("users_select", "(id = 1)"));
+
+        let source = SchemaModel {
+            tables: vec![source_table.clone()],
+            ..Default::default()
+        };
+        let target = SchemaModel {
+            tables: vec![target_table],
+            ..Default::default()
+        };
+
+        let report = compare_schemas(&source, &target);
+
+        assert_eq!(report.items.len(), 1);
+        assert_eq!(report.items[0].kind, DiffKind::Modified);
+        assert_eq!(
+            report.items[0].generated_up_sql,
+            "ALTER POLICY \"users_select\" ON \"users\" USING ((id = 2));"
+        );
+
+        // Changing the command cannot be done with ALTER POLICY
+        source_table.policies[0].command = "ALL".to_string();
+        let source = SchemaModel {
+            tables: vec![source_table],
+            ..Default::default()
+        };
+        let report = compare_schemas(&source, &target);
+        assert!(report.items[0].generated_up_sql.starts_with("DROP POLICY IF EXISTS"));
+    }
+
+    fn create_test_privilege(grantee: &str, grantable: bool) -> Privilege {
+        Privilege {
+            object_type: "table".to_string(),
+            object_name: "users".to_string(),
+            column_name: None,
+            arguments: None,
+            grantee: grantee.to_string(),
+            privilege_type: "SELECT".to_string(),
+            is_grantable: grantable,
+        }
+    }
+
+    #[test]
+    fn test_privileges_are_opt_in() {
+        let source = SchemaModel {
+            privileges: vec![create_test_privilege("app_user", false)],
+            ..Default::default()
+        };
+        let target = SchemaModel::new();
+
+        assert!(compare_schemas(&source, &target).items.is_empty());
+
+        let options = DiffOptions { include_privileges: true, ..Default::default() };
+        let report = compare_schemas_with_options(&source, &target, &options);
+
+        assert_eq!(report.items.len(), 1);
+        assert_eq!(report.items[0].kind, DiffKind::Added);
+        assert_eq!(report.items[0].object_type, "privilege");
+        assert_eq!(report.items[0].generated_up_sql, "GRANT SELECT ON TABLE \"users\" TO \"app_user\";");
+        assert_eq!(report.items[0].generated_down_sql, "REVOKE SELECT ON TABLE \"users\" FROM \"app_user\";");
+    }
+
+    #[test]
+    fn test_privilege_grant_option_and_revoke() {
+        let source = SchemaModel {
+            privileges: vec![create_test_privilege("app_user", true)],
+            ..Default::default()
+        };
+        let target = SchemaModel {
+            privileges: vec![
+                create_test_privilege("app_user", false),
+                create_test_privilege("PUBLIC", false),
+            ],
+            ..Default::default()
+        };
+
+        let options = DiffOptions { include_privileges: true, ..Default::default() };
+        let report = compare_schemas_with_options(&source, &target, &options);
+
+        assert_eq!(report.items.len(), 2);
+        assert_eq!(report.items[0].kind, DiffKind::Modified);
+        assert_eq!(
+            report.items[0].generated_up_sql,
+            "GRANT SELECT ON TABLE \"users\" TO \"app_user\" WITH GRANT OPTION;"
+        );
+        assert_eq!(report.items[1].kind, DiffKind::Removed);
+        assert_eq!(report.items[1].generated_up_sql, "REVOKE SELECT ON TABLE \"users\" FROM PUBLIC;");
+    }
+
+    #[test]
+    fn test_default_function_names() {
+        assert_eq!(default_function_names("now()"), vec!["now"]);
+        assert_eq!(default_function_names("pg_catalog.gen_random_uuid()"), vec!["gen_random_uuid"]);
+        assert_eq!(default_function_names("nextval('users_id_seq'::regclass)"), vec!["nextval"]);
+        assert_eq!(default_function_names("'f(x)'::text"), Vec::<String>::new());
+        assert_eq!(default_function_names("0.00::numeric(10,2)"), Vec::<String>::new());
+        assert_eq!(default_function_names("CURRENT_TIMESTAMP"), Vec::<String>::new());
+    }
+
+    #[test]
+    fn test_default_rewrites_table() {
+        let options = DiffOptions::default();
+        assert!(!options.default_rewrites_table("now()"));
+        assert!(!options.default_rewrites_table("'active'::text"));
+        assert!(options.default_rewrites_table("gen_random_uuid()"));
+        assert!(options.default_rewrites_table("clock_timestamp()"));
 
-    fn create_test_table(name: &str, columns: Vec<Column>) -> Table {
-        Table {
-            name: name.to_string(),
-            columns,
-            primary_key: None,
-            unique_constraints: Vec::new(),
-            indexes: Vec::new(),
-        }
+        let options = DiffOptions {
+            safe_default_functions: Some(vec!["gen_random_uuid()".to_string(), "now".to_string()]),
+            ..Default::default()
+        };
+        assert!(!options.default_rewrites_table("gen_random_uuid()"));
+
+        let options = DiffOptions { target_major_version: Some(10), ..Default::default() };
+        assert!(options.default_rewrites_table("'active'::text"));
     }
 
     #[test]
-    fn test_added_table() {
+    fn test_added_column_with_volatile_default() {
+        let mut column = create_test_column("id", "uuid", false);
+        column.default_value = Some("gen_random_uuid()".to_string());
         let source = SchemaModel {
-            tables: vec![create_test_table(
-                "users",
-                vec![create_test_column("id", "integer", false)],
-            )],
+            tables: vec![create_test_table("users", vec![column])],
+            ..Default::default()
+        };
+        let target = SchemaModel {
+            tables: vec![create_test_table("users", vec![])],
+            ..Default::default()
         };
-        let target = SchemaModel { tables: vec![] };
 
         let report = compare_schemas(&source, &target);
 
         assert_eq!(report.items.len(), 1);
-        assert_eq!(report.items[0].kind, DiffKind::Added);
-        assert_eq!(report.items[0].object_type, "table");
-        assert_eq!(report.items[0].object_name, "users");
+        assert!(report.items[0].rewrites_table);
         assert!(!report.items[0].dangerous);
     }
 
     #[test]
-    fn test_removed_table() {
-        let source = SchemaModel { tables: vec![] };
+    fn test_added_generated_column() {
+        let mut column = create_test_column("total", "numeric", true);
+        column.generation_expression = Some("(price * qty)".to_string());
+        let source = SchemaModel {
+            tables: vec![create_test_table("orders", vec![column])],
+            ..Default::default()
+        };
         let target = SchemaModel {
-            tables: vec![create_test_table(
-                "users",
-                vec![create_test_column("id", "integer", false)],
-            )],
+            tables: vec![create_test_table("orders", vec![])],
+            ..Default::default()
         };
 
         let report = compare_schemas(&source, &target);
 
-        assert_eq!(report.items.len(), 1);
-        assert_eq!(report.items[0].kind, DiffKind::Removed);
-        assert_eq!(report.items[0].object_type, "table");
-        assert_eq!(report.items[0].object_name, "users");
-        assert!(report.items[0].dangerous);
+        assert_eq!(
+            report.items[0].generated_up_sql,
+            "ALTER TABLE \"orders\" ADD COLUMN \"total\" numeric GENERATED ALWAYS AS ((price * qty)) STORED;"
+        );
+        assert!(report.items[0].rewrites_table);
+
+        let options = DiffOptions { target_major_version: Some(11), ..Default::default() };
+        let report = compare_schemas_with_options(&source, &target, &options);
+        assert_eq!(report.warnings.len(), 1);
+        assert!(report.warnings[0].contains("PostgreSQL 11 does not support generated columns"));
     }
 
     #[test]
-    fn test_added_column() {
+    fn test_generation_expression_changed() {
+        let mut source_col = create_test_column("total", "numeric", true);
+        source_col.generation_expression = Some("(price * qty * 2)".to_string());
+        let mut target_col = create_test_column("total", "numeric", true);
+        target_col.generation_expression = Some("(price * qty)".to_string());
+
         let source = SchemaModel {
-            tables: vec![create_test_table(
-                "users",
-                vec![
-                    create_test_column("id", "integer", false),
-                    create_test_column("email", "varchar(255)", false),
-                ],
-            )],
+            tables: vec![create_test_table("orders", vec![source_col.clone()])],
+            ..Default::default()
         };
         let target = SchemaModel {
-            tables: vec![create_test_table(
-                "users",
-                vec![create_test_column("id", "integer", false)],
-            )],
+            tables: vec![create_test_table("orders", vec![target_col.clone()])],
+            ..Default::default()
         };
 
         let report = compare_schemas(&source, &target);
 
         assert_eq!(report.items.len(), 1);
-        assert_eq!(report.items[0].kind, DiffKind::Added);
-        assert_eq!(report.items[0].object_type, "column");
-        assert_eq!(report.items[0].object_name, "users.email");
+        assert!(report.items[0].generated_up_sql.starts_with(
+            "ALTER TABLE \"orders\" DROP COLUMN IF EXISTS \"total\";\nALTER TABLE \"orders\" ADD COLUMN"
+        ));
+        assert!(!report.items[0].dangerous);
+
+        // Generated -> plain keeps the data
+        source_col.generation_expression = None;
+        let source = SchemaModel {
+            tables: vec![create_test_table("orders", vec![source_col])],
+            ..Default::default()
+        };
+        let report = compare_schemas(&source, &target);
+        assert_eq!(
+            report.items[0].generated_up_sql,
+            "ALTER TABLE \"orders\" ALTER COLUMN \"total\" DROP EXPRESSION;"
+        );
+
+        // Plain -> generated discards the stored values
+        let report = compare_schemas(&target, &source);
+        assert!(report.items[0].dangerous);
+    }
+
+    fn create_test_function(body: &str) -> Function {
+        Function {
+            name: "add_one".to_string(),
+            arguments: "x integer".to_string(),
+            return_type: Some("integer".to_string()),
+            language: "plpgsql".to_string(),
+            is_procedure: false,
+            definition: format!(
+                "CREATE OR REPLACE FUNCTION public.add_one(x integer)\n RETURNS integer\n LANGUAGE plpgsql\nAS $function$\n{}\n$function$\n",
+                body
+            ),
+        }
     }
 
     #[test]
-    fn test_removed_column() {
+    fn test_function_formatting_only_change() {
         let source = SchemaModel {
-            tables: vec![create_test_table(
-                "users",
-                vec![create_test_column("id", "integer", false)],
-            )],
+            functions: vec![create_test_function("BEGIN\n  -- add one\n  RETURN x + 1;\nEND")],
+            ..Default::default()
         };
         let target = SchemaModel {
-            tables: vec![create_test_table(
-                "users",
-                vec![
-                    create_test_column("id", "integer", false),
-                    create_test_column("email", "varchar(255)", false),
-                ],
-            )],
+            functions: vec![create_test_function("BEGIN RETURN x + 1; END")],
+            ..Default::default()
         };
 
+        assert!(compare_schemas(&source, &target).items.is_empty());
+
+        let options = DiffOptions { strict_body_comparison: true, ..Default::default() };
+        let report = compare_schemas_with_options(&source, &target, &options);
+        assert_eq!(report.items.len(), 1);
+        assert_eq!(report.items[0].object_type, "function");
+        assert!(report.items[0].generated_up_sql.starts_with("CREATE OR REPLACE FUNCTION"));
+        assert!(report.items[0].generated_up_sql.ends_with("$function$;"));
+    }
+
+    #[test]
+    fn test_function_return_type_change_recreates() {
+        let source_fn = create_test_function("BEGIN RETURN x + 1; END");
+        let mut target_fn = source_fn.clone();
+        target_fn.return_type = Some("bigint".to_string());
+        target_fn.definition = target_fn.definition.replace("RETURNS integer", "RETURNS bigint");
+
+        let source = SchemaModel { functions: vec![source_fn], ..Default::default() };
+        let target = SchemaModel { functions: vec![target_fn], ..Default::default() };
+
+        let report = compare_schemas(&source, &target);
+
+        assert!(report.items[0].generated_up_sql.starts_with("DROP FUNCTION IF EXISTS \"add_one\"(x integer);"));
+    }
+
+    #[test]
+    fn test_trigger_added() {
+        let mut source_table = create_test_table("users", vec![]);
+        source_table.triggers.push(Trigger {
+            name: "users_touch".to_string(),
+            definition: "CREATE TRIGGER users_touch BEFORE UPDATE ON public.users FOR EACH ROW EXECUTE FUNCTION touch()".to_string(),
+        });
+        let source = SchemaModel { tables: vec![source_table], ..Default::default() };
+        let target = SchemaModel { tables: vec![create_test_table("users", vec![])], ..Default::default() };
+
         let report = compare_schemas(&source, &target);
 
         assert_eq!(report.items.len(), 1);
-        assert_eq!(report.items[0].kind, DiffKind::Removed);
-        assert_eq!(report.items[0].object_type, "column");
-        assert!(report.items[0].dangerous);
+        assert_eq!(report.items[0].object_type, "trigger");
+        assert_eq!(report.items[0].generated_down_sql, "DROP TRIGGER IF EXISTS \"users_touch\" ON \"users\";");
     }
 
     #[test]
-    fn test_modified_column_type() {
-        let source = SchemaModel {
-            tables: vec![create_test_table(
-                "users",
-                vec![create_test_column("name", "text", false)],
-            )],
+    fn test_partial_index_differs_from_full_index() {
+        let full_index = Index {
+            name: "idx_users_email".to_string(),
+            columns: vec!["email".to_string()],
+            is_unique: true,
+            index_type: "btree".to_string(),
+            predicate: None,
+            column_orders: Vec::new(),
+            include_columns: Vec::new(),
+            column_opclasses: Vec::new(),
+            storage_parameters: Vec::new(),
         };
-        let target = SchemaModel {
-            tables: vec![create_test_table(
-                "users",
-                vec![create_test_column("name", "varchar(100)", false)],
-            )],
+        let partial_index = Index {
+            predicate: Some("(deleted_at IS NULL)".to_string()),
+            ..full_index.clone()
         };
 
+        let mut source_table = create_test_table("users", vec![]);
+        source_table.indexes.push(partial_index.clone());
+        let mut target_table = create_test_table("users", vec![]);
+        target_table.indexes.push(full_index);
+
+        let source = SchemaModel { tables: vec![source_table], ..Default::default() };
+        let target = SchemaModel { tables: vec![target_table], ..Default::default() };
+
         let report = compare_schemas(&source, &target);
 
         assert_eq!(report.items.len(), 1);
         assert_eq!(report.items[0].kind, DiffKind::Modified);
-        assert_eq!(report.items[0].object_type, "column");
-        assert!(report.items[0].dangerous); // Type change is dangerous
+        assert_eq!(
+            report.items[0].generated_up_sql,
+            "DROP INDEX IF EXISTS \"idx_users_email\";\n\
+             CREATE UNIQUE INDEX \"idx_users_email\" ON \"users\" (\"email\") WHERE (deleted_at IS NULL);"
+        );
+
+        // Differently named partial index on the same columns is not a match either
+        let mut renamed = partial_index;
+        renamed.name = "idx_users_email_active".to_string();
+        let mut source_table = create_test_table("users", vec![]);
+        source_table.indexes.push(renamed);
+        let source = SchemaModel { tables: vec![source_table], ..Default::default() };
+
+        let report = compare_schemas(&source, &target);
+
+        assert_eq!(report.items.len(), 2);
     }
 
     #[test]
-    fn test_modified_column_nullability() {
+    fn test_ignore_config() {
+        let mut users = create_test_table("users", vec![create_test_column("id", "integer", false)]);
+        let target = SchemaModel { tables: vec![users.clone()], ..Default::default() };
+        users.columns.push(create_test_column("updated_at", "timestamptz", true));
+        users.rls_enabled = true;
         let source = SchemaModel {
-            tables: vec![create_test_table(
-                "users",
-                vec![create_test_column("name", "text", false)],
-            )],
+            tables: vec![
+                users,
+                create_test_table("spatial_ref_sys", vec![create_test_column("srid", "integer", false)]),
+                create_test_table("_prisma_migrations", vec![create_test_column("id", "text", false)]),
+            ],
+            ..Default::default()
         };
+        assert_eq!(compare_schemas(&source, &target).items.len(), 4);
+
+        let options = DiffOptions {
+            ignore: IgnoreConfig {
+                tables: vec!["spatial_ref_sys".to_string(), "_prisma_*".to_string()],
+                columns: vec!["*.updated_at".to_string()],
+                object_types: vec!["rls".to_string()],
+            },
+            ..Default::default()
+        };
+        assert!(compare_schemas_with_options(&source, &target, &options).items.is_empty());
+    }
+
+    #[test]
+    fn test_report_types() {
         let target = SchemaModel {
-            tables: vec![create_test_table(
-                "users",
-                vec![create_test_column("name", "text", true)],
-            )],
+            tables: vec![create_test_table("users", vec![create_test_column("id", "integer", false)])],
+            ..Default::default()
+        };
+        let mut source = target.clone();
+        source.tables[0].columns.push(create_test_column("email", "text", true));
+        source.tables.push(create_test_table("orders", vec![create_test_column("id", "integer", false)]));
+
+        let options = DiffOptions { include_types: vec!["column".to_string()], ..Default::default() };
+        let report = compare_schemas_with_options(&source, &target, &options);
+        assert_eq!(report.items.len(), 1);
+        assert_eq!(report.items[0].object_name, "users.email");
+
+        let options = DiffOptions {
+            ignore: IgnoreConfig { object_types: vec!["column".to_string()], ..Default::default() },
+            ..Default::default()
+        };
+        let report = compare_schemas_with_options(&source, &target, &options);
+        assert_eq!(report.items.len(), 1);
+        assert_eq!(report.items[0].object_type, "table");
+
+        assert_eq!(object_type_name("table").unwrap(), "table");
+        assert_eq!(object_type_name("tables").unwrap(), "table");
+        assert_eq!(object_type_name("columns").unwrap(), "column");
+        assert_eq!(object_type_name("indexes").unwrap(), "index");
+        assert_eq!(object_type_name("indices").unwrap(), "index");
+        assert_eq!(object_type_name("enums").unwrap(), "enum");
+        assert_eq!(object_type_name("constraints").unwrap(), "constraint");
+        assert_eq!(object_type_name("policies").unwrap(), "policy");
+        assert_eq!(object_type_name("rls").unwrap(), "rls");
+        assert_eq!(object_type_name("storage_parameters").unwrap(), "storage_parameters");
+        assert!(object_type_name("views").unwrap_err().starts_with("Unknown object type 'views'"));
+        assert!(object_type_name("Tables").is_err());
+    }
+
+    #[test]
+    fn test_rename_matching_indexes() {
+        let index = |name: &str, column: &str| Index {
+            name: name.to_string(),
+            columns: vec![column.to_string()],
+            is_unique: false,
+            index_type: "btree".to_string(),
+            predicate: None,
+            column_orders: Vec::new(),
+            include_columns: Vec::new(),
+            column_opclasses: Vec::new(),
+            storage_parameters: Vec::new(),
+        };
+
+        let mut source_table = create_test_table("users", vec![]);
+        source_table.indexes.push(index("users_email_idx", "email"));
+        source_table.indexes.push(index("users_email_idx1", "email"));
+        let mut target_table = create_test_table("users", vec![]);
+        target_table.indexes.push(index("users_email_idx2", "email"));
+        let source = SchemaModel { tables: vec![source_table], ..Default::default() };
+        let target = SchemaModel { tables: vec![target_table], ..Default::default() };
+
+        // By default a matching definition is enough
+        assert!(compare_schemas(&source, &target).items.is_empty());
+
+        let options = DiffOptions { rename_matching_indexes: true, ..Default::default() };
+        let report = compare_schemas_with_options(&source, &target, &options);
+
+        assert_eq!(report.items.len(), 1);
+        assert_eq!(report.items[0].kind, DiffKind::Renamed);
+        assert!(!report.items[0].dangerous);
+        assert_eq!(report.items[0].object_name, "users.users_email_idx");
+        assert_eq!(
+            report.items[0].generated_up_sql,
+            "ALTER INDEX \"users_email_idx2\" RENAME TO \"users_email_idx\";"
+        );
+        assert_eq!(
+            report.items[0].generated_down_sql,
+            "ALTER INDEX \"users_email_idx\" RENAME TO \"users_email_idx2\";"
+        );
+    }
+
+    #[test]
+    fn test_index_ordering_and_include() {
+        let plain = Index {
+            name: "idx_events_recent".to_string(),
+            columns: vec!["account_id".to_string(), "created_at".to_string()],
+            is_unique: false,
+            index_type: "btree".to_string(),
+            predicate: None,
+            column_orders: Vec::new(),
+            include_columns: Vec::new(),
+            column_opclasses: Vec::new(),
+            storage_parameters: Vec::new(),
+        };
+        let covering = Index {
+            column_orders: vec![
+                IndexColumnOrder::default(),
+                IndexColumnOrder { descending: true, nulls_first: false },
+            ],
+            include_columns: vec!["payload".to_string()],
+            ..plain.clone()
+        };
+
+        // Explicit default ordering is the same as no ordering
+        let explicit_default = Index {
+            column_orders: vec![IndexColumnOrder::default(); 2],
+            ..plain.clone()
         };
+        assert!(plain.same_definition(&explicit_default));
+
+        let mut source_table = create_test_table("events", vec![]);
+        source_table.indexes.push(covering);
+        let mut target_table = create_test_table("events", vec![]);
+        target_table.indexes.push(plain);
+
+        let source = SchemaModel { tables: vec![source_table], ..Default::default() };
+        let target = SchemaModel { tables: vec![target_table], ..Default::default() };
 
         let report = compare_schemas(&source, &target);
 
         assert_eq!(report.items.len(), 1);
         assert_eq!(report.items[0].kind, DiffKind::Modified);
-        assert!(!report.items[0].dangerous); // Nullability change is not dangerous
+        assert!(report.items[0].generated_up_sql.ends_with(
+            "CREATE INDEX \"idx_events_recent\" ON \"events\" (\"account_id\", \"created_at\" DESC NULLS LAST) INCLUDE (\"payload\");"
+        ));
+
+        // PostgreSQL 10 has no covering indexes
+        let options = DiffOptions { target_major_version: Some(10), ..Default::default() };
+        let report = compare_schemas_with_options(&source, &target, &options);
+        assert!(report.items[0].generated_up_sql.ends_with(
+            "CREATE INDEX \"idx_events_recent\" ON \"events\" (\"account_id\", \"created_at\" DESC NULLS LAST);"
+        ));
+        assert_eq!(report.target_major_version, Some(10));
+        assert!(report.warnings[0].contains("without \"payload\""));
     }
 
     #[test]
-    fn test_no_diff() {
-        let schema = SchemaModel {
-            tables: vec![create_test_table(
-                "users",
-                vec![create_test_column("id", "integer", false)],
-            )],
+    fn test_index_opclass_and_storage() {
+        let btree = Index {
+            name: "idx_users_name".to_string(),
+            columns: vec!["name".to_string()],
+            is_unique: false,
+            index_type: "btree".to_string(),
+            predicate: None,
+            column_orders: Vec::new(),
+            include_columns: Vec::new(),
+            column_opclasses: Vec::new(),
+            storage_parameters: Vec::new(),
+        };
+        let trigram = Index {
+            index_type: "gin".to_string(),
+            column_opclasses: vec![Some("gin_trgm_ops".to_string())],
+            storage_parameters: vec!["fastupdate=off".to_string()],
+            ..btree.clone()
         };
 
-        let report = compare_schemas(&schema, &schema);
+        let mut source_table = create_test_table("users", vec![]);
+        source_table.indexes.push(trigram);
+        let mut target_table = create_test_table("users", vec![]);
+        target_table.indexes.push(btree);
 
-        assert!(report.items.is_empty());
+        let source = SchemaModel { tables: vec![source_table], ..Default::default() };
+        let target = SchemaModel { tables: vec![target_table], ..Default::default() };
+
+        let report = compare_schemas(&source, &target);
+
+        assert_eq!(report.items.len(), 1);
+        assert!(report.items[0].generated_up_sql.ends_with(
+            "CREATE INDEX \"idx_users_name\" ON \"users\" USING gin (\"name\" gin_trgm_ops) WITH (fastupdate=off);"
+        ));
+        assert!(report.items[0].generated_down_sql.ends_with(
+            "CREATE INDEX \"idx_users_name\" ON \"users\" (\"name\");"
+        ));
+    }
+
+    fn create_test_server(options: Vec<&str>) -> ForeignServer {
+        ForeignServer {
+            name: "warehouse".to_string(),
+            wrapper: "postgres_fdw".to_string(),
+            server_type: None,
+            version: None,
+            options: options.into_iter().map(String::from).collect(),
+        }
     }
 
     #[test]
-    fn test_primary_key_added() {
-        let mut source_table = create_test_table(
-            "users",
-            vec![create_test_column("id", "integer", false)],
+    fn test_foreign_data_is_opt_in_and_ordered() {
+        let source = SchemaModel {
+            foreign_servers: vec![create_test_server(vec!["host=wh.internal", "dbname=dw"])],
+            user_mappings: vec![UserMapping {
+                server: "warehouse".to_string(),
+                user: "PUBLIC".to_string(),
+                options: vec!["user=reader".to_string()],
+            }],
+            foreign_tables: vec![ForeignTable {
+                name: "orders".to_string(),
+                server: "warehouse".to_string(),
+                columns: vec![create_test_column("id", "integer", false)],
+                options: vec!["table_name=orders".to_string()],
+            }],
+            ..Default::default()
+        };
+        let target = SchemaModel::new();
+
+        assert!(compare_schemas(&source, &target).items.is_empty());
+
+        let options = DiffOptions { include_foreign_data: true, ..Default::default() };
+        let report = compare_schemas_with_options(&source, &target, &options);
+
+        let types: Vec<_> = report.items.iter().map(|i| i.object_type.as_str()).collect();
+        assert_eq!(types, vec!["foreign_server", "user_mapping", "foreign_table"]);
+        assert_eq!(
+            report.items[0].generated_up_sql,
+            "CREATE SERVER \"warehouse\" FOREIGN DATA WRAPPER \"postgres_fdw\" OPTIONS (host 'wh.internal', dbname 'dw');"
         );
-        source_table.primary_key = Some(Constraint {
-            name: "users_pkey".to_string(),
-            constraint_type: "PRIMARY KEY".to_string(),
-            columns: vec!["id".to_string()],
-        });
+        assert_eq!(
+            report.items[1].generated_up_sql,
+            "CREATE USER MAPPING FOR PUBLIC SERVER \"warehouse\" OPTIONS (user 'reader');"
+        );
+        assert_eq!(
+            report.items[2].generated_up_sql,
+            "CREATE FOREIGN TABLE \"orders\" (\n    \"id\" integer NOT NULL\n) SERVER \"warehouse\" OPTIONS (table_name 'orders');"
+        );
+
+        // Dropping everything reverses the order
+        let report = compare_schemas_with_options(&target, &source, &options);
+        let types: Vec<_> = report.items.iter().map(|i| i.object_type.as_str()).collect();
+        assert_eq!(types, vec!["foreign_table", "user_mapping", "foreign_server"]);
+        assert!(report.items.iter().all(|i| i.kind == DiffKind::Removed && !i.dangerous));
+    }
 
+    #[test]
+    fn test_foreign_server_options_change() {
         let source = SchemaModel {
-            tables: vec![source_table],
+            foreign_servers: vec![create_test_server(vec!["host=new.internal", "port=5433"])],
+            ..Default::default()
         };
         let target = SchemaModel {
-            tables: vec![create_test_table(
-                "users",
-                vec![create_test_column("id", "integer", false)],
-            )],
+            foreign_servers: vec![create_test_server(vec!["host=old.internal", "dbname=dw"])],
+            ..Default::default()
         };
 
-        let report = compare_schemas(&source, &target);
+        let options = DiffOptions { include_foreign_data: true, ..Default::default() };
+        let report = compare_schemas_with_options(&source, &target, &options);
 
         assert_eq!(report.items.len(), 1);
-        assert_eq!(report.items[0].kind, DiffKind::Added);
-        assert_eq!(report.items[0].object_type, "constraint");
+        assert_eq!(report.items[0].kind, DiffKind::Modified);
+        assert_eq!(
+            report.items[0].generated_up_sql,
+            "ALTER SERVER \"warehouse\" OPTIONS (SET host 'new.internal', ADD port '5433', DROP dbname);"
+        );
+        assert_eq!(
+            report.items[0].generated_down_sql,
+            "ALTER SERVER \"warehouse\" OPTIONS (SET host 'old.internal', ADD dbname 'dw', DROP port);"
+        );
     }
 
-    #[test]
-    fn test_index_added() {
-        let mut source_table = create_test_table(
-            "users",
-            vec![create_test_column("email", "varchar(255)", false)],
-        );
-        source_table.indexes.push(Index {
-            name: "idx_users_email".to_string(),
-            columns: vec!["email".to_string()],
-            is_unique: false,
-            index_type: "btree".to_string(),
-        });
+    fn create_test_publication(tables: Vec<&str>, publish: Vec<&str>) -> Publication {
+        Publication {
+            name: "app_pub".to_string(),
+            all_tables: false,
+            tables: tables.into_iter().map(String::from).collect(),
+            publish: publish.into_iter().map(String::from).collect(),
+            publish_via_partition_root: false,
+        }
+    }
 
+    #[test]
+    fn test_publication_changes() {
         let source = SchemaModel {
-            tables: vec![source_table],
+            publications: vec![create_test_publication(vec!["orders", "users"], vec!["insert", "update"])],
+            ..Default::default()
         };
         let target = SchemaModel {
-            tables: vec![create_test_table(
-                "users",
-                vec![create_test_column("email", "varchar(255)", false)],
+            publications: vec![create_test_publication(
+                vec!["audit", "users"],
+                vec!["insert", "update", "delete"],
             )],
+            ..Default::default()
         };
 
         let report = compare_schemas(&source, &target);
 
         assert_eq!(report.items.len(), 1);
-        assert_eq!(report.items[0].kind, DiffKind::Added);
-        assert_eq!(report.items[0].object_type, "index");
+        assert_eq!(report.items[0].object_type, "publication");
+        assert_eq!(
+            report.items[0].generated_up_sql,
+            "ALTER PUBLICATION \"app_pub\" ADD TABLE \"orders\";\n\
+             ALTER PUBLICATION \"app_pub\" DROP TABLE \"audit\";\n\
+             ALTER PUBLICATION \"app_pub\" SET (publish = 'insert, update', publish_via_partition_root = false);"
+        );
+
+        let report = compare_schemas(&source, &SchemaModel::new());
+        assert_eq!(
+            report.items[0].generated_up_sql,
+            "CREATE PUBLICATION \"app_pub\" FOR TABLE \"orders\", \"users\" \
+             WITH (publish = 'insert, update', publish_via_partition_root = false);"
+        );
+
+        let report = compare_schemas(&SchemaModel::new(), &target);
+        assert_eq!(report.items[0].kind, DiffKind::Removed);
+        assert!(report.items[0].dangerous);
+    }
+
+    #[test]
+    fn test_subscriptions_are_opt_in() {
+        let source = SchemaModel {
+            subscriptions: vec![Subscription {
+                name: "app_sub".to_string(),
+                connection: "host=pub.internal dbname=app".to_string(),
+                publications: vec!["app_pub".to_string()],
+                enabled: true,
+                slot_name: Some("app_sub".to_string()),
+            }],
+            ..Default::default()
+        };
+        let target = SchemaModel::new();
+
+        assert!(compare_schemas(&source, &target).items.is_empty());
+
+        let options = DiffOptions { include_subscriptions: true, ..Default::default() };
+        let report = compare_schemas_with_options(&source, &target, &options);
+
+        assert_eq!(report.items.len(), 1);
+        assert_eq!(
+            report.items[0].generated_up_sql,
+            "CREATE SUBSCRIPTION \"app_sub\" CONNECTION 'host=pub.internal dbname=app' \
+             PUBLICATION \"app_pub\" WITH (connect = false, slot_name = 'app_sub');"
+        );
+        assert!(report.items[0].generated_down_sql.contains("SET (slot_name = NONE)"));
+
+        // Subscriptions the target could not read are not dropped
+        let unreadable = SchemaModel { unreadable_object_types: vec!["subscription".to_string()], ..Default::default() };
+        let report = compare_schemas_with_options(&target, &unreadable, &options);
+        assert!(report.items.is_empty());
+        assert_eq!(report.warnings, ["Subscriptions were not compared: reading pg_subscription on the target needs superuser"]);
     }
 }
@@ -0,0 +1,264 @@
+//! Disk space estimates for statements that copy data.
+//!
+//! A table rewrite writes a new copy of the table and its indexes while the
+//! old files stay until the transaction commits; an index build writes the
+//! index and may spill its sort to temporary files. Both are estimated from
+//! the target's current sizes, so an operator can check the free space on
+//! the volume before applying.
+
+use serde::{Deserialize, Serialize};
+use crate::db::sizes::{RelationSizes, TableSize};
+use crate::model::schema::{SchemaModel, Table};
+use super::diff::{DiffKind, DiffReport};
+
+/// Header of a B-tree index tuple.
+const INDEX_TUPLE_HEADER: i64 = 8;
+
+/// Line pointer of each tuple on an index page.
+const LINE_POINTER: i64 = 4;
+
+/// Default leaf fill factor of B-tree indexes, in percent.
+const INDEX_FILL_FACTOR: i64 = 90;
+
+/// Width assumed for index keys that are not plain columns.
+const UNKNOWN_KEY_WIDTH: i64 = 32;
+
+/// Estimated disk space a statement needs.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq)]
+pub struct DiskEstimate {
+    /// Space used while the migration runs and released once it commits.
+    pub temporary_bytes: i64,
+    /// Space the database grows by once the migration is applied.
+    pub final_bytes: i64,
+}
+
+impl DiskEstimate {
+    /// Disk space to have free before applying.
+    pub fn peak_bytes(&self) -> i64 {
+        self.temporary_bytes + self.final_bytes
+    }
+}
+
+impl std::ops::Add for DiskEstimate {
+    type Output = DiskEstimate;
+
+    fn add(self, other: DiskEstimate) -> DiskEstimate {
+        DiskEstimate {
+            temporary_bytes: self.temporary_bytes + other.temporary_bytes,
+            final_bytes: self.final_bytes + other.final_bytes,
+        }
+    }
+}
+
+/// Attach disk estimates to the items of `report` that rewrite tables or
+/// build indexes, using the current `sizes` of the target.
+///
+/// `source` is the desired schema, where new index definitions are looked up.
+/// Items on tables missing from `sizes` (e.g. tables the migration creates)
+/// get no estimate.
+pub fn estimate_disk_space(report: &mut DiffReport, source: &SchemaModel, sizes: &RelationSizes) {
+    for item in &mut report.items {
+        // Table-level items are named after the table alone
+        let (table_name, object) = item.object_name.split_once('.').unwrap_or((&item.object_name, ""));
+        let Some(size) = sizes.tables.get(table_name) else {
+            continue;
+        };
+
+        item.disk_estimate = if item.rewrites_table {
+            // Old and new copies both exist until commit; the size barely changes
+            Some(DiskEstimate {
+                temporary_bytes: size.table_bytes + size.indexes_bytes,
+                final_bytes: 0,
+            })
+        } else if matches!(item.kind, DiffKind::Removed | DiffKind::Renamed) {
+            None
+        } else if let Some(columns) = index_columns(source.find_table(table_name), &item.object_type, object) {
+            let index_bytes = estimate_index_bytes(size, &columns);
+            // A recreated index replaces the old file, which is kept until commit
+            let old_bytes = match item.kind {
+                DiffKind::Modified => size.index_bytes.get(object).copied().unwrap_or(0),
+                _ => 0,
+            };
+            Some(DiskEstimate {
+                temporary_bytes: index_bytes + old_bytes,
+                final_bytes: (index_bytes - old_bytes).max(0),
+            })
+        } else {
+            None
+        };
+    }
+}
+
+/// Key and included columns of the index an `index` or `constraint` item builds.
+fn index_columns(table: Option<&Table>, object_type: &str, name: &str) -> Option<Vec<String>> {
+    let table = table?;
+    match object_type {
+        "index" => table.find_index(name).map(|i| {
+            i.columns.iter().chain(&i.include_columns).cloned().collect()
+        }),
+        // Primary keys and unique constraints are backed by an index
+        "constraint" => table.find_constraint(name).map(|c| c.columns.clone()),
+        _ => None,
+    }
+}
+
+/// Estimate the size of a B-tree index on `columns` of a table.
+///
+/// Without a row estimate the table is assumed never analyzed and its own
+/// size is used as an upper bound.
+pub fn estimate_index_bytes(size: &TableSize, columns: &[String]) -> i64 {
+    let Some(rows) = size.row_estimate else {
+        return size.table_bytes;
+    };
+
+    let key_width: i64 = columns.iter()
+        .map(|c| size.column_widths.get(c).copied().unwrap_or(UNKNOWN_KEY_WIDTH))
+        .sum();
+    // Tuples are padded to 8 bytes
+    let tuple_bytes = (INDEX_TUPLE_HEADER + key_width + 7) / 8 * 8 + LINE_POINTER;
+
+    (rows * tuple_bytes as f64) as i64 * 100 / INDEX_FILL_FACTOR
+}
+
+/// Format a byte count for plan output, e.g. `1.5 GB`.
+pub fn format_bytes(bytes: i64) -> String {
+    const UNITS: &[&str] = &["bytes", "kB", "MB", "GB", "TB"];
+
+    let mut value = bytes as f64;
+    let mut unit = 0;
+    while value.abs() >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} {}", bytes, UNITS[0])
+    } else {
+        format!("{:.1} {}", value, UNITS[unit])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+    use crate::diff::diff::{compare_schemas, DiffItem};
+    use crate::model::schema::{Column, Index};
+
+    fn create_test_sizes() -> RelationSizes {
+        RelationSizes {
+            tables: HashMap::from([("orders".to_string(), TableSize {
+                table_bytes: 800_000_000,
+                indexes_bytes: 200_000_000,
+                index_bytes: HashMap::from([("orders_customer_idx".to_string(), 150_000_000)]),
+                row_estimate: Some(10_000_000.0),
+                column_widths: HashMap::from([
+                    ("id".to_string(), 8),
+                    ("customer_id".to_string(), 4),
+                ]),
+            })]),
+        }
+    }
+
+    fn create_test_index(name: &str, columns: &[&str]) -> Index {
+        Index {
+            name: name.to_string(),
+            columns: columns.iter().map(|c| c.to_string()).collect(),
+            is_unique: false,
+            index_type: "btree".to_string(),
+            predicate: None,
+            column_orders: Vec::new(),
+            include_columns: Vec::new(),
+            column_opclasses: Vec::new(),
+            storage_parameters: Vec::new(),
+        }
+    }
+
+    fn create_test_table(indexes: Vec<Index>) -> Table {
+        let column = |name: &str, ordinal_position| Column {
+            name: name.to_string(),
+            data_type: "integer".to_string(),
+            is_nullable: false,
+            default_value: None,
+            ordinal_position,
+            generation_expression: None,
+            comment: None,
+            inherited: false,
+            storage: None,
+            statistics_target: None,
+        };
+        Table {
+            name: "orders".to_string(),
+            columns: vec![column("id", 1), column("customer_id", 2)],
+            primary_key: None,
+            unique_constraints: Vec::new(),
+            indexes,
+            rls_enabled: false,
+            rls_forced: false,
+            policies: Vec::new(),
+            triggers: Vec::new(),
+            foreign_keys: Vec::new(),
+            comment: None,
+            inherits: Vec::new(),
+            unlogged: false,
+            storage_parameters: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_index_estimates() {
+        let source = SchemaModel {
+            tables: vec![create_test_table(vec![
+                create_test_index("orders_customer_idx", &["customer_id", "id"]),
+                create_test_index("orders_id_idx", &["id"]),
+            ])],
+            ..Default::default()
+        };
+        let target = SchemaModel {
+            tables: vec![create_test_table(vec![create_test_index("orders_customer_idx", &["customer_id"])])],
+            ..Default::default()
+        };
+
+        let mut report = compare_schemas(&source, &target);
+        estimate_disk_space(&mut report, &source, &create_test_sizes());
+
+        let estimate = |name: &str| report.items.iter()
+            .find(|i| i.object_name == name)
+            .and_then(|i| i.disk_estimate)
+            .unwrap();
+
+        // 10M rows of 16 + 4 bytes at 90% fill
+        let created = estimate("orders.orders_id_idx");
+        assert_eq!(created, DiskEstimate { temporary_bytes: 222_222_222, final_bytes: 222_222_222 });
+
+        // 24 + 4 bytes per row; the old 150 MB file stays until commit
+        let recreated = estimate("orders.orders_customer_idx");
+        assert_eq!(recreated.temporary_bytes, 311_111_111 + 150_000_000);
+        assert_eq!(recreated.final_bytes, 311_111_111 - 150_000_000);
+    }
+
+    #[test]
+    fn test_rewrite_estimate() {
+        let source = SchemaModel::new();
+        let mut report = DiffReport::new();
+        let mut item = DiffItem::new(DiffKind::Added, "column", "orders.total", "", "", "", false);
+        item.rewrites_table = true;
+        report.items.push(item);
+        report.items.push(DiffItem::new(DiffKind::Added, "column", "invoices.total", "", "", "", false));
+
+        estimate_disk_space(&mut report, &source, &create_test_sizes());
+
+        assert_eq!(report.items[0].disk_estimate, Some(DiskEstimate {
+            temporary_bytes: 1_000_000_000,
+            final_bytes: 0,
+        }));
+        assert_eq!(report.items[1].disk_estimate, None);
+        assert_eq!(report.disk_estimate().unwrap().peak_bytes(), 1_000_000_000);
+    }
+
+    #[test]
+    fn test_format_bytes() {
+        assert_eq!(format_bytes(512), "512 bytes");
+        assert_eq!(format_bytes(1536), "1.5 kB");
+        assert_eq!(format_bytes(1_000_000_000), "953.7 MB");
+    }
+}
@@ -0,0 +1,147 @@
+//! Tables, columns and object types left out of a comparison.
+//!
+//! Extension and framework tables such as `spatial_ref_sys` or
+//! `_prisma_migrations` are managed outside the migrations and would show up
+//! in every diff. Ignored tables and columns are removed from copies of both
+//! schemas before comparing; ignored object types are removed from the report.
+//!
+//! A project can keep its rules in [`IGNORE_CONFIG_FILE`] in the migrations
+//! directory, next to the migrations they apply to.
+
+use std::fs;
+use std::path::Path;
+use serde::{Deserialize, Serialize};
+use crate::model::schema::SchemaModel;
+
+/// File in a base path holding its [`IgnoreConfig`].
+pub const IGNORE_CONFIG_FILE: &str = "pgshift.ignore.json";
+
+/// What a comparison leaves out. Names may use `*` (any run of characters)
+/// and `?` (one character).
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct IgnoreConfig {
+    /// Table names, e.g. `spatial_ref_sys` or `_prisma_*`.
+    #[serde(default)]
+    pub tables: Vec<String>,
+    /// Columns as `table.column`, e.g. `*.updated_at`.
+    #[serde(default)]
+    pub columns: Vec<String>,
+    /// Kinds of diff items, e.g. `privilege` or `trigger`.
+    #[serde(default)]
+    pub object_types: Vec<String>,
+}
+
+impl IgnoreConfig {
+    /// Load the config of `base_path`; empty when it has none.
+    pub fn load(base_path: &str) -> Result<Self, String> {
+        let path = Path::new(base_path).join(IGNORE_CONFIG_FILE);
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let content = fs::read_to_string(&path)
+            .map_err(|e| format!("Failed to read {}: {}", IGNORE_CONFIG_FILE, e))?;
+        serde_json::from_str(&content)
+            .map_err(|e| format!("Failed to parse {}: {}", IGNORE_CONFIG_FILE, e))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tables.is_empty() && self.columns.is_empty() && self.object_types.is_empty()
+    }
+
+    /// Add the rules of `other` to these.
+    pub fn merge(&mut self, other: IgnoreConfig) {
+        self.tables.extend(other.tables);
+        self.columns.extend(other.columns);
+        self.object_types.extend(other.object_types);
+    }
+
+    pub fn ignores_table(&self, table_name: &str) -> bool {
+        self.tables.iter().any(|p| glob_match(p, table_name))
+    }
+
+    pub fn ignores_column(&self, table_name: &str, column_name: &str) -> bool {
+        self.columns.iter().any(|p| match p.split_once('.') {
+            Some((table, column)) => glob_match(table, table_name) && glob_match(column, column_name),
+            None => false,
+        })
+    }
+
+    pub fn ignores_object_type(&self, object_type: &str) -> bool {
+        self.object_types.iter().any(|t| t == object_type)
+    }
+
+    /// Copy of `schema` without the ignored tables and columns.
+    ///
+    /// Grants and publication entries of ignored tables go with them; foreign
+    /// keys of other tables that reference an ignored table are kept.
+    pub fn apply(&self, schema: &SchemaModel) -> SchemaModel {
+        let mut filtered = schema.clone();
+
+        filtered.tables.retain(|t| !self.ignores_table(&t.name));
+        for table in &mut filtered.tables {
+            let name = table.name.clone();
+            table.columns.retain(|c| !self.ignores_column(&name, &c.name));
+        }
+        filtered.foreign_tables.retain(|t| !self.ignores_table(&t.name));
+        filtered.indexes = filtered.tables.iter().flat_map(|t| t.indexes.clone()).collect();
+        filtered.privileges.retain(|p| {
+            p.object_type != "table"
+                || !(self.ignores_table(&p.object_name)
+                    || p.column_name.as_ref().is_some_and(|c| self.ignores_column(&p.object_name, c)))
+        });
+        for publication in &mut filtered.publications {
+            publication.tables.retain(|t| !self.ignores_table(t));
+        }
+
+        filtered
+    }
+}
+
+/// Match `name` against a pattern where `*` is any run of characters and
+/// `?` is one character.
+pub fn glob_match(pattern: &str, name: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let name: Vec<char> = name.chars().collect();
+    let (mut p, mut n) = (0, 0);
+    // Position after the last `*` and the name position it was tried at
+    let mut backtrack: Option<(usize, usize)> = None;
+
+    while n < name.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                backtrack = Some((p + 1, n));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == name[n] => {
+                p += 1;
+                n += 1;
+            }
+            _ => match backtrack {
+                Some((star, tried)) => {
+                    p = star;
+                    n = tried + 1;
+                    backtrack = Some((star, tried + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_glob_match() {
+        assert!(glob_match("spatial_ref_sys", "spatial_ref_sys"));
+        assert!(glob_match("_prisma_*", "_prisma_migrations"));
+        assert!(glob_match("*_audit", "orders_audit"));
+        assert!(glob_match("log_20??", "log_2024"));
+        assert!(glob_match("*", ""));
+        assert!(!glob_match("_prisma_*", "prisma_migrations"));
+        assert!(!glob_match("log_20??", "log_202"));
+        assert!(!glob_match("users", "users_old"));
+    }
+}
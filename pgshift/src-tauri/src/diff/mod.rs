@@ -1,5 +1,19 @@
 //! Schema diff engine.
 
 pub mod diff;
+pub mod store;
+pub mod roles;
+pub mod normalize;
+pub mod disk;
+pub mod renames;
+pub mod ignore;
+pub mod depends;
+pub mod delta;
+pub mod not_null;
+pub mod data_loss;
 
-pub use diff::{DiffReport, DiffItem, DiffKind};
+pub use diff::{DiffReport, DiffItem, DiffKind, DiffOptions, DangerAcknowledgment, EnumReorder, EnumReorderStrategy, PropertyChange};
+pub use data_loss::DataLoss;
+pub use disk::DiskEstimate;
+pub use ignore::IgnoreConfig;
+pub use renames::RenameMapping;
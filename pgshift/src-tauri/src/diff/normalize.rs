@@ -0,0 +1,130 @@
+//! Normalization of function and trigger bodies for comparison.
+//!
+//! Formatting-only edits (re-indentation, comments) should not produce
+//! migrations, so bodies are compared by a hash of their normalized text.
+
+use sha2::{Digest, Sha256};
+
+/// Normalize SQL text: strip `--` and `/* */` comments and collapse runs of
+/// whitespace into a single space. String literals and quoted identifiers are
+/// kept verbatim.
+pub fn normalize_sql_body(sql: &str) -> String {
+    let chars: Vec<char> = sql.chars().collect();
+    let mut out = String::with_capacity(sql.len());
+    let mut pending_space = false;
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+
+        if c == '-' && chars.get(i + 1) == Some(&'-') {
+            while i < chars.len() && chars[i] != '\n' {
+                i += 1;
+            }
+            pending_space = true;
+            continue;
+        }
+
+        if c == '/' && chars.get(i + 1) == Some(&'*') {
+            // Block comments nest in PostgreSQL
+            let mut depth = 0;
+            while i < chars.len() {
+                if chars[i] == '/' && chars.get(i + 1) == Some(&'*') {
+                    depth += 1;
+                    i += 2;
+                } else if chars[i] == '*' && chars.get(i + 1) == Some(&'/') {
+                    depth -= 1;
+                    i += 2;
+                    if depth == 0 {
+                        break;
+                    }
+                } else {
+                    i += 1;
+                }
+            }
+            pending_space = true;
+            continue;
+        }
+
+        if c.is_whitespace() {
+            pending_space = true;
+            i += 1;
+            continue;
+        }
+
+        if pending_space && !out.is_empty() {
+            out.push(' ');
+        }
+        pending_space = false;
+
+        if c == '\'' || c == '"' {
+            // Copy the literal including doubled quote escapes
+            out.push(c);
+            i += 1;
+            while i < chars.len() {
+                out.push(chars[i]);
+                if chars[i] == c {
+                    if chars.get(i + 1) == Some(&c) {
+                        out.push(c);
+                        i += 2;
+                        continue;
+                    }
+                    break;
+                }
+                i += 1;
+            }
+            i += 1;
+            continue;
+        }
+
+        out.push(c);
+        i += 1;
+    }
+
+    out
+}
+
+/// SHA-256 hex digest of the normalized body.
+pub fn body_hash(sql: &str) -> String {
+    let digest = Sha256::digest(normalize_sql_body(sql).as_bytes());
+    digest.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Check whether two bodies are equivalent.
+///
+/// With `strict` the raw text must match exactly; otherwise the normalized
+/// hashes are compared.
+pub fn bodies_equal(a: &str, b: &str, strict: bool) -> bool {
+    if strict {
+        a == b
+    } else {
+        body_hash(a) == body_hash(b)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize_strips_comments_and_whitespace() {
+        let body = "BEGIN\n    -- bump counter\n    x := x + 1; /* outer /* nested */ */\n  RETURN x;\nEND";
+        assert_eq!(normalize_sql_body(body), "BEGIN x := x + 1; RETURN x; END");
+    }
+
+    #[test]
+    fn test_normalize_keeps_literals() {
+        let body = "SELECT '--  not a comment', 'it''s   spaced'";
+        assert_eq!(normalize_sql_body(body), body);
+    }
+
+    #[test]
+    fn test_bodies_equal() {
+        let a = "BEGIN\n  RETURN 1;\nEND";
+        let b = "BEGIN RETURN 1; -- one\nEND";
+
+        assert!(bodies_equal(a, b, false));
+        assert!(!bodies_equal(a, b, true));
+        assert!(!bodies_equal(a, "BEGIN RETURN 2; END", false));
+    }
+}
@@ -0,0 +1,102 @@
+//! NOT NULL columns added to existing tables in steps.
+//!
+//! `ADD COLUMN ... NOT NULL` without a default fails on a table that has
+//! rows, and with a volatile default it rewrites the table under an ACCESS
+//! EXCLUSIVE lock. With safe migrations on, such a column is instead added
+//! nullable with a temporary default, so rows written meanwhile get a value,
+//! existing rows are filled in batches that each commit, and NOT NULL is set
+//! once a validated `CHECK (... IS NOT NULL)` lets PostgreSQL skip the scan
+//! that `SET NOT NULL` would otherwise make under its lock.
+
+use crate::model::schema::{Column, Table};
+use crate::render::dual_write::DEFAULT_BATCH_SIZE;
+
+/// Value existing rows get for a column of `data_type`, if there is an
+/// obvious one.
+pub fn backfill_value(data_type: &str) -> Option<&'static str> {
+    let data_type = data_type.to_lowercase();
+    if data_type.ends_with("[]") {
+        return Some("'{}'");
+    }
+    let base = data_type.split('(').next().unwrap_or_default().trim();
+    let value = match base {
+        "smallint" | "integer" | "bigint" | "numeric" | "decimal" | "real" | "double precision" | "money" => "0",
+        "text" | "character varying" | "varchar" | "character" | "char" | "citext" => "''",
+        "boolean" => "false",
+        "json" | "jsonb" => "'{}'",
+        "uuid" => "gen_random_uuid()",
+        "date" => "current_date",
+        "timestamp without time zone" | "timestamp with time zone" | "timestamp" | "timestamptz" => "now()",
+        _ => return None,
+    };
+    Some(value)
+}
+
+/// Statements adding `column`, NOT NULL without a default, to the existing
+/// `table` in steps, or `None` when its type has no [`backfill_value`].
+///
+/// `add_column_sql` adds the column nullable, with its storage settings.
+pub fn not_null_column_steps(table: &Table, column: &Column, add_column_sql: &str) -> Option<String> {
+    let value = backfill_value(&column.data_type)?;
+    let t = quote(&table.name);
+    let c = quote(&column.name);
+    let check = quote(&format!("{}_{}_not_null", table.name, column.name));
+
+    Some([
+        add_column_sql.to_string(),
+        format!("ALTER TABLE {t} ALTER COLUMN {c} SET DEFAULT {value};"),
+        backfill_sql(table, &t, &c, value),
+        format!("ALTER TABLE {t} ADD CONSTRAINT {check} CHECK ({c} IS NOT NULL) NOT VALID;"),
+        format!("ALTER TABLE {t} VALIDATE CONSTRAINT {check};"),
+        format!("ALTER TABLE {t} ALTER COLUMN {c} SET NOT NULL;"),
+        format!("ALTER TABLE {t} DROP CONSTRAINT {check};"),
+        format!("ALTER TABLE {t} ALTER COLUMN {c} DROP DEFAULT;"),
+    ].join("\n"))
+}
+
+/// A `DO` block filling the rows where `c` is null, committing after every
+/// batch. Batches walk a single-column primary key when the table has one,
+/// and otherwise take the next null rows by `ctid`.
+fn backfill_sql(table: &Table, t: &str, c: &str, value: &str) -> String {
+    let key = match table.primary_key.as_ref().map(|pk| pk.columns.as_slice()) {
+        Some([key]) => table.find_column(key),
+        _ => None,
+    };
+    let Some(key) = key else {
+        return format!(
+            "DO $$\n\
+             DECLARE\n    batch_rows bigint;\n\
+             BEGIN\n    LOOP\n        \
+             UPDATE {t} SET {c} = {value}\n        \
+             WHERE ctid IN (SELECT ctid FROM {t} WHERE {c} IS NULL LIMIT {DEFAULT_BATCH_SIZE});\n        \
+             GET DIAGNOSTICS batch_rows = ROW_COUNT;\n        \
+             EXIT WHEN batch_rows = 0;\n        \
+             COMMIT;\n    END LOOP;\nEND;\n$$;"
+        );
+    };
+
+    let k = quote(&key.name);
+    format!(
+        "DO $$\n\
+         DECLARE\n    last_key {};\n\
+         BEGIN\n    LOOP\n        \
+         WITH batch AS (\n            \
+         SELECT {k} FROM {t}\n            \
+         WHERE last_key IS NULL OR {k} > last_key\n            \
+         ORDER BY {k}\n            \
+         LIMIT {DEFAULT_BATCH_SIZE}\n        \
+         ), updated AS (\n            \
+         UPDATE {t} SET {c} = {value}\n            \
+         FROM batch WHERE {t}.{k} = batch.{k} AND {t}.{c} IS NULL\n        \
+         )\n        \
+         SELECT max({k}) INTO last_key FROM batch;\n        \
+         EXIT WHEN last_key IS NULL;\n        \
+         COMMIT;\n    END LOOP;\nEND;\n$$;",
+        key.data_type
+    )
+}
+
+fn quote(name: &str) -> String {
+    format!("\"{}\"", name.replace('"', "\"\""))
+}
+
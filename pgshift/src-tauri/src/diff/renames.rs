@@ -0,0 +1,158 @@
+//! Explicit table and column renames.
+//!
+//! A renamed table or column looks like one object removed and another
+//! added, which would drop its data. Renames listed by the user are applied
+//! to a copy of the target before comparing, so both sides line up, and are
+//! reported as `RENAME` statements that run before every other change.
+
+use serde::{Deserialize, Serialize};
+use crate::model::schema::{SchemaModel, Table};
+use super::diff::{DiffItem, DiffKind, DiffReport, PropertyChange};
+
+/// Kind of object a [`RenameMapping`] renames.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum RenameObject {
+    Table,
+    Column,
+}
+
+/// A table or column named `from` in the target and `to` in the source.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct RenameMapping {
+    pub object_type: RenameObject,
+    /// Table of a renamed column, by its name in the source.
+    #[serde(default)]
+    pub table: Option<String>,
+    pub from: String,
+    pub to: String,
+}
+
+/// Apply `renames` to a copy of `target`, adding a rename item to `report`
+/// for each one and a warning for each that does not match both schemas.
+///
+/// Table renames are applied before column renames, so a column's table is
+/// always given by its new name.
+pub fn apply_renames(
+    report: &mut DiffReport,
+    source: &SchemaModel,
+    target: &SchemaModel,
+    renames: &[RenameMapping],
+) -> SchemaModel {
+    let mut renamed = target.clone();
+
+    for rename in renames.iter().filter(|r| r.object_type == RenameObject::Table) {
+        let valid = renamed.find_table(&rename.from).is_some()
+            && renamed.find_table(&rename.to).is_none()
+            && source.find_table(&rename.to).is_some();
+        if !valid {
+            report.warnings.push(format!(
+                "Ignored rename of table '{}' to '{}': the target must have '{}' and not '{}', and the source must have '{}'",
+                rename.from, rename.to, rename.from, rename.to, rename.to
+            ));
+            continue;
+        }
+
+        rename_table(&mut renamed, &rename.from, &rename.to);
+        report.items.push(DiffItem::new(
+            DiffKind::Renamed,
+            "rename",
+            &rename.to,
+            &format!("Rename table '{}' to '{}'", rename.from, rename.to),
+            &format!("ALTER TABLE \"{}\" RENAME TO \"{}\";", rename.from, rename.to),
+            &format!("ALTER TABLE \"{}\" RENAME TO \"{}\";", rename.to, rename.from),
+            false,
+        ).with_changes(vec![PropertyChange::new("name", &rename.from, &rename.to)]));
+    }
+
+    for rename in renames.iter().filter(|r| r.object_type == RenameObject::Column) {
+        let table_name = rename.table.clone().unwrap_or_default();
+        let valid = renamed.find_table(&table_name).is_some_and(|t| {
+            t.find_column(&rename.from).is_some() && t.find_column(&rename.to).is_none()
+        }) && source.find_table(&table_name).is_some_and(|t| t.find_column(&rename.to).is_some());
+        if !valid {
+            report.warnings.push(format!(
+                "Ignored rename of column '{}.{}' to '{}': the target must have '{}' and not '{}', and the source must have '{}'",
+                table_name, rename.from, rename.to, rename.from, rename.to, rename.to
+            ));
+            continue;
+        }
+
+        rename_column(&mut renamed, &table_name, &rename.from, &rename.to);
+        report.items.push(DiffItem::new(
+            DiffKind::Renamed,
+            "rename",
+            &format!("{}.{}", table_name, rename.to),
+            &format!("Rename column '{}' of table '{}' to '{}'", rename.from, table_name, rename.to),
+            &format!("ALTER TABLE \"{}\" RENAME COLUMN \"{}\" TO \"{}\";", table_name, rename.from, rename.to),
+            &format!("ALTER TABLE \"{}\" RENAME COLUMN \"{}\" TO \"{}\";", table_name, rename.to, rename.from),
+            false,
+        ).with_changes(vec![PropertyChange::new("name", &rename.from, &rename.to)]));
+    }
+
+    renamed.indexes = renamed.tables.iter().flat_map(|t| t.indexes.clone()).collect();
+    renamed
+}
+
+/// Rename a table and every reference the server updates along with it.
+fn rename_table(schema: &mut SchemaModel, from: &str, to: &str) {
+    let rename = |name: &mut String| {
+        if name == from {
+            *name = to.to_string();
+        }
+    };
+
+    for table in &mut schema.tables {
+        rename(&mut table.name);
+        table.inherits.iter_mut().for_each(rename);
+        table.foreign_keys.iter_mut().for_each(|fk| rename(&mut fk.referenced_table));
+    }
+    for publication in &mut schema.publications {
+        publication.tables.iter_mut().for_each(rename);
+    }
+    for privilege in schema.privileges.iter_mut().filter(|p| p.object_type == "table") {
+        rename(&mut privilege.object_name);
+    }
+}
+
+/// Rename a column and the key, index and foreign key column lists that
+/// refer to it.
+fn rename_column(schema: &mut SchemaModel, table_name: &str, from: &str, to: &str) {
+    let rename = |name: &mut String| {
+        if name == from {
+            *name = to.to_string();
+        }
+    };
+
+    for table in &mut schema.tables {
+        if table.name == table_name {
+            rename_own_column(table, from, to);
+        }
+        for fk in table.foreign_keys.iter_mut().filter(|fk| fk.referenced_table == table_name) {
+            fk.referenced_columns.iter_mut().for_each(rename);
+        }
+    }
+    for privilege in &mut schema.privileges {
+        if privilege.object_type == "table" && privilege.object_name == table_name {
+            if let Some(column) = &mut privilege.column_name {
+                rename(column);
+            }
+        }
+    }
+}
+
+fn rename_own_column(table: &mut Table, from: &str, to: &str) {
+    let rename = |name: &mut String| {
+        if name == from {
+            *name = to.to_string();
+        }
+    };
+
+    table.columns.iter_mut().for_each(|c| rename(&mut c.name));
+    let constraints = table.primary_key.iter_mut().chain(table.unique_constraints.iter_mut());
+    constraints.for_each(|c| c.columns.iter_mut().for_each(rename));
+    for index in &mut table.indexes {
+        index.columns.iter_mut().chain(index.include_columns.iter_mut()).for_each(rename);
+    }
+    table.foreign_keys.iter_mut().for_each(|fk| fk.columns.iter_mut().for_each(rename));
+}
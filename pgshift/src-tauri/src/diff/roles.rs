@@ -0,0 +1,272 @@
+//! Cluster-level role comparison.
+//!
+//! Roles live outside any single database, so their differences are reported
+//! in a [`RoleDiffReport`] marked with [`DiffScope::Cluster`] rather than mixed
+//! into a schema [`DiffReport`](super::DiffReport).
+
+use serde::{Deserialize, Serialize};
+use crate::model::role::Role;
+use super::depends::role_key;
+use super::diff::{DiffItem, DiffKind};
+
+/// Whether a report describes one database or the whole server.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum DiffScope {
+    Schema,
+    Cluster,
+}
+
+/// Differences between the roles of two servers.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RoleDiffReport {
+    /// Always [`DiffScope::Cluster`]: applying these statements affects every
+    /// database on the target server.
+    pub scope: DiffScope,
+    pub items: Vec<DiffItem>,
+    pub source_connection: String,
+    pub target_connection: String,
+    pub generated_at: String,
+}
+
+impl RoleDiffReport {
+    pub fn new() -> Self {
+        Self {
+            scope: DiffScope::Cluster,
+            items: Vec::new(),
+            source_connection: String::new(),
+            target_connection: String::new(),
+            generated_at: chrono::Utc::now().to_rfc3339(),
+        }
+    }
+
+    pub fn has_dangerous(&self) -> bool {
+        self.items.iter().any(|i| i.dangerous)
+    }
+}
+
+impl Default for RoleDiffReport {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Compare roles of two servers.
+/// Source is the desired state, target is the current state.
+pub fn compare_roles(source: &[Role], target: &[Role]) -> RoleDiffReport {
+    let mut report = RoleDiffReport::new();
+
+    // Find roles to create
+    for source_role in source {
+        if !target.iter().any(|t| t.name == source_role.name) {
+            let mut up_sql = generate_create_role_sql(source_role);
+            for parent in &source_role.member_of {
+                up_sql.push('\n');
+                up_sql.push_str(&generate_grant_membership_sql(parent, &source_role.name));
+            }
+
+            report.items.push(DiffItem::new(
+                DiffKind::Added,
+                "role",
+                &source_role.name,
+                &format!("Create role '{}' (password is not copied)", source_role.name),
+                &up_sql,
+                &format!("DROP ROLE IF EXISTS \"{}\";", source_role.name),
+                false,
+            ).with_dependencies(vec![role_key(&source_role.name)], membership_requires(source_role)));
+        }
+    }
+
+    // Find roles to drop
+    for target_role in target {
+        if !source.iter().any(|s| s.name == target_role.name) {
+            let mut down_sql = generate_create_role_sql(target_role);
+            for parent in &target_role.member_of {
+                down_sql.push('\n');
+                down_sql.push_str(&generate_grant_membership_sql(parent, &target_role.name));
+            }
+
+            report.items.push(DiffItem::new(
+                DiffKind::Removed,
+                "role",
+                &target_role.name,
+                &format!("Drop role '{}'", target_role.name),
+                &format!("DROP ROLE IF EXISTS \"{}\";", target_role.name),
+                &down_sql,
+                true, // Fails or orphans access if the role owns objects or is in use
+            ).with_dependencies(vec![role_key(&target_role.name)], membership_requires(target_role)));
+        }
+    }
+
+    // Find modified roles and memberships
+    for source_role in source {
+        if let Some(target_role) = target.iter().find(|t| t.name == source_role.name) {
+            let up_options = role_option_changes(source_role, target_role);
+            if !up_options.is_empty() {
+                let down_options = role_option_changes(target_role, source_role);
+                // Granting or revoking superuser changes what the role can do everywhere
+                let dangerous = source_role.superuser != target_role.superuser;
+
+                report.items.push(DiffItem::new(
+                    DiffKind::Modified,
+                    "role",
+                    &source_role.name,
+                    &format!("Alter role '{}': {}", source_role.name, up_options.join(" ")),
+                    &format!("ALTER ROLE \"{}\" WITH {};", source_role.name, up_options.join(" ")),
+                    &format!("ALTER ROLE \"{}\" WITH {};", source_role.name, down_options.join(" ")),
+                    dangerous,
+                ));
+            }
+
+            for parent in &source_role.member_of {
+                if !target_role.member_of.contains(parent) {
+                    report.items.push(DiffItem::new(
+                        DiffKind::Added,
+                        "role_membership",
+                        &format!("{}.{}", parent, source_role.name),
+                        &format!("Grant role '{}' to '{}'", parent, source_role.name),
+                        &generate_grant_membership_sql(parent, &source_role.name),
+                        &generate_revoke_membership_sql(parent, &source_role.name),
+                        false,
+                    ).with_dependencies(Vec::new(), vec![role_key(parent)]));
+                }
+            }
+
+            for parent in &target_role.member_of {
+                if !source_role.member_of.contains(parent) {
+                    report.items.push(DiffItem::new(
+                        DiffKind::Removed,
+                        "role_membership",
+                        &format!("{}.{}", parent, target_role.name),
+                        &format!("Revoke role '{}' from '{}'", parent, target_role.name),
+                        &generate_revoke_membership_sql(parent, &target_role.name),
+                        &generate_grant_membership_sql(parent, &target_role.name),
+                        false,
+                    ));
+                }
+            }
+        }
+    }
+
+    report
+}
+
+/// Parent roles a role is granted when it is created.
+fn membership_requires(role: &Role) -> Vec<String> {
+    role.member_of.iter().map(|parent| role_key(parent)).collect()
+}
+
+/// Render all role attributes as CREATE/ALTER ROLE options.
+fn role_options(role: &Role) -> Vec<String> {
+    let mut options = vec![
+        flag(role.superuser, "SUPERUSER", "NOSUPERUSER"),
+        flag(role.inherit, "INHERIT", "NOINHERIT"),
+        flag(role.create_role, "CREATEROLE", "NOCREATEROLE"),
+        flag(role.create_db, "CREATEDB", "NOCREATEDB"),
+        flag(role.can_login, "LOGIN", "NOLOGIN"),
+        flag(role.replication, "REPLICATION", "NOREPLICATION"),
+        flag(role.bypass_rls, "BYPASSRLS", "NOBYPASSRLS"),
+        format!("CONNECTION LIMIT {}", role.connection_limit),
+    ];
+    options.push(match &role.valid_until {
+        Some(until) => format!("VALID UNTIL '{}'", until.replace('\'', "''")),
+        None => "VALID UNTIL 'infinity'".to_string(),
+    });
+    options
+}
+
+/// Options that differ, rendered with the values of `to`.
+fn role_option_changes(to: &Role, from: &Role) -> Vec<String> {
+    let to_options = role_options(to);
+    let from_options = role_options(from);
+    to_options
+        .into_iter()
+        .zip(from_options)
+        .filter(|(t, f)| t != f)
+        .map(|(t, _)| t)
+        .collect()
+}
+
+fn flag(value: bool, on: &str, off: &str) -> String {
+    if value { on } else { off }.to_string()
+}
+
+/// Generate CREATE ROLE SQL statement.
+fn generate_create_role_sql(role: &Role) -> String {
+    let options: Vec<String> = role_options(role)
+        .into_iter()
+        .filter(|o| o != "VALID UNTIL 'infinity'")
+        .collect();
+    format!("CREATE ROLE \"{}\" WITH {};", role.name, options.join(" "))
+}
+
+fn generate_grant_membership_sql(parent: &str, member: &str) -> String {
+    format!("GRANT \"{}\" TO \"{}\";", parent, member)
+}
+
+fn generate_revoke_membership_sql(parent: &str, member: &str) -> String {
+    format!("REVOKE \"{}\" FROM \"{}\";", parent, member)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn create_test_role(name: &str) -> Role {
+        Role {
+            name: name.to_string(),
+            superuser: false,
+            inherit: true,
+            create_role: false,
+            create_db: false,
+            can_login: true,
+            replication: false,
+            bypass_rls: false,
+            connection_limit: -1,
+            valid_until: None,
+            member_of: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_role_added() {
+        let mut role = create_test_role("app");
+        role.member_of.push("readers".to_string());
+
+        let report = compare_roles(&[role], &[]);
+
+        assert_eq!(report.scope, DiffScope::Cluster);
+        assert_eq!(report.items.len(), 1);
+        assert_eq!(report.items[0].kind, DiffKind::Added);
+        assert_eq!(
+            report.items[0].generated_up_sql,
+            "CREATE ROLE \"app\" WITH NOSUPERUSER INHERIT NOCREATEROLE NOCREATEDB LOGIN \
+             NOREPLICATION NOBYPASSRLS CONNECTION LIMIT -1;\nGRANT \"readers\" TO \"app\";"
+        );
+    }
+
+    #[test]
+    fn test_role_altered_and_membership() {
+        let mut source = create_test_role("app");
+        source.create_db = true;
+        source.member_of.push("writers".to_string());
+        let mut target = create_test_role("app");
+        target.member_of.push("readers".to_string());
+
+        let report = compare_roles(&[source], &[target]);
+
+        assert_eq!(report.items.len(), 3);
+        assert_eq!(report.items[0].generated_up_sql, "ALTER ROLE \"app\" WITH CREATEDB;");
+        assert_eq!(report.items[0].generated_down_sql, "ALTER ROLE \"app\" WITH NOCREATEDB;");
+        assert_eq!(report.items[1].generated_up_sql, "GRANT \"writers\" TO \"app\";");
+        assert_eq!(report.items[2].generated_up_sql, "REVOKE \"readers\" FROM \"app\";");
+    }
+
+    #[test]
+    fn test_role_removed_is_dangerous() {
+        let report = compare_roles(&[], &[create_test_role("old")]);
+
+        assert_eq!(report.items[0].kind, DiffKind::Removed);
+        assert!(report.items[0].dangerous);
+    }
+}
@@ -0,0 +1,316 @@
+//! Server-side storage of diff reports for paged retrieval.
+//!
+//! Very divergent databases produce reports with tens of thousands of items.
+//! Instead of shipping the whole report over IPC, the report is kept here and
+//! the frontend fetches a summary plus pages of items.
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+use super::diff::{DangerAcknowledgment, DiffItem, DiffKind, DiffReport, EnumReorderStrategy};
+
+/// Maximum number of reports kept in memory; the oldest is evicted first.
+const MAX_STORED_REPORTS: usize = 16;
+
+/// Upper bound on items returned in a single page.
+pub const MAX_PAGE_ITEMS: usize = 1000;
+
+/// Approximate upper bound on the serialized size of a single page.
+pub const MAX_PAGE_BYTES: usize = 1024 * 1024;
+
+/// Filters applied when paging through a stored report.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct DiffItemFilter {
+    #[serde(default)]
+    pub kinds: Option<Vec<DiffKind>>,
+    #[serde(default)]
+    pub object_types: Option<Vec<String>>,
+    #[serde(default)]
+    pub dangerous_only: bool,
+    /// Case-insensitive substring match on object name and details.
+    #[serde(default)]
+    pub search: Option<String>,
+}
+
+impl DiffItemFilter {
+    pub fn matches(&self, item: &DiffItem) -> bool {
+        if let Some(kinds) = &self.kinds {
+            if !kinds.contains(&item.kind) {
+                return false;
+            }
+        }
+        if let Some(types) = &self.object_types {
+            if !types.contains(&item.object_type) {
+                return false;
+            }
+        }
+        if self.dangerous_only && !item.dangerous {
+            return false;
+        }
+        if let Some(search) = &self.search {
+            let needle = search.to_lowercase();
+            if !item.object_name.to_lowercase().contains(&needle)
+                && !item.details.to_lowercase().contains(&needle)
+            {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+/// Summary of a stored report, returned instead of the full item list.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DiffReportSummary {
+    pub report_id: String,
+    pub source_connection: String,
+    pub target_connection: String,
+    pub generated_at: String,
+    pub total_items: usize,
+    pub added: usize,
+    pub removed: usize,
+    pub modified: usize,
+    pub reordered: usize,
+    pub renamed: usize,
+    pub dangerous: usize,
+    /// Item counts per object type.
+    pub object_types: HashMap<String, usize>,
+}
+
+impl DiffReportSummary {
+    fn from_report(report_id: &str, report: &DiffReport) -> Self {
+        let mut object_types = HashMap::new();
+        for item in &report.items {
+            *object_types.entry(item.object_type.clone()).or_insert(0) += 1;
+        }
+
+        Self {
+            report_id: report_id.to_string(),
+            source_connection: report.source_connection.clone(),
+            target_connection: report.target_connection.clone(),
+            generated_at: report.generated_at.clone(),
+            total_items: report.items.len(),
+            added: report.items.iter().filter(|i| i.kind == DiffKind::Added).count(),
+            removed: report.items.iter().filter(|i| i.kind == DiffKind::Removed).count(),
+            modified: report.items.iter().filter(|i| i.kind == DiffKind::Modified).count(),
+            reordered: report.items.iter().filter(|i| i.kind == DiffKind::Reordered).count(),
+            renamed: report.items.iter().filter(|i| i.kind == DiffKind::Renamed).count(),
+            dangerous: report.items.iter().filter(|i| i.dangerous).count(),
+            object_types,
+        }
+    }
+}
+
+/// A page of items from a stored report.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DiffItemsPage {
+    pub report_id: String,
+    /// Number of items matching the filter.
+    pub total: usize,
+    pub offset: usize,
+    pub items: Vec<DiffItem>,
+    /// Offset of the next page, or None when this page is the last one.
+    pub next_offset: Option<usize>,
+}
+
+/// In-memory store of diff reports, managed as Tauri state.
+#[derive(Default)]
+pub struct ReportStore {
+    reports: Mutex<Vec<(String, DiffReport)>>,
+}
+
+impl ReportStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Store a report and return its summary.
+    pub fn insert(&self, report: DiffReport) -> DiffReportSummary {
+        let report_id = Uuid::new_v4().to_string();
+        let summary = DiffReportSummary::from_report(&report_id, &report);
+
+        let mut reports = self.reports.lock().unwrap();
+        if reports.len() >= MAX_STORED_REPORTS {
+            reports.remove(0);
+        }
+        reports.push((report_id, report));
+
+        summary
+    }
+
+    /// Get a copy of a stored report.
+    pub fn get(&self, report_id: &str) -> Option<DiffReport> {
+        let reports = self.reports.lock().unwrap();
+        reports.iter().find(|(id, _)| id == report_id).map(|(_, r)| r.clone())
+    }
+
+    /// Record an acknowledgment on a dangerous item of a stored report.
+    pub fn acknowledge(
+        &self,
+        report_id: &str,
+        item_id: &str,
+        acknowledgment: DangerAcknowledgment,
+    ) -> Result<DiffItem, String> {
+        let mut reports = self.reports.lock().unwrap();
+        let (_, report) = reports.iter_mut()
+            .find(|(id, _)| id == report_id)
+            .ok_or_else(|| format!("Report not found: {}", report_id))?;
+        let item = report.items.iter_mut()
+            .find(|i| i.id == item_id)
+            .ok_or_else(|| format!("Item not found: {}", item_id))?;
+        if !item.dangerous {
+            return Err(format!("{} {} is not a dangerous change", item.object_type, item.object_name));
+        }
+        item.acknowledgment = Some(acknowledgment);
+        Ok(item.clone())
+    }
+
+    /// Choose the strategy for a reordered enum item of a stored report.
+    pub fn resolve_enum_reorder(
+        &self,
+        report_id: &str,
+        item_id: &str,
+        strategy: EnumReorderStrategy,
+    ) -> Result<DiffItem, String> {
+        let mut reports = self.reports.lock().unwrap();
+        let (_, report) = reports.iter_mut()
+            .find(|(id, _)| id == report_id)
+            .ok_or_else(|| format!("Report not found: {}", report_id))?;
+        let item = report.items.iter_mut()
+            .find(|i| i.id == item_id)
+            .ok_or_else(|| format!("Item not found: {}", item_id))?;
+        item.resolve_enum_reorder(strategy)?;
+        Ok(item.clone())
+    }
+
+    /// Drop a stored report. Returns false if it was not found.
+    pub fn remove(&self, report_id: &str) -> bool {
+        let mut reports = self.reports.lock().unwrap();
+        let before = reports.len();
+        reports.retain(|(id, _)| id != report_id);
+        reports.len() != before
+    }
+
+    /// Get a page of items matching `filter`.
+    ///
+    /// The page holds at most `limit` items (capped at [`MAX_PAGE_ITEMS`]) and
+    /// stops early once the serialized size reaches [`MAX_PAGE_BYTES`]; it
+    /// always contains at least one item when any remain.
+    pub fn page(
+        &self,
+        report_id: &str,
+        offset: usize,
+        limit: usize,
+        filter: &DiffItemFilter,
+    ) -> Option<DiffItemsPage> {
+        let reports = self.reports.lock().unwrap();
+        let (_, report) = reports.iter().find(|(id, _)| id == report_id)?;
+
+        let matching: Vec<&DiffItem> = report.items.iter().filter(|i| filter.matches(i)).collect();
+        let limit = limit.clamp(1, MAX_PAGE_ITEMS);
+
+        let mut items = Vec::new();
+        let mut bytes = 0;
+        for item in matching.iter().skip(offset).take(limit) {
+            let size = serde_json::to_vec(item).map(|v| v.len()).unwrap_or(0);
+            if !items.is_empty() && bytes + size > MAX_PAGE_BYTES {
+                break;
+            }
+            bytes += size;
+            items.push((*item).clone());
+        }
+
+        let end = offset + items.len();
+        Some(DiffItemsPage {
+            report_id: report_id.to_string(),
+            total: matching.len(),
+            offset,
+            next_offset: if end < matching.len() { Some(end) } else { None },
+            items,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn create_test_report(count: usize) -> DiffReport {
+        let mut report = DiffReport::new();
+        for i in 0..count {
+            report.items.push(DiffItem::new(
+                if i % 2 == 0 { DiffKind::Added } else { DiffKind::Removed },
+                "table",
+                &format!("table_{}", i),
+                "",
+                &"x".repeat(1000),
+                "",
+                i % 2 == 1,
+            ));
+        }
+        report
+    }
+
+    #[test]
+    fn test_paging() {
+        let store = ReportStore::new();
+        let summary = store.insert(create_test_report(25));
+
+        assert_eq!(summary.total_items, 25);
+        assert_eq!(summary.added, 13);
+        assert_eq!(summary.dangerous, 12);
+
+        let page = store.page(&summary.report_id, 20, 10, &DiffItemFilter::default()).unwrap();
+        assert_eq!(page.items.len(), 5);
+        assert_eq!(page.next_offset, None);
+
+        let filter = DiffItemFilter { dangerous_only: true, ..Default::default() };
+        let page = store.page(&summary.report_id, 0, 5, &filter).unwrap();
+        assert_eq!(page.total, 12);
+        assert_eq!(page.next_offset, Some(5));
+        assert!(page.items.iter().all(|i| i.dangerous));
+    }
+
+    #[test]
+    fn test_page_byte_budget() {
+        let store = ReportStore::new();
+        let summary = store.insert(create_test_report(MAX_PAGE_ITEMS * 2));
+
+        let page = store.page(&summary.report_id, 0, MAX_PAGE_ITEMS, &DiffItemFilter::default()).unwrap();
+
+        assert!(page.items.len() < MAX_PAGE_ITEMS);
+        assert_eq!(page.next_offset, Some(page.items.len()));
+    }
+
+    #[test]
+    fn test_acknowledge() {
+        let store = ReportStore::new();
+        let summary = store.insert(create_test_report(2));
+        let report = store.get(&summary.report_id).unwrap();
+        let acknowledgment = DangerAcknowledgment {
+            acknowledged_by: "dba".to_string(),
+            acknowledged_at: "2026-02-03T02:00:00Z".to_string(),
+            reason: "Table was archived".to_string(),
+        };
+
+        // Only dangerous items can be acknowledged
+        assert!(store.acknowledge(&summary.report_id, &report.items[0].id, acknowledgment.clone()).is_err());
+
+        let item = store.acknowledge(&summary.report_id, &report.items[1].id, acknowledgment.clone()).unwrap();
+        assert_eq!(item.acknowledgment, Some(acknowledgment));
+        assert_eq!(store.get(&summary.report_id).unwrap().unacknowledged_dangerous(), 0);
+    }
+
+    #[test]
+    fn test_eviction() {
+        let store = ReportStore::new();
+        let first = store.insert(create_test_report(1));
+        for _ in 0..MAX_STORED_REPORTS {
+            store.insert(create_test_report(1));
+        }
+
+        assert!(store.get(&first.report_id).is_none());
+        assert!(!store.remove(&first.report_id));
+    }
+}
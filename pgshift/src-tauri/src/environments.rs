@@ -0,0 +1,231 @@
+//! Named environments of a project.
+//!
+//! [`ENVIRONMENTS_FILE`] in the default base path names the databases of a
+//! project, e.g. `dev`, `staging` and `prod`, with the settings that go with
+//! each: where its migrations live, what its comparisons ignore and whether
+//! it may be changed at all. Wherever a connection string is expected, the
+//! name of an environment can be passed instead.
+//!
+//! ```toml
+//! [environments.prod]
+//! connection_string = "postgres://deploy@db.internal:5432/app"
+//! migrations_dir = "/srv/app/migrations"
+//! read_only = true
+//!
+//! [environments.prod.tls]
+//! ssl_mode = "verify-full"
+//! root_cert = "/etc/ssl/rds-global-bundle.pem"
+//!
+//! [environments.prod.ssh]
+//! host = "bastion.example.com"
+//! user = "deploy"
+//!
+//! [environments.prod.ignore]
+//! tables = ["audit_*"]
+//! ```
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+use serde::{Deserialize, Serialize};
+use crate::apply::backup::BackupOptions;
+use crate::commands::default_migrations_dir;
+use crate::db::connect::TlsOptions;
+use crate::db::rds_iam::RdsIamOptions;
+use crate::db::tunnel::SshTunnelOptions;
+use crate::diff::ignore::IgnoreConfig;
+
+/// File in a base path holding its [`ProjectConfig`].
+pub const ENVIRONMENTS_FILE: &str = "pgshift.toml";
+
+/// The environments of a project.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ProjectConfig {
+    #[serde(default)]
+    pub environments: BTreeMap<String, Environment>,
+}
+
+/// A database of the project and its settings.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Environment {
+    pub connection_string: String,
+    /// Certificates and `sslmode` added to the connection string.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tls: Option<TlsOptions>,
+    /// Bastion the database is reached through.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ssh: Option<SshTunnelOptions>,
+    /// Authenticate with RDS IAM tokens instead of a password.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rds_iam: Option<RdsIamOptions>,
+    /// Migrations applied to it, used when a command is given no base path.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub migrations_dir: Option<String>,
+    /// Rules added to those of the base path when comparing it.
+    #[serde(default, skip_serializing_if = "IgnoreConfig::is_empty")]
+    pub ignore: IgnoreConfig,
+    /// Refuse applies, rollbacks and clones, as safe mode does everywhere.
+    #[serde(default)]
+    pub read_only: bool,
+    /// Back up before applying, when the apply itself asks for no backup.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub backup: Option<BackupOptions>,
+}
+
+impl ProjectConfig {
+    /// Load the config of `base_path`; empty when it has none.
+    pub fn load(base_path: &str) -> Result<Self, String> {
+        let path = Path::new(base_path).join(ENVIRONMENTS_FILE);
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let content = fs::read_to_string(&path)
+            .map_err(|e| format!("Failed to read {}: {}", ENVIRONMENTS_FILE, e))?;
+        toml::from_str(&content)
+            .map_err(|e| format!("Failed to parse {}: {}", ENVIRONMENTS_FILE, e))
+    }
+
+    pub fn save(&self, base_path: &str) -> Result<(), String> {
+        let content = toml::to_string_pretty(self)
+            .map_err(|e| format!("Failed to serialize {}: {}", ENVIRONMENTS_FILE, e))?;
+        fs::create_dir_all(base_path)
+            .map_err(|e| format!("Failed to create {}: {}", base_path, e))?;
+        fs::write(Path::new(base_path).join(ENVIRONMENTS_FILE), content)
+            .map_err(|e| format!("Failed to write {}: {}", ENVIRONMENTS_FILE, e))
+    }
+
+    /// The environment `connection` names; None when it is a connection
+    /// string.
+    pub fn find(&self, connection: &str) -> Result<Option<&Environment>, String> {
+        if !is_environment_name(connection) {
+            return Ok(None);
+        }
+        self.environments.get(connection.trim()).map(Some).ok_or_else(|| {
+            let names: Vec<&str> = self.environments.keys().map(String::as_str).collect();
+            if names.is_empty() {
+                format!("Unknown environment '{}'; no environments are defined in {}", connection.trim(), ENVIRONMENTS_FILE)
+            } else {
+                format!("Unknown environment '{}'; expected a connection string or one of: {}", connection.trim(), names.join(", "))
+            }
+        })
+    }
+}
+
+/// Whether `connection` is the name of an environment rather than a
+/// `postgres://` URL or `key=value` connection string.
+pub fn is_environment_name(connection: &str) -> bool {
+    !connection.contains("://") && !connection.contains('=')
+}
+
+/// Names may hold letters, digits, `-` and `_`, so they never look like a
+/// connection string.
+fn validate_name(name: &str) -> Result<(), String> {
+    if name.is_empty() || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_') {
+        return Err(format!("Invalid environment name '{}'; use letters, digits, '-' and '_'", name));
+    }
+    Ok(())
+}
+
+/// Add or replace the environment `name` of `base_path`.
+pub fn save_environment(base_path: &str, name: &str, environment: Environment) -> Result<(), String> {
+    validate_name(name)?;
+    if is_environment_name(&environment.connection_string) {
+        return Err(format!("The connection string of environment '{}' is not a connection string", name));
+    }
+    let mut config = ProjectConfig::load(base_path)?;
+    config.environments.insert(name.to_string(), environment);
+    config.save(base_path)
+}
+
+/// Remove the environment `name` of `base_path`.
+pub fn delete_environment(base_path: &str, name: &str) -> Result<(), String> {
+    let mut config = ProjectConfig::load(base_path)?;
+    if config.environments.remove(name).is_none() {
+        return Err(format!("Environment not found: {}", name));
+    }
+    config.save(base_path)
+}
+
+/// The environment `connection` names in the default base path; None when
+/// it is a connection string.
+pub fn find_environment(connection: &str) -> Result<Option<Environment>, String> {
+    if !is_environment_name(connection) {
+        return Ok(None);
+    }
+    Ok(ProjectConfig::load(&default_migrations_dir()?)?.find(connection)?.cloned())
+}
+
+/// The connection string of `connection`: itself, or that of the
+/// environment it names with its TLS, SSH tunnel and RDS IAM options.
+pub fn resolve_connection_string(connection: &str) -> Result<String, String> {
+    let Some(environment) = find_environment(connection)? else {
+        return Ok(connection.to_string());
+    };
+    let mut connection_string = environment.connection_string;
+    if let Some(tls) = &environment.tls {
+        connection_string = tls.apply(&connection_string)?;
+    }
+    if let Some(ssh) = &environment.ssh {
+        connection_string = ssh.apply(&connection_string)?;
+    }
+    if let Some(rds_iam) = &environment.rds_iam {
+        connection_string = rds_iam.apply(&connection_string)?;
+    }
+    Ok(connection_string)
+}
+
+/// Refuse `action` (e.g. "Applying migrations") on a read-only environment.
+pub fn ensure_writes_allowed(connection: &str, action: &str) -> Result<(), String> {
+    if find_environment(connection)?.is_some_and(|e| e.read_only) {
+        return Err(format!("{} is disabled for the read-only environment '{}'", action, connection.trim()));
+    }
+    Ok(())
+}
+
+/// `base_path`, or the migrations directory of the environment
+/// `connection` names when it is empty.
+pub fn migrations_dir(base_path: &str, connection: &str) -> Result<String, String> {
+    if !base_path.is_empty() {
+        return Ok(base_path.to_string());
+    }
+    find_environment(connection)?
+        .and_then(|e| e.migrations_dir)
+        .ok_or_else(|| "No base path given, and the connection is not an environment with a migrations directory".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::store::temp_base_path;
+
+    #[test]
+    fn test_environments() {
+        let base = temp_base_path();
+        let prod = Environment {
+            connection_string: "postgres://deploy@db:5432/app".to_string(),
+            read_only: true,
+            ignore: IgnoreConfig { tables: vec!["audit_*".to_string()], ..Default::default() },
+            ..Default::default()
+        };
+        save_environment(&base, "prod", prod.clone()).unwrap();
+        assert!(save_environment(&base, "prod db", prod.clone()).is_err());
+        assert!(save_environment(&base, "dev", Environment::default()).is_err());
+
+        let config = ProjectConfig::load(&base).unwrap();
+        assert_eq!(config.find("prod").unwrap(), Some(&prod));
+        assert_eq!(config.find("postgres://u@h/db").unwrap(), None);
+        assert_eq!(config.find("host=h dbname=db").unwrap(), None);
+        assert_eq!(
+            config.find("staging").unwrap_err(),
+            "Unknown environment 'staging'; expected a connection string or one of: prod"
+        );
+        let content = fs::read_to_string(Path::new(&base).join(ENVIRONMENTS_FILE)).unwrap();
+        assert!(content.contains("[environments.prod]"), "{}", content);
+
+        delete_environment(&base, "prod").unwrap();
+        assert!(ProjectConfig::load(&base).unwrap().environments.is_empty());
+        assert!(delete_environment(&base, "prod").is_err());
+
+        fs::remove_dir_all(&base).unwrap();
+    }
+}
@@ -0,0 +1,221 @@
+//! Object types contributed by extensions.
+//!
+//! pgshift reads, compares and renders the object types PostgreSQL has
+//! built in. Objects that live in the tables of a PostgreSQL extension, such
+//! as pg_cron jobs or pgmq queues, are added by registering an
+//! [`ObjectTypeExtension`] before the app or server starts:
+//!
+//! ```ignore
+//! pgshift_lib::extension::register_extension(Arc::new(CronJobs));
+//! ```
+//!
+//! Introspection then stores the objects each extension reads in
+//! `custom_objects` of the schema model, and comparisons turn the objects
+//! added, removed or changed into diff items of the extension's type. The
+//! items go through the same pipeline as built-in ones: ignore rules,
+//! approvals, rendering and applying. Objects of a type no registered
+//! extension handles are kept in the model but not compared.
+
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, RwLock};
+use sqlx::postgres::PgPool;
+use crate::diff::{DiffItem, DiffKind, DiffReport};
+use crate::model::schema::{CustomObject, SchemaModel};
+
+/// Objects read by [`ObjectTypeExtension::introspect`].
+pub type IntrospectFuture<'a> = Pin<Box<dyn Future<Output = Result<Vec<CustomObject>, sqlx::Error>> + Send + 'a>>;
+
+/// Introspection, comparison and rendering of one object type.
+pub trait ObjectTypeExtension: Send + Sync {
+    /// Name of the type, e.g. `cron_job`: the `object_type` of its objects
+    /// and diff items, and what ignore rules match.
+    fn object_type(&self) -> &str;
+
+    /// Read the objects of this type from the database of `pool`, connected
+    /// to the schema being introspected. Return none when the PostgreSQL
+    /// extension is not installed.
+    fn introspect<'a>(&'a self, pool: &'a PgPool) -> IntrospectFuture<'a>;
+
+    /// Statements creating `object`.
+    fn create_sql(&self, object: &CustomObject) -> String;
+
+    /// Statements dropping `object`.
+    fn drop_sql(&self, object: &CustomObject) -> String;
+
+    /// Statements turning `old` into `new`; by default drops and recreates it.
+    fn alter_sql(&self, old: &CustomObject, new: &CustomObject) -> String {
+        format!("{}\n{}", self.drop_sql(old), self.create_sql(new))
+    }
+
+    /// Whether dropping an object loses data, e.g. the messages of a queue.
+    fn drop_is_dangerous(&self) -> bool {
+        false
+    }
+
+    /// Objects `object` refers to, as dependency keys such as `table:users`
+    /// or `function:refresh_stats`, so it is created after them and dropped
+    /// before them.
+    fn requires(&self, _object: &CustomObject) -> Vec<String> {
+        Vec::new()
+    }
+}
+
+static EXTENSIONS: RwLock<Vec<Arc<dyn ObjectTypeExtension>>> = RwLock::new(Vec::new());
+
+/// Register `extension`, replacing any registered for the same object type.
+pub fn register_extension(extension: Arc<dyn ObjectTypeExtension>) {
+    let mut extensions = EXTENSIONS.write().unwrap_or_else(|e| e.into_inner());
+    extensions.retain(|e| e.object_type() != extension.object_type());
+    extensions.push(extension);
+}
+
+/// Object types of the registered extensions.
+pub fn registered_object_types() -> Vec<String> {
+    extensions().iter().map(|e| e.object_type().to_string()).collect()
+}
+
+fn extensions() -> Vec<Arc<dyn ObjectTypeExtension>> {
+    EXTENSIONS.read().unwrap_or_else(|e| e.into_inner()).clone()
+}
+
+/// The extension handling `object_type`.
+pub(crate) fn find_extension(object_type: &str) -> Option<Arc<dyn ObjectTypeExtension>> {
+    extensions().into_iter().find(|e| e.object_type() == object_type)
+}
+
+/// Objects of every registered type in the database of `pool`.
+pub(crate) async fn introspect_custom_objects(pool: &PgPool) -> Result<Vec<CustomObject>, sqlx::Error> {
+    let mut objects = Vec::new();
+    for extension in extensions() {
+        let mut found = extension.introspect(pool).await?;
+        // The model only holds objects of the type the extension handles
+        found.retain(|o| o.object_type == extension.object_type());
+        found.sort_by(|a, b| a.name.cmp(&b.name));
+        objects.extend(found);
+    }
+    Ok(objects)
+}
+
+/// Add items for the custom objects of registered types that differ
+/// between `source` and `target`.
+pub(crate) fn compare_custom_objects(report: &mut DiffReport, source: &SchemaModel, target: &SchemaModel) {
+    let key = |o: &CustomObject| custom_object_key(&o.object_type, &o.name);
+    for extension in extensions() {
+        let object_type = extension.object_type();
+        let of_type = |schema: &SchemaModel| -> Vec<CustomObject> {
+            schema.custom_objects.iter().filter(|o| o.object_type == object_type).cloned().collect()
+        };
+        let (source_objects, target_objects) = (of_type(source), of_type(target));
+
+        for object in &source_objects {
+            let item = match target_objects.iter().find(|t| t.name == object.name) {
+                None => DiffItem::new(
+                    DiffKind::Added,
+                    object_type,
+                    &object.name,
+                    &format!("Create {} '{}'", object_type, object.name),
+                    &extension.create_sql(object),
+                    &extension.drop_sql(object),
+                    false,
+                ),
+                Some(existing) if existing.definition != object.definition => DiffItem::new(
+                    DiffKind::Modified,
+                    object_type,
+                    &object.name,
+                    &format!("Change {} '{}'", object_type, object.name),
+                    &extension.alter_sql(existing, object),
+                    &extension.alter_sql(object, existing),
+                    false,
+                ),
+                Some(_) => continue,
+            };
+            report.items.push(item.with_dependencies(vec![key(object)], extension.requires(object)));
+        }
+        for object in target_objects.iter().filter(|t| !source_objects.iter().any(|s| s.name == t.name)) {
+            report.items.push(DiffItem::new(
+                DiffKind::Removed,
+                object_type,
+                &object.name,
+                &format!("Drop {} '{}'", object_type, object.name),
+                &extension.drop_sql(object),
+                &extension.create_sql(object),
+                extension.drop_is_dangerous(),
+            ).with_dependencies(vec![key(object)], extension.requires(object)));
+        }
+    }
+}
+
+/// Dependency key of a custom object.
+fn custom_object_key(object_type: &str, name: &str) -> String {
+    format!("{}:{}", object_type, name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::diff::diff::compare_schemas;
+    use crate::render::sql::generate_up_sql;
+
+    struct Queues;
+
+    impl ObjectTypeExtension for Queues {
+        fn object_type(&self) -> &str {
+            "test_queue"
+        }
+
+        fn introspect<'a>(&'a self, _pool: &'a PgPool) -> IntrospectFuture<'a> {
+            Box::pin(async { Ok(Vec::new()) })
+        }
+
+        fn create_sql(&self, object: &CustomObject) -> String {
+            format!("SELECT pgmq.create('{}');", object.name)
+        }
+
+        fn drop_sql(&self, object: &CustomObject) -> String {
+            format!("SELECT pgmq.drop_queue('{}');", object.name)
+        }
+
+        fn drop_is_dangerous(&self) -> bool {
+            true
+        }
+
+        fn requires(&self, _object: &CustomObject) -> Vec<String> {
+            vec!["table:events".to_string()]
+        }
+    }
+
+    fn queue(name: &str, definition: serde_json::Value) -> CustomObject {
+        CustomObject { object_type: "test_queue".to_string(), name: name.to_string(), definition }
+    }
+
+    #[test]
+    fn test_compare_custom_objects() {
+        register_extension(Arc::new(Queues));
+        let source = SchemaModel {
+            custom_objects: vec![
+                queue("orders", serde_json::json!({ "partitioned": false })),
+                queue("emails", serde_json::json!({})),
+                CustomObject { object_type: "unregistered".to_string(), name: "x".to_string(), definition: serde_json::json!({}) },
+            ],
+            ..Default::default()
+        };
+        let target = SchemaModel {
+            custom_objects: vec![
+                queue("orders", serde_json::json!({ "partitioned": true })),
+                queue("audit", serde_json::json!({})),
+            ],
+            ..Default::default()
+        };
+
+        let report = compare_schemas(&source, &target);
+        let items: Vec<(DiffKind, &str)> = report.items.iter().map(|i| (i.kind.clone(), i.object_name.as_str())).collect();
+        assert_eq!(items, [(DiffKind::Modified, "orders"), (DiffKind::Added, "emails"), (DiffKind::Removed, "audit")]);
+        assert!(report.items[2].dangerous);
+        assert_eq!(report.items[1].requires, ["table:events"]);
+
+        let up_sql = generate_up_sql(&report, None, false);
+        assert!(up_sql.contains("SELECT pgmq.create('emails');"));
+        assert!(up_sql.find("drop_queue('audit')").unwrap() < up_sql.find("create('emails')").unwrap());
+    }
+}
@@ -3,11 +3,26 @@
 //! This library provides the core functionality for comparing PostgreSQL schemas
 //! and generating migration files.
 
+pub mod cli;
 pub mod commands;
 pub mod db;
 pub mod model;
 pub mod diff;
 pub mod render;
 pub mod apply;
+pub mod schedule;
+pub mod source;
+pub mod ddl;
+pub mod server;
+pub mod safe_mode;
+pub mod audit;
+pub mod logging;
+pub mod environments;
+pub mod cancel;
+pub mod storage;
+pub mod store;
+pub mod extension;
+#[cfg(feature = "test-support")]
+pub mod test_support;
 
 pub use commands::*;
@@ -0,0 +1,221 @@
+//! Structured logging.
+//!
+//! pgshift logs through `tracing`. [`init`] writes events to a log file in
+//! [`default_log_dir`] that rotates daily, keeping the last
+//! [`MAX_LOG_FILES`], and keeps the latest [`RECENT_CAPACITY`] events in
+//! memory for the log console (`get_recent_logs`). Events of pgshift are
+//! kept from `INFO` on, or the level in the `PGSHIFT_LOG` environment
+//! variable; those of its dependencies from `WARN` on.
+//!
+//! Logs shown with the result of an operation, such as the run of a
+//! migration, are written with [`log_line!`], which emits each line as an
+//! event as well.
+
+use std::collections::VecDeque;
+use std::fmt::{self, Debug};
+use std::fs;
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::sync::Mutex;
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use tracing::field::{Field, Visit};
+use tracing::{Event, Level, Subscriber};
+use tracing_appender::rolling::{RollingFileAppender, Rotation};
+use tracing_subscriber::filter::Targets;
+use tracing_subscriber::layer::{Context, Layer, SubscriberExt};
+use tracing_subscriber::util::SubscriberInitExt;
+
+/// Daily log files kept; older ones are deleted.
+pub const MAX_LOG_FILES: usize = 14;
+
+/// Events kept in memory for [`recent_logs`].
+pub const RECENT_CAPACITY: usize = 2000;
+
+/// Environment variable setting the level of pgshift's own events.
+pub const LOG_LEVEL_VAR: &str = "PGSHIFT_LOG";
+
+/// Targets of pgshift's own events: the library and its binaries.
+const TARGETS: &[&str] = &["pgshift_lib", "pgshift", "pgshift_cli"];
+
+static RECENT: RecentLogs = RecentLogs::new();
+
+/// Append a line to the log of an operation, e.g. `run.logs`, and emit it
+/// as an event of `level`.
+///
+/// The line is prefixed with the time, as the log of an operation is read on
+/// its own.
+#[macro_export]
+macro_rules! log_line {
+    ($logs:expr, $level:expr, $($arg:tt)+) => {{
+        let message = format!($($arg)+);
+        tracing::event!($level, "{}", message);
+        $logs.push(format!("[{}] {}", $crate::logging::timestamp(), message));
+    }};
+}
+
+/// One logged event.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LogRecord {
+    pub timestamp: String,
+    /// `ERROR`, `WARN`, `INFO`, `DEBUG` or `TRACE`.
+    pub level: String,
+    /// Module that logged it, e.g. `pgshift_lib::apply::exec`.
+    pub target: String,
+    /// The message, followed by any other fields as `name=value`.
+    pub message: String,
+}
+
+/// Directory of the log files: `~/PGShift/logs`.
+pub fn default_log_dir() -> Result<PathBuf, String> {
+    let home_dir = dirs::home_dir().ok_or_else(|| "Could not find home directory".to_string())?;
+    Ok(home_dir.join("PGShift").join("logs"))
+}
+
+/// Log to rotating files in `default_log_dir()` and to the in-memory
+/// buffer. Does nothing when logging is already set up.
+pub fn init() -> Result<(), String> {
+    let log_dir = default_log_dir()?;
+    fs::create_dir_all(&log_dir).map_err(|e| format!("Failed to create log directory: {}", e))?;
+    let files = RollingFileAppender::builder()
+        .rotation(Rotation::DAILY)
+        .filename_prefix("pgshift")
+        .filename_suffix("log")
+        .max_log_files(MAX_LOG_FILES)
+        .build(log_dir)
+        .map_err(|e| format!("Failed to open log file: {}", e))?;
+
+    let level = std::env::var(LOG_LEVEL_VAR)
+        .ok()
+        .and_then(|level| Level::from_str(&level).ok())
+        .unwrap_or(Level::INFO);
+    let filter = TARGETS
+        .iter()
+        .fold(Targets::new().with_default(Level::WARN), |filter, target| filter.with_target(*target, level));
+
+    let _ = tracing_subscriber::registry()
+        .with(filter)
+        .with(tracing_subscriber::fmt::layer().with_writer(files).with_ansi(false))
+        .with(RecentLayer(&RECENT))
+        .try_init();
+    Ok(())
+}
+
+/// The latest `limit` events at `level` or more severe, oldest first.
+pub fn recent_logs(level: Level, limit: usize) -> Vec<LogRecord> {
+    RECENT.query(level, limit)
+}
+
+/// Time of day a line of an operation's log is prefixed with.
+pub fn timestamp() -> String {
+    Utc::now().format("%H:%M:%S%.3f").to_string()
+}
+
+/// The latest events, up to [`RECENT_CAPACITY`].
+struct RecentLogs {
+    records: Mutex<VecDeque<(Level, LogRecord)>>,
+}
+
+impl RecentLogs {
+    const fn new() -> Self {
+        Self { records: Mutex::new(VecDeque::new()) }
+    }
+
+    fn push(&self, level: Level, record: LogRecord) {
+        let mut records = self.records.lock().unwrap_or_else(|e| e.into_inner());
+        if records.len() == RECENT_CAPACITY {
+            records.pop_front();
+        }
+        records.push_back((level, record));
+    }
+
+    fn query(&self, level: Level, limit: usize) -> Vec<LogRecord> {
+        let records = self.records.lock().unwrap_or_else(|e| e.into_inner());
+        // More verbose levels compare greater
+        let mut matching: Vec<LogRecord> = records
+            .iter()
+            .rev()
+            .filter(|(l, _)| *l <= level)
+            .take(limit)
+            .map(|(_, record)| record.clone())
+            .collect();
+        matching.reverse();
+        matching
+    }
+}
+
+/// Layer copying events into a [`RecentLogs`].
+struct RecentLayer(&'static RecentLogs);
+
+impl<S: Subscriber> Layer<S> for RecentLayer {
+    fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+        let mut message = MessageVisitor::default();
+        event.record(&mut message);
+        let metadata = event.metadata();
+        self.0.push(*metadata.level(), LogRecord {
+            timestamp: Utc::now().to_rfc3339(),
+            level: metadata.level().to_string(),
+            target: metadata.target().to_string(),
+            message: message.0,
+        });
+    }
+}
+
+/// Writes the fields of an event as its message then `name=value` pairs.
+#[derive(Default)]
+struct MessageVisitor(String);
+
+impl MessageVisitor {
+    fn append(&mut self, field: &Field, value: fmt::Arguments<'_>) {
+        if field.name() == "message" {
+            self.0.insert_str(0, &value.to_string());
+        } else {
+            self.0.push_str(&format!(" {}={}", field.name(), value));
+        }
+    }
+}
+
+impl Visit for MessageVisitor {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.append(field, format_args!("{}", value));
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn Debug) {
+        self.append(field, format_args!("{:?}", value));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_recent_logs() {
+        let recent: &'static RecentLogs = Box::leak(Box::new(RecentLogs::new()));
+        let mut logs = Vec::new();
+        tracing::subscriber::with_default(tracing_subscriber::registry().with(RecentLayer(recent)), || {
+            tracing::info!(migration = "20240101__a", "Applying");
+            tracing::warn!("Slow statement");
+            log_line!(logs, Level::ERROR, "Migration FAILED: {}", "relation exists");
+        });
+
+        assert_eq!(logs.len(), 1);
+        assert!(logs[0].ends_with("] Migration FAILED: relation exists"));
+        let info = recent.query(Level::INFO, 10);
+        assert_eq!(info[0].message, "Applying migration=20240101__a");
+        assert_eq!(info[2].level, "ERROR");
+        assert_eq!(info[2].target, "pgshift_lib::logging::tests");
+        let warnings = recent.query(Level::WARN, 1);
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(warnings[0].message, "Migration FAILED: relation exists");
+
+        tracing::subscriber::with_default(tracing_subscriber::registry().with(RecentLayer(recent)), || {
+            for i in 0..RECENT_CAPACITY {
+                tracing::debug!("Statement {}", i);
+            }
+        });
+        assert_eq!(recent.query(Level::TRACE, usize::MAX).len(), RECENT_CAPACITY);
+        assert!(recent.query(Level::INFO, 10).is_empty());
+        assert_eq!(recent.query(Level::DEBUG, 1)[0].message, format!("Statement {}", RECENT_CAPACITY - 1));
+    }
+}
@@ -1,33 +1,197 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
 use pgshift_lib::commands;
+use pgshift_lib::cancel::OperationRegistry;
+use pgshift_lib::db::pool::{load_pool_settings, PoolManager};
+use pgshift_lib::db::retry::{load_retry_policy, set_retry_policy};
+use pgshift_lib::db::tunnel;
+use pgshift_lib::diff::store::ReportStore;
+use pgshift_lib::logging;
+use pgshift_lib::schedule::jobs::run_scheduler;
+use pgshift_lib::server::http::{run_server, DEFAULT_PORT};
 
 fn main() {
+    if let Err(e) = logging::init() {
+        eprintln!("Logging to files disabled: {}", e);
+    }
+    
+    // Headless mode: serve the command surface over local JSON-RPC instead of opening the UI
+    let args: Vec<String> = std::env::args().collect();
+    if args.iter().any(|a| a == "--server") {
+        std::process::exit(match run_headless_server(&args) {
+            Ok(()) => 0,
+            Err(e) => {
+                eprintln!("{}", e);
+                1
+            }
+        });
+    }
+    
+    // Pools are shared between commands for the life of the app
+    let base_path = commands::default_migrations_dir();
+    let pool_settings = base_path.clone()
+        .and_then(|base_path| load_pool_settings(&base_path))
+        .unwrap_or_default();
+    if let Ok(policy) = base_path.and_then(|base_path| load_retry_policy(&base_path)) {
+        set_retry_policy(policy);
+    }
+    let pools = PoolManager::new(pool_settings);
+    pools.install();
+    
     tauri::Builder::default()
         .plugin(tauri_plugin_fs::init())
         .plugin(tauri_plugin_dialog::init())
+        .manage(ReportStore::new())
+        .manage(OperationRegistry::new())
+        .manage(pools.clone())
+        .setup(|_app| {
+            // Run scheduled comparisons in the background while the app is open
+            match commands::default_migrations_dir() {
+                Ok(base_path) => {
+                    tauri::async_runtime::spawn(run_scheduler(base_path));
+                }
+                Err(e) => tracing::warn!("Scheduled comparisons disabled: {}", e),
+            }
+            Ok(())
+        })
         .invoke_handler(tauri::generate_handler![
             commands::test_connection,
             commands::introspect,
+            commands::cancel_operation,
             commands::diff,
+            commands::compare_diff_reports,
             commands::render_migration,
+            commands::render_dual_write_migration,
+            commands::compare,
+            commands::check_same_database,
+            commands::preflight_locales,
+            // Paged diff reports
+            commands::diff_stored,
+            commands::get_diff_items,
+            commands::release_diff_report,
+            commands::acknowledge_diff_item,
+            commands::resolve_enum_reorder,
+            commands::render_stored_migration,
+            // Cluster-level role comparison
+            commands::compare_roles,
+            commands::render_role_migration,
+            commands::estimate_data_loss,
+            commands::plan_migration,
+            commands::analyze_lock_impact,
             commands::apply_migration,
+            commands::apply_migration_multi,
+            commands::get_migration_status,
+            commands::apply_pending,
+            commands::rollback_migration,
+            commands::rollback_last,
+            commands::clone_schema,
+            commands::get_operation_progress,
+            commands::list_apply_history,
+            commands::get_audit_log,
+            commands::get_recent_logs,
             commands::get_migrations_dir,
             // Database browser commands
             commands::get_database_info,
+            commands::get_server_capabilities,
+            commands::list_databases,
             commands::get_table_data,
             commands::execute_query,
             // Migration export
             commands::export_migration,
             commands::list_migrations,
+            commands::squash_migrations,
+            commands::export_data_dictionary,
+            commands::export_diff_report,
+            commands::export_liquibase_changelog,
+            commands::export_dbml,
+            commands::export_mermaid,
+            commands::export_version_mermaid,
+            commands::schema_graph,
+            commands::replay_migrations,
+            commands::introspect_sql_file,
+            commands::import_prisma_schema,
+            commands::import_diesel_schema,
+            commands::load_schema_file,
+            commands::export_schema_file,
+            commands::dump_schema_sql,
             // Schema versioning
             commands::save_schema_version,
             commands::list_schema_versions,
+            commands::search_schema_versions,
             commands::get_schema_version,
             commands::delete_schema_version,
             commands::compare_schema_versions,
             commands::compare_version_with_live,
+            // Read-only safe mode
+            commands::get_safe_mode,
+            commands::set_safe_mode,
+            // Default schema of each connection
+            commands::get_schema_filter,
+            commands::save_schema_filter,
+            commands::list_connections,
+            commands::save_connection,
+            commands::delete_connection,
+            commands::get_saved_connection_string,
+            commands::close_connection,
+            commands::get_pool_settings,
+            commands::save_pool_settings,
+            commands::get_retry_policy,
+            commands::save_retry_policy,
+            commands::get_webhooks,
+            commands::save_webhooks,
+            commands::test_webhook,
+            // Backup of app data
+            commands::export_app_data,
+            commands::import_app_data,
+            // Project ignore rules
+            commands::get_ignore_config,
+            // Named environments
+            commands::list_environments,
+            commands::save_environment,
+            commands::delete_environment,
+            // Snapshot and history storage backend
+            commands::get_storage_config,
+            commands::save_storage_config,
+            commands::storage_report,
+            commands::get_retention_settings,
+            commands::save_retention_settings,
+            commands::compact_storage,
+            // Scheduled comparisons
+            commands::save_scheduled_comparison,
+            commands::list_scheduled_comparisons,
+            commands::delete_scheduled_comparison,
+            commands::run_scheduled_comparison_now,
         ])
-        .run(tauri::generate_context!())
-        .expect("error while running tauri application");
+        .build(tauri::generate_context!())
+        .expect("error while building tauri application")
+        .run(|_app, event| {
+            if let tauri::RunEvent::Exit = event {
+                tauri::async_runtime::block_on(pools.close_all());
+                tunnel::close_tunnels();
+            }
+        });
+}
+
+/// Run `pgshift --server [--port N]`.
+///
+/// The bearer token is read from `PGSHIFT_RPC_TOKEN`; when unset a random one
+/// is generated and printed.
+fn run_headless_server(args: &[String]) -> Result<(), String> {
+    let port = match args.iter().position(|a| a == "--port") {
+        Some(i) => args.get(i + 1)
+            .and_then(|p| p.parse::<u16>().ok())
+            .ok_or("--port needs a port number")?,
+        None => DEFAULT_PORT,
+    };
+    
+    let token = match std::env::var("PGSHIFT_RPC_TOKEN") {
+        Ok(token) if !token.is_empty() => token,
+        _ => {
+            let token = uuid::Uuid::new_v4().simple().to_string();
+            eprintln!("Bearer token: {}", token);
+            token
+        }
+    };
+    
+    run_server(port, &token)
 }
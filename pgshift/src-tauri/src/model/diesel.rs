@@ -0,0 +1,599 @@
+//! Diesel `schema.rs` importer.
+//!
+//! Reads the `table!` and `joinable!` macros `diesel print-schema` writes
+//! into a schema model, so the ORM definitions of a Rust project can be
+//! compared against a database like any other source. A `schema.rs` holds
+//! columns, their SQL types and nullability, primary keys and foreign keys
+//! only: defaults, indexes, unique and check constraints are not part of it.
+
+use std::collections::HashMap;
+use std::fs;
+use crate::ddl::parse::{truncate_identifier, ParsedSchema};
+use super::schema::{Column, Constraint, ForeignKey, Table};
+
+/// Doc comment line Diesel ends the docs it generates with.
+const GENERATED_DOC: &str = "(Automatically generated by Diesel.)";
+
+/// Read the Diesel schema at `path`.
+pub fn load_diesel_schema(path: &str) -> Result<ParsedSchema, String> {
+    let content = fs::read_to_string(path)
+        .map_err(|e| format!("Failed to read {}: {}", path, e))?;
+    parse_diesel_schema(&content)
+}
+
+/// Build a schema model from the text of a Diesel `schema.rs`.
+///
+/// Custom SQL types, which are named but not defined, columns of types that
+/// cannot be mapped and joins to tables the file does not declare are
+/// reported as warnings.
+pub fn parse_diesel_schema(text: &str) -> Result<ParsedSchema, String> {
+    let mut parser = Parser { tokens: tokenize(text)?, pos: 0 };
+    let mut tables = Vec::new();
+    let mut joins = Vec::new();
+    let mut custom_types = HashMap::new();
+    while let Some(token) = parser.next() {
+        match token {
+            Token::Ident(name) if name == "table" && parser.eat('!') => tables.push(parser.table()?),
+            Token::Ident(name) if name == "joinable" && parser.eat('!') => joins.push(parser.joinable()?),
+            Token::Ident(name) if name == "postgres_type" && parser.eat('(') => {
+                let (struct_name, type_name) = parser.postgres_type()?;
+                custom_types.insert(struct_name, type_name);
+            }
+            _ => {}
+        }
+    }
+
+    let mut parsed = ParsedSchema::default();
+    let mut type_names: Vec<&String> = custom_types.values().collect();
+    type_names.sort();
+    for type_name in type_names {
+        parsed.warnings.push(format!(
+            "type {}: schema.rs does not define custom types; ignore `enum` or compare it separately",
+            type_name
+        ));
+    }
+    for block in &tables {
+        let table = build_table(block, &custom_types, &mut parsed.warnings);
+        parsed.schema.tables.push(table);
+    }
+    for join in &joins {
+        let child = tables.iter().position(|t| t.rust_name == join.child);
+        let parent = tables.iter().find(|t| t.rust_name == join.parent);
+        let (Some(child), Some(parent)) = (child, parent) else {
+            parsed.warnings.push(format!(
+                "joinable!({} -> {}): table {} is not declared",
+                join.child, join.parent, if child.is_none() { &join.child } else { &join.parent }
+            ));
+            continue;
+        };
+        let table = &mut parsed.schema.tables[child];
+        let column = tables[child].column_name(&join.column);
+        table.foreign_keys.push(ForeignKey {
+            name: truncate_identifier(&format!("{}_{}_fkey", table.name, column)),
+            columns: vec![column],
+            referenced_table: parent.name.clone(),
+            referenced_columns: parent.primary_key.iter().map(|k| parent.column_name(k)).collect(),
+            match_type: "SIMPLE".to_string(),
+            on_delete: "NO ACTION".to_string(),
+            on_update: "NO ACTION".to_string(),
+            deferrable: false,
+            initially_deferred: false,
+            validated: true,
+        });
+    }
+
+    Ok(parsed)
+}
+
+fn build_table(block: &TableBlock, custom_types: &HashMap<String, String>, warnings: &mut Vec<String>) -> Table {
+    let mut table = Table {
+        name: block.name.clone(),
+        columns: Vec::new(),
+        primary_key: None,
+        unique_constraints: Vec::new(),
+        indexes: Vec::new(),
+        rls_enabled: false,
+        rls_forced: false,
+        policies: Vec::new(),
+        triggers: Vec::new(),
+        foreign_keys: Vec::new(),
+        comment: block.comment.clone(),
+        inherits: Vec::new(),
+        unlogged: false,
+        storage_parameters: Vec::new(),
+    };
+
+    for field in &block.columns {
+        let max_length = field.attributes.get("max_length").map(String::as_str);
+        match column_type(&field.sql_type, max_length, custom_types) {
+            Ok((data_type, is_nullable)) => table.columns.push(Column {
+                name: field.name.clone(),
+                data_type,
+                is_nullable,
+                default_value: None,
+                ordinal_position: table.columns.len() as i32 + 1,
+                generation_expression: None,
+                comment: field.comment.clone(),
+                inherited: false,
+                storage: None,
+                statistics_target: None,
+            }),
+            Err(e) => warnings.push(format!("{}.{}: {}", block.rust_name, field.rust_name, e)),
+        }
+    }
+
+    if !block.primary_key.is_empty() {
+        table.primary_key = Some(Constraint {
+            name: truncate_identifier(&format!("{}_pkey", block.name)),
+            constraint_type: "PRIMARY KEY".to_string(),
+            columns: block.primary_key.iter().map(|k| block.column_name(k)).collect(),
+        });
+    }
+    table
+}
+
+/// Data type of a column of `sql_type` as PostgreSQL reports it, and whether
+/// the column is nullable.
+fn column_type(
+    sql_type: &SqlType,
+    max_length: Option<&str>,
+    custom_types: &HashMap<String, String>,
+) -> Result<(String, bool), String> {
+    match sql_type.name.as_str() {
+        "Nullable" => Ok((column_type(sql_type.argument()?, max_length, custom_types)?.0, true)),
+        "Array" => {
+            let mut element = sql_type.argument()?;
+            if element.name == "Nullable" {
+                element = element.argument()?;
+            }
+            let (_, udt_name) = base_type(&element.name, None, custom_types)?;
+            Ok((format!("{}[]", udt_name), false))
+        }
+        name => Ok((base_type(name, max_length, custom_types)?.0, false)),
+    }
+}
+
+/// Data type and type name (`udt_name`) of the Diesel SQL type `name`.
+fn base_type(name: &str, max_length: Option<&str>, custom_types: &HashMap<String, String>) -> Result<(String, String), String> {
+    if let Some(type_name) = custom_types.get(name) {
+        return Ok((type_name.clone(), type_name.clone()));
+    }
+    let (data_type, udt_name) = match name {
+        "SmallInt" | "Int2" => ("smallint", "int2"),
+        "Integer" | "Int4" => ("integer", "int4"),
+        "BigInt" | "Int8" => ("bigint", "int8"),
+        "Float" | "Float4" => ("real", "float4"),
+        "Double" | "Float8" => ("double precision", "float8"),
+        // The precision is not part of schema.rs
+        "Numeric" | "Decimal" => ("numeric", "numeric"),
+        "Text" => ("text", "text"),
+        "VarChar" | "Varchar" => {
+            let data_type = max_length.map_or("varchar".to_string(), |n| format!("varchar({})", n));
+            return Ok((data_type, "varchar".to_string()));
+        }
+        "Bpchar" | "Char" => return Ok((format!("char({})", max_length.unwrap_or("1")), "bpchar".to_string())),
+        "CChar" => ("\"char\"", "char"),
+        "Bool" => ("boolean", "bool"),
+        "Date" => ("date", "date"),
+        "Time" => ("time without time zone", "time"),
+        "Timestamp" => ("timestamp without time zone", "timestamp"),
+        "Timestamptz" => ("timestamp with time zone", "timestamptz"),
+        "Interval" => ("interval", "interval"),
+        "Uuid" => ("uuid", "uuid"),
+        "Json" => ("json", "json"),
+        "Jsonb" => ("jsonb", "jsonb"),
+        "Bytea" | "Binary" => ("bytea", "bytea"),
+        "Inet" => ("inet", "inet"),
+        "Cidr" => ("cidr", "cidr"),
+        "MacAddr" => ("macaddr", "macaddr"),
+        "MacAddr8" => ("macaddr8", "macaddr8"),
+        "Money" => ("money", "money"),
+        "Oid" => ("oid", "oid"),
+        "Int4range" => ("int4range", "int4range"),
+        "Int8range" => ("int8range", "int8range"),
+        "Numrange" => ("numrange", "numrange"),
+        "Tsrange" => ("tsrange", "tsrange"),
+        "Tstzrange" => ("tstzrange", "tstzrange"),
+        "Daterange" => ("daterange", "daterange"),
+        // From diesel_full_text_search and diesel-citext
+        "Tsvector" | "TsVector" => ("tsvector", "tsvector"),
+        "Tsquery" | "TsQuery" => ("tsquery", "tsquery"),
+        "Citext" => ("citext", "citext"),
+        other => return Err(format!("SQL type {} is not supported", other)),
+    };
+    Ok((data_type.to_string(), udt_name.to_string()))
+}
+
+/// A `table!` block.
+struct TableBlock {
+    /// Name of the table module in Rust.
+    rust_name: String,
+    /// Name of the table in the database.
+    name: String,
+    comment: Option<String>,
+    /// Rust names of the primary key columns.
+    primary_key: Vec<String>,
+    columns: Vec<ColumnLine>,
+}
+
+impl TableBlock {
+    /// Column of the Rust column `rust_name`; the Rust name when unknown.
+    fn column_name(&self, rust_name: &str) -> String {
+        self.columns.iter()
+            .find(|c| c.rust_name == rust_name)
+            .map_or(rust_name, |c| c.name.as_str())
+            .to_string()
+    }
+}
+
+/// A `name -> Type` line of a `table!` block.
+struct ColumnLine {
+    rust_name: String,
+    name: String,
+    comment: Option<String>,
+    sql_type: SqlType,
+    attributes: HashMap<String, String>,
+}
+
+/// A SQL type path such as `Nullable<Array<Text>>`, by last segment.
+struct SqlType {
+    name: String,
+    arguments: Vec<SqlType>,
+}
+
+impl SqlType {
+    fn argument(&self) -> Result<&SqlType, String> {
+        self.arguments.first().ok_or_else(|| format!("{} needs a type argument", self.name))
+    }
+}
+
+/// A `joinable!(child -> parent (column))` line.
+struct Join {
+    child: String,
+    parent: String,
+    column: String,
+}
+
+/// `#[name = value]` attributes and `///` docs before an item.
+#[derive(Default)]
+struct Attributes {
+    values: HashMap<String, String>,
+    docs: Vec<String>,
+}
+
+impl Attributes {
+    /// Docs written by hand; Diesel's generated docs are not comments.
+    fn comment(&self) -> Option<String> {
+        if self.docs.is_empty() || self.docs.iter().any(|d| d == GENERATED_DOC) {
+            return None;
+        }
+        Some(self.docs.join("\n"))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Ident(String),
+    Str(String),
+    Num(String),
+    Doc(String),
+    Punct(char),
+}
+
+fn tokenize(text: &str) -> Result<Vec<Token>, String> {
+    let chars: Vec<char> = text.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        if c.is_whitespace() {
+            i += 1;
+        } else if c == '/' && chars.get(i + 1) == Some(&'/') {
+            let end = chars[i..].iter().position(|c| *c == '\n').map_or(chars.len(), |n| i + n);
+            let line: String = chars[i..end].iter().collect();
+            if let Some(doc) = line.strip_prefix("///").filter(|d| !d.starts_with('/')) {
+                tokens.push(Token::Doc(doc.trim().to_string()));
+            }
+            i = end;
+        } else if c == '/' && chars.get(i + 1) == Some(&'*') {
+            let rest: String = chars[i + 2..].iter().collect();
+            let end = rest.find("*/").ok_or("Unterminated block comment")?;
+            i += 2 + rest[..end].chars().count() + 2;
+        } else if c == '"' {
+            let mut value = String::new();
+            i += 1;
+            while i < chars.len() && chars[i] != '"' {
+                if chars[i] == '\\' {
+                    i += 1;
+                }
+                if let Some(c) = chars.get(i) {
+                    value.push(*c);
+                }
+                i += 1;
+            }
+            if i >= chars.len() {
+                return Err("Unterminated string".to_string());
+            }
+            i += 1;
+            tokens.push(Token::Str(value));
+        } else if c.is_ascii_digit() {
+            let start = i;
+            while i < chars.len() && chars[i].is_ascii_alphanumeric() {
+                i += 1;
+            }
+            tokens.push(Token::Num(chars[start..i].iter().collect()));
+        } else if c.is_alphabetic() || c == '_' {
+            // Raw identifiers such as r#type
+            if c == 'r' && chars.get(i + 1) == Some(&'#') {
+                i += 2;
+            }
+            let start = i;
+            while i < chars.len() && (chars[i].is_alphanumeric() || chars[i] == '_') {
+                i += 1;
+            }
+            tokens.push(Token::Ident(chars[start..i].iter().collect()));
+        } else {
+            tokens.push(Token::Punct(c));
+            i += 1;
+        }
+    }
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+
+    /// Consume the punctuation `c` if it is next.
+    fn eat(&mut self, c: char) -> bool {
+        if self.peek() == Some(&Token::Punct(c)) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn punct(&mut self, expected: char) -> Result<(), String> {
+        match self.next() {
+            Some(Token::Punct(c)) if c == expected => Ok(()),
+            other => Err(format!("Expected '{}', found {:?}", expected, other)),
+        }
+    }
+
+    fn ident(&mut self, what: &str) -> Result<String, String> {
+        match self.next() {
+            Some(Token::Ident(name)) => Ok(name),
+            other => Err(format!("Expected {}, found {:?}", what, other)),
+        }
+    }
+
+    /// Skip tokens up to and including the bracket closing one already consumed.
+    fn skip_group(&mut self, open: char, close: char) -> Result<(), String> {
+        let mut depth = 1;
+        while depth > 0 {
+            match self.next() {
+                Some(Token::Punct(c)) if c == open => depth += 1,
+                Some(Token::Punct(c)) if c == close => depth -= 1,
+                Some(_) => {}
+                None => return Err(format!("Expected '{}'", close)),
+            }
+        }
+        Ok(())
+    }
+
+    fn attributes(&mut self) -> Result<Attributes, String> {
+        let mut attributes = Attributes::default();
+        loop {
+            match self.peek() {
+                Some(Token::Doc(doc)) => {
+                    attributes.docs.push(doc.clone());
+                    self.pos += 1;
+                }
+                Some(Token::Punct('#')) => {
+                    self.pos += 1;
+                    self.punct('[')?;
+                    let name = self.ident("attribute name")?;
+                    if self.eat('=') {
+                        match self.next() {
+                            Some(Token::Str(value) | Token::Num(value)) => {
+                                attributes.values.insert(name, value);
+                            }
+                            other => return Err(format!("Expected value of {}, found {:?}", name, other)),
+                        }
+                    }
+                    self.skip_group('[', ']')?;
+                }
+                _ => return Ok(attributes),
+            }
+        }
+    }
+
+    /// The body of `table! { ... }`.
+    fn table(&mut self) -> Result<TableBlock, String> {
+        self.punct('{')?;
+        while self.peek() == Some(&Token::Ident("use".to_string())) {
+            while !self.eat(';') {
+                self.next().ok_or("Expected ';'")?;
+            }
+        }
+        let attributes = self.attributes()?;
+        let mut rust_name = self.ident("table name")?;
+        // Tables of other schemas are written schema.table
+        if self.eat('.') {
+            rust_name = self.ident("table name")?;
+        }
+
+        let mut primary_key = Vec::new();
+        self.punct('(')?;
+        while !self.eat(')') {
+            primary_key.push(self.ident("primary key column")?);
+            self.eat(',');
+        }
+
+        let mut columns = Vec::new();
+        self.punct('{')?;
+        while !self.eat('}') {
+            let attributes = self.attributes()?;
+            let rust_name = self.ident("column name")?;
+            self.punct('-')?;
+            self.punct('>')?;
+            let sql_type = self.sql_type()?;
+            self.eat(',');
+            columns.push(ColumnLine {
+                name: attributes.values.get("sql_name").cloned().unwrap_or_else(|| rust_name.clone()),
+                comment: attributes.comment(),
+                rust_name,
+                sql_type,
+                attributes: attributes.values,
+            });
+        }
+        self.punct('}')?;
+
+        Ok(TableBlock {
+            name: attributes.values.get("sql_name").cloned().unwrap_or_else(|| rust_name.clone()),
+            comment: attributes.comment(),
+            rust_name,
+            primary_key,
+            columns,
+        })
+    }
+
+    fn sql_type(&mut self) -> Result<SqlType, String> {
+        let mut name = self.ident("SQL type")?;
+        while self.eat(':') {
+            self.punct(':')?;
+            name = self.ident("SQL type")?;
+        }
+        let mut arguments = Vec::new();
+        if self.eat('<') {
+            while !self.eat('>') {
+                arguments.push(self.sql_type()?);
+                self.eat(',');
+            }
+        }
+        Ok(SqlType { name, arguments })
+    }
+
+    /// The arguments of `joinable!(child -> parent (column))`.
+    fn joinable(&mut self) -> Result<Join, String> {
+        self.punct('(')?;
+        let child = self.ident("table name")?;
+        self.punct('-')?;
+        self.punct('>')?;
+        let parent = self.ident("table name")?;
+        self.punct('(')?;
+        let column = self.ident("column name")?;
+        self.punct(')')?;
+        self.punct(')')?;
+        Ok(Join { child, parent, column })
+    }
+
+    /// The arguments of `postgres_type(name = "...")` and the name of the
+    /// struct it is on; the database name of a custom SQL type.
+    fn postgres_type(&mut self) -> Result<(String, String), String> {
+        let mut type_name = None;
+        while !self.eat(')') {
+            let key = self.ident("postgres_type argument")?;
+            self.punct('=')?;
+            let value = self.next();
+            if let (true, Some(Token::Str(value))) = (key == "name", value) {
+                type_name = Some(value);
+            }
+            self.eat(',');
+        }
+        let type_name = type_name.ok_or("postgres_type needs a name")?;
+        while self.next() != Some(Token::Ident("struct".to_string())) {
+            if self.peek().is_none() {
+                return Err(format!("No struct for postgres_type {}", type_name));
+            }
+        }
+        Ok((self.ident("struct name")?, type_name))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SCHEMA: &str = r#"
+// @generated automatically by Diesel CLI.
+
+pub mod sql_types {
+    #[derive(diesel::query_builder::QueryId, diesel::sql_types::SqlType)]
+    #[diesel(postgres_type(name = "mood"))]
+    pub struct Mood;
+}
+
+diesel::table! {
+    use diesel::sql_types::*;
+    use super::sql_types::Mood;
+
+    /// Posts of a user
+    posts (id) {
+        id -> Int4,
+        user_id -> Int4,
+        #[max_length = 200]
+        title -> Varchar,
+        #[sql_name = "type"]
+        type_ -> Nullable<Text>,
+        tags -> Array<Nullable<Text>>,
+        mood -> Mood,
+        shape -> Geometry,
+    }
+}
+
+diesel::table! {
+    /// Representation of the `users` table.
+    ///
+    /// (Automatically generated by Diesel.)
+    users (id) {
+        id -> diesel::sql_types::Int8,
+        created_at -> Timestamptz,
+    }
+}
+
+diesel::joinable!(posts -> users (user_id));
+diesel::joinable!(posts -> authors (user_id));
+diesel::allow_tables_to_appear_in_same_query!(posts, users);
+"#;
+
+    #[test]
+    fn test_parse_diesel_schema() {
+        let parsed = parse_diesel_schema(SCHEMA).unwrap();
+        assert_eq!(parsed.warnings, [
+            "type mood: schema.rs does not define custom types; ignore `enum` or compare it separately",
+            "posts.shape: SQL type Geometry is not supported",
+            "joinable!(posts -> authors): table authors is not declared",
+        ]);
+
+        let posts = parsed.schema.find_table("posts").unwrap();
+        assert_eq!(posts.comment.as_deref(), Some("Posts of a user"));
+        assert_eq!(posts.primary_key.as_ref().unwrap().name, "posts_pkey");
+        assert_eq!(posts.find_column("title").unwrap().data_type, "varchar(200)");
+        let kind = posts.find_column("type").unwrap();
+        assert_eq!((kind.data_type.as_str(), kind.is_nullable), ("text", true));
+        assert_eq!(posts.find_column("tags").unwrap().data_type, "text[]");
+        assert_eq!(posts.find_column("mood").unwrap().data_type, "mood");
+        let foreign_key = &posts.foreign_keys[0];
+        assert_eq!(foreign_key.name, "posts_user_id_fkey");
+        assert_eq!((foreign_key.referenced_table.as_str(), foreign_key.referenced_columns.as_slice()), ("users", &["id".to_string()][..]));
+
+        let users = parsed.schema.find_table("users").unwrap();
+        assert_eq!(users.comment, None);
+        assert_eq!(users.find_column("id").unwrap().data_type, "bigint");
+        assert_eq!(users.find_column("created_at").unwrap().data_type, "timestamp with time zone");
+    }
+}
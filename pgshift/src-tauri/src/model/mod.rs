@@ -1,3 +1,7 @@
 //! Schema model definitions.
 
 pub mod schema;
+pub mod schema_file;
+pub mod prisma;
+pub mod diesel;
+pub mod role;
@@ -0,0 +1,898 @@
+//! Prisma schema importer.
+//!
+//! Reads the models, enums, keys, indexes and relations of a `schema.prisma`
+//! into a schema model, with the types, defaults and names `prisma migrate`
+//! gives them on PostgreSQL, so a Prisma schema can be compared against a
+//! database like any other source. Defaults Prisma generates in the client
+//! (`uuid()`, `cuid()`, `@updatedAt`) have no database counterpart and are
+//! left out.
+
+use std::collections::HashMap;
+use std::fs;
+use crate::ddl::parse::{truncate_identifier, ParsedSchema};
+use super::schema::{Column, Constraint, EnumType, ForeignKey, Index, IndexColumnOrder, Table};
+
+/// Read the Prisma schema at `path`.
+pub fn load_prisma_schema(path: &str) -> Result<ParsedSchema, String> {
+    let content = fs::read_to_string(path)
+        .map_err(|e| format!("Failed to read {}: {}", path, e))?;
+    parse_prisma_schema(&content)
+}
+
+/// Build a schema model from the text of a Prisma schema.
+///
+/// Blocks that have no PostgreSQL table, such as views and composite types,
+/// are reported as warnings.
+pub fn parse_prisma_schema(text: &str) -> Result<ParsedSchema, String> {
+    let blocks = Parser { tokens: tokenize(text)?, pos: 0 }.blocks()?;
+    let mut parsed = ParsedSchema::default();
+
+    let mut enums = HashMap::new();
+    let mut models = HashMap::new();
+    for block in &blocks {
+        match block.kind.as_str() {
+            "enum" => {
+                enums.insert(block.name.as_str(), EnumNames {
+                    name: mapped_name(&block.attributes, "@map").unwrap_or(&block.name).to_string(),
+                    values: block.fields.iter()
+                        .map(|v| (v.name.clone(), mapped_name(&v.attributes, "map").unwrap_or(&v.name).to_string()))
+                        .collect(),
+                });
+            }
+            "model" if !has_attribute(&block.attributes, "@ignore") => {
+                models.insert(block.name.as_str(), block);
+            }
+            "model" | "datasource" | "generator" => {}
+            other => parsed.warnings.push(format!("{} {}: {} blocks are not imported", other, block.name, other)),
+        }
+    }
+    let names = Names { enums: &enums, models: &models };
+
+    for block in blocks.iter().filter(|b| b.kind == "enum") {
+        let names = &enums[block.name.as_str()];
+        parsed.schema.enums.push(EnumType {
+            name: names.name.clone(),
+            values: names.values.iter().map(|(_, value)| value.clone()).collect(),
+        });
+    }
+    for block in blocks.iter().filter(|b| b.kind == "model" && models.contains_key(b.name.as_str())) {
+        let table = names.build_table(block, &mut parsed.warnings);
+        parsed.schema.tables.push(table);
+    }
+
+    Ok(parsed)
+}
+
+/// Database names of an enum type and of its values, in order.
+struct EnumNames {
+    name: String,
+    values: Vec<(String, String)>,
+}
+
+/// The enums and models of a schema, by Prisma name.
+struct Names<'a> {
+    enums: &'a HashMap<&'a str, EnumNames>,
+    models: &'a HashMap<&'a str, &'a Block>,
+}
+
+impl Names<'_> {
+    fn table_name(&self, model: &Block) -> String {
+        mapped_name(&model.attributes, "@map").unwrap_or(&model.name).to_string()
+    }
+
+    /// Column of the field `field` of `model`; the field name when unknown.
+    fn column_name(&self, model: &Block, field: &str) -> String {
+        model.fields.iter()
+            .find(|f| f.name == field)
+            .and_then(|f| mapped_name(&f.attributes, "map"))
+            .unwrap_or(field)
+            .to_string()
+    }
+
+    fn build_table(&self, model: &Block, warnings: &mut Vec<String>) -> Table {
+        let table_name = self.table_name(model);
+        let mut table = Table {
+            name: table_name.clone(),
+            columns: Vec::new(),
+            primary_key: None,
+            unique_constraints: Vec::new(),
+            indexes: Vec::new(),
+            rls_enabled: false,
+            rls_forced: false,
+            policies: Vec::new(),
+            triggers: Vec::new(),
+            foreign_keys: Vec::new(),
+            comment: None,
+            inherits: Vec::new(),
+            unlogged: false,
+            storage_parameters: Vec::new(),
+        };
+
+        for field in model.fields.iter().filter(|f| !has_attribute(&f.attributes, "ignore")) {
+            if let Some(target) = self.models.get(field.type_name.as_str()) {
+                self.add_relation(&mut table, model, field, target, warnings);
+                continue;
+            }
+            let column_name = self.column_name(model, &field.name);
+            match self.column(&table_name, &column_name, field, table.columns.len() as i32 + 1) {
+                Ok(column) => table.columns.push(column),
+                Err(e) => {
+                    warnings.push(format!("{}.{}: {}", model.name, field.name, e));
+                    continue;
+                }
+            }
+
+            if let Some(id) = attribute(&field.attributes, "id") {
+                table.primary_key = Some(Constraint {
+                    name: map_argument(id).unwrap_or_else(|| truncate_identifier(&format!("{}_pkey", table_name))),
+                    constraint_type: "PRIMARY KEY".to_string(),
+                    columns: vec![column_name.clone()],
+                });
+            }
+            if let Some(unique) = attribute(&field.attributes, "unique") {
+                let columns = vec![column_name.clone()];
+                table.indexes.push(index(&table_name, columns, Vec::new(), true, unique, "key"));
+            }
+        }
+
+        for attr in &model.attributes {
+            let fields = || field_list(attr.argument(0, "fields"));
+            let columns = |fields: &[(String, Vec<Arg>)]| -> Vec<String> {
+                fields.iter().map(|(f, _)| self.column_name(model, f)).collect()
+            };
+            match attr.name.as_str() {
+                "@id" => {
+                    let columns = columns(&fields());
+                    for column in table.columns.iter_mut().filter(|c| columns.contains(&c.name)) {
+                        column.is_nullable = false;
+                    }
+                    table.primary_key = Some(Constraint {
+                        name: map_argument(attr).unwrap_or_else(|| truncate_identifier(&format!("{}_pkey", table_name))),
+                        constraint_type: "PRIMARY KEY".to_string(),
+                        columns,
+                    });
+                }
+                "@unique" | "@index" => {
+                    let fields = fields();
+                    let suffix = if attr.name == "@unique" { "key" } else { "idx" };
+                    table.indexes.push(index(&table_name, columns(&fields), fields, attr.name == "@unique", attr, suffix));
+                }
+                "@map" | "@schema" => {}
+                other => warnings.push(format!("{}: attribute {} is not imported", model.name, other)),
+            }
+        }
+
+        table
+    }
+
+    /// Column of the scalar or enum field `field`.
+    fn column(&self, table: &str, name: &str, field: &Field, position: i32) -> Result<Column, String> {
+        let native = field.attributes.iter().find(|a| a.name.starts_with("db."));
+        let enum_type = self.enums.get(field.type_name.as_str());
+        let mut data_type = match (native, enum_type) {
+            (Some(native), _) => native_type(native)?,
+            (None, Some(enum_type)) => enum_type.name.clone(),
+            (None, None) => scalar_type(field)?,
+        };
+        if field.list {
+            data_type = format!("{}[]", udt_name(&data_type));
+        }
+
+        let mut column = Column {
+            name: name.to_string(),
+            data_type,
+            // Lists cannot be optional in Prisma but are nullable columns
+            is_nullable: field.optional || field.list,
+            default_value: None,
+            ordinal_position: position,
+            generation_expression: None,
+            comment: None,
+            inherited: false,
+            storage: None,
+            statistics_target: None,
+        };
+        if let Some(default) = attribute(&field.attributes, "default").and_then(|d| d.argument(0, "value")) {
+            if matches!(default, Value::Call(f, _) if f == "autoincrement") {
+                column.default_value = Some(format!(
+                    "nextval('{}'::regclass)",
+                    truncate_identifier(&format!("{}_{}_seq", table, name))
+                ));
+            } else {
+                column.default_value = default_value(default, &column.data_type, enum_type)?;
+            }
+        }
+        Ok(column)
+    }
+
+    /// Add the foreign key of the relation field `field` of `model`, which
+    /// points at `target`.
+    fn add_relation(&self, table: &mut Table, model: &Block, field: &Field, target: &Block, warnings: &mut Vec<String>) {
+        let relation = attribute(&field.attributes, "relation")
+            .filter(|r| r.argument(usize::MAX, "fields").is_some());
+        let Some(relation) = relation else {
+            // The back side of a relation holds no columns; a list on both
+            // sides is an implicit many-to-many relation
+            let back_is_list = target.fields.iter().any(|f| f.type_name == model.name && f.list);
+            if field.list && back_is_list && model.name <= target.name {
+                warnings.push(format!(
+                    "{}.{}: implicit many-to-many relations are not imported; declare the join table as a model",
+                    model.name, field.name
+                ));
+            }
+            return;
+        };
+        let columns: Vec<String> = field_list(relation.argument(usize::MAX, "fields")).iter()
+            .map(|(f, _)| self.column_name(model, f))
+            .collect();
+        let referenced_columns = field_list(relation.argument(usize::MAX, "references")).iter()
+            .map(|(f, _)| self.column_name(target, f))
+            .collect();
+        let action = |name: &str, default: &str| match relation.argument(usize::MAX, name) {
+            Some(Value::Ident(action)) => referential_action(action),
+            _ => default.to_string(),
+        };
+
+        table.foreign_keys.push(ForeignKey {
+            name: map_argument(relation)
+                .unwrap_or_else(|| truncate_identifier(&format!("{}_{}_fkey", table.name, columns.join("_")))),
+            referenced_table: self.table_name(target),
+            referenced_columns,
+            match_type: "SIMPLE".to_string(),
+            // Prisma's defaults: required relations restrict deletes, optional ones clear the key
+            on_delete: action("onDelete", if field.optional { "SET NULL" } else { "RESTRICT" }),
+            on_update: action("onUpdate", "CASCADE"),
+            deferrable: false,
+            initially_deferred: false,
+            validated: true,
+            columns,
+        });
+    }
+}
+
+/// The default of a column of `data_type` as PostgreSQL reports it.
+fn default_value(value: &Value, data_type: &str, enum_type: Option<&EnumNames>) -> Result<Option<String>, String> {
+    Ok(match value {
+        Value::Call(function, args) => match function.as_str() {
+            "now" => Some("CURRENT_TIMESTAMP".to_string()),
+            "dbgenerated" => match args.first().map(|a| &a.value) {
+                Some(Value::Str(expression)) => Some(expression.clone()),
+                _ => None,
+            },
+            // Generated by the Prisma client, not the database
+            "uuid" | "cuid" | "nanoid" | "ulid" => None,
+            other => return Err(format!("default {}() is not supported", other)),
+        },
+        Value::Str(text) => Some(format!("'{}'::{}", text.replace('\'', "''"), cast_type(data_type))),
+        // A negative constant keeps the type of the literal
+        Value::Num(number) if number.starts_with('-') => Some(format!("'{}'::{}", number, literal_type(number))),
+        Value::Num(number) => Some(number.clone()),
+        Value::Ident(word) if word == "true" || word == "false" => Some(word.clone()),
+        Value::Ident(word) => match enum_type {
+            Some(enum_type) => {
+                let value = enum_type.values.iter().find(|(v, _)| v == word).map_or(word, |(_, db)| db);
+                Some(format!("'{}'::{}", value.replace('\'', "''"), quote_type(&enum_type.name)))
+            }
+            None => return Err(format!("default {} is not supported", word)),
+        },
+        Value::List(items) => {
+            let element_type = data_type.trim_end_matches("[]");
+            let elements = items.iter()
+                .map(|item| default_value(item, element_type, enum_type).map(Option::unwrap_or_default))
+                .collect::<Result<Vec<_>, _>>()?;
+            if elements.is_empty() {
+                Some(format!("ARRAY[]::{}", data_type))
+            } else {
+                Some(format!("ARRAY[{}]", elements.join(", ")))
+            }
+        }
+    })
+}
+
+/// Index of `columns`, declared by `@unique`, `@@unique` or `@@index`
+/// `attr`; `fields` holds the arguments of each field, such as `sort`.
+fn index(table: &str, columns: Vec<String>, fields: Vec<(String, Vec<Arg>)>, is_unique: bool, attr: &Attribute, suffix: &str) -> Index {
+    let field_argument = |i: usize, name: &str| fields.get(i).and_then(|(_, args)| {
+        args.iter().find(|a| a.name.as_deref() == Some(name)).map(|a| &a.value)
+    });
+    let column_orders = (0..columns.len())
+        .map(|i| {
+            let descending = matches!(field_argument(i, "sort"), Some(Value::Ident(sort)) if sort == "Desc");
+            IndexColumnOrder { descending, nulls_first: descending }
+        })
+        .collect();
+    let opclasses: Vec<Option<String>> = (0..columns.len())
+        .map(|i| match field_argument(i, "ops") {
+            Some(Value::Ident(ops)) => Some(snake_case(ops)),
+            Some(Value::Call(f, args)) if f == "raw" => raw_argument(args),
+            _ => None,
+        })
+        .collect();
+
+    Index {
+        name: map_argument(attr)
+            .unwrap_or_else(|| truncate_identifier(&format!("{}_{}_{}", table, columns.join("_"), suffix))),
+        is_unique,
+        index_type: match attr.argument(usize::MAX, "type") {
+            Some(Value::Ident(method)) => method.to_lowercase(),
+            _ => "btree".to_string(),
+        },
+        predicate: match attr.argument(usize::MAX, "where") {
+            Some(Value::Call(f, args)) if f == "raw" => raw_argument(args),
+            _ => None,
+        },
+        column_orders,
+        include_columns: Vec::new(),
+        column_opclasses: if opclasses.iter().all(Option::is_none) { Vec::new() } else { opclasses },
+        storage_parameters: Vec::new(),
+        columns,
+    }
+}
+
+/// Column type of a field with a native type attribute such as `@db.VarChar(255)`.
+fn native_type(attr: &Attribute) -> Result<String, String> {
+    let number = |i: usize| attr.args.get(i).and_then(|a| match &a.value {
+        Value::Num(n) => Some(n.as_str()),
+        _ => None,
+    });
+    let name = attr.name.trim_start_matches("db.");
+    Ok(match name {
+        "Text" => "text".to_string(),
+        "VarChar" => number(0).map_or("varchar".to_string(), |n| format!("varchar({})", n)),
+        "Char" => format!("char({})", number(0).unwrap_or("1")),
+        "SmallInt" => "smallint".to_string(),
+        "Integer" => "integer".to_string(),
+        "BigInt" => "bigint".to_string(),
+        "Oid" => "oid".to_string(),
+        "Real" => "real".to_string(),
+        "DoublePrecision" => "double precision".to_string(),
+        "Decimal" => match (number(0), number(1)) {
+            (Some(p), Some(s)) if s != "0" => format!("numeric({},{})", p, s),
+            (Some(p), _) => format!("numeric({})", p),
+            _ => "numeric".to_string(),
+        },
+        "Money" => "money".to_string(),
+        "Boolean" => "boolean".to_string(),
+        "Uuid" => "uuid".to_string(),
+        "Json" => "json".to_string(),
+        "JsonB" => "jsonb".to_string(),
+        "ByteA" => "bytea".to_string(),
+        "Xml" => "xml".to_string(),
+        "Inet" => "inet".to_string(),
+        "Citext" => "citext".to_string(),
+        "Date" => "date".to_string(),
+        "Timestamp" => "timestamp without time zone".to_string(),
+        "Timestamptz" => "timestamp with time zone".to_string(),
+        "Time" => "time without time zone".to_string(),
+        "Timetz" => "time with time zone".to_string(),
+        "Bit" => "bit".to_string(),
+        "VarBit" => "bit varying".to_string(),
+        other => return Err(format!("native type @db.{} is not supported", other)),
+    })
+}
+
+/// Column type `prisma migrate` creates for a scalar field without a native type.
+fn scalar_type(field: &Field) -> Result<String, String> {
+    Ok(match field.type_name.as_str() {
+        "String" => "text",
+        "Boolean" => "boolean",
+        "Int" => "integer",
+        "BigInt" => "bigint",
+        "Float" => "double precision",
+        "Decimal" => "numeric(65,30)",
+        // TIMESTAMP(3); the precision is not part of the model
+        "DateTime" => "timestamp without time zone",
+        "Json" => "jsonb",
+        "Bytes" => "bytea",
+        "Unsupported" => match &field.type_argument {
+            Some(data_type) => data_type.as_str(),
+            None => return Err("Unsupported needs a type".to_string()),
+        },
+        other => return Err(format!("type {} is not a model, enum or scalar", other)),
+    }.to_string())
+}
+
+/// Element type name of an array of `data_type`, as introspection reports it.
+fn udt_name(data_type: &str) -> String {
+    let base = data_type.split('(').next().unwrap_or(data_type);
+    match base {
+        "smallint" => "int2",
+        "integer" => "int4",
+        "bigint" => "int8",
+        "real" => "float4",
+        "double precision" => "float8",
+        "boolean" => "bool",
+        "char" => "bpchar",
+        "timestamp with time zone" => "timestamptz",
+        "timestamp without time zone" => "timestamp",
+        "time with time zone" => "timetz",
+        "time without time zone" => "time",
+        other => other,
+    }.to_string()
+}
+
+/// Type a literal default is cast to, as PostgreSQL prints it.
+fn cast_type(data_type: &str) -> String {
+    let base = data_type.split('(').next().unwrap_or(data_type);
+    match base {
+        "varchar" => "character varying".to_string(),
+        "char" => "bpchar".to_string(),
+        other => other.to_string(),
+    }
+}
+
+/// Type PostgreSQL gives the numeric literal `number`.
+fn literal_type(number: &str) -> &'static str {
+    if number.parse::<i32>().is_ok() {
+        "integer"
+    } else if number.parse::<i64>().is_ok() {
+        "bigint"
+    } else {
+        "numeric"
+    }
+}
+
+/// Type name quoted when PostgreSQL would quote it.
+fn quote_type(name: &str) -> String {
+    let plain = name.chars().next().is_some_and(|c| c.is_ascii_lowercase() || c == '_')
+        && name.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_');
+    if plain {
+        name.to_string()
+    } else {
+        format!("\"{}\"", name.replace('"', "\"\""))
+    }
+}
+
+fn referential_action(action: &str) -> String {
+    match action {
+        "Cascade" => "CASCADE",
+        "Restrict" => "RESTRICT",
+        "NoAction" => "NO ACTION",
+        "SetNull" => "SET NULL",
+        "SetDefault" => "SET DEFAULT",
+        other => other,
+    }.to_string()
+}
+
+/// `JsonbPathOps` as `jsonb_path_ops`.
+fn snake_case(name: &str) -> String {
+    let mut snake = String::new();
+    for (i, c) in name.chars().enumerate() {
+        if c.is_ascii_uppercase() && i > 0 {
+            snake.push('_');
+        }
+        snake.push(c.to_ascii_lowercase());
+    }
+    snake
+}
+
+fn raw_argument(args: &[Arg]) -> Option<String> {
+    match args.first().map(|a| &a.value) {
+        Some(Value::Str(text)) => Some(text.clone()),
+        _ => None,
+    }
+}
+
+fn attribute<'a>(attributes: &'a [Attribute], name: &str) -> Option<&'a Attribute> {
+    attributes.iter().find(|a| a.name == name)
+}
+
+fn has_attribute(attributes: &[Attribute], name: &str) -> bool {
+    attribute(attributes, name).is_some()
+}
+
+/// Name given by a `@map("...")` or `@@map("...")` attribute.
+fn mapped_name<'a>(attributes: &'a [Attribute], name: &str) -> Option<&'a str> {
+    match attribute(attributes, name)?.argument(0, "name") {
+        Some(Value::Str(mapped)) => Some(mapped),
+        _ => None,
+    }
+}
+
+/// Database name of a key, index or relation given with `map:`.
+fn map_argument(attr: &Attribute) -> Option<String> {
+    match attr.argument(usize::MAX, "map") {
+        Some(Value::Str(name)) => Some(name.clone()),
+        _ => None,
+    }
+}
+
+/// Field names of a `[a, b(sort: Desc)]` list, with the arguments of each.
+fn field_list(value: Option<&Value>) -> Vec<(String, Vec<Arg>)> {
+    let Some(Value::List(items)) = value else { return Vec::new() };
+    items.iter()
+        .filter_map(|item| match item {
+            Value::Ident(name) => Some((name.clone(), Vec::new())),
+            Value::Call(name, args) => Some((name.clone(), args.clone())),
+            _ => None,
+        })
+        .collect()
+}
+
+// ===================== SYNTAX =====================
+
+/// A `model`, `enum`, `view`, `type`, `datasource` or `generator` block.
+#[derive(Debug)]
+struct Block {
+    kind: String,
+    name: String,
+    /// Fields of a model, or values of an enum.
+    fields: Vec<Field>,
+    /// Block attributes, named with their leading `@`, e.g. `@map`.
+    attributes: Vec<Attribute>,
+}
+
+#[derive(Debug)]
+struct Field {
+    name: String,
+    /// Empty for enum values.
+    type_name: String,
+    /// Database type of `Unsupported("...")`.
+    type_argument: Option<String>,
+    optional: bool,
+    list: bool,
+    attributes: Vec<Attribute>,
+}
+
+#[derive(Debug, Clone)]
+struct Attribute {
+    /// Name without the leading `@`, e.g. `default` or `db.VarChar`.
+    name: String,
+    args: Vec<Arg>,
+}
+
+impl Attribute {
+    /// The argument called `name`, or else the unnamed argument at `position`.
+    fn argument(&self, position: usize, name: &str) -> Option<&Value> {
+        self.args.iter()
+            .find(|a| a.name.as_deref() == Some(name))
+            .or_else(|| self.args.iter().filter(|a| a.name.is_none()).nth(position))
+            .map(|a| &a.value)
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Arg {
+    name: Option<String>,
+    value: Value,
+}
+
+#[derive(Debug, Clone)]
+enum Value {
+    Ident(String),
+    Str(String),
+    Num(String),
+    Call(String, Vec<Arg>),
+    List(Vec<Value>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Ident(String),
+    Str(String),
+    Num(String),
+    /// `@` of a field attribute.
+    At,
+    /// `@@` of a block attribute.
+    AtAt,
+    Newline,
+    Punct(char),
+}
+
+fn tokenize(text: &str) -> Result<Vec<Token>, String> {
+    let mut tokens = Vec::new();
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '\n' => tokens.push(Token::Newline),
+            c if c.is_whitespace() => {}
+            '/' if chars.peek() == Some(&'/') => {
+                while chars.peek().is_some_and(|c| *c != '\n') {
+                    chars.next();
+                }
+            }
+            '@' if chars.peek() == Some(&'@') => {
+                chars.next();
+                tokens.push(Token::AtAt);
+            }
+            '@' => tokens.push(Token::At),
+            '"' => {
+                let mut text = String::new();
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some('\\') => match chars.next() {
+                            Some('n') => text.push('\n'),
+                            Some('t') => text.push('\t'),
+                            Some(escaped) => text.push(escaped),
+                            None => return Err("Unterminated string".to_string()),
+                        },
+                        Some(c) => text.push(c),
+                        None => return Err("Unterminated string".to_string()),
+                    }
+                }
+                tokens.push(Token::Str(text));
+            }
+            c if c.is_ascii_digit() || (c == '-' && chars.peek().is_some_and(|n| n.is_ascii_digit())) => {
+                let mut number = c.to_string();
+                while let Some(n) = chars.next_if(|n| n.is_ascii_alphanumeric() || *n == '.') {
+                    number.push(n);
+                }
+                tokens.push(Token::Num(number));
+            }
+            c if c.is_alphanumeric() || c == '_' => {
+                let mut ident = c.to_string();
+                while let Some(n) = chars.next_if(|n| n.is_alphanumeric() || *n == '_' || *n == '.') {
+                    ident.push(n);
+                }
+                tokens.push(Token::Ident(ident));
+            }
+            other => tokens.push(Token::Punct(other)),
+        }
+    }
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+
+    fn skip_newlines(&mut self) {
+        while self.peek() == Some(&Token::Newline) {
+            self.pos += 1;
+        }
+    }
+
+    fn ident(&mut self, what: &str) -> Result<String, String> {
+        match self.next() {
+            Some(Token::Ident(name)) => Ok(name),
+            other => Err(format!("Expected {}, found {:?}", what, other)),
+        }
+    }
+
+    fn punct(&mut self, expected: char) -> Result<(), String> {
+        match self.next() {
+            Some(Token::Punct(c)) if c == expected => Ok(()),
+            other => Err(format!("Expected '{}', found {:?}", expected, other)),
+        }
+    }
+
+    fn blocks(mut self) -> Result<Vec<Block>, String> {
+        let mut blocks = Vec::new();
+        loop {
+            self.skip_newlines();
+            if self.peek().is_none() {
+                return Ok(blocks);
+            }
+            let kind = self.ident("a block")?;
+            let name = self.ident("a block name")?;
+            self.punct('{')?;
+            blocks.push(self.block(kind, name)?);
+        }
+    }
+
+    fn block(&mut self, kind: String, name: String) -> Result<Block, String> {
+        let mut block = Block { kind, name, fields: Vec::new(), attributes: Vec::new() };
+        // Settings of datasources and generators are not needed
+        if block.kind == "datasource" || block.kind == "generator" {
+            while let Some(token) = self.next() {
+                if token == Token::Punct('}') {
+                    return Ok(block);
+                }
+            }
+            return Err(format!("{} {} is not closed", block.kind, block.name));
+        }
+
+        loop {
+            self.skip_newlines();
+            match self.peek() {
+                Some(Token::Punct('}')) => {
+                    self.pos += 1;
+                    return Ok(block);
+                }
+                Some(Token::AtAt) => {
+                    self.pos += 1;
+                    let mut attribute = self.attribute()?;
+                    attribute.name.insert(0, '@');
+                    block.attributes.push(attribute);
+                }
+                Some(Token::Ident(_)) => {
+                    let field = self.field(block.kind != "enum")
+                        .map_err(|e| format!("{} {}: {}", block.kind, block.name, e))?;
+                    block.fields.push(field);
+                }
+                other => return Err(format!("{} {}: unexpected {:?}", block.kind, block.name, other)),
+            }
+        }
+    }
+
+    /// A field of a model, or a value of an enum when `typed` is false.
+    fn field(&mut self, typed: bool) -> Result<Field, String> {
+        let mut field = Field {
+            name: self.ident("a field")?,
+            type_name: String::new(),
+            type_argument: None,
+            optional: false,
+            list: false,
+            attributes: Vec::new(),
+        };
+        if typed {
+            field.type_name = self.ident("a type")?;
+            if self.peek() == Some(&Token::Punct('(')) {
+                field.type_argument = self.args()?.into_iter().find_map(|a| match a.value {
+                    Value::Str(text) => Some(text),
+                    _ => None,
+                });
+            }
+            if self.peek() == Some(&Token::Punct('?')) {
+                self.pos += 1;
+                field.optional = true;
+            } else if self.peek() == Some(&Token::Punct('[')) {
+                self.pos += 1;
+                self.punct(']')?;
+                field.list = true;
+            }
+        }
+        while self.peek() == Some(&Token::At) {
+            self.pos += 1;
+            field.attributes.push(self.attribute()?);
+        }
+        match self.peek() {
+            None | Some(Token::Newline) | Some(Token::Punct('}')) => Ok(field),
+            other => Err(format!("unexpected {:?} after {}", other, field.name)),
+        }
+    }
+
+    fn attribute(&mut self) -> Result<Attribute, String> {
+        let name = self.ident("an attribute")?;
+        let args = if self.peek() == Some(&Token::Punct('(')) { self.args()? } else { Vec::new() };
+        Ok(Attribute { name, args })
+    }
+
+    fn args(&mut self) -> Result<Vec<Arg>, String> {
+        self.punct('(')?;
+        let mut args = Vec::new();
+        loop {
+            self.skip_newlines();
+            if self.peek() == Some(&Token::Punct(')')) {
+                self.pos += 1;
+                return Ok(args);
+            }
+            let name = match (self.peek(), self.tokens.get(self.pos + 1)) {
+                (Some(Token::Ident(name)), Some(Token::Punct(':'))) => {
+                    let name = name.clone();
+                    self.pos += 2;
+                    Some(name)
+                }
+                _ => None,
+            };
+            args.push(Arg { name, value: self.value()? });
+            self.skip_newlines();
+            match self.next() {
+                Some(Token::Punct(',')) => {}
+                Some(Token::Punct(')')) => return Ok(args),
+                other => return Err(format!("Expected ',' or ')', found {:?}", other)),
+            }
+        }
+    }
+
+    fn value(&mut self) -> Result<Value, String> {
+        match self.next() {
+            Some(Token::Str(text)) => Ok(Value::Str(text)),
+            Some(Token::Num(number)) => Ok(Value::Num(number)),
+            Some(Token::Ident(name)) if self.peek() == Some(&Token::Punct('(')) => {
+                Ok(Value::Call(name, self.args()?))
+            }
+            Some(Token::Ident(name)) => Ok(Value::Ident(name)),
+            Some(Token::Punct('[')) => {
+                let mut items = Vec::new();
+                loop {
+                    self.skip_newlines();
+                    if self.peek() == Some(&Token::Punct(']')) {
+                        self.pos += 1;
+                        return Ok(Value::List(items));
+                    }
+                    items.push(self.value()?);
+                    self.skip_newlines();
+                    if self.peek() == Some(&Token::Punct(',')) {
+                        self.pos += 1;
+                    }
+                }
+            }
+            other => Err(format!("Expected a value, found {:?}", other)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SCHEMA: &str = r#"
+datasource db {
+  provider = "postgresql"
+  url      = env("DATABASE_URL")
+}
+
+/// Access level
+enum Role {
+  USER
+  ADMIN @map("admin")
+
+  @@map("role")
+}
+
+model User {
+  id        Int      @id @default(autoincrement())
+  email     String   @unique @db.VarChar(255)
+  role      Role     @default(ADMIN)
+  name      String?
+  tags      String[] @default([])
+  createdAt DateTime @default(now()) @map("created_at")
+  posts     Post[]
+
+  @@map("users")
+}
+
+model Post {
+  id       String  @id @default(uuid()) @db.Uuid
+  title    String  @default("Untitled")
+  score    Decimal @default(-1) @db.Decimal(10, 2)
+  authorId Int     @map("author_id")
+  author   User    @relation(fields: [authorId], references: [id], onDelete: Cascade)
+
+  @@index([authorId, title(sort: Desc)])
+  @@unique([title, authorId], map: "post_title_author")
+}
+
+view Stats {
+  id Int @unique
+}
+"#;
+
+    #[test]
+    fn test_parse_prisma_schema() {
+        let parsed = parse_prisma_schema(SCHEMA).unwrap();
+        let schema = parsed.schema;
+        assert_eq!(parsed.warnings, ["view Stats: view blocks are not imported"]);
+        assert_eq!(schema.enums, [EnumType { name: "role".to_string(), values: vec!["USER".to_string(), "admin".to_string()] }]);
+
+        let users = schema.find_table("users").unwrap();
+        let id = users.find_column("id").unwrap();
+        assert_eq!((id.data_type.as_str(), id.is_nullable), ("integer", false));
+        assert_eq!(id.default_value.as_deref(), Some("nextval('users_id_seq'::regclass)"));
+        assert_eq!(users.find_column("email").unwrap().data_type, "varchar(255)");
+        assert_eq!(users.find_column("role").unwrap().default_value.as_deref(), Some("'admin'::role"));
+        assert!(users.find_column("name").unwrap().is_nullable);
+        let tags = users.find_column("tags").unwrap();
+        assert_eq!((tags.data_type.as_str(), tags.default_value.as_deref()), ("text[]", Some("ARRAY[]::text[]")));
+        assert_eq!(users.find_column("created_at").unwrap().default_value.as_deref(), Some("CURRENT_TIMESTAMP"));
+        assert!(users.find_column("posts").is_none());
+        assert_eq!(users.primary_key.as_ref().unwrap().name, "users_pkey");
+        assert!(users.find_index("users_email_key").unwrap().is_unique);
+
+        let posts = schema.find_table("Post").unwrap();
+        assert_eq!(posts.find_column("id").unwrap().default_value, None);
+        assert_eq!(posts.find_column("title").unwrap().default_value.as_deref(), Some("'Untitled'::text"));
+        let score = posts.find_column("score").unwrap();
+        assert_eq!((score.data_type.as_str(), score.default_value.as_deref()), ("numeric(10,2)", Some("'-1'::integer")));
+        let foreign_key = &posts.foreign_keys[0];
+        assert_eq!(foreign_key.name, "Post_author_id_fkey");
+        assert_eq!((foreign_key.referenced_table.as_str(), foreign_key.referenced_columns.as_slice()), ("users", &["id".to_string()][..]));
+        assert_eq!((foreign_key.on_delete.as_str(), foreign_key.on_update.as_str()), ("CASCADE", "CASCADE"));
+        let index = posts.find_index("Post_author_id_title_idx").unwrap();
+        assert!(!index.is_unique && index.column_orders[1].descending);
+        assert_eq!(posts.find_index("post_title_author").unwrap().columns, ["title", "author_id"]);
+    }
+}
@@ -0,0 +1,24 @@
+//! Cluster-level role model.
+//!
+//! Roles are shared by every database on a server, so they are kept apart
+//! from the per-database [`SchemaModel`](super::schema::SchemaModel).
+
+use serde::{Deserialize, Serialize};
+
+/// Represents a PostgreSQL role (user or group).
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Role {
+    pub name: String,
+    pub superuser: bool,
+    pub inherit: bool,
+    pub create_role: bool,
+    pub create_db: bool,
+    pub can_login: bool,
+    pub replication: bool,
+    pub bypass_rls: bool,
+    /// -1 means no limit.
+    pub connection_limit: i32,
+    pub valid_until: Option<String>,
+    /// Roles this role is a member of.
+    pub member_of: Vec<String>,
+}
@@ -16,11 +16,46 @@ pub struct SchemaModel {
     pub indexes: Vec<Index>,
     #[serde(default)]
     pub enums: Vec<EnumType>,
+    #[serde(default)]
+    pub privileges: Vec<Privilege>,
+    #[serde(default)]
+    pub functions: Vec<Function>,
+    #[serde(default)]
+    pub foreign_servers: Vec<ForeignServer>,
+    #[serde(default)]
+    pub user_mappings: Vec<UserMapping>,
+    #[serde(default)]
+    pub foreign_tables: Vec<ForeignTable>,
+    #[serde(default)]
+    pub publications: Vec<Publication>,
+    #[serde(default)]
+    pub subscriptions: Vec<Subscription>,
+    /// Objects of types registered through [`crate::extension`].
+    #[serde(default)]
+    pub custom_objects: Vec<CustomObject>,
+    /// Object types whose catalogs introspection was not allowed to read,
+    /// e.g. `subscription`; the model has none of them, so they are not
+    /// compared.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub unreadable_object_types: Vec<String>,
 }
 
 impl SchemaModel {
     pub fn new() -> Self {
-        Self { tables: Vec::new(), indexes: Vec::new(), enums: Vec::new() }
+        Self {
+            tables: Vec::new(),
+            indexes: Vec::new(),
+            enums: Vec::new(),
+            privileges: Vec::new(),
+            functions: Vec::new(),
+            foreign_servers: Vec::new(),
+            user_mappings: Vec::new(),
+            foreign_tables: Vec::new(),
+            publications: Vec::new(),
+            subscriptions: Vec::new(),
+            custom_objects: Vec::new(),
+            unreadable_object_types: Vec::new(),
+        }
     }
     
     pub fn find_table(&self, name: &str) -> Option<&Table> {
@@ -30,6 +65,32 @@ impl SchemaModel {
     pub fn find_enum(&self, name: &str) -> Option<&EnumType> {
         self.enums.iter().find(|e| e.name == name)
     }
+    
+    /// Find a function by name and identity arguments (functions can be overloaded).
+    pub fn find_function(&self, name: &str, arguments: &str) -> Option<&Function> {
+        self.functions.iter().find(|f| f.name == name && f.arguments == arguments)
+    }
+    
+    pub fn find_foreign_server(&self, name: &str) -> Option<&ForeignServer> {
+        self.foreign_servers.iter().find(|s| s.name == name)
+    }
+    
+    /// Find the user mapping of `user` (a role name or PUBLIC) on `server`.
+    pub fn find_user_mapping(&self, server: &str, user: &str) -> Option<&UserMapping> {
+        self.user_mappings.iter().find(|m| m.server == server && m.user == user)
+    }
+    
+    pub fn find_foreign_table(&self, name: &str) -> Option<&ForeignTable> {
+        self.foreign_tables.iter().find(|t| t.name == name)
+    }
+    
+    pub fn find_publication(&self, name: &str) -> Option<&Publication> {
+        self.publications.iter().find(|p| p.name == name)
+    }
+    
+    pub fn find_subscription(&self, name: &str) -> Option<&Subscription> {
+        self.subscriptions.iter().find(|s| s.name == name)
+    }
 }
 
 impl Default for SchemaModel {
@@ -46,6 +107,31 @@ pub struct Table {
     pub primary_key: Option<Constraint>,
     pub unique_constraints: Vec<Constraint>,
     pub indexes: Vec<Index>,
+    #[serde(default)]
+    pub rls_enabled: bool,
+    #[serde(default)]
+    pub rls_forced: bool,
+    #[serde(default)]
+    pub policies: Vec<Policy>,
+    #[serde(default)]
+    pub triggers: Vec<Trigger>,
+    /// Foreign keys declared on this table.
+    #[serde(default)]
+    pub foreign_keys: Vec<ForeignKey>,
+    /// `COMMENT ON TABLE` text.
+    #[serde(default)]
+    pub comment: Option<String>,
+    /// Parent tables of `INHERITS (...)`, in declaration order.
+    #[serde(default)]
+    pub inherits: Vec<String>,
+    /// `CREATE UNLOGGED TABLE`: not written to WAL, emptied after a crash
+    /// and not replicated.
+    #[serde(default)]
+    pub unlogged: bool,
+    /// Storage parameters as `name=value`, e.g. `fillfactor=70` or
+    /// `autovacuum_vacuum_scale_factor=0.05`.
+    #[serde(default)]
+    pub storage_parameters: Vec<String>,
 }
 
 impl Table {
@@ -65,6 +151,14 @@ impl Table {
     pub fn find_index(&self, name: &str) -> Option<&Index> {
         self.indexes.iter().find(|i| i.name == name)
     }
+    
+    pub fn find_policy(&self, name: &str) -> Option<&Policy> {
+        self.policies.iter().find(|p| p.name == name)
+    }
+    
+    pub fn find_trigger(&self, name: &str) -> Option<&Trigger> {
+        self.triggers.iter().find(|t| t.name == name)
+    }
 }
 
 /// Represents a table column.
@@ -75,6 +169,22 @@ pub struct Column {
     pub is_nullable: bool,
     pub default_value: Option<String>,
     pub ordinal_position: i32,
+    /// Expression of a `GENERATED ALWAYS AS (...) STORED` column.
+    #[serde(default)]
+    pub generation_expression: Option<String>,
+    /// `COMMENT ON COLUMN` text.
+    #[serde(default)]
+    pub comment: Option<String>,
+    /// The column comes only from a parent table and is not declared locally.
+    #[serde(default)]
+    pub inherited: bool,
+    /// `SET STORAGE` strategy (`PLAIN`, `EXTERNAL`, `EXTENDED` or `MAIN`) when
+    /// it differs from the type's default.
+    #[serde(default)]
+    pub storage: Option<String>,
+    /// `SET STATISTICS` target; None uses `default_statistics_target`.
+    #[serde(default)]
+    pub statistics_target: Option<i32>,
 }
 
 impl Column {
@@ -84,6 +194,25 @@ impl Column {
             && self.data_type == other.data_type
             && self.is_nullable == other.is_nullable
             && self.default_value == other.default_value
+            && self.generation_expression == other.generation_expression
+            && self.storage == other.storage
+            && self.statistics_target == other.statistics_target
+    }
+
+    /// Storage strategy of the column's type, which `storage` overrides.
+    ///
+    /// `SET STORAGE DEFAULT` needs PostgreSQL 16, so resets spell the default
+    /// out: fixed-length built-in types are PLAIN, anything else is assumed to
+    /// be a TOAST-able variable-length type.
+    pub fn default_storage(&self) -> &'static str {
+        match self.data_type.to_lowercase().as_str() {
+            "smallint" | "integer" | "bigint" | "real" | "double precision" | "boolean"
+            | "date" | "time without time zone" | "time with time zone"
+            | "timestamp without time zone" | "timestamp with time zone" | "interval"
+            | "uuid" | "money" | "oid" | "point" | "box" | "lseg" | "circle"
+            | "macaddr" | "macaddr8" | "pg_lsn" | "\"char\"" => "PLAIN",
+            _ => "EXTENDED",
+        }
     }
 }
 
@@ -95,6 +224,58 @@ pub struct Constraint {
     pub columns: Vec<String>,
 }
 
+/// Represents a foreign key constraint.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ForeignKey {
+    pub name: String,
+    pub columns: Vec<String>,
+    pub referenced_table: String,
+    pub referenced_columns: Vec<String>,
+    /// `SIMPLE`, `FULL` or `PARTIAL`.
+    #[serde(default = "default_match_type")]
+    pub match_type: String,
+    /// Referential action: `NO ACTION`, `RESTRICT`, `CASCADE`, `SET NULL` or `SET DEFAULT`.
+    #[serde(default = "default_referential_action")]
+    pub on_delete: String,
+    #[serde(default = "default_referential_action")]
+    pub on_update: String,
+    #[serde(default)]
+    pub deferrable: bool,
+    #[serde(default)]
+    pub initially_deferred: bool,
+    /// False for a constraint added `NOT VALID` and not validated since:
+    /// existing rows may violate it.
+    #[serde(default = "default_validated")]
+    pub validated: bool,
+}
+
+fn default_validated() -> bool {
+    true
+}
+
+fn default_match_type() -> String {
+    "SIMPLE".to_string()
+}
+
+fn default_referential_action() -> String {
+    "NO ACTION".to_string()
+}
+
+impl ForeignKey {
+    /// Check if two foreign keys enforce the same reference (ignoring the name
+    /// and whether existing rows were validated).
+    pub fn same_definition(&self, other: &ForeignKey) -> bool {
+        self.columns == other.columns
+            && self.referenced_table == other.referenced_table
+            && self.referenced_columns == other.referenced_columns
+            && self.match_type == other.match_type
+            && self.on_delete == other.on_delete
+            && self.on_update == other.on_update
+            && self.deferrable == other.deferrable
+            && self.initially_deferred == other.initially_deferred
+    }
+}
+
 /// Represents an index.
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct Index {
@@ -102,4 +283,212 @@ pub struct Index {
     pub columns: Vec<String>,
     pub is_unique: bool,
     pub index_type: String,
+    /// WHERE clause of a partial index, as returned by `pg_get_expr`.
+    #[serde(default)]
+    pub predicate: Option<String>,
+    /// Sort order of each key column, parallel to `columns`. Missing entries
+    /// mean ascending with nulls last.
+    #[serde(default)]
+    pub column_orders: Vec<IndexColumnOrder>,
+    /// Non-key columns stored in the index (`INCLUDE (...)`).
+    #[serde(default)]
+    pub include_columns: Vec<String>,
+    /// Operator class of each key column, parallel to `columns`; None (or a
+    /// missing entry) means the type's default operator class.
+    #[serde(default)]
+    pub column_opclasses: Vec<Option<String>>,
+    /// Storage parameters as `name=value`, e.g. `fillfactor=70`.
+    #[serde(default)]
+    pub storage_parameters: Vec<String>,
+}
+
+impl Index {
+    /// Check if two indexes have the same definition (ignoring name).
+    pub fn same_definition(&self, other: &Index) -> bool {
+        self.columns == other.columns
+            && self.is_unique == other.is_unique
+            && self.index_type == other.index_type
+            && self.predicate == other.predicate
+            && (0..self.columns.len()).all(|i| {
+                self.column_order(i) == other.column_order(i)
+                    && self.column_opclass(i) == other.column_opclass(i)
+            })
+            && self.include_columns == other.include_columns
+            && self.storage_parameters == other.storage_parameters
+    }
+    
+    /// Sort order of the key column at `position`.
+    pub fn column_order(&self, position: usize) -> IndexColumnOrder {
+        self.column_orders.get(position).cloned().unwrap_or_default()
+    }
+    
+    /// Non-default operator class of the key column at `position`.
+    pub fn column_opclass(&self, position: usize) -> Option<&str> {
+        self.column_opclasses.get(position).and_then(|o| o.as_deref())
+    }
+}
+
+/// Sort order of an index key column.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct IndexColumnOrder {
+    pub descending: bool,
+    pub nulls_first: bool,
+}
+
+impl IndexColumnOrder {
+    /// Ordering keywords as written after the column in CREATE INDEX; empty for the default.
+    pub fn keywords(&self) -> &'static str {
+        match (self.descending, self.nulls_first) {
+            (false, false) => "",
+            (false, true) => " NULLS FIRST",
+            (true, true) => " DESC",
+            (true, false) => " DESC NULLS LAST",
+        }
+    }
+}
+
+/// Represents a row-level security policy on a table.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Policy {
+    pub name: String,
+    /// Command the policy applies to: ALL, SELECT, INSERT, UPDATE or DELETE.
+    pub command: String,
+    /// PERMISSIVE (true) or RESTRICTIVE (false).
+    pub permissive: bool,
+    pub roles: Vec<String>,
+    pub using_expr: Option<String>,
+    pub check_expr: Option<String>,
+}
+
+/// Represents a single privilege granted on a table, column, sequence or function.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Privilege {
+    /// One of: table, column, sequence, function.
+    pub object_type: String,
+    /// Table, sequence or function name.
+    pub object_name: String,
+    /// Column name for column-level privileges.
+    #[serde(default)]
+    pub column_name: Option<String>,
+    /// Identity argument list for function privileges, e.g. `integer, text`.
+    #[serde(default)]
+    pub arguments: Option<String>,
+    /// Role name, or PUBLIC.
+    pub grantee: String,
+    pub privilege_type: String,
+    pub is_grantable: bool,
+}
+
+impl Privilege {
+    /// Check if two privileges refer to the same grant (ignoring the grant option).
+    pub fn same_grant(&self, other: &Privilege) -> bool {
+        self.object_type == other.object_type
+            && self.object_name == other.object_name
+            && self.column_name == other.column_name
+            && self.arguments == other.arguments
+            && self.grantee == other.grantee
+            && self.privilege_type == other.privilege_type
+    }
+}
+
+/// Represents a function or procedure.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Function {
+    pub name: String,
+    /// Identity argument list, e.g. `a integer, b text`.
+    pub arguments: String,
+    /// Result type; None for procedures.
+    pub return_type: Option<String>,
+    pub language: String,
+    pub is_procedure: bool,
+    /// Full `CREATE OR REPLACE FUNCTION` statement from `pg_get_functiondef`.
+    pub definition: String,
+}
+
+/// Represents a trigger on a table.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Trigger {
+    pub name: String,
+    /// Full `CREATE TRIGGER` statement from `pg_get_triggerdef`.
+    pub definition: String,
+}
+
+/// Represents a foreign server (`CREATE SERVER`).
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ForeignServer {
+    pub name: String,
+    /// Foreign data wrapper, e.g. `postgres_fdw`.
+    pub wrapper: String,
+    #[serde(default)]
+    pub server_type: Option<String>,
+    #[serde(default)]
+    pub version: Option<String>,
+    /// Options as `name=value`, e.g. `host=db.internal`.
+    #[serde(default)]
+    pub options: Vec<String>,
+}
+
+/// Represents a user mapping on a foreign server.
+///
+/// The `password` option is never introspected, so mappings can be compared
+/// and generated without exposing credentials.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct UserMapping {
+    pub server: String,
+    /// Role name, or PUBLIC.
+    pub user: String,
+    /// Options as `name=value`, e.g. `user=reporting`.
+    #[serde(default)]
+    pub options: Vec<String>,
+}
+
+/// Represents a foreign table.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ForeignTable {
+    pub name: String,
+    pub server: String,
+    pub columns: Vec<Column>,
+    /// Options as `name=value`, e.g. `table_name=orders`.
+    #[serde(default)]
+    pub options: Vec<String>,
+}
+
+/// Represents a logical replication publication.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Publication {
+    pub name: String,
+    /// `FOR ALL TABLES`; `tables` is empty in that case.
+    pub all_tables: bool,
+    /// Published tables in the public schema, sorted by name.
+    #[serde(default)]
+    pub tables: Vec<String>,
+    /// Published operations: insert, update, delete and/or truncate.
+    pub publish: Vec<String>,
+    #[serde(default)]
+    pub publish_via_partition_root: bool,
+}
+
+/// Represents a logical replication subscription in the current database.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Subscription {
+    pub name: String,
+    /// Connection string to the publisher, with any password removed.
+    pub connection: String,
+    pub publications: Vec<String>,
+    pub enabled: bool,
+    /// Replication slot on the publisher; None for `slot_name = NONE`.
+    #[serde(default)]
+    pub slot_name: Option<String>,
+}
+
+/// An object of a type pgshift does not know, read by an
+/// [`crate::extension::ObjectTypeExtension`].
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct CustomObject {
+    /// Type registered by the extension, e.g. `cron_job`.
+    pub object_type: String,
+    pub name: String,
+    /// Everything the extension needs to create the object; two objects
+    /// with equal definitions are the same.
+    pub definition: serde_json::Value,
 }
@@ -0,0 +1,425 @@
+//! Declarative schema files.
+//!
+//! A `schema.toml` or `schema.yaml` describes the desired schema by hand,
+//! like a Terraform configuration: it is loaded as the source of a
+//! comparison, and a live database can be exported into one to start from.
+//! Tables, columns, keys and indexes use a short form where anything left out
+//! takes PostgreSQL's default; the other objects are written as in the schema
+//! model. Files ending in `.yaml` or `.yml` are YAML, any other TOML.
+//!
+//! ```toml
+//! [[enums]]
+//! name = "status"
+//! values = ["active", "archived"]
+//!
+//! [[tables]]
+//! name = "users"
+//! primary_key = { name = "users_pkey", columns = ["id"] }
+//!
+//! [[tables.columns]]
+//! name = "id"
+//! type = "bigint"
+//! nullable = false
+//!
+//! [[tables.columns]]
+//! name = "status"
+//! type = "status"
+//! default = "'active'::status"
+//!
+//! [[tables.indexes]]
+//! name = "users_status_idx"
+//! columns = ["status"]
+//! ```
+
+use std::fs;
+use std::path::Path;
+use serde::{Deserialize, Serialize};
+use super::schema::{
+    Column, Constraint, CustomObject, EnumType, ForeignKey, ForeignServer, ForeignTable, Function,
+    Index, IndexColumnOrder, Policy, Privilege, Publication, SchemaModel, Subscription, Table,
+    Trigger, UserMapping,
+};
+
+/// Usual name of a declarative schema file.
+pub const SCHEMA_FILE: &str = "schema.toml";
+
+/// Contents of a schema file.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SchemaFile {
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub enums: Vec<EnumType>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub functions: Vec<Function>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tables: Vec<TableDef>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub privileges: Vec<Privilege>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub foreign_servers: Vec<ForeignServer>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub user_mappings: Vec<UserMapping>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub foreign_tables: Vec<ForeignTable>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub publications: Vec<Publication>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub subscriptions: Vec<Subscription>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub custom_objects: Vec<CustomObject>,
+}
+
+/// A table in a schema file.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TableDef {
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub comment: Option<String>,
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub unlogged: bool,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub inherits: Vec<String>,
+    /// As `name=value`, e.g. `fillfactor=70`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub storage_parameters: Vec<String>,
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub rls_enabled: bool,
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub rls_forced: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub primary_key: Option<KeyDef>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub columns: Vec<ColumnDef>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub unique: Vec<KeyDef>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub foreign_keys: Vec<ForeignKey>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub indexes: Vec<IndexDef>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub policies: Vec<Policy>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub triggers: Vec<Trigger>,
+}
+
+/// A column in a schema file; columns are numbered in the order written.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ColumnDef {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub data_type: String,
+    #[serde(default = "default_true", skip_serializing_if = "is_true")]
+    pub nullable: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub default: Option<String>,
+    /// Expression of a stored generated column.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub generated: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub comment: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub storage: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub statistics_target: Option<i32>,
+    /// Inherited from a parent table rather than declared here.
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub inherited: bool,
+}
+
+/// A primary key or unique constraint in a schema file.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct KeyDef {
+    pub name: String,
+    pub columns: Vec<String>,
+}
+
+/// An index in a schema file.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IndexDef {
+    pub name: String,
+    pub columns: Vec<String>,
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub unique: bool,
+    #[serde(default = "default_index_method", skip_serializing_if = "is_default_index_method")]
+    pub method: String,
+    /// WHERE clause of a partial index.
+    #[serde(default, rename = "where", skip_serializing_if = "Option::is_none")]
+    pub predicate: Option<String>,
+    /// Sort order of each key column; missing entries are ascending with
+    /// nulls last.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub orders: Vec<IndexColumnOrder>,
+    /// Operator class of each key column; `""` is the type's default.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub opclasses: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub include: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub storage_parameters: Vec<String>,
+}
+
+fn is_false(value: &bool) -> bool {
+    !value
+}
+
+fn is_true(value: &bool) -> bool {
+    *value
+}
+
+fn default_true() -> bool {
+    true
+}
+
+fn default_index_method() -> String {
+    "btree".to_string()
+}
+
+fn is_default_index_method(method: &String) -> bool {
+    method == "btree"
+}
+
+impl SchemaFile {
+    /// Describe `schema` as a schema file.
+    pub fn from_model(schema: &SchemaModel) -> Self {
+        Self {
+            enums: schema.enums.clone(),
+            functions: schema.functions.clone(),
+            tables: schema.tables.iter().map(TableDef::from_table).collect(),
+            privileges: schema.privileges.clone(),
+            foreign_servers: schema.foreign_servers.clone(),
+            user_mappings: schema.user_mappings.clone(),
+            foreign_tables: schema.foreign_tables.clone(),
+            publications: schema.publications.clone(),
+            subscriptions: schema.subscriptions.clone(),
+            custom_objects: schema.custom_objects.clone(),
+        }
+    }
+
+    /// The schema model this file describes.
+    pub fn into_model(self) -> SchemaModel {
+        let tables: Vec<Table> = self.tables.into_iter().map(TableDef::into_table).collect();
+        SchemaModel {
+            indexes: tables.iter().flat_map(|t| t.indexes.clone()).collect(),
+            tables,
+            enums: self.enums,
+            privileges: self.privileges,
+            functions: self.functions,
+            foreign_servers: self.foreign_servers,
+            user_mappings: self.user_mappings,
+            foreign_tables: self.foreign_tables,
+            publications: self.publications,
+            subscriptions: self.subscriptions,
+            custom_objects: self.custom_objects,
+            unreadable_object_types: Vec::new(),
+        }
+    }
+}
+
+impl TableDef {
+    fn from_table(table: &Table) -> Self {
+        let key = |c: &Constraint| KeyDef { name: c.name.clone(), columns: c.columns.clone() };
+        Self {
+            name: table.name.clone(),
+            comment: table.comment.clone(),
+            unlogged: table.unlogged,
+            inherits: table.inherits.clone(),
+            storage_parameters: table.storage_parameters.clone(),
+            rls_enabled: table.rls_enabled,
+            rls_forced: table.rls_forced,
+            primary_key: table.primary_key.as_ref().map(key),
+            columns: table.columns.iter().map(|c| ColumnDef {
+                name: c.name.clone(),
+                data_type: c.data_type.clone(),
+                nullable: c.is_nullable,
+                default: c.default_value.clone(),
+                generated: c.generation_expression.clone(),
+                comment: c.comment.clone(),
+                storage: c.storage.clone(),
+                statistics_target: c.statistics_target,
+                inherited: c.inherited,
+            }).collect(),
+            unique: table.unique_constraints.iter().map(key).collect(),
+            foreign_keys: table.foreign_keys.clone(),
+            indexes: table.indexes.iter().map(|i| IndexDef {
+                name: i.name.clone(),
+                columns: i.columns.clone(),
+                unique: i.is_unique,
+                method: i.index_type.clone(),
+                predicate: i.predicate.clone(),
+                // Introspection lists every column; defaults need no entry
+                orders: if i.column_orders.iter().all(|o| *o == IndexColumnOrder::default()) {
+                    Vec::new()
+                } else {
+                    i.column_orders.clone()
+                },
+                opclasses: if i.column_opclasses.iter().all(Option::is_none) {
+                    Vec::new()
+                } else {
+                    i.column_opclasses.iter().map(|o| o.clone().unwrap_or_default()).collect()
+                },
+                include: i.include_columns.clone(),
+                storage_parameters: i.storage_parameters.clone(),
+            }).collect(),
+            policies: table.policies.clone(),
+            triggers: table.triggers.clone(),
+        }
+    }
+
+    fn into_table(self) -> Table {
+        let key = |k: KeyDef, constraint_type: &str| Constraint {
+            name: k.name,
+            constraint_type: constraint_type.to_string(),
+            columns: k.columns,
+        };
+        Table {
+            columns: self.columns.into_iter().enumerate().map(|(i, c)| Column {
+                name: c.name,
+                data_type: c.data_type,
+                is_nullable: c.nullable,
+                default_value: c.default,
+                ordinal_position: i as i32 + 1,
+                generation_expression: c.generated,
+                comment: c.comment,
+                inherited: c.inherited,
+                storage: c.storage,
+                statistics_target: c.statistics_target,
+            }).collect(),
+            primary_key: self.primary_key.map(|k| key(k, "PRIMARY KEY")),
+            unique_constraints: self.unique.into_iter().map(|k| key(k, "UNIQUE")).collect(),
+            indexes: self.indexes.into_iter().map(|i| Index {
+                name: i.name,
+                columns: i.columns,
+                is_unique: i.unique,
+                index_type: i.method,
+                predicate: i.predicate,
+                column_orders: i.orders,
+                include_columns: i.include,
+                column_opclasses: i.opclasses.into_iter().map(|o| Some(o).filter(|o| !o.is_empty())).collect(),
+                storage_parameters: i.storage_parameters,
+            }).collect(),
+            rls_enabled: self.rls_enabled,
+            rls_forced: self.rls_forced,
+            policies: self.policies,
+            triggers: self.triggers,
+            foreign_keys: self.foreign_keys,
+            comment: self.comment,
+            inherits: self.inherits,
+            unlogged: self.unlogged,
+            storage_parameters: self.storage_parameters,
+            name: self.name,
+        }
+    }
+}
+
+/// Read the schema described by the file at `path`.
+pub fn load_schema_file(path: &str) -> Result<SchemaModel, String> {
+    let content = fs::read_to_string(path)
+        .map_err(|e| format!("Failed to read {}: {}", path, e))?;
+    let file: SchemaFile = if is_yaml(path) {
+        serde_yaml::from_str(&content).map_err(|e| format!("Failed to parse {}: {}", path, e))?
+    } else {
+        toml::from_str(&content).map_err(|e| format!("Failed to parse {}: {}", path, e))?
+    };
+    Ok(file.into_model())
+}
+
+/// Write `schema` to a schema file at `path`.
+pub fn save_schema_file(schema: &SchemaModel, path: &str) -> Result<(), String> {
+    let file = SchemaFile::from_model(schema);
+    let content = if is_yaml(path) {
+        serde_yaml::to_string(&file).map_err(|e| format!("Failed to serialize schema: {}", e))?
+    } else {
+        toml::to_string_pretty(&file).map_err(|e| format!("Failed to serialize schema: {}", e))?
+    };
+    fs::write(path, content).map_err(|e| format!("Failed to write {}: {}", path, e))
+}
+
+/// Whether the schema file at `path` is YAML rather than TOML.
+fn is_yaml(path: &str) -> bool {
+    Path::new(path)
+        .extension()
+        .and_then(|e| e.to_str())
+        .is_some_and(|e| e.eq_ignore_ascii_case("yaml") || e.eq_ignore_ascii_case("yml"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_schema_file_round_trip() {
+        let content = r#"
+            [[enums]]
+            name = "status"
+            values = ["active", "archived"]
+
+            [[tables]]
+            name = "users"
+            primary_key = { name = "users_pkey", columns = ["id"] }
+
+            [[tables.columns]]
+            name = "id"
+            type = "bigint"
+            nullable = false
+
+            [[tables.columns]]
+            name = "status"
+            type = "status"
+            default = "'active'::status"
+
+            [[tables.indexes]]
+            name = "users_status_idx"
+            columns = ["status"]
+            where = "(status = 'active'::status)"
+
+            [[tables.policies]]
+            name = "own_rows"
+            command = "SELECT"
+            permissive = true
+            roles = ["app"]
+            using_expr = "(id = 1)"
+        "#;
+        let schema = toml::from_str::<SchemaFile>(content).unwrap().into_model();
+
+        let users = schema.find_table("users").unwrap();
+        assert!(!users.columns[0].is_nullable && users.columns[1].is_nullable);
+        assert_eq!(users.columns[1].ordinal_position, 2);
+        assert_eq!(users.primary_key.as_ref().unwrap().constraint_type, "PRIMARY KEY");
+        assert_eq!(users.indexes[0].index_type, "btree");
+        assert_eq!(schema.indexes.len(), 1);
+
+        let written = toml::to_string_pretty(&SchemaFile::from_model(&schema)).unwrap();
+        assert!(!written.contains("nullable = true") && !written.contains("method"));
+        let reloaded = toml::from_str::<SchemaFile>(&written).unwrap().into_model();
+        assert_eq!(reloaded, schema);
+    }
+
+    #[test]
+    fn test_yaml_schema_file_round_trip() {
+        let content = r#"
+enums:
+  - name: status
+    values: [active, archived]
+tables:
+  - name: users
+    primary_key: { name: users_pkey, columns: [id] }
+    columns:
+      - { name: id, type: bigint, nullable: false }
+      - { name: status, type: status, default: "'active'::status" }
+    indexes:
+      - name: users_status_idx
+        columns: [status]
+        where: "(status = 'active'::status)"
+"#;
+        let schema = serde_yaml::from_str::<SchemaFile>(content).unwrap().into_model();
+        let users = schema.find_table("users").unwrap();
+        assert!(!users.columns[0].is_nullable && users.columns[1].is_nullable);
+        assert_eq!(users.indexes[0].predicate.as_deref(), Some("(status = 'active'::status)"));
+
+        let path = std::env::temp_dir().join(format!("pgshift-{}.yaml", uuid::Uuid::new_v4()));
+        let path = path.to_str().unwrap();
+        save_schema_file(&schema, path).unwrap();
+        assert!(fs::read_to_string(path).unwrap().contains("- name: users\n"));
+        assert_eq!(load_schema_file(path).unwrap(), schema);
+        fs::remove_file(path).unwrap();
+    }
+}
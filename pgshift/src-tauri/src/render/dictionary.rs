@@ -0,0 +1,358 @@
+//! Static HTML data dictionary for a schema model.
+//!
+//! Produces an `index.html` listing every table and enum plus one page per
+//! table with its columns, keys, indexes and relationships. Pages link to each
+//! other with relative paths so the output can be published as-is.
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use crate::model::schema::{SchemaModel, Table};
+use super::html::escape_html;
+use super::sql::sanitize_name;
+
+/// A single page of the data dictionary.
+#[derive(Debug, Clone)]
+pub struct DictionaryPage {
+    /// File name relative to the output directory.
+    pub file_name: String,
+    pub html: String,
+}
+
+/// Render the data dictionary pages for `schema`. The first page is `index.html`.
+pub fn render_data_dictionary(schema: &SchemaModel, title: &str) -> Vec<DictionaryPage> {
+    let mut tables: Vec<&Table> = schema.tables.iter().collect();
+    tables.sort_by(|a, b| a.name.cmp(&b.name));
+
+    let file_names = table_file_names(&tables);
+    let mut pages = vec![DictionaryPage {
+        file_name: "index.html".to_string(),
+        html: render_index(schema, &tables, &file_names, title),
+    }];
+
+    for table in &tables {
+        pages.push(DictionaryPage {
+            file_name: file_names[table.name.as_str()].clone(),
+            html: render_table(schema, table, &file_names, title),
+        });
+    }
+
+    pages
+}
+
+/// Write the data dictionary into `out_dir` and return the path of `index.html`.
+pub fn write_data_dictionary(schema: &SchemaModel, title: &str, out_dir: &str) -> std::io::Result<String> {
+    let dir = Path::new(out_dir);
+    fs::create_dir_all(dir)?;
+
+    for page in render_data_dictionary(schema, title) {
+        fs::write(dir.join(&page.file_name), page.html)?;
+    }
+
+    Ok(dir.join("index.html").to_string_lossy().to_string())
+}
+
+/// Assign a unique page file name to every table.
+fn table_file_names<'a>(tables: &[&'a Table]) -> HashMap<&'a str, String> {
+    let mut names = HashMap::new();
+    let mut used: HashMap<String, usize> = HashMap::new();
+
+    for table in tables {
+        let base = format!("table_{}", sanitize_name(&table.name));
+        let count = used.entry(base.clone()).or_insert(0);
+        *count += 1;
+        // Names that only differ in case or punctuation would collide after sanitizing
+        let file_name = if *count == 1 {
+            format!("{}.html", base)
+        } else {
+            format!("{}_{}.html", base, count)
+        };
+        names.insert(table.name.as_str(), file_name);
+    }
+
+    names
+}
+
+fn render_index(
+    schema: &SchemaModel,
+    tables: &[&Table],
+    file_names: &HashMap<&str, String>,
+    title: &str,
+) -> String {
+    let mut html = page_header(title, title);
+
+    html.push_str(&format!("<h2>Tables ({})</h2>\n", tables.len()));
+    if tables.is_empty() {
+        html.push_str("<p>No tables.</p>\n");
+    } else {
+        html.push_str("<table>\n<tr><th>Table</th><th>Columns</th><th>Description</th></tr>\n");
+        for table in tables {
+            html.push_str(&format!(
+                "<tr><td><a href=\"{}\">{}</a></td><td>{}</td><td>{}</td></tr>\n",
+                escape_html(&file_names[table.name.as_str()]),
+                escape_html(&table.name),
+                table.columns.len(),
+                escape_html(table.comment.as_deref().unwrap_or(""))
+            ));
+        }
+        html.push_str("</table>\n");
+    }
+
+    if !schema.enums.is_empty() {
+        html.push_str(&format!("<h2>Enums ({})</h2>\n", schema.enums.len()));
+        html.push_str("<table>\n<tr><th>Enum</th><th>Values</th></tr>\n");
+        for enum_type in &schema.enums {
+            html.push_str(&format!(
+                "<tr><td id=\"enum-{}\">{}</td><td>{}</td></tr>\n",
+                escape_html(&enum_type.name),
+                escape_html(&enum_type.name),
+                escape_html(&enum_type.values.join(", "))
+            ));
+        }
+        html.push_str("</table>\n");
+    }
+
+    html.push_str("</body>\n</html>\n");
+    html
+}
+
+fn render_table(
+    schema: &SchemaModel,
+    table: &Table,
+    file_names: &HashMap<&str, String>,
+    title: &str,
+) -> String {
+    let mut html = page_header(&format!("{} — {}", table.name, title), &table.name);
+    html.push_str("<p class=\"meta\"><a href=\"index.html\">&larr; All tables</a></p>\n");
+
+    if let Some(comment) = &table.comment {
+        html.push_str(&format!("<p>{}</p>\n", escape_html(comment)));
+    }
+
+    let pk_columns: Vec<&String> = table.primary_key.iter().flat_map(|pk| &pk.columns).collect();
+
+    // Columns
+    html.push_str("<h2>Columns</h2>\n");
+    html.push_str("<table>\n<tr><th>Column</th><th>Type</th><th>Nullable</th><th>Default</th><th>Description</th></tr>\n");
+    let mut columns: Vec<_> = table.columns.iter().collect();
+    columns.sort_by_key(|c| c.ordinal_position);
+    for column in columns {
+        let mut name = escape_html(&column.name);
+        if pk_columns.contains(&&column.name) {
+            name.push_str(" <span class=\"tag\">PK</span>");
+        }
+        if table.foreign_keys.iter().any(|fk| fk.columns.contains(&column.name)) {
+            name.push_str(" <span class=\"tag\">FK</span>");
+        }
+
+        let data_type = if schema.find_enum(&column.data_type).is_some() {
+            format!(
+                "<a href=\"index.html#enum-{}\">{}</a>",
+                escape_html(&column.data_type),
+                escape_html(&column.data_type)
+            )
+        } else {
+            escape_html(&column.data_type)
+        };
+
+        let default = match (&column.generation_expression, &column.default_value) {
+            (Some(expr), _) => format!("GENERATED ALWAYS AS ({}) STORED", expr),
+            (None, Some(default)) => default.clone(),
+            (None, None) => String::new(),
+        };
+
+        html.push_str(&format!(
+            "<tr><td>{}</td><td>{}</td><td>{}</td><td><code>{}</code></td><td>{}</td></tr>\n",
+            name,
+            data_type,
+            if column.is_nullable { "yes" } else { "no" },
+            escape_html(&default),
+            escape_html(column.comment.as_deref().unwrap_or(""))
+        ));
+    }
+    html.push_str("</table>\n");
+
+    // Keys and constraints
+    let constraints: Vec<_> = table.primary_key.iter().chain(&table.unique_constraints).collect();
+    if !constraints.is_empty() {
+        html.push_str("<h2>Constraints</h2>\n");
+        html.push_str("<table>\n<tr><th>Name</th><th>Type</th><th>Columns</th></tr>\n");
+        for constraint in constraints {
+            html.push_str(&format!(
+                "<tr><td>{}</td><td>{}</td><td>{}</td></tr>\n",
+                escape_html(&constraint.name),
+                escape_html(&constraint.constraint_type),
+                escape_html(&constraint.columns.join(", "))
+            ));
+        }
+        html.push_str("</table>\n");
+    }
+
+    // Indexes
+    if !table.indexes.is_empty() {
+        html.push_str("<h2>Indexes</h2>\n");
+        html.push_str("<table>\n<tr><th>Name</th><th>Method</th><th>Unique</th><th>Columns</th><th>Include</th><th>Predicate</th></tr>\n");
+        for index in &table.indexes {
+            let columns: Vec<String> = index.columns.iter()
+                .enumerate()
+                .map(|(i, c)| {
+                    let opclass = index.column_opclass(i).map(|o| format!(" {}", o)).unwrap_or_default();
+                    format!("{}{}{}", c, opclass, index.column_order(i).keywords())
+                })
+                .collect();
+            html.push_str(&format!(
+                "<tr><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td><code>{}</code></td></tr>\n",
+                escape_html(&index.name),
+                escape_html(&index.index_type),
+                if index.is_unique { "yes" } else { "no" },
+                escape_html(&columns.join(", ")),
+                escape_html(&index.include_columns.join(", ")),
+                escape_html(index.predicate.as_deref().unwrap_or(""))
+            ));
+        }
+        html.push_str("</table>\n");
+    }
+
+    // Outgoing relationships
+    if !table.foreign_keys.is_empty() {
+        html.push_str("<h2>References</h2>\n");
+        html.push_str("<table>\n<tr><th>Constraint</th><th>Columns</th><th>Referenced table</th><th>Referenced columns</th></tr>\n");
+        for fk in &table.foreign_keys {
+            html.push_str(&format!(
+                "<tr><td>{}</td><td>{}</td><td>{}</td><td>{}</td></tr>\n",
+                escape_html(&fk.name),
+                escape_html(&fk.columns.join(", ")),
+                table_link(&fk.referenced_table, file_names),
+                escape_html(&fk.referenced_columns.join(", "))
+            ));
+        }
+        html.push_str("</table>\n");
+    }
+
+    // Incoming relationships
+    let mut referenced_by: Vec<_> = schema.tables.iter()
+        .flat_map(|t| t.foreign_keys.iter().map(move |fk| (t, fk)))
+        .filter(|(_, fk)| fk.referenced_table == table.name)
+        .collect();
+    referenced_by.sort_by(|a, b| (&a.0.name, &a.1.name).cmp(&(&b.0.name, &b.1.name)));
+    if !referenced_by.is_empty() {
+        html.push_str("<h2>Referenced by</h2>\n");
+        html.push_str("<table>\n<tr><th>Table</th><th>Constraint</th><th>Columns</th><th>Referenced columns</th></tr>\n");
+        for (other, fk) in referenced_by {
+            html.push_str(&format!(
+                "<tr><td>{}</td><td>{}</td><td>{}</td><td>{}</td></tr>\n",
+                table_link(&other.name, file_names),
+                escape_html(&fk.name),
+                escape_html(&fk.columns.join(", ")),
+                escape_html(&fk.referenced_columns.join(", "))
+            ));
+        }
+        html.push_str("</table>\n");
+    }
+
+    html.push_str("</body>\n</html>\n");
+    html
+}
+
+/// Link to a table page, or plain text for tables outside the snapshot.
+fn table_link(table_name: &str, file_names: &HashMap<&str, String>) -> String {
+    match file_names.get(table_name) {
+        Some(file_name) => format!("<a href=\"{}\">{}</a>", escape_html(file_name), escape_html(table_name)),
+        None => escape_html(table_name),
+    }
+}
+
+fn page_header(title: &str, heading: &str) -> String {
+    let mut html = String::new();
+    html.push_str("<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n");
+    html.push_str(&format!("<title>{}</title>\n", escape_html(title)));
+    html.push_str(STYLE);
+    html.push_str("</head>\n<body>\n");
+    html.push_str(&format!("<h1>{}</h1>\n", escape_html(heading)));
+    html
+}
+
+const STYLE: &str = r#"<style>
+body { font-family: -apple-system, "Segoe UI", sans-serif; margin: 2em; color: #222; }
+.meta { color: #666; font-size: 0.9em; }
+table { border-collapse: collapse; width: 100%; margin-bottom: 1.5em; }
+th, td { border: 1px solid #ddd; padding: 6px 8px; text-align: left; vertical-align: top; }
+th { background: #f4f4f4; }
+code { font-size: 0.85em; }
+a { color: #1565c0; text-decoration: none; }
+a:hover { text-decoration: underline; }
+.tag { background: #e3f2fd; color: #1565c0; border-radius: 3px; padding: 0 4px; font-size: 0.75em; }
+</style>
+"#;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::schema::{Column, Constraint, ForeignKey};
+
+    fn create_test_table(name: &str, foreign_keys: Vec<ForeignKey>) -> Table {
+        Table {
+            name: name.to_string(),
+            columns: vec![Column {
+                name: "id".to_string(),
+                data_type: "integer".to_string(),
+                is_nullable: false,
+                default_value: None,
+                ordinal_position: 1,
+                generation_expression: None,
+                comment: Some("Surrogate <key>".to_string()),
+                inherited: false,
+                storage: None,
+                statistics_target: None,
+            }],
+            primary_key: Some(Constraint {
+                name: format!("{}_pkey", name),
+                constraint_type: "PRIMARY KEY".to_string(),
+                columns: vec!["id".to_string()],
+            }),
+            unique_constraints: Vec::new(),
+            indexes: Vec::new(),
+            rls_enabled: false,
+            rls_forced: false,
+            policies: Vec::new(),
+            triggers: Vec::new(),
+            foreign_keys,
+            comment: None,
+            inherits: Vec::new(),
+            unlogged: false,
+            storage_parameters: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_data_dictionary_pages_and_relationships() {
+        let schema = SchemaModel {
+            tables: vec![
+                create_test_table("orders", vec![ForeignKey {
+                    name: "orders_id_fkey".to_string(),
+                    columns: vec!["id".to_string()],
+                    referenced_table: "Users".to_string(),
+                    referenced_columns: vec!["id".to_string()],
+                    match_type: "SIMPLE".to_string(),
+                    on_delete: "NO ACTION".to_string(),
+                    on_update: "NO ACTION".to_string(),
+                    deferrable: false,
+                    initially_deferred: false,
+                    validated: true,
+                }]),
+                create_test_table("Users", Vec::new()),
+                create_test_table("users", Vec::new()),
+            ],
+            ..Default::default()
+        };
+
+        let pages = render_data_dictionary(&schema, "Shop");
+        let names: Vec<_> = pages.iter().map(|p| p.file_name.as_str()).collect();
+
+        assert_eq!(names, vec!["index.html", "table_users.html", "table_orders.html", "table_users_2.html"]);
+        assert!(pages[1].html.contains("Surrogate &lt;key&gt;"));
+        assert!(pages[1].html.contains("<h2>Referenced by</h2>"));
+        assert!(pages[2].html.contains("<a href=\"table_users.html\">Users</a>"));
+        assert!(!pages[3].html.contains("Referenced by"));
+    }
+}
@@ -0,0 +1,508 @@
+//! Dual-write migrations for column type changes on busy tables.
+//!
+//! `ALTER COLUMN ... TYPE` rewrites the table under an ACCESS EXCLUSIVE lock
+//! for as long as the rewrite takes. On a busy table the change can instead
+//! be made in phases that each lock it only briefly: a new column is added
+//! and kept in sync by a trigger, existing rows are copied in batches, the
+//! copy is verified and the columns are swapped, and once nothing uses it
+//! the old column is dropped. Each phase is written as its own migration
+//! folder, applied one at a time, with instructions in its `meta.json`; or,
+//! with [`DualWriteOptions::expand_contract`], as two migrations: one that
+//! expands the table up to the swap and one that contracts it.
+
+use std::fs;
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use crate::model::schema::{Column, SchemaModel};
+use super::sql::{sanitize_name, MigrationItemMeta, MigrationMeta, MigrationPhase};
+use super::version::{create_migration_dir, VersionScheme};
+
+/// Rows updated per backfill transaction when not configured.
+pub const DEFAULT_BATCH_SIZE: u32 = 10_000;
+
+/// Suffix of the column that receives the new type.
+const NEW_COLUMN_SUFFIX: &str = "__new";
+
+/// Suffix the old column is renamed to by the swap.
+const OLD_COLUMN_SUFFIX: &str = "__old";
+
+/// Options for a dual-write migration.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct DualWriteOptions {
+    /// Rows updated per backfill transaction; [`DEFAULT_BATCH_SIZE`] if unset.
+    #[serde(default)]
+    pub batch_size: Option<u32>,
+    /// Expression converting a value of the old column, written `{value}`,
+    /// to the new type, e.g. `round({value} * 100)::bigint`. A cast if unset.
+    #[serde(default)]
+    pub using: Option<String>,
+    /// Write two migrations, `expand` and `contract`, instead of one per
+    /// phase; see [`expand_contract`].
+    #[serde(default)]
+    pub expand_contract: bool,
+}
+
+/// One phase of a dual-write migration.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DualWritePhase {
+    /// e.g. `backfill`.
+    pub kind: String,
+    pub details: String,
+    pub up_sql: String,
+    pub down_sql: String,
+    pub instructions: Vec<String>,
+    /// False when `up_sql` commits by itself.
+    pub transactional: bool,
+    /// Whether the phase loses data, as dropping the old column does.
+    pub dangerous: bool,
+}
+
+/// Plan the phases that change `table.column` from its type in `target` to
+/// its type in `source`.
+///
+/// The table needs a single-column primary key to backfill in batches.
+pub fn plan_dual_write(
+    source: &SchemaModel,
+    target: &SchemaModel,
+    table: &str,
+    column: &str,
+    options: &DualWriteOptions,
+) -> Result<Vec<DualWritePhase>, String> {
+    let target_table = target.find_table(table)
+        .ok_or_else(|| format!("Table '{}' not found in the target", table))?;
+    let old = target_table.find_column(column)
+        .ok_or_else(|| format!("Column '{}.{}' not found in the target", table, column))?;
+    let new = source.find_table(table)
+        .and_then(|t| t.find_column(column))
+        .ok_or_else(|| format!("Column '{}.{}' not found in the source", table, column))?;
+    if old.data_type == new.data_type {
+        return Err(format!("Column '{}.{}' has the same type on both sides", table, column));
+    }
+    if old.generation_expression.is_some() || new.generation_expression.is_some() {
+        return Err(format!("Column '{}.{}' is generated", table, column));
+    }
+    let key = match target_table.primary_key.as_ref().map(|pk| pk.columns.as_slice()) {
+        Some([key]) => target_table.find_column(key)
+            .ok_or_else(|| format!("Primary key column '{}.{}' not found", table, key))?,
+        _ => return Err(format!("Table '{}' needs a single-column primary key to backfill in batches", table)),
+    };
+
+    let t = quote(table);
+    let c = quote(column);
+    let n = quote(&format!("{}{}", column, NEW_COLUMN_SUFFIX));
+    let o = quote(&format!("{}{}", column, OLD_COLUMN_SUFFIX));
+    let k = quote(&key.name);
+    let sync = quote(&format!("{}_{}_dual_write", table, column));
+    let convert = |value: &str| match &options.using {
+        Some(using) => using.replace("{value}", value),
+        None => format!("{}::{}", value, new.data_type),
+    };
+    let batch_size = options.batch_size.unwrap_or(DEFAULT_BATCH_SIZE);
+
+    let create_sync = format!(
+        "CREATE OR REPLACE FUNCTION {sync}() RETURNS trigger AS $$\n\
+         BEGIN\n    NEW.{n} := {};\n    RETURN NEW;\nEND;\n$$ LANGUAGE plpgsql;\n\
+         CREATE TRIGGER {sync} BEFORE INSERT OR UPDATE OF {c} ON {t}\n    FOR EACH ROW EXECUTE FUNCTION {sync}();",
+        convert(&format!("NEW.{}", c))
+    );
+    let drop_sync = format!("DROP TRIGGER IF EXISTS {sync} ON {t};\nDROP FUNCTION IF EXISTS {sync}();");
+    let old_type = &old.data_type;
+    let nothing = "-- Nothing to revert".to_string();
+
+    Ok(vec![
+        DualWritePhase {
+            kind: "add_column".to_string(),
+            details: format!("Add {} as {}", n, new.data_type),
+            up_sql: format!("ALTER TABLE {t} ADD COLUMN {n} {};", new.data_type),
+            down_sql: format!("ALTER TABLE {t} DROP COLUMN IF EXISTS {n};"),
+            instructions: vec![
+                "The column has no default or NOT NULL yet, so adding it only changes the catalog.".to_string(),
+            ],
+            transactional: true,
+            dangerous: false,
+        },
+        DualWritePhase {
+            kind: "sync_trigger".to_string(),
+            details: format!("Copy {} into {} on every insert and update", c, n),
+            up_sql: create_sync.clone(),
+            down_sql: drop_sync.clone(),
+            instructions: vec![
+                "From here on every write to the column also writes the new column.".to_string(),
+                "Deploy application code that can read either column before the swap.".to_string(),
+            ],
+            transactional: true,
+            dangerous: false,
+        },
+        DualWritePhase {
+            kind: "backfill".to_string(),
+            details: format!("Copy existing rows in batches of {}", batch_size),
+            up_sql: format!(
+                "DO $$\n\
+                 DECLARE\n    last_key {};\n    batch_rows bigint;\n\
+                 BEGIN\n    LOOP\n        \
+                 WITH batch AS (\n            \
+                 SELECT {k} FROM {t}\n            \
+                 WHERE last_key IS NULL OR {k} > last_key\n            \
+                 ORDER BY {k}\n            \
+                 LIMIT {batch_size}\n        \
+                 ), updated AS (\n            \
+                 UPDATE {t} SET {n} = {}\n            \
+                 FROM batch WHERE {t}.{k} = batch.{k}\n            \
+                 RETURNING {t}.{k}\n        \
+                 )\n        \
+                 SELECT max({k}), count(*) INTO last_key, batch_rows FROM updated;\n        \
+                 EXIT WHEN batch_rows = 0;\n        \
+                 COMMIT;\n    END LOOP;\nEND;\n$$;",
+                key.data_type,
+                convert(&format!("{}.{}", t, c))
+            ),
+            down_sql: nothing.clone(),
+            instructions: vec![
+                "Commits after every batch, so it must run outside a transaction block.".to_string(),
+                "Safe to run again if interrupted; rows are copied again from the start.".to_string(),
+                "Rows written meanwhile are kept in sync by the trigger.".to_string(),
+            ],
+            transactional: false,
+            dangerous: false,
+        },
+        DualWritePhase {
+            kind: "verify".to_string(),
+            details: format!("Check that {} matches {} on every row", n, c),
+            up_sql: format!(
+                "DO $$\n\
+                 DECLARE\n    mismatched bigint;\n\
+                 BEGIN\n    \
+                 SELECT count(*) INTO mismatched FROM {t} WHERE {n} IS DISTINCT FROM {};\n    \
+                 IF mismatched > 0 THEN\n        \
+                 RAISE EXCEPTION '% rows of {} differ between {} and {}', mismatched;\n    \
+                 END IF;\nEND;\n$$;",
+                convert(&format!("{}.{}", t, c)),
+                literal_text(&t),
+                literal_text(&c),
+                literal_text(&n)
+            ),
+            down_sql: nothing,
+            instructions: vec![
+                "Reads the whole table; run it when the scan does not hurt.".to_string(),
+                "If it fails, run the backfill again and check the conversion expression.".to_string(),
+            ],
+            transactional: true,
+            dangerous: false,
+        },
+        DualWritePhase {
+            kind: "swap".to_string(),
+            details: format!("Swap {} and {}", c, n),
+            up_sql: swap_sql(&t, &c, &n, &o, old, new, &drop_sync),
+            down_sql: unswap_sql(&t, &c, &n, &o, old, new, &create_sync),
+            instructions: vec![
+                "Holds an ACCESS EXCLUSIVE lock on the table until it commits.".to_string(),
+                format!(
+                    "Indexes, constraints and views on the old column now refer to {}; recreate them on {} first.",
+                    o, c
+                ),
+                format!("The old column is kept as {} until the last phase drops it.", o),
+            ],
+            transactional: true,
+            dangerous: false,
+        },
+        DualWritePhase {
+            kind: "drop_old".to_string(),
+            details: format!("Drop {}", o),
+            up_sql: format!("ALTER TABLE {t} DROP COLUMN IF EXISTS {o};"),
+            down_sql: format!("ALTER TABLE {t} ADD COLUMN {o} {old_type};\nUPDATE {t} SET {o} = {c}::{old_type};"),
+            instructions: vec![
+                format!("Apply once no deployed code reads {} and nothing depends on it.", o),
+                format!("Rolling back adds {} again, filled by casting {} back to {}.", o, c, old_type),
+            ],
+            transactional: true,
+            dangerous: true,
+        },
+    ])
+}
+
+/// Group the phases of [`plan_dual_write`] into two: `expand`, running
+/// every phase up to the swap in one migration, and `contract`, dropping the
+/// old column once the application no longer uses it.
+///
+/// The expand migration runs its transactional phases each in a transaction
+/// of its own and the backfill between them, so it is not transactional as a
+/// whole; its rollback reverts the phases in reverse order the same way.
+pub fn expand_contract(phases: Vec<DualWritePhase>) -> Vec<DualWritePhase> {
+    let (expand, contract): (Vec<_>, Vec<_>) = phases.into_iter().partition(|p| p.kind != "drop_old");
+    let up_sql = expand.iter()
+        .map(|p| phase_sql(p, &p.up_sql))
+        .collect::<Vec<_>>()
+        .join("\n\n");
+    let down_sql = expand.iter()
+        .rev()
+        .map(|p| phase_sql(p, &p.down_sql))
+        .collect::<Vec<_>>()
+        .join("\n\n");
+    let instructions = expand.iter()
+        .flat_map(|p| p.instructions.iter().map(move |line| format!("{}: {}", p.kind, line)))
+        .collect();
+
+    let mut grouped = vec![DualWritePhase {
+        kind: "expand".to_string(),
+        details: expand.iter().map(|p| p.details.as_str()).collect::<Vec<_>>().join("; "),
+        up_sql,
+        down_sql,
+        instructions,
+        transactional: false,
+        dangerous: expand.iter().any(|p| p.dangerous),
+    }];
+    grouped.extend(contract.into_iter().map(|p| DualWritePhase { kind: "contract".to_string(), ..p }));
+    grouped
+}
+
+/// `sql` of `phase` under a heading, in a transaction of its own when the
+/// phase is transactional.
+fn phase_sql(phase: &DualWritePhase, sql: &str) -> String {
+    let sql = format!("-- {}: {}\n{}", phase.kind, phase.details, sql);
+    if phase.transactional { format!("BEGIN;\n\n{}\n\nCOMMIT;", sql) } else { sql }
+}
+
+/// Rename the old column away and the new one into its place, moving the
+/// default and NOT NULL over.
+fn swap_sql(t: &str, c: &str, n: &str, o: &str, old: &Column, new: &Column, drop_sync: &str) -> String {
+    let mut statements = vec![format!("LOCK TABLE {} IN ACCESS EXCLUSIVE MODE;", t), drop_sync.to_string()];
+    if old.default_value.is_some() {
+        statements.push(format!("ALTER TABLE {} ALTER COLUMN {} DROP DEFAULT;", t, c));
+    }
+    if !old.is_nullable {
+        statements.push(format!("ALTER TABLE {} ALTER COLUMN {} DROP NOT NULL;", t, c));
+    }
+    statements.push(format!("ALTER TABLE {} RENAME COLUMN {} TO {};", t, c, o));
+    statements.push(format!("ALTER TABLE {} RENAME COLUMN {} TO {};", t, n, c));
+    if let Some(default) = &new.default_value {
+        statements.push(format!("ALTER TABLE {} ALTER COLUMN {} SET DEFAULT {};", t, c, default));
+    }
+    if !new.is_nullable {
+        // Scans the table while the lock is held
+        statements.push(format!("ALTER TABLE {} ALTER COLUMN {} SET NOT NULL;", t, c));
+    }
+    statements.join("\n")
+}
+
+/// Undo [`swap_sql`] and put the sync trigger back.
+fn unswap_sql(t: &str, c: &str, n: &str, o: &str, old: &Column, new: &Column, create_sync: &str) -> String {
+    let mut statements = vec![format!("LOCK TABLE {} IN ACCESS EXCLUSIVE MODE;", t)];
+    if new.default_value.is_some() {
+        statements.push(format!("ALTER TABLE {} ALTER COLUMN {} DROP DEFAULT;", t, c));
+    }
+    if !new.is_nullable {
+        statements.push(format!("ALTER TABLE {} ALTER COLUMN {} DROP NOT NULL;", t, c));
+    }
+    statements.push(format!("ALTER TABLE {} RENAME COLUMN {} TO {};", t, c, n));
+    statements.push(format!("ALTER TABLE {} RENAME COLUMN {} TO {};", t, o, c));
+    if let Some(default) = &old.default_value {
+        statements.push(format!("ALTER TABLE {} ALTER COLUMN {} SET DEFAULT {};", t, c, default));
+    }
+    if !old.is_nullable {
+        statements.push(format!("ALTER TABLE {} ALTER COLUMN {} SET NOT NULL;", t, c));
+    }
+    statements.push(create_sync.to_string());
+    statements.join("\n")
+}
+
+fn quote(name: &str) -> String {
+    format!("\"{}\"", name.replace('"', "\"\""))
+}
+
+/// Text for use inside a string literal; `%` is doubled for RAISE.
+fn literal_text(text: &str) -> String {
+    text.replace('\'', "''").replace('%', "%%")
+}
+
+/// Write each phase of a dual-write migration as a migration folder under
+/// `base_path`, returning the folders in the order they are applied.
+pub fn render_dual_write_migrations(
+    phases: &[DualWritePhase],
+    table: &str,
+    column: &str,
+    name: &str,
+    base_path: &str,
+) -> Result<Vec<String>, std::io::Error> {
+    let sanitized_name = sanitize_name(name);
+    let mut folders = Vec::new();
+
+    for (i, phase) in phases.iter().enumerate() {
+        // Each phase gets a later version than the one before
+        let phase_name = format!("{}_{}_{}", sanitized_name, i + 1, phase.kind);
+        let (migration_dir, timestamp) = create_migration_dir(base_path, &phase_name, VersionScheme::Timestamp)?;
+
+        let header = |script: &str| {
+            let mut lines = vec![
+                format!("-- Migration {} Script", script),
+                format!("-- Generated at: {}", Utc::now().to_rfc3339()),
+                format!("-- Phase {} of {} ({}): {}", i + 1, phases.len(), phase.kind, phase.details),
+            ];
+            lines.extend(phase.instructions.iter().map(|line| format!("-- {}", line)));
+            lines.push(String::new());
+            lines.join("\n")
+        };
+        let up_sql = if phase.transactional {
+            format!("{}BEGIN;\n\n{}\n\nCOMMIT;", header("UP"), phase.up_sql)
+        } else {
+            format!("{}{}", header("UP"), phase.up_sql)
+        };
+        fs::write(migration_dir.join("up.sql"), up_sql)?;
+        fs::write(
+            migration_dir.join("down.sql"),
+            format!("{}BEGIN;\n\n{}\n\nCOMMIT;", header("DOWN (Rollback)"), phase.down_sql),
+        )?;
+
+        let meta = MigrationMeta {
+            name: phase_name,
+            timestamp: timestamp.clone(),
+            generated_at: Utc::now().to_rfc3339(),
+            items_count: 1,
+            has_dangerous: phase.dangerous,
+            unacknowledged_dangerous: usize::from(phase.dangerous),
+            disk_estimate: None,
+            items: vec![MigrationItemMeta {
+                id: uuid::Uuid::new_v4().to_string(),
+                kind: "modified".to_string(),
+                object_type: "column".to_string(),
+                object_name: format!("{}.{}", table, column),
+                dangerous: phase.dangerous,
+                acknowledgment: None,
+                disk_estimate: None,
+                data_loss: None,
+            }],
+            phase: Some(MigrationPhase {
+                index: i + 1,
+                count: phases.len(),
+                kind: phase.kind.clone(),
+                instructions: phase.instructions.clone(),
+                transactional: phase.transactional,
+            }),
+            squashes: Vec::new(),
+        };
+        let meta_json = serde_json::to_string_pretty(&meta).map_err(std::io::Error::other)?;
+        fs::write(migration_dir.join("meta.json"), meta_json)?;
+
+        folders.push(migration_dir.to_string_lossy().to_string());
+    }
+
+    Ok(folders)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::schema::{Constraint, Table};
+
+    fn create_test_schema(amount_type: &str) -> SchemaModel {
+        let column = |name: &str, data_type: &str| Column {
+            name: name.to_string(),
+            data_type: data_type.to_string(),
+            is_nullable: false,
+            default_value: None,
+            ordinal_position: 1,
+            generation_expression: None,
+            comment: None,
+            inherited: false,
+            storage: None,
+            statistics_target: None,
+        };
+        SchemaModel {
+            tables: vec![Table {
+                name: "payments".to_string(),
+                columns: vec![column("id", "bigint"), column("amount", amount_type)],
+                primary_key: Some(Constraint {
+                    name: "payments_pkey".to_string(),
+                    constraint_type: "PRIMARY KEY".to_string(),
+                    columns: vec!["id".to_string()],
+                }),
+                unique_constraints: Vec::new(),
+                indexes: Vec::new(),
+                rls_enabled: false,
+                rls_forced: false,
+                policies: Vec::new(),
+                triggers: Vec::new(),
+                foreign_keys: Vec::new(),
+                comment: None,
+                inherits: Vec::new(),
+                unlogged: false,
+                storage_parameters: Vec::new(),
+            }],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_plan_dual_write() {
+        let source = create_test_schema("numeric(12,2)");
+        let target = create_test_schema("integer");
+        let options = DualWriteOptions {
+            batch_size: Some(500),
+            using: Some("{value} / 100.0".to_string()),
+            expand_contract: false,
+        };
+
+        let phases = plan_dual_write(&source, &target, "payments", "amount", &options).unwrap();
+        let kinds: Vec<_> = phases.iter().map(|p| p.kind.as_str()).collect();
+        assert_eq!(kinds, vec!["add_column", "sync_trigger", "backfill", "verify", "swap", "drop_old"]);
+
+        assert_eq!(phases[0].up_sql, "ALTER TABLE \"payments\" ADD COLUMN \"amount__new\" numeric(12,2);");
+        assert!(phases[1].up_sql.contains("NEW.\"amount__new\" := NEW.\"amount\" / 100.0;"));
+        assert!(phases[2].up_sql.contains("last_key bigint;"));
+        assert!(phases[2].up_sql.contains("LIMIT 500"));
+        assert!(!phases[2].transactional);
+        assert!(phases[4].up_sql.ends_with(
+            "ALTER TABLE \"payments\" RENAME COLUMN \"amount\" TO \"amount__old\";\n\
+             ALTER TABLE \"payments\" RENAME COLUMN \"amount__new\" TO \"amount\";\n\
+             ALTER TABLE \"payments\" ALTER COLUMN \"amount\" SET NOT NULL;"
+        ));
+
+        assert_eq!(phases[5].up_sql, "ALTER TABLE \"payments\" DROP COLUMN IF EXISTS \"amount__old\";");
+        assert!(phases[5].dangerous && !phases[4].dangerous);
+
+        assert!(plan_dual_write(&target, &target, "payments", "amount", &options).is_err());
+        let mut keyless = target.clone();
+        keyless.tables[0].primary_key = None;
+        assert!(plan_dual_write(&source, &keyless, "payments", "amount", &options)
+            .unwrap_err()
+            .contains("primary key"));
+    }
+
+    #[test]
+    fn test_expand_contract() {
+        let source = create_test_schema("numeric(12,2)");
+        let target = create_test_schema("integer");
+        let options = DualWriteOptions { batch_size: Some(500), using: None, expand_contract: true };
+        let phases = plan_dual_write(&source, &target, "payments", "amount", &options).unwrap();
+
+        let grouped = expand_contract(phases.clone());
+        let kinds: Vec<_> = grouped.iter().map(|p| p.kind.as_str()).collect();
+        assert_eq!(kinds, vec!["expand", "contract"]);
+        let (expand, contract) = (&grouped[0], &grouped[1]);
+        assert!(!expand.transactional && !expand.dangerous);
+
+        let order = |sql: &str| -> Vec<usize> {
+            ["add_column", "sync_trigger", "backfill", "verify", "swap"].iter()
+                .map(|kind| sql.find(&format!("-- {}: ", kind)).unwrap())
+                .collect()
+        };
+        let up = order(&expand.up_sql);
+        assert!(up.windows(2).all(|w| w[0] < w[1]));
+        let down = order(&expand.down_sql);
+        assert!(down.windows(2).all(|w| w[0] > w[1]));
+        assert!(!expand.up_sql.contains("-- drop_old") && !expand.down_sql.contains("-- drop_old"));
+
+        assert!(expand.up_sql.starts_with("BEGIN;\n\n-- add_column: "));
+        assert!(expand.up_sql.contains("COMMIT;\n\n-- backfill: Copy existing rows in batches of 500\nDO $$"));
+        assert!(expand.up_sql.ends_with("COMMIT;"));
+        assert!(expand.down_sql.starts_with("BEGIN;\n\n-- swap: "));
+        assert!(expand.down_sql.contains("COMMIT;\n\n-- backfill: "));
+        assert!(expand.down_sql.ends_with("COMMIT;"));
+        assert_eq!(expand.down_sql.matches("BEGIN;").count(), 4);
+        assert_eq!(expand.down_sql.matches("BEGIN;").count(), expand.up_sql.matches("BEGIN;").count());
+
+        assert_eq!(contract.details, phases[5].details);
+        assert_eq!(contract.up_sql, phases[5].up_sql);
+        assert_eq!(contract.down_sql, phases[5].down_sql);
+        assert!(contract.transactional && contract.dangerous);
+    }
+}
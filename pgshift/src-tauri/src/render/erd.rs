@@ -0,0 +1,439 @@
+//! Entity-relationship views of a schema model.
+//!
+//! [`render_dbml`] writes the schema in DBML, the format of dbdiagram.io and
+//! dbdocs, [`render_mermaid`] as a Mermaid `erDiagram` for markdown pages,
+//! and [`schema_graph`] gives the tables and foreign keys as nodes and edges
+//! for drawing a diagram.
+
+use serde::{Deserialize, Serialize};
+use crate::model::schema::{Column, SchemaModel, Table};
+
+/// Tables and foreign keys of a schema, as a graph.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SchemaGraph {
+    pub nodes: Vec<GraphNode>,
+    pub edges: Vec<GraphEdge>,
+}
+
+/// A table.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GraphNode {
+    /// The table name.
+    pub id: String,
+    pub columns: Vec<GraphColumn>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub comment: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GraphColumn {
+    pub name: String,
+    pub data_type: String,
+    pub nullable: bool,
+    pub primary_key: bool,
+    /// Part of a foreign key.
+    pub foreign_key: bool,
+}
+
+/// A foreign key, from the referencing table to the referenced one.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GraphEdge {
+    /// `table.constraint`, unique within the schema.
+    pub id: String,
+    pub source: String,
+    pub target: String,
+    pub columns: Vec<String>,
+    pub referenced_columns: Vec<String>,
+    pub on_delete: String,
+    pub on_update: String,
+}
+
+/// The tables of `schema` as nodes, sorted by name, and their foreign keys
+/// as edges. Keys referencing tables not in the schema are left out.
+pub fn schema_graph(schema: &SchemaModel) -> SchemaGraph {
+    let tables = sorted_tables(schema);
+    let nodes = tables.iter()
+        .map(|table| GraphNode {
+            id: table.name.clone(),
+            columns: table.columns.iter()
+                .filter(|c| !c.inherited)
+                .map(|c| GraphColumn {
+                    name: c.name.clone(),
+                    data_type: c.data_type.clone(),
+                    nullable: c.is_nullable,
+                    primary_key: is_primary_key(table, c),
+                    foreign_key: table.foreign_keys.iter().any(|fk| fk.columns.contains(&c.name)),
+                })
+                .collect(),
+            comment: table.comment.clone(),
+        })
+        .collect();
+    let edges = tables.iter()
+        .flat_map(|table| table.foreign_keys.iter().map(move |fk| (table, fk)))
+        .filter(|(_, fk)| schema.find_table(&fk.referenced_table).is_some())
+        .map(|(table, fk)| GraphEdge {
+            id: format!("{}.{}", table.name, fk.name),
+            source: table.name.clone(),
+            target: fk.referenced_table.clone(),
+            columns: fk.columns.clone(),
+            referenced_columns: fk.referenced_columns.clone(),
+            on_delete: fk.on_delete.clone(),
+            on_update: fk.on_update.clone(),
+        })
+        .collect();
+
+    SchemaGraph { nodes, edges }
+}
+
+/// DBML describing the enums, tables, indexes and foreign keys of `schema`.
+pub fn render_dbml(schema: &SchemaModel) -> String {
+    let mut blocks = Vec::new();
+
+    let mut enums: Vec<_> = schema.enums.iter().collect();
+    enums.sort_by(|a, b| a.name.cmp(&b.name));
+    for enum_type in enums {
+        let mut lines = vec![format!("Enum {} {{", name(&enum_type.name))];
+        lines.extend(enum_type.values.iter().map(|v| format!("  {}", name(v))));
+        lines.push("}".to_string());
+        blocks.push(lines.join("\n"));
+    }
+
+    let tables = sorted_tables(schema);
+    for table in &tables {
+        blocks.push(table_dbml(table));
+    }
+
+    let refs: Vec<String> = tables.iter()
+        .flat_map(|table| table.foreign_keys.iter().map(move |fk| {
+            let mut settings = Vec::new();
+            for (event, action) in [("delete", &fk.on_delete), ("update", &fk.on_update)] {
+                if action != "NO ACTION" {
+                    settings.push(format!("{}: {}", event, action.to_lowercase()));
+                }
+            }
+            format!(
+                "Ref {}: {}.{} > {}.{}{}",
+                name(&fk.name),
+                name(&table.name),
+                column_list(&fk.columns),
+                name(&fk.referenced_table),
+                column_list(&fk.referenced_columns),
+                settings_suffix(&settings)
+            )
+        }))
+        .collect();
+    if !refs.is_empty() {
+        blocks.push(refs.join("\n"));
+    }
+
+    let mut dbml = blocks.join("\n\n");
+    dbml.push('\n');
+    dbml
+}
+
+/// A Mermaid `erDiagram` of the tables and foreign keys of `schema`, with
+/// `title` in its front matter. Keys referencing tables not in the schema
+/// are left out.
+pub fn render_mermaid(schema: &SchemaModel, title: Option<&str>) -> String {
+    let mut lines = Vec::new();
+    if let Some(title) = title {
+        lines.push("---".to_string());
+        lines.push(format!("title: \"{}\"", title.replace('"', "'")));
+        lines.push("---".to_string());
+    }
+    lines.push("erDiagram".to_string());
+
+    let tables = sorted_tables(schema);
+    for table in &tables {
+        let columns: Vec<&Column> = table.columns.iter().filter(|c| !c.inherited).collect();
+        if columns.is_empty() {
+            lines.push(format!("    {} {{ }}", mermaid_entity(&table.name)));
+            continue;
+        }
+        lines.push(format!("    {} {{", mermaid_entity(&table.name)));
+        for column in columns {
+            let mut keys = Vec::new();
+            if is_primary_key(table, column) {
+                keys.push("PK");
+            }
+            if table.foreign_keys.iter().any(|fk| fk.columns.contains(&column.name)) {
+                keys.push("FK");
+            }
+            if table.unique_constraints.iter().any(|u| u.columns == [column.name.clone()]) {
+                keys.push("UK");
+            }
+            let mut line = format!("        {} {}", mermaid_word(&column.data_type), mermaid_word(&column.name));
+            if !keys.is_empty() {
+                line.push(' ');
+                line.push_str(&keys.join(", "));
+            }
+            if let Some(comment) = &column.comment {
+                line.push_str(&format!(" \"{}\"", mermaid_text(comment)));
+            }
+            lines.push(line);
+        }
+        lines.push("    }".to_string());
+    }
+
+    for table in &tables {
+        for fk in &table.foreign_keys {
+            if schema.find_table(&fk.referenced_table).is_none() {
+                continue;
+            }
+            // The referenced row is optional when a key column can be null
+            let required = fk.columns.iter().all(|c| table.find_column(c).is_some_and(|col| !col.is_nullable));
+            // At most one referencing row when the key columns are unique
+            let unique = table.primary_key.iter()
+                .chain(&table.unique_constraints)
+                .any(|c| c.columns == fk.columns);
+            // Identifying when the key is part of the primary key
+            let identifying = table.primary_key.as_ref()
+                .is_some_and(|pk| fk.columns.iter().all(|c| pk.columns.contains(c)));
+            lines.push(format!(
+                "    {} {}{}{} {} : \"{}\"",
+                mermaid_entity(&fk.referenced_table),
+                if required { "||" } else { "|o" },
+                if identifying { "--" } else { ".." },
+                if unique { "o|" } else { "o{" },
+                mermaid_entity(&table.name),
+                mermaid_text(&fk.name)
+            ));
+        }
+    }
+
+    let mut mermaid = lines.join("\n");
+    mermaid.push('\n');
+    mermaid
+}
+
+fn table_dbml(table: &Table) -> String {
+    let mut lines = vec![format!("Table {} {{", name(&table.name))];
+    let single_key = table.primary_key.as_ref().filter(|pk| pk.columns.len() == 1);
+    for column in table.columns.iter().filter(|c| !c.inherited) {
+        let mut settings = Vec::new();
+        if single_key.is_some_and(|pk| pk.columns[0] == column.name) {
+            settings.push("pk".to_string());
+        }
+        if !column.is_nullable {
+            settings.push("not null".to_string());
+        }
+        match column.default_value.as_deref() {
+            Some(default) if default.starts_with("nextval(") => settings.push("increment".to_string()),
+            Some(default) => settings.push(format!("default: {}", default_value(default))),
+            None => {}
+        }
+        if let Some(comment) = &column.comment {
+            settings.push(format!("note: {}", string(comment)));
+        }
+        lines.push(format!("  {} {}{}", name(&column.name), data_type(column), settings_suffix(&settings)));
+    }
+
+    let mut indexes = Vec::new();
+    if let Some(pk) = table.primary_key.as_ref().filter(|pk| pk.columns.len() > 1) {
+        indexes.push(format!("{} [pk]", column_list(&pk.columns)));
+    }
+    for constraint in &table.unique_constraints {
+        indexes.push(format!("{} [unique, name: {}]", column_list(&constraint.columns), string(&constraint.name)));
+    }
+    // Indexes backing the key and unique constraints are already listed
+    let constraint_names: Vec<&str> = table.primary_key.iter()
+        .chain(&table.unique_constraints)
+        .map(|c| c.name.as_str())
+        .collect();
+    for index in table.indexes.iter().filter(|i| !constraint_names.contains(&i.name.as_str())) {
+        let columns: Vec<String> = index.columns.iter()
+            .map(|c| if table.find_column(c).is_some() { name(c) } else { format!("`{}`", c) })
+            .collect();
+        let columns = if columns.len() == 1 { columns[0].clone() } else { format!("({})", columns.join(", ")) };
+        let mut settings = Vec::new();
+        if index.is_unique {
+            settings.push("unique".to_string());
+        }
+        if index.index_type != "btree" {
+            settings.push(format!("type: {}", index.index_type));
+        }
+        settings.push(format!("name: {}", string(&index.name)));
+        indexes.push(format!("{}{}", columns, settings_suffix(&settings)));
+    }
+    if !indexes.is_empty() {
+        lines.push(String::new());
+        lines.push("  indexes {".to_string());
+        lines.extend(indexes.iter().map(|i| format!("    {}", i)));
+        lines.push("  }".to_string());
+    }
+
+    if let Some(comment) = &table.comment {
+        lines.push(String::new());
+        lines.push(format!("  Note: {}", string(comment)));
+    }
+    lines.push("}".to_string());
+    lines.join("\n")
+}
+
+fn sorted_tables(schema: &SchemaModel) -> Vec<&Table> {
+    let mut tables: Vec<&Table> = schema.tables.iter().collect();
+    tables.sort_by(|a, b| a.name.cmp(&b.name));
+    tables
+}
+
+fn is_primary_key(table: &Table, column: &Column) -> bool {
+    table.primary_key.as_ref().is_some_and(|pk| pk.columns.contains(&column.name))
+}
+
+/// `name`, double-quoted unless it is a plain identifier.
+fn name(name: &str) -> String {
+    let plain = name.chars().next().is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+    if plain {
+        name.to_string()
+    } else {
+        format!("\"{}\"", name.replace('\\', "\\\\").replace('"', "\\\""))
+    }
+}
+
+/// A column type; DBML needs types with spaces quoted.
+fn data_type(column: &Column) -> String {
+    if column.data_type.contains(' ') {
+        format!("\"{}\"", column.data_type)
+    } else {
+        column.data_type.clone()
+    }
+}
+
+/// `column` or `(a, b)`.
+fn column_list(columns: &[String]) -> String {
+    match columns {
+        [column] => name(column),
+        columns => format!("({})", columns.iter().map(|c| name(c)).collect::<Vec<_>>().join(", ")),
+    }
+}
+
+fn string(text: &str) -> String {
+    format!("'{}'", text.replace('\\', "\\\\").replace('\'', "\\'").replace('\n', "\\n"))
+}
+
+/// A default as DBML writes it: numbers and booleans as they are, string
+/// literals quoted, anything else as an expression.
+fn default_value(default: &str) -> String {
+    if default.parse::<f64>().is_ok() || matches!(default, "true" | "false" | "NULL") {
+        return default.to_lowercase();
+    }
+    // A literal cast to its type, e.g. 'active'::status
+    if let Some(literal) = default.strip_prefix('\'') {
+        if let Some((text, cast)) = literal.rsplit_once('\'') {
+            if cast.is_empty() || cast.starts_with("::") && !cast.contains('(') {
+                return string(&text.replace("''", "'"));
+            }
+        }
+    }
+    format!("`{}`", default)
+}
+
+/// An entity name, double-quoted unless it is a plain identifier.
+fn mermaid_entity(name: &str) -> String {
+    let plain = name.chars().next().is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-');
+    if plain {
+        name.to_string()
+    } else {
+        format!("\"{}\"", mermaid_text(name))
+    }
+}
+
+/// An attribute name or type. Mermaid cannot quote them, so characters it
+/// does not allow become underscores: `timestamp_with_time_zone`,
+/// `numeric(12_2)`.
+fn mermaid_word(word: &str) -> String {
+    let mut word: String = word.replace(", ", ",")
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() || "_-[]()".contains(c) { c } else { '_' })
+        .collect();
+    if word.starts_with(|c: char| c.is_ascii_digit() || c == '-') {
+        word.insert(0, '_');
+    }
+    word
+}
+
+/// Text inside double quotes, which Mermaid cannot escape.
+fn mermaid_text(text: &str) -> String {
+    text.replace('"', "'").replace('\n', " ")
+}
+
+fn settings_suffix(settings: &[String]) -> String {
+    if settings.is_empty() {
+        String::new()
+    } else {
+        format!(" [{}]", settings.join(", "))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ddl::parse::parse_schema_sql;
+
+    #[test]
+    fn test_render_dbml_and_graph() {
+        let schema = parse_schema_sql(r#"
+            CREATE TYPE status AS ENUM ('active', 'archived');
+            CREATE TABLE users (
+                id serial PRIMARY KEY,
+                email text NOT NULL,
+                status status NOT NULL DEFAULT 'active',
+                created_at timestamp with time zone DEFAULT now()
+            );
+            COMMENT ON TABLE users IS 'People who can sign in';
+            CREATE UNIQUE INDEX users_email_idx ON users (lower(email));
+            CREATE TABLE memberships (
+                user_id integer REFERENCES users (id) ON DELETE CASCADE,
+                team_id integer,
+                PRIMARY KEY (user_id, team_id)
+            );
+        "#).schema;
+
+        let dbml = render_dbml(&schema);
+        assert!(dbml.starts_with("Enum status {\n  active\n  archived\n}"));
+        assert!(dbml.contains("  id integer [pk, not null, increment]"));
+        assert!(dbml.contains("  status status [not null, default: 'active']"));
+        assert!(dbml.contains("  created_at \"timestamp with time zone\" [default: `now()`]"));
+        assert!(dbml.contains("    `lower(email)` [unique, name: 'users_email_idx']"));
+        assert!(dbml.contains("    (user_id, team_id) [pk]"));
+        assert!(dbml.contains("  Note: 'People who can sign in'"));
+        assert!(dbml.contains("Ref memberships_user_id_fkey: memberships.user_id > users.id [delete: cascade]"));
+        // Tables are sorted by name
+        assert!(dbml.find("Table memberships").unwrap() < dbml.find("Table users").unwrap());
+
+        let graph = schema_graph(&schema);
+        assert_eq!(graph.nodes.iter().map(|n| n.id.as_str()).collect::<Vec<_>>(), ["memberships", "users"]);
+        let user_id = &graph.nodes[0].columns[0];
+        assert!(user_id.primary_key && user_id.foreign_key);
+        assert_eq!(graph.edges.len(), 1);
+        assert_eq!((graph.edges[0].source.as_str(), graph.edges[0].target.as_str()), ("memberships", "users"));
+    }
+
+    #[test]
+    fn test_render_mermaid() {
+        let schema = parse_schema_sql(r#"
+            CREATE TABLE users (
+                id serial PRIMARY KEY,
+                email text NOT NULL UNIQUE,
+                amount numeric(12, 2),
+                created_at timestamp with time zone
+            );
+            COMMENT ON COLUMN users.email IS 'Sign-in "address"';
+            CREATE TABLE profiles (user_id integer PRIMARY KEY REFERENCES users (id));
+            CREATE TABLE posts (id serial PRIMARY KEY, author_id integer REFERENCES users (id));
+        "#).schema;
+
+        let mermaid = render_mermaid(&schema, Some("v1.2"));
+        assert!(mermaid.starts_with("---\ntitle: \"v1.2\"\n---\nerDiagram\n    posts {"));
+        assert!(mermaid.contains("        integer id PK\n"));
+        assert!(mermaid.contains("        text email UK \"Sign-in 'address'\"\n"));
+        assert!(mermaid.contains("        numeric(12_2) amount\n"));
+        assert!(mermaid.contains("        timestamp_with_time_zone created_at\n"));
+        assert!(mermaid.contains("        integer user_id PK, FK\n"));
+        assert!(mermaid.contains("    users |o..o{ posts : \"posts_author_id_fkey\"\n"));
+        assert!(mermaid.contains("    users ||--o| profiles : \"profiles_user_id_fkey\"\n"));
+        assert!(render_mermaid(&schema, None).starts_with("erDiagram\n"));
+    }
+}
@@ -0,0 +1,103 @@
+//! Migrations in Flyway's layout.
+//!
+//! Flyway reads versioned scripts `V<version>__<description>.sql` and undo
+//! scripts `U<version>__<description>.sql` from one directory, runs each in
+//! a transaction of its own, and records a checksum of every script it
+//! applies to detect scripts edited afterwards. The scripts written here
+//! therefore have no `BEGIN`/`COMMIT` of their own and no generation time,
+//! so rendering the same diff again gives the same checksum.
+
+use std::fs;
+use std::io;
+use crate::diff::DiffReport;
+use super::sql::{generate_down_sql, generate_up_sql, unwrap_script, RenderOptions};
+use super::version::{create_migration_file, VersionScheme};
+
+/// Write the versioned and undo scripts of `report` as Flyway migration
+/// `name` under `base_path` and return the path of the versioned script.
+pub(crate) fn render_flyway_files(
+    report: &DiffReport,
+    name: &str,
+    base_path: &str,
+    options: &RenderOptions,
+) -> io::Result<String> {
+    if options.version_scheme != VersionScheme::Timestamp {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "Flyway versions must be numeric; use the timestamp version scheme",
+        ));
+    }
+
+    let (up_path, version) = create_migration_file(base_path, |version| format!("V{}__{}.sql", version, name))?;
+    let script_name = format!("V{}__{}", version, name);
+    let trace = options.trace_comments.then_some(script_name.as_str());
+
+    let up_sql = unwrap_script(&generate_up_sql(report, trace, options.batch_alter_table), true);
+    fs::write(&up_path, up_sql)?;
+    let down_sql = unwrap_script(&generate_down_sql(report, trace), true);
+    fs::write(up_path.with_file_name(format!("U{}__{}.sql", version, name)), down_sql)?;
+
+    Ok(up_path.to_string_lossy().to_string())
+}
+
+/// The checksum Flyway records for a script in `flyway_schema_history`: a
+/// CRC32 of its lines, without line endings or a leading byte order mark.
+pub fn flyway_checksum(script: &str) -> i32 {
+    let script = script.strip_prefix('\u{feff}').unwrap_or(script);
+    let crc = script.split(['\r', '\n']).fold(0, |crc, line| crc32(crc, line.as_bytes()));
+    crc as i32
+}
+
+/// Continue the CRC32 (IEEE) `crc` over `bytes`.
+fn crc32(crc: u32, bytes: &[u8]) -> u32 {
+    let mut crc = !crc;
+    for byte in bytes {
+        crc ^= *byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 == 1 { (crc >> 1) ^ 0xEDB8_8320 } else { crc >> 1 };
+        }
+    }
+    !crc
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::diff::{DiffItem, DiffKind};
+    use crate::render::sql::{render_migration_files, MigrationFormat};
+
+    #[test]
+    fn test_render_flyway_files() {
+        let mut report = DiffReport::new();
+        report.items.push(DiffItem::new(
+            DiffKind::Added, "table", "users", "Create table 'users'",
+            "CREATE TABLE \"users\" (\"id\" integer);", "DROP TABLE \"users\";", false,
+        ));
+        let base = std::env::temp_dir().join(format!("pgshift_flyway_{}", uuid::Uuid::new_v4()));
+        let base_path = base.to_str().unwrap();
+        let options = RenderOptions { format: MigrationFormat::Flyway, ..Default::default() };
+
+        let first = render_migration_files(&report, "Add users", base_path, &options).unwrap();
+        let second = render_migration_files(&report, "Add users", base_path, &options).unwrap();
+        let (first_sql, second_sql) = (fs::read_to_string(&first).unwrap(), fs::read_to_string(&second).unwrap());
+        let file_name = std::path::Path::new(&first).file_name().unwrap().to_string_lossy().to_string();
+        let undo = base.join(file_name.replacen('V', "U", 1));
+        let undo_sql = fs::read_to_string(&undo).unwrap();
+        fs::remove_dir_all(&base).unwrap();
+
+        assert!(file_name.starts_with('V') && file_name.ends_with("__add_users.sql"));
+        assert_ne!(first, second);
+        assert!(first_sql.contains("CREATE TABLE \"users\" (\"id\" integer);"));
+        assert!(!first_sql.contains("BEGIN;") && !first_sql.contains("COMMIT;") && !first_sql.contains("Generated at"));
+        assert!(undo_sql.contains("DROP TABLE \"users\";"));
+        // The same diff gives the same checksum
+        assert_eq!(flyway_checksum(&first_sql), flyway_checksum(&second_sql));
+
+        assert_eq!(crc32(0, b"123456789"), 0xCBF4_3926);
+        assert_eq!(flyway_checksum("\u{feff}SELECT 1;\r\nSELECT 2;\r\n"), flyway_checksum("SELECT 1;\nSELECT 2;"));
+
+        let ulid = RenderOptions { version_scheme: VersionScheme::Ulid, ..options };
+        assert!(render_migration_files(&report, "x", base_path, &ulid).is_err());
+        fs::remove_dir_all(&base).ok();
+    }
+}
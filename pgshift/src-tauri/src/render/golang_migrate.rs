@@ -0,0 +1,69 @@
+//! Migrations in golang-migrate's layout.
+//!
+//! golang-migrate reads `<version>_<name>.up.sql` and
+//! `<version>_<name>.down.sql` pairs from one flat directory, with unsigned
+//! integer versions, and runs each file as it is, so the scripts keep their
+//! own `BEGIN`/`COMMIT`.
+
+use std::fs;
+use std::io;
+use crate::diff::DiffReport;
+use super::sql::{generate_down_sql, generate_up_sql, RenderOptions};
+use super::version::{create_migration_file, VersionScheme};
+
+/// Write the up and down scripts of `report` as golang-migrate migration
+/// `name` under `base_path` and return the path of the up script.
+pub(crate) fn render_golang_migrate_files(
+    report: &DiffReport,
+    name: &str,
+    base_path: &str,
+    options: &RenderOptions,
+) -> io::Result<String> {
+    if options.version_scheme != VersionScheme::Timestamp {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "golang-migrate versions must be numeric; use the timestamp version scheme",
+        ));
+    }
+
+    let (up_path, version) = create_migration_file(base_path, |version| format!("{}_{}.up.sql", version, name))?;
+    let migration_name = format!("{}_{}", version, name);
+    let trace = options.trace_comments.then_some(migration_name.as_str());
+
+    fs::write(&up_path, generate_up_sql(report, trace, options.batch_alter_table))?;
+    fs::write(up_path.with_file_name(format!("{}.down.sql", migration_name)), generate_down_sql(report, trace))?;
+
+    Ok(up_path.to_string_lossy().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::diff::{DiffItem, DiffKind};
+    use crate::render::sql::{render_migration_files, MigrationFormat};
+
+    #[test]
+    fn test_render_golang_migrate_files() {
+        let mut report = DiffReport::new();
+        report.items.push(DiffItem::new(
+            DiffKind::Added, "table", "users", "Create table 'users'",
+            "CREATE TABLE \"users\" (\"id\" integer);", "DROP TABLE \"users\";", false,
+        ));
+        let base = std::env::temp_dir().join(format!("pgshift_golang_migrate_{}", uuid::Uuid::new_v4()));
+        let base_path = base.to_str().unwrap();
+        // A folder from the future; flat files are versioned after it
+        fs::create_dir_all(base.join("29990101000000__future")).unwrap();
+        let options = RenderOptions { format: MigrationFormat::GolangMigrate, ..Default::default() };
+
+        let first = render_migration_files(&report, "Add users", base_path, &options).unwrap();
+        let second = render_migration_files(&report, "Add users", base_path, &options).unwrap();
+        let down_sql = fs::read_to_string(base.join("29990101000001_add_users.down.sql")).unwrap();
+        let up_sql = fs::read_to_string(&first).unwrap();
+        fs::remove_dir_all(&base).unwrap();
+
+        assert!(first.ends_with("29990101000001_add_users.up.sql"));
+        assert!(second.ends_with("29990101000002_add_users.up.sql"));
+        assert!(up_sql.contains("BEGIN;") && up_sql.contains("CREATE TABLE \"users\" (\"id\" integer);"));
+        assert!(down_sql.contains("DROP TABLE \"users\";"));
+    }
+}
@@ -0,0 +1,98 @@
+//! HTML rendering for diff reports.
+
+use crate::diff::{DiffKind, DiffReport};
+use super::report::data_loss_suffix;
+
+/// Render a diff report as a standalone HTML document.
+pub fn render_report_html(report: &DiffReport, title: &str) -> String {
+    let added = report.items.iter().filter(|i| i.kind == DiffKind::Added).count();
+    let removed = report.items.iter().filter(|i| i.kind == DiffKind::Removed).count();
+    let modified = report.items.iter().filter(|i| i.kind == DiffKind::Modified).count();
+    let reordered = report.items.iter().filter(|i| i.kind == DiffKind::Reordered).count();
+    let renamed = report.items.iter().filter(|i| i.kind == DiffKind::Renamed).count();
+    let dangerous = report.items.iter().filter(|i| i.dangerous).count();
+
+    let mut html = String::new();
+
+    html.push_str("<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n");
+    html.push_str(&format!("<title>{}</title>\n", escape_html(title)));
+    html.push_str(STYLE);
+    html.push_str("</head>\n<body>\n");
+
+    html.push_str(&format!("<h1>{}</h1>\n", escape_html(title)));
+    html.push_str(&format!(
+        "<p class=\"meta\">Source: {}<br>Target: {}<br>Generated at: {}</p>\n",
+        escape_html(&report.source_connection),
+        escape_html(&report.target_connection),
+        escape_html(&report.generated_at)
+    ));
+
+    html.push_str(&format!(
+        "<p class=\"summary\"><span class=\"added\">{} added</span> · \
+         <span class=\"removed\">{} removed</span> · \
+         <span class=\"modified\">{} modified</span> · \
+         <span class=\"modified\">{} reordered</span> · \
+         <span class=\"modified\">{} renamed</span> · \
+         <span class=\"dangerous\">{} dangerous</span></p>\n",
+        added, removed, modified, reordered, renamed, dangerous
+    ));
+
+    for warning in &report.warnings {
+        html.push_str(&format!("<p class=\"warning\">{}</p>\n", escape_html(warning)));
+    }
+
+    if report.items.is_empty() {
+        html.push_str("<p>No differences found. The schemas are in sync.</p>\n");
+    } else {
+        html.push_str("<table>\n<tr><th>Change</th><th>Type</th><th>Object</th><th>Details</th><th>SQL</th></tr>\n");
+        for item in &report.items {
+            let kind = format!("{:?}", item.kind).to_lowercase();
+            html.push_str(&format!(
+                "<tr{}><td class=\"{}\">{}</td><td>{}</td><td>{}</td><td>{}</td><td><pre>{}</pre></td></tr>\n",
+                if item.dangerous { " class=\"danger\"" } else { "" },
+                kind,
+                kind,
+                escape_html(&item.object_type),
+                escape_html(&item.object_name),
+                escape_html(&format!("{}{}", item.details, data_loss_suffix(item))),
+                escape_html(&item.generated_up_sql)
+            ));
+        }
+        html.push_str("</table>\n");
+    }
+
+    html.push_str("</body>\n</html>\n");
+    html
+}
+
+/// Escape text for inclusion in HTML.
+pub fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+const STYLE: &str = r#"<style>
+body { font-family: -apple-system, "Segoe UI", sans-serif; margin: 2em; color: #222; }
+.meta { color: #666; font-size: 0.9em; }
+table { border-collapse: collapse; width: 100%; }
+th, td { border: 1px solid #ddd; padding: 6px 8px; text-align: left; vertical-align: top; }
+th { background: #f4f4f4; }
+pre { margin: 0; white-space: pre-wrap; font-size: 0.85em; }
+.added { color: #2e7d32; }
+.removed { color: #c62828; }
+.modified { color: #ef6c00; }
+.dangerous { color: #c62828; font-weight: bold; }
+tr.danger { background: #fff4f4; }
+.warning { background: #fff8e1; border-left: 4px solid #ef6c00; padding: 6px 10px; }
+</style>
+"#;
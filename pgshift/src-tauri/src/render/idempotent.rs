@@ -0,0 +1,348 @@
+//! Idempotent variants of generated statements.
+//!
+//! A migration that failed halfway, or ran outside a transaction, leaves
+//! some of its objects behind, and running it again fails on the first one.
+//! Here each statement is rewritten so that it does nothing when its work is
+//! already done: `IF NOT EXISTS`, `IF EXISTS` or `CREATE OR REPLACE` where
+//! PostgreSQL has them, and otherwise a `DO` block that checks the catalogs
+//! before running the statement. Statements that are already idempotent,
+//! such as `ALTER COLUMN ... SET DEFAULT`, `COMMENT ON` and `GRANT`, and
+//! SQL this module does not recognize are left as they are.
+
+use crate::ddl::parse::split_statements;
+use crate::diff::DiffReport;
+
+/// Tag of the dollar quotes around `DO` block bodies.
+const DO_TAG: &str = "$pgshift$";
+
+/// Statement prefixes and their idempotent replacements.
+const REWRITES: &[(&str, &str)] = &[
+    ("CREATE TABLE ", "CREATE TABLE IF NOT EXISTS "),
+    ("CREATE UNLOGGED TABLE ", "CREATE UNLOGGED TABLE IF NOT EXISTS "),
+    ("CREATE FOREIGN TABLE ", "CREATE FOREIGN TABLE IF NOT EXISTS "),
+    ("CREATE INDEX CONCURRENTLY ", "CREATE INDEX CONCURRENTLY IF NOT EXISTS "),
+    ("CREATE UNIQUE INDEX CONCURRENTLY ", "CREATE UNIQUE INDEX CONCURRENTLY IF NOT EXISTS "),
+    ("CREATE INDEX ", "CREATE INDEX IF NOT EXISTS "),
+    ("CREATE UNIQUE INDEX ", "CREATE UNIQUE INDEX IF NOT EXISTS "),
+    ("CREATE SEQUENCE ", "CREATE SEQUENCE IF NOT EXISTS "),
+    ("CREATE SCHEMA ", "CREATE SCHEMA IF NOT EXISTS "),
+    ("CREATE EXTENSION ", "CREATE EXTENSION IF NOT EXISTS "),
+    ("CREATE SERVER ", "CREATE SERVER IF NOT EXISTS "),
+    ("CREATE USER MAPPING FOR ", "CREATE USER MAPPING IF NOT EXISTS FOR "),
+    ("CREATE FUNCTION ", "CREATE OR REPLACE FUNCTION "),
+    ("CREATE PROCEDURE ", "CREATE OR REPLACE PROCEDURE "),
+    ("CREATE VIEW ", "CREATE OR REPLACE VIEW "),
+    ("DROP TABLE ", "DROP TABLE IF EXISTS "),
+    ("DROP FOREIGN TABLE ", "DROP FOREIGN TABLE IF EXISTS "),
+    ("DROP INDEX CONCURRENTLY ", "DROP INDEX CONCURRENTLY IF EXISTS "),
+    ("DROP INDEX ", "DROP INDEX IF EXISTS "),
+    ("DROP SEQUENCE ", "DROP SEQUENCE IF EXISTS "),
+    ("DROP VIEW ", "DROP VIEW IF EXISTS "),
+    ("DROP MATERIALIZED VIEW ", "DROP MATERIALIZED VIEW IF EXISTS "),
+    ("DROP TYPE ", "DROP TYPE IF EXISTS "),
+    ("DROP FUNCTION ", "DROP FUNCTION IF EXISTS "),
+    ("DROP PROCEDURE ", "DROP PROCEDURE IF EXISTS "),
+    ("DROP TRIGGER ", "DROP TRIGGER IF EXISTS "),
+    ("DROP POLICY ", "DROP POLICY IF EXISTS "),
+    ("DROP PUBLICATION ", "DROP PUBLICATION IF EXISTS "),
+    ("DROP SUBSCRIPTION ", "DROP SUBSCRIPTION IF EXISTS "),
+    ("DROP SERVER ", "DROP SERVER IF EXISTS "),
+    ("DROP USER MAPPING FOR ", "DROP USER MAPPING IF EXISTS FOR "),
+    ("DROP SCHEMA ", "DROP SCHEMA IF EXISTS "),
+    ("DROP EXTENSION ", "DROP EXTENSION IF EXISTS "),
+    ("DROP ROLE ", "DROP ROLE IF EXISTS "),
+];
+
+/// `ALTER TABLE` actions and their idempotent replacements.
+const ACTION_REWRITES: &[(&str, &str)] = &[
+    ("ADD COLUMN ", "ADD COLUMN IF NOT EXISTS "),
+    ("DROP COLUMN ", "DROP COLUMN IF EXISTS "),
+    ("DROP CONSTRAINT ", "DROP CONSTRAINT IF EXISTS "),
+];
+
+/// A copy of `report` whose up and down SQL are idempotent.
+pub(crate) fn idempotent_report(report: &DiffReport) -> DiffReport {
+    let mut report = report.clone();
+    for item in &mut report.items {
+        item.generated_up_sql = idempotent_sql(&item.generated_up_sql);
+        item.generated_down_sql = idempotent_sql(&item.generated_down_sql);
+    }
+    report
+}
+
+/// `sql` with every statement replaced by its idempotent variant; comments
+/// and layout between statements are kept.
+pub fn idempotent_sql(sql: &str) -> String {
+    let mut out = String::with_capacity(sql.len());
+    let mut rest = sql;
+    for statement in split_statements(sql) {
+        let Some(start) = rest.find(&statement) else { continue };
+        out.push_str(&rest[..start]);
+        out.push_str(&idempotent_statement(&statement));
+        rest = &rest[start + statement.len()..];
+    }
+    out.push_str(rest);
+    out
+}
+
+/// The idempotent variant of one statement, without its semicolon.
+fn idempotent_statement(statement: &str) -> String {
+    // Comment lines before the statement stay where they are
+    let mut body = statement;
+    while body.starts_with("--") {
+        body = body.split_once('\n').map_or("", |(_, next)| next.trim_start());
+    }
+    let comments = &statement[..statement.len() - body.len()];
+    if body.is_empty() {
+        return statement.to_string();
+    }
+
+    let rewritten = match REWRITES.iter().find(|(prefix, _)| body.starts_with(prefix)) {
+        Some((prefix, replacement)) => if_missing(&body[prefix.len()..], replacement),
+        None => alter_table(body).or_else(|| guarded(body)),
+    };
+    format!("{}{}", comments, rewritten.unwrap_or_else(|| body.to_string()))
+}
+
+/// Idempotent `ALTER TABLE` statements whose action has an `IF [NOT] EXISTS`
+/// form; drops also skip missing tables.
+fn alter_table(statement: &str) -> Option<String> {
+    let (table, action) = identifier(statement.strip_prefix("ALTER TABLE ")?)?;
+    let (prefix, replacement) = ACTION_REWRITES.iter().find(|(prefix, _)| action.starts_with(prefix))?;
+    let action = if_missing(&action[prefix.len()..], replacement).unwrap_or_else(|| action.to_string());
+    // What a drop removes is gone with its table as well
+    let if_exists = if prefix.starts_with("DROP") { "IF EXISTS " } else { "" };
+    Some(format!("ALTER TABLE {}{} {}", if_exists, table, action))
+}
+
+/// `replacement` followed by `rest`, unless `rest` already starts with
+/// `IF [NOT] EXISTS`.
+fn if_missing(rest: &str, replacement: &str) -> Option<String> {
+    (!rest.starts_with("IF ")).then(|| format!("{}{}", replacement, rest))
+}
+
+/// Statements without an `IF [NOT] EXISTS` form, in a `DO` block that runs
+/// them only when the catalogs show their work is not done yet.
+fn guarded(statement: &str) -> Option<String> {
+    // A comment on the last line would hide the semicolon added after it
+    if statement.contains(DO_TAG) || statement.lines().last().is_some_and(|line| line.contains("--")) {
+        return None;
+    }
+    let condition = if let Some(rest) = statement.strip_prefix("ALTER TABLE ") {
+        let (table, action) = identifier(rest)?;
+        if let Some(rest) = action.strip_prefix("ADD CONSTRAINT ") {
+            let (constraint, _) = identifier(rest)?;
+            format!("NOT {}", constraint_exists(table, constraint))
+        } else if let Some(rest) = action.strip_prefix("RENAME CONSTRAINT ") {
+            let (from, to) = rename_targets(rest)?;
+            format!("{} AND NOT {}", constraint_exists(table, from), constraint_exists(table, to))
+        } else if let Some(rest) = action.strip_prefix("RENAME COLUMN ") {
+            let (from, to) = rename_targets(rest)?;
+            format!("{} AND NOT {}", column_exists(table, from), column_exists(table, to))
+        } else if let Some(rest) = action.strip_prefix("RENAME TO ") {
+            let (to, _) = identifier(rest)?;
+            relation_renamable(table, &qualified_like(table, to))
+        } else {
+            return None;
+        }
+    } else if let Some(rest) = statement.strip_prefix("ALTER INDEX ") {
+        let (index, action) = identifier(rest)?;
+        let (to, _) = identifier(action.strip_prefix("RENAME TO ")?)?;
+        relation_renamable(index, &qualified_like(index, to))
+    } else if let Some(rest) = statement.strip_prefix("ALTER TYPE ") {
+        let (type_name, action) = identifier(rest)?;
+        let (to, _) = identifier(action.strip_prefix("RENAME TO ")?)?;
+        format!(
+            "to_regtype({}) IS NOT NULL AND to_regtype({}) IS NULL",
+            literal(type_name),
+            literal(&qualified_like(type_name, to))
+        )
+    } else if let Some(rest) = statement.strip_prefix("CREATE TYPE ") {
+        let (type_name, _) = identifier(rest)?;
+        format!("to_regtype({}) IS NULL", literal(type_name))
+    } else if let Some(rest) = statement
+        .strip_prefix("CREATE TRIGGER ")
+        .or_else(|| statement.strip_prefix("CREATE CONSTRAINT TRIGGER "))
+    {
+        let (trigger, rest) = identifier(rest)?;
+        let (table, _) = identifier(&rest[rest.find(" ON ")? + 4..])?;
+        format!(
+            "NOT EXISTS (SELECT 1 FROM pg_trigger WHERE tgrelid = to_regclass({}) AND tgname = {})",
+            literal(table),
+            literal(&unquoted(trigger))
+        )
+    } else if let Some(rest) = statement.strip_prefix("CREATE POLICY ") {
+        let (policy, rest) = identifier(rest)?;
+        let (table, _) = identifier(rest.strip_prefix("ON ")?)?;
+        format!(
+            "NOT EXISTS (SELECT 1 FROM pg_policy WHERE polrelid = to_regclass({}) AND polname = {})",
+            literal(table),
+            literal(&unquoted(policy))
+        )
+    } else if let Some(rest) = statement.strip_prefix("CREATE PUBLICATION ") {
+        let (publication, _) = identifier(rest)?;
+        format!("NOT EXISTS (SELECT 1 FROM pg_publication WHERE pubname = {})", literal(&unquoted(publication)))
+    } else if let Some(rest) = statement.strip_prefix("CREATE ROLE ") {
+        let (role, _) = identifier(rest)?;
+        format!("NOT EXISTS (SELECT 1 FROM pg_roles WHERE rolname = {})", literal(&unquoted(role)))
+    } else {
+        return None;
+    };
+
+    Some(format!(
+        "DO {tag}\nBEGIN\n    IF {condition} THEN\n        {statement};\n    END IF;\nEND\n{tag}",
+        tag = DO_TAG,
+        condition = condition,
+        statement = statement
+    ))
+}
+
+fn constraint_exists(table: &str, constraint: &str) -> String {
+    format!(
+        "EXISTS (SELECT 1 FROM pg_constraint WHERE conrelid = to_regclass({}) AND conname = {})",
+        literal(table),
+        literal(&unquoted(constraint))
+    )
+}
+
+fn column_exists(table: &str, column: &str) -> String {
+    format!(
+        "EXISTS (SELECT 1 FROM pg_attribute WHERE attrelid = to_regclass({}) AND attname = {} AND NOT attisdropped)",
+        literal(table),
+        literal(&unquoted(column))
+    )
+}
+
+/// A relation can be renamed when it exists and its new name is free.
+fn relation_renamable(from: &str, to: &str) -> String {
+    format!("to_regclass({}) IS NOT NULL AND to_regclass({}) IS NULL", literal(from), literal(to))
+}
+
+/// The `a` and `b` of `a TO b`.
+fn rename_targets(text: &str) -> Option<(&str, &str)> {
+    let (from, rest) = identifier(text)?;
+    let (to, _) = identifier(rest.strip_prefix("TO ")?)?;
+    Some((from, to))
+}
+
+/// `name` in the schema of `qualified`, since renamed objects stay in
+/// their schema.
+fn qualified_like(qualified: &str, name: &str) -> String {
+    match split_identifier(qualified).split_last() {
+        Some((_, schema)) if !schema.is_empty() => format!("{}.{}", schema.join("."), name),
+        _ => name.to_string(),
+    }
+}
+
+/// The possibly qualified identifier at the start of `text`, as written,
+/// and the text after it with leading whitespace removed.
+fn identifier(text: &str) -> Option<(&str, &str)> {
+    let bytes = text.as_bytes();
+    let mut end = 0;
+    loop {
+        if bytes.get(end) == Some(&b'"') {
+            end += 1;
+            loop {
+                match bytes.get(end) {
+                    Some(b'"') if bytes.get(end + 1) == Some(&b'"') => end += 2,
+                    Some(b'"') => break,
+                    Some(_) => end += 1,
+                    None => return None,
+                }
+            }
+            end += 1;
+        } else {
+            let start = end;
+            while bytes.get(end).is_some_and(|b| b.is_ascii_alphanumeric() || *b == b'_' || *b == b'$' || *b >= 0x80) {
+                end += 1;
+            }
+            if end == start {
+                return None;
+            }
+        }
+        if bytes.get(end) == Some(&b'.') {
+            end += 1;
+        } else {
+            break;
+        }
+    }
+    Some((&text[..end], text[end..].trim_start()))
+}
+
+/// The parts of a qualified identifier, as written.
+fn split_identifier(identifier: &str) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let mut start = 0;
+    let mut quoted = false;
+    for (i, c) in identifier.char_indices() {
+        match c {
+            '"' => quoted = !quoted,
+            '.' if !quoted => {
+                parts.push(&identifier[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    parts.push(&identifier[start..]);
+    parts
+}
+
+/// The name the catalogs store for the last part of `identifier`.
+fn unquoted(identifier: &str) -> String {
+    let name = split_identifier(identifier).pop().unwrap_or(identifier);
+    match name.strip_prefix('"').and_then(|n| n.strip_suffix('"')) {
+        Some(quoted) => quoted.replace("\"\"", "\""),
+        None => name.to_lowercase(),
+    }
+}
+
+fn literal(text: &str) -> String {
+    format!("'{}'", text.replace('\'', "''"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_idempotent_sql() {
+        let sql = "-- Create table\nCREATE TABLE \"users\" (\"id\" integer);\n\
+                   CREATE UNIQUE INDEX \"users_id\" ON \"users\" (\"id\");\n\
+                   ALTER TABLE \"users\" ADD COLUMN \"email\" text;\n\
+                   ALTER TABLE \"users\" ALTER COLUMN \"email\" SET NOT NULL;\n\
+                   ALTER TABLE \"users\" DROP COLUMN \"name\";\n\
+                   ALTER TABLE \"users\" DROP CONSTRAINT IF EXISTS \"users_pkey\";\n\
+                   DROP TABLE IF EXISTS \"legacy\";";
+        assert_eq!(
+            idempotent_sql(sql),
+            "-- Create table\nCREATE TABLE IF NOT EXISTS \"users\" (\"id\" integer);\n\
+             CREATE UNIQUE INDEX IF NOT EXISTS \"users_id\" ON \"users\" (\"id\");\n\
+             ALTER TABLE \"users\" ADD COLUMN IF NOT EXISTS \"email\" text;\n\
+             ALTER TABLE \"users\" ALTER COLUMN \"email\" SET NOT NULL;\n\
+             ALTER TABLE IF EXISTS \"users\" DROP COLUMN IF EXISTS \"name\";\n\
+             ALTER TABLE IF EXISTS \"users\" DROP CONSTRAINT IF EXISTS \"users_pkey\";\n\
+             DROP TABLE IF EXISTS \"legacy\";"
+        );
+
+        let constraint = idempotent_sql("ALTER TABLE \"Orders\" ADD CONSTRAINT \"o_pk\" PRIMARY KEY (\"id\");");
+        assert_eq!(
+            constraint,
+            "DO $pgshift$\nBEGIN\n    IF NOT EXISTS (SELECT 1 FROM pg_constraint WHERE conrelid = to_regclass('\"Orders\"') \
+             AND conname = 'o_pk') THEN\n        ALTER TABLE \"Orders\" ADD CONSTRAINT \"o_pk\" PRIMARY KEY (\"id\");\n    \
+             END IF;\nEND\n$pgshift$;"
+        );
+
+        let trigger = idempotent_sql(
+            "CREATE TRIGGER Touch BEFORE UPDATE ON public.users FOR EACH ROW EXECUTE FUNCTION touch();",
+        );
+        assert!(trigger.contains("tgrelid = to_regclass('public.users') AND tgname = 'touch'"));
+        let rename = idempotent_sql("ALTER INDEX public.\"a\" RENAME TO \"b\";");
+        assert!(rename.contains("to_regclass('public.\"a\"') IS NOT NULL AND to_regclass('public.\"b\"') IS NULL"));
+        assert!(idempotent_sql("CREATE TYPE \"mood\" AS ENUM ('ok');").contains("IF to_regtype('\"mood\"') IS NULL THEN"));
+
+        // Function bodies are not rewritten
+        let function = "CREATE OR REPLACE FUNCTION f() RETURNS void AS $$ BEGIN CREATE TABLE t (); END $$ LANGUAGE plpgsql;";
+        assert_eq!(idempotent_sql(function), function);
+    }
+}
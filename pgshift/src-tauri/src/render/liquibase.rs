@@ -0,0 +1,195 @@
+//! Diff reports as Liquibase changelogs.
+//!
+//! Each diff item becomes a changeset whose change is its up SQL and whose
+//! rollback is its down SQL, in the order `up.sql` applies them, so
+//! Liquibase can apply, track and roll back the changes one by one.
+//! Changesets are identified by the item's content id, so exporting the
+//! same change again gives the same changeset.
+
+use std::fs;
+use std::path::Path;
+use serde::{Deserialize, Serialize};
+use crate::diff::depends::dependency_order;
+use crate::diff::{DiffItem, DiffReport};
+use super::html::escape_html;
+use super::sql::Section;
+
+/// Author of the changesets when none is given.
+pub const DEFAULT_AUTHOR: &str = "pgshift";
+
+/// Serialization of a Liquibase changelog.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ChangelogFormat {
+    Xml,
+    Yaml,
+}
+
+/// Render `report` as a Liquibase changelog whose changesets are by `author`.
+pub fn render_liquibase_changelog(report: &DiffReport, format: ChangelogFormat, author: &str) -> String {
+    let items: Vec<&DiffItem> = dependency_order(&report.items, Section::of)
+        .into_iter()
+        .filter(|item| Section::of(item).is_some())
+        .collect();
+    match format {
+        ChangelogFormat::Xml => changelog_xml(&items, author),
+        ChangelogFormat::Yaml => changelog_yaml(&items, author),
+    }
+}
+
+/// Write the changelog of `report` to the file at `path`, creating its directory.
+pub fn write_liquibase_changelog(
+    report: &DiffReport,
+    format: ChangelogFormat,
+    author: &str,
+    path: &str,
+) -> std::io::Result<()> {
+    if let Some(parent) = Path::new(path).parent().filter(|p| !p.as_os_str().is_empty()) {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(path, render_liquibase_changelog(report, format, author))
+}
+
+fn changelog_xml(items: &[&DiffItem], author: &str) -> String {
+    let mut xml = String::from(concat!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n",
+        "<databaseChangeLog\n",
+        "    xmlns=\"http://www.liquibase.org/xml/ns/dbchangelog\"\n",
+        "    xmlns:xsi=\"http://www.w3.org/2001/XMLSchema-instance\"\n",
+        "    xsi:schemaLocation=\"http://www.liquibase.org/xml/ns/dbchangelog ",
+        "http://www.liquibase.org/xml/ns/dbchangelog/dbchangelog-latest.xsd\">\n",
+    ));
+    for item in items {
+        xml.push_str(&format!(
+            "\n    <changeSet id=\"{}\" author=\"{}\" dbms=\"postgresql\">\n",
+            escape_html(&item.id),
+            escape_html(author)
+        ));
+        xml.push_str(&format!("        <comment>{}</comment>\n", escape_html(&comment(item))));
+        xml.push_str(&format!(
+            "        <sql splitStatements=\"false\" stripComments=\"false\">{}</sql>\n",
+            cdata(item.generated_up_sql.trim())
+        ));
+        let down_sql = item.generated_down_sql.trim();
+        if down_sql.is_empty() {
+            xml.push_str("        <rollback/>\n");
+        } else {
+            xml.push_str(&format!(
+                "        <rollback>\n            <sql splitStatements=\"false\" stripComments=\"false\">{}</sql>\n        </rollback>\n",
+                cdata(down_sql)
+            ));
+        }
+        xml.push_str("    </changeSet>\n");
+    }
+    xml.push_str("\n</databaseChangeLog>\n");
+    xml
+}
+
+fn changelog_yaml(items: &[&DiffItem], author: &str) -> String {
+    if items.is_empty() {
+        return "databaseChangeLog: []\n".to_string();
+    }
+    let mut yaml = String::from("databaseChangeLog:\n");
+    for item in items {
+        yaml.push_str("  - changeSet:\n");
+        yaml.push_str(&format!("      id: {}\n", yaml_string(&item.id)));
+        yaml.push_str(&format!("      author: {}\n", yaml_string(author)));
+        yaml.push_str("      dbms: postgresql\n");
+        yaml.push_str(&format!("      comment: {}\n", yaml_string(&comment(item))));
+        yaml.push_str("      changes:\n");
+        yaml.push_str(&yaml_sql_change(item.generated_up_sql.trim()));
+        let down_sql = item.generated_down_sql.trim();
+        if down_sql.is_empty() {
+            yaml.push_str("      rollback: []\n");
+        } else {
+            yaml.push_str("      rollback:\n");
+            yaml.push_str(&yaml_sql_change(down_sql));
+        }
+    }
+    yaml
+}
+
+/// An `sql` change holding `sql` as a literal block.
+fn yaml_sql_change(sql: &str) -> String {
+    let mut yaml = String::from(concat!(
+        "        - sql:\n",
+        "            splitStatements: false\n",
+        "            stripComments: false\n",
+        "            sql: |-\n",
+    ));
+    for line in sql.lines() {
+        if line.trim().is_empty() {
+            yaml.push('\n');
+        } else {
+            yaml.push_str(&format!("              {}\n", line));
+        }
+    }
+    yaml
+}
+
+fn comment(item: &DiffItem) -> String {
+    if item.dangerous {
+        format!("{} (dangerous)", item.details)
+    } else {
+        item.details.clone()
+    }
+}
+
+/// `text` in a CDATA section; `]]>` would end it, so it is split there.
+fn cdata(text: &str) -> String {
+    format!("<![CDATA[{}]]>", text.replace("]]>", "]]]]><![CDATA[>"))
+}
+
+/// A double-quoted YAML string.
+fn yaml_string(text: &str) -> String {
+    let mut quoted = String::from("\"");
+    for c in text.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            _ => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::diff::DiffKind;
+
+    #[test]
+    fn test_render_liquibase_changelog() {
+        let mut report = DiffReport::new();
+        report.items.push(DiffItem::new(
+            DiffKind::Removed, "table", "legacy", "Drop table 'legacy'",
+            "DROP TABLE \"legacy\";", "CREATE TABLE \"legacy\" (\n    \"id\" integer\n);", true,
+        ));
+        report.items.push(DiffItem::new(
+            DiffKind::Added, "function", "f()", "Create function \"f\"",
+            "CREATE FUNCTION f() RETURNS text AS $$ SELECT ']]>' $$ LANGUAGE sql;", "", false,
+        ));
+        let (drop_id, function_id) = (report.items[0].id.clone(), report.items[1].id.clone());
+
+        let xml = render_liquibase_changelog(&report, ChangelogFormat::Xml, "dba & co");
+        // Functions are created before tables are dropped, as in up.sql
+        assert!(xml.find(&function_id).unwrap() < xml.find(&drop_id).unwrap());
+        assert!(xml.contains(&format!("<changeSet id=\"{}\" author=\"dba &amp; co\" dbms=\"postgresql\">", drop_id)));
+        assert!(xml.contains("<comment>Drop table &#39;legacy&#39; (dangerous)</comment>"));
+        assert!(xml.contains("<![CDATA[DROP TABLE \"legacy\";]]>"));
+        assert!(xml.contains("<rollback>\n            <sql splitStatements=\"false\" stripComments=\"false\"><![CDATA[CREATE TABLE"));
+        assert!(xml.contains("SELECT ']]]]><![CDATA[>' $$"));
+        assert!(xml.contains("<rollback/>"));
+
+        let yaml = render_liquibase_changelog(&report, ChangelogFormat::Yaml, DEFAULT_AUTHOR);
+        assert!(yaml.starts_with("databaseChangeLog:\n  - changeSet:\n"));
+        assert!(yaml.contains("      comment: \"Create function \\\"f\\\"\"\n"));
+        assert!(yaml.contains("            sql: |-\n              CREATE TABLE \"legacy\" (\n                  \"id\" integer\n              );\n"));
+        assert!(yaml.contains("      rollback: []\n"));
+        assert_eq!(render_liquibase_changelog(&DiffReport::new(), ChangelogFormat::Yaml, "x"), "databaseChangeLog: []\n");
+    }
+}
@@ -1,3 +1,17 @@
-//! SQL rendering for migrations.
+//! SQL and report rendering.
 
 pub mod sql;
+pub mod html;
+pub mod report;
+pub mod dictionary;
+pub mod dual_write;
+pub mod roles;
+pub mod version;
+pub mod schema_sql;
+pub mod erd;
+pub mod squash;
+pub mod flyway;
+pub mod golang_migrate;
+pub mod rust_tools;
+pub mod liquibase;
+pub mod idempotent;
@@ -0,0 +1,153 @@
+//! Diff reports as documents to attach to pull requests and change tickets.
+
+use std::fs;
+use std::path::Path;
+use serde::{Deserialize, Serialize};
+use crate::db::connect::connection_label;
+use crate::diff::{DiffItem, DiffKind, DiffReport};
+use super::html::render_report_html;
+
+/// Document format of an exported diff report.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ReportFormat {
+    Markdown,
+    Html,
+}
+
+/// Render `report` in `format`. Passwords are removed from the source and
+/// target connections, since reports are meant to be shared.
+pub fn render_report(report: &DiffReport, format: ReportFormat, title: &str) -> String {
+    let mut report = report.clone();
+    report.source_connection = connection_label(&report.source_connection);
+    report.target_connection = connection_label(&report.target_connection);
+    match format {
+        ReportFormat::Markdown => render_report_markdown(&report, title),
+        ReportFormat::Html => render_report_html(&report, title),
+    }
+}
+
+/// Write `report` in `format` to the file at `path`, creating its directory.
+pub fn write_report(report: &DiffReport, format: ReportFormat, path: &str) -> std::io::Result<()> {
+    if let Some(parent) = Path::new(path).parent().filter(|p| !p.as_os_str().is_empty()) {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(path, render_report(report, format, "Schema diff report"))
+}
+
+/// Render a diff report as GitHub-flavored Markdown: a summary, a table of
+/// the changes, and the SQL of each change.
+pub fn render_report_markdown(report: &DiffReport, title: &str) -> String {
+    let count = |kind: DiffKind| report.items.iter().filter(|i| i.kind == kind).count();
+    let dangerous: Vec<_> = report.items.iter().filter(|i| i.dangerous).collect();
+
+    let mut md = format!("# {}\n\n", title);
+    md.push_str(&format!(
+        "Source: `{}`  \nTarget: `{}`  \nGenerated at: {}\n\n",
+        report.source_connection, report.target_connection, report.generated_at
+    ));
+    md.push_str(&format!(
+        "**{} added · {} removed · {} modified · {} reordered · {} renamed · {} dangerous**\n\n",
+        count(DiffKind::Added),
+        count(DiffKind::Removed),
+        count(DiffKind::Modified),
+        count(DiffKind::Reordered),
+        count(DiffKind::Renamed),
+        dangerous.len()
+    ));
+
+    for warning in &report.warnings {
+        md.push_str(&format!("> [!WARNING]\n> {}\n\n", warning.replace('\n', "\n> ")));
+    }
+    if !dangerous.is_empty() {
+        md.push_str("> [!CAUTION]\n> These changes can lose data or block writes:\n");
+        for item in &dangerous {
+            md.push_str(&format!("> - {} `{}`: {}{}\n", item.object_type, item.object_name, item.details, data_loss_suffix(item)));
+        }
+        md.push('\n');
+    }
+
+    if report.items.is_empty() {
+        md.push_str("No differences found. The schemas are in sync.\n");
+        return md;
+    }
+
+    md.push_str("| # | Change | Type | Object | Details |\n|---|---|---|---|---|\n");
+    for (i, item) in report.items.iter().enumerate() {
+        let kind = format!("{:?}", item.kind).to_lowercase();
+        md.push_str(&format!(
+            "| {} | {} | {} | `{}` | {} |\n",
+            i + 1,
+            if item.dangerous { format!("**{} (dangerous)**", kind) } else { kind },
+            table_cell(&item.object_type),
+            table_cell(&item.object_name),
+            table_cell(&format!("{}{}", item.details, data_loss_suffix(item)))
+        ));
+    }
+
+    md.push_str("\n## SQL\n");
+    for (i, item) in report.items.iter().enumerate() {
+        md.push_str(&format!(
+            "\n### {}. {:?} {} `{}`{}\n\n{}\n",
+            i + 1,
+            item.kind,
+            item.object_type,
+            item.object_name,
+            if item.dangerous { " (dangerous)" } else { "" },
+            item.details
+        ));
+        let sql = item.generated_up_sql.trim();
+        if !sql.is_empty() {
+            let fence = code_fence(sql);
+            md.push_str(&format!("\n{}sql\n{}\n{}\n", fence, sql, fence));
+        }
+    }
+    md
+}
+
+/// ` (4.2M rows)` after the details of an item whose dropped rows were counted.
+pub(super) fn data_loss_suffix(item: &DiffItem) -> String {
+    item.data_loss.map(|loss| format!(" ({})", loss.summary())).unwrap_or_default()
+}
+
+/// Text for a table cell, which must stay on one line.
+fn table_cell(text: &str) -> String {
+    text.replace('|', "\\|").replace('\n', " ")
+}
+
+/// A backtick fence longer than any run of backticks in `text`.
+fn code_fence(text: &str) -> String {
+    let longest = text.split(|c| c != '`').map(str::len).max().unwrap_or(0);
+    "`".repeat(longest.max(2) + 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::diff::DiffItem;
+
+    #[test]
+    fn test_render_report_markdown() {
+        let mut report = DiffReport::new();
+        report.source_connection = "postgres://app:secret@db/app".to_string();
+        report.items.push(DiffItem::new(
+            DiffKind::Added, "table", "users", "Create table 'users'",
+            "CREATE TABLE \"users\" (\"id\" integer);", "DROP TABLE \"users\";", false,
+        ));
+        report.items.push(DiffItem::new(
+            DiffKind::Removed, "column", "users.email", "Drop column 'email' | unused",
+            "ALTER TABLE \"users\" DROP COLUMN \"email\";", "", true,
+        ));
+
+        let md = render_report(&report, ReportFormat::Markdown, "Release 12");
+        assert!(md.starts_with("# Release 12\n\nSource: `postgres://app@db/app`"));
+        assert!(md.contains("**1 added · 1 removed · 0 modified · 0 reordered · 0 renamed · 1 dangerous**"));
+        assert!(md.contains("> - column `users.email`: Drop column 'email' | unused\n"));
+        assert!(md.contains("| 2 | **removed (dangerous)** | column | `users.email` | Drop column 'email' \\| unused |\n"));
+        assert!(md.contains("### 1. Added table `users`\n\nCreate table 'users'\n\n```sql\nCREATE TABLE \"users\" (\"id\" integer);\n```\n"));
+
+        let html = render_report(&report, ReportFormat::Html, "Release 12");
+        assert!(html.contains("postgres://app@db/app") && !html.contains("secret"));
+        assert_eq!(code_fence("SELECT '```'"), "````");
+    }
+}
@@ -0,0 +1,150 @@
+//! Migration scripts for role differences.
+//!
+//! A [`RoleDiffReport`] is rendered like a schema migration, into a folder
+//! with `up.sql`, `down.sql` and `meta.json`, but its scripts run against the
+//! whole target server. Roles are created before the memberships that need
+//! them and dropped after the memberships are revoked.
+
+use std::fs;
+use chrono::Utc;
+use crate::diff::depends::dependency_order;
+use crate::diff::roles::RoleDiffReport;
+use crate::diff::{DiffItem, DiffKind};
+use super::sql::{sanitize_name, MigrationItemMeta, MigrationMeta};
+use super::version::{create_migration_dir, VersionScheme};
+
+/// Position of an item in the up script: memberships are revoked first and
+/// roles dropped last.
+fn rank(item: &DiffItem) -> Option<u8> {
+    Some(match (item.object_type.as_str(), &item.kind) {
+        ("role_membership", DiffKind::Removed) => 0,
+        ("role", DiffKind::Removed) => 3,
+        ("role", _) => 1,
+        _ => 2,
+    })
+}
+
+/// Generate the UP script of a role migration.
+pub(crate) fn generate_role_up_sql(report: &RoleDiffReport) -> String {
+    let mut parts = vec![
+        "-- Role Migration UP Script".to_string(),
+        format!("-- Generated at: {}", Utc::now().to_rfc3339()),
+        "-- Roles are shared by every database on the server; this script changes them all.\n".to_string(),
+        "BEGIN;\n".to_string(),
+    ];
+
+    for item in dependency_order(&report.items, rank) {
+        if item.dangerous {
+            parts.push("-- WARNING: Dangerous operation".to_string());
+        }
+        parts.push(format!("-- {}", item.details));
+        parts.push(item.generated_up_sql.clone());
+    }
+
+    parts.push("\nCOMMIT;".to_string());
+    parts.join("\n")
+}
+
+/// Generate the DOWN script of a role migration, undoing the up script's
+/// statements in reverse order.
+pub(crate) fn generate_role_down_sql(report: &RoleDiffReport) -> String {
+    let mut parts = vec![
+        "-- Role Migration DOWN Script (Rollback)".to_string(),
+        format!("-- Generated at: {}", Utc::now().to_rfc3339()),
+        "-- This script reverts the role changes; passwords of dropped roles are not restored.\n".to_string(),
+        "BEGIN;\n".to_string(),
+    ];
+
+    for item in dependency_order(&report.items, rank).into_iter().rev() {
+        parts.push(format!("-- Revert: {}", item.details));
+        parts.push(item.generated_down_sql.clone());
+    }
+
+    parts.push("\nCOMMIT;".to_string());
+    parts.join("\n")
+}
+
+/// Render a role migration folder to disk and return its path.
+pub fn render_role_migration_files(
+    report: &RoleDiffReport,
+    name: &str,
+    base_path: &str,
+) -> Result<String, std::io::Error> {
+    let sanitized_name = sanitize_name(name);
+    let (migration_dir, timestamp) = create_migration_dir(base_path, &sanitized_name, VersionScheme::Timestamp)?;
+
+    fs::write(migration_dir.join("up.sql"), generate_role_up_sql(report))?;
+    fs::write(migration_dir.join("down.sql"), generate_role_down_sql(report))?;
+
+    let meta = MigrationMeta {
+        name: sanitized_name,
+        timestamp,
+        generated_at: Utc::now().to_rfc3339(),
+        items_count: report.items.len(),
+        has_dangerous: report.has_dangerous(),
+        unacknowledged_dangerous: report.items.iter().filter(|i| i.dangerous).count(),
+        disk_estimate: None,
+        items: report
+            .items
+            .iter()
+            .map(|item| MigrationItemMeta {
+                id: item.id.clone(),
+                kind: format!("{:?}", item.kind).to_lowercase(),
+                object_type: item.object_type.clone(),
+                object_name: item.object_name.clone(),
+                dangerous: item.dangerous,
+                acknowledgment: None,
+                disk_estimate: None,
+                data_loss: None,
+            })
+            .collect(),
+        phase: None,
+        squashes: Vec::new(),
+    };
+    let meta_json = serde_json::to_string_pretty(&meta).map_err(std::io::Error::other)?;
+    fs::write(migration_dir.join("meta.json"), meta_json)?;
+
+    Ok(migration_dir.to_string_lossy().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::diff::roles::compare_roles;
+    use crate::model::role::Role;
+
+    fn role(name: &str, member_of: &[&str]) -> Role {
+        Role {
+            name: name.to_string(),
+            superuser: false,
+            inherit: true,
+            create_role: false,
+            create_db: false,
+            can_login: false,
+            replication: false,
+            bypass_rls: false,
+            connection_limit: -1,
+            valid_until: None,
+            member_of: member_of.iter().map(|r| r.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn test_role_scripts_order() {
+        // "app" sorts first but is granted "readers", which is new as well
+        let source = vec![role("app", &["readers"]), role("readers", &[]), role("ops", &["readers"])];
+        let target = vec![role("ops", &["legacy"]), role("legacy", &[]), role("old", &["legacy"])];
+        let report = compare_roles(&source, &target);
+
+        let up = generate_role_up_sql(&report);
+        let position = |sql: &str, needle: &str| sql.find(needle).unwrap_or_else(|| panic!("missing {}", needle));
+        assert!(position(&up, "CREATE ROLE \"readers\"") < position(&up, "CREATE ROLE \"app\""));
+        assert!(position(&up, "CREATE ROLE \"readers\"") < position(&up, "GRANT \"readers\" TO \"ops\""));
+        assert!(position(&up, "REVOKE \"legacy\" FROM \"ops\"") < position(&up, "DROP ROLE IF EXISTS \"legacy\""));
+        assert!(position(&up, "DROP ROLE IF EXISTS \"old\"") < position(&up, "DROP ROLE IF EXISTS \"legacy\""));
+
+        let down = generate_role_down_sql(&report);
+        assert!(position(&down, "CREATE ROLE \"legacy\"") < position(&down, "CREATE ROLE \"old\""));
+        assert!(position(&down, "REVOKE \"readers\" FROM \"ops\"") < position(&down, "DROP ROLE IF EXISTS \"readers\""));
+    }
+}
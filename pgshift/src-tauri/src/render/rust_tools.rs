@@ -0,0 +1,108 @@
+//! Migrations in the layouts of `sqlx migrate` and Diesel.
+//!
+//! Both run each migration in a transaction of their own, so the scripts
+//! written here have no `BEGIN`/`COMMIT`. sqlx reads reversible migrations
+//! as `<version>_<name>.up.sql` and `<version>_<name>.down.sql` files with
+//! integer versions and checksums every script it applies, so its scripts
+//! carry no generation time either. Diesel reads `<version>_<name>/` folders
+//! holding `up.sql` and `down.sql`.
+
+use std::fs;
+use std::io;
+use crate::diff::DiffReport;
+use super::sql::{generate_down_sql, generate_up_sql, unwrap_script, RenderOptions};
+use super::version::{create_migration_file, create_named_migration_dir, VersionScheme};
+
+/// Write the up and down scripts of `report` as sqlx migration `name` under
+/// `base_path` and return the path of the up script.
+pub(crate) fn render_sqlx_files(
+    report: &DiffReport,
+    name: &str,
+    base_path: &str,
+    options: &RenderOptions,
+) -> io::Result<String> {
+    if options.version_scheme != VersionScheme::Timestamp {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "sqlx versions must be numeric; use the timestamp version scheme",
+        ));
+    }
+
+    let (up_path, version) = create_migration_file(base_path, |version| format!("{}_{}.up.sql", version, name))?;
+    let migration_name = format!("{}_{}", version, name);
+    let trace = options.trace_comments.then_some(migration_name.as_str());
+
+    fs::write(&up_path, unwrap_script(&generate_up_sql(report, trace, options.batch_alter_table), true))?;
+    fs::write(
+        up_path.with_file_name(format!("{}.down.sql", migration_name)),
+        unwrap_script(&generate_down_sql(report, trace), true),
+    )?;
+
+    Ok(up_path.to_string_lossy().to_string())
+}
+
+/// Write `report` as Diesel migration `name` under `base_path` and return
+/// the path of its folder.
+pub(crate) fn render_diesel_files(
+    report: &DiffReport,
+    name: &str,
+    base_path: &str,
+    options: &RenderOptions,
+) -> io::Result<String> {
+    let (migration_dir, version) = create_named_migration_dir(base_path, options.version_scheme, |version| {
+        format!("{}_{}", version, name)
+    })?;
+    let migration_name = format!("{}_{}", version, name);
+    let trace = options.trace_comments.then_some(migration_name.as_str());
+
+    fs::write(
+        migration_dir.join("up.sql"),
+        unwrap_script(&generate_up_sql(report, trace, options.batch_alter_table), false),
+    )?;
+    fs::write(migration_dir.join("down.sql"), unwrap_script(&generate_down_sql(report, trace), false))?;
+
+    Ok(migration_dir.to_string_lossy().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+    use super::*;
+    use crate::diff::{DiffItem, DiffKind};
+    use crate::render::sql::{render_migration_files, MigrationFormat};
+    use crate::render::version::compare_folder_names;
+
+    #[test]
+    fn test_render_sqlx_and_diesel_files() {
+        let mut report = DiffReport::new();
+        report.items.push(DiffItem::new(
+            DiffKind::Added, "table", "users", "Create table 'users'",
+            "CREATE TABLE \"users\" (\"id\" integer);", "DROP TABLE \"users\";", false,
+        ));
+        let base = std::env::temp_dir().join(format!("pgshift_rust_tools_{}", uuid::Uuid::new_v4()));
+        let base_path = base.to_str().unwrap();
+
+        let sqlx = RenderOptions { format: MigrationFormat::Sqlx, ..Default::default() };
+        let up_path = render_migration_files(&report, "Add users", base_path, &sqlx).unwrap();
+        let up_sql = fs::read_to_string(&up_path).unwrap();
+        let down_sql = fs::read_to_string(up_path.replace(".up.sql", ".down.sql")).unwrap();
+        assert!(up_path.ends_with("_add_users.up.sql"));
+        assert!(up_sql.contains("CREATE TABLE \"users\" (\"id\" integer);"));
+        assert!(!up_sql.contains("BEGIN;") && !up_sql.contains("COMMIT;") && !up_sql.contains("Generated at"));
+        assert!(down_sql.contains("DROP TABLE \"users\";") && !down_sql.contains("COMMIT;"));
+
+        let diesel = RenderOptions { format: MigrationFormat::Diesel, ..Default::default() };
+        let dir = render_migration_files(&report, "Add users", base_path, &diesel).unwrap();
+        let folder = Path::new(&dir).file_name().unwrap().to_string_lossy().to_string();
+        let up_sql = fs::read_to_string(Path::new(&dir).join("up.sql")).unwrap();
+        let down_sql = fs::read_to_string(Path::new(&dir).join("down.sql")).unwrap();
+        let sqlx_file = Path::new(&up_path).file_name().unwrap().to_string_lossy().to_string();
+        fs::remove_dir_all(&base).unwrap();
+
+        // Versioned after the sqlx migration, with Diesel's single underscore
+        assert!(folder.ends_with("_add_users") && !folder.contains("__"));
+        assert_eq!(compare_folder_names(&sqlx_file, &folder), std::cmp::Ordering::Less);
+        assert!(up_sql.contains("CREATE TABLE \"users\" (\"id\" integer);") && !up_sql.contains("BEGIN;"));
+        assert!(down_sql.contains("DROP TABLE \"users\";"));
+    }
+}
@@ -0,0 +1,141 @@
+//! Canonical `schema.sql` of a schema model.
+//!
+//! The dump holds the statements that create every object of the schema,
+//! as a clone would run them, with the objects sorted by name and no
+//! timestamps, so the same schema always renders the same file. Committed
+//! to git, it shows every schema change as a readable diff, and it can be
+//! compared later as a `sql_file` source.
+
+use std::fs;
+use std::path::Path;
+use serde::{Deserialize, Serialize};
+use crate::apply::clone::bootstrap_report;
+use crate::diff::depends::dependency_order;
+use crate::diff::DiffOptions;
+use crate::model::schema::SchemaModel;
+use super::sql::Section;
+
+/// Outcome of writing a `schema.sql`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SchemaSqlDump {
+    pub path: String,
+    /// Objects created by the dump, counted as diff items.
+    pub objects: usize,
+    /// False when the file already held this dump.
+    pub changed: bool,
+}
+
+/// Copy of `schema` with every list of named objects sorted by name.
+///
+/// Column order is part of a table's definition and is kept.
+pub fn canonical_schema(schema: &SchemaModel) -> SchemaModel {
+    let mut schema = schema.clone();
+    schema.tables.sort_by(|a, b| a.name.cmp(&b.name));
+    for table in &mut schema.tables {
+        table.columns.sort_by_key(|c| c.ordinal_position);
+        table.unique_constraints.sort_by(|a, b| a.name.cmp(&b.name));
+        table.indexes.sort_by(|a, b| a.name.cmp(&b.name));
+        table.foreign_keys.sort_by(|a, b| a.name.cmp(&b.name));
+        table.policies.sort_by(|a, b| a.name.cmp(&b.name));
+        table.triggers.sort_by(|a, b| a.name.cmp(&b.name));
+        table.storage_parameters.sort();
+    }
+    schema.indexes.sort_by(|a, b| a.name.cmp(&b.name));
+    schema.enums.sort_by(|a, b| a.name.cmp(&b.name));
+    schema.functions.sort_by(|a, b| (&a.name, &a.arguments).cmp(&(&b.name, &b.arguments)));
+    schema.privileges.sort_by(|a, b| {
+        (&a.object_type, &a.object_name, &a.column_name, &a.arguments, &a.grantee)
+            .cmp(&(&b.object_type, &b.object_name, &b.column_name, &b.arguments, &b.grantee))
+    });
+    schema.foreign_servers.sort_by(|a, b| a.name.cmp(&b.name));
+    schema.user_mappings.sort_by(|a, b| (&a.server, &a.user).cmp(&(&b.server, &b.user)));
+    schema.foreign_tables.sort_by(|a, b| a.name.cmp(&b.name));
+    schema.publications.sort_by(|a, b| a.name.cmp(&b.name));
+    schema.subscriptions.sort_by(|a, b| a.name.cmp(&b.name));
+    schema.custom_objects.sort_by(|a, b| (&a.object_type, &a.name).cmp(&(&b.object_type, &b.name)));
+    schema
+}
+
+/// Render the statements creating `schema`, with the object kinds `options`
+/// covers, and how many objects they create.
+pub fn render_schema_sql(schema: &SchemaModel, options: &DiffOptions) -> (String, usize) {
+    let report = bootstrap_report(&canonical_schema(schema), options);
+    let mut parts = vec![
+        "-- Schema dump generated by pgshift; regenerate it instead of editing it.".to_string(),
+        "-- Objects are sorted by name, so the file only changes when the schema does.".to_string(),
+    ];
+
+    let mut section = None;
+    for item in dependency_order(&report.items, Section::of) {
+        let Some(item_section) = Section::of(item) else { continue };
+        parts.push(String::new());
+        if section != Some(item_section) {
+            parts.push(format!("-- {}", item_section.header()));
+            section = Some(item_section);
+        }
+        parts.push(item.generated_up_sql.trim_end().to_string());
+    }
+    parts.push(String::new());
+
+    (parts.join("\n"), report.items.len())
+}
+
+/// Write the dump of `schema` to `path`, leaving the file untouched when it
+/// already holds it.
+pub fn write_schema_sql(schema: &SchemaModel, options: &DiffOptions, path: &str) -> std::io::Result<SchemaSqlDump> {
+    let (sql, objects) = render_schema_sql(schema, options);
+    let changed = fs::read_to_string(path).map_or(true, |existing| existing != sql);
+    if changed {
+        if let Some(parent) = Path::new(path).parent().filter(|p| !p.as_os_str().is_empty()) {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(path, &sql)?;
+    }
+    Ok(SchemaSqlDump { path: path.to_string(), objects, changed })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ddl::parse::parse_schema_sql;
+    use crate::diff::diff::compare_schemas;
+
+    const DDL: &str = r#"
+        CREATE TYPE status AS ENUM ('active', 'archived');
+        CREATE TABLE users (
+            id bigint PRIMARY KEY,
+            email varchar(255) NOT NULL,
+            status status NOT NULL DEFAULT 'active'
+        );
+        CREATE TABLE accounts (
+            id integer PRIMARY KEY,
+            owner_id bigint NOT NULL REFERENCES users (id)
+        );
+        CREATE UNIQUE INDEX users_email_key ON users (email);
+        CREATE INDEX accounts_owner_idx ON accounts (owner_id);
+    "#;
+
+    #[test]
+    fn test_render_schema_sql() {
+        let mut schema = parse_schema_sql(DDL).schema;
+        let (sql, objects) = render_schema_sql(&schema, &DiffOptions::default());
+        assert!(objects > 0);
+        assert!(sql.find("CREATE TABLE \"accounts\"").unwrap() < sql.find("CREATE TABLE \"users\"").unwrap());
+
+        // Declaration order does not change the dump
+        schema.tables.reverse();
+        assert_eq!(render_schema_sql(&schema, &DiffOptions::default()).0, sql);
+
+        // The dump reads back as the same schema
+        let parsed = parse_schema_sql(&sql);
+        assert!(parsed.warnings.is_empty(), "{:?}", parsed.warnings);
+        assert!(compare_schemas(&parsed.schema, &schema).items.is_empty());
+
+        let path = std::env::temp_dir().join(format!("pgshift_dump_{}", uuid::Uuid::new_v4())).join("schema.sql");
+        let path = path.to_str().unwrap();
+        let first = write_schema_sql(&schema, &DiffOptions::default(), path).unwrap();
+        let second = write_schema_sql(&schema, &DiffOptions::default(), path).unwrap();
+        fs::remove_dir_all(Path::new(path).parent().unwrap()).unwrap();
+        assert!(first.changed && !second.changed);
+    }
+}
@@ -4,7 +4,17 @@ use std::fs;
 use std::path::Path;
 use chrono::Utc;
 use serde::{Deserialize, Serialize};
-use crate::diff::DiffReport;
+use crate::diff::{DangerAcknowledgment, DataLoss, DiffItem, DiffKind, DiffReport, DiskEstimate};
+use crate::diff::depends::dependency_order;
+use crate::diff::disk::format_bytes;
+use crate::ddl::parse::{adds_enum_value, runs_in_transaction, split_statements, validates_constraint};
+use super::flyway::render_flyway_files;
+use super::golang_migrate::render_golang_migrate_files;
+use super::idempotent::idempotent_report;
+use super::rust_tools::{render_diesel_files, render_sqlx_files};
+use super::version::{create_migration_dir, VersionScheme};
+use crate::extension::find_extension;
+use crate::db::capabilities::TRANSACTIONAL_ADD_VALUE_SINCE;
 
 /// Metadata for a migration.
 #[derive(Debug, Serialize, Deserialize)]
@@ -14,7 +24,35 @@ pub struct MigrationMeta {
     pub generated_at: String,
     pub items_count: usize,
     pub has_dangerous: bool,
+    /// Dangerous items generated without an acknowledgment.
+    #[serde(default)]
+    pub unacknowledged_dangerous: usize,
+    /// Disk space the whole migration needs, when the target's sizes were known.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub disk_estimate: Option<DiskEstimate>,
     pub items: Vec<MigrationItemMeta>,
+    /// Position in a multi-phase migration, e.g. a dual-write type change.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub phase: Option<MigrationPhase>,
+    /// Folders this migration replaced when they were squashed into it.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub squashes: Vec<String>,
+}
+
+/// A phase of a multi-phase migration; phases are applied one at a time,
+/// in order.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct MigrationPhase {
+    /// Position in the set, from 1.
+    pub index: usize,
+    pub count: usize,
+    /// e.g. `backfill`.
+    pub kind: String,
+    /// What the operator should know or do around this phase.
+    pub instructions: Vec<String>,
+    /// False when `up.sql` commits by itself and must not be wrapped in a
+    /// transaction.
+    pub transactional: bool,
 }
 
 /// Metadata for a single migration item.
@@ -25,36 +63,144 @@ pub struct MigrationItemMeta {
     pub object_type: String,
     pub object_name: String,
     pub dangerous: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub acknowledgment: Option<DangerAcknowledgment>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub disk_estimate: Option<DiskEstimate>,
+    /// Rows the item drops, when they were counted before rendering.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub data_loss: Option<DataLoss>,
+}
+
+/// Options for rendering migration files.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RenderOptions {
+    /// End each statement with `/* migration=NAME item=ID */`, so entries in
+    /// pg_stat_statements and the server log can be traced back to the diff
+    /// item that generated them.
+    #[serde(default)]
+    pub trace_comments: bool,
+    /// Combine column and constraint changes of the same table into one
+    /// multi-action `ALTER TABLE`, so the table is locked, and rewritten,
+    /// once instead of once per change.
+    #[serde(default)]
+    pub batch_alter_table: bool,
+    /// Create indexes added to existing tables with `CREATE INDEX
+    /// CONCURRENTLY`, which does not block writes to the table while the
+    /// index is built. These statements run outside a transaction.
+    #[serde(default)]
+    pub concurrent_indexes: bool,
+    /// Write statements that do nothing when their work is already done
+    /// (`IF NOT EXISTS`, `IF EXISTS`, `CREATE OR REPLACE`, or a `DO` block
+    /// checking the catalogs), so a partly applied migration can be run
+    /// again.
+    #[serde(default)]
+    pub idempotent: bool,
+    /// How the version of the migration folder is written.
+    #[serde(default)]
+    pub version_scheme: VersionScheme,
+    /// Layout of the written migration.
+    #[serde(default)]
+    pub format: MigrationFormat,
 }
 
-/// Render migration files to disk.
+/// Layout of a rendered migration.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MigrationFormat {
+    /// A `<version>__<name>` folder with `up.sql`, `down.sql` and `meta.json`.
+    #[default]
+    Folder,
+    /// Flyway scripts `V<version>__<name>.sql` and `U<version>__<name>.sql`
+    /// in the migrations directory itself.
+    Flyway,
+    /// golang-migrate scripts `<version>_<name>.up.sql` and
+    /// `<version>_<name>.down.sql` in the migrations directory itself.
+    GolangMigrate,
+    /// `sqlx migrate` reversible scripts, named like golang-migrate's.
+    Sqlx,
+    /// A Diesel `<version>_<name>` folder with `up.sql` and `down.sql`.
+    Diesel,
+}
+
+/// Render migration files to disk and return the path of the migration
+/// folder, or of the versioned or up script of flat-file formats.
 pub fn render_migration_files(
     report: &DiffReport,
     name: &str,
     base_path: &str,
+    options: &RenderOptions,
 ) -> Result<String, std::io::Error> {
-    let timestamp = Utc::now().format("%Y%m%d%H%M%S").to_string();
     let sanitized_name = sanitize_name(name);
-    let folder_name = format!("{}__{}", timestamp, sanitized_name);
+    let mut rewritten = None;
+    if options.concurrent_indexes {
+        rewritten = Some(concurrent_index_report(report));
+    }
+    if options.idempotent {
+        rewritten = Some(idempotent_report(rewritten.as_ref().unwrap_or(report)));
+    }
+    let report = rewritten.as_ref().unwrap_or(report);
+    match options.format {
+        MigrationFormat::Folder => {}
+        MigrationFormat::Flyway => return render_flyway_files(report, &sanitized_name, base_path, options),
+        MigrationFormat::GolangMigrate => return render_golang_migrate_files(report, &sanitized_name, base_path, options),
+        MigrationFormat::Sqlx => return render_sqlx_files(report, &sanitized_name, base_path, options),
+        MigrationFormat::Diesel => return render_diesel_files(report, &sanitized_name, base_path, options),
+    }
+    let (migration_dir, timestamp) = create_migration_dir(base_path, &sanitized_name, options.version_scheme)?;
+    write_migration_folder(report, &migration_dir, &sanitized_name, &timestamp, options, Vec::new())?;
+    Ok(migration_dir.to_string_lossy().to_string())
+}
+
+/// A copy of `report` whose added indexes are created concurrently. Indexes
+/// of new tables are part of the table's item and stay as they are, since
+/// nothing writes to those tables yet.
+fn concurrent_index_report(report: &DiffReport) -> DiffReport {
+    let mut report = report.clone();
+    for item in report.items.iter_mut().filter(|i| i.object_type == "index" && i.kind == DiffKind::Added) {
+        for (plain, concurrent) in [
+            ("CREATE INDEX ", "CREATE INDEX CONCURRENTLY "),
+            ("CREATE UNIQUE INDEX ", "CREATE UNIQUE INDEX CONCURRENTLY "),
+        ] {
+            if let Some(rest) = item.generated_up_sql.strip_prefix(plain) {
+                item.generated_up_sql = format!("{}{}", concurrent, rest);
+            }
+        }
+    }
+    report
+}
+
+/// Write `up.sql`, `down.sql` and `meta.json` of `report` into the existing
+/// folder `migration_dir` of migration `name` at `timestamp`.
+pub(crate) fn write_migration_folder(
+    report: &DiffReport,
+    migration_dir: &Path,
+    name: &str,
+    timestamp: &str,
+    options: &RenderOptions,
+    squashes: Vec<String>,
+) -> Result<(), std::io::Error> {
+    let folder_name = format!("{}__{}", timestamp, name);
     
-    let migration_dir = Path::new(base_path).join(&folder_name);
-    fs::create_dir_all(&migration_dir)?;
+    let trace = options.trace_comments.then_some(folder_name.as_str());
     
     // Generate UP SQL
-    let up_sql = generate_up_sql(report);
+    let up_sql = generate_up_sql(report, trace, options.batch_alter_table);
     fs::write(migration_dir.join("up.sql"), &up_sql)?;
     
     // Generate DOWN SQL
-    let down_sql = generate_down_sql(report);
+    let down_sql = generate_down_sql(report, trace);
     fs::write(migration_dir.join("down.sql"), &down_sql)?;
     
     // Generate metadata
     let meta = MigrationMeta {
-        name: sanitized_name.clone(),
-        timestamp: timestamp.clone(),
+        name: name.to_string(),
+        timestamp: timestamp.to_string(),
         generated_at: Utc::now().to_rfc3339(),
         items_count: report.items.len(),
         has_dangerous: report.has_dangerous(),
+        unacknowledged_dangerous: report.unacknowledged_dangerous(),
+        disk_estimate: report.disk_estimate(),
         items: report
             .items
             .iter()
@@ -64,181 +210,773 @@ pub fn render_migration_files(
                 object_type: item.object_type.clone(),
                 object_name: item.object_name.clone(),
                 dangerous: item.dangerous,
+                acknowledgment: acknowledgment(item).cloned(),
+                disk_estimate: item.disk_estimate,
+                data_loss: item.data_loss,
             })
             .collect(),
+        phase: None,
+        squashes,
     };
     
     let meta_json = serde_json::to_string_pretty(&meta)
         .map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e))?;
     fs::write(migration_dir.join("meta.json"), &meta_json)?;
     
-    Ok(migration_dir.to_string_lossy().to_string())
+    Ok(())
 }
 
 /// Generate the UP SQL migration script.
-fn generate_up_sql(report: &DiffReport) -> String {
+///
+/// With a `trace` migration name, each statement carries a trace comment
+/// (see [`RenderOptions::trace_comments`]); with `batch_alter_table`, see
+/// [`RenderOptions::batch_alter_table`].
+pub(crate) fn generate_up_sql(report: &DiffReport, trace: Option<&str>, batch_alter_table: bool) -> String {
     let mut parts = Vec::new();
     
     parts.push("-- Migration UP Script".to_string());
     parts.push(format!("-- Generated at: {}", Utc::now().to_rfc3339()));
     parts.push("-- This script applies the schema changes to the target database.\n".to_string());
     
-    parts.push("BEGIN;\n".to_string());
-    
-    // Group items by type for better organization
-    // IMPORTANT: ENUMs must be created FIRST, before tables that use them
-    let enums_added: Vec<_> = report.items.iter()
-        .filter(|i| i.object_type == "enum" && matches!(i.kind, crate::diff::DiffKind::Added))
-        .collect();
-    
-    let enums_modified: Vec<_> = report.items.iter()
-        .filter(|i| i.object_type == "enum" && matches!(i.kind, crate::diff::DiffKind::Modified))
-        .collect();
-    
-    let enums_removed: Vec<_> = report.items.iter()
-        .filter(|i| i.object_type == "enum" && matches!(i.kind, crate::diff::DiffKind::Removed))
-        .collect();
-    
-    let tables_added: Vec<_> = report.items.iter()
-        .filter(|i| i.object_type == "table" && matches!(i.kind, crate::diff::DiffKind::Added))
-        .collect();
-    
-    let tables_removed: Vec<_> = report.items.iter()
-        .filter(|i| i.object_type == "table" && matches!(i.kind, crate::diff::DiffKind::Removed))
-        .collect();
-    
-    let columns: Vec<_> = report.items.iter()
-        .filter(|i| i.object_type == "column")
-        .collect();
-    
-    let constraints: Vec<_> = report.items.iter()
-        .filter(|i| i.object_type == "constraint")
-        .collect();
+    let dangerous = report.items.iter().filter(|i| i.dangerous).count();
+    if dangerous > 0 {
+        parts.push(format!(
+            "-- Dangerous changes: {} ({} acknowledged)\n",
+            dangerous,
+            dangerous - report.unacknowledged_dangerous()
+        ));
+    }
     
-    let indexes: Vec<_> = report.items.iter()
-        .filter(|i| i.object_type == "index")
+    let dropped: Vec<_> = report.items.iter()
+        .filter_map(|i| i.data_loss.map(|loss| (i, loss)))
         .collect();
-    
-    // ENUM types MUST be created FIRST (before tables that use them)
-    if !enums_added.is_empty() {
-        parts.push("-- Create enum types (must be before tables)".to_string());
-        for item in &enums_added {
-            parts.push(format!("-- {}", item.details));
-            parts.push(item.generated_up_sql.clone());
+    if !dropped.is_empty() {
+        parts.push("-- Data dropped, as counted on the target:".to_string());
+        for (item, loss) in &dropped {
+            parts.push(format!("--   {} {}: {}", item.object_type, item.object_name, loss.summary()));
         }
         parts.push(String::new());
     }
     
-    // Modify existing ENUMs (add values)
-    if !enums_modified.is_empty() {
-        parts.push("-- Modify enum types".to_string());
-        for item in &enums_modified {
-            parts.push(format!("-- {}", item.details));
-            if item.dangerous {
-                parts.push("-- ⚠️  DANGEROUS: Removing ENUM values may cause data issues".to_string());
+    if let Some(estimate) = report.disk_estimate() {
+        parts.push("-- Estimated disk space, from the target's current table and index sizes:".to_string());
+        parts.push(format!(
+            "--   {} temporary, released at commit; {} kept after",
+            format_bytes(estimate.temporary_bytes),
+            format_bytes(estimate.final_bytes)
+        ));
+        parts.push(format!("--   Have at least {} free before applying.\n", format_bytes(estimate.peak_bytes())));
+    }
+    
+    let header = parts.len();
+    
+    // Items go by section, and out of it only when they depend on an item
+    // of a later one
+    let mut section = None;
+    let mut batch = AlterTableBatch::default();
+    for item in dependency_order(&report.items, Section::of) {
+        let Some(item_section) = Section::of(item) else { continue };
+        let actions = if batch_alter_table && item_section.batches_alter_table() {
+            alter_table_actions(&item.generated_up_sql)
+        } else {
+            None
+        };
+        if section != Some(item_section) || actions.as_ref().map(|(table, _)| table) != batch.table.as_ref() {
+            batch.flush(&mut parts, trace);
+        }
+        
+        if section != Some(item_section) {
+            if section.is_some() {
+                parts.push(String::new());
             }
-            parts.push(item.generated_up_sql.clone());
+            parts.push(format!("-- {}", item_section.header()));
+            section = Some(item_section);
         }
+        parts.push(format!("-- {}", item.details));
+        for warning in item_section.warnings(item) {
+            parts.push(format!("-- ⚠️  {}", warning));
+        }
+        push_annotations(&mut parts, item);
+        match actions {
+            Some((table, actions)) => batch.add(item, table, actions),
+            None => parts.push(traced(&item.generated_up_sql, trace, item)),
+        }
+    }
+    batch.flush(&mut parts, trace);
+    if section.is_some() {
         parts.push(String::new());
     }
     
-    // Add tables
-    if !tables_added.is_empty() {
-        parts.push("-- Create new tables".to_string());
-        for item in &tables_added {
-            parts.push(format!("-- {}", item.details));
-            parts.push(item.generated_up_sql.clone());
+    let body = parts.split_off(header).join("\n");
+    parts.push(in_transactions(&body, report.target_major_version));
+    
+    parts.join("\n")
+}
+
+/// `body` in `BEGIN;` / `COMMIT;`.
+///
+/// Statements that cannot run in a transaction block, such as `CREATE INDEX
+/// CONCURRENTLY` and `ALTER TYPE ... ADD VALUE` (see [`runs_in_transaction`]),
+/// are left between transactions, splitting the script into sections that
+/// apply in order. Validations of constraints start a transaction of their
+/// own, so the constraints added `NOT VALID` before them are committed, and
+/// their locks released, before the rows are checked.
+///
+/// From PostgreSQL 12 on, `ADD VALUE` runs in a transaction, but a new value
+/// cannot be used before that transaction commits: enum values are added in
+/// a transaction of their own when the target's `major_version` is known.
+fn in_transactions(body: &str, major_version: Option<u32>) -> String {
+    let transactional_add_value = major_version.is_some_and(|v| v >= TRANSACTIONAL_ADD_VALUE_SINCE);
+    let mut script = String::new();
+    let mut copied = 0;
+    let mut offset = 0;
+    // Outside a transaction (None), or inside one of some kind
+    let mut section: Option<Option<Transaction>> = None;
+    for statement in split_statements(body) {
+        let Some(found) = body[offset..].find(&statement).map(|i| offset + i) else {
+            break;
+        };
+        offset = found + statement.len();
+        let statement_section = if transactional_add_value && adds_enum_value(&statement) {
+            Some(Transaction::EnumValues)
+        } else if validates_constraint(&statement) {
+            runs_in_transaction(&statement).then_some(Transaction::Validation)
+        } else {
+            runs_in_transaction(&statement).then_some(Transaction::Plain)
+        };
+        let continues = match (section, statement_section) {
+            (Some(previous), current) if previous == current => true,
+            // Statements after a validation may share its transaction
+            (Some(Some(Transaction::Validation)), Some(Transaction::Plain)) => true,
+            _ => false,
+        };
+        if continues {
+            section = Some(statement_section);
+            continue;
         }
-        parts.push(String::new());
+        // The first section starts with the script
+        let start = if section.is_none() { 0 } else { found };
+        script.push_str(&body[copied..start]);
+        // Keep a blank line before a split inside an item
+        if !script.is_empty() && !script.ends_with("\n\n") {
+            script.push('\n');
+        }
+        if let Some(Some(_)) = section {
+            script.push_str("COMMIT;\n\n");
+        }
+        match statement_section {
+            Some(_) => script.push_str("BEGIN;\n\n"),
+            None => script.push_str("-- Outside a transaction: this cannot run in a transaction block\n"),
+        }
+        copied = start;
+        section = Some(statement_section);
     }
     
-    // Add columns
-    if !columns.is_empty() {
-        parts.push("-- Column changes".to_string());
-        for item in &columns {
-            parts.push(format!("-- {}", item.details));
-            if item.dangerous {
-                parts.push("-- ⚠️  DANGEROUS: This operation may cause data loss".to_string());
+    match section {
+        None if body.is_empty() => "BEGIN;\n\nCOMMIT;".to_string(),
+        None => format!("BEGIN;\n\n{}\nCOMMIT;", body),
+        Some(current) => {
+            script.push_str(&body[copied..]);
+            if current.is_some() {
+                script.push_str("\nCOMMIT;");
             }
-            parts.push(item.generated_up_sql.clone());
+            script
         }
-        parts.push(String::new());
+    }
+}
+
+/// Kind of the transaction a statement of [`in_transactions`] runs in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Transaction {
+    Plain,
+    /// Validating constraints added `NOT VALID` before.
+    Validation,
+    /// Adding enum values, committed before anything uses them.
+    EnumValues,
+}
+
+/// Consecutive items whose statements are all `ALTER TABLE` actions on one
+/// table, written as a single statement.
+#[derive(Default)]
+struct AlterTableBatch<'a> {
+    /// Quoted name of the table.
+    table: Option<String>,
+    items: Vec<&'a DiffItem>,
+    actions: Vec<String>,
+}
+
+impl<'a> AlterTableBatch<'a> {
+    fn add(&mut self, item: &'a DiffItem, table: String, actions: Vec<String>) {
+        self.table = Some(table);
+        self.items.push(item);
+        self.actions.extend(actions);
     }
     
-    // Add constraints
-    if !constraints.is_empty() {
-        parts.push("-- Constraint changes".to_string());
-        for item in &constraints {
-            parts.push(format!("-- {}", item.details));
-            parts.push(item.generated_up_sql.clone());
+    /// Append the batch's statement and start a new batch.
+    fn flush(&mut self, parts: &mut Vec<String>, trace: Option<&str>) {
+        let batch = std::mem::take(self);
+        let Some(table) = batch.table else { return };
+        // A single action is written as generated
+        if let [item] = batch.items[..] {
+            if batch.actions.len() == 1 {
+                parts.push(traced(&item.generated_up_sql, trace, item));
+                return;
+            }
         }
-        parts.push(String::new());
+        
+        let mut statement = format!("ALTER TABLE {}\n    {}", table, batch.actions.join(",\n    "));
+        if let Some(migration) = trace {
+            let ids: Vec<&str> = batch.items.iter().map(|i| i.id.as_str()).collect();
+            statement.push_str(&format!(" /* migration={} item={} */", migration, ids.join(",")));
+        }
+        statement.push(';');
+        parts.push(statement);
+    }
+}
+
+/// The table and actions of SQL made only of `ALTER TABLE "name" ...`
+/// statements on one table; `None` for anything else.
+///
+/// Renames cannot share a statement with other actions, and SQL with
+/// comments is left alone so they stay next to their statement.
+fn alter_table_actions(sql: &str) -> Option<(String, Vec<String>)> {
+    if sql.contains("--") || sql.contains("/*") {
+        return None;
     }
     
-    // Add indexes
-    if !indexes.is_empty() {
-        parts.push("-- Index changes".to_string());
-        for item in &indexes {
-            parts.push(format!("-- {}", item.details));
-            parts.push(item.generated_up_sql.clone());
+    let mut table: Option<String> = None;
+    let mut actions = Vec::new();
+    for statement in split_statements(sql) {
+        let rest = statement.strip_prefix("ALTER TABLE \"")?;
+        // The name ends at the first quote that is not doubled
+        let mut end = None;
+        let mut chars = rest.char_indices().peekable();
+        while let Some((i, c)) = chars.next() {
+            if c == '"' {
+                if chars.peek().is_some_and(|&(_, next)| next == '"') {
+                    chars.next();
+                } else {
+                    end = Some(i);
+                    break;
+                }
+            }
         }
-        parts.push(String::new());
+        let end = end?;
+        let name = format!("\"{}\"", &rest[..end]);
+        let action = rest[end + 1..].strip_prefix(char::is_whitespace)?.trim();
+        if action.is_empty() || action.starts_with("RENAME") {
+            return None;
+        }
+        if table.as_ref().is_some_and(|t| *t != name) {
+            return None;
+        }
+        table = Some(name);
+        actions.push(action.to_string());
+    }
+    table.map(|table| (table, actions))
+}
+
+/// Section of the up script, in the order the sections run.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub(super) enum Section {
+    /// Renames come first; every later statement uses the new names
+    Renames,
+    /// Enum types must exist before the tables that use them
+    EnumsAdded,
+    EnumsModified,
+    /// Functions may be used by defaults, generated columns and policies
+    FunctionsChanged,
+    TablesAdded,
+    /// Servers, user mappings and foreign tables, already in dependency
+    /// order; foreign tables may use enum types, so they follow the types
+    ForeignDataChanged,
+    /// Drop triggers before the columns and functions they use change
+    TriggersRemoved,
+    /// Policies may reference columns, so drops run before column changes
+    /// and creates run after them
+    PoliciesRemoved,
+    /// An inherited column can only be dropped once the table is detached
+    InheritanceRemoved,
+    Columns,
+    Constraints,
+    /// Switch between logged and unlogged once the columns are final
+    Persistence,
+    /// A table must have the parent's columns before it can inherit from it
+    InheritanceAdded,
+    Indexes,
+    /// Validating scans the table, which may use the indexes just built
+    Validations,
+    StorageParameters,
+    RowSecurity,
+    TriggersChanged,
+    /// Publications list tables, so they change once the tables exist
+    Replication,
+    /// Privileges are granted once the objects they refer to exist
+    Privileges,
+    /// Objects of extension types may refer to any built-in object, so
+    /// they are dropped before and created after all of them change
+    CustomObjectsRemoved,
+    CustomObjectsChanged,
+    /// Foreign tables, then mappings, then servers
+    ForeignDataRemoved,
+    /// Drop functions once nothing in this migration uses them
+    FunctionsRemoved,
+    EnumsRemoved,
+    TablesRemoved,
+}
+
+impl Section {
+    /// Section of an item; items of unknown types are not rendered.
+    pub(super) fn of(item: &DiffItem) -> Option<Self> {
+        let removed = item.kind == DiffKind::Removed;
+        let section = match item.object_type.as_str() {
+            "rename" => Self::Renames,
+            "enum" => match item.kind {
+                DiffKind::Added => Self::EnumsAdded,
+                DiffKind::Removed => Self::EnumsRemoved,
+                DiffKind::Modified | DiffKind::Reordered | DiffKind::Renamed => Self::EnumsModified,
+            },
+            "function" if removed => Self::FunctionsRemoved,
+            "function" => Self::FunctionsChanged,
+            "table" => match item.kind {
+                DiffKind::Added => Self::TablesAdded,
+                DiffKind::Removed => Self::TablesRemoved,
+                _ => return None,
+            },
+            "foreign_server" | "user_mapping" | "foreign_table" if removed => Self::ForeignDataRemoved,
+            "foreign_server" | "user_mapping" | "foreign_table" => Self::ForeignDataChanged,
+            "trigger" if removed => Self::TriggersRemoved,
+            "trigger" => Self::TriggersChanged,
+            "policy" if removed => Self::PoliciesRemoved,
+            "policy" | "rls" => Self::RowSecurity,
+            "inheritance" if removed => Self::InheritanceRemoved,
+            "inheritance" => Self::InheritanceAdded,
+            "column" => Self::Columns,
+            "constraint" => Self::Constraints,
+            "persistence" => Self::Persistence,
+            "index" => Self::Indexes,
+            "validation" => Self::Validations,
+            "storage_parameters" => Self::StorageParameters,
+            "publication" | "subscription" => Self::Replication,
+            "privilege" => Self::Privileges,
+            other if find_extension(other).is_some() => {
+                if removed { Self::CustomObjectsRemoved } else { Self::CustomObjectsChanged }
+            }
+            _ => return None,
+        };
+        Some(section)
     }
     
-    // Drop ENUMs after tables that use them are dropped
-    if !enums_removed.is_empty() {
-        parts.push("-- Drop enum types".to_string());
-        for item in &enums_removed {
-            parts.push(format!("-- {}", item.details));
-            parts.push("-- ⚠️  DANGEROUS: This will fail if the type is still in use".to_string());
-            parts.push(item.generated_up_sql.clone());
+    pub(super) fn header(self) -> &'static str {
+        match self {
+            Self::Renames => "Rename tables and columns",
+            Self::EnumsAdded => "Create enum types (must be before tables)",
+            Self::EnumsModified => "Modify enum types",
+            Self::FunctionsChanged => "Create or replace functions",
+            Self::TablesAdded => "Create new tables",
+            Self::ForeignDataChanged => "Foreign data changes",
+            Self::TriggersRemoved => "Drop triggers",
+            Self::PoliciesRemoved => "Drop row level security policies",
+            Self::InheritanceRemoved => "Remove table inheritance",
+            Self::Columns => "Column changes",
+            Self::Constraints => "Constraint changes",
+            Self::Persistence => "Table persistence changes",
+            Self::InheritanceAdded => "Add table inheritance",
+            Self::Indexes => "Index changes",
+            Self::Validations => "Constraint validation",
+            Self::StorageParameters => "Storage parameter changes",
+            Self::RowSecurity => "Row level security changes",
+            Self::TriggersChanged => "Trigger changes",
+            Self::Replication => "Logical replication changes",
+            Self::Privileges => "Privilege changes",
+            Self::CustomObjectsRemoved => "Drop custom objects",
+            Self::CustomObjectsChanged => "Custom object changes",
+            Self::ForeignDataRemoved => "Drop foreign data objects",
+            Self::FunctionsRemoved => "Drop functions",
+            Self::EnumsRemoved => "Drop enum types",
+            Self::TablesRemoved => "Drop tables",
         }
-        parts.push(String::new());
     }
     
-    // Drop tables last
-    if !tables_removed.is_empty() {
-        parts.push("-- Drop tables".to_string());
-        for item in &tables_removed {
-            parts.push(format!("-- {}", item.details));
-            parts.push("-- ⚠️  DANGEROUS: This operation will permanently delete data".to_string());
-            parts.push(item.generated_up_sql.clone());
-        }
-        parts.push(String::new());
+    /// Whether statements of this section may share an `ALTER TABLE`.
+    fn batches_alter_table(self) -> bool {
+        matches!(self, Self::Columns | Self::Constraints)
     }
     
-    parts.push("COMMIT;".to_string());
+    /// Warnings written above an item of this section.
+    fn warnings(self, item: &DiffItem) -> Vec<&'static str> {
+        let mut warnings = Vec::new();
+        let danger = match self {
+            Self::EnumsModified => Some("DANGEROUS: Removing ENUM values may cause data issues"),
+            Self::ForeignDataChanged => Some("DANGEROUS: Fails while foreign tables or user mappings still use the server"),
+            Self::InheritanceRemoved => Some("DANGEROUS: Queries on the parent stop returning this table's rows"),
+            Self::Columns => Some("DANGEROUS: This operation may cause data loss"),
+            Self::Persistence => Some("DANGEROUS: Unlogged tables are emptied after a crash and not replicated"),
+            Self::RowSecurity => Some("DANGEROUS: Disabling row level security exposes all rows"),
+            Self::Replication => Some("DANGEROUS: Interrupts replication to existing subscribers"),
+            _ => None,
+        };
+        warnings.extend(danger.filter(|_| item.dangerous));
+        match self {
+            Self::EnumsRemoved => warnings.push("DANGEROUS: This will fail if the type is still in use"),
+            Self::TablesRemoved => warnings.push("DANGEROUS: This operation will permanently delete data"),
+            _ => {}
+        }
+        match self {
+            Self::EnumsModified if item.rewrites_table => {
+                warnings.push("Rewrites every table using the type under an ACCESS EXCLUSIVE lock");
+            }
+            Self::Columns if item.rewrites_table => {
+                warnings.push("Rewrites the whole table under an ACCESS EXCLUSIVE lock");
+            }
+            Self::Persistence => warnings.push("Rewrites the whole table under an ACCESS EXCLUSIVE lock"),
+            _ => {}
+        }
+        warnings
+    }
+}
+
+/// The acknowledgment of a dangerous item; ignored on safe items.
+fn acknowledgment(item: &DiffItem) -> Option<&DangerAcknowledgment> {
+    item.acknowledgment.as_ref().filter(|_| item.dangerous)
+}
+
+/// Append the comments written right above an item's statement: its
+/// acknowledgment if it has one, and its disk estimate.
+///
+/// Acknowledgments are written as `-- @key: value` lines so reviewers and
+/// tools can find them next to the statement they cover.
+fn push_annotations(parts: &mut Vec<String>, item: &DiffItem) {
+    if let Some(ack) = acknowledgment(item) {
+        parts.push(format!("-- @acknowledged-by: {}", comment_lines(&ack.acknowledged_by).join(" ")));
+        parts.push(format!("-- @acknowledged-at: {}", comment_lines(&ack.acknowledged_at).join(" ")));
+        for (i, line) in comment_lines(&ack.reason).iter().enumerate() {
+            if i == 0 {
+                parts.push(format!("-- @reason: {}", line));
+            } else {
+                parts.push(format!("--   {}", line));
+            }
+        }
+    }
+    if let Some(estimate) = &item.disk_estimate {
+        parts.push(format!(
+            "-- Disk: ~{} temporary, ~{} kept",
+            format_bytes(estimate.temporary_bytes),
+            format_bytes(estimate.final_bytes)
+        ));
+    }
+}
+
+/// `sql` with a `/* migration=NAME item=ID */` comment before the semicolon
+/// of each statement, where pg_stat_statements and the server log keep it.
+///
+/// Everything else, including comments between statements, is kept as is.
+fn traced(sql: &str, trace: Option<&str>, item: &DiffItem) -> String {
+    let Some(migration) = trace else {
+        return sql.to_string();
+    };
     
-    parts.join("\n")
+    let comment = format!("/* migration={} item={} */", migration, item.id);
+    let mut traced = String::new();
+    let mut rest = sql;
+    // Statements are trimmed slices of the SQL, in order
+    for statement in split_statements(sql) {
+        let Some(start) = rest.find(&statement) else {
+            break;
+        };
+        let end = start + statement.len();
+        traced.push_str(&rest[..end]);
+        // A trailing line comment would swallow the trace comment
+        let separator = match statement.lines().last() {
+            Some(line) if line.contains("--") => "\n",
+            _ => " ",
+        };
+        traced.push_str(separator);
+        traced.push_str(&comment);
+        rest = &rest[end..];
+    }
+    traced.push_str(rest);
+    traced
+}
+
+/// Split user input into lines that cannot end a `--` comment early.
+fn comment_lines(text: &str) -> Vec<&str> {
+    text.split(['\r', '\n'])
+        .map(str::trim_end)
+        .filter(|line| !line.is_empty())
+        .collect()
 }
 
 /// Generate the DOWN SQL migration script (rollback).
-fn generate_down_sql(report: &DiffReport) -> String {
+pub(crate) fn generate_down_sql(report: &DiffReport, trace: Option<&str>) -> String {
     let mut parts = Vec::new();
     
     parts.push("-- Migration DOWN Script (Rollback)".to_string());
     parts.push(format!("-- Generated at: {}", Utc::now().to_rfc3339()));
     parts.push("-- This script reverts the schema changes.\n".to_string());
     
-    parts.push("BEGIN;\n".to_string());
-    
-    // Reverse order: indexes, constraints, columns, tables
-    let items_reversed: Vec<_> = report.items.iter().rev().collect();
+    // Undo the up script's statements in reverse order
+    let items_reversed = dependency_order(&report.items, Section::of).into_iter().rev();
     
+    let mut body = Vec::new();
     for item in items_reversed {
-        parts.push(format!("-- Revert: {}", item.details));
-        parts.push(item.generated_down_sql.clone());
+        body.push(format!("-- Revert: {}", item.details));
+        body.push(traced(&item.generated_down_sql, trace, item));
     }
-    
-    parts.push("\nCOMMIT;".to_string());
+    body.push(String::new());
+    parts.push(in_transactions(&body.join("\n"), report.target_major_version));
     
     parts.join("\n")
 }
 
+/// `script` without its `BEGIN`/`COMMIT` lines, for tools that run each
+/// migration in a transaction of their own. With `stable`, the
+/// generation time is left out too, so tools that checksum scripts get the
+/// same checksum when the same diff is rendered again.
+pub(crate) fn unwrap_script(script: &str, stable: bool) -> String {
+    let lines: Vec<&str> = script.lines()
+        .filter(|line| !(stable && line.starts_with("-- Generated at: ")))
+        .filter(|line| !matches!(*line, "BEGIN;" | "COMMIT;"))
+        .collect();
+    let mut script = lines.join("\n").trim_end().to_string();
+    script.push('\n');
+    script
+}
+
 /// Sanitize the migration name for use in filenames.
-fn sanitize_name(name: &str) -> String {
+pub(crate) fn sanitize_name(name: &str) -> String {
     name.chars()
         .map(|c| if c.is_alphanumeric() || c == '_' || c == '-' { c } else { '_' })
         .collect::<String>()
         .to_lowercase()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_acknowledgment_comments() {
+        let mut report = DiffReport::new();
+        let mut item = DiffItem::new(
+            DiffKind::Removed, "table", "public.audit_log", "Drop table audit_log",
+            "DROP TABLE public.audit_log;", "", true,
+        );
+        item.acknowledgment = Some(DangerAcknowledgment {
+            acknowledged_by: "dba".to_string(),
+            acknowledged_at: "2026-02-03T02:00:00Z".to_string(),
+            reason: "Archived to cold storage\r\nDROP TABLE users;".to_string(),
+        });
+        report.items.push(item);
+        report.items.push(DiffItem::new(
+            DiffKind::Removed, "table", "public.sessions", "Drop table sessions",
+            "DROP TABLE public.sessions;", "", true,
+        ));
+
+        let sql = generate_up_sql(&report, None, false);
+
+        assert!(sql.contains("-- Dangerous changes: 2 (1 acknowledged)"));
+        assert!(sql.contains(concat!(
+            "-- @acknowledged-by: dba\n",
+            "-- @acknowledged-at: 2026-02-03T02:00:00Z\n",
+            "-- @reason: Archived to cold storage\n",
+            "--   DROP TABLE users;\n",
+            "DROP TABLE public.audit_log;",
+        )));
+        assert_eq!(sql.matches("@acknowledged-by").count(), 1);
+    }
+
+    #[test]
+    fn test_disk_estimate_comments() {
+        let mut report = DiffReport::new();
+        let mut item = DiffItem::new(
+            DiffKind::Added, "index", "orders.orders_customer_idx", "Create index",
+            "CREATE INDEX \"orders_customer_idx\" ON \"orders\" (\"customer_id\");", "", false,
+        );
+        item.disk_estimate = Some(DiskEstimate { temporary_bytes: 3 << 20, final_bytes: 3 << 20 });
+        report.items.push(item);
+
+        let sql = generate_up_sql(&report, None, false);
+        assert!(sql.contains("-- Disk: ~3.0 MB temporary, ~3.0 MB kept\nCREATE INDEX"));
+        assert!(sql.contains("Have at least 6.0 MB free before applying."));
+
+        assert!(!generate_up_sql(&DiffReport::new(), None, false).contains("disk space"));
+    }
+
+    #[test]
+    fn test_trace_comments() {
+        let mut report = DiffReport::new();
+        let item = DiffItem::new(
+            DiffKind::Modified, "constraint", "orders.orders_user_id_fkey", "Modify foreign key",
+            "ALTER TABLE \"orders\" DROP CONSTRAINT IF EXISTS \"orders_user_id_fkey\";\n\
+             ALTER TABLE \"orders\" ADD CONSTRAINT \"orders_user_id_fkey\" CHECK (note <> ';'); -- keeps rows",
+            "-- Nothing to revert",
+            false,
+        );
+        let comment = format!("/* migration=20260101000000__fk item={} */", item.id);
+        report.items.push(item);
+
+        let up = generate_up_sql(&report, Some("20260101000000__fk"), false);
+        assert!(up.contains(&format!("DROP CONSTRAINT IF EXISTS \"orders_user_id_fkey\" {};\n", comment)));
+        assert!(up.contains(&format!("CHECK (note <> ';') {};", comment)));
+        assert!(up.contains(&format!("{}; -- keeps rows", comment)));
+
+        let down = generate_down_sql(&report, Some("20260101000000__fk"));
+        assert!(down.contains("-- Nothing to revert") && !down.contains("item="));
+        assert!(!generate_up_sql(&report, None, false).contains("migration="));
+    }
+
+    #[test]
+    fn test_non_transactional_sections() {
+        let mut report = DiffReport::new();
+        report.items.push(DiffItem::new(
+            DiffKind::Modified, "enum", "mood", "Add enum value",
+            "ALTER TYPE \"mood\" ADD VALUE IF NOT EXISTS 'meh';", "", false,
+        ));
+        report.items.push(DiffItem::new(
+            DiffKind::Added, "table", "ratings", "Create table ratings",
+            "CREATE TABLE \"ratings\" (\"score\" mood DEFAULT 'meh');", "DROP TABLE IF EXISTS \"ratings\";", false,
+        ));
+
+        let up = generate_up_sql(&report, None, false);
+        let position = |text: &str| up.find(text).unwrap();
+        assert!(position("-- Outside a transaction") < position("ALTER TYPE"));
+        assert!(position("ALTER TYPE") < position("BEGIN;"));
+        assert!(position("BEGIN;") < position("CREATE TABLE"));
+        assert!(up.trim_end().ends_with("COMMIT;"));
+        assert!(!unwrap_script(&up, false).lines().any(|line| line == "BEGIN;" || line == "COMMIT;"));
+
+        let down = generate_down_sql(&report, None);
+        assert!(down.starts_with("BEGIN;") || down.contains("\nBEGIN;\n"));
+        assert!(!down.contains("Outside a transaction"));
+
+        // PostgreSQL 12 adds the value in a transaction committed before the table uses it
+        report.target_major_version = Some(12);
+        let up = generate_up_sql(&report, None, false);
+        let position = |text: &str| up.find(text).unwrap();
+        assert!(!up.contains("Outside a transaction"));
+        assert!(position("BEGIN;") < position("ALTER TYPE"));
+        assert!(position("ALTER TYPE") < position("COMMIT;"));
+        assert!(position("COMMIT;") < position("CREATE TABLE"));
+        assert_eq!(up.matches("BEGIN;").count(), 2);
+    }
+
+    #[test]
+    fn test_concurrent_indexes() {
+        let mut report = DiffReport::new();
+        report.items.push(DiffItem::new(
+            DiffKind::Added, "table", "ratings", "Create table ratings",
+            "CREATE TABLE \"ratings\" (\"id\" integer);\nCREATE INDEX \"ratings_id_idx\" ON \"ratings\" (\"id\");",
+            "DROP TABLE IF EXISTS \"ratings\";", false,
+        ));
+        report.items.push(DiffItem::new(
+            DiffKind::Added, "index", "orders.orders_code_key", "Create index",
+            "CREATE UNIQUE INDEX \"orders_code_key\" ON \"orders\" (\"code\");",
+            "DROP INDEX IF EXISTS \"orders_code_key\";", false,
+        ));
+
+        let concurrent = concurrent_index_report(&report);
+        let up = generate_up_sql(&concurrent, None, false);
+        assert!(up.contains("CREATE UNIQUE INDEX CONCURRENTLY \"orders_code_key\""));
+        assert!(up.contains("CREATE INDEX \"ratings_id_idx\""));
+        assert!(up.find("COMMIT;").unwrap() < up.find("CONCURRENTLY").unwrap());
+
+        let idempotent = idempotent_report(&concurrent);
+        assert!(idempotent.items[1].generated_up_sql.starts_with("CREATE UNIQUE INDEX CONCURRENTLY IF NOT EXISTS "));
+    }
+
+    #[test]
+    fn test_validation_transaction() {
+        let mut report = DiffReport::new();
+        report.items.push(DiffItem::new(
+            DiffKind::Added, "constraint", "orders.orders_user_id_fkey", "Add foreign key",
+            "ALTER TABLE \"orders\" ADD CONSTRAINT \"orders_user_id_fkey\" FOREIGN KEY (\"user_id\") REFERENCES \"users\" (\"id\") NOT VALID;",
+            "ALTER TABLE \"orders\" DROP CONSTRAINT IF EXISTS \"orders_user_id_fkey\";", false,
+        ));
+        report.items.push(DiffItem::new(
+            DiffKind::Modified, "validation", "orders.orders_user_id_fkey", "Validate foreign key",
+            "ALTER TABLE \"orders\" VALIDATE CONSTRAINT \"orders_user_id_fkey\";", "", false,
+        ));
+        report.items.push(DiffItem::new(
+            DiffKind::Modified, "rls", "orders", "Enable row level security",
+            "ALTER TABLE \"orders\" ENABLE ROW LEVEL SECURITY;", "", false,
+        ));
+
+        let up = generate_up_sql(&report, None, false);
+        let position = |text: &str| up.find(text).unwrap();
+        assert!(position("NOT VALID;\n\nCOMMIT;\n\nBEGIN;") < position("VALIDATE CONSTRAINT"));
+        assert_eq!(up.matches("BEGIN;").count(), 2);
+        assert!(position("VALIDATE CONSTRAINT") < position("ENABLE ROW LEVEL SECURITY"));
+        assert!(up.trim_end().ends_with("COMMIT;"));
+
+        // A validation alone needs no commit before it
+        report.items.remove(0);
+        assert_eq!(generate_up_sql(&report, None, false).matches("BEGIN;").count(), 1);
+    }
+
+    #[test]
+    fn test_batch_alter_table() {
+        let mut report = DiffReport::new();
+        let column = |name: &str, up_sql: &str| DiffItem::new(DiffKind::Modified, "column", name, "Modify column", up_sql, "", false);
+        report.items.push(column("orders.total", "ALTER TABLE \"orders\" ALTER COLUMN \"total\" TYPE numeric(12,2);"));
+        report.items.push(column(
+            "orders.note",
+            "ALTER TABLE \"orders\" ALTER COLUMN \"note\" SET DEFAULT ';';\nALTER TABLE \"orders\" ALTER COLUMN \"note\" SET NOT NULL;",
+        ));
+        report.items.push(column("users.email", "ALTER TABLE \"users\" ALTER COLUMN \"email\" SET NOT NULL;"));
+        report.items.push(column("users.name", "UPDATE \"users\" SET \"name\" = '';\nALTER TABLE \"users\" ALTER COLUMN \"name\" SET NOT NULL;"));
+
+        let up = generate_up_sql(&report, None, true);
+        assert!(up.contains(concat!(
+            "ALTER TABLE \"orders\"\n",
+            "    ALTER COLUMN \"total\" TYPE numeric(12,2),\n",
+            "    ALTER COLUMN \"note\" SET DEFAULT ';',\n",
+            "    ALTER COLUMN \"note\" SET NOT NULL;",
+        )));
+        // A lone action, and items with other statements, are written as generated
+        assert!(up.contains("-- Modify column\nALTER TABLE \"users\" ALTER COLUMN \"email\" SET NOT NULL;"));
+        assert!(up.contains("UPDATE \"users\" SET \"name\" = '';"));
+        assert_eq!(up.matches("ALTER TABLE \"orders\"").count(), 1);
+
+        let traced = generate_up_sql(&report, Some("20260101000000__batch"), true);
+        let ids = format!("item={},{} */;", report.items[0].id, report.items[1].id);
+        assert!(traced.contains(&format!("SET NOT NULL /* migration=20260101000000__batch {}", ids)));
+
+        assert_eq!(generate_up_sql(&report, None, false).matches("ALTER TABLE \"orders\"").count(), 3);
+    }
+
+    #[test]
+    fn test_dependency_ordering() {
+        let mut report = DiffReport::new();
+        report.items.push(DiffItem::new(
+            DiffKind::Added, "constraint", "orders.orders_email_fkey", "Add foreign key",
+            "ALTER TABLE \"orders\" ADD CONSTRAINT \"orders_email_fkey\" FOREIGN KEY (\"email\") REFERENCES \"users\" (\"email\");",
+            "ALTER TABLE \"orders\" DROP CONSTRAINT IF EXISTS \"orders_email_fkey\";", false,
+        ).with_dependencies(Vec::new(), vec!["table:users".to_string(), "key:users(email)".to_string()]));
+        report.items.push(DiffItem::new(
+            DiffKind::Added, "constraint", "users.users_email_key", "Add unique constraint",
+            "ALTER TABLE \"users\" ADD CONSTRAINT \"users_email_key\" UNIQUE (\"email\");",
+            "ALTER TABLE \"users\" DROP CONSTRAINT IF EXISTS \"users_email_key\";", false,
+        ).with_dependencies(vec!["key:users(email)".to_string()], Vec::new()));
+        report.items.push(DiffItem::new(
+            DiffKind::Removed, "function", "next_code()", "Drop function",
+            "DROP FUNCTION IF EXISTS \"next_code\"();", "CREATE FUNCTION next_code() ...;", false,
+        ).with_dependencies(vec!["function:next_code".to_string()], Vec::new()));
+        report.items.push(DiffItem::new(
+            DiffKind::Removed, "table", "coupons", "Drop table",
+            "DROP TABLE IF EXISTS \"coupons\" CASCADE;", "CREATE TABLE \"coupons\" (...);", true,
+        ).with_dependencies(vec!["table:coupons".to_string()], vec!["function:next_code".to_string()]));
+
+        let up = generate_up_sql(&report, None, false);
+        let position = |sql: &str, text: &str| sql.find(text).unwrap();
+        assert!(position(&up, "UNIQUE (\"email\")") < position(&up, "FOREIGN KEY"));
+        assert_eq!(up.matches("-- Constraint changes").count(), 1);
+        // The table still uses the function, so it is dropped first
+        assert!(position(&up, "-- Drop tables") < position(&up, "-- Drop functions"));
+
+        let down = generate_down_sql(&report, None);
+        assert!(position(&down, "CREATE FUNCTION") < position(&down, "CREATE TABLE"));
+        assert!(position(&down, "DROP CONSTRAINT IF EXISTS \"orders_email_fkey\"")
+            < position(&down, "DROP CONSTRAINT IF EXISTS \"users_email_key\""));
+    }
+}
@@ -0,0 +1,248 @@
+//! Squash a range of migration folders into one.
+//!
+//! When every statement of the range can be replayed into a schema model,
+//! the range is merged: the schema before it is compared with the schema
+//! after it, so objects created and dropped again within the range, or
+//! columns added one migration at a time, collapse into one diff. Ranges
+//! with statements replay does not model, such as functions or data
+//! changes, or whose merged diff would drop data, are concatenated instead,
+//! each script unchanged and in order, since merging them could lose or
+//! reorder their effects. So are ranges with [hooks](crate::apply::hooks),
+//! which are written into the script around their folder's `up.sql`.
+//!
+//! The squashed folder takes the version of the last folder of the range,
+//! so it keeps its place before later migrations, and the original folders
+//! are moved under `.squashed/<new folder>/`.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use serde::{Deserialize, Serialize};
+use crate::apply::hooks::{has_hooks, hook_script, HookPoint};
+use crate::ddl::parse::apply_sql;
+use crate::ddl::replay::migration_folders;
+use crate::diff::diff::compare_schemas;
+use crate::model::schema::SchemaModel;
+use super::sql::{sanitize_name, write_migration_folder, MigrationMeta, RenderOptions};
+
+/// Directory under the migrations directory that squashed folders are moved to.
+pub const SQUASHED_DIR: &str = ".squashed";
+
+/// Outcome of [`squash_migrations`].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SquashResult {
+    /// The new migration folder.
+    pub path: String,
+    /// Names of the folders it replaces, in order.
+    pub squashed: Vec<String>,
+    /// Where those folders were moved.
+    pub archive_path: String,
+    /// True when the range was merged into one diff, false when its scripts
+    /// were concatenated.
+    pub merged: bool,
+    /// Why the range was concatenated rather than merged.
+    pub warnings: Vec<String>,
+}
+
+/// Squash the migration folders from `from` to `to`, inclusive, under
+/// `base_path` into one folder called `name`. `from` and `to` are folder
+/// names or versions.
+pub fn squash_migrations(base_path: &str, from: &str, to: &str, name: &str) -> Result<SquashResult, String> {
+    let folders = migration_folders(base_path)?;
+    let names: Vec<String> = folders.iter().map(|f| folder_name(f)).collect();
+    let position = |query: &str| names.iter()
+        .position(|n| n == query || version(n) == query)
+        .ok_or_else(|| format!("Migration not found: {}", query));
+    let (start, end) = (position(from)?, position(to)?);
+    if start > end {
+        return Err(format!("{} comes after {}", from, to));
+    }
+    if start == end {
+        return Err("Nothing to squash: the range holds one migration".to_string());
+    }
+    let range = &folders[start..=end];
+
+    let name = sanitize_name(name);
+    let version = version(&names[end]).to_string();
+    let new_folder = format!("{}__{}", version, name);
+    let base = Path::new(base_path);
+    let archive_dir = base.join(SQUASHED_DIR).join(&new_folder);
+    if archive_dir.exists() {
+        return Err(format!("Archive folder already exists: {}", archive_dir.display()));
+    }
+
+    // The schema before the range and after it
+    let mut before = SchemaModel::new();
+    for folder in &folders[..start] {
+        apply_sql(&mut before, &read(folder, "up.sql")?);
+    }
+    let mut after = before.clone();
+    let mut warnings = Vec::new();
+    for folder in range {
+        for warning in apply_sql(&mut after, &read(folder, "up.sql")?) {
+            warnings.push(format!("{}: {}", folder_name(folder), warning));
+        }
+    }
+
+    let report = compare_schemas(&after, &before);
+    if warnings.is_empty() {
+        for item in report.items.iter().filter(|i| i.dangerous || i.rewrites_table) {
+            warnings.push(format!("Merging would {}", item.details.to_lowercase()));
+        }
+    }
+    // Merging would drop them; concatenating keeps them in the script
+    for folder in range.iter().filter(|f| has_hooks(f)) {
+        warnings.push(format!("{}: has before / after hooks", folder_name(folder)));
+    }
+    let merged = warnings.is_empty();
+    let squashed: Vec<String> = names[start..=end].to_vec();
+
+    // Everything is read before any folder is moved
+    let concatenated = if merged { None } else { Some(concatenate(range)?) };
+
+    fs::create_dir_all(&archive_dir).map_err(|e| format!("Failed to create {}: {}", archive_dir.display(), e))?;
+    for (folder, folder_name) in range.iter().zip(&squashed) {
+        fs::rename(folder, archive_dir.join(folder_name))
+            .map_err(|e| format!("Failed to archive {}: {}", folder_name, e))?;
+    }
+
+    let migration_dir = base.join(&new_folder);
+    let written = fs::create_dir(&migration_dir).and_then(|_| match concatenated {
+        None => write_migration_folder(&report, &migration_dir, &name, &version, &RenderOptions::default(), squashed.clone()),
+        Some((up_sql, down_sql, mut meta)) => {
+            meta.name = name.clone();
+            meta.timestamp = version.clone();
+            meta.squashes = squashed.clone();
+            fs::write(migration_dir.join("up.sql"), up_sql)?;
+            fs::write(migration_dir.join("down.sql"), down_sql)?;
+            fs::write(migration_dir.join("meta.json"), serde_json::to_string_pretty(&meta).map_err(std::io::Error::other)?)
+        }
+    });
+    written.map_err(|e| format!(
+        "Failed to write {}: {}; the original folders are in {}",
+        new_folder, e, archive_dir.display()
+    ))?;
+
+    Ok(SquashResult {
+        path: migration_dir.to_string_lossy().to_string(),
+        squashed,
+        archive_path: archive_dir.to_string_lossy().to_string(),
+        merged,
+        warnings,
+    })
+}
+
+/// The scripts of `range` in order, each with its hooks around it, the down
+/// scripts in reverse order, and metadata summing up their `meta.json` files.
+fn concatenate(range: &[PathBuf]) -> Result<(String, String, MigrationMeta), String> {
+    let mut up_sql = String::from("-- Migration UP Script (squashed)\n");
+    let mut down_sql = String::from("-- Migration DOWN Script (squashed)\n");
+    let mut meta = MigrationMeta {
+        name: String::new(),
+        timestamp: String::new(),
+        generated_at: chrono::Utc::now().to_rfc3339(),
+        items_count: 0,
+        has_dangerous: false,
+        unacknowledged_dangerous: 0,
+        disk_estimate: None,
+        items: Vec::new(),
+        phase: None,
+        squashes: Vec::new(),
+    };
+
+    for folder in range {
+        let name = folder_name(folder);
+        let mut script = read(folder, "up.sql")?.trim_end().to_string();
+        if let Some(hook) = hook_script(folder, HookPoint::Before)? {
+            script = format!("-- before hook\n{}\n{}", hook, script);
+        }
+        if let Some(hook) = hook_script(folder, HookPoint::After)? {
+            script = format!("{}\n-- after hook\n{}", script, hook);
+        }
+        up_sql.push_str(&format!("\n-- Squashed from {}\n{}\n", name, script));
+        if let Some(folder_meta) = fs::read_to_string(folder.join("meta.json"))
+            .ok()
+            .and_then(|json| serde_json::from_str::<MigrationMeta>(&json).ok())
+        {
+            meta.items_count += folder_meta.items_count;
+            meta.has_dangerous |= folder_meta.has_dangerous;
+            meta.unacknowledged_dangerous += folder_meta.unacknowledged_dangerous;
+            meta.items.extend(folder_meta.items);
+        }
+    }
+    for folder in range.iter().rev() {
+        let name = folder_name(folder);
+        let script = fs::read_to_string(folder.join("down.sql"))
+            .map(|sql| sql.trim_end().to_string())
+            .unwrap_or_else(|_| format!("-- {} has no down.sql", name));
+        down_sql.push_str(&format!("\n-- Squashed from {}\n{}\n", name, script));
+    }
+
+    Ok((up_sql, down_sql, meta))
+}
+
+fn read(folder: &Path, file: &str) -> Result<String, String> {
+    fs::read_to_string(folder.join(file))
+        .map_err(|e| format!("Failed to read {}/{}: {}", folder_name(folder), file, e))
+}
+
+fn folder_name(folder: &Path) -> String {
+    folder.file_name().unwrap_or_default().to_string_lossy().to_string()
+}
+
+/// Version of a `<version>__<name>` folder.
+fn version(folder_name: &str) -> &str {
+    folder_name.split_once("__").map_or(folder_name, |(version, _)| version)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn migrations(folders: &[(&str, &str)]) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("pgshift_squash_{}", uuid::Uuid::new_v4()));
+        for (folder, sql) in folders {
+            fs::create_dir_all(dir.join(folder)).unwrap();
+            fs::write(dir.join(folder).join("up.sql"), sql).unwrap();
+            fs::write(dir.join(folder).join("down.sql"), format!("-- undo {}", folder)).unwrap();
+        }
+        dir
+    }
+
+    #[test]
+    fn test_squash_migrations() {
+        let dir = migrations(&[
+            ("20260101000000__init", "CREATE TABLE users (id integer PRIMARY KEY);"),
+            ("20260102000000__scratch", "BEGIN;\nCREATE TABLE scratch (id integer);\nCOMMIT;"),
+            ("20260103000000__add_email", "ALTER TABLE users ADD COLUMN email text;"),
+            ("20260104000000__drop_scratch", "DROP TABLE scratch;"),
+            ("20260105000000__later", "ALTER TABLE users ADD COLUMN name text;"),
+        ]);
+        let base = dir.to_str().unwrap();
+
+        let result = squash_migrations(base, "20260102000000", "20260104000000__drop_scratch", "Squash").unwrap();
+        assert!(result.merged, "{:?}", result.warnings);
+        assert_eq!(result.squashed.len(), 3);
+        assert!(result.path.ends_with("20260104000000__squash"));
+        let up_sql = fs::read_to_string(Path::new(&result.path).join("up.sql")).unwrap();
+        assert!(up_sql.contains("ALTER TABLE \"users\" ADD COLUMN \"email\" text"));
+        assert!(!up_sql.contains("scratch"));
+        let meta: MigrationMeta = serde_json::from_str(&fs::read_to_string(Path::new(&result.path).join("meta.json")).unwrap()).unwrap();
+        assert_eq!(meta.squashes, result.squashed);
+        assert!(Path::new(&result.archive_path).join("20260102000000__scratch/up.sql").is_file());
+        let remaining: Vec<String> = migration_folders(base).unwrap().iter().map(|f| folder_name(f)).collect();
+        assert_eq!(remaining, ["20260101000000__init", "20260104000000__squash", "20260105000000__later"]);
+
+        // A data change cannot be merged, so the scripts are concatenated
+        fs::create_dir_all(dir.join("20260106000000__backfill")).unwrap();
+        fs::write(dir.join("20260106000000__backfill/up.sql"), "UPDATE users SET name = email;").unwrap();
+        let result = squash_migrations(base, "20260105000000", "20260106000000", "names").unwrap();
+        assert!(!result.merged);
+        let up_sql = fs::read_to_string(Path::new(&result.path).join("up.sql")).unwrap();
+        assert!(up_sql.find("ADD COLUMN name text").unwrap() < up_sql.find("UPDATE users").unwrap());
+        let down_sql = fs::read_to_string(Path::new(&result.path).join("down.sql")).unwrap();
+        assert!(down_sql.contains("-- 20260106000000__backfill has no down.sql"));
+        assert!(down_sql.find("backfill").unwrap() < down_sql.find("-- undo 20260105000000__later").unwrap());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,233 @@
+//! Versions of migration folders.
+//!
+//! A migration folder is named `<version>__<name>`. Versions are allocated
+//! against the folders already in the migrations directory so every new
+//! version sorts after all existing ones, even when two migrations are
+//! generated within the same second or the clock steps back, and two
+//! folders never share a version.
+
+use std::cmp::Ordering;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use chrono::{NaiveDateTime, TimeDelta, Utc};
+use serde::{Deserialize, Serialize};
+
+/// Format of timestamp versions, e.g. `20240101120000`.
+const TIMESTAMP_FORMAT: &str = "%Y%m%d%H%M%S";
+
+/// Crockford base32 alphabet used by ULIDs.
+const ULID_ALPHABET: &[u8; 32] = b"0123456789ABCDEFGHJKMNPQRSTVWXYZ";
+
+/// How versions of new migration folders are written.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum VersionScheme {
+    /// UTC time to the second, e.g. `20240101120000`.
+    #[default]
+    Timestamp,
+    /// A ULID, e.g. `01HQ3K4N5P6R7S8T9V0W1X2Y3Z`: time to the millisecond
+    /// followed by random bits, so folders generated on different machines
+    /// do not collide either.
+    Ulid,
+}
+
+/// Create the folder of a new migration called `name` under `base_path` and
+/// return its path and version.
+///
+/// The folder is created exclusively; if another process allocated the same
+/// version meanwhile, the next one is tried.
+pub fn create_migration_dir(base_path: &str, name: &str, scheme: VersionScheme) -> io::Result<(PathBuf, String)> {
+    create_named_migration_dir(base_path, scheme, |version| format!("{}__{}", version, name))
+}
+
+/// Create the folder `dir_name(version)` of a new migration under
+/// `base_path`, for tools naming folders differently, such as Diesel's
+/// `<version>_<name>`, and return its path and version.
+pub fn create_named_migration_dir(
+    base_path: &str,
+    scheme: VersionScheme,
+    dir_name: impl Fn(&str) -> String,
+) -> io::Result<(PathBuf, String)> {
+    let base = Path::new(base_path);
+    fs::create_dir_all(base)?;
+
+    loop {
+        let existing = folder_versions(base)?;
+        let version = next_version(scheme, existing.iter().map(String::as_str));
+        let dir = base.join(dir_name(&version));
+        match fs::create_dir(&dir) {
+            Ok(()) => {}
+            Err(e) if e.kind() == io::ErrorKind::AlreadyExists => continue,
+            Err(e) => return Err(e),
+        }
+        // Another process may have taken the version under a different name
+        if folder_versions(base)?.iter().filter(|v| **v == version).count() == 1 {
+            return Ok((dir, version));
+        }
+        fs::remove_dir(&dir)?;
+    }
+}
+
+/// Create the file `file_name(version)` of a new migration written as flat
+/// files, such as Flyway's `V<version>__<name>.sql`, under `base_path`,
+/// empty, and return its path and version.
+///
+/// Versions are timestamps allocated like those of folders, against
+/// folders and files alike, so the layouts can share a directory.
+pub fn create_migration_file(base_path: &str, file_name: impl Fn(&str) -> String) -> io::Result<(PathBuf, String)> {
+    let base = Path::new(base_path);
+    fs::create_dir_all(base)?;
+
+    loop {
+        let existing = folder_versions(base)?;
+        let version = next_version(VersionScheme::Timestamp, existing.iter().map(String::as_str));
+        let path = base.join(file_name(&version));
+        match fs::OpenOptions::new().write(true).create_new(true).open(&path) {
+            Ok(_) => {}
+            Err(e) if e.kind() == io::ErrorKind::AlreadyExists => continue,
+            Err(e) => return Err(e),
+        }
+        if folder_versions(base)?.iter().filter(|v| **v == version).count() == 1 {
+            return Ok((path, version));
+        }
+        fs::remove_file(&path)?;
+    }
+}
+
+/// The version after every one of `existing`.
+fn next_version<'a>(scheme: VersionScheme, existing: impl Iterator<Item = &'a str>) -> String {
+    match scheme {
+        VersionScheme::Timestamp => {
+            let latest = existing.filter_map(parse_timestamp).max();
+            let now = Utc::now().naive_utc();
+            let version = match latest {
+                Some(latest) if latest >= now => latest + TimeDelta::seconds(1),
+                _ => now,
+            };
+            version.format(TIMESTAMP_FORMAT).to_string()
+        }
+        VersionScheme::Ulid => {
+            let latest = existing.filter_map(decode_ulid).max();
+            let millis = Utc::now().timestamp_millis().max(0) as u128;
+            let random = u128::from_be_bytes(*uuid::Uuid::new_v4().as_bytes()) & ((1 << 80) - 1);
+            let ulid = (millis << 80) | random;
+            encode_ulid(match latest {
+                // Within the same millisecond, or behind: count up from the latest
+                Some(latest) if latest >> 80 >= millis => latest + 1,
+                _ => ulid,
+            })
+        }
+    }
+}
+
+/// Order of two migration folder names: by the time of their versions, so
+/// timestamp and ULID folders interleave correctly, then by name.
+pub fn compare_folder_names(a: &str, b: &str) -> Ordering {
+    sort_key(a).cmp(&sort_key(b)).then_with(|| a.cmp(b))
+}
+
+/// Milliseconds of the version of a folder name; folders without a version
+/// sort after those with one.
+fn sort_key(folder: &str) -> (i64, &str) {
+    let version = split_version(folder).map_or(folder, |(version, _)| version);
+    let millis = parse_timestamp(version)
+        .map(|t| t.and_utc().timestamp_millis())
+        .or_else(|| decode_ulid(version).map(|ulid| (ulid >> 80) as i64))
+        .unwrap_or(i64::MAX);
+    (millis, version)
+}
+
+/// Versions of the migration folders, Flyway versioned scripts and
+/// golang-migrate up scripts under `base`.
+fn folder_versions(base: &Path) -> io::Result<Vec<String>> {
+    let mut versions = Vec::new();
+    for entry in fs::read_dir(base)? {
+        let entry = entry?;
+        let name = entry.file_name().to_string_lossy().to_string();
+        let version = if entry.file_type()?.is_dir() {
+            split_version(&name).map(|(version, _)| version)
+        } else if name.ends_with(".up.sql") {
+            name.split_once('_').map(|(version, _)| version)
+        } else {
+            name.strip_prefix('V')
+                .filter(|_| name.ends_with(".sql"))
+                .and_then(|rest| rest.split_once("__"))
+                .map(|(version, _)| version)
+        };
+        if let Some(version) = version {
+            versions.push(version.to_string());
+        }
+    }
+    Ok(versions)
+}
+
+/// Version and name of a migration folder: `<version>__<name>`, or
+/// `<version>_<name>` as Diesel names them.
+fn split_version(folder: &str) -> Option<(&str, &str)> {
+    let is_version = |version: &str| parse_timestamp(version).is_some() || decode_ulid(version).is_some();
+    match (folder.split_once("__"), folder.split_once('_')) {
+        (Some(double), _) if is_version(double.0) => Some(double),
+        (_, Some(single)) if is_version(single.0) => Some(single),
+        (double, _) => double,
+    }
+}
+
+fn parse_timestamp(version: &str) -> Option<NaiveDateTime> {
+    if version.len() != 14 {
+        return None;
+    }
+    NaiveDateTime::parse_from_str(version, TIMESTAMP_FORMAT).ok()
+}
+
+fn encode_ulid(value: u128) -> String {
+    (0..26)
+        .rev()
+        .map(|i| ULID_ALPHABET[((value >> (i * 5)) & 0x1f) as usize] as char)
+        .collect()
+}
+
+fn decode_ulid(version: &str) -> Option<u128> {
+    if version.len() != 26 {
+        return None;
+    }
+    version.bytes().try_fold(0u128, |value, c| {
+        let digit = ULID_ALPHABET.iter().position(|a| *a == c.to_ascii_uppercase())?;
+        Some((value << 5) | digit as u128)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_versions_are_monotonic() {
+        let base = std::env::temp_dir().join(format!("pgshift_versions_{}", uuid::Uuid::new_v4()));
+        let base_path = base.to_str().unwrap();
+        // A folder from the future, e.g. written on a machine with a fast clock
+        fs::create_dir_all(base.join("29990101000000__future")).unwrap();
+
+        let (_, first) = create_migration_dir(base_path, "add_users", VersionScheme::Timestamp).unwrap();
+        let (_, second) = create_migration_dir(base_path, "add_users", VersionScheme::Timestamp).unwrap();
+        let (_, ulid) = create_migration_dir(base_path, "add_orders", VersionScheme::Ulid).unwrap();
+        let (_, next_ulid) = create_migration_dir(base_path, "add_orders", VersionScheme::Ulid).unwrap();
+        fs::remove_dir_all(&base).unwrap();
+
+        assert_eq!(first, "29990101000001");
+        assert_eq!(second, "29990101000002");
+        assert_eq!(ulid.len(), 26);
+        assert_eq!(decode_ulid(&ulid).map(encode_ulid), Some(ulid.clone()));
+        assert!(next_ulid > ulid);
+
+        let mut folders = [
+            format!("{}__add_orders", ulid),
+            "20240101120000__b".to_string(),
+            "notes".to_string(),
+            "20240101120000__a".to_string(),
+        ];
+        folders.sort_by(|a, b| compare_folder_names(a, b));
+        assert_eq!(folders[..2], ["20240101120000__a", "20240101120000__b"]);
+        assert_eq!(folders[3], "notes");
+    }
+}
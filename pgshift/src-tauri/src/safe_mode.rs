@@ -0,0 +1,80 @@
+//! Read-only safe mode.
+//!
+//! With safe mode on, pgshift only inspects and compares databases: applying
+//! or cloning migrations is refused, and the query console runs only reads,
+//! inside read-only transactions. The switch is saved as a setting of the
+//! default base path, so it holds for every window and the headless server.
+
+use sqlx::postgres::{PgPool, PgRow};
+use crate::store::MetadataStore;
+
+/// Settings key holding whether safe mode is on.
+pub const SAFE_MODE_SETTING: &str = "safe_mode";
+
+/// Whether safe mode is on for `base_path`; off when never saved.
+pub fn load_safe_mode(base_path: &str) -> Result<bool, String> {
+    Ok(MetadataStore::open(base_path)?
+        .get_setting(SAFE_MODE_SETTING)?
+        .unwrap_or(false))
+}
+
+/// Turn safe mode on or off for `base_path`.
+pub fn save_safe_mode(base_path: &str, enabled: bool) -> Result<(), String> {
+    MetadataStore::open(base_path)?.set_setting(SAFE_MODE_SETTING, &enabled)
+}
+
+/// Refuse `action` (e.g. "Applying migrations") when safe mode is on.
+pub fn ensure_writes_allowed(base_path: &str, action: &str) -> Result<(), String> {
+    if load_safe_mode(base_path)? {
+        return Err(format!("{} is disabled in safe mode", action));
+    }
+    Ok(())
+}
+
+/// Whether the query console treats `query` as a read.
+pub fn is_read_query(query: &str) -> bool {
+    let trimmed = query.trim().to_uppercase();
+    trimmed.starts_with("SELECT") || trimmed.starts_with("WITH")
+}
+
+/// Run the read `query` on `pool`, inside a read-only transaction when
+/// `read_only`, so the server rejects a write hidden in it such as a data
+/// modifying `WITH`.
+pub async fn fetch_read_query(pool: &PgPool, query: &str, read_only: bool) -> Result<Vec<PgRow>, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+    if read_only {
+        sqlx::query("SET TRANSACTION READ ONLY").execute(&mut *tx).await?;
+    }
+    let rows = sqlx::query(query).fetch_all(&mut *tx).await?;
+    tx.commit().await?;
+    Ok(rows)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::store::temp_base_path;
+
+    #[test]
+    fn test_safe_mode_setting() {
+        let base = temp_base_path();
+        assert!(!load_safe_mode(&base).unwrap());
+        assert!(ensure_writes_allowed(&base, "Applying migrations").is_ok());
+
+        save_safe_mode(&base, true).unwrap();
+        assert!(load_safe_mode(&base).unwrap());
+        assert_eq!(
+            ensure_writes_allowed(&base, "Applying migrations").unwrap_err(),
+            "Applying migrations is disabled in safe mode"
+        );
+
+        std::fs::remove_dir_all(&base).unwrap();
+    }
+
+    #[test]
+    fn test_is_read_query() {
+        assert!(is_read_query("  select 1"));
+        assert!(is_read_query("WITH t AS (SELECT 1) SELECT * FROM t"));
+        assert!(!is_read_query("DELETE FROM users"));
+    }
+}
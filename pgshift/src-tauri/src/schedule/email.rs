@@ -0,0 +1,80 @@
+//! SMTP delivery of rendered reports.
+
+use lettre::message::header::ContentType;
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::{AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor};
+use serde::{Deserialize, Serialize};
+
+/// SMTP server settings used to send reports.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SmtpSettings {
+    pub host: String,
+    pub port: u16,
+    pub username: Option<String>,
+    /// Password to store in the OS keychain when the settings are saved:
+    /// `None` keeps the stored one and an empty one removes it. It is taken
+    /// out before the settings are written to the metadata database.
+    #[serde(default)]
+    pub password: Option<String>,
+    /// Whether a password is stored in the keychain.
+    #[serde(default)]
+    pub has_password: bool,
+    /// Use STARTTLS on the connection (plain SMTP otherwise).
+    #[serde(default = "default_starttls")]
+    pub starttls: bool,
+    pub from: String,
+}
+
+fn default_starttls() -> bool {
+    true
+}
+
+/// Send an HTML document to the given recipients.
+pub async fn send_html_email(
+    smtp: &SmtpSettings,
+    to: &[String],
+    subject: &str,
+    html: String,
+) -> Result<(), String> {
+    if to.is_empty() {
+        return Err("No email recipients configured".to_string());
+    }
+
+    let mut builder = Message::builder()
+        .from(smtp.from.parse().map_err(|e| format!("Invalid sender address '{}': {}", smtp.from, e))?)
+        .subject(subject)
+        .header(ContentType::TEXT_HTML);
+
+    for recipient in to {
+        builder = builder.to(recipient
+            .parse()
+            .map_err(|e| format!("Invalid recipient address '{}': {}", recipient, e))?);
+    }
+
+    let message = builder
+        .body(html)
+        .map_err(|e| format!("Failed to build email: {}", e))?;
+
+    let mut transport = if smtp.starttls {
+        AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(&smtp.host)
+            .map_err(|e| format!("Invalid SMTP host '{}': {}", smtp.host, e))?
+    } else {
+        AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(&smtp.host)
+    }
+    .port(smtp.port);
+
+    if let Some(username) = &smtp.username {
+        transport = transport.credentials(Credentials::new(
+            username.clone(),
+            smtp.password.clone().unwrap_or_default(),
+        ));
+    }
+
+    transport
+        .build()
+        .send(message)
+        .await
+        .map_err(|e| format!("Failed to send email: {}", e))?;
+
+    Ok(())
+}
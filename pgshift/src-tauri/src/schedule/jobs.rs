@@ -0,0 +1,411 @@
+//! Recurring schema comparisons and their persistence.
+
+use std::fs;
+use std::path::Path;
+use std::time::Duration;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use crate::commands::default_migrations_dir;
+use crate::db::capabilities::fetch_capabilities;
+use crate::db::connect::connection_label;
+use crate::db::saved_connections::{delete_password, load_password, store_password};
+use crate::db::introspect;
+use crate::db::pool::release_pool;
+use crate::db::schemas::connect_to_schema;
+use crate::db::identity::{fetch_identity, same_database_warning};
+use crate::diff::diff::compare_schemas_with_options;
+use crate::diff::{DiffOptions, IgnoreConfig};
+use crate::render::html::render_report_html;
+use crate::render::sql::sanitize_name;
+use crate::store::MetadataStore;
+use super::email::{send_html_email, SmtpSettings};
+use super::webhooks::{notify, Notification};
+
+/// How often the scheduler checks for due comparisons.
+const SCHEDULER_TICK: Duration = Duration::from_secs(60);
+
+/// Where the HTML report of a scheduled comparison is delivered.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum ReportDelivery {
+    /// Write the report into a directory.
+    Directory { path: String },
+    /// Send the report by email.
+    Email { smtp: SmtpSettings, to: Vec<String> },
+}
+
+/// A comparison between two databases that runs on a fixed interval.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScheduledComparison {
+    pub id: String,
+    pub name: String,
+    /// Label of the source; a connection string with a password goes to the
+    /// OS keychain when the comparison is saved.
+    pub source_connection: String,
+    pub target_connection: String,
+    /// Whether the keychain holds the full source connection string.
+    #[serde(default)]
+    pub source_has_password: bool,
+    #[serde(default)]
+    pub target_has_password: bool,
+    pub interval_minutes: u32,
+    /// RFC 3339 timestamp of the next run; advanced by the interval after each run.
+    pub next_run_at: String,
+    pub enabled: bool,
+    pub delivery: ReportDelivery,
+    #[serde(default)]
+    pub last_run_at: Option<String>,
+    #[serde(default)]
+    pub last_status: Option<String>,
+    pub created_at: String,
+}
+
+impl ScheduledComparison {
+    /// Check whether the comparison should run at `now`.
+    pub fn is_due(&self, now: DateTime<Utc>) -> bool {
+        if !self.enabled {
+            return false;
+        }
+        match DateTime::parse_from_rfc3339(&self.next_run_at) {
+            Ok(next) => next.with_timezone(&Utc) <= now,
+            // An unreadable timestamp is treated as due so it gets rewritten
+            Err(_) => true,
+        }
+    }
+
+    /// Move `next_run_at` past `now`, skipping any runs missed while the app was closed.
+    pub fn advance(&mut self, now: DateTime<Utc>) {
+        let interval = chrono::Duration::minutes(i64::from(self.interval_minutes.max(1)));
+        let mut next = DateTime::parse_from_rfc3339(&self.next_run_at)
+            .map(|d| d.with_timezone(&Utc))
+            .unwrap_or(now);
+        while next <= now {
+            next += interval;
+        }
+        self.next_run_at = next.to_rfc3339();
+    }
+
+    /// Whether a password is still written in the comparison itself, as
+    /// comparisons saved by earlier releases keep them.
+    fn holds_secrets(&self) -> bool {
+        connection_label(&self.source_connection) != self.source_connection
+            || connection_label(&self.target_connection) != self.target_connection
+            || matches!(&self.delivery, ReportDelivery::Email { smtp, .. } if smtp.password.is_some())
+    }
+
+    /// Move the passwords of the comparison into the OS keychain, leaving
+    /// only connection labels to be stored.
+    ///
+    /// A connection given by its label keeps the password of `stored`, the
+    /// comparison as saved before. An SMTP password of `None` keeps the
+    /// stored one and an empty one removes it.
+    pub fn store_secrets(&mut self, stored: Option<&ScheduledComparison>) -> Result<(), String> {
+        let stored_flags = stored.map(|s| {
+            let smtp = match &s.delivery {
+                ReportDelivery::Email { smtp, .. } => smtp.has_password,
+                ReportDelivery::Directory { .. } => false,
+            };
+            (s.source_has_password, s.target_has_password, smtp)
+        });
+        let (source, target, smtp_flag) = stored_flags.unwrap_or_default();
+
+        self.source_has_password = store_connection(&self.id, "source", &mut self.source_connection, source)?;
+        self.target_has_password = store_connection(&self.id, "target", &mut self.target_connection, target)?;
+        match &mut self.delivery {
+            ReportDelivery::Email { smtp, .. } => smtp.has_password = match smtp.password.take().as_deref() {
+                Some("") => {
+                    if smtp_flag {
+                        delete_password(&secret_id(&self.id, "smtp"))?;
+                    }
+                    false
+                }
+                Some(password) => {
+                    store_password(&secret_id(&self.id, "smtp"), password)?;
+                    true
+                }
+                None => smtp_flag,
+            },
+            ReportDelivery::Directory { .. } if smtp_flag => delete_password(&secret_id(&self.id, "smtp"))?,
+            ReportDelivery::Directory { .. } => {}
+        }
+        Ok(())
+    }
+
+    /// The comparison with its connection strings and SMTP password read
+    /// back from the OS keychain, to run it.
+    pub fn with_secrets(&self) -> Result<ScheduledComparison, String> {
+        let mut schedule = self.clone();
+        if self.source_has_password {
+            load_connection(&self.id, "source", &mut schedule.source_connection)?;
+        }
+        if self.target_has_password {
+            load_connection(&self.id, "target", &mut schedule.target_connection)?;
+        }
+        if let ReportDelivery::Email { smtp, .. } = &mut schedule.delivery {
+            if smtp.has_password {
+                smtp.password = load_password(&secret_id(&self.id, "smtp"))?;
+            }
+        }
+        Ok(schedule)
+    }
+
+    /// Remove the passwords of the comparison from the OS keychain.
+    pub fn delete_secrets(&self) -> Result<(), String> {
+        let smtp = matches!(&self.delivery, ReportDelivery::Email { smtp, .. } if smtp.has_password);
+        for (side, stored) in [("source", self.source_has_password), ("target", self.target_has_password), ("smtp", smtp)] {
+            if stored {
+                delete_password(&secret_id(&self.id, side))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Keychain id of the `side` password of the comparison `id`.
+fn secret_id(id: &str, side: &str) -> String {
+    format!("schedule:{}:{}", id, side)
+}
+
+/// Replace `connection` by its label, storing it in the keychain when it
+/// carries a password, and return whether the keychain holds it.
+fn store_connection(id: &str, side: &str, connection: &mut String, stored: bool) -> Result<bool, String> {
+    let label = connection_label(connection);
+    if label == *connection {
+        return Ok(stored);
+    }
+    store_password(&secret_id(id, side), connection)?;
+    *connection = label;
+    Ok(true)
+}
+
+/// Replace the label `connection` by the connection string in the keychain,
+/// unless the connection was changed since it was stored.
+fn load_connection(id: &str, side: &str, connection: &mut String) -> Result<(), String> {
+    if let Some(stored) = load_password(&secret_id(id, side))? {
+        if connection_label(&stored) == *connection {
+            *connection = stored;
+        }
+    }
+    Ok(())
+}
+
+/// Load all scheduled comparisons stored under `base_path`.
+///
+/// Passwords still stored with a comparison are moved to the OS keychain
+/// first.
+pub fn load_schedules(base_path: &str) -> Result<Vec<ScheduledComparison>, String> {
+    let mut store = MetadataStore::open(base_path)?;
+    let schedules = store.list_schedules()?;
+    if !schedules.iter().any(ScheduledComparison::holds_secrets) {
+        return Ok(schedules);
+    }
+    store.update_schedules(|schedules| {
+        for schedule in schedules.iter_mut().filter(|s| s.holds_secrets()) {
+            let stored = schedule.clone();
+            schedule.store_secrets(Some(&stored))?;
+        }
+        Ok(schedules.clone())
+    })
+}
+
+/// Modify the scheduled comparisons stored under `base_path`.
+///
+/// Runs in a single transaction, so updates from the app and the scheduler
+/// never overwrite each other.
+pub fn update_schedules<R>(
+    base_path: &str,
+    f: impl FnOnce(&mut Vec<ScheduledComparison>) -> Result<R, String>,
+) -> Result<R, String> {
+    MetadataStore::open(base_path)?.update_schedules(f)
+}
+
+/// Run one scheduled comparison and deliver its report.
+///
+/// Tables, columns and object types in `ignore` are left out of the report.
+/// Returns a short human-readable status describing what was delivered.
+pub async fn run_comparison(schedule: &ScheduledComparison, ignore: &IgnoreConfig) -> Result<String, String> {
+    let schedule = &schedule.with_secrets()?;
+    let base_path = default_migrations_dir()?;
+    let source_pool = connect_to_schema(&base_path, &schedule.source_connection, "scheduled_comparison", None)
+        .await
+        .map_err(|e| format!("Source: {}", e))?;
+    let source = introspect::introspect_schema(&source_pool)
+        .await
+        .map_err(|e| format!("Source introspection failed: {}", e))?;
+    let source_identity = fetch_identity(&source_pool)
+        .await
+        .map_err(|e| format!("Failed to identify source: {}", e))?;
+    release_pool(source_pool).await;
+
+    let target_pool = connect_to_schema(&base_path, &schedule.target_connection, "scheduled_comparison", None)
+        .await
+        .map_err(|e| format!("Target: {}", e))?;
+    let target = introspect::introspect_schema(&target_pool)
+        .await
+        .map_err(|e| format!("Target introspection failed: {}", e))?;
+    let target_identity = fetch_identity(&target_pool)
+        .await
+        .map_err(|e| format!("Failed to identify target: {}", e))?;
+    let target_capabilities = fetch_capabilities(&target_pool)
+        .await
+        .map_err(|e| format!("Failed to read target server version: {}", e))?;
+    release_pool(target_pool).await;
+    
+    // A schedule pointing at the same database twice would report nothing forever
+    if let Some(warning) = same_database_warning(Some(&source_identity), Some(&target_identity)) {
+        return Err(warning);
+    }
+
+    let options = DiffOptions {
+        ignore: ignore.clone(),
+        target_major_version: Some(target_capabilities.major_version),
+        ..Default::default()
+    };
+    let mut report = compare_schemas_with_options(&source, &target, &options);
+    report.source_connection = connection_label(&schedule.source_connection);
+    report.target_connection = connection_label(&schedule.target_connection);
+    if !report.items.is_empty() {
+        notify(&base_path, &Notification::drift(&schedule.name, &report)).await;
+    }
+
+    let now = Utc::now();
+    let title = format!("{} — {}", schedule.name, now.format("%Y-%m-%d %H:%M UTC"));
+    let html = render_report_html(&report, &title);
+
+    match &schedule.delivery {
+        ReportDelivery::Directory { path } => {
+            fs::create_dir_all(path)
+                .map_err(|e| format!("Failed to create reports directory: {}", e))?;
+            let file = Path::new(path).join(format!(
+                "{}__{}.html",
+                now.format("%Y%m%d%H%M%S"),
+                sanitize_name(&schedule.name)
+            ));
+            fs::write(&file, html)
+                .map_err(|e| format!("Failed to write report: {}", e))?;
+            Ok(format!("{} differences, report written to {}", report.items.len(), file.display()))
+        }
+        ReportDelivery::Email { smtp, to } => {
+            let subject = format!("[PGShift] {}: {} differences", schedule.name, report.items.len());
+            send_html_email(smtp, to, &subject, html).await?;
+            Ok(format!("{} differences, report sent to {}", report.items.len(), to.join(", ")))
+        }
+    }
+}
+
+/// Run every due comparison stored under `base_path` and record the outcome.
+pub async fn run_due_comparisons(base_path: &str) -> Result<(), String> {
+    let now = Utc::now();
+    let due: Vec<ScheduledComparison> = load_schedules(base_path)?
+        .into_iter()
+        .filter(|s| s.is_due(now))
+        .collect();
+    let ignore = IgnoreConfig::load(base_path)?;
+
+    for schedule in due {
+        let status = match run_comparison(&schedule, &ignore).await {
+            Ok(status) => status,
+            Err(e) => format!("FAILED: {}", e),
+        };
+
+        // Update in place so edits made while the comparison was running are kept
+        update_schedules(base_path, |schedules| {
+            if let Some(stored) = schedules.iter_mut().find(|s| s.id == schedule.id) {
+                stored.last_run_at = Some(Utc::now().to_rfc3339());
+                stored.last_status = Some(status.clone());
+                stored.advance(Utc::now());
+            }
+            Ok(())
+        })?;
+    }
+
+    Ok(())
+}
+
+/// Background loop that runs due comparisons for as long as the app is open.
+pub async fn run_scheduler(base_path: String) {
+    let mut ticker = tokio::time::interval(SCHEDULER_TICK);
+    loop {
+        ticker.tick().await;
+        if let Err(e) = run_due_comparisons(&base_path).await {
+            tracing::error!("Scheduled comparison run failed: {}", e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn create_test_schedule(next_run_at: &str) -> ScheduledComparison {
+        ScheduledComparison {
+            id: "1".to_string(),
+            name: "staging vs prod".to_string(),
+            source_connection: String::new(),
+            target_connection: String::new(),
+            source_has_password: false,
+            target_has_password: false,
+            interval_minutes: 1440,
+            next_run_at: next_run_at.to_string(),
+            enabled: true,
+            delivery: ReportDelivery::Directory { path: String::new() },
+            last_run_at: None,
+            last_status: None,
+            created_at: String::new(),
+        }
+    }
+
+    #[test]
+    fn test_is_due() {
+        let now = DateTime::parse_from_rfc3339("2026-02-03T02:00:00Z").unwrap().with_timezone(&Utc);
+
+        assert!(create_test_schedule("2026-02-03T01:59:00Z").is_due(now));
+        assert!(!create_test_schedule("2026-02-03T02:01:00Z").is_due(now));
+
+        let mut disabled = create_test_schedule("2026-02-03T01:59:00Z");
+        disabled.enabled = false;
+        assert!(!disabled.is_due(now));
+    }
+
+    #[test]
+    fn test_advance_skips_missed_runs() {
+        let now = DateTime::parse_from_rfc3339("2026-02-05T03:00:00Z").unwrap().with_timezone(&Utc);
+        let mut schedule = create_test_schedule("2026-02-03T02:00:00Z");
+
+        schedule.advance(now);
+
+        assert_eq!(schedule.next_run_at, "2026-02-06T02:00:00+00:00");
+    }
+
+    #[test]
+    fn test_secrets_kept_out_of_schedules() {
+        let mut schedule = create_test_schedule("2026-02-03T02:00:00Z");
+        schedule.source_connection = "postgres://app@db/app".to_string();
+        schedule.target_connection = "host=db dbname=app".to_string();
+        assert!(!schedule.holds_secrets());
+
+        // A connection given by its label keeps the stored password
+        let stored = ScheduledComparison { source_has_password: true, ..schedule.clone() };
+        schedule.store_secrets(Some(&stored)).unwrap();
+        assert!(schedule.source_has_password && !schedule.target_has_password);
+        assert_eq!(schedule.source_connection, "postgres://app@db/app");
+
+        schedule.target_connection = "host=db password=s3cr3t dbname=app".to_string();
+        assert!(schedule.holds_secrets());
+
+        schedule.target_connection = "host=db dbname=app".to_string();
+        schedule.delivery = ReportDelivery::Email {
+            smtp: SmtpSettings {
+                host: "smtp".to_string(),
+                port: 587,
+                username: Some("bot".to_string()),
+                password: Some("s3cr3t".to_string()),
+                has_password: false,
+                starttls: true,
+                from: "bot@example.com".to_string(),
+            },
+            to: Vec::new(),
+        };
+        assert!(schedule.holds_secrets());
+    }
+}
@@ -0,0 +1,5 @@
+//! Scheduled schema comparisons, report delivery and webhook notifications.
+
+pub mod jobs;
+pub mod email;
+pub mod webhooks;
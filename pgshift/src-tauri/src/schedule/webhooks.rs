@@ -0,0 +1,256 @@
+//! Webhook notifications of applies and drift.
+//!
+//! Each configured webhook receives a POST when a migration is applied or
+//! fails, or when a scheduled comparison finds differences. Generic hooks get
+//! the [`Notification`] as JSON; Slack-compatible ones, such as Slack or
+//! Mattermost incoming webhooks, a `{"text": ...}` message. A failing hook
+//! is logged and never fails the operation that triggered it.
+
+use std::time::Duration;
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use crate::diff::{DiffKind, DiffReport};
+use crate::store::MetadataStore;
+
+/// Setting the webhooks are stored under.
+pub const WEBHOOKS_SETTING: &str = "webhooks";
+
+/// How long one delivery may take.
+const DELIVERY_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Items listed in a Slack message; the full list is in the JSON payload.
+const SLACK_ITEMS: usize = 10;
+
+/// What happened.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WebhookEvent {
+    MigrationApplied,
+    MigrationFailed,
+    DriftDetected,
+}
+
+/// Shape of the request body.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WebhookFormat {
+    /// The [`Notification`] itself.
+    #[default]
+    Json,
+    /// `{"text": ...}`, as Slack and compatible incoming webhooks take it.
+    Slack,
+}
+
+/// A URL notified of events.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Webhook {
+    pub name: String,
+    pub url: String,
+    #[serde(default)]
+    pub format: WebhookFormat,
+    /// Events sent to the hook; empty sends every event.
+    #[serde(default)]
+    pub events: Vec<WebhookEvent>,
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+impl Webhook {
+    fn receives(&self, event: WebhookEvent) -> bool {
+        self.enabled && (self.events.is_empty() || self.events.contains(&event))
+    }
+}
+
+/// Counts and descriptions of the items of a diff report.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct DiffSummary {
+    pub total: usize,
+    pub added: usize,
+    pub removed: usize,
+    pub modified: usize,
+    pub dangerous: usize,
+    /// `details` of each item.
+    pub items: Vec<String>,
+}
+
+impl DiffSummary {
+    pub fn of(report: &DiffReport) -> Self {
+        let count = |kind: DiffKind| report.items.iter().filter(|i| i.kind == kind).count();
+        Self {
+            total: report.items.len(),
+            added: count(DiffKind::Added),
+            removed: count(DiffKind::Removed),
+            modified: count(DiffKind::Modified),
+            dangerous: report.items.iter().filter(|i| i.dangerous).count(),
+            items: report.items.iter().map(|i| i.details.clone()).collect(),
+        }
+    }
+}
+
+/// Body of a generic webhook.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Notification {
+    pub event: WebhookEvent,
+    /// One line saying what happened, e.g. `Migration 20240101__add_users applied to app@db`.
+    pub title: String,
+    /// Label of the database; connection strings never carry their password.
+    pub connection: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub migration: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// Differences found, for drift.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub summary: Option<DiffSummary>,
+    pub occurred_at: String,
+}
+
+impl Notification {
+    /// A migration applied to, or rolled back from, `connection`, or that
+    /// failed with `error`.
+    pub fn migration(connection: &str, migration: &str, rollback: bool, error: Option<&str>) -> Self {
+        let (event, outcome) = match (error, rollback) {
+            (None, false) => (WebhookEvent::MigrationApplied, "applied to"),
+            (None, true) => (WebhookEvent::MigrationApplied, "rolled back on"),
+            (Some(_), false) => (WebhookEvent::MigrationFailed, "failed on"),
+            (Some(_), true) => (WebhookEvent::MigrationFailed, "failed to roll back on"),
+        };
+        Self {
+            event,
+            title: format!("Migration {} {} {}", migration, outcome, connection),
+            connection: connection.to_string(),
+            migration: Some(migration.to_string()),
+            error: error.map(str::to_string),
+            summary: None,
+            occurred_at: Utc::now().to_rfc3339(),
+        }
+    }
+
+    /// Differences the scheduled comparison `name` found between the
+    /// databases of `report`.
+    pub fn drift(name: &str, report: &DiffReport) -> Self {
+        Self {
+            event: WebhookEvent::DriftDetected,
+            title: format!(
+                "{}: {} differences between {} and {}",
+                name,
+                report.items.len(),
+                report.source_connection,
+                report.target_connection
+            ),
+            connection: report.target_connection.clone(),
+            migration: None,
+            error: None,
+            summary: Some(DiffSummary::of(report)),
+            occurred_at: Utc::now().to_rfc3339(),
+        }
+    }
+
+    /// Body sent in `format`.
+    pub fn body(&self, format: WebhookFormat) -> serde_json::Value {
+        match format {
+            WebhookFormat::Json => json!(self),
+            WebhookFormat::Slack => {
+                let mut lines = vec![format!("*[PGShift] {}*", self.title)];
+                if let Some(error) = &self.error {
+                    lines.push(format!("```{}```", error));
+                }
+                if let Some(summary) = &self.summary {
+                    lines.push(format!(
+                        "{} added, {} removed, {} modified, {} dangerous",
+                        summary.added, summary.removed, summary.modified, summary.dangerous
+                    ));
+                    lines.extend(summary.items.iter().take(SLACK_ITEMS).map(|item| format!("• {}", item)));
+                    if summary.items.len() > SLACK_ITEMS {
+                        lines.push(format!("… and {} more", summary.items.len() - SLACK_ITEMS));
+                    }
+                }
+                json!({ "text": lines.join("\n") })
+            }
+        }
+    }
+}
+
+/// Load the webhooks stored under `base_path`.
+pub fn load_webhooks(base_path: &str) -> Result<Vec<Webhook>, String> {
+    Ok(MetadataStore::open(base_path)?
+        .get_setting(WEBHOOKS_SETTING)?
+        .unwrap_or_default())
+}
+
+pub fn save_webhooks(base_path: &str, webhooks: &[Webhook]) -> Result<(), String> {
+    if let Some(hook) = webhooks.iter().find(|h| !h.url.starts_with("https://") && !h.url.starts_with("http://")) {
+        return Err(format!("Webhook '{}' needs an http(s) URL", hook.name));
+    }
+    MetadataStore::open(base_path)?.set_setting(WEBHOOKS_SETTING, &webhooks)
+}
+
+/// POST `notification` to `webhook`.
+pub async fn send_webhook(webhook: &Webhook, notification: &Notification) -> Result<(), String> {
+    let url = webhook.url.clone();
+    let body = notification.body(webhook.format).to_string();
+    tokio::task::spawn_blocking(move || {
+        ureq::post(&url)
+            .timeout(DELIVERY_TIMEOUT)
+            .set("Content-Type", "application/json")
+            .send_string(&body)
+            .map(|_| ())
+            .map_err(|e| format!("Failed to call webhook: {}", e))
+    })
+    .await
+    .map_err(|e| format!("Failed to call webhook: {}", e))?
+}
+
+/// Send `notification` to every webhook stored under `base_path` that
+/// receives its event, logging failures.
+pub async fn notify(base_path: &str, notification: &Notification) {
+    let webhooks = match load_webhooks(base_path) {
+        Ok(webhooks) => webhooks,
+        Err(e) => {
+            tracing::error!("Failed to load webhooks: {}", e);
+            return;
+        }
+    };
+    for webhook in webhooks.iter().filter(|w| w.receives(notification.event)) {
+        if let Err(e) = send_webhook(webhook, notification).await {
+            tracing::warn!("Webhook '{}': {}", webhook.name, e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::diff::DiffItem;
+
+    #[test]
+    fn test_notification_bodies() {
+        let failed = Notification::migration("app@db.internal:5432/app", "20240101120000__add_users", false, Some("relation exists"));
+        assert_eq!(failed.event, WebhookEvent::MigrationFailed);
+        assert_eq!(failed.body(WebhookFormat::Json)["event"], "migration_failed");
+        assert_eq!(
+            failed.body(WebhookFormat::Slack)["text"],
+            "*[PGShift] Migration 20240101120000__add_users failed on app@db.internal:5432/app*\n```relation exists```"
+        );
+
+        let mut report = DiffReport::new();
+        report.target_connection = "prod".to_string();
+        for i in 0..12 {
+            report.items.push(DiffItem::new(DiffKind::Added, "table", &format!("t{}", i), &format!("Create table 't{}'", i), "", "", false));
+        }
+        let drift = Notification::drift("staging vs prod", &report);
+        assert_eq!(drift.summary.as_ref().unwrap().added, 12);
+        let text = drift.body(WebhookFormat::Slack)["text"].as_str().unwrap().to_string();
+        assert!(text.contains("12 added, 0 removed, 0 modified, 0 dangerous\n• Create table 't0'"));
+        assert!(text.ends_with("… and 2 more"));
+
+        let hook = Webhook { name: "ops".to_string(), url: String::new(), format: WebhookFormat::Slack, events: vec![WebhookEvent::DriftDetected], enabled: true };
+        assert!(hook.receives(WebhookEvent::DriftDetected));
+        assert!(!hook.receives(WebhookEvent::MigrationApplied));
+    }
+}
@@ -0,0 +1,110 @@
+//! Local HTTP transport for the JSON-RPC server.
+
+use std::io::Read;
+use tiny_http::{Header, Method, Request, Response, Server};
+use crate::commands::default_migrations_dir;
+use crate::db::pool::{load_pool_settings, PoolManager};
+use crate::db::retry::{load_retry_policy, set_retry_policy};
+use crate::diff::store::ReportStore;
+use super::rpc::handle_body;
+
+/// Default port of the headless server.
+pub const DEFAULT_PORT: u16 = 7331;
+
+/// Largest request body accepted, in bytes.
+const MAX_BODY_BYTES: u64 = 64 * 1024 * 1024;
+
+/// Serve JSON-RPC on `127.0.0.1:<port>/rpc` until the process exits.
+///
+/// Requests must send `Authorization: Bearer <token>`. Only the loopback
+/// interface is bound: the endpoint can run arbitrary SQL on the databases
+/// it is given.
+pub fn run_server(port: u16, token: &str) -> Result<(), String> {
+    let server = Server::http(("127.0.0.1", port))
+        .map_err(|e| format!("Failed to start server on port {}: {}", port, e))?;
+    let runtime = tokio::runtime::Runtime::new()
+        .map_err(|e| format!("Failed to start async runtime: {}", e))?;
+    let store = ReportStore::new();
+    let base_path = default_migrations_dir();
+    let pool_settings = base_path.clone()
+        .and_then(|base_path| load_pool_settings(&base_path))
+        .unwrap_or_default();
+    PoolManager::new(pool_settings).install();
+    if let Ok(policy) = base_path.and_then(|base_path| load_retry_policy(&base_path)) {
+        set_retry_policy(policy);
+    }
+
+    eprintln!("PGShift JSON-RPC server listening on http://127.0.0.1:{}/rpc", port);
+
+    // Requests are handled one at a time; the endpoint serves a single local client
+    for mut request in server.incoming_requests() {
+        let (status, body) = match check_request(&request, token).and_then(|_| read_body(&mut request)) {
+            Ok(body) => (200, runtime.block_on(handle_body(&body, &store))),
+            Err(e) => e,
+        };
+        // The client may have gone away; nothing to do then
+        let _ = request.respond(
+            Response::from_string(body)
+                .with_status_code(status)
+                .with_header(json_header()),
+        );
+    }
+
+    Ok(())
+}
+
+/// Check path, method and token before the body is read.
+fn check_request(request: &Request, token: &str) -> Result<(), (u16, String)> {
+    if request.url() != "/rpc" {
+        return Err((404, error_body("Not found")));
+    }
+    if *request.method() != Method::Post {
+        return Err((405, error_body("Use POST")));
+    }
+    if !is_authorized(request, token) {
+        return Err((401, error_body("Missing or invalid bearer token")));
+    }
+    Ok(())
+}
+
+fn read_body(request: &mut Request) -> Result<String, (u16, String)> {
+    let mut body = String::new();
+    request
+        .as_reader()
+        .take(MAX_BODY_BYTES)
+        .read_to_string(&mut body)
+        .map_err(|e| (400, error_body(&format!("Failed to read body: {}", e))))?;
+    Ok(body)
+}
+
+fn is_authorized(request: &Request, token: &str) -> bool {
+    request.headers().iter().any(|h| {
+        h.field.equiv("Authorization")
+            && h.value.as_str().strip_prefix("Bearer ").is_some_and(|t| constant_time_eq(t, token))
+    })
+}
+
+/// Compare tokens without leaking the matching prefix length through timing.
+fn constant_time_eq(a: &str, b: &str) -> bool {
+    a.len() == b.len() && a.bytes().zip(b.bytes()).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+fn error_body(message: &str) -> String {
+    serde_json::json!({ "error": message }).to_string()
+}
+
+fn json_header() -> Header {
+    Header::from_bytes(&b"Content-Type"[..], &b"application/json"[..]).expect("static header is valid")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_constant_time_eq() {
+        assert!(constant_time_eq("secret", "secret"));
+        assert!(!constant_time_eq("secret", "secreT"));
+        assert!(!constant_time_eq("secret", "secret2"));
+    }
+}
@@ -0,0 +1,8 @@
+//! Headless JSON-RPC server mode.
+//!
+//! Exposes the Tauri command surface over a local HTTP endpoint so editor
+//! plugins and scripts can drive pgshift without the desktop UI. Start it with
+//! `pgshift --server [--port N]`; every request must carry the bearer token.
+
+pub mod http;
+pub mod rpc;
@@ -0,0 +1,461 @@
+//! JSON-RPC 2.0 request handling.
+//!
+//! Method names and parameter names match the frontend's `invoke` calls
+//! (camelCase parameters), so the same payloads work in both places.
+
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use crate::cancel::CancelToken;
+use crate::commands;
+use crate::db::schemas::SchemaFilter;
+use crate::diff::diff as diff_engine;
+use crate::diff::{DangerAcknowledgment, EnumReorderStrategy};
+use crate::diff::store::ReportStore;
+use crate::render::liquibase::ChangelogFormat;
+use crate::render::report::ReportFormat;
+use crate::render::sql::RenderOptions;
+use crate::store::VersionQuery;
+use crate::store::compact::RetentionSettings;
+
+/// JSON-RPC error codes.
+pub const PARSE_ERROR: i64 = -32700;
+pub const INVALID_REQUEST: i64 = -32600;
+pub const METHOD_NOT_FOUND: i64 = -32601;
+pub const INVALID_PARAMS: i64 = -32602;
+/// The command ran and returned an error.
+pub const COMMAND_FAILED: i64 = -32000;
+
+#[derive(Debug, Deserialize)]
+pub struct RpcRequest {
+    pub jsonrpc: String,
+    pub method: String,
+    #[serde(default)]
+    pub params: Option<Value>,
+    #[serde(default)]
+    pub id: Option<Value>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct RpcError {
+    pub code: i64,
+    pub message: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RpcResponse {
+    pub jsonrpc: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub result: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<RpcError>,
+    pub id: Value,
+}
+
+impl RpcResponse {
+    fn new(id: Value, outcome: Result<Value, RpcError>) -> Self {
+        match outcome {
+            Ok(result) => Self { jsonrpc: "2.0", result: Some(result), error: None, id },
+            Err(error) => Self { jsonrpc: "2.0", result: None, error: Some(error), id },
+        }
+    }
+}
+
+/// Handle a raw request body and return the response body.
+pub async fn handle_body(body: &str, store: &ReportStore) -> String {
+    let response = match serde_json::from_str::<RpcRequest>(body) {
+        Ok(request) if request.jsonrpc == "2.0" => {
+            let id = request.id.unwrap_or(Value::Null);
+            RpcResponse::new(id, dispatch(&request.method, request.params.unwrap_or(Value::Null), store).await)
+        }
+        Ok(request) => RpcResponse::new(
+            request.id.unwrap_or(Value::Null),
+            Err(RpcError { code: INVALID_REQUEST, message: "jsonrpc must be \"2.0\"".to_string() }),
+        ),
+        Err(e) => RpcResponse::new(
+            Value::Null,
+            Err(RpcError { code: PARSE_ERROR, message: format!("Invalid request: {}", e) }),
+        ),
+    };
+
+    serde_json::to_string(&response).unwrap_or_default()
+}
+
+/// Deserialize method parameters; a missing params object is treated as empty.
+fn params<T: DeserializeOwned>(value: Value) -> Result<T, RpcError> {
+    let value = if value.is_null() { Value::Object(Default::default()) } else { value };
+    serde_json::from_value(value).map_err(|e| RpcError { code: INVALID_PARAMS, message: e.to_string() })
+}
+
+fn result<T: Serialize>(outcome: Result<T, String>) -> Result<Value, RpcError> {
+    outcome
+        .and_then(|v| serde_json::to_value(v).map_err(|e| format!("Failed to serialize result: {}", e)))
+        .map_err(|message| RpcError { code: COMMAND_FAILED, message })
+}
+
+/// Call a command with named parameters deserialized from the request.
+macro_rules! call {
+    ($value:expr, $command:path $(, $name:ident: $ty:ty)* $(,)?) => {{
+        #[derive(Deserialize)]
+        #[serde(rename_all = "camelCase")]
+        #[allow(dead_code)]
+        struct Params { $($name: $ty),* }
+        #[allow(unused_variables)]
+        let p: Params = params($value)?;
+        result($command($(p.$name),*).await)
+    }};
+}
+
+/// Run a single method.
+pub async fn dispatch(method: &str, value: Value, store: &ReportStore) -> Result<Value, RpcError> {
+    use crate::apply::clone::CloneOptions;
+    use crate::apply::exec::ApplyOptions;
+    use crate::apply::multi::ConnectionRef;
+    use crate::environments::Environment;
+    use crate::db::saved_connections::SavedConnection;
+    use crate::db::connect::{self, TlsOptions};
+    use crate::db::pool::{self as db_pool, PoolManager, PoolSettings};
+    use crate::db::retry::RetryPolicy;
+    use crate::schedule::webhooks::Webhook;
+    use crate::audit::AuditQuery;
+    use crate::db::introspect::IntrospectOptions;
+    use crate::render::dual_write::DualWriteOptions;
+    use crate::model::schema::SchemaModel;
+    use crate::diff::{DiffOptions, DiffReport, RenameMapping};
+    use crate::diff::roles::RoleDiffReport;
+    use crate::diff::store::DiffItemFilter;
+    use crate::schedule::jobs::ScheduledComparison;
+    use crate::source::SchemaSource;
+    use crate::storage::StorageConfig;
+
+    match method {
+        "test_connection" => {
+            #[derive(Deserialize)]
+            #[serde(rename_all = "camelCase")]
+            struct Params { connection_string: String, tls: Option<TlsOptions> }
+            let p: Params = params(value)?;
+            result(commands::test_connection(p.connection_string, p.tls).await.map_err(|e| e.to_string()))
+        }
+        "introspect" => {
+            #[derive(Deserialize)]
+            #[serde(rename_all = "camelCase")]
+            struct Params { connection_string: String, options: Option<IntrospectOptions>, tls: Option<TlsOptions> }
+            let p: Params = params(value)?;
+            let options = p.options.unwrap_or_default();
+            result(async {
+                let connection_string = connect::with_tls(&p.connection_string, p.tls.as_ref())?;
+                commands::run_introspect(&connection_string, &options, |_| {}).await
+            }.await)
+        }
+        "check_same_database" => call!(value, commands::check_same_database,
+            source_connection: String, target_connection: String),
+        "preflight_locales" => call!(value, commands::preflight_locales,
+            source_connection: String, target_connection: String),
+        "diff" => {
+            #[derive(Deserialize)]
+            #[serde(rename_all = "camelCase")]
+            struct Params {
+                source: SchemaModel,
+                target: SchemaModel,
+                options: Option<DiffOptions>,
+                renames: Option<Vec<RenameMapping>>,
+                include_types: Option<Vec<String>>,
+                exclude_types: Option<Vec<String>>,
+            }
+            let p: Params = params(value)?;
+            result(commands::run_diff(p.source, p.target, p.options, p.renames, p.include_types, p.exclude_types))
+        }
+        "compare_diff_reports" => call!(value, commands::compare_diff_reports,
+            previous: DiffReport, current: DiffReport),
+        "compare" => call!(value, commands::run_compare,
+            source: SchemaSource, target: SchemaSource, options: Option<DiffOptions>,
+            allow_same_database: Option<bool>),
+        "compare_roles" => call!(value, commands::compare_roles,
+            source_connection: String, target_connection: String),
+        "render_role_migration" => call!(value, commands::render_role_migration,
+            report: RoleDiffReport, name: String, base_path: String),
+        "render_migration" => call!(value, commands::render_migration,
+            report: DiffReport, name: String, base_path: String, options: Option<RenderOptions>),
+        "render_dual_write_migration" => call!(value, commands::render_dual_write_migration,
+            source: SchemaModel, target: SchemaModel, table: String, column: String,
+            name: String, base_path: String, options: Option<DualWriteOptions>),
+        "estimate_data_loss" => call!(value, commands::estimate_data_loss,
+            connection_string: String, report: DiffReport, exact: Option<bool>),
+        "plan_migration" => call!(value, commands::plan_migration,
+            connection_string: String, migration_path: String),
+        "analyze_lock_impact" => call!(value, commands::analyze_lock_impact,
+            connection_string: String, migration_path: String),
+        // Progress events need the app; over RPC, poll get_operation_progress instead
+        "apply_migration" => {
+            #[derive(Deserialize)]
+            #[serde(rename_all = "camelCase")]
+            struct Params { connection_string: String, migration_path: String, options: Option<ApplyOptions> }
+            let p: Params = params(value)?;
+            result(commands::run_apply_migration(&p.connection_string, &p.migration_path,
+                &p.options.unwrap_or_default(), &CancelToken::new(), |_| {}, |_| {}).await)
+        }
+        "apply_migration_multi" => {
+            #[derive(Deserialize)]
+            #[serde(rename_all = "camelCase")]
+            struct Params {
+                targets: Vec<ConnectionRef>,
+                migration_path: String,
+                options: Option<ApplyOptions>,
+                parallelism: Option<usize>,
+            }
+            let p: Params = params(value)?;
+            result(commands::run_apply_migration_multi(&p.targets, &p.migration_path, &p.options.unwrap_or_default(),
+                p.parallelism.unwrap_or(1), &CancelToken::new(), |_| {}, |_| {}).await)
+        }
+        "get_migration_status" => call!(value, commands::get_migration_status,
+            connection_string: String, base_path: String),
+        "apply_pending" => {
+            #[derive(Deserialize)]
+            #[serde(rename_all = "camelCase")]
+            struct Params { connection_string: String, base_path: String }
+            let p: Params = params(value)?;
+            result(commands::run_apply_pending(&p.connection_string, &p.base_path, &CancelToken::new(), |_| {}, |_| {}).await)
+        }
+        "rollback_migration" => {
+            #[derive(Deserialize)]
+            #[serde(rename_all = "camelCase")]
+            struct Params { connection_string: String, migration_path: String }
+            let p: Params = params(value)?;
+            result(commands::run_rollback_migration(&p.connection_string, &p.migration_path, &CancelToken::new(), |_| {}, |_| {}).await)
+        }
+        "rollback_last" => {
+            #[derive(Deserialize)]
+            #[serde(rename_all = "camelCase")]
+            struct Params { connection_string: String, base_path: String }
+            let p: Params = params(value)?;
+            result(commands::run_rollback_last(&p.connection_string, &p.base_path, &CancelToken::new(), |_| {}, |_| {}).await)
+        }
+        "clone_schema" => {
+            #[derive(Deserialize)]
+            #[serde(rename_all = "camelCase")]
+            struct Params { source_connection: String, target_connection: String, options: Option<CloneOptions> }
+            let p: Params = params(value)?;
+            let options = p.options.unwrap_or_default();
+            result(commands::run_clone_schema(&p.source_connection, &p.target_connection, &options, &CancelToken::new(), |_| {}).await)
+        }
+        "get_operation_progress" => call!(value, commands::get_operation_progress, connection_string: String),
+        "list_apply_history" => call!(value, commands::list_apply_history, base_path: String),
+        "get_audit_log" => call!(value, commands::get_audit_log, query: Option<AuditQuery>),
+        "get_recent_logs" => call!(value, commands::get_recent_logs, level: Option<String>, limit: Option<usize>),
+        "get_migrations_dir" => call!(value, commands::get_migrations_dir),
+        "get_safe_mode" => call!(value, commands::get_safe_mode),
+        "set_safe_mode" => call!(value, commands::set_safe_mode, enabled: bool),
+        "get_schema_filter" => call!(value, commands::get_schema_filter, connection_string: String),
+        "save_schema_filter" => call!(value, commands::save_schema_filter, connection_string: String, filter: SchemaFilter),
+        "list_connections" => call!(value, commands::list_connections),
+        "save_connection" => call!(value, commands::save_connection,
+            connection: SavedConnection, password: Option<String>),
+        "delete_connection" => call!(value, commands::delete_connection, id: String),
+        "get_saved_connection_string" => call!(value, commands::get_saved_connection_string, id: String),
+        "close_connection" => {
+            #[derive(Deserialize)]
+            #[serde(rename_all = "camelCase")]
+            struct Params { connection_string: String }
+            let p: Params = params(value)?;
+            let closed = match PoolManager::installed() {
+                Some(pools) => pools.close_connection(&p.connection_string).await,
+                None => 0,
+            };
+            result(Ok(closed))
+        }
+        "get_pool_settings" => call!(value, commands::get_pool_settings, base_path: String),
+        "save_pool_settings" => {
+            #[derive(Deserialize)]
+            #[serde(rename_all = "camelCase")]
+            struct Params { base_path: String, settings: PoolSettings }
+            let p: Params = params(value)?;
+            result(db_pool::save_pool_settings(&p.base_path, &p.settings).map(|()| {
+                if let Some(pools) = PoolManager::installed() {
+                    pools.configure(p.settings);
+                }
+            }))
+        }
+        "get_retry_policy" => call!(value, commands::get_retry_policy, base_path: String),
+        "save_retry_policy" => call!(value, commands::save_retry_policy, base_path: String, policy: RetryPolicy),
+        "get_webhooks" => call!(value, commands::get_webhooks, base_path: String),
+        "save_webhooks" => call!(value, commands::save_webhooks, base_path: String, webhooks: Vec<Webhook>),
+        "test_webhook" => call!(value, commands::test_webhook, webhook: Webhook),
+        "get_ignore_config" => call!(value, commands::get_ignore_config,
+            base_path: String, environment: Option<String>),
+        "list_environments" => call!(value, commands::list_environments),
+        "save_environment" => call!(value, commands::save_environment,
+            name: String, environment: Environment),
+        "delete_environment" => call!(value, commands::delete_environment, name: String),
+        "export_app_data" => call!(value, commands::export_app_data, path: String),
+        "import_app_data" => call!(value, commands::import_app_data, path: String),
+        "get_database_info" => call!(value, commands::get_database_info, connection_string: String),
+        "get_server_capabilities" => call!(value, commands::get_server_capabilities, connection_string: String),
+        "list_databases" => call!(value, commands::list_databases, connection_string: String),
+        "get_table_data" => call!(value, commands::get_table_data,
+            connection_string: String, table_name: String, page: i32, page_size: i32,
+            order_by: Option<String>, order_dir: Option<String>),
+        "execute_query" => call!(value, commands::execute_query, connection_string: String, query: String),
+        "export_migration" => call!(value, commands::export_migration,
+            report: DiffReport, name: String, export_path: String, options: Option<RenderOptions>),
+        "export_data_dictionary" => call!(value, commands::export_data_dictionary,
+            schema: SchemaModel, title: String, export_path: String),
+        "export_diff_report" => call!(value, commands::export_diff_report,
+            report: DiffReport, format: ReportFormat, path: String),
+        "export_liquibase_changelog" => call!(value, commands::export_liquibase_changelog,
+            report: DiffReport, format: ChangelogFormat, path: String, author: Option<String>),
+        "export_dbml" => call!(value, commands::export_dbml, schema: SchemaModel),
+        "export_mermaid" => call!(value, commands::export_mermaid, schema: SchemaModel, title: Option<String>),
+        "export_version_mermaid" => call!(value, commands::export_version_mermaid, base_path: String, version_id: String),
+        "schema_graph" => call!(value, commands::schema_graph, schema: SchemaModel),
+        "list_migrations" => call!(value, commands::list_migrations, base_path: String),
+        "squash_migrations" => call!(value, commands::squash_migrations,
+            base_path: String, from: String, to: String, name: String),
+        "replay_migrations" => call!(value, commands::replay_migrations, base_path: String),
+        "introspect_sql_file" => call!(value, commands::introspect_sql_file, path: String),
+        "import_prisma_schema" => call!(value, commands::import_prisma_schema, path: String),
+        "import_diesel_schema" => call!(value, commands::import_diesel_schema, path: String),
+        "load_schema_file" => call!(value, commands::load_schema_file, path: String),
+        "export_schema_file" => call!(value, commands::export_schema_file, schema: SchemaModel, path: String),
+        "dump_schema_sql" => call!(value, commands::dump_schema_sql, connection_string: String, path: String),
+        "save_schema_version" => call!(value, commands::save_schema_version,
+            connection_string: String, name: String, description: String, tags: Vec<String>,
+            base_path: String),
+        "list_schema_versions" => call!(value, commands::list_schema_versions, base_path: String),
+        "search_schema_versions" => call!(value, commands::search_schema_versions,
+            base_path: String, query: VersionQuery),
+        "get_schema_version" => call!(value, commands::get_schema_version,
+            base_path: String, version_id: String),
+        "delete_schema_version" => call!(value, commands::delete_schema_version,
+            base_path: String, version_id: String),
+        "compare_schema_versions" => call!(value, commands::compare_schema_versions,
+            base_path: String, from_version_id: String, to_version_id: String),
+        "compare_version_with_live" => call!(value, commands::compare_version_with_live,
+            base_path: String, version_id: String, connection_string: String),
+        "get_storage_config" => call!(value, commands::get_storage_config, base_path: String),
+        "save_storage_config" => call!(value, commands::save_storage_config,
+            base_path: String, config: StorageConfig),
+        "storage_report" => call!(value, commands::storage_report, base_path: String),
+        "get_retention_settings" => call!(value, commands::get_retention_settings, base_path: String),
+        "save_retention_settings" => call!(value, commands::save_retention_settings,
+            base_path: String, retention: RetentionSettings),
+        "compact_storage" => call!(value, commands::compact_storage, base_path: String),
+        "save_scheduled_comparison" => call!(value, commands::save_scheduled_comparison,
+            base_path: String, schedule: ScheduledComparison),
+        "list_scheduled_comparisons" => call!(value, commands::list_scheduled_comparisons, base_path: String),
+        "delete_scheduled_comparison" => call!(value, commands::delete_scheduled_comparison,
+            base_path: String, schedule_id: String),
+        "run_scheduled_comparison_now" => call!(value, commands::run_scheduled_comparison_now,
+            base_path: String, schedule_id: String),
+
+        // Stored reports use the server's own store instead of Tauri state
+        "diff_stored" => {
+            #[derive(Deserialize)]
+            struct Params { source: SchemaModel, target: SchemaModel, options: Option<DiffOptions> }
+            let p: Params = params(value)?;
+            let report = diff_engine::compare_schemas_with_options(&p.source, &p.target, &p.options.unwrap_or_default());
+            result(Ok(store.insert(report)))
+        }
+        "get_diff_items" => {
+            #[derive(Deserialize)]
+            #[serde(rename_all = "camelCase")]
+            struct Params { report_id: String, offset: usize, limit: usize, filters: Option<DiffItemFilter> }
+            let p: Params = params(value)?;
+            result(store
+                .page(&p.report_id, p.offset, p.limit, &p.filters.unwrap_or_default())
+                .ok_or_else(|| format!("Report not found: {}", p.report_id)))
+        }
+        "release_diff_report" => {
+            #[derive(Deserialize)]
+            #[serde(rename_all = "camelCase")]
+            struct Params { report_id: String }
+            let p: Params = params(value)?;
+            result(if store.remove(&p.report_id) {
+                Ok(())
+            } else {
+                Err(format!("Report not found: {}", p.report_id))
+            })
+        }
+        "acknowledge_diff_item" => {
+            #[derive(Deserialize)]
+            #[serde(rename_all = "camelCase")]
+            struct Params { report_id: String, item_id: String, acknowledged_by: String, reason: String }
+            let p: Params = params(value)?;
+            result(DangerAcknowledgment::new(&p.acknowledged_by, &p.reason)
+                .and_then(|ack| store.acknowledge(&p.report_id, &p.item_id, ack)))
+        }
+        "resolve_enum_reorder" => {
+            #[derive(Deserialize)]
+            #[serde(rename_all = "camelCase")]
+            struct Params { report_id: String, item_id: String, strategy: EnumReorderStrategy }
+            let p: Params = params(value)?;
+            result(store.resolve_enum_reorder(&p.report_id, &p.item_id, p.strategy))
+        }
+        "render_stored_migration" => {
+            #[derive(Deserialize)]
+            #[serde(rename_all = "camelCase")]
+            struct Params { report_id: String, name: String, base_path: String, options: Option<RenderOptions> }
+            let p: Params = params(value)?;
+            let options = p.options.unwrap_or_default();
+            result(store
+                .get(&p.report_id)
+                .ok_or_else(|| format!("Report not found: {}", p.report_id))
+                .and_then(|report| commands::run_render(&report, &p.name, &p.base_path, &options)))
+        }
+
+        _ => Err(RpcError { code: METHOD_NOT_FOUND, message: format!("Unknown method: {}", method) }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run(body: &str) -> Value {
+        let store = ReportStore::new();
+        let response = tokio_test::block_on(handle_body(body, &store));
+        serde_json::from_str(&response).unwrap()
+    }
+
+    #[test]
+    fn test_dispatch_diff() {
+        let response = run(
+            r#"{"jsonrpc": "2.0", "id": 7, "method": "diff",
+                "params": {"source": {"tables": [], "indexes": [], "enums": [{"name": "mood", "values": ["ok"]}]},
+                           "target": {"tables": [], "indexes": []}}}"#,
+        );
+
+        assert_eq!(response["id"], 7);
+        assert_eq!(response["result"]["items"][0]["object_type"], "enum");
+    }
+
+    #[test]
+    fn test_dispatch_diff_types() {
+        let diff = |types: &str| run(&format!(
+            r#"{{"jsonrpc": "2.0", "id": 1, "method": "diff",
+                "params": {{"source": {{"tables": [], "indexes": [], "enums": [{{"name": "mood", "values": ["ok"]}}]}},
+                            "target": {{"tables": [], "indexes": []}}, {}}}}}"#,
+            types
+        ));
+
+        assert_eq!(diff(r#""excludeTypes": ["enum"]"#)["result"]["items"], serde_json::json!([]));
+        assert_eq!(diff(r#""excludeTypes": ["enums"]"#)["result"]["items"], serde_json::json!([]));
+        assert_eq!(diff(r#""excludeTypes": ["tables"]"#)["result"]["items"][0]["object_type"], "enum");
+        assert_eq!(diff(r#""includeTypes": ["indexes"]"#)["result"]["items"], serde_json::json!([]));
+        assert_eq!(diff(r#""includeTypes": ["enums"]"#)["result"]["items"][0]["object_type"], "enum");
+
+        let unknown = diff(r#""excludeTypes": ["enmus"]"#);
+        assert_eq!(unknown["error"]["code"], COMMAND_FAILED);
+        assert!(unknown["error"]["message"].as_str().unwrap().starts_with("Unknown object type 'enmus'"));
+        assert_eq!(diff(r#""includeTypes": ["views"]"#)["error"]["code"], COMMAND_FAILED);
+    }
+
+    #[test]
+    fn test_errors() {
+        assert_eq!(run("not json")["error"]["code"], PARSE_ERROR);
+        assert_eq!(run(r#"{"jsonrpc": "2.0", "id": 1, "method": "nope"}"#)["error"]["code"], METHOD_NOT_FOUND);
+        assert_eq!(
+            run(r#"{"jsonrpc": "2.0", "id": 1, "method": "introspect", "params": {}}"#)["error"]["code"],
+            INVALID_PARAMS
+        );
+    }
+}
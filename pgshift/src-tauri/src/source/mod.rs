@@ -0,0 +1,118 @@
+//! Schema sources that can be compared against each other.
+//!
+//! Every side of a comparison is described by a [`SchemaSource`], so any two
+//! representations of a schema (live database, stored version, migration
+//! history, SQL files, schema files, Prisma and Diesel schemas) can be
+//! diffed through the same entry point.
+
+use serde::{Deserialize, Serialize};
+use crate::commands::{default_migrations_dir, find_version};
+use crate::db::capabilities::{fetch_capabilities, ServerCapabilities};
+use crate::db::connect::connection_label;
+use crate::db::introspect;
+use crate::db::pool::release_pool;
+use crate::db::schemas::connect_to_schema;
+use crate::db::identity::{fetch_identity, DatabaseIdentity};
+use crate::db::locale::{fetch_locale, DatabaseLocale};
+use crate::db::sizes::{fetch_relation_sizes, RelationSizes};
+use crate::ddl::files::parse_schema_files;
+use crate::ddl::replay::replay_migrations;
+use crate::model::schema::SchemaModel;
+use crate::model::diesel::load_diesel_schema;
+use crate::model::prisma::load_prisma_schema;
+use crate::model::schema_file::load_schema_file;
+
+/// A point in time or representation of a schema.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum SchemaSource {
+    /// Introspect a live database.
+    Live { connection_string: String },
+    /// A schema version snapshot stored under `base_path`.
+    Version { base_path: String, version_id: String },
+    /// The cumulative state after applying every `up.sql` in a migrations directory.
+    Migrations { path: String },
+    /// A SQL file, or a directory of SQL files, describing the desired schema.
+    SqlFile { path: String },
+    /// A declarative `schema.toml` or `schema.yaml` describing the desired schema.
+    SchemaFile { path: String },
+    /// A `schema.prisma` describing the desired schema.
+    Prisma { path: String },
+    /// A Diesel `schema.rs` describing the desired schema.
+    Diesel { path: String },
+}
+
+impl SchemaSource {
+    /// Short label recorded as the source/target of a diff report.
+    pub fn label(&self) -> String {
+        match self {
+            SchemaSource::Live { connection_string } => connection_label(connection_string),
+            SchemaSource::Version { version_id, .. } => format!("version:{}", version_id),
+            SchemaSource::Migrations { path } => format!("migrations:{}", path),
+            SchemaSource::SqlFile { path } => format!("sql:{}", path),
+            SchemaSource::SchemaFile { path } => format!("schema_file:{}", path),
+            SchemaSource::Prisma { path } => format!("prisma:{}", path),
+            SchemaSource::Diesel { path } => format!("diesel:{}", path),
+        }
+    }
+
+    /// Whether the source is a live database connection.
+    pub fn is_live(&self) -> bool {
+        matches!(self, SchemaSource::Live { .. })
+    }
+
+    /// Load the schema model this source describes.
+    pub async fn load(&self) -> Result<LoadedSchema, String> {
+        match self {
+            SchemaSource::Live { connection_string } => {
+                let pool = connect_to_schema(&default_migrations_dir()?, connection_string, "introspect", None).await?;
+                let identity = fetch_identity(&pool)
+                    .await
+                    .map_err(|e| format!("Failed to identify database: {}", e));
+                let locale = fetch_locale(&pool)
+                    .await
+                    .map_err(|e| format!("Failed to read database locale: {}", e));
+                let schema = introspect::introspect_schema(&pool)
+                    .await
+                    .map_err(|e| format!("Introspection failed: {}", e));
+                // Sizes only feed estimates; a comparison does not fail without them
+                let sizes = fetch_relation_sizes(&pool).await.ok();
+                let capabilities = fetch_capabilities(&pool)
+                    .await
+                    .map_err(|e| format!("Failed to read server version: {}", e));
+                release_pool(pool).await;
+                Ok(LoadedSchema {
+                    schema: schema?,
+                    identity: Some(identity?),
+                    locale: Some(locale?),
+                    sizes,
+                    capabilities: Some(capabilities?),
+                })
+            }
+            SchemaSource::Version { base_path, version_id } => find_version(base_path, version_id)
+                .map(|v| LoadedSchema { schema: v.schema, identity: v.identity, locale: v.locale, sizes: None, capabilities: None }),
+            SchemaSource::Migrations { path } => replay_migrations(path)
+                .map(|p| LoadedSchema { schema: p.schema, identity: None, locale: None, sizes: None, capabilities: None }),
+            SchemaSource::SqlFile { path } => parse_schema_files(path)
+                .map(|p| LoadedSchema { schema: p.schema, identity: None, locale: None, sizes: None, capabilities: None }),
+            SchemaSource::SchemaFile { path } => load_schema_file(path)
+                .map(|schema| LoadedSchema { schema, identity: None, locale: None, sizes: None, capabilities: None }),
+            SchemaSource::Prisma { path } => load_prisma_schema(path)
+                .map(|p| LoadedSchema { schema: p.schema, identity: None, locale: None, sizes: None, capabilities: None }),
+            SchemaSource::Diesel { path } => load_diesel_schema(path)
+                .map(|p| LoadedSchema { schema: p.schema, identity: None, locale: None, sizes: None, capabilities: None }),
+        }
+    }
+}
+
+/// A loaded schema and, when known, the database it came from.
+#[derive(Debug, Clone)]
+pub struct LoadedSchema {
+    pub schema: SchemaModel,
+    pub identity: Option<DatabaseIdentity>,
+    pub locale: Option<DatabaseLocale>,
+    /// Current table and index sizes; only known for live databases.
+    pub sizes: Option<RelationSizes>,
+    /// Version of the server; only known for live databases.
+    pub capabilities: Option<ServerCapabilities>,
+}
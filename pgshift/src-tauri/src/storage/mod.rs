@@ -0,0 +1,143 @@
+//! Shared storage for schema snapshots and apply history.
+//!
+//! By default snapshots and history live in the local metadata database
+//! ([`crate::store`]). A base path can instead point them at an S3-compatible
+//! bucket so a team shares one history instead of keeping it per machine;
+//! there they are stored as named JSON documents in a [`HistoryStore`].
+//!
+//! Writes are conditional on the revision that was read, so concurrent
+//! read-modify-write cycles (two windows, teammates sharing a bucket) are
+//! retried instead of silently overwriting each other.
+
+pub mod s3;
+
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use crate::store::MetadataStore;
+use self::s3::{S3Settings, S3Store};
+
+/// Settings key holding the [`StorageConfig`] of a base path.
+pub const STORAGE_SETTING: &str = "storage";
+
+/// How often an update is retried after losing a race with another writer.
+const MAX_UPDATE_ATTEMPTS: usize = 5;
+
+/// A stored document and the revision it was read at.
+#[derive(Debug, Clone)]
+pub struct Document {
+    pub data: Vec<u8>,
+    pub revision: String,
+}
+
+/// Key/value storage for JSON documents such as `schema_versions.json`.
+pub trait HistoryStore: Send + Sync {
+    /// Read a document; `None` when it does not exist yet.
+    fn read(&self, key: &str) -> Result<Option<Document>, String>;
+
+    /// Replace a document if it is still at revision `expected`, or create it
+    /// when `expected` is `None`.
+    ///
+    /// Returns `false` without writing when another writer got there first.
+    fn write(&self, key: &str, data: &[u8], expected: Option<&str>) -> Result<bool, String>;
+}
+
+/// Which backend stores snapshots and history for a base path.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum StorageConfig {
+    /// The metadata database next to the migrations in the base path.
+    #[default]
+    Local,
+    /// An S3-compatible bucket.
+    S3(S3Settings),
+}
+
+/// Load the storage configuration for `base_path`; local when none is saved.
+///
+/// A secret key still stored with the configuration is moved to the OS
+/// keychain first.
+pub fn load_storage_config(base_path: &str) -> Result<StorageConfig, String> {
+    let config: StorageConfig = MetadataStore::open(base_path)?
+        .get_setting(STORAGE_SETTING)?
+        .unwrap_or_default();
+    match config {
+        StorageConfig::S3(mut settings) if settings.secret_access_key.is_some() => {
+            settings.store_secret()?;
+            let config = StorageConfig::S3(settings);
+            save_storage_config(base_path, &config)?;
+            Ok(config)
+        }
+        config => Ok(config),
+    }
+}
+
+/// Persist the storage configuration for `base_path`, with the S3 secret key
+/// in the OS keychain.
+pub fn save_storage_config(base_path: &str, config: &StorageConfig) -> Result<(), String> {
+    let mut config = config.clone();
+    if let StorageConfig::S3(settings) = &mut config {
+        settings.store_secret()?;
+    }
+    MetadataStore::open(base_path)?.set_setting(STORAGE_SETTING, &config)
+}
+
+/// Open the shared store configured for `base_path`; `None` when snapshots
+/// and history stay in the local metadata database.
+pub fn open_shared_store(base_path: &str) -> Result<Option<Box<dyn HistoryStore>>, String> {
+    Ok(match load_storage_config(base_path)? {
+        StorageConfig::Local => None,
+        StorageConfig::S3(settings) => Some(Box::new(S3Store::new(settings)?)),
+    })
+}
+
+/// JSON helpers available on every store.
+pub trait HistoryStoreExt {
+    /// Read and parse a document; the type's default when it does not exist.
+    fn read_json<T: DeserializeOwned + Default>(&self, key: &str) -> Result<T, String>;
+
+    /// Read a document, let `f` modify it and write it back.
+    ///
+    /// When the document changed in between, `f` runs again on the fresh
+    /// copy, so it must not have side effects outside the document.
+    fn update_json<T, R, F>(&self, key: &str, f: F) -> Result<R, String>
+    where
+        T: DeserializeOwned + Serialize + Default,
+        F: FnMut(&mut T) -> Result<R, String>;
+}
+
+impl<S: HistoryStore + ?Sized> HistoryStoreExt for S {
+    fn read_json<T: DeserializeOwned + Default>(&self, key: &str) -> Result<T, String> {
+        match self.read(key)? {
+            Some(document) => parse(key, &document.data),
+            None => Ok(T::default()),
+        }
+    }
+
+    fn update_json<T, R, F>(&self, key: &str, mut f: F) -> Result<R, String>
+    where
+        T: DeserializeOwned + Serialize + Default,
+        F: FnMut(&mut T) -> Result<R, String>,
+    {
+        for _ in 0..MAX_UPDATE_ATTEMPTS {
+            let current = self.read(key)?;
+            let mut value = match &current {
+                Some(document) => parse(key, &document.data)?,
+                None => T::default(),
+            };
+
+            let result = f(&mut value)?;
+
+            let content = serde_json::to_vec_pretty(&value)
+                .map_err(|e| format!("Failed to serialize {}: {}", key, e))?;
+            if self.write(key, &content, current.as_ref().map(|d| d.revision.as_str()))? {
+                return Ok(result);
+            }
+        }
+
+        Err(format!("{} keeps changing while being updated; try again", key))
+    }
+}
+
+fn parse<T: DeserializeOwned>(key: &str, data: &[u8]) -> Result<T, String> {
+    serde_json::from_slice(data).map_err(|e| format!("Failed to parse {}: {}", key, e))
+}
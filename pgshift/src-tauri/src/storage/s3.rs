@@ -0,0 +1,290 @@
+//! Store documents as objects in an S3-compatible bucket.
+//!
+//! Requests are signed with AWS Signature Version 4, which AWS S3, MinIO,
+//! Cloudflare R2 and most other S3-compatible services accept. The secret
+//! key of a configured access key is kept in the OS keychain; without one,
+//! credentials come from the `AWS_*` variables or an AWS profile, and
+//! temporary ones sign with their session token.
+//!
+//! Object ETags are used as revisions: writes send `If-Match` (or
+//! `If-None-Match: *` for new objects) and a `412 Precondition Failed` is
+//! reported as a conflict. Services that ignore conditional writes fall back
+//! to last-writer-wins.
+
+use std::io::Read;
+use chrono::Utc;
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use crate::db::rds_iam::{load_credentials, AwsCredentials};
+use crate::db::saved_connections::{load_password, store_password};
+use super::{Document, HistoryStore};
+
+/// Connection settings for an S3-compatible bucket.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct S3Settings {
+    /// Service endpoint, e.g. `https://s3.eu-west-1.amazonaws.com` or `http://localhost:9000`.
+    pub endpoint: String,
+    pub bucket: String,
+    pub region: String,
+    /// Key prefix shared by every document, e.g. `pgshift/team-a`.
+    #[serde(default)]
+    pub prefix: String,
+    /// Access key whose secret is kept in the OS keychain; when unset, the
+    /// credentials of the environment or `profile` are used.
+    #[serde(default)]
+    pub access_key_id: Option<String>,
+    /// Secret of `access_key_id`, moved to the OS keychain when the settings
+    /// are saved; never stored with them.
+    #[serde(default)]
+    pub secret_access_key: Option<String>,
+    /// Profile of `~/.aws/credentials`; defaults to the `AWS_*` variables,
+    /// then `AWS_PROFILE` or `default`.
+    #[serde(default)]
+    pub profile: Option<String>,
+    /// Address the bucket as `endpoint/bucket/key` instead of `bucket.endpoint/key`.
+    #[serde(default)]
+    pub path_style: bool,
+}
+
+impl S3Settings {
+    /// Move `secret_access_key` into the OS keychain, under its access key.
+    pub fn store_secret(&mut self) -> Result<(), String> {
+        if let Some(secret) = self.secret_access_key.take().filter(|s| !s.is_empty()) {
+            let access_key_id = self.access_key()
+                .ok_or("An S3 secret key needs its access key id")?;
+            store_password(&secret_id(access_key_id), &secret)?;
+        }
+        Ok(())
+    }
+
+    fn access_key(&self) -> Option<&str> {
+        self.access_key_id.as_deref().filter(|k| !k.is_empty())
+    }
+
+    /// Credentials requests are signed with.
+    fn credentials(&self) -> Result<AwsCredentials, String> {
+        let Some(access_key_id) = self.access_key() else {
+            return load_credentials(self.profile.as_deref());
+        };
+        let secret_access_key = match &self.secret_access_key {
+            Some(secret) => secret.clone(),
+            None => load_password(&secret_id(access_key_id))?
+                .ok_or_else(|| format!("No S3 secret key stored for access key {}", access_key_id))?,
+        };
+        Ok(AwsCredentials { access_key_id: access_key_id.to_string(), secret_access_key, session_token: None })
+    }
+}
+
+/// Keychain id of the secret of `access_key_id`.
+fn secret_id(access_key_id: &str) -> String {
+    format!("s3:{}", access_key_id)
+}
+
+/// Documents are objects under `prefix` in the bucket.
+pub struct S3Store {
+    settings: S3Settings,
+    credentials: AwsCredentials,
+}
+
+impl S3Store {
+    pub fn new(settings: S3Settings) -> Result<Self, String> {
+        let credentials = settings.credentials()
+            .map_err(|e| format!("No S3 credentials: {}", e))?;
+        Ok(Self { settings, credentials })
+    }
+
+    /// Host header and URL for an object.
+    fn locate(&self, key: &str) -> Result<(String, String, String), String> {
+        let endpoint = self.settings.endpoint.trim_end_matches('/');
+        let (scheme, host) = endpoint.split_once("://")
+            .ok_or_else(|| format!("Invalid S3 endpoint: {}", endpoint))?;
+
+        let prefix = self.settings.prefix.trim_matches('/');
+        let object = if prefix.is_empty() { key.to_string() } else { format!("{}/{}", prefix, key) };
+
+        let (host, path) = if self.settings.path_style {
+            (host.to_string(), format!("/{}/{}", self.settings.bucket, object))
+        } else {
+            (format!("{}.{}", self.settings.bucket, host), format!("/{}", object))
+        };
+        let path = uri_encode_path(&path);
+        let url = format!("{}://{}{}", scheme, host, path);
+        Ok((host, path, url))
+    }
+
+    /// Headers (besides Host) that authorize `method` on `path`.
+    fn sign(&self, method: &str, host: &str, path: &str, payload: &[u8]) -> Vec<(&'static str, String)> {
+        let now = Utc::now();
+        let amz_date = now.format("%Y%m%dT%H%M%SZ").to_string();
+        let date = now.format("%Y%m%d").to_string();
+        let payload_hash = hex::encode(Sha256::digest(payload));
+
+        let mut headers = vec![
+            ("x-amz-date", amz_date.clone()),
+            ("x-amz-content-sha256", payload_hash.clone()),
+        ];
+        if let Some(token) = &self.credentials.session_token {
+            headers.push(("x-amz-security-token", token.clone()));
+        }
+        let mut canonical_headers = format!("host:{}\n", host);
+        let mut signed_headers = "host".to_string();
+        let mut sorted: Vec<_> = headers.iter().collect();
+        sorted.sort_by_key(|(name, _)| *name);
+        for (name, value) in sorted {
+            canonical_headers.push_str(&format!("{}:{}\n", name, value));
+            signed_headers.push(';');
+            signed_headers.push_str(name);
+        }
+        let canonical_request = format!(
+            "{}\n{}\n\n{}\n{}\n{}",
+            method, path, canonical_headers, signed_headers, payload_hash
+        );
+
+        let scope = format!("{}/{}/s3/aws4_request", date, self.settings.region);
+        let string_to_sign = format!(
+            "AWS4-HMAC-SHA256\n{}\n{}\n{}",
+            amz_date,
+            scope,
+            hex::encode(Sha256::digest(canonical_request.as_bytes()))
+        );
+
+        let key = signing_key(&self.credentials.secret_access_key, &date, &self.settings.region, "s3");
+        let signature = hex::encode(hmac_sha256(&key, string_to_sign.as_bytes()));
+
+        headers.push(("Authorization", format!(
+            "AWS4-HMAC-SHA256 Credential={}/{}, SignedHeaders={}, Signature={}",
+            self.credentials.access_key_id, scope, signed_headers, signature
+        )));
+        headers
+    }
+}
+
+impl HistoryStore for S3Store {
+    fn read(&self, key: &str) -> Result<Option<Document>, String> {
+        let (host, path, url) = self.locate(key)?;
+        let mut request = ureq::get(&url);
+        for (name, value) in self.sign("GET", &host, &path, b"") {
+            request = request.set(name, &value);
+        }
+
+        match request.call() {
+            Ok(response) => {
+                let revision = response.header("ETag").unwrap_or_default().to_string();
+                let mut data = Vec::new();
+                response.into_reader()
+                    .read_to_end(&mut data)
+                    .map_err(|e| format!("Failed to read {} from S3: {}", key, e))?;
+                Ok(Some(Document { data, revision }))
+            }
+            Err(ureq::Error::Status(404, _)) => Ok(None),
+            Err(e) => Err(format!("Failed to read {} from S3: {}", key, e)),
+        }
+    }
+
+    fn write(&self, key: &str, data: &[u8], expected: Option<&str>) -> Result<bool, String> {
+        let (host, path, url) = self.locate(key)?;
+        let mut request = ureq::put(&url).set("Content-Type", "application/json");
+        request = match expected {
+            Some(etag) => request.set("If-Match", etag),
+            None => request.set("If-None-Match", "*"),
+        };
+        for (name, value) in self.sign("PUT", &host, &path, data) {
+            request = request.set(name, &value);
+        }
+
+        match request.send_bytes(data) {
+            Ok(_) => Ok(true),
+            // 409 is returned when a concurrent conditional write is still in flight
+            Err(ureq::Error::Status(412 | 409, _)) => Ok(false),
+            Err(e) => Err(format!("Failed to write {} to S3: {}", key, e)),
+        }
+    }
+}
+
+pub(crate) fn hmac_sha256(key: &[u8], data: &[u8]) -> Vec<u8> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC accepts any key length");
+    mac.update(data);
+    mac.finalize().into_bytes().to_vec()
+}
+
+/// Derive the SigV4 signing key for a day, region and service.
+pub(crate) fn signing_key(secret: &str, date: &str, region: &str, service: &str) -> Vec<u8> {
+    let k_date = hmac_sha256(format!("AWS4{}", secret).as_bytes(), date.as_bytes());
+    let k_region = hmac_sha256(&k_date, region.as_bytes());
+    let k_service = hmac_sha256(&k_region, service.as_bytes());
+    hmac_sha256(&k_service, b"aws4_request")
+}
+
+/// Percent-encode a path the way SigV4 expects, keeping `/` separators.
+fn uri_encode_path(path: &str) -> String {
+    let mut encoded = String::with_capacity(path.len());
+    for byte in path.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' | b'/' => {
+                encoded.push(byte as char)
+            }
+            _ => encoded.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    encoded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_signing_key() {
+        // Example from the AWS Signature Version 4 documentation
+        let key = signing_key("wJalrXUtnFEMI/K7MDENG+bPxRfiCYEXAMPLEKEY", "20120215", "us-east-1", "iam");
+        assert_eq!(hex::encode(key), "f4780e2d9f65fa895f9c67b32ce1baf0b0d8a43505a000a1a9e090d414db404d");
+    }
+
+    #[test]
+    fn test_locate() {
+        let mut store = S3Store::new(S3Settings {
+            endpoint: "http://localhost:9000/".to_string(),
+            bucket: "schemas".to_string(),
+            region: "us-east-1".to_string(),
+            prefix: "/team a/".to_string(),
+            access_key_id: Some("key".to_string()),
+            secret_access_key: Some("secret".to_string()),
+            profile: None,
+            path_style: true,
+        }).unwrap();
+
+        let (host, path, url) = store.locate("schema_versions.json").unwrap();
+        assert_eq!(host, "localhost:9000");
+        assert_eq!(path, "/schemas/team%20a/schema_versions.json");
+        assert_eq!(url, "http://localhost:9000/schemas/team%20a/schema_versions.json");
+
+        store.settings.path_style = false;
+        let (host, _, url) = store.locate("schema_versions.json").unwrap();
+        assert_eq!(host, "schemas.localhost:9000");
+        assert_eq!(url, "http://schemas.localhost:9000/team%20a/schema_versions.json");
+    }
+
+    #[test]
+    fn test_session_token_is_signed() {
+        let mut store = S3Store::new(S3Settings {
+            endpoint: "https://s3.eu-west-1.amazonaws.com".to_string(),
+            bucket: "schemas".to_string(),
+            region: "eu-west-1".to_string(),
+            prefix: String::new(),
+            access_key_id: Some("ASIAKEY".to_string()),
+            secret_access_key: Some("secret".to_string()),
+            profile: None,
+            path_style: false,
+        }).unwrap();
+        let authorization = |headers: &[(&str, String)]| headers.iter().find(|(n, _)| *n == "Authorization").unwrap().1.clone();
+
+        let headers = store.sign("GET", "schemas.s3.eu-west-1.amazonaws.com", "/schema_versions.json", b"");
+        assert!(authorization(&headers).contains("SignedHeaders=host;x-amz-content-sha256;x-amz-date,"));
+
+        store.credentials.session_token = Some("tok/en".to_string());
+        let headers = store.sign("GET", "schemas.s3.eu-west-1.amazonaws.com", "/schema_versions.json", b"");
+        assert!(headers.contains(&("x-amz-security-token", "tok/en".to_string())));
+        assert!(authorization(&headers).contains("SignedHeaders=host;x-amz-content-sha256;x-amz-date;x-amz-security-token,"));
+    }
+}
@@ -0,0 +1,68 @@
+//! Append-only audit log.
+
+use rusqlite::{params, params_from_iter};
+use crate::audit::{AuditEntry, AuditQuery};
+use super::{from_json, to_json, MetadataStore};
+
+impl MetadataStore {
+    /// Entries matching `query`, newest first.
+    pub fn list_audit_log(&self, query: &AuditQuery) -> Result<Vec<AuditEntry>, String> {
+        let mut conditions = Vec::new();
+        let mut values = Vec::new();
+
+        if let Some(action) = query.action {
+            conditions.push("action = ?");
+            values.push(action.as_str().to_string());
+        }
+        if let Some(connection) = query.connection.as_deref().filter(|c| !c.is_empty()) {
+            conditions.push("connection LIKE ? ESCAPE '\\'");
+            values.push(format!("%{}%", super::versions::escape_like(connection)));
+        }
+        if let Some(success) = query.success {
+            conditions.push("success = ?");
+            values.push(i32::from(success).to_string());
+        }
+        if let Some(since) = &query.since {
+            conditions.push("occurred_at >= ?");
+            values.push(since.clone());
+        }
+        if let Some(until) = &query.until {
+            conditions.push("occurred_at < ?");
+            values.push(until.clone());
+        }
+
+        let mut sql = "SELECT id, document FROM audit_log".to_string();
+        if !conditions.is_empty() {
+            sql.push_str(" WHERE ");
+            sql.push_str(&conditions.join(" AND "));
+        }
+        sql.push_str(" ORDER BY occurred_at DESC, id DESC");
+        if let Some(limit) = query.limit {
+            sql.push_str(&format!(" LIMIT {}", limit));
+        }
+
+        let mut statement = self.conn.prepare(&sql)
+            .map_err(|e| format!("Failed to read audit log: {}", e))?;
+        let rows = statement.query_map(params_from_iter(values), |row| Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?)))
+            .and_then(|rows| rows.collect::<Result<Vec<_>, _>>())
+            .map_err(|e| format!("Failed to read audit log: {}", e))?;
+
+        rows.iter()
+            .map(|(id, document)| {
+                let mut entry: AuditEntry = from_json(document)?;
+                entry.id = *id;
+                Ok(entry)
+            })
+            .collect()
+    }
+
+    /// Append `entry`; its `id` is assigned by the database.
+    pub fn append_audit_entry(&mut self, entry: &AuditEntry) -> Result<(), String> {
+        self.conn.execute(
+            "INSERT INTO audit_log (occurred_at, action, connection, success, document)
+             VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![entry.occurred_at, entry.action.as_str(), entry.connection, entry.success, to_json(entry)?],
+        ).map_err(|e| format!("Failed to write audit log: {}", e))?;
+        Ok(())
+    }
+}
@@ -0,0 +1,327 @@
+//! Portable backups of a base path.
+//!
+//! [`export_app_data`] writes the metadata database (schema versions, apply
+//! history, scheduled comparisons and settings) and the migration folders of
+//! a base path to one JSON archive; [`import_app_data`] merges an archive
+//! into another base path. Passwords and access keys are left out, so an
+//! archive can be handed to a teammate.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+use rusqlite::Connection;
+use serde::{Deserialize, Serialize};
+use crate::apply::history::ApplyRecord;
+use crate::commands::SchemaVersion;
+use crate::db::introspect::strip_conninfo_password;
+use crate::schedule::jobs::{ReportDelivery, ScheduledComparison};
+use crate::schedule::webhooks::WEBHOOKS_SETTING;
+use crate::storage::{StorageConfig, STORAGE_SETTING};
+use super::{commit, from_json, to_json, MetadataStore, VersionQuery};
+
+/// Format of archives written by this release.
+pub const BACKUP_FORMAT_VERSION: u32 = 1;
+
+/// Everything a base path holds, as written to an archive.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AppDataBackup {
+    pub format_version: u32,
+    pub exported_at: String,
+    pub versions: Vec<SchemaVersion>,
+    pub apply_history: Vec<ApplyRecord>,
+    pub schedules: Vec<ScheduledComparison>,
+    pub settings: BTreeMap<String, serde_json::Value>,
+    pub migrations: Vec<MigrationFolder>,
+}
+
+/// A migration folder and the contents of its files.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MigrationFolder {
+    pub name: String,
+    pub files: BTreeMap<String, String>,
+}
+
+/// How many entries of each kind were exported, or imported.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct AppDataSummary {
+    pub versions: usize,
+    pub apply_history: usize,
+    pub schedules: usize,
+    pub settings: usize,
+    pub migrations: usize,
+}
+
+impl AppDataBackup {
+    fn summary(&self) -> AppDataSummary {
+        AppDataSummary {
+            versions: self.versions.len(),
+            apply_history: self.apply_history.len(),
+            schedules: self.schedules.len(),
+            settings: self.settings.len(),
+            migrations: self.migrations.len(),
+        }
+    }
+
+    /// Drop passwords from connection strings and SMTP settings, access
+    /// keys from the storage setting, and the webhooks, whose URLs are
+    /// their secret.
+    fn strip_secrets(&mut self) -> Result<(), String> {
+        for schedule in &mut self.schedules {
+            schedule.source_connection = strip_conninfo_password(&schedule.source_connection);
+            schedule.target_connection = strip_conninfo_password(&schedule.target_connection);
+            // Their passwords stay in the keychain of this machine
+            schedule.source_has_password = false;
+            schedule.target_has_password = false;
+            if let ReportDelivery::Email { smtp, .. } = &mut schedule.delivery {
+                smtp.password = None;
+                smtp.has_password = false;
+            }
+        }
+        if let Some(value) = self.settings.get_mut(STORAGE_SETTING) {
+            let mut config: StorageConfig = serde_json::from_value(value.clone())
+                .map_err(|e| format!("Failed to read storage setting: {}", e))?;
+            if let StorageConfig::S3(settings) = &mut config {
+                settings.access_key_id = None;
+                settings.secret_access_key = None;
+            }
+            *value = serde_json::to_value(&config)
+                .map_err(|e| format!("Failed to serialize storage setting: {}", e))?;
+        }
+        self.settings.remove(WEBHOOKS_SETTING);
+        Ok(())
+    }
+}
+
+impl MetadataStore {
+    /// Read the whole database, without migration folders.
+    ///
+    /// The reads share one transaction, so the copy is consistent even while
+    /// the app or the scheduler keep writing.
+    pub fn export_backup(&self) -> Result<AppDataBackup, String> {
+        let tx = self.conn.unchecked_transaction()
+            .map_err(|e| format!("Failed to start transaction: {}", e))?;
+        let backup = AppDataBackup {
+            format_version: BACKUP_FORMAT_VERSION,
+            exported_at: chrono::Utc::now().to_rfc3339(),
+            versions: self.list_versions(&VersionQuery::default())?,
+            apply_history: self.list_apply_history()?,
+            schedules: self.list_schedules()?,
+            settings: super::settings::load_all(&tx)?,
+            migrations: Vec::new(),
+        };
+        commit(tx)?;
+        Ok(backup)
+    }
+
+    /// Add the entries of `backup` that are not here yet, in one transaction.
+    ///
+    /// Entries are matched by id, settings by key; existing ones are kept as
+    /// they are. Returns how many of each were added.
+    pub fn import_backup(&mut self, backup: &AppDataBackup) -> Result<AppDataSummary, String> {
+        let tx = self.write()?;
+        let mut summary = AppDataSummary::default();
+
+        for version in &backup.versions {
+            if !exists(&tx, "schema_versions", "id", &version.id)? {
+                super::versions::insert(&tx, version)?;
+                summary.versions += 1;
+            }
+        }
+        for record in &backup.apply_history {
+            if !exists(&tx, "apply_history", "id", &record.id)? {
+                super::history::insert(&tx, record)?;
+                summary.apply_history += 1;
+            }
+        }
+
+        let mut schedules = super::schedules::load_all(&tx)?;
+        for schedule in &backup.schedules {
+            if !schedules.iter().any(|s| s.id == schedule.id) {
+                schedules.push(schedule.clone());
+                summary.schedules += 1;
+            }
+        }
+        super::schedules::replace_all(&tx, &schedules)?;
+
+        for (key, value) in &backup.settings {
+            if !exists(&tx, "settings", "key", key)? {
+                super::settings::put(&tx, key, value)?;
+                summary.settings += 1;
+            }
+        }
+
+        commit(tx)?;
+        Ok(summary)
+    }
+}
+
+/// Check whether `table` has a row whose `column` is `value`.
+fn exists(conn: &Connection, table: &str, column: &str, value: &str) -> Result<bool, String> {
+    conn.query_row(
+        &format!("SELECT EXISTS (SELECT 1 FROM {} WHERE {} = ?1)", table, column),
+        [value],
+        |row| row.get(0),
+    ).map_err(|e| format!("Failed to read {}: {}", table, e))
+}
+
+/// Write everything `base_path` holds to the archive at `path`.
+pub fn export_app_data(base_path: &str, path: &str) -> Result<AppDataSummary, String> {
+    let mut backup = MetadataStore::open(base_path)?.export_backup()?;
+    backup.migrations = read_migration_folders(base_path)?;
+    backup.strip_secrets()?;
+
+    fs::write(path, to_json(&backup)?)
+        .map_err(|e| format!("Failed to write {}: {}", path, e))?;
+    Ok(backup.summary())
+}
+
+/// Merge the archive at `path` into `base_path`, keeping whatever is
+/// already there.
+pub fn import_app_data(base_path: &str, path: &str) -> Result<AppDataSummary, String> {
+    let content = fs::read_to_string(path)
+        .map_err(|e| format!("Failed to read {}: {}", path, e))?;
+    let backup: AppDataBackup = from_json(&content)?;
+    if backup.format_version > BACKUP_FORMAT_VERSION {
+        return Err(format!(
+            "Archive format {} is newer than this release supports ({})",
+            backup.format_version, BACKUP_FORMAT_VERSION
+        ));
+    }
+
+    let mut summary = MetadataStore::open(base_path)?.import_backup(&backup)?;
+    summary.migrations = write_migration_folders(base_path, &backup.migrations)?;
+    Ok(summary)
+}
+
+/// Folders of `base_path` holding an `up.sql`, with their files.
+fn read_migration_folders(base_path: &str) -> Result<Vec<MigrationFolder>, String> {
+    let mut folders = Vec::new();
+    let entries = fs::read_dir(base_path)
+        .map_err(|e| format!("Failed to read directory: {}", e))?;
+
+    for entry in entries {
+        let dir = entry.map_err(|e| format!("Failed to read entry: {}", e))?.path();
+        if !dir.join("up.sql").is_file() {
+            continue;
+        }
+
+        let mut files = BTreeMap::new();
+        for file in fs::read_dir(&dir).map_err(|e| format!("Failed to read directory: {}", e))? {
+            let file = file.map_err(|e| format!("Failed to read entry: {}", e))?.path();
+            if let (true, Some(name)) = (file.is_file(), file.file_name().and_then(|n| n.to_str())) {
+                let content = fs::read_to_string(&file)
+                    .map_err(|e| format!("Failed to read {}: {}", file.display(), e))?;
+                files.insert(name.to_string(), content);
+            }
+        }
+
+        let name = dir.file_name().and_then(|n| n.to_str()).unwrap_or_default().to_string();
+        folders.push(MigrationFolder { name, files });
+    }
+
+    folders.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(folders)
+}
+
+/// Create the folders that `base_path` does not have yet; returns how many.
+fn write_migration_folders(base_path: &str, folders: &[MigrationFolder]) -> Result<usize, String> {
+    let mut written = 0;
+    for folder in folders {
+        // Names come from the archive; only plain names stay inside base_path
+        let plain = |name: &str| Path::new(name).file_name().and_then(|n| n.to_str()) == Some(name);
+        if !plain(&folder.name) || !folder.files.keys().all(|f| plain(f)) {
+            return Err(format!("Archive has an invalid migration folder '{}'", folder.name));
+        }
+
+        let dir = Path::new(base_path).join(&folder.name);
+        if dir.exists() {
+            continue;
+        }
+        fs::create_dir_all(&dir)
+            .map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+        for (name, content) in &folder.files {
+            fs::write(dir.join(name), content)
+                .map_err(|e| format!("Failed to write {}: {}", name, e))?;
+        }
+        written += 1;
+    }
+    Ok(written)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::schema::SchemaModel;
+    use crate::schedule::email::SmtpSettings;
+    use crate::store::temp_base_path;
+
+    #[test]
+    fn test_export_and_import() {
+        let source = temp_base_path();
+        let mut store = MetadataStore::open(&source).unwrap();
+        store.insert_version(&SchemaVersion {
+            id: "v1".to_string(),
+            name: "Nightly".to_string(),
+            description: String::new(),
+            connection: "postgres://app:s3cr3t@db/app".to_string(),
+            database_name: "app".to_string(),
+            schema: SchemaModel::new(),
+            created_at: "2026-01-01T00:00:00Z".to_string(),
+            tags: Vec::new(),
+            identity: None,
+            locale: None,
+        }).unwrap();
+        store.update_schedules(|schedules| {
+            schedules.push(ScheduledComparison {
+                id: "s1".to_string(),
+                name: "Drift".to_string(),
+                source_connection: "host=db password=s3cr3t dbname=app".to_string(),
+                target_connection: "postgres://db/app".to_string(),
+                source_has_password: false,
+                target_has_password: false,
+                interval_minutes: 60,
+                next_run_at: "2026-01-01T00:00:00Z".to_string(),
+                enabled: true,
+                delivery: ReportDelivery::Email {
+                    smtp: SmtpSettings {
+                        host: "smtp".to_string(),
+                        port: 587,
+                        username: Some("bot".to_string()),
+                        password: Some("s3cr3t".to_string()),
+                        has_password: false,
+                        starttls: true,
+                        from: "bot@example.com".to_string(),
+                    },
+                    to: Vec::new(),
+                },
+                last_run_at: None,
+                last_status: None,
+                created_at: "2026-01-01T00:00:00Z".to_string(),
+            });
+            Ok(())
+        }).unwrap();
+        store.set_setting("safe_mode", &true).unwrap();
+        let migration = Path::new(&source).join("20260101000000__init");
+        fs::create_dir_all(&migration).unwrap();
+        fs::write(migration.join("up.sql"), "CREATE TABLE t ();").unwrap();
+
+        let archive = format!("{}/backup.json", source);
+        let exported = export_app_data(&source, &archive).unwrap();
+        assert_eq!(exported, AppDataSummary { versions: 1, apply_history: 0, schedules: 1, settings: 1, migrations: 1 });
+        assert!(!fs::read_to_string(&archive).unwrap().contains("s3cr3t"));
+
+        let target = temp_base_path();
+        assert_eq!(import_app_data(&target, &archive).unwrap(), exported);
+        let store = MetadataStore::open(&target).unwrap();
+        assert_eq!(store.get_version("v1").unwrap().unwrap().connection, "postgres://app@db/app");
+        assert_eq!(store.list_schedules().unwrap()[0].source_connection, "host=db dbname=app");
+        assert_eq!(store.get_setting::<bool>("safe_mode").unwrap(), Some(true));
+        assert!(Path::new(&target).join("20260101000000__init/up.sql").is_file());
+
+        // Importing again adds nothing
+        assert_eq!(import_app_data(&target, &archive).unwrap(), AppDataSummary::default());
+
+        fs::remove_dir_all(&source).unwrap();
+        fs::remove_dir_all(&target).unwrap();
+    }
+}
@@ -0,0 +1,305 @@
+//! Size report and compaction of the metadata database.
+//!
+//! Snapshots hold a full schema model each, so a base path that saves one
+//! per deploy grows without bound. Retention settings bound how many
+//! snapshots and apply records are kept; compacting prunes the rest and
+//! vacuums the database so the file shrinks on disk. Snapshots with tags
+//! are never pruned.
+
+use std::fs;
+use std::path::Path;
+use chrono::{DateTime, Duration, Utc};
+use rusqlite::params;
+use serde::{Deserialize, Serialize};
+use super::{commit, MetadataStore, DATABASE_FILE};
+
+/// Settings key holding the [`RetentionSettings`] of a base path.
+pub const RETENTION_SETTING: &str = "retention";
+
+/// How much history compaction keeps; `None` keeps everything.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RetentionSettings {
+    /// Most recent untagged snapshots kept.
+    #[serde(default)]
+    pub max_snapshots: Option<usize>,
+    /// Days an untagged snapshot is kept.
+    #[serde(default)]
+    pub max_snapshot_age_days: Option<u32>,
+    /// Most recent apply records kept.
+    #[serde(default)]
+    pub max_apply_records: Option<usize>,
+    /// Days an apply record is kept.
+    #[serde(default)]
+    pub max_apply_age_days: Option<u32>,
+}
+
+/// Entries of one kind of stored data and the bytes their documents take.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StorageCategory {
+    /// `snapshots`, `apply_history`, `scheduled_comparisons`, `settings` or
+    /// `migrations`.
+    pub name: String,
+    pub entries: u64,
+    pub bytes: u64,
+}
+
+/// What the data of a base path takes on disk.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StorageReport {
+    pub categories: Vec<StorageCategory>,
+    /// The metadata database file and its write-ahead log.
+    pub database_bytes: u64,
+    /// Unused pages of the database that compacting gives back.
+    pub free_bytes: u64,
+}
+
+/// What a compaction removed.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CompactResult {
+    pub snapshots_removed: usize,
+    pub apply_records_removed: usize,
+    pub database_bytes_before: u64,
+    pub database_bytes_after: u64,
+}
+
+/// The retention settings of `base_path`; everything is kept when none are saved.
+pub fn load_retention_settings(base_path: &str) -> Result<RetentionSettings, String> {
+    Ok(MetadataStore::open(base_path)?
+        .get_setting(RETENTION_SETTING)?
+        .unwrap_or_default())
+}
+
+pub fn save_retention_settings(base_path: &str, retention: &RetentionSettings) -> Result<(), String> {
+    MetadataStore::open(base_path)?.set_setting(RETENTION_SETTING, retention)
+}
+
+/// Sizes of the data stored under `base_path`.
+pub fn storage_report(base_path: &str) -> Result<StorageReport, String> {
+    let store = MetadataStore::open(base_path)?;
+    let mut categories = vec![
+        store.table_usage("snapshots", "schema_versions", "length(CAST(document AS BLOB))")?,
+        store.table_usage("apply_history", "apply_history", "length(CAST(document AS BLOB))")?,
+        store.table_usage("scheduled_comparisons", "scheduled_comparisons", "length(CAST(document AS BLOB))")?,
+        store.table_usage("settings", "settings", "length(CAST(value AS BLOB))")?,
+    ];
+    categories.push(migrations_usage(Path::new(base_path))?);
+
+    Ok(StorageReport {
+        categories,
+        database_bytes: database_bytes(base_path),
+        free_bytes: store.free_bytes()?,
+    })
+}
+
+/// Prune `base_path` to `retention`, then vacuum the database.
+pub fn compact_storage(base_path: &str, retention: &RetentionSettings) -> Result<CompactResult, String> {
+    let database_bytes_before = database_bytes(base_path);
+    let mut store = MetadataStore::open(base_path)?;
+    let (snapshots_removed, apply_records_removed) = store.prune(retention, Utc::now())?;
+    store.vacuum()?;
+    drop(store);
+
+    Ok(CompactResult {
+        snapshots_removed,
+        apply_records_removed,
+        database_bytes_before,
+        database_bytes_after: database_bytes(base_path),
+    })
+}
+
+impl MetadataStore {
+    fn table_usage(&self, name: &str, table: &str, size: &str) -> Result<StorageCategory, String> {
+        let (entries, bytes): (i64, i64) = self.conn
+            .query_row(
+                &format!("SELECT count(*), coalesce(sum({}), 0) FROM {}", size, table),
+                [],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .map_err(|e| format!("Failed to measure {}: {}", name, e))?;
+        Ok(StorageCategory { name: name.to_string(), entries: entries as u64, bytes: bytes as u64 })
+    }
+
+    fn free_bytes(&self) -> Result<u64, String> {
+        let pragma = |name: &str| self.conn
+            .pragma_query_value(None, name, |row| row.get::<_, i64>(0))
+            .map_err(|e| format!("Failed to read {}: {}", name, e));
+        Ok((pragma("freelist_count")? * pragma("page_size")?) as u64)
+    }
+
+    /// Delete the snapshots and apply records `retention` does not keep, as
+    /// of `now`; returns how many of each were deleted.
+    fn prune(&mut self, retention: &RetentionSettings, now: DateTime<Utc>) -> Result<(usize, usize), String> {
+        let snapshot_cutoff = retention.max_snapshot_age_days.map(|days| now - Duration::days(days.into()));
+        let apply_cutoff = retention.max_apply_age_days.map(|days| now - Duration::days(days.into()));
+
+        let tx = self.write()?;
+        let snapshots = expired(
+            &tx,
+            "SELECT id, created_at FROM schema_versions v
+             WHERE NOT EXISTS (SELECT 1 FROM schema_version_tags t WHERE t.version_id = v.id)
+             ORDER BY created_at DESC, rowid DESC",
+            retention.max_snapshots,
+            snapshot_cutoff,
+        )?;
+        let records = expired(
+            &tx,
+            "SELECT id, applied_at FROM apply_history ORDER BY applied_at DESC, rowid DESC",
+            retention.max_apply_records,
+            apply_cutoff,
+        )?;
+        for id in &snapshots {
+            tx.execute("DELETE FROM schema_versions WHERE id = ?1", params![id])
+                .map_err(|e| format!("Failed to delete version: {}", e))?;
+        }
+        for id in &records {
+            tx.execute("DELETE FROM apply_history WHERE id = ?1", params![id])
+                .map_err(|e| format!("Failed to delete apply record: {}", e))?;
+        }
+        commit(tx)?;
+
+        Ok((snapshots.len(), records.len()))
+    }
+
+    /// Rebuild the database without its free pages and fold the write-ahead
+    /// log back into it.
+    fn vacuum(&self) -> Result<(), String> {
+        self.conn.execute_batch("VACUUM; PRAGMA wal_checkpoint(TRUNCATE);")
+            .map_err(|e| format!("Failed to vacuum metadata database: {}", e))
+    }
+}
+
+/// Ids of the rows of `query` (id and timestamp, newest first) past the
+/// `max` newest or older than `cutoff`.
+fn expired(
+    conn: &rusqlite::Connection,
+    query: &str,
+    max: Option<usize>,
+    cutoff: Option<DateTime<Utc>>,
+) -> Result<Vec<String>, String> {
+    let mut statement = conn.prepare(query)
+        .map_err(|e| format!("Failed to read history: {}", e))?;
+    let rows = statement.query_map([], |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?)))
+        .and_then(|rows| rows.collect::<Result<Vec<_>, _>>())
+        .map_err(|e| format!("Failed to read history: {}", e))?;
+
+    Ok(rows.into_iter()
+        .enumerate()
+        .filter(|(i, (_, at))| {
+            let too_many = max.is_some_and(|max| *i >= max);
+            // Unparseable timestamps are kept rather than guessed at
+            let too_old = cutoff.is_some_and(|cutoff| {
+                DateTime::parse_from_rfc3339(at).is_ok_and(|at| at < cutoff)
+            });
+            too_many || too_old
+        })
+        .map(|(_, (id, _))| id)
+        .collect())
+}
+
+/// Bytes of the metadata database and its write-ahead log.
+fn database_bytes(base_path: &str) -> u64 {
+    ["", "-wal"].iter()
+        .filter_map(|suffix| fs::metadata(Path::new(base_path).join(format!("{}{}", DATABASE_FILE, suffix))).ok())
+        .map(|m| m.len())
+        .sum()
+}
+
+/// Migration folders under `base` and the bytes of their files.
+fn migrations_usage(base: &Path) -> Result<StorageCategory, String> {
+    let mut usage = StorageCategory { name: "migrations".to_string(), entries: 0, bytes: 0 };
+    let entries = fs::read_dir(base)
+        .map_err(|e| format!("Failed to read directory {}: {}", base.display(), e))?;
+    for entry in entries {
+        let path = entry.map_err(|e| format!("Failed to read entry: {}", e))?.path();
+        if path.is_dir() {
+            usage.entries += 1;
+            usage.bytes += directory_bytes(&path);
+        }
+    }
+    Ok(usage)
+}
+
+fn directory_bytes(dir: &Path) -> u64 {
+    let Ok(entries) = fs::read_dir(dir) else { return 0 };
+    entries.flatten()
+        .map(|entry| {
+            let path = entry.path();
+            if path.is_dir() {
+                directory_bytes(&path)
+            } else {
+                entry.metadata().map(|m| m.len()).unwrap_or(0)
+            }
+        })
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::apply::history::ApplyRecord;
+    use crate::commands::SchemaVersion;
+    use crate::model::schema::SchemaModel;
+    use crate::store::temp_base_path;
+
+    fn version(id: &str, created_at: &str, tags: &[&str]) -> SchemaVersion {
+        SchemaVersion {
+            id: id.to_string(),
+            name: id.to_string(),
+            description: String::new(),
+            connection: "postgres://db/app".to_string(),
+            database_name: "app".to_string(),
+            created_at: created_at.to_string(),
+            schema: SchemaModel::new(),
+            tags: tags.iter().map(|t| t.to_string()).collect(),
+            identity: None,
+            locale: None,
+        }
+    }
+
+    #[test]
+    fn test_compact_storage() {
+        let base = temp_base_path();
+        let mut store = MetadataStore::open(&base).unwrap();
+        store.insert_version(&version("1", "2026-01-01T00:00:00Z", &["release"])).unwrap();
+        store.insert_version(&version("2", "2026-01-02T00:00:00Z", &[])).unwrap();
+        store.insert_version(&version("3", "2026-03-01T00:00:00Z", &[])).unwrap();
+        store.insert_version(&version("4", "2026-03-02T00:00:00Z", &[])).unwrap();
+        for (id, applied_at) in [("a", "2026-01-01T00:00:00Z"), ("b", "2026-03-01T00:00:00Z")] {
+            store.record_apply(&ApplyRecord {
+                id: id.to_string(),
+                migration_name: id.to_string(),
+                target: None,
+                applied_at: applied_at.to_string(),
+                success: true,
+                error: None,
+                session: None,
+                rollback: false,
+                statements: Vec::new(),
+                backup_path: None,
+            }).unwrap();
+        }
+
+        let retention = RetentionSettings {
+            max_snapshots: Some(2),
+            max_apply_age_days: Some(30),
+            ..Default::default()
+        };
+        let now = DateTime::parse_from_rfc3339("2026-03-10T00:00:00Z").unwrap().with_timezone(&Utc);
+        assert_eq!(store.prune(&retention, now).unwrap(), (1, 1));
+        drop(store);
+
+        let store = MetadataStore::open(&base).unwrap();
+        let kept: Vec<String> = store.list_versions(&Default::default()).unwrap().into_iter().map(|v| v.id).collect();
+        assert_eq!(kept, ["1", "3", "4"]);
+        assert_eq!(store.list_apply_history().unwrap().len(), 1);
+        drop(store);
+
+        let report = storage_report(&base).unwrap();
+        let snapshots = report.categories.iter().find(|c| c.name == "snapshots").unwrap();
+        assert_eq!(snapshots.entries, 3);
+        assert!(snapshots.bytes > 0);
+        let compacted = compact_storage(&base, &RetentionSettings::default()).unwrap();
+        assert_eq!((compacted.snapshots_removed, compacted.apply_records_removed), (0, 0));
+        fs::remove_dir_all(&base).unwrap();
+    }
+}
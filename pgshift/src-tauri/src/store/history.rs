@@ -0,0 +1,34 @@
+//! Record of applied migrations.
+
+use rusqlite::{params, Connection};
+use crate::apply::history::ApplyRecord;
+use super::{commit, from_json, to_json, MetadataStore};
+
+impl MetadataStore {
+    /// Every recorded apply, oldest first.
+    pub fn list_apply_history(&self) -> Result<Vec<ApplyRecord>, String> {
+        let mut statement = self.conn
+            .prepare("SELECT document FROM apply_history ORDER BY applied_at, rowid")
+            .map_err(|e| format!("Failed to read apply history: {}", e))?;
+        let documents = statement.query_map([], |row| row.get::<_, String>(0))
+            .and_then(|rows| rows.collect::<Result<Vec<_>, _>>())
+            .map_err(|e| format!("Failed to read apply history: {}", e))?;
+
+        documents.iter().map(|d| from_json(d)).collect()
+    }
+
+    pub fn record_apply(&mut self, record: &ApplyRecord) -> Result<(), String> {
+        let tx = self.write()?;
+        insert(&tx, record)?;
+        commit(tx)
+    }
+}
+
+pub(super) fn insert(conn: &Connection, record: &ApplyRecord) -> Result<(), String> {
+    conn.execute(
+        "INSERT INTO apply_history (id, migration_name, applied_at, success, document)
+         VALUES (?1, ?2, ?3, ?4, ?5)",
+        params![record.id, record.migration_name, record.applied_at, record.success, to_json(record)?],
+    ).map_err(|e| format!("Failed to record apply: {}", e))?;
+    Ok(())
+}
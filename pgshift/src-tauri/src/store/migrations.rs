@@ -0,0 +1,260 @@
+//! Schema of the metadata database and its upgrades.
+//!
+//! `PRAGMA user_version` records how many entries of [`MIGRATIONS`] have been
+//! applied. Released entries must never change; append a new one instead.
+
+use std::fs;
+use std::path::Path;
+use rusqlite::{Connection, Transaction, TransactionBehavior};
+use serde::de::DeserializeOwned;
+use crate::apply::history::ApplyRecord;
+use crate::commands::SchemaVersion;
+use crate::db::connect::connection_label;
+use crate::schedule::jobs::ScheduledComparison;
+use crate::storage::StorageConfig;
+
+/// Upgrades applied in order, each in its own transaction.
+pub const MIGRATIONS: &[&str] = &[
+    // 1: versions, apply history, schedules and settings
+    "CREATE TABLE schema_versions (
+         id TEXT PRIMARY KEY,
+         name TEXT NOT NULL,
+         description TEXT NOT NULL,
+         database_name TEXT NOT NULL,
+         created_at TEXT NOT NULL,
+         document TEXT NOT NULL
+     );
+     CREATE INDEX schema_versions_created_at ON schema_versions (created_at);
+     CREATE INDEX schema_versions_name ON schema_versions (name COLLATE NOCASE);
+     CREATE INDEX schema_versions_database_name ON schema_versions (database_name);
+
+     CREATE TABLE schema_version_tags (
+         version_id TEXT NOT NULL REFERENCES schema_versions (id) ON DELETE CASCADE,
+         tag TEXT NOT NULL,
+         PRIMARY KEY (version_id, tag)
+     );
+     CREATE INDEX schema_version_tags_tag ON schema_version_tags (tag);
+
+     CREATE TABLE apply_history (
+         id TEXT PRIMARY KEY,
+         migration_name TEXT NOT NULL,
+         applied_at TEXT NOT NULL,
+         success INTEGER NOT NULL,
+         document TEXT NOT NULL
+     );
+     CREATE INDEX apply_history_applied_at ON apply_history (applied_at);
+     CREATE INDEX apply_history_migration_name ON apply_history (migration_name);
+
+     CREATE TABLE scheduled_comparisons (
+         id TEXT PRIMARY KEY,
+         position INTEGER NOT NULL,
+         document TEXT NOT NULL
+     );
+
+     CREATE TABLE settings (
+         key TEXT PRIMARY KEY,
+         value TEXT NOT NULL
+     );",
+    // 2: passwords removed from the connections of versions, by
+    // scrub_version_connections; the schema itself is unchanged
+    "",
+    // 3: audit log, append-only
+    "CREATE TABLE audit_log (
+         id INTEGER PRIMARY KEY AUTOINCREMENT,
+         occurred_at TEXT NOT NULL,
+         action TEXT NOT NULL,
+         connection TEXT,
+         success INTEGER NOT NULL,
+         document TEXT NOT NULL
+     );
+     CREATE INDEX audit_log_occurred_at ON audit_log (occurred_at);
+     CREATE INDEX audit_log_action ON audit_log (action);
+
+     CREATE TRIGGER audit_log_no_update BEFORE UPDATE ON audit_log
+     BEGIN SELECT RAISE(ABORT, 'audit log is append-only'); END;
+     CREATE TRIGGER audit_log_no_delete BEFORE DELETE ON audit_log
+     BEGIN SELECT RAISE(ABORT, 'audit log is append-only'); END;",
+];
+
+/// JSON files written by releases before the metadata database.
+const LEGACY_VERSIONS_FILE: &str = "schema_versions.json";
+const LEGACY_HISTORY_FILE: &str = "apply_history.json";
+const LEGACY_SCHEDULES_FILE: &str = "scheduled_comparisons.json";
+const LEGACY_STORAGE_CONFIG_FILE: &str = "storage.json";
+
+/// Bring the database up to the latest schema.
+pub fn migrate(conn: &mut Connection, base: &Path) -> Result<(), String> {
+    loop {
+        let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate)
+            .map_err(|e| format!("Failed to start migration: {}", e))?;
+
+        // Read inside the transaction: another process may have just upgraded
+        let version: usize = tx.pragma_query_value(None, "user_version", |row| row.get(0))
+            .map_err(|e| format!("Failed to read metadata database version: {}", e))?;
+        if version > MIGRATIONS.len() {
+            return Err(format!(
+                "Metadata database is at version {}, newer than this release supports ({})",
+                version,
+                MIGRATIONS.len()
+            ));
+        }
+        if version == MIGRATIONS.len() {
+            return Ok(());
+        }
+
+        tx.execute_batch(MIGRATIONS[version])
+            .map_err(|e| format!("Failed to upgrade metadata database to version {}: {}", version + 1, e))?;
+        if version == 0 {
+            import_legacy_files(&tx, base)?;
+        }
+        if version == 1 {
+            scrub_version_connections(&tx, base)?;
+        }
+        tx.pragma_update(None, "user_version", version + 1)
+            .map_err(|e| format!("Failed to record metadata database version: {}", e))?;
+        tx.commit()
+            .map_err(|e| format!("Failed to upgrade metadata database: {}", e))?;
+    }
+}
+
+/// Copy the documents of the JSON-file layout into a freshly created database.
+///
+/// The files are left in place so an older release still finds them.
+fn import_legacy_files(tx: &Transaction<'_>, base: &Path) -> Result<(), String> {
+    for version in read_legacy::<Vec<SchemaVersion>>(base, LEGACY_VERSIONS_FILE)?.unwrap_or_default() {
+        super::versions::insert(tx, &version)?;
+    }
+    for record in read_legacy::<Vec<ApplyRecord>>(base, LEGACY_HISTORY_FILE)?.unwrap_or_default() {
+        super::history::insert(tx, &record)?;
+    }
+    let schedules = read_legacy::<Vec<ScheduledComparison>>(base, LEGACY_SCHEDULES_FILE)?.unwrap_or_default();
+    super::schedules::replace_all(tx, &schedules)?;
+    if let Some(config) = read_legacy::<StorageConfig>(base, LEGACY_STORAGE_CONFIG_FILE)? {
+        super::settings::put(tx, crate::storage::STORAGE_SETTING, &config)?;
+    }
+    Ok(())
+}
+
+/// Rewrite the versions stored, and those of the legacy file, with the
+/// password dropped from the connection string they used to keep in full.
+///
+/// Deserializing a [`SchemaVersion`] already drops it, so writing each back
+/// is enough. The legacy file keeps its layout for older releases. Versions
+/// in shared storage are rewritten with the next version saved or deleted
+/// there.
+fn scrub_version_connections(tx: &Transaction<'_>, base: &Path) -> Result<(), String> {
+    let documents = {
+        let mut stmt = tx.prepare("SELECT id, document FROM schema_versions")
+            .map_err(|e| format!("Failed to read schema versions: {}", e))?;
+        let rows = stmt.query_map([], |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?)))
+            .map_err(|e| format!("Failed to read schema versions: {}", e))?;
+        rows.collect::<Result<Vec<_>, _>>()
+            .map_err(|e| format!("Failed to read schema versions: {}", e))?
+    };
+    for (id, document) in documents {
+        let version: SchemaVersion = serde_json::from_str(&document)
+            .map_err(|e| format!("Failed to read schema version {}: {}", id, e))?;
+        let document = serde_json::to_string(&version)
+            .map_err(|e| format!("Failed to serialize schema version {}: {}", id, e))?;
+        tx.execute("UPDATE schema_versions SET document = ?1 WHERE id = ?2", (&document, &id))
+            .map_err(|e| format!("Failed to update schema version {}: {}", id, e))?;
+    }
+
+    if let Some(mut versions) = read_legacy::<Vec<serde_json::Value>>(base, LEGACY_VERSIONS_FILE)? {
+        for version in &mut versions {
+            if let Some(serde_json::Value::String(connection)) = version.get_mut("connection_string") {
+                *connection = connection_label(connection);
+            }
+        }
+        let content = serde_json::to_string_pretty(&versions)
+            .map_err(|e| format!("Failed to serialize {}: {}", LEGACY_VERSIONS_FILE, e))?;
+        fs::write(base.join(LEGACY_VERSIONS_FILE), content)
+            .map_err(|e| format!("Failed to write {}: {}", LEGACY_VERSIONS_FILE, e))?;
+    }
+    Ok(())
+}
+
+fn read_legacy<T: DeserializeOwned>(base: &Path, file: &str) -> Result<Option<T>, String> {
+    let path = base.join(file);
+    if !path.exists() {
+        return Ok(None);
+    }
+    let content = fs::read_to_string(&path)
+        .map_err(|e| format!("Failed to read {}: {}", file, e))?;
+    serde_json::from_str(&content)
+        .map(Some)
+        .map_err(|e| format!("Failed to import {}: {}", file, e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::store::{temp_base_path, MetadataStore};
+
+    #[test]
+    fn test_migrate_is_idempotent() {
+        let base = temp_base_path();
+        drop(MetadataStore::open(&base).unwrap());
+        let store = MetadataStore::open(&base).unwrap();
+
+        let version: usize = store.conn.pragma_query_value(None, "user_version", |row| row.get(0)).unwrap();
+        assert_eq!(version, MIGRATIONS.len());
+
+        fs::remove_dir_all(&base).unwrap();
+    }
+
+    #[test]
+    fn test_rejects_newer_database() {
+        let base = temp_base_path();
+        let store = MetadataStore::open(&base).unwrap();
+        store.conn.pragma_update(None, "user_version", MIGRATIONS.len() + 1).unwrap();
+        drop(store);
+
+        assert!(MetadataStore::open(&base).is_err());
+
+        fs::remove_dir_all(&base).unwrap();
+    }
+
+    #[test]
+    fn test_imports_legacy_files() {
+        let base = temp_base_path();
+        fs::create_dir_all(&base).unwrap();
+        fs::write(Path::new(&base).join(LEGACY_HISTORY_FILE), r#"[
+            {"id": "1", "migration_name": "20260101000000__init", "target": null,
+             "applied_at": "2026-01-01T00:00:00Z", "success": true}
+        ]"#).unwrap();
+        fs::write(Path::new(&base).join(LEGACY_STORAGE_CONFIG_FILE), r#"{"type": "local"}"#).unwrap();
+
+        let store = MetadataStore::open(&base).unwrap();
+        let history = store.list_apply_history().unwrap();
+        assert_eq!(history.len(), 1);
+        assert_eq!(history[0].migration_name, "20260101000000__init");
+        assert!(store.get_setting::<StorageConfig>(crate::storage::STORAGE_SETTING).unwrap().is_some());
+
+        fs::remove_dir_all(&base).unwrap();
+    }
+
+    #[test]
+    fn test_scrubs_version_connections() {
+        let base = temp_base_path();
+        fs::create_dir_all(&base).unwrap();
+        let legacy = Path::new(&base).join(LEGACY_VERSIONS_FILE);
+        let versions = serde_json::json!([{
+            "id": "v1", "name": "before", "description": "", "connection_string": "postgres://app:s3cr3t@db/app",
+            "database_name": "app", "schema": crate::model::schema::SchemaModel::new(),
+            "created_at": "2026-01-01T00:00:00Z", "tags": []
+        }]);
+        fs::write(&legacy, versions.to_string()).unwrap();
+
+        let store = MetadataStore::open(&base).unwrap();
+        assert_eq!(store.get_version("v1").unwrap().unwrap().connection, "postgres://app@db/app");
+        let stored: String = store.conn
+            .query_row("SELECT document FROM schema_versions WHERE id = 'v1'", [], |row| row.get(0))
+            .unwrap();
+        assert!(!stored.contains("s3cr3t"), "{}", stored);
+        let content = fs::read_to_string(&legacy).unwrap();
+        assert!(content.contains(r#""connection_string": "postgres://app@db/app""#), "{}", content);
+
+        fs::remove_dir_all(&base).unwrap();
+    }
+}
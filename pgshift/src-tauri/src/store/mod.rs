@@ -0,0 +1,86 @@
+//! Local metadata database.
+//!
+//! Schema versions, apply history, scheduled comparisons, settings and the
+//! audit log of a base path live in one SQLite database, `pgshift.db`, next
+//! to the migrations. Every change runs in a transaction, so the app, the
+//! scheduler and a headless server can share the database without losing
+//! writes.
+//!
+//! The database upgrades itself on open (see [`migrations`]); the JSON files
+//! earlier releases wrote are imported the first time.
+
+pub mod backup;
+pub mod compact;
+pub mod migrations;
+mod audit;
+mod history;
+mod schedules;
+mod settings;
+mod versions;
+
+use std::fs;
+use std::path::Path;
+use std::time::Duration;
+use rusqlite::{Connection, Transaction, TransactionBehavior};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+pub use self::versions::VersionQuery;
+
+pub const DATABASE_FILE: &str = "pgshift.db";
+
+/// How long a write waits for another connection to finish its transaction.
+const BUSY_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Connection to the metadata database of one base path.
+pub struct MetadataStore {
+    conn: Connection,
+}
+
+impl MetadataStore {
+    /// Open (and create or upgrade) the database under `base_path`.
+    pub fn open(base_path: &str) -> Result<Self, String> {
+        let base = Path::new(base_path);
+        if !base.exists() {
+            fs::create_dir_all(base)
+                .map_err(|e| format!("Failed to create directory: {}", e))?;
+        }
+
+        let mut conn = Connection::open(base.join(DATABASE_FILE))
+            .map_err(|e| format!("Failed to open metadata database: {}", e))?;
+        conn.busy_timeout(BUSY_TIMEOUT)
+            .map_err(|e| format!("Failed to configure metadata database: {}", e))?;
+        conn.execute_batch("PRAGMA journal_mode = WAL; PRAGMA foreign_keys = ON;")
+            .map_err(|e| format!("Failed to configure metadata database: {}", e))?;
+
+        migrations::migrate(&mut conn, base)?;
+        Ok(Self { conn })
+    }
+
+    /// Start a write transaction; the write lock is taken up front so
+    /// read-modify-write cycles cannot interleave.
+    fn write(&mut self) -> Result<Transaction<'_>, String> {
+        self.conn.transaction_with_behavior(TransactionBehavior::Immediate)
+            .map_err(|e| format!("Failed to start transaction: {}", e))
+    }
+}
+
+fn commit(tx: Transaction<'_>) -> Result<(), String> {
+    tx.commit().map_err(|e| format!("Failed to commit: {}", e))
+}
+
+fn to_json<T: Serialize>(value: &T) -> Result<String, String> {
+    serde_json::to_string(value).map_err(|e| format!("Failed to serialize: {}", e))
+}
+
+fn from_json<T: DeserializeOwned>(json: &str) -> Result<T, String> {
+    serde_json::from_str(json).map_err(|e| format!("Failed to parse stored document: {}", e))
+}
+
+#[cfg(test)]
+pub(crate) fn temp_base_path() -> String {
+    std::env::temp_dir()
+        .join(format!("pgshift-store-{}", uuid::Uuid::new_v4()))
+        .to_string_lossy()
+        .to_string()
+}
@@ -0,0 +1,51 @@
+//! Scheduled comparisons.
+
+use rusqlite::{params, Connection};
+use crate::schedule::jobs::ScheduledComparison;
+use super::{commit, from_json, to_json, MetadataStore};
+
+impl MetadataStore {
+    /// All scheduled comparisons in the order they were created.
+    pub fn list_schedules(&self) -> Result<Vec<ScheduledComparison>, String> {
+        load_all(&self.conn)
+    }
+
+    /// Let `f` modify the scheduled comparisons and store the result.
+    ///
+    /// Runs in one transaction, so concurrent updates are applied one after
+    /// the other; nothing is stored when `f` fails.
+    pub fn update_schedules<R>(
+        &mut self,
+        f: impl FnOnce(&mut Vec<ScheduledComparison>) -> Result<R, String>,
+    ) -> Result<R, String> {
+        let tx = self.write()?;
+        let mut schedules = load_all(&tx)?;
+        let result = f(&mut schedules)?;
+        replace_all(&tx, &schedules)?;
+        commit(tx)?;
+        Ok(result)
+    }
+}
+
+pub(super) fn load_all(conn: &Connection) -> Result<Vec<ScheduledComparison>, String> {
+    let mut statement = conn
+        .prepare("SELECT document FROM scheduled_comparisons ORDER BY position")
+        .map_err(|e| format!("Failed to read schedules: {}", e))?;
+    let documents = statement.query_map([], |row| row.get::<_, String>(0))
+        .and_then(|rows| rows.collect::<Result<Vec<_>, _>>())
+        .map_err(|e| format!("Failed to read schedules: {}", e))?;
+
+    documents.iter().map(|d| from_json(d)).collect()
+}
+
+pub(super) fn replace_all(conn: &Connection, schedules: &[ScheduledComparison]) -> Result<(), String> {
+    conn.execute("DELETE FROM scheduled_comparisons", [])
+        .map_err(|e| format!("Failed to save schedules: {}", e))?;
+    for (position, schedule) in schedules.iter().enumerate() {
+        conn.execute(
+            "INSERT INTO scheduled_comparisons (id, position, document) VALUES (?1, ?2, ?3)",
+            params![schedule.id, position as i64, to_json(schedule)?],
+        ).map_err(|e| format!("Failed to save schedules: {}", e))?;
+    }
+    Ok(())
+}
@@ -0,0 +1,43 @@
+//! Application settings stored as JSON values by key.
+
+use std::collections::BTreeMap;
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use super::{from_json, to_json, MetadataStore};
+
+impl MetadataStore {
+    /// Read a setting; `None` when it was never saved.
+    pub fn get_setting<T: DeserializeOwned>(&self, key: &str) -> Result<Option<T>, String> {
+        let value: Option<String> = self.conn
+            .query_row("SELECT value FROM settings WHERE key = ?1", [key], |row| row.get(0))
+            .optional()
+            .map_err(|e| format!("Failed to read setting {}: {}", key, e))?;
+        value.map(|v| from_json(&v)).transpose()
+    }
+
+    pub fn set_setting<T: Serialize>(&mut self, key: &str, value: &T) -> Result<(), String> {
+        put(&self.conn, key, value)
+    }
+}
+
+/// Every setting, by key.
+pub(super) fn load_all(conn: &Connection) -> Result<BTreeMap<String, serde_json::Value>, String> {
+    let mut statement = conn
+        .prepare("SELECT key, value FROM settings ORDER BY key")
+        .map_err(|e| format!("Failed to read settings: {}", e))?;
+    let rows = statement.query_map([], |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?)))
+        .and_then(|rows| rows.collect::<Result<Vec<_>, _>>())
+        .map_err(|e| format!("Failed to read settings: {}", e))?;
+
+    rows.iter().map(|(key, value)| Ok((key.clone(), from_json(value)?))).collect()
+}
+
+pub(super) fn put<T: Serialize>(conn: &Connection, key: &str, value: &T) -> Result<(), String> {
+    conn.execute(
+        "INSERT INTO settings (key, value) VALUES (?1, ?2)
+         ON CONFLICT (key) DO UPDATE SET value = excluded.value",
+        params![key, to_json(value)?],
+    ).map_err(|e| format!("Failed to save setting {}: {}", key, e))?;
+    Ok(())
+}
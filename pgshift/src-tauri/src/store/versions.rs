@@ -0,0 +1,188 @@
+//! Schema version snapshots.
+
+use rusqlite::{params, params_from_iter, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use crate::commands::SchemaVersion;
+use super::{commit, from_json, to_json, MetadataStore};
+
+/// Filters for listing schema versions; empty fields match everything.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct VersionQuery {
+    /// Case-insensitive substring of the name or description.
+    #[serde(default)]
+    pub text: Option<String>,
+    #[serde(default)]
+    pub database_name: Option<String>,
+    #[serde(default)]
+    pub tag: Option<String>,
+}
+
+impl VersionQuery {
+    /// Check a version against the query in memory, the same way
+    /// [`MetadataStore::list_versions`] filters in SQL.
+    pub fn matches(&self, version: &SchemaVersion) -> bool {
+        let text_matches = match self.text.as_deref().filter(|t| !t.is_empty()) {
+            Some(text) => {
+                let text = text.to_lowercase();
+                version.name.to_lowercase().contains(&text)
+                    || version.description.to_lowercase().contains(&text)
+            }
+            None => true,
+        };
+        text_matches
+            && self.database_name.as_ref().is_none_or(|d| &version.database_name == d)
+            && self.tag.as_ref().is_none_or(|t| version.tags.contains(t))
+    }
+}
+
+impl MetadataStore {
+    /// Versions matching `query`, oldest first.
+    pub fn list_versions(&self, query: &VersionQuery) -> Result<Vec<SchemaVersion>, String> {
+        let mut conditions = Vec::new();
+        let mut values = Vec::new();
+
+        if let Some(text) = query.text.as_deref().filter(|t| !t.is_empty()) {
+            conditions.push("(v.name LIKE ? ESCAPE '\\' OR v.description LIKE ? ESCAPE '\\')");
+            let pattern = format!("%{}%", escape_like(text));
+            values.push(pattern.clone());
+            values.push(pattern);
+        }
+        if let Some(database_name) = &query.database_name {
+            conditions.push("v.database_name = ?");
+            values.push(database_name.clone());
+        }
+        if let Some(tag) = &query.tag {
+            conditions.push("EXISTS (SELECT 1 FROM schema_version_tags t WHERE t.version_id = v.id AND t.tag = ?)");
+            values.push(tag.clone());
+        }
+
+        let mut sql = "SELECT v.document FROM schema_versions v".to_string();
+        if !conditions.is_empty() {
+            sql.push_str(" WHERE ");
+            sql.push_str(&conditions.join(" AND "));
+        }
+        sql.push_str(" ORDER BY v.created_at, v.rowid");
+
+        let mut statement = self.conn.prepare(&sql)
+            .map_err(|e| format!("Failed to list versions: {}", e))?;
+        let documents = statement.query_map(params_from_iter(values), |row| row.get::<_, String>(0))
+            .and_then(|rows| rows.collect::<Result<Vec<_>, _>>())
+            .map_err(|e| format!("Failed to list versions: {}", e))?;
+
+        documents.iter().map(|d| from_json(d)).collect()
+    }
+
+    pub fn get_version(&self, id: &str) -> Result<Option<SchemaVersion>, String> {
+        let document: Option<String> = self.conn
+            .query_row("SELECT document FROM schema_versions WHERE id = ?1", [id], |row| row.get(0))
+            .optional()
+            .map_err(|e| format!("Failed to read version: {}", e))?;
+        document.map(|d| from_json(&d)).transpose()
+    }
+
+    pub fn insert_version(&mut self, version: &SchemaVersion) -> Result<(), String> {
+        let tx = self.write()?;
+        insert(&tx, version)?;
+        commit(tx)
+    }
+
+    pub fn delete_version(&mut self, id: &str) -> Result<(), String> {
+        self.conn.execute("DELETE FROM schema_versions WHERE id = ?1", [id])
+            .map_err(|e| format!("Failed to delete version: {}", e))?;
+        Ok(())
+    }
+}
+
+pub(super) fn insert(conn: &Connection, version: &SchemaVersion) -> Result<(), String> {
+    conn.execute(
+        "INSERT INTO schema_versions (id, name, description, database_name, created_at, document)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+        params![
+            version.id,
+            version.name,
+            version.description,
+            version.database_name,
+            version.created_at,
+            to_json(version)?,
+        ],
+    ).map_err(|e| format!("Failed to save version: {}", e))?;
+
+    for tag in &version.tags {
+        conn.execute(
+            "INSERT OR IGNORE INTO schema_version_tags (version_id, tag) VALUES (?1, ?2)",
+            params![version.id, tag],
+        ).map_err(|e| format!("Failed to save version tags: {}", e))?;
+    }
+    Ok(())
+}
+
+/// Escape `LIKE` wildcards so user input matches literally.
+pub(super) fn escape_like(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        if matches!(c, '%' | '_' | '\\') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::schema::SchemaModel;
+    use crate::store::temp_base_path;
+
+    fn create_test_version(id: &str, name: &str, database_name: &str, tags: &[&str]) -> SchemaVersion {
+        SchemaVersion {
+            id: id.to_string(),
+            name: name.to_string(),
+            description: String::new(),
+            connection: String::new(),
+            database_name: database_name.to_string(),
+            schema: SchemaModel::new(),
+            created_at: format!("2026-01-0{}T00:00:00Z", id),
+            tags: tags.iter().map(|t| t.to_string()).collect(),
+            identity: None,
+            locale: None,
+        }
+    }
+
+    #[test]
+    fn test_search_versions() {
+        let base = temp_base_path();
+        let mut store = MetadataStore::open(&base).unwrap();
+        store.insert_version(&create_test_version("1", "Before 100% rollout", "app", &["release"])).unwrap();
+        store.insert_version(&create_test_version("2", "Nightly", "app", &[])).unwrap();
+        store.insert_version(&create_test_version("3", "Nightly", "billing", &["release"])).unwrap();
+
+        // The SQL filter and the in-memory filter used for shared storage must agree
+        let ids = |store: &MetadataStore, query: VersionQuery| -> Vec<String> {
+            let found: Vec<String> = store.list_versions(&query).unwrap().into_iter().map(|v| v.id).collect();
+            let matched: Vec<String> = store.list_versions(&VersionQuery::default()).unwrap()
+                .into_iter()
+                .filter(|v| query.matches(v))
+                .map(|v| v.id)
+                .collect();
+            assert_eq!(found, matched);
+            found
+        };
+
+        assert_eq!(ids(&store, VersionQuery::default()), vec!["1", "2", "3"]);
+        assert_eq!(ids(&store, VersionQuery { text: Some("nightly".into()), ..Default::default() }), vec!["2", "3"]);
+        assert_eq!(ids(&store, VersionQuery { text: Some("0%".into()), ..Default::default() }), vec!["1"]);
+        assert_eq!(ids(&store, VersionQuery { database_name: Some("app".into()), ..Default::default() }), vec!["1", "2"]);
+        assert_eq!(ids(&store, VersionQuery {
+            tag: Some("release".into()),
+            database_name: Some("billing".into()),
+            ..Default::default()
+        }), vec!["3"]);
+
+        store.delete_version("3").unwrap();
+        assert!(store.get_version("3").unwrap().is_none());
+        assert_eq!(ids(&store, VersionQuery { tag: Some("release".into()), ..Default::default() }), vec!["1"]);
+
+        std::fs::remove_dir_all(&base).unwrap();
+    }
+}
@@ -0,0 +1,269 @@
+//! Disposable PostgreSQL databases for integration tests.
+//!
+//! Enabled with the `test-support` feature. [`TestPostgres::start`] runs a
+//! throwaway cluster from the local PostgreSQL binaries (`initdb` and
+//! `pg_ctl`, found through `PGSHIFT_PG_BIN`, `pg_config --bindir` or the
+//! `PATH`) on a free port, removed again when it is dropped. When
+//! `PGSHIFT_TEST_DATABASE_URL` is set, the server it points at is used
+//! instead, e.g. a CI service container or a root shell where `initdb`
+//! refuses to run; the databases created on it are dropped with it.
+//!
+//! Each [`TestDatabase`] is a new, empty database, so tests can run in
+//! parallel on one server:
+//!
+//! ```ignore
+//! let postgres = TestPostgres::start()?;
+//! let db = postgres.create_database().await?;
+//! db.execute("CREATE TABLE users (id bigint PRIMARY KEY)").await?;
+//! let schema = db.schema().await?;
+//! ```
+
+use std::env;
+use std::fs;
+use std::net::TcpListener;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::Mutex;
+use sqlx::postgres::PgPool;
+use sqlx::Executor;
+use crate::apply::exec::{apply_migration_sql, fetch_applied_migrations, rollback_migration_sql, ApplyOptions, MigrationRun};
+use crate::cancel::CancelToken;
+use crate::db::connect::create_pool;
+use crate::db::introspect::introspect_schema;
+use crate::db::session::fetch_session_settings;
+use crate::model::schema::SchemaModel;
+
+/// Environment variable naming an existing server to create test databases on.
+pub const TEST_DATABASE_URL_VAR: &str = "PGSHIFT_TEST_DATABASE_URL";
+
+/// Environment variable naming the directory of the PostgreSQL binaries.
+pub const PG_BIN_VAR: &str = "PGSHIFT_PG_BIN";
+
+/// A PostgreSQL server test databases are created on.
+pub struct TestPostgres {
+    server: Server,
+    /// Databases created so far, dropped with an external server.
+    databases: Mutex<Vec<String>>,
+}
+
+enum Server {
+    /// A cluster this process started, in its own data directory.
+    Spawned { bin_dir: Option<PathBuf>, data_dir: PathBuf, port: u16 },
+    /// A server given by connection string.
+    External { url: String },
+}
+
+impl TestPostgres {
+    /// Start a disposable cluster, or use the server of
+    /// `PGSHIFT_TEST_DATABASE_URL` when it is set.
+    pub fn start() -> Result<Self, String> {
+        let server = match env::var(TEST_DATABASE_URL_VAR) {
+            Ok(url) if !url.is_empty() => Server::External { url },
+            _ => spawn_cluster()?,
+        };
+        Ok(Self { server, databases: Mutex::new(Vec::new()) })
+    }
+
+    /// Connection string of `database` on this server.
+    pub fn connection_string(&self, database: &str) -> String {
+        match &self.server {
+            Server::Spawned { port, .. } => format!("postgres://postgres@127.0.0.1:{}/{}", port, database),
+            Server::External { url } => with_database(url, database),
+        }
+    }
+
+    /// Create a new, empty database with a unique name.
+    pub async fn create_database(&self) -> Result<TestDatabase, String> {
+        let name = format!("pgshift_test_{}", uuid::Uuid::new_v4().simple());
+        let admin = create_pool(&self.connection_string("postgres"), "test")
+            .await
+            .map_err(|e| format!("Failed to connect to the test server: {}", e))?;
+        let created = admin.execute(format!("CREATE DATABASE \"{}\"", name).as_str()).await;
+        admin.close().await;
+        created.map_err(|e| format!("Failed to create database {}: {}", name, e))?;
+        self.databases.lock().unwrap_or_else(|e| e.into_inner()).push(name.clone());
+
+        let connection_string = self.connection_string(&name);
+        let pool = create_pool(&connection_string, "test")
+            .await
+            .map_err(|e| format!("Failed to connect to {}: {}", name, e))?;
+        Ok(TestDatabase { name, connection_string, pool })
+    }
+}
+
+impl Drop for TestPostgres {
+    fn drop(&mut self) {
+        match &self.server {
+            Server::Spawned { bin_dir, data_dir, .. } => {
+                let _ = pg_command(bin_dir, "pg_ctl")
+                    .args(["stop", "-m", "immediate", "-D"])
+                    .arg(data_dir)
+                    .output();
+                let _ = fs::remove_dir_all(data_dir);
+            }
+            Server::External { .. } => {
+                let databases = std::mem::take(&mut *self.databases.lock().unwrap_or_else(|e| e.into_inner()));
+                if databases.is_empty() {
+                    return;
+                }
+                let admin_url = self.connection_string("postgres");
+                // Drop may run inside a runtime, which cannot be blocked on
+                let _ = std::thread::spawn(move || {
+                    let runtime = tokio::runtime::Builder::new_current_thread().enable_all().build().ok()?;
+                    runtime.block_on(async {
+                        let admin = create_pool(&admin_url, "test").await.ok()?;
+                        for name in databases {
+                            let _ = admin.execute(format!("DROP DATABASE IF EXISTS \"{}\" WITH (FORCE)", name).as_str()).await;
+                        }
+                        admin.close().await;
+                        Some(())
+                    })
+                }).join();
+            }
+        }
+    }
+}
+
+/// A database of a [`TestPostgres`].
+pub struct TestDatabase {
+    pub name: String,
+    pub connection_string: String,
+    pub pool: PgPool,
+}
+
+impl TestDatabase {
+    /// Run `sql`, which may hold several statements.
+    pub async fn execute(&self, sql: &str) -> Result<(), String> {
+        self.pool.execute(sql)
+            .await
+            .map(|_| ())
+            .map_err(|e| format!("Failed to run SQL on {}: {}", self.name, e))
+    }
+
+    /// The schema of the database, as pgshift reads it.
+    pub async fn schema(&self) -> Result<SchemaModel, String> {
+        introspect_schema(&self.pool)
+            .await
+            .map_err(|e| format!("Introspection of {} failed: {}", self.name, e))
+    }
+
+    /// Apply the `up.sql` of the migration folder at `migration_path`, as
+    /// `apply_migration` does, and return the apply log.
+    pub async fn apply_migration(&self, migration_path: &str) -> Result<Vec<String>, String> {
+        Ok(self.apply_migration_with(migration_path, &ApplyOptions::default()).await?.logs)
+    }
+
+    /// Apply the migration folder at `migration_path` with `options` and
+    /// return the run.
+    pub async fn apply_migration_with(&self, migration_path: &str, options: &ApplyOptions) -> Result<MigrationRun, String> {
+        let mut conn = self.pool.acquire()
+            .await
+            .map_err(|e| format!("Failed to acquire connection: {}", e))?;
+        let session = fetch_session_settings(&mut conn)
+            .await
+            .map_err(|e| format!("Failed to read session settings: {}", e))?;
+        let mut run = MigrationRun::default();
+        apply_migration_sql(&self.pool, &mut conn, &session, migration_path, options, &mut run, &CancelToken::new(), |_| {}, |_| {}).await?;
+        Ok(run)
+    }
+
+    /// Run the `down.sql` of the migration folder at `migration_path`, as
+    /// `rollback_migration` does, and return the run.
+    pub async fn rollback_migration(&self, migration_path: &str) -> Result<MigrationRun, String> {
+        let mut conn = self.pool.acquire()
+            .await
+            .map_err(|e| format!("Failed to acquire connection: {}", e))?;
+        let session = fetch_session_settings(&mut conn)
+            .await
+            .map_err(|e| format!("Failed to read session settings: {}", e))?;
+        let mut run = MigrationRun::default();
+        rollback_migration_sql(&self.pool, &mut conn, &session, migration_path, &mut run, &CancelToken::new(), |_| {}, |_| {}).await?;
+        Ok(run)
+    }
+
+    /// Names of the migrations recorded as applied, oldest first.
+    pub async fn applied_migrations(&self) -> Result<Vec<String>, String> {
+        let mut conn = self.pool.acquire()
+            .await
+            .map_err(|e| format!("Failed to acquire connection: {}", e))?;
+        let applied = fetch_applied_migrations(&mut conn)
+            .await
+            .map_err(|e| format!("Failed to read applied migrations: {}", e))?;
+        Ok(applied.into_iter().map(|m| m.name).collect())
+    }
+}
+
+/// Initialize and start a cluster in a new temporary directory.
+fn spawn_cluster() -> Result<Server, String> {
+    let bin_dir = bin_dir();
+    let data_dir = env::temp_dir().join(format!("pgshift_pg_{}", uuid::Uuid::new_v4().simple()));
+    let port = TcpListener::bind("127.0.0.1:0")
+        .and_then(|listener| listener.local_addr())
+        .map_err(|e| format!("Failed to find a free port: {}", e))?
+        .port();
+
+    run(pg_command(&bin_dir, "initdb")
+        .args(["-U", "postgres", "--auth=trust", "--no-sync", "-E", "UTF8", "-D"])
+        .arg(&data_dir))?;
+    let options = format!(
+        "-p {} -k {} -c listen_addresses=127.0.0.1 -c fsync=off -c full_page_writes=off",
+        port,
+        data_dir.display()
+    );
+    let started = run(pg_command(&bin_dir, "pg_ctl")
+        .args(["start", "-w", "-D"])
+        .arg(&data_dir)
+        .arg("-l")
+        .arg(data_dir.join("server.log"))
+        .args(["-o", &options]));
+    if let Err(e) = started {
+        let log = fs::read_to_string(data_dir.join("server.log")).unwrap_or_default();
+        let _ = fs::remove_dir_all(&data_dir);
+        return Err(format!("{}\n{}", e, log));
+    }
+
+    Ok(Server::Spawned { bin_dir, data_dir, port })
+}
+
+/// Directory of the PostgreSQL binaries; `None` runs them from the `PATH`.
+fn bin_dir() -> Option<PathBuf> {
+    if let Ok(dir) = env::var(PG_BIN_VAR) {
+        return Some(PathBuf::from(dir));
+    }
+    let output = Command::new("pg_config").arg("--bindir").output().ok()?;
+    let dir = PathBuf::from(String::from_utf8_lossy(&output.stdout).trim());
+    (output.status.success() && dir.join("initdb").exists()).then_some(dir)
+}
+
+fn pg_command(bin_dir: &Option<PathBuf>, program: &str) -> Command {
+    Command::new(bin_dir.as_deref().map_or_else(|| PathBuf::from(program), |dir| Path::new(dir).join(program)))
+}
+
+fn run(command: &mut Command) -> Result<(), String> {
+    let program = command.get_program().to_string_lossy().to_string();
+    let output = command.output()
+        .map_err(|e| format!("Failed to run {}: {}; set {} to the PostgreSQL bin directory or {} to a server", program, e, PG_BIN_VAR, TEST_DATABASE_URL_VAR))?;
+    if output.status.success() {
+        Ok(())
+    } else {
+        Err(format!("{} failed: {}", program, String::from_utf8_lossy(&output.stderr).trim()))
+    }
+}
+
+/// `url` with its database replaced by `database`, keeping any parameters.
+fn with_database(url: &str, database: &str) -> String {
+    let (base, query) = match url.split_once('?') {
+        Some((base, query)) => (base, Some(query)),
+        None => (url, None),
+    };
+    // The database follows the first slash after the authority
+    let authority_start = base.find("://").map_or(0, |i| i + 3);
+    let base = match base[authority_start..].find('/') {
+        Some(i) => &base[..authority_start + i],
+        None => base,
+    };
+    match query {
+        Some(query) => format!("{}/{}?{}", base, database, query),
+        None => format!("{}/{}", base, database),
+    }
+}
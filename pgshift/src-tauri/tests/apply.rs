@@ -0,0 +1,79 @@
+//! Applying migrations to a real database.
+
+use pgshift_lib::apply::exec::{ApplyOptions, StatementStatus};
+use pgshift_lib::test_support::TestPostgres;
+
+#[tokio::test]
+async fn test_continue_on_error_rolls_back_to_savepoint() {
+    let postgres = TestPostgres::start().unwrap();
+    let db = postgres.create_database().await.unwrap();
+
+    let dir = std::env::temp_dir().join(format!("pgshift_continue_{}", std::process::id()));
+    let path = dir.join("20260101000000__widgets");
+    std::fs::create_dir_all(&path).unwrap();
+    std::fs::write(path.join("up.sql"), "\
+CREATE TABLE widgets (id integer PRIMARY KEY);
+INSERT INTO widgets VALUES (1);
+INSERT INTO widgets VALUES (1);
+INSERT INTO widgets VALUES (2);
+").unwrap();
+    let options = ApplyOptions { continue_on_error: true, ..Default::default() };
+    let run = db.apply_migration_with(&path.to_string_lossy(), &options).await;
+    let _ = std::fs::remove_dir_all(dir);
+    let run = run.unwrap();
+
+    // The duplicate is rolled back to its savepoint and the rest applied
+    let statuses: Vec<StatementStatus> = run.statements.iter().map(|s| s.status).collect();
+    assert_eq!(statuses, [StatementStatus::Succeeded, StatementStatus::Succeeded, StatementStatus::Failed, StatementStatus::Succeeded]);
+    assert!(run.statements[2].error.as_deref().unwrap_or_default().contains("duplicate key"));
+    let ids: Vec<i32> = sqlx::query_scalar("SELECT id FROM widgets ORDER BY id").fetch_all(&db.pool).await.unwrap();
+    assert_eq!(ids, [1, 2]);
+
+    // Only partly applied, so it stays pending
+    assert!(db.applied_migrations().await.unwrap().is_empty());
+    assert!(run.logs.iter().any(|l| l.contains("not recorded")));
+    assert!(!run.logs.iter().any(|l| l.contains("completed successfully")));
+}
+
+#[tokio::test]
+async fn test_rollback_of_squashed_folder_removes_its_records() {
+    let postgres = TestPostgres::start().unwrap();
+    let db = postgres.create_database().await.unwrap();
+
+    let dir = std::env::temp_dir().join(format!("pgshift_rollback_{}", std::process::id()));
+    let folder = |name: &str, up: &str, down: &str| {
+        let path = dir.join(name);
+        std::fs::create_dir_all(&path).unwrap();
+        std::fs::write(path.join("up.sql"), up).unwrap();
+        std::fs::write(path.join("down.sql"), down).unwrap();
+        path.to_string_lossy().to_string()
+    };
+    let init = folder("20260101000000__init", "CREATE TABLE users (id integer);", "DROP TABLE users;");
+    let a = folder("20260102000000__a", "ALTER TABLE users ADD COLUMN email text;", "ALTER TABLE users DROP COLUMN email;");
+    let b = folder("20260103000000__b", "ALTER TABLE users ADD COLUMN name text;", "ALTER TABLE users DROP COLUMN name;");
+    for path in [&init, &a, &b] {
+        db.apply_migration(path).await.unwrap();
+    }
+    // a and b squashed into one folder, as an applied database sees it
+    let squash = folder(
+        "20260103000000__squash",
+        "ALTER TABLE users ADD COLUMN email text, ADD COLUMN name text;",
+        "ALTER TABLE users DROP COLUMN email, DROP COLUMN name;",
+    );
+    std::fs::write(
+        std::path::Path::new(&squash).join("meta.json"),
+        r#"{"name": "squash", "timestamp": "20260103000000", "generated_at": "", "items_count": 2,
+            "has_dangerous": false, "items": [],
+            "squashes": ["20260102000000__a", "20260103000000__b"]}"#,
+    ).unwrap();
+
+    let run = db.rollback_migration(&squash).await;
+    let _ = std::fs::remove_dir_all(&dir);
+    run.unwrap();
+
+    assert_eq!(db.applied_migrations().await.unwrap(), ["20260101000000__init"]);
+    let columns: Vec<String> = sqlx::query_scalar(
+        "SELECT column_name::text FROM information_schema.columns WHERE table_name = 'users' ORDER BY ordinal_position",
+    ).fetch_all(&db.pool).await.unwrap();
+    assert_eq!(columns, ["id"]);
+}
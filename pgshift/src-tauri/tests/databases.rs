@@ -0,0 +1,58 @@
+//! Listing the databases of a real server.
+
+use pgshift_lib::db::databases::list_databases;
+use pgshift_lib::test_support::TestPostgres;
+use sqlx::postgres::{PgConnectOptions, PgPool};
+
+#[tokio::test]
+async fn test_list_databases_leaves_out_templates_and_closed_databases() {
+    let postgres = TestPostgres::start().unwrap();
+    let listed = postgres.create_database().await.unwrap();
+    let template = postgres.create_database().await.unwrap();
+    let closed = postgres.create_database().await.unwrap();
+    let revoked = postgres.create_database().await.unwrap();
+    let role = format!("pgshift_test_{}", uuid::Uuid::new_v4().simple());
+
+    listed.execute(&format!(
+        "CREATE ROLE \"{role}\" LOGIN;
+         ALTER DATABASE \"{}\" IS_TEMPLATE true;
+         ALTER DATABASE \"{}\" ALLOW_CONNECTIONS false;
+         REVOKE CONNECT ON DATABASE \"{}\" FROM PUBLIC;",
+        template.name, closed.name, revoked.name
+    )).await.unwrap();
+
+    // Listed as a role without CONNECT on `revoked`, as superusers have it everywhere
+    let options: PgConnectOptions = listed.connection_string.parse().unwrap();
+    let pool = PgPool::connect_with(options.username(&role)).await.unwrap();
+    let databases = list_databases(&pool).await;
+    pool.close().await;
+
+    // Undo before asserting, so the databases and the role can be dropped
+    listed.execute(&format!(
+        "ALTER DATABASE \"{}\" IS_TEMPLATE false;
+         ALTER DATABASE \"{}\" ALLOW_CONNECTIONS true;
+         DROP ROLE \"{role}\";",
+        template.name, closed.name
+    )).await.unwrap();
+
+    let databases = databases.unwrap();
+    let find = |name: &str| databases.iter().find(|d| d.name == name);
+    assert!(find(&template.name).is_none());
+    assert!(find(&closed.name).is_none());
+
+    let entry = find(&listed.name).unwrap();
+    assert!(entry.can_connect);
+    assert!(entry.size_bytes.is_some_and(|size| size > 0));
+    let encoding: String = sqlx::query_scalar(
+        "SELECT pg_encoding_to_char(encoding) FROM pg_database WHERE datname = current_database()",
+    )
+        .fetch_one(&listed.pool)
+        .await
+        .unwrap();
+    assert_eq!(entry.encoding, encoding);
+
+    // Shown so the user sees it exists, but without a size, as it is not readable
+    let entry = find(&revoked.name).unwrap();
+    assert!(!entry.can_connect);
+    assert_eq!(entry.size_bytes, None);
+}
@@ -0,0 +1,23 @@
+//! Queries on read-only connections, against a real database.
+
+use pgshift_lib::safe_mode::{fetch_read_query, is_read_query};
+use pgshift_lib::test_support::TestPostgres;
+
+#[tokio::test]
+async fn test_read_only_query_rejects_writes_in_with() {
+    let postgres = TestPostgres::start().unwrap();
+    let db = postgres.create_database().await.unwrap();
+    db.execute("CREATE TABLE users (id integer); INSERT INTO users VALUES (1), (2);").await.unwrap();
+
+    // Passes as a read, but deletes in its CTE
+    let query = "WITH gone AS (DELETE FROM users RETURNING id) SELECT id FROM gone";
+    assert!(is_read_query(query));
+    let error = match fetch_read_query(&db.pool, query, true).await {
+        Ok(_) => panic!("the DELETE in the WITH ran"),
+        Err(e) => e.to_string(),
+    };
+    assert!(error.contains("read-only transaction"), "{}", error);
+
+    let rows = fetch_read_query(&db.pool, "SELECT id FROM users", true).await.unwrap();
+    assert_eq!(rows.len(), 2);
+}
@@ -0,0 +1,115 @@
+//! Round trips through a real database: introspect, diff, render, apply.
+
+use pgshift_lib::diff::diff::compare_schemas;
+use pgshift_lib::render::sql::{render_migration_files, RenderOptions};
+use pgshift_lib::test_support::{TestDatabase, TestPostgres};
+
+const SCHEMA: &str = r#"
+    CREATE TYPE status AS ENUM ('active', 'archived');
+    CREATE SEQUENCE invoice_numbers START 1000;
+    CREATE TABLE users (
+        id bigserial PRIMARY KEY,
+        email text NOT NULL UNIQUE,
+        status status NOT NULL DEFAULT 'active',
+        created_at timestamptz NOT NULL DEFAULT now()
+    );
+    CREATE INDEX users_created_at_idx ON users (created_at);
+    CREATE TABLE invoices (
+        id bigserial PRIMARY KEY,
+        number integer NOT NULL DEFAULT nextval('invoice_numbers'),
+        user_id bigint NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+        amount numeric(12, 2) NOT NULL
+    );
+    COMMENT ON TABLE invoices IS 'Issued invoices';
+    -- Functions are created before tables, and PL/pgSQL bodies are only
+    -- checked when they run
+    CREATE FUNCTION invoice_total(uid bigint) RETURNS numeric LANGUAGE plpgsql STABLE AS $$
+    BEGIN
+        RETURN (SELECT coalesce(sum(amount), 0) FROM invoices WHERE user_id = uid);
+    END
+    $$;
+"#;
+
+/// Render the migration turning `target` into `source` and apply its up.sql
+/// to `target`; returns the migration folder.
+async fn migrate(source: &TestDatabase, target: &TestDatabase, dir: &str) -> String {
+    let report = compare_schemas(&source.schema().await.unwrap(), &target.schema().await.unwrap());
+    assert!(!report.items.is_empty());
+    let path = render_migration_files(&report, "round_trip", dir, &RenderOptions::default()).unwrap();
+    target.apply_migration(&path).await.unwrap();
+    path
+}
+
+#[tokio::test]
+async fn test_apply_and_revert_round_trip() {
+    let postgres = TestPostgres::start().unwrap();
+    let source = postgres.create_database().await.unwrap();
+    let target = postgres.create_database().await.unwrap();
+    source.execute(SCHEMA).await.unwrap();
+
+    let dir = std::env::temp_dir().join(format!("pgshift_round_trip_{}", std::process::id()));
+    let path = migrate(&source, &target, &dir.to_string_lossy()).await;
+
+    // After up.sql, the target matches the source
+    let report = compare_schemas(&source.schema().await.unwrap(), &target.schema().await.unwrap());
+    assert!(report.items.is_empty(), "left after up.sql: {:?}", report.items);
+
+    // down.sql brings it back to an empty schema
+    let down_sql = std::fs::read_to_string(std::path::Path::new(&path).join("down.sql")).unwrap();
+    target.execute(&down_sql).await.unwrap();
+    let empty = postgres.create_database().await.unwrap();
+    let report = compare_schemas(&empty.schema().await.unwrap(), &target.schema().await.unwrap());
+    assert!(report.items.is_empty(), "left after down.sql: {:?}", report.items);
+
+    let _ = std::fs::remove_dir_all(dir);
+}
+
+#[tokio::test]
+async fn test_changes_to_existing_schema_round_trip() {
+    let postgres = TestPostgres::start().unwrap();
+    let source = postgres.create_database().await.unwrap();
+    let target = postgres.create_database().await.unwrap();
+    target.execute(SCHEMA).await.unwrap();
+    source.execute(SCHEMA).await.unwrap();
+    source.execute(r#"
+        ALTER TABLE users ADD COLUMN name text;
+        ALTER TABLE users DROP COLUMN created_at;
+        ALTER TYPE status ADD VALUE 'suspended';
+        CREATE INDEX invoices_user_id_idx ON invoices (user_id);
+        COMMENT ON TABLE invoices IS 'Invoices sent to users';
+    "#).await.unwrap();
+
+    let dir = std::env::temp_dir().join(format!("pgshift_round_trip_changes_{}", std::process::id()));
+    migrate(&source, &target, &dir.to_string_lossy()).await;
+
+    let report = compare_schemas(&source.schema().await.unwrap(), &target.schema().await.unwrap());
+    assert!(report.items.is_empty(), "left after up.sql: {:?}", report.items);
+
+    let _ = std::fs::remove_dir_all(dir);
+}
+
+#[tokio::test]
+async fn test_unrecorded_migration_is_rolled_back() {
+    let postgres = TestPostgres::start().unwrap();
+    let target = postgres.create_database().await.unwrap();
+    // A migrations table that refuses every record
+    target.execute(
+        "CREATE TABLE pgshift_migrations (
+            name text PRIMARY KEY CHECK (false),
+            checksum text NOT NULL,
+            applied_at timestamptz NOT NULL DEFAULT now(),
+            duration_ms bigint NOT NULL
+        )",
+    ).await.unwrap();
+
+    let dir = std::env::temp_dir().join(format!("pgshift_unrecorded_{}", std::process::id()));
+    let path = dir.join("20260101000000__widgets");
+    std::fs::create_dir_all(&path).unwrap();
+    std::fs::write(path.join("up.sql"), "CREATE TABLE widgets (id integer);\nINSERT INTO widgets VALUES (1);\n").unwrap();
+    let result = target.apply_migration(&path.to_string_lossy()).await;
+    let _ = std::fs::remove_dir_all(dir);
+
+    // The script and its record commit together or not at all
+    assert!(result.unwrap_err().contains("could not be recorded"));
+    assert!(target.schema().await.unwrap().tables.iter().all(|t| t.name != "widgets"));
+}
@@ -1,4 +1,5 @@
-import { useState, useCallback } from 'react';
+import { useState, useCallback, useEffect } from 'react';
+import { invoke } from '@tauri-apps/api/core';
 import { BrowserRouter, Routes, Route, NavLink } from 'react-router-dom';
 import ConnectionsPage from './pages/ConnectionsPage';
 import ComparePage from './pages/ComparePage';
@@ -38,6 +39,23 @@ function App() {
     error: null,
   });
 
+  const [safeMode, setSafeMode] = useState(false);
+
+  useEffect(() => {
+    invoke<boolean>('get_safe_mode')
+      .then(setSafeMode)
+      .catch((err) => setState((prev) => ({ ...prev, error: String(err) })));
+  }, []);
+
+  const toggleSafeMode = useCallback(async () => {
+    try {
+      await invoke('set_safe_mode', { enabled: !safeMode });
+      setSafeMode(!safeMode);
+    } catch (err) {
+      setState((prev) => ({ ...prev, error: String(err) }));
+    }
+  }, [safeMode]);
+
   const updateConnections = useCallback((connections: ConnectionState) => {
     setState((prev) => ({ ...prev, connections }));
   }, []);
@@ -171,6 +189,11 @@ function App() {
             <NavLink to="/apply" className={({ isActive }) => isActive ? 'nav-link active' : 'nav-link'}>
               ⚡ Apply
             </NavLink>
+            <label className={safeMode ? 'safe-mode-toggle on' : 'safe-mode-toggle'}
+              title="Disable applying, cloning and non-SELECT queries">
+              <input type="checkbox" checked={safeMode} onChange={toggleSafeMode} />
+              🔒 Safe mode
+            </label>
           </nav>
         </header>
         
@@ -1,36 +1,75 @@
-import { DiffItem } from '../types';
+import { useState } from 'react';
+import { DiffItem, DangerAcknowledgment, EnumReorderStrategy } from '../types';
 
 interface DiffDetailsProps {
   item: DiffItem;
   sourceDb?: string;
   targetDb?: string;
+  onAcknowledge?: (item: DiffItem, acknowledgment: DangerAcknowledgment | null) => void;
+  onResolveReorder?: (item: DiffItem, strategy: EnumReorderStrategy) => void;
+  onExpandContract?: (item: DiffItem) => void;
 }
 
-function DiffDetails({ item }: DiffDetailsProps) {
+const formatBytes = (bytes: number) => {
+  const units = ['bytes', 'kB', 'MB', 'GB', 'TB'];
+  let value = bytes;
+  let unit = 0;
+  while (Math.abs(value) >= 1024 && unit < units.length - 1) {
+    value /= 1024;
+    unit++;
+  }
+  return unit === 0 ? `${bytes} bytes` : `${value.toFixed(1)} ${units[unit]}`;
+};
+
+function DiffDetails({ item, onAcknowledge, onResolveReorder, onExpandContract }: DiffDetailsProps) {
+  const [acknowledgedBy, setAcknowledgedBy] = useState('');
+  const [reason, setReason] = useState('');
+
+  const acknowledge = () => {
+    if (!onAcknowledge || !acknowledgedBy.trim() || !reason.trim()) return;
+    onAcknowledge(item, {
+      acknowledged_by: acknowledgedBy.trim(),
+      acknowledged_at: new Date().toISOString(),
+      reason: reason.trim(),
+    });
+    setReason('');
+  };
+
   const kindColors: Record<string, string> = {
     added: 'var(--success)',
     removed: 'var(--danger)',
     modified: 'var(--warning)',
+    reordered: 'var(--accent-info)',
+    renamed: 'var(--accent-info)',
   };
 
   const kindLabels: Record<string, string> = {
     added: '➕ Added',
     removed: '➖ Removed',
     modified: '✏️ Modified',
+    reordered: '🔀 Reordered',
+    renamed: '🏷️ Renamed',
   };
 
   const kindDescriptions: Record<string, string> = {
     added: 'This object exists in Source but not in Target. It will be CREATED.',
     removed: 'This object exists in Target but not in Source. It will be DROPPED.',
     modified: 'This object exists in both databases but has differences. It will be ALTERED.',
+    reordered: 'This enum has the same values in both databases but in a different order. Choose how to migrate it.',
+    renamed: 'This object exists in both databases under different names. It will be RENAMED.',
   };
 
+  const formatValue = (value: unknown) =>
+    value === null || value === undefined ? '—' : typeof value === 'string' ? value : JSON.stringify(value);
+
   const getObjectIcon = (type: string) => {
     switch (type) {
       case 'table': return '📋';
       case 'column': return '📝';
       case 'constraint': return '🔒';
       case 'index': return '📇';
+      case 'rls':
+      case 'policy': return '🛡️';
       default: return '📦';
     }
   };
@@ -55,6 +94,95 @@ function DiffDetails({ item }: DiffDetailsProps) {
           </div>
         </div>
       )}
+
+      {onExpandContract && item.object_type === 'column' && item.changes?.some((c) => c.property === 'data_type') && (
+        <div className="details-section">
+          <h4>Expand and Contract</h4>
+          <p style={{ color: 'var(--text-secondary)' }}>
+            Instead of rewriting the table, add a column of the new type, keep it in sync, backfill it in
+            batches and swap the columns in one migration, then drop the old column in a second one.
+          </p>
+          <button className="btn btn-secondary" onClick={() => onExpandContract(item)}>
+            Generate expand/contract migrations
+          </button>
+        </div>
+      )}
+
+      {item.dangerous && item.acknowledgment && (
+        <div className="details-section">
+          <h4>Acknowledgment</h4>
+          <p>
+            Acknowledged by <strong>{item.acknowledgment.acknowledged_by}</strong> on{' '}
+            {new Date(item.acknowledgment.acknowledged_at).toLocaleString()}
+          </p>
+          <p style={{ color: 'var(--text-secondary)' }}>{item.acknowledgment.reason}</p>
+          {onAcknowledge && (
+            <button className="btn btn-secondary" onClick={() => onAcknowledge(item, null)}>
+              Withdraw
+            </button>
+          )}
+        </div>
+      )}
+
+      {item.dangerous && !item.acknowledgment && onAcknowledge && (
+        <div className="details-section">
+          <h4>Acknowledge</h4>
+          <p style={{ color: 'var(--text-secondary)' }}>
+            Record that this change is deliberate. The acknowledgment is written into up.sql and meta.json.
+          </p>
+          <input
+            type="text"
+            className="input"
+            value={acknowledgedBy}
+            onChange={(e) => setAcknowledgedBy(e.target.value)}
+            placeholder="Your name"
+          />
+          <textarea
+            className="input"
+            value={reason}
+            onChange={(e) => setReason(e.target.value)}
+            placeholder="Why is this change intended?"
+            rows={2}
+            style={{ marginTop: '0.5rem' }}
+          />
+          <button
+            className="btn btn-warning"
+            onClick={acknowledge}
+            disabled={!acknowledgedBy.trim() || !reason.trim()}
+            style={{ marginTop: '0.5rem' }}
+          >
+            Acknowledge
+          </button>
+        </div>
+      )}
+      
+      {item.enum_reorder && (
+        <div className="details-section">
+          <h4>Value Order</h4>
+          <div className="details-grid">
+            <div className="details-row">
+              <span className="details-label">Source:</span>
+              <span className="details-value">{item.enum_reorder.source_order.join(', ')}</span>
+            </div>
+            <div className="details-row">
+              <span className="details-label">Target:</span>
+              <span className="details-value">{item.enum_reorder.target_order.join(', ')}</span>
+            </div>
+          </div>
+          {item.enum_reorder.resolutions.map((resolution) => (
+            <label key={resolution.strategy} style={{ display: 'block', marginTop: '0.5rem' }}>
+              <input
+                type="radio"
+                name={`reorder-${item.id}`}
+                checked={item.enum_reorder?.strategy === resolution.strategy}
+                disabled={!onResolveReorder}
+                onChange={() => onResolveReorder?.(item, resolution.strategy)}
+              />{' '}
+              {resolution.description}
+            </label>
+          ))}
+        </div>
+      )}
       
       <div className="details-content">
         <div className="details-section">
@@ -70,6 +198,26 @@ function DiffDetails({ item }: DiffDetailsProps) {
               <span className="details-label">Name:</span>
               <span className="details-value">{item.object_name}</span>
             </div>
+            {item.data_loss && (
+              <div className="details-row">
+                <span className="details-label">Data dropped:</span>
+                <span className="details-value" style={{ color: item.data_loss.rows > 0 ? 'var(--danger)' : undefined }}>
+                  {item.data_loss.estimated ? '~' : ''}{item.data_loss.rows.toLocaleString()} rows
+                  {item.data_loss.non_null_rows != null && (
+                    <>, {item.data_loss.estimated ? '~' : ''}{item.data_loss.non_null_rows.toLocaleString()} with a value</>
+                  )}
+                </span>
+              </div>
+            )}
+            {item.disk_estimate && (
+              <div className="details-row">
+                <span className="details-label">Disk space:</span>
+                <span className="details-value">
+                  ~{formatBytes(item.disk_estimate.temporary_bytes)} temporary,
+                  ~{formatBytes(item.disk_estimate.final_bytes)} kept
+                </span>
+              </div>
+            )}
           </div>
         </div>
 
@@ -81,6 +229,26 @@ function DiffDetails({ item }: DiffDetailsProps) {
             </p>
             <p style={{ color: 'var(--text-secondary)' }}>{item.details}</p>
           </div>
+          {item.changes && item.changes.length > 0 && (
+            <table className="property-changes" style={{ width: '100%', marginTop: '0.5rem', borderCollapse: 'collapse' }}>
+              <thead>
+                <tr>
+                  <th style={{ textAlign: 'left' }}>Property</th>
+                  <th style={{ textAlign: 'left' }}>Target (Current)</th>
+                  <th style={{ textAlign: 'left' }}>Source (Desired)</th>
+                </tr>
+              </thead>
+              <tbody>
+                {item.changes.map((change) => (
+                  <tr key={change.property}>
+                    <td><code>{change.property}</code></td>
+                    <td style={{ color: 'var(--danger)' }}><code>{formatValue(change.old)}</code></td>
+                    <td style={{ color: 'var(--success)' }}><code>{formatValue(change.new)}</code></td>
+                  </tr>
+                ))}
+              </tbody>
+            </table>
+          )}
         </div>
 
         <div className="details-section">
@@ -121,6 +289,12 @@ function DiffDetails({ item }: DiffDetailsProps) {
             {item.kind === 'modified' && (
               <p>🔄 <strong>ALTER</strong> this {item.object_type} in Target database</p>
             )}
+            {item.kind === 'renamed' && (
+              <p>🏷️ <strong>RENAME</strong> this {item.object_type} in Target database</p>
+            )}
+            {item.kind === 'reordered' && (
+              <p>🔀 <strong>CHOOSE</strong> whether to reorder this {item.object_type} in Target database</p>
+            )}
           </div>
         </div>
       </div>
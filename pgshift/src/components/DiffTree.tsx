@@ -18,13 +18,31 @@ function DiffTree({ items, selectedItem, onSelectItem }: DiffTreeProps) {
   }, {} as Record<string, DiffItem[]>);
 
   const typeLabels: Record<string, string> = {
+    rename: '✏️ Renames',
     table: '📋 Tables',
     column: '📝 Columns',
+    inheritance: '🧬 Inheritance',
+    persistence: '💾 Persistence',
+    storage_parameters: '⚙️ Storage Parameters',
     constraint: '🔒 Constraints',
     index: '📇 Indexes',
+    validation: '✔️ Constraint Validation',
+    rls: '🛡️ Row Level Security',
+    policy: '🛡️ Policies',
+    privilege: '🔑 Privileges',
+    function: 'ƒ Functions',
+    trigger: '⚡ Triggers',
+    foreign_server: '🌐 Foreign Servers',
+    user_mapping: '🌐 User Mappings',
+    foreign_table: '🌐 Foreign Tables',
+    publication: '📡 Publications',
+    subscription: '📡 Subscriptions',
   };
 
-  const typeOrder = ['table', 'column', 'constraint', 'index'];
+  const typeOrder = [
+    'rename', 'function', 'foreign_server', 'user_mapping', 'foreign_table', 'table', 'inheritance', 'column', 'constraint',
+    'persistence', 'index', 'validation', 'storage_parameters', 'rls', 'policy', 'trigger', 'publication', 'subscription', 'privilege',
+  ];
 
   return (
     <div className="diff-tree">
@@ -44,14 +62,19 @@ function DiffTree({ items, selectedItem, onSelectItem }: DiffTreeProps) {
                 onClick={() => onSelectItem(item)}
               >
                 <span className={`diff-item-badge ${item.kind}`}>
-                  {item.kind.charAt(0)}
+                  {item.kind === 'reordered' ? '⇅' : item.kind.charAt(0)}
                 </span>
                 <span className="diff-item-name" title={item.object_name}>
                   {item.object_name}
                 </span>
                 {item.dangerous && (
-                  <span className="diff-item-dangerous" title="Dangerous operation">
-                    ⚠️
+                  <span
+                    className="diff-item-dangerous"
+                    title={item.acknowledgment
+                      ? `Dangerous operation, acknowledged by ${item.acknowledgment.acknowledged_by}`
+                      : 'Dangerous operation'}
+                  >
+                    {item.acknowledgment ? '✔️' : '⚠️'}
                   </span>
                 )}
               </div>
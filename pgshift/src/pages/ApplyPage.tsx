@@ -1,6 +1,7 @@
-import { useState } from 'react';
+import { useEffect, useRef, useState } from 'react';
 import { invoke } from '@tauri-apps/api/core';
-import { ConnectionState, MultiConnectionState, DatabaseConnection, MigrationApplyResult } from '../types';
+import { listen } from '@tauri-apps/api/event';
+import { ConnectionState, MultiConnectionState, DatabaseConnection, MigrationApplyResult, MigrationPlan, TargetResult, LockImpact, MigrationRun, ApplyOptions, OperationProgress, ApplyProgress } from '../types';
 import LogViewer from '../components/LogViewer';
 
 interface ApplyPageProps {
@@ -34,10 +35,72 @@ function ApplyPage({
   const [applyMode, setApplyMode] = useState<'single' | 'multi'>('single');
   const [multiResults, setMultiResults] = useState<MigrationApplyResult[]>([]);
   const [applyingToId, setApplyingToId] = useState<string | null>(null);
+  const [progress, setProgress] = useState<OperationProgress | null>(null);
+  const [statementProgress, setStatementProgress] = useState<ApplyProgress | null>(null);
+  const [operationId, setOperationId] = useState<string | null>(null);
+  const cancelRequested = useRef(false);
+  const [plan, setPlan] = useState<MigrationPlan | null>(null);
+  const [lockImpact, setLockImpact] = useState<LockImpact | null>(null);
+  const [continueOnError, setContinueOnError] = useState(false);
+  const [parallelism, setParallelism] = useState(1);
+  const [backupMode, setBackupMode] = useState<'none' | 'full' | 'schema'>('none');
+  const applyOptions: ApplyOptions = {
+    continue_on_error: continueOnError,
+    backup: backupMode === 'none' ? undefined : { schema_only: backupMode === 'schema' },
+  };
+
+  // Statements skipped past with continue on error
+  const logFailedStatements = (run: MigrationRun, prefix = '') => {
+    run.statements
+      .filter((statement) => statement.status === 'failed')
+      .forEach((statement) => addLog(`⚠️ ${prefix}Statement ${statement.index} (line ${statement.line}) failed: ${statement.error}`));
+  };
+
+  // Index builds and table rewrites report progress while a migration runs
+  useEffect(() => {
+    const unlisten = listen<OperationProgress>('operation-progress', (event) => setProgress(event.payload));
+    const unlistenStatements = listen<ApplyProgress>('apply-progress', (event) => {
+      setStatementProgress(event.payload);
+      // The index build or rewrite of the previous statement is over
+      if (event.payload.status === null) {
+        setProgress(null);
+      }
+    });
+    return () => {
+      unlisten.then((stop) => stop());
+      unlistenStatements.then((stop) => stop());
+    };
+  }, []);
+
+  useEffect(() => {
+    if (!loading && applyingToId === null) {
+      setProgress(null);
+      setStatementProgress(null);
+    }
+  }, [loading, applyingToId]);
 
   const effectivePath = customPath || migrationPath;
   const hasMultiTargets = multiConnections.targets.filter(c => c.connected).length > 0;
 
+  // Id under which the running apply or rollback can be cancelled
+  const startOperation = () => {
+    const id = `apply-${Date.now()}`;
+    cancelRequested.current = false;
+    setOperationId(id);
+    return id;
+  };
+
+  const cancelOperation = async () => {
+    if (!operationId) return;
+    cancelRequested.current = true;
+    addLog('⏹ Cancelling...');
+    try {
+      await invoke<boolean>('cancel_operation', { operationId });
+    } catch (err) {
+      addLog(`❌ Error: ${err}`);
+    }
+  };
+
   const applyMigration = async () => {
     if (!effectivePath) {
       setError('Please specify a migration path.');
@@ -54,20 +117,114 @@ function ApplyPage({
     setApplySuccess(false);
 
     try {
+      if (connections.sourceConnected) {
+        const localeWarnings = await invoke<string[]>('preflight_locales', {
+          sourceConnection: connections.source,
+          targetConnection: connections.target,
+        });
+        localeWarnings.forEach((warning) => addLog(`⚠️ ${warning}`));
+      }
+
       addLog('Starting migration application...');
       
-      const resultLogs = await invoke<string[]>('apply_migration', {
+      const run = await invoke<MigrationRun>('apply_migration', {
         connectionString: connections.target,
         migrationPath: effectivePath,
+        options: applyOptions,
+        operationId: startOperation(),
       });
 
-      resultLogs.forEach((log: string) => addLog(log));
+      run.logs.forEach((log: string) => addLog(log));
+      logFailedStatements(run);
       setApplySuccess(true);
       addLog('✅ Migration applied successfully!');
     } catch (err) {
       setError(`Migration failed: ${err}`);
       addLog(`❌ Error: ${err}`);
       setApplySuccess(false);
+    } finally {
+      setOperationId(null);
+      setLoading(false);
+    }
+  };
+
+  const rollbackMigration = async () => {
+    if (!effectivePath) {
+      setError('Please specify a migration path.');
+      return;
+    }
+
+    if (!window.confirm(`Run down.sql of ${effectivePath} on the target database?`)) {
+      return;
+    }
+
+    setLoading(true);
+    setError(null);
+    setApplySuccess(false);
+
+    try {
+      addLog('Starting rollback...');
+
+      const run = await invoke<MigrationRun>('rollback_migration', {
+        connectionString: connections.target,
+        migrationPath: effectivePath,
+        operationId: startOperation(),
+      });
+
+      run.logs.forEach((log: string) => addLog(log));
+      addLog('✅ Migration rolled back successfully!');
+    } catch (err) {
+      setError(`Rollback failed: ${err}`);
+      addLog(`❌ Error: ${err}`);
+    } finally {
+      setOperationId(null);
+      setLoading(false);
+    }
+  };
+
+  const planMigration = async () => {
+    if (!effectivePath) {
+      setError('Please specify a migration path.');
+      return;
+    }
+
+    setLoading(true);
+    setError(null);
+    setPlan(null);
+
+    try {
+      const result = await invoke<MigrationPlan>('plan_migration', {
+        connectionString: connections.target,
+        migrationPath: effectivePath,
+      });
+      setPlan(result);
+      addLog(`Planned ${result.statements.length} data-modifying statements of ${result.total_statements}`);
+    } catch (err) {
+      setError(`Planning failed: ${err}`);
+    } finally {
+      setLoading(false);
+    }
+  };
+
+  const analyzeLocks = async () => {
+    if (!effectivePath) {
+      setError('Please specify a migration path.');
+      return;
+    }
+
+    setLoading(true);
+    setError(null);
+    setLockImpact(null);
+
+    try {
+      const result = await invoke<LockImpact>('analyze_lock_impact', {
+        connectionString: connections.target,
+        migrationPath: effectivePath,
+      });
+      setLockImpact(result);
+      addLog(`Lock impact: ${result.risk} risk over ${result.statements.length} locking statements`);
+    } catch (err) {
+      setError(`Lock analysis failed: ${err}`);
     } finally {
       setLoading(false);
     }
@@ -90,52 +247,65 @@ function ApplyPage({
     setApplySuccess(false);
     setMultiResults([]);
 
-    const results: MigrationApplyResult[] = [];
+    const id = startOperation();
+    addLog(`\n--- Applying to ${connectedTargets.length} targets, ${parallelism} at a time ---`);
 
-    for (const target of connectedTargets) {
-      setApplyingToId(target.id);
-      addLog(`\n--- Applying to: ${target.name} ---`);
-      
-      const result: MigrationApplyResult = {
-        connectionId: target.id,
-        connectionName: target.name,
-        success: false,
-        logs: [],
-      };
-
-      try {
-        const resultLogs = await invoke<string[]>('apply_migration', {
-          connectionString: target.connectionString,
-          migrationPath: effectivePath,
-        });
-
-        resultLogs.forEach((log: string) => {
-          addLog(log);
-          result.logs.push(log);
-        });
-        
-        result.success = true;
-        addLog(`✅ ${target.name}: Migration applied successfully!`);
-      } catch (err) {
-        result.success = false;
-        result.error = String(err);
-        addLog(`❌ ${target.name}: Failed - ${err}`);
-      }
+    let results: MigrationApplyResult[] = [];
+    try {
+      const outcomes = await invoke<TargetResult[]>('apply_migration_multi', {
+        targets: connectedTargets.map((target) => ({ name: target.name, connection_string: target.connectionString })),
+        migrationPath: effectivePath,
+        options: applyOptions,
+        parallelism,
+        operationId: id,
+      });
 
-      results.push(result);
+      results = outcomes.map((outcome, i) => {
+        const target = connectedTargets[i];
+        const logs = outcome.run?.logs ?? [];
+        addLog(`\n--- ${target.name} ---`);
+        logs.forEach((log) => addLog(log));
+        if (outcome.run) {
+          logFailedStatements(outcome.run, `${target.name}: `);
+        }
+        if (outcome.status === 'applied') {
+          addLog(`✅ ${target.name}: Migration applied successfully!`);
+        } else if (outcome.status === 'failed') {
+          addLog(`❌ ${target.name}: Failed - ${outcome.error}`);
+        } else {
+          addLog(`⏭ ${target.name}: Skipped`);
+        }
+        return {
+          connectionId: target.id,
+          connectionName: target.name,
+          success: outcome.status === 'applied',
+          skipped: outcome.status === 'skipped',
+          error: outcome.error ?? undefined,
+          logs,
+        };
+      });
+    } catch (err) {
+      setError(`Migration failed: ${err}`);
+      addLog(`❌ Error: ${err}`);
     }
 
     setApplyingToId(null);
+    setOperationId(null);
     setMultiResults(results);
     
     const successCount = results.filter(r => r.success).length;
-    const failCount = results.filter(r => !r.success).length;
+    const failCount = results.filter(r => !r.success && !r.skipped).length;
+    const skipCount = results.filter(r => r.skipped).length;
     
-    if (failCount === 0) {
+    if (results.length === 0) {
+      // The rollout did not start
+    } else if (cancelRequested.current) {
+      setError(`Cancelled after applying to ${successCount} of ${connectedTargets.length} targets.`);
+    } else if (failCount > 0) {
+      setError(`${failCount} of ${results.length} migrations failed; ${skipCount} targets were skipped.`);
+    } else if (skipCount === 0) {
       setApplySuccess(true);
       addLog(`\n✅ All ${successCount} migrations applied successfully!`);
-    } else {
-      setError(`${failCount} of ${results.length} migrations failed.`);
     }
     
     setLoading(false);
@@ -154,17 +324,21 @@ function ApplyPage({
     addLog(`\n--- Applying to: ${target.name} ---`);
 
     try {
-      const resultLogs = await invoke<string[]>('apply_migration', {
+      const run = await invoke<MigrationRun>('apply_migration', {
         connectionString: target.connectionString,
         migrationPath: effectivePath,
+        options: applyOptions,
+        operationId: startOperation(),
       });
 
-      resultLogs.forEach((log: string) => addLog(log));
+      run.logs.forEach((log: string) => addLog(log));
+      logFailedStatements(run, `${target.name}: `);
       addLog(`✅ ${target.name}: Migration applied successfully!`);
     } catch (err) {
       setError(`Migration failed for ${target.name}: ${err}`);
       addLog(`❌ ${target.name}: Failed - ${err}`);
     } finally {
+      setOperationId(null);
       setApplyingToId(null);
     }
   };
@@ -279,6 +453,38 @@ function ApplyPage({
       )}
 
       <div className="action-bar">
+        <label title="Run each statement in a savepoint and apply the rest when one fails, e.g. to re-run a mostly applied migration">
+          <input
+            type="checkbox"
+            checked={continueOnError}
+            onChange={(e) => setContinueOnError(e.target.checked)}
+            disabled={loading}
+          />
+          Continue on error
+        </label>
+        <select
+          value={backupMode}
+          onChange={(e) => setBackupMode(e.target.value as 'none' | 'full' | 'schema')}
+          disabled={loading}
+          title="Run pg_dump on the target before applying a migration marked dangerous"
+        >
+          <option value="none">No backup</option>
+          <option value="full">Back up dangerous migrations</option>
+          <option value="schema">Back up schema of dangerous migrations</option>
+        </select>
+        {applyMode === 'multi' && (
+          <label title="Targets applied at once; after a failure, no further target starts">
+            <input
+              type="number"
+              min={1}
+              value={parallelism}
+              onChange={(e) => setParallelism(Math.max(1, Number(e.target.value) || 1))}
+              disabled={loading}
+              style={{ width: '4rem' }}
+            />
+            {' '}at a time
+          </label>
+        )}
         {applyMode === 'single' ? (
           <button
             className="btn btn-danger"
@@ -311,6 +517,49 @@ function ApplyPage({
           </button>
         )}
 
+        {applyMode === 'single' && (
+          <button
+            className="btn btn-secondary"
+            onClick={planMigration}
+            disabled={loading || !effectivePath || !connections.targetConnected}
+            title="EXPLAIN the backfills and other data changes without running them"
+          >
+            🔎 Plan Data Changes
+          </button>
+        )}
+
+        {applyMode === 'single' && (
+          <button
+            className="btn btn-secondary"
+            onClick={analyzeLocks}
+            disabled={loading || !effectivePath || !connections.targetConnected}
+            title="Show the locks each statement takes and the sessions it would wait on"
+          >
+            🔒 Lock Impact
+          </button>
+        )}
+
+        {applyMode === 'single' && (
+          <button
+            className="btn btn-secondary"
+            onClick={rollbackMigration}
+            disabled={loading || !effectivePath || !connections.targetConnected}
+            title="Run the migration's down.sql on the target database"
+          >
+            ↩️ Roll Back
+          </button>
+        )}
+
+        {operationId && (
+          <button
+            className="btn btn-secondary"
+            onClick={cancelOperation}
+            title="Cancel the running statement and roll back its transaction"
+          >
+            ⏹ Cancel
+          </button>
+        )}
+
         <button
           className="btn btn-secondary"
           onClick={clearLogs}
@@ -326,6 +575,123 @@ function ApplyPage({
         )}
       </div>
 
+      {statementProgress && (loading || applyingToId !== null) && (
+        <div className="card" style={{ marginBottom: '1rem' }}>
+          <h3 className="card-title" style={{ marginBottom: '0.5rem' }}>
+            ⏳ Statement {statementProgress.statement} of {statementProgress.statements_total}
+            {' '}(line {statementProgress.line})
+          </h3>
+          <pre style={{ color: 'var(--text-secondary)', fontSize: '0.75rem', whiteSpace: 'pre-wrap', maxHeight: '6rem', overflow: 'hidden' }}>
+            {statementProgress.sql}
+          </pre>
+          <div style={{ background: 'var(--bg-primary)', borderRadius: '4px', height: '8px', marginTop: '0.5rem' }}>
+            <div
+              style={{
+                width: `${(100 * (statementProgress.statement - (statementProgress.status === null ? 1 : 0))) / statementProgress.statements_total}%`,
+                background: 'var(--accent-primary)',
+                borderRadius: '4px',
+                height: '100%',
+              }}
+            />
+          </div>
+        </div>
+      )}
+
+      {progress && (loading || applyingToId !== null) && (
+        <div className="card" style={{ marginBottom: '1rem' }}>
+          <h3 className="card-title" style={{ marginBottom: '0.5rem' }}>
+            ⏳ {progress.command ?? progress.operation} {progress.index_name ?? progress.relation ?? ''}
+          </h3>
+          <p style={{ color: 'var(--text-secondary)', fontSize: '0.875rem' }}>
+            {progress.phase}
+            {progress.percent !== null && ` — ${progress.percent.toFixed(1)}%`}
+          </p>
+          {progress.percent !== null && (
+            <div style={{ background: 'var(--bg-primary)', borderRadius: '4px', height: '8px', marginTop: '0.5rem' }}>
+              <div
+                style={{
+                  width: `${progress.percent}%`,
+                  background: 'var(--accent-primary)',
+                  borderRadius: '4px',
+                  height: '100%',
+                }}
+              />
+            </div>
+          )}
+        </div>
+      )}
+
+      {plan && applyMode === 'single' && (
+        <div className="card" style={{ marginBottom: '1rem' }}>
+          <h3 className="card-title" style={{ marginBottom: '0.75rem' }}>
+            🔎 Data Changes ({plan.statements.length} of {plan.total_statements} statements)
+          </h3>
+          {plan.statements.length === 0 && (
+            <p style={{ color: 'var(--text-secondary)', fontSize: '0.875rem' }}>
+              The migration does not modify rows.
+            </p>
+          )}
+          {plan.statements.map((statement) => (
+            <div key={statement.position} style={{ marginBottom: '0.75rem' }}>
+              <div style={{ fontSize: '0.875rem' }}>
+                #{statement.position} {statement.operation}
+                {statement.estimated_rows !== null && ` — ~${Math.round(statement.estimated_rows).toLocaleString()} rows`}
+                {statement.unbounded && (
+                  <span style={{ color: 'var(--error)' }}> ⚠️ no WHERE clause</span>
+                )}
+              </div>
+              {statement.error && (
+                <div style={{ color: 'var(--warning)', fontSize: '0.75rem' }}>{statement.error}</div>
+              )}
+              <pre style={{ fontSize: '0.75rem', whiteSpace: 'pre-wrap', color: 'var(--text-secondary)' }}>
+                {statement.statement}
+              </pre>
+            </div>
+          ))}
+        </div>
+      )}
+
+      {lockImpact && applyMode === 'single' && (
+        <div className="card" style={{ marginBottom: '1rem' }}>
+          <h3 className="card-title" style={{ marginBottom: '0.75rem' }}>
+            🔒 Lock Impact — {lockImpact.risk} risk
+          </h3>
+          {lockImpact.statements.length === 0 && (
+            <p style={{ color: 'var(--text-secondary)', fontSize: '0.875rem' }}>
+              None of the {lockImpact.total_statements} statements lock a table.
+            </p>
+          )}
+          {lockImpact.statements.map((statement) => (
+            <div key={statement.position} style={{ marginBottom: '0.75rem' }}>
+              {statement.locks.map((lock) => (
+                <div key={lock.table} style={{ fontSize: '0.875rem' }}>
+                  #{statement.position} {lock.level.replace(/_/g, ' ').toUpperCase()} on {lock.table}
+                  {lock.exists && lock.blocks_reads && (
+                    <span style={{ color: 'var(--error)' }}> — blocks reads and writes</span>
+                  )}
+                  {lock.exists && !lock.blocks_reads && lock.blocks_writes && (
+                    <span style={{ color: 'var(--warning)' }}> — blocks writes</span>
+                  )}
+                  {lock.conflicting_sessions.map((session) => (
+                    <div key={session.pid} style={{ color: 'var(--error)', fontSize: '0.75rem', marginLeft: '1rem' }}>
+                      ⚠️ Waits on pid {session.pid} ({session.mode}{session.granted ? '' : ', waiting'}
+                      {session.running_seconds !== null && `, ${Math.round(session.running_seconds)}s`}
+                      {session.application_name && `, ${session.application_name}`}): {session.query}
+                    </div>
+                  ))}
+                </div>
+              ))}
+              {statement.note && (
+                <div style={{ color: 'var(--warning)', fontSize: '0.75rem' }}>#{statement.position} {statement.note}</div>
+              )}
+              <pre style={{ fontSize: '0.75rem', whiteSpace: 'pre-wrap', color: 'var(--text-secondary)' }}>
+                {statement.statement}
+              </pre>
+            </div>
+          ))}
+        </div>
+      )}
+
       {/* Multi-target results summary */}
       {multiResults.length > 0 && (
         <div className="card" style={{ marginBottom: '1rem' }}>
@@ -336,11 +702,11 @@ function ApplyPage({
                 key={result.connectionId} 
                 className="multi-connection-item"
                 style={{ 
-                  borderColor: result.success ? 'var(--success)' : 'var(--error)',
-                  background: result.success ? 'rgba(16, 185, 129, 0.1)' : 'rgba(239, 68, 68, 0.1)'
+                  borderColor: result.success ? 'var(--success)' : result.skipped ? 'var(--border-color)' : 'var(--error)',
+                  background: result.success ? 'rgba(16, 185, 129, 0.1)' : result.skipped ? 'transparent' : 'rgba(239, 68, 68, 0.1)'
                 }}
               >
-                <span style={{ fontSize: '1.25rem' }}>{result.success ? '✅' : '❌'}</span>
+                <span style={{ fontSize: '1.25rem' }}>{result.success ? '✅' : result.skipped ? '⏭' : '❌'}</span>
                 <span className="multi-conn-name">{result.connectionName}</span>
                 {result.error && (
                   <span style={{ color: 'var(--error)', fontSize: '0.75rem' }}>{result.error}</span>
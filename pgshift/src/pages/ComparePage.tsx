@@ -1,6 +1,7 @@
-import { useState } from 'react';
+import { useEffect, useRef, useState } from 'react';
 import { invoke } from '@tauri-apps/api/core';
-import { ConnectionState, SchemaModel, DiffReport, DiffItem, MultiConnectionState } from '../types';
+import { listen } from '@tauri-apps/api/event';
+import { ConnectionState, SchemaModel, DiffReport, DiffItem, DangerAcknowledgment, EnumReorderStrategy, IgnoreConfig, DiffOptions, MultiConnectionState, IntrospectProgress } from '../types';
 import DiffTree from '../components/DiffTree';
 import DiffDetails from '../components/DiffDetails';
 import SqlPreview from '../components/SqlPreview';
@@ -36,6 +37,11 @@ function parseDbName(connStr: string): string {
   }
 }
 
+// Named environments from pgshift.toml are passed in place of a connection string
+function isEnvironmentName(connStr: string): boolean {
+  return connStr.trim() !== '' && !connStr.includes('://') && !connStr.includes('=');
+}
+
 function parseDbDetails(connStr: string): { host: string; port: string; database: string; user: string } {
   try {
     const url = new URL(connStr);
@@ -50,6 +56,15 @@ function parseDbDetails(connStr: string): { host: string; port: string; database
   }
 }
 
+// Object types passed to the diff command for each comparison scope
+const COMPARE_SCOPES = {
+  all: { label: 'All objects', includeTypes: [], excludeTypes: [] },
+  structure: { label: 'Structure only', includeTypes: ['table', 'column', 'constraint', 'enum'], excludeTypes: [] },
+  indexes: { label: 'Indexes only', includeTypes: ['index'], excludeTypes: [] },
+} as const;
+
+type CompareScope = keyof typeof COMPARE_SCOPES;
+
 function ComparePage({
   connections,
   multiConnections,
@@ -70,8 +85,58 @@ function ComparePage({
   addLog,
 }: ComparePageProps) {
   const [migrationName, setMigrationName] = useState('');
+  const [traceComments, setTraceComments] = useState(false);
+  const [batchAlterTable, setBatchAlterTable] = useState(false);
+  const [concurrentIndexes, setConcurrentIndexes] = useState(false);
+  const [idempotent, setIdempotent] = useState(false);
   const [generatingMigration, setGeneratingMigration] = useState(false);
   const [compareMode, setCompareMode] = useState<'single' | 'multi'>('single');
+  const [compareScope, setCompareScope] = useState<CompareScope>('all');
+  const [validateSeparately, setValidateSeparately] = useState(false);
+  const [safeMigrations, setSafeMigrations] = useState(false);
+  const [introspectProgress, setIntrospectProgress] = useState<IntrospectProgress | null>(null);
+  const [operationId, setOperationId] = useState<string | null>(null);
+  const cancelRequested = useRef(false);
+
+  // Large databases report the tables introspected so far
+  useEffect(() => {
+    const unlisten = listen<IntrospectProgress>('introspect-progress', (event) => setIntrospectProgress(event.payload));
+    return () => {
+      unlisten.then((stop) => stop());
+    };
+  }, []);
+
+  useEffect(() => {
+    if (!loading) {
+      setIntrospectProgress(null);
+    }
+  }, [loading]);
+
+  // Id under which the running comparison's introspections and diff can be cancelled
+  const startOperation = () => {
+    const id = `compare-${Date.now()}`;
+    cancelRequested.current = false;
+    setOperationId(id);
+    return id;
+  };
+
+  // Stops a comparison between steps, when no cancellable command is running
+  const checkCancelled = () => {
+    if (cancelRequested.current) {
+      throw new Error('Operation cancelled');
+    }
+  };
+
+  const cancelOperation = async () => {
+    if (!operationId) return;
+    cancelRequested.current = true;
+    addLog('⏹ Cancelling...');
+    await invoke<boolean>('cancel_operation', { operationId });
+  };
+
+  const tablesProgress = introspectProgress && introspectProgress.tables_done < introspectProgress.tables_total
+    ? ` (table ${introspectProgress.tables_done}/${introspectProgress.tables_total})`
+    : '';
 
   // Check if we have multi-connections
   const hasMultiSources = multiConnections.sources.filter(c => c.connected).length > 0;
@@ -97,6 +162,13 @@ function ComparePage({
       tables: [],
       enums: [],
       indexes: [],
+      privileges: [],
+      functions: [],
+      foreign_servers: [],
+      user_mappings: [],
+      foreign_tables: [],
+      publications: [],
+      subscriptions: [],
     };
 
     const tableMap = new Map<string, SchemaModel['tables'][0]>();
@@ -124,6 +196,44 @@ function ComparePage({
           indexMap.set(index.name, index);
         }
       }
+
+      merged.privileges.push(...(source.schema.privileges || []));
+
+      for (const fn of source.schema.functions || []) {
+        if (!merged.functions.some(f => f.name === fn.name && f.arguments === fn.arguments)) {
+          merged.functions.push(fn);
+        }
+      }
+
+      for (const server of source.schema.foreign_servers || []) {
+        if (!merged.foreign_servers.some(s => s.name === server.name)) {
+          merged.foreign_servers.push(server);
+        }
+      }
+
+      for (const mapping of source.schema.user_mappings || []) {
+        if (!merged.user_mappings.some(m => m.server === mapping.server && m.user === mapping.user)) {
+          merged.user_mappings.push(mapping);
+        }
+      }
+
+      for (const table of source.schema.foreign_tables || []) {
+        if (!merged.foreign_tables.some(t => t.name === table.name)) {
+          merged.foreign_tables.push(table);
+        }
+      }
+
+      for (const publication of source.schema.publications || []) {
+        if (!merged.publications.some(p => p.name === publication.name)) {
+          merged.publications.push(publication);
+        }
+      }
+
+      for (const subscription of source.schema.subscriptions || []) {
+        if (!merged.subscriptions.some(s => s.name === subscription.name)) {
+          merged.subscriptions.push(subscription);
+        }
+      }
     }
 
     merged.tables = Array.from(tableMap.values());
@@ -133,6 +243,25 @@ function ComparePage({
     return merged;
   };
 
+  const diffOptions = (ignore: IgnoreConfig): DiffOptions => ({
+    ignore,
+    validate_constraints_separately: validateSeparately,
+    safe_migrations: safeMigrations,
+  });
+
+  // Project ignore rules from pgshift.ignore.json in the migrations directory,
+  // and those of the target's environment when it is one
+  const loadIgnoreConfig = async () => {
+    const basePath = await invoke<string>('get_migrations_dir');
+    const environment = isEnvironmentName(connections.target) ? connections.target.trim() : null;
+    const ignore = await invoke<IgnoreConfig>('get_ignore_config', { basePath, environment });
+    const rules = (ignore.tables?.length ?? 0) + (ignore.columns?.length ?? 0) + (ignore.object_types?.length ?? 0);
+    if (rules > 0) {
+      addLog(`Applying ${rules} ignore rules`);
+    }
+    return ignore;
+  };
+
   const runMultiComparison = async () => {
     if (!canMultiCompare) {
       setError('Please add and connect source databases in Multi Mode first.');
@@ -158,21 +287,29 @@ function ComparePage({
         ? multiConnections.targets[0].connectionString 
         : connections.target;
       
+      const operationId = startOperation();
       addLog('Introspecting target database...');
       const target = await invoke<SchemaModel>('introspect', {
         connectionString: targetConnStr,
+        operationId,
       });
+      checkCancelled();
       setTargetSchema(target);
       addLog(`Found ${target.tables.length} tables in target`);
 
       addLog('Computing schema differences...');
-      const report = await invoke<DiffReport>('diff', { source: merged, target });
+      const ignore = await loadIgnoreConfig();
+      const { includeTypes, excludeTypes } = COMPARE_SCOPES[compareScope];
+      const report = await invoke<DiffReport>('diff', {
+        source: merged, target, options: diffOptions(ignore), includeTypes, excludeTypes, operationId,
+      });
       setDiffReport(report);
       addLog(`Found ${report.items.length} differences`);
 
     } catch (err) {
       setError(`Multi-comparison failed: ${err}`);
     } finally {
+      setOperationId(null);
       setLoading(false);
     }
   };
@@ -189,22 +326,64 @@ function ComparePage({
     setSelectedDiffItem(null);
 
     try {
+      const sameDatabase = await invoke<boolean>('check_same_database', {
+        sourceConnection: connections.source,
+        targetConnection: connections.target,
+      });
+      if (sameDatabase) {
+        throw new Error('Source and target are the same database. Select a different target.');
+      }
+
+      const operationId = startOperation();
       addLog('Introspecting source database...');
       const source = await invoke<SchemaModel>('introspect', {
         connectionString: connections.source,
+        operationId,
       });
+      checkCancelled();
       setSourceSchema(source);
       addLog(`Found ${source.tables.length} tables in source`);
 
       addLog('Introspecting target database...');
       const target = await invoke<SchemaModel>('introspect', {
         connectionString: connections.target,
+        operationId,
       });
+      checkCancelled();
       setTargetSchema(target);
       addLog(`Found ${target.tables.length} tables in target`);
 
       addLog('Computing schema differences...');
-      const report = await invoke<DiffReport>('diff', { source, target });
+      const ignore = await loadIgnoreConfig();
+      const { includeTypes, excludeTypes } = COMPARE_SCOPES[compareScope];
+      const report = await invoke<DiffReport>('diff', {
+        source, target, options: diffOptions(ignore), includeTypes, excludeTypes, operationId,
+      });
+      checkCancelled();
+
+      addLog('Checking encodings, locales and collation versions...');
+      const localeWarnings = await invoke<string[]>('preflight_locales', {
+        sourceConnection: connections.source,
+        targetConnection: connections.target,
+      });
+      localeWarnings.forEach((warning) => addLog(`⚠️ ${warning}`));
+      report.warnings = [...(report.warnings || []), ...localeWarnings];
+
+      const dropsData = report.items.some((item) =>
+        item.kind === 'removed' && (item.object_type === 'table' || item.object_type === 'column'));
+      if (dropsData) {
+        addLog('Counting rows of dropped tables and columns...');
+        try {
+          const counted = await invoke<DiffReport>('estimate_data_loss', {
+            connectionString: connections.target,
+            report,
+          });
+          report.items = counted.items;
+        } catch (err) {
+          addLog(`⚠️ Could not count dropped rows: ${err}`);
+        }
+      }
+
       setDiffReport(report);
       addLog(`Found ${report.items.length} differences`);
 
@@ -215,6 +394,7 @@ function ComparePage({
       setError(`Comparison failed: ${err}`);
       addLog(`Error: ${err}`);
     } finally {
+      setOperationId(null);
       setLoading(false);
     }
   };
@@ -241,6 +421,7 @@ function ComparePage({
         report: diffReport,
         name: migrationName.trim(),
         basePath,
+        options: { trace_comments: traceComments, batch_alter_table: batchAlterTable, concurrent_indexes: concurrentIndexes, idempotent },
       });
 
       setMigrationPath(path);
@@ -254,6 +435,65 @@ function ComparePage({
     }
   };
 
+  const generateExpandContract = async (item: DiffItem) => {
+    const source = compareMode === 'multi' ? mergedSchema : sourceSchema;
+    if (!source || !targetSchema) return;
+    if (!migrationName.trim()) {
+      setError('Please enter a migration name.');
+      return;
+    }
+    const dot = item.object_name.lastIndexOf('.');
+    const table = item.object_name.slice(0, dot);
+    const column = item.object_name.slice(dot + 1);
+
+    setGeneratingMigration(true);
+    setError(null);
+    try {
+      const basePath = await invoke<string>('get_migrations_dir');
+      const folders = await invoke<string[]>('render_dual_write_migration', {
+        source, target: targetSchema, table, column,
+        name: migrationName.trim(),
+        basePath,
+        options: { expand_contract: true },
+      });
+      folders.forEach((folder) => addLog(`Migration generated at: ${folder}`));
+      setMigrationPath(folders[0]);
+    } catch (err) {
+      setError(`Failed to generate migration: ${err}`);
+      addLog(`Error: ${err}`);
+    } finally {
+      setGeneratingMigration(false);
+    }
+  };
+
+  const acknowledgeItem = (item: DiffItem, acknowledgment: DangerAcknowledgment | null) => {
+    if (!diffReport) return;
+    const updated = { ...item, acknowledgment };
+    setDiffReport({
+      ...diffReport,
+      items: diffReport.items.map((i) => (i.id === item.id ? updated : i)),
+    });
+    setSelectedDiffItem(updated);
+  };
+
+  const resolveReorder = (item: DiffItem, strategy: EnumReorderStrategy) => {
+    if (!diffReport || !item.enum_reorder) return;
+    const resolution = item.enum_reorder.resolutions.find((r) => r.strategy === strategy);
+    if (!resolution) return;
+    const updated = {
+      ...item,
+      generated_up_sql: resolution.up_sql,
+      generated_down_sql: resolution.down_sql,
+      rewrites_table: resolution.rewrites_table,
+      enum_reorder: { ...item.enum_reorder, strategy },
+    };
+    setDiffReport({
+      ...diffReport,
+      items: diffReport.items.map((i) => (i.id === item.id ? updated : i)),
+    });
+    setSelectedDiffItem(updated);
+  };
+
   const getDiffStats = () => {
     if (!diffReport) return { added: 0, removed: 0, modified: 0, dangerous: 0 };
     
@@ -311,6 +551,34 @@ function ComparePage({
       )}
 
       <div className="action-bar">
+        <select
+          value={compareScope}
+          onChange={(e) => setCompareScope(e.target.value as CompareScope)}
+          disabled={loading}
+        >
+          {Object.entries(COMPARE_SCOPES).map(([value, scope]) => (
+            <option key={value} value={value}>{scope.label}</option>
+          ))}
+        </select>
+        <label title="Add foreign keys to existing tables NOT VALID and validate them afterwards, without blocking writes">
+          <input
+            type="checkbox"
+            checked={validateSeparately}
+            onChange={(e) => setValidateSeparately(e.target.checked)}
+            disabled={loading}
+          />
+          Validate foreign keys separately
+        </label>
+        <label title="Add NOT NULL columns without a default in steps: nullable, backfilled in batches, then SET NOT NULL">
+          <input
+            type="checkbox"
+            checked={safeMigrations}
+            onChange={(e) => setSafeMigrations(e.target.checked)}
+            disabled={loading}
+          />
+          Safe migrations
+        </label>
+
         {compareMode === 'single' ? (
           <button
             className="btn btn-primary"
@@ -320,7 +588,7 @@ function ComparePage({
             {loading ? (
               <>
                 <span className="spinner"></span>
-                Comparing...
+                Comparing...{tablesProgress}
               </>
             ) : (
               '🔍 Compare Schemas'
@@ -335,7 +603,7 @@ function ComparePage({
             {loading ? (
               <>
                 <span className="spinner"></span>
-                Merging & Comparing...
+                Merging & Comparing...{tablesProgress}
               </>
             ) : (
               '🔀 Merge & Compare'
@@ -343,6 +611,12 @@ function ComparePage({
           </button>
         )}
 
+        {operationId && (
+          <button className="btn btn-secondary" onClick={cancelOperation}>
+            ⏹ Cancel
+          </button>
+        )}
+
         {compareMode === 'single' && !canCompare && (
           <span style={{ color: 'var(--text-secondary)', fontSize: '0.875rem', alignSelf: 'center' }}>
             ⚠️ Connect to both databases on the Connections page first
@@ -475,6 +749,17 @@ function ComparePage({
             </div>
           </div>
 
+          {diffReport.warnings && diffReport.warnings.length > 0 && (
+            <div className="warning-box">
+              <h4>⚠️ Check before generating a migration</h4>
+              <ul style={{ marginLeft: '1.5rem' }}>
+                {diffReport.warnings.map((warning, i) => (
+                  <li key={i}>{warning}</li>
+                ))}
+              </ul>
+            </div>
+          )}
+
           {/* Statistics */}
           <div className="stats">
             <div className="stat">
@@ -506,6 +791,38 @@ function ComparePage({
                 placeholder="Migration name (e.g., add_users_table)"
                 style={{ flex: 1 }}
               />
+              <label title="End each statement with /* migration=NAME item=ID */ for pg_stat_statements and server logs">
+                <input
+                  type="checkbox"
+                  checked={traceComments}
+                  onChange={(e) => setTraceComments(e.target.checked)}
+                />
+                Trace comments
+              </label>
+              <label title="Combine column and constraint changes of a table into one ALTER TABLE">
+                <input
+                  type="checkbox"
+                  checked={batchAlterTable}
+                  onChange={(e) => setBatchAlterTable(e.target.checked)}
+                />
+                Batch ALTER TABLE
+              </label>
+              <label title="Create indexes on existing tables with CREATE INDEX CONCURRENTLY, outside the transaction">
+                <input
+                  type="checkbox"
+                  checked={concurrentIndexes}
+                  onChange={(e) => setConcurrentIndexes(e.target.checked)}
+                />
+                Concurrent indexes
+              </label>
+              <label title="Skip statements whose work is already done, so the migration can be run again">
+                <input
+                  type="checkbox"
+                  checked={idempotent}
+                  onChange={(e) => setIdempotent(e.target.checked)}
+                />
+                Idempotent
+              </label>
               <button
                 className="btn btn-success"
                 onClick={generateMigration}
@@ -542,7 +859,12 @@ function ComparePage({
             {selectedDiffItem ? (
               <>
                 <div className="card" style={{ marginBottom: '1rem' }}>
-                  <DiffDetails item={selectedDiffItem} />
+                  <DiffDetails
+                    item={selectedDiffItem}
+                    onAcknowledge={acknowledgeItem}
+                    onResolveReorder={resolveReorder}
+                    onExpandContract={generateExpandContract}
+                  />
                 </div>
                 <div className="card" style={{ flex: 1, display: 'flex', flexDirection: 'column', overflow: 'hidden' }}>
                   <SqlPreview item={selectedDiffItem} />
@@ -313,12 +313,24 @@ function MigrationsPage({ diffReport, setError }: MigrationsPageProps) {
               {/* Expanded Content */}
               {selectedMigration?.name === migration.name && (
                 <div className="migration-content" style={{ marginTop: '1rem' }}>
+                  {migration.before_sql && (
+                    <div className="sql-section" style={{ marginBottom: '1rem' }}>
+                      <h5>🪝 before hook</h5>
+                      <pre className="sql-preview">{migration.before_sql}</pre>
+                    </div>
+                  )}
                   {migration.up_sql && (
                     <div className="sql-section">
                       <h5>⬆️ up.sql</h5>
                       <pre className="sql-preview">{migration.up_sql}</pre>
                     </div>
                   )}
+                  {migration.after_sql && (
+                    <div className="sql-section" style={{ marginTop: '1rem' }}>
+                      <h5>🪝 after hook</h5>
+                      <pre className="sql-preview">{migration.after_sql}</pre>
+                    </div>
+                  )}
                   {migration.down_sql && (
                     <div className="sql-section" style={{ marginTop: '1rem' }}>
                       <h5>⬇️ down.sql</h5>
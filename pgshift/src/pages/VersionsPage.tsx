@@ -1,7 +1,7 @@
 import { useState, useEffect, useCallback } from 'react';
 import { invoke } from '@tauri-apps/api/core';
 import { open } from '@tauri-apps/plugin-dialog';
-import { SchemaVersion, ConnectionState, DiffReport, VersionDiff } from '../types';
+import { SchemaVersion, ConnectionState, DiffReport, VersionDiff, VersionQuery } from '../types';
 
 interface VersionsPageProps {
   connections: ConnectionState;
@@ -12,6 +12,7 @@ function VersionsPage({ connections, setError }: VersionsPageProps) {
   const [versions, setVersions] = useState<SchemaVersion[]>([]);
   const [loading, setLoading] = useState(false);
   const [basePath, setBasePath] = useState('');
+  const [search, setSearch] = useState('');
   
   // New version form
   const [showNewVersion, setShowNewVersion] = useState(false);
@@ -35,7 +36,8 @@ function VersionsPage({ connections, setError }: VersionsPageProps) {
     
     setLoading(true);
     try {
-      const result = await invoke<SchemaVersion[]>('list_schema_versions', { basePath });
+      const query: VersionQuery = { text: search.trim() || null };
+      const result = await invoke<SchemaVersion[]>('search_schema_versions', { basePath, query });
       setVersions(result);
       setError(null);
     } catch (err) {
@@ -43,7 +45,7 @@ function VersionsPage({ connections, setError }: VersionsPageProps) {
     } finally {
       setLoading(false);
     }
-  }, [basePath, setError]);
+  }, [basePath, search, setError]);
 
   useEffect(() => {
     // Get default migrations directory as base path
@@ -208,6 +210,16 @@ function VersionsPage({ connections, setError }: VersionsPageProps) {
             🔄 Refresh
           </button>
         </div>
+        <div className="path-selector" style={{ marginTop: '0.5rem' }}>
+          <label>Search:</label>
+          <input
+            type="text"
+            className="input"
+            value={search}
+            onChange={(e) => setSearch(e.target.value)}
+            placeholder="Filter by name or description"
+          />
+        </div>
       </div>
 
       {/* Actions */}
@@ -6,6 +6,11 @@ export interface Column {
   is_nullable: boolean;
   default_value: string | null;
   ordinal_position: number;
+  generation_expression: string | null;
+  comment: string | null;
+  inherited: boolean;
+  storage: string | null;
+  statistics_target: number | null;
 }
 
 export interface Constraint {
@@ -14,11 +19,37 @@ export interface Constraint {
   columns: string[];
 }
 
+export interface ForeignKey {
+  name: string;
+  columns: string[];
+  referenced_table: string;
+  referenced_columns: string[];
+  match_type: string;
+  on_delete: string;
+  on_update: string;
+  deferrable: boolean;
+  initially_deferred: boolean;
+  validated: boolean;
+}
+
 export interface Index {
   name: string;
   columns: string[];
   is_unique: boolean;
   index_type: string;
+  predicate: string | null;
+  // Parallel to columns; missing entries mean ascending, nulls last
+  column_orders?: IndexColumnOrder[];
+  include_columns?: string[];
+  // Parallel to columns; null means the default operator class
+  column_opclasses?: (string | null)[];
+  // e.g. "fillfactor=70"
+  storage_parameters?: string[];
+}
+
+export interface IndexColumnOrder {
+  descending: boolean;
+  nulls_first: boolean;
 }
 
 export interface EnumType {
@@ -26,21 +57,180 @@ export interface EnumType {
   values: string[];
 }
 
+export interface Policy {
+  name: string;
+  command: string;
+  permissive: boolean;
+  roles: string[];
+  using_expr: string | null;
+  check_expr: string | null;
+}
+
+export interface Trigger {
+  name: string;
+  definition: string;
+}
+
 export interface Table {
   name: string;
   columns: Column[];
   primary_key: Constraint | null;
   unique_constraints: Constraint[];
   indexes: Index[];
+  rls_enabled: boolean;
+  rls_forced: boolean;
+  policies: Policy[];
+  triggers: Trigger[];
+  foreign_keys: ForeignKey[];
+  comment: string | null;
+  inherits: string[];
+  unlogged: boolean;
+  storage_parameters: string[];
+}
+
+export interface Privilege {
+  object_type: 'table' | 'column' | 'sequence' | 'function';
+  object_name: string;
+  column_name: string | null;
+  arguments: string | null;
+  grantee: string;
+  privilege_type: string;
+  is_grantable: boolean;
+}
+
+export interface PgFunction {
+  name: string;
+  arguments: string;
+  return_type: string | null;
+  language: string;
+  is_procedure: boolean;
+  definition: string;
+}
+
+export interface ForeignServer {
+  name: string;
+  wrapper: string;
+  server_type: string | null;
+  version: string | null;
+  options: string[];
+}
+
+export interface UserMapping {
+  server: string;
+  user: string;
+  options: string[];
+}
+
+export interface ForeignTable {
+  name: string;
+  server: string;
+  columns: Column[];
+  options: string[];
+}
+
+export interface Publication {
+  name: string;
+  all_tables: boolean;
+  tables: string[];
+  publish: string[];
+  publish_via_partition_root: boolean;
+}
+
+export interface Subscription {
+  name: string;
+  connection: string;
+  publications: string[];
+  enabled: boolean;
+  slot_name: string | null;
+}
+
+// Object of a type registered by a backend extension, e.g. a pg_cron job
+export interface CustomObject {
+  object_type: string;
+  name: string;
+  definition: unknown;
 }
 
 export interface SchemaModel {
   tables: Table[];
   indexes: Index[];
   enums: EnumType[];
+  privileges: Privilege[];
+  functions: PgFunction[];
+  foreign_servers: ForeignServer[];
+  user_mappings: UserMapping[];
+  foreign_tables: ForeignTable[];
+  publications: Publication[];
+  subscriptions: Subscription[];
+  custom_objects?: CustomObject[];
+  unreadable_object_types?: string[]; // e.g. subscription, when pg_subscription needs superuser
+}
+
+export type DiffKind = 'added' | 'removed' | 'modified' | 'reordered' | 'renamed';
+
+// Format of export_diff_report
+export type ReportFormat = 'markdown' | 'html';
+
+// Format of export_liquibase_changelog
+export type ChangelogFormat = 'xml' | 'yaml';
+
+export interface DiffOptions {
+  include_privileges?: boolean;
+  // Functions considered safe as column defaults; omit to use the built-in list
+  safe_default_functions?: string[] | null;
+  // Filled in from a live target when omitted; generated SQL adapts to it
+  target_major_version?: number | null;
+  // Compare function/trigger bodies as exact text instead of normalized hash
+  strict_body_comparison?: boolean;
+  // Compare foreign servers, user mappings and foreign tables
+  include_foreign_data?: boolean;
+  // Compare logical replication subscriptions (they include connection strings)
+  include_subscriptions?: boolean;
+  // Tables and columns renamed from the target to the source
+  renames?: RenameMapping[];
+  // Rename indexes defined the same under another name
+  rename_matching_indexes?: boolean;
+  // Add foreign keys NOT VALID, then VALIDATE CONSTRAINT in a later transaction
+  validate_constraints_separately?: boolean;
+  // Add NOT NULL columns without a default nullable, backfill, then SET NOT NULL
+  safe_migrations?: boolean;
+  // Tables, columns and object types left out of the comparison
+  ignore?: IgnoreConfig;
+  // Object types to report (e.g. "index"); empty reports all
+  include_types?: string[];
+}
+
+// Names may use * and ?; columns are written as table.column
+export interface IgnoreConfig {
+  tables?: string[];
+  columns?: string[];
+  object_types?: string[];
+}
+
+// A table or column named `from` in the target and `to` in the source;
+// `table` is the column's table by its new name
+export interface RenameMapping {
+  object_type: 'table' | 'column';
+  table?: string | null;
+  from: string;
+  to: string;
+}
+
+// Schema reconstructed from SQL, with statements that could not be applied
+export interface ParsedSchema {
+  schema: SchemaModel;
+  warnings: string[];
 }
 
-export type DiffKind = 'added' | 'removed' | 'modified';
+// Any representation of a schema that can be one side of a comparison
+export type SchemaSource =
+  | { type: 'live'; connection_string: string }
+  | { type: 'version'; base_path: string; version_id: string }
+  | { type: 'migrations'; path: string }
+  | { type: 'sql_file'; path: string }
+  | { type: 'schema_file'; path: string }
+  | { type: 'prisma'; path: string }
+  | { type: 'diesel'; path: string };
 
 export interface DiffItem {
   id: string;
@@ -51,6 +241,63 @@ export interface DiffItem {
   generated_up_sql: string;
   generated_down_sql: string;
   dangerous: boolean;
+  rewrites_table: boolean;
+  acknowledgment?: DangerAcknowledgment | null;
+  disk_estimate?: DiskEstimate | null;
+  // Rows a dropped table or column holds, from estimate_data_loss
+  data_loss?: DataLoss | null;
+  enum_reorder?: EnumReorder | null;
+  // Objects created (or dropped) and referred to, e.g. 'table:users'
+  provides?: string[];
+  requires?: string[];
+  changes?: PropertyChange[];
+}
+
+// A property that differs; `old` is the target's value, `new` the source's,
+// null when unset
+export interface PropertyChange {
+  property: string;
+  old: unknown;
+  new: unknown;
+}
+
+// Enum declared with the same values in another order; the item's SQL
+// follows the chosen strategy
+export type EnumReorderStrategy = 'ignore_order' | 'recreate_type';
+
+export interface EnumReorder {
+  source_order: string[];
+  target_order: string[];
+  strategy: EnumReorderStrategy;
+  resolutions: EnumReorderResolution[];
+}
+
+export interface EnumReorderResolution {
+  strategy: EnumReorderStrategy;
+  description: string;
+  up_sql: string;
+  down_sql: string;
+  rewrites_table: boolean;
+}
+
+// Disk space a statement needs, estimated from the target's current sizes
+export interface DiskEstimate {
+  temporary_bytes: number;
+  final_bytes: number;
+}
+
+export interface DataLoss {
+  rows: number;
+  // Rows with a value in a dropped column
+  non_null_rows?: number | null;
+  // From planner statistics rather than counted
+  estimated: boolean;
+}
+
+export interface DangerAcknowledgment {
+  acknowledged_by: string;
+  acknowledged_at: string;
+  reason: string;
 }
 
 export interface DiffReport {
@@ -58,6 +305,59 @@ export interface DiffReport {
   source_connection: string;
   target_connection: string;
   generated_at: string;
+  // Problems with the comparison itself, e.g. both sides being the same database
+  warnings?: string[];
+  // Major version of the target server the SQL was generated for
+  target_major_version?: number | null;
+}
+
+// Version of a server and features generated SQL depends on (get_server_capabilities)
+export interface ServerCapabilities {
+  server_version: string;
+  server_version_num: number;
+  major_version: number;
+  index_include: boolean;
+  generated_columns: boolean;
+  transactional_add_value: boolean;
+}
+
+// Items of a current report sorted against a previous one (compare_diff_reports)
+export interface ReportDelta {
+  new: DiffItem[];
+  resolved: DiffItem[];
+  changed: { previous: DiffItem; current: DiffItem }[];
+  unchanged: DiffItem[];
+}
+
+// Paged diff report types (report kept on the backend)
+export interface DiffReportSummary {
+  report_id: string;
+  source_connection: string;
+  target_connection: string;
+  generated_at: string;
+  total_items: number;
+  added: number;
+  removed: number;
+  modified: number;
+  reordered: number;
+  renamed: number;
+  dangerous: number;
+  object_types: Record<string, number>;
+}
+
+export interface DiffItemFilter {
+  kinds?: DiffKind[];
+  object_types?: string[];
+  dangerous_only?: boolean;
+  search?: string;
+}
+
+export interface DiffItemsPage {
+  report_id: string;
+  total: number;
+  offset: number;
+  items: DiffItem[];
+  next_offset: number | null;
 }
 
 // Single database connection info
@@ -114,6 +414,15 @@ export interface DatabaseInfo {
   table_count: number;
 }
 
+// A database of the server (list_databases); size is null without CONNECT privilege
+export interface DatabaseEntry {
+  name: string;
+  owner: string;
+  size_bytes: number | null;
+  encoding: string;
+  can_connect: boolean;
+}
+
 export interface TableRow {
   values: Record<string, unknown>;
 }
@@ -139,11 +448,36 @@ export interface SchemaVersion {
   id: string;
   name: string;
   description: string;
-  connection_string: string;
+  connection: string;
   database_name: string;
   schema: SchemaModel;
   created_at: string;
   tags: string[];
+  identity?: DatabaseIdentity | null;
+  locale?: DatabaseLocale | null;
+}
+
+export interface VersionQuery {
+  text?: string | null;
+  database_name?: string | null;
+  tag?: string | null;
+}
+
+export interface DatabaseIdentity {
+  system_identifier: string | null;
+  database_oid: number;
+  database_name: string;
+  server_addr: string | null;
+  server_port: number | null;
+}
+
+export interface DatabaseLocale {
+  encoding: string;
+  collate: string;
+  ctype: string;
+  provider: string | null;
+  icu_locale: string | null;
+  collation_version: string | null;
 }
 
 export interface VersionDiff {
@@ -152,12 +486,297 @@ export interface VersionDiff {
   diff_report: DiffReport;
 }
 
+// Snapshot and apply history storage
+export interface S3Settings {
+  endpoint: string;
+  bucket: string;
+  region: string;
+  prefix: string;
+  access_key_id: string | null;
+  secret_access_key?: string | null; // write-only: moved to the OS keychain
+  profile?: string | null;
+  path_style: boolean;
+}
+
+export type StorageConfig =
+  | { type: 'local' }
+  | ({ type: 's3' } & S3Settings);
+
+// Entries exported by export_app_data, or added by import_app_data
+export interface AppDataSummary {
+  versions: number;
+  apply_history: number;
+  schedules: number;
+  settings: number;
+  migrations: number;
+}
+
+export interface PoolSettings {
+  max_connections: number;
+  idle_timeout_secs: number;
+}
+
+// How often opening a connection is retried; delays double up to max_delay_ms
+export interface RetryPolicy {
+  attempts: number;
+  initial_delay_ms: number;
+  max_delay_ms: number;
+}
+
+export type ConnectErrorKind =
+  | 'dns'
+  | 'auth'
+  | 'tls'
+  | 'timeout'
+  | 'refused'
+  | 'unknown_database'
+  | 'unavailable'
+  | 'configuration'
+  | 'other';
+
+// Rejection of test_connection
+export interface ConnectError {
+  kind: ConnectErrorKind;
+  message: string;
+  hint: string;
+  attempts: number;
+}
+
+// How much history compact_storage keeps; tagged snapshots are never pruned
+
+export interface RetentionSettings {
+  max_snapshots?: number | null;
+  max_snapshot_age_days?: number | null;
+  max_apply_records?: number | null;
+  max_apply_age_days?: number | null;
+}
+
+export interface StorageCategory {
+  name: 'snapshots' | 'apply_history' | 'scheduled_comparisons' | 'settings' | 'migrations';
+  entries: number;
+  bytes: number;
+}
+
+export interface StorageReport {
+  categories: StorageCategory[];
+  database_bytes: number;
+  free_bytes: number;
+}
+
+export interface CompactResult {
+  snapshots_removed: number;
+  apply_records_removed: number;
+  database_bytes_before: number;
+  database_bytes_after: number;
+}
+
+export interface ApplyRecord {
+  id: string;
+  migration_name: string;
+  target: DatabaseIdentity | null;
+  applied_at: string;
+  success: boolean;
+  error: string | null;
+  session: SessionSettings | null;
+  // down.sql was run to roll the migration back
+  rollback: boolean;
+  statements: StatementResult[];
+  backup_path?: string;
+}
+
+export type LogLevel = 'error' | 'warn' | 'info' | 'debug' | 'trace';
+
+// One event of get_recent_logs
+export interface LogRecord {
+  timestamp: string;
+  level: 'ERROR' | 'WARN' | 'INFO' | 'DEBUG' | 'TRACE';
+  target: string; // module that logged it
+  message: string; // followed by other fields as name=value
+}
+
+export type AuditAction =
+  | 'introspect'
+  | 'diff'
+  | 'render'
+  | 'apply'
+  | 'rollback'
+  | 'execute_query'
+  | 'save_version'
+  | 'delete_version';
+
+// One entry of the append-only audit log
+export interface AuditEntry {
+  id: number;
+  occurred_at: string;
+  action: AuditAction;
+  connection: string | null; // label, without the password
+  detail: string; // migration, version, query text or compared sources
+  actor: string; // operating system user
+  success: boolean;
+  error?: string;
+}
+
+// Filters of get_audit_log; unset fields match everything
+export interface AuditQuery {
+  action?: AuditAction;
+  connection?: string; // substring of the label
+  success?: boolean;
+  since?: string;
+  until?: string;
+  limit?: number;
+}
+
+export type StatementStatus = 'succeeded' | 'failed' | 'skipped';
+
+// Outcome of one statement of a migration script
+export interface StatementResult {
+  // 1-based position of the statement in the script
+  index: number;
+  // Line of the script the statement starts on
+  line: number;
+  sql: string;
+  status: StatementStatus;
+  duration_ms: number;
+  rows_affected: number;
+  error: string | null;
+}
+
+// Emitted as 'apply-progress' events when each statement of a migration
+// starts (status null) and finishes
+export interface ApplyProgress {
+  statement: number;
+  statements_total: number;
+  line: number;
+  sql: string;
+  status: StatementStatus | null;
+  duration_ms: number;
+}
+
+// Result of applying or rolling back a migration
+export interface MigrationRun {
+  logs: string[];
+  statements: StatementResult[];
+}
+
+// How apply_migration applies a migration
+export interface ApplyOptions {
+  // Run each statement in a savepoint and continue past failing ones
+  continue_on_error?: boolean;
+  // pg_dump the target first when the migration is dangerous
+  backup?: BackupOptions;
+}
+
+export interface BackupOptions {
+  // ~/PGShift/backups by default
+  directory?: string;
+  schema_only?: boolean;
+  // Back up before every migration, not only dangerous ones
+  always?: boolean;
+  pg_dump_path?: string;
+}
+
+// Connection saved by save_connection; its password is kept in the OS keychain
+export interface SavedConnection {
+  // Empty for a new connection
+  id: string;
+  name: string;
+  host: string;
+  port: number;
+  database: string;
+  user: string;
+  // Query parameters, e.g. sslmode=require
+  params?: string | null;
+  has_password?: boolean;
+  created_at?: string;
+}
+
+// Named database of pgshift.toml, usable in place of a connection string
+export type SslMode = 'disable' | 'allow' | 'prefer' | 'require' | 'verify-ca' | 'verify-full';
+
+// Added to a connection string; certificate paths must exist
+export interface TlsOptions {
+  ssl_mode?: SslMode | null;
+  root_cert?: string | null;
+  client_cert?: string | null;
+  client_key?: string | null;
+}
+
+// Bastion host; keys come from the SSH agent unless identity_file is set
+export interface SshTunnelOptions {
+  host: string;
+  port?: number | null;
+  user?: string | null;
+  identity_file?: string | null;
+}
+
+// Region defaults to AWS_REGION or the RDS host name, credentials to the AWS_* variables or a profile
+export interface RdsIamOptions {
+  region?: string | null;
+  profile?: string | null;
+  role_arn?: string | null;
+}
+
+export interface Environment {
+  connection_string: string;
+  tls?: TlsOptions | null;
+  ssh?: SshTunnelOptions | null;
+  rds_iam?: RdsIamOptions | null;
+  // Used by migration status, apply pending and rollback given no base path
+  migrations_dir?: string | null;
+  ignore?: IgnoreConfig;
+  // Refuse applies, rollbacks, clones and writing queries
+  read_only?: boolean;
+  // Back up before applying when the apply asks for no backup
+  backup?: BackupOptions | null;
+}
+
+// A row of the pgshift_migrations table
+export interface AppliedMigration {
+  name: string;
+  // SHA-256 of up.sql when it was applied
+  checksum: string;
+  applied_at: string;
+  duration_ms: number;
+}
+
+export interface MigrationStatus {
+  name: string;
+  checksum: string;
+  // null while pending; for a squashed folder, the record of the last folder it replaces
+  applied: AppliedMigration | null;
+  // up.sql changed after it was applied
+  modified: boolean;
+}
+
+// Result of get_migration_status
+export interface MigrationStatusReport {
+  migrations: MigrationStatus[];
+  pending: string[];
+  // Applied migrations without a folder in the base path
+  missing: AppliedMigration[];
+}
+
+export interface SessionSettings {
+  backend_pid: number;
+  server_version: string;
+  session_user: string;
+  current_user: string;
+  search_path: string;
+  statement_timeout: string;
+  lock_timeout: string;
+  idle_in_transaction_session_timeout: string;
+  settings: Record<string, string>;
+}
+
 // Migration types
 export interface Migration {
   name: string;
   path: string;
   up_sql: string | null;
   down_sql: string | null;
+  // Hook scripts run around up.sql: before.sql / after.sql and the hooks of meta.json
+  before_sql: string | null;
+  after_sql: string | null;
   meta: {
     name?: string;
     generated_at?: string;
@@ -186,13 +805,314 @@ export interface MigrationApplyResult {
   connectionId: string;
   connectionName: string;
   success: boolean;
+  // Not started after another target failed
+  skipped?: boolean;
   error?: string;
   logs: string[];
 }
 
+// Outcome on one target of apply_migration_multi
+export interface TargetResult {
+  name: string;
+  status: 'applied' | 'failed' | 'skipped';
+  run: MigrationRun | null;
+  error: string | null;
+}
+
 // Schema merge options
 export interface MergeOptions {
   conflictResolution: 'first' | 'last' | 'error';
   includeAllTables: boolean;
   includeAllEnums: boolean;
 }
+
+// Scheduled comparison types
+export interface SmtpSettings {
+  host: string;
+  port: number;
+  username: string | null;
+  password?: string | null; // write-only: moved to the OS keychain, '' removes it
+  has_password?: boolean;
+  starttls: boolean;
+  from: string;
+}
+
+export type ReportDelivery =
+  | { type: 'directory'; path: string }
+  | { type: 'email'; smtp: SmtpSettings; to: string[] };
+
+export interface ScheduledComparison {
+  id: string;
+  name: string;
+  source_connection: string; // label; a password goes to the OS keychain
+  target_connection: string;
+  source_has_password?: boolean;
+  target_has_password?: boolean;
+  interval_minutes: number;
+  next_run_at: string;
+  enabled: boolean;
+  delivery: ReportDelivery;
+  last_run_at: string | null;
+  last_status: string | null;
+  created_at: string;
+}
+
+export type WebhookEvent = 'migration_applied' | 'migration_failed' | 'drift_detected';
+
+// json posts a WebhookNotification, slack a {"text": ...} message
+export type WebhookFormat = 'json' | 'slack';
+
+export interface Webhook {
+  name: string;
+  url: string;
+  format: WebhookFormat;
+  events: WebhookEvent[]; // empty receives every event
+  enabled: boolean;
+}
+
+export interface WebhookDiffSummary {
+  total: number;
+  added: number;
+  removed: number;
+  modified: number;
+  dangerous: number;
+  items: string[];
+}
+
+export interface WebhookNotification {
+  event: WebhookEvent;
+  title: string;
+  connection: string;
+  migration?: string;
+  error?: string;
+  summary?: WebhookDiffSummary;
+  occurred_at: string;
+}
+
+// Cluster-level role comparison types
+export interface Role {
+  name: string;
+  superuser: boolean;
+  inherit: boolean;
+  create_role: boolean;
+  create_db: boolean;
+  can_login: boolean;
+  replication: boolean;
+  bypass_rls: boolean;
+  connection_limit: number;
+  valid_until: string | null;
+  member_of: string[];
+}
+
+export interface RoleDiffReport {
+  scope: 'schema' | 'cluster';
+  items: DiffItem[];
+  source_connection: string;
+  target_connection: string;
+  generated_at: string;
+}
+
+// A running operation from the pg_stat_progress_* views
+export interface OperationProgress {
+  pid: number;
+  operation: string;
+  command: string | null;
+  relation: string | null;
+  index_name: string | null;
+  phase: string;
+  done: number | null;
+  total: number | null;
+  percent: number | null;
+}
+
+// Data-modifying statement of a migration, as planned by plan_migration
+export interface PlannedStatement {
+  position: number;
+  operation: string;
+  statement: string;
+  estimated_rows: number | null;
+  // UPDATE or DELETE without a WHERE clause
+  unbounded: boolean;
+  error: string | null;
+}
+
+export interface MigrationPlan {
+  statements: PlannedStatement[];
+  total_statements: number;
+}
+
+export type LockLevel =
+  | 'access_share'
+  | 'row_share'
+  | 'row_exclusive'
+  | 'share_update_exclusive'
+  | 'share'
+  | 'share_row_exclusive'
+  | 'exclusive'
+  | 'access_exclusive';
+
+export type LockRisk = 'low' | 'medium' | 'high';
+
+// Session holding or waiting for a lock that conflicts with the migration's
+export interface BlockingSession {
+  pid: number;
+  mode: string;
+  granted: boolean;
+  state: string | null;
+  application_name: string;
+  username: string | null;
+  query: string | null;
+  running_seconds: number | null;
+}
+
+export interface TableLock {
+  table: string;
+  level: LockLevel;
+  blocks_reads: boolean;
+  blocks_writes: boolean;
+  // False for tables the migration creates first
+  exists: boolean;
+  conflicting_sessions: BlockingSession[];
+}
+
+export interface StatementLocks {
+  position: number;
+  statement: string;
+  locks: TableLock[];
+  risk: LockRisk;
+  note: string | null;
+}
+
+// Result of analyze_lock_impact
+export interface LockImpact {
+  statements: StatementLocks[];
+  total_statements: number;
+  risk: LockRisk;
+}
+
+export interface RenderOptions {
+  trace_comments?: boolean;
+  // One multi-action ALTER TABLE per table for column and constraint changes
+  batch_alter_table?: boolean;
+  // CREATE INDEX CONCURRENTLY for indexes added to existing tables
+  concurrent_indexes?: boolean;
+  // IF [NOT] EXISTS variants and guarded DO blocks, so up.sql can be re-run
+  idempotent?: boolean;
+  // Folder versions: 20260203120000 (default) or a ULID
+  version_scheme?: 'timestamp' | 'ulid';
+  // A migration folder (default), Flyway V/U scripts, golang-migrate or
+  // sqlx .up.sql/.down.sql pairs, or a Diesel folder
+  format?: 'folder' | 'flyway' | 'golang_migrate' | 'sqlx' | 'diesel';
+}
+
+// Options for render_dual_write_migration
+export interface DualWriteOptions {
+  batch_size?: number | null;
+  // Conversion from the old column, written {value}; a cast if omitted
+  using?: string | null;
+  // Two migrations (expand up to the swap, contract dropping the old
+  // column) instead of one per phase
+  expand_contract?: boolean;
+}
+
+// `phase` of meta.json in a multi-phase migration
+export interface MigrationPhase {
+  index: number;
+  count: number;
+  kind: string;
+  instructions: string[];
+  transactional: boolean;
+}
+
+// Result of squash_migrations
+export interface SquashResult {
+  path: string;
+  // Folders the new one replaces, in order
+  squashed: string[];
+  // Where those folders were moved
+  archive_path: string;
+  // False when the scripts were concatenated instead of merged
+  merged: boolean;
+  warnings: string[];
+}
+
+export interface IntrospectOptions {
+  tables_per_chunk?: number;
+  // Replaces the schema filter saved for the connection
+  schema_filter?: SchemaFilter;
+}
+
+// Schemas a connection is introspected, browsed and compared on; the
+// patterns must select exactly one schema, and an empty filter reads public
+export interface SchemaFilter {
+  include: string[];
+  exclude: string[];
+}
+
+// Emitted as 'introspect-progress' events before the first and after each
+// chunk of tables
+export interface IntrospectProgress {
+  tables_done: number;
+  tables_total: number;
+}
+
+export interface CloneOptions {
+  diff?: DiffOptions;
+  dry_run?: boolean;
+}
+
+export type ClonePhase = 'introspect' | 'check_target' | 'render' | 'apply' | 'done';
+
+// Emitted as 'clone-progress' events
+export interface CloneProgress {
+  phase: ClonePhase;
+  message: string;
+  operation?: OperationProgress;
+}
+
+export interface CloneResult {
+  items_count: number;
+  up_sql: string;
+  applied: boolean;
+  logs: string[];
+}
+
+// Result of schema_graph: tables as nodes, foreign keys as edges
+export interface SchemaGraph {
+  nodes: GraphNode[];
+  edges: GraphEdge[];
+}
+
+export interface GraphNode {
+  // The table name
+  id: string;
+  columns: GraphColumn[];
+  comment?: string;
+}
+
+export interface GraphColumn {
+  name: string;
+  data_type: string;
+  nullable: boolean;
+  primary_key: boolean;
+  foreign_key: boolean;
+}
+
+export interface GraphEdge {
+  // table.constraint
+  id: string;
+  source: string;
+  target: string;
+  columns: string[];
+  referenced_columns: string[];
+  on_delete: string;
+  on_update: string;
+}
+
+// Result of dump_schema_sql
+export interface SchemaSqlDump {
+  path: string;
+  objects: number;
+  // False when the file already held this dump
+  changed: boolean;
+}